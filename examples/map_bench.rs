@@ -0,0 +1,68 @@
+//! Time loading and wall lookups on a generated 1024x1024 map.
+//!
+//! Usage: `cargo run --release --example map_bench [size]`
+
+use std::{env, fs, hint::black_box, path::Path, time::Instant};
+
+use rec_wars::{map, prelude::*};
+
+fn main() {
+    let size: usize = env::args().nth(1).map_or(1024, |s| s.parse().unwrap());
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tex_list_text = fs::read_to_string(root.join("data/texture_list.txt")).unwrap();
+    let surfaces = map::parse_texture_list(&tex_list_text);
+
+    // Mostly grass with 20% walls, roughly like the real maps.
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+    let mut text = String::new();
+    for _ in 0..size {
+        let row: Vec<_> = (0..size)
+            .map(|_| if rng.gen_bool(0.2) { "16" } else { "0" })
+            .collect();
+        text.push_str(&row.join(" "));
+        text.push('\n');
+    }
+
+    let start = Instant::now();
+    let map = map::parse_map(&text, surfaces, "bench");
+    println!("parse_map {size}x{size}: {:?}", start.elapsed());
+
+    let maxs = map.maxs();
+    let points: Vec<_> = (0..1_000_000)
+        .map(|_| Vec2f::new(rng.gen_range(0.0..maxs.x), rng.gen_range(0.0..maxs.y)))
+        .collect();
+
+    let start = Instant::now();
+    let walls = points.iter().filter(|&&pos| map.is_wall(pos)).count();
+    let elapsed = start.elapsed();
+    println!(
+        "is_wall: {:?} per call ({walls} walls)",
+        elapsed / points.len() as u32
+    );
+
+    // Short segments like a projectile's step in one tick.
+    let start = Instant::now();
+    let mut hits = 0;
+    for &pos in &points {
+        let end = pos + Vec2f::new(30.0, 20.0);
+        if map.is_wall_trace(pos, end).is_some() {
+            hits += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "is_wall_trace: {:?} per call ({hits} hits)",
+        elapsed / points.len() as u32
+    );
+
+    let start = Instant::now();
+    for _ in 0..points.len() {
+        black_box(map.random_nonwall(&mut rng));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "random_nonwall: {:?} per call",
+        elapsed / points.len() as u32
+    );
+}
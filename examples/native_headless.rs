@@ -0,0 +1,45 @@
+//! Run a bot match without a window or networking and print the scores.
+//!
+//! Usage: `cargo run --example native_headless [map_path] [seconds]`
+
+use std::{env, fs, path::Path};
+
+use rec_wars::{map, prelude::*, sim::Sim};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let map_path = args.next().unwrap_or_else(|| "maps/Atrium.map".to_owned());
+    let seconds: f64 = args.next().map_or(60.0, |s| s.parse().unwrap());
+
+    let cvars = Cvars {
+        bots_max: 8,
+        ..Cvars::default()
+    };
+
+    // Relative to the crate root so it works from any directory.
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tex_list_text = fs::read_to_string(root.join("data/texture_list.txt")).unwrap();
+    let surfaces = map::parse_texture_list(&tex_list_text);
+    let map_text = fs::read_to_string(root.join(&map_path)).unwrap();
+    let map = map::parse_map(&map_text, surfaces, &map_path);
+
+    let mut sim = Sim::new(&cvars, map);
+    let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+    while sim.gs().game_time < seconds {
+        sim.tick(&cvars, &[], dt);
+    }
+
+    let mut players: Vec<_> = sim.gs().players.iter().map(|(_, player)| player).collect();
+    players.sort_by_key(|player| -player.score.points(&cvars));
+    println!("{} after {seconds} s:", map_path);
+    for player in players {
+        let score = &player.score;
+        println!(
+            "{:>20} {:>4} points {:>4} kills {:>4} deaths",
+            player.name,
+            score.points(&cvars),
+            score.kills,
+            score.deaths,
+        );
+    }
+}
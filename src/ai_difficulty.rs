@@ -0,0 +1,115 @@
+//! Rubber-banding for solo play against bots.
+//!
+//! Each bot is compared to the best human. Bots far ahead get weaker,
+//! bots far behind get stronger, always within `ai_difficulty_min` and `ai_difficulty_max`.
+//!
+//! Everything here is pure, `sys_ai_difficulty` feeds it scores and applies the results.
+
+use crate::prelude::*;
+
+/// Where a bot's score is relative to the best human.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoreBand {
+    Behind,
+    #[default]
+    Even,
+    Ahead,
+}
+
+/// Classify the gap (bot's points minus the best human's points).
+///
+/// Entering Ahead / Behind requires a gap of at least `ai_difficulty_gap`
+/// but leaving requires it to shrink by `ai_difficulty_hysteresis` more
+/// so a gap hovering right at the threshold doesn't flip the band every evaluation.
+pub fn score_band(cvars: &Cvars, prev: ScoreBand, gap: i32) -> ScoreBand {
+    let enter = cvars.ai_difficulty_gap;
+    let exit = enter - cvars.ai_difficulty_hysteresis;
+    match prev {
+        ScoreBand::Ahead if gap > exit => ScoreBand::Ahead,
+        ScoreBand::Behind if gap < -exit => ScoreBand::Behind,
+        _ if gap >= enter => ScoreBand::Ahead,
+        _ if gap <= -enter => ScoreBand::Behind,
+        _ => ScoreBand::Even,
+    }
+}
+
+/// New difficulty of a bot in the given band.
+pub fn adjust_difficulty(cvars: &Cvars, band: ScoreBand, difficulty: f64) -> f64 {
+    let new = match band {
+        ScoreBand::Behind => difficulty + cvars.ai_difficulty_step,
+        ScoreBand::Even => difficulty,
+        ScoreBand::Ahead => difficulty - cvars.ai_difficulty_step,
+    };
+    new.clamped(cvars.ai_difficulty_min, cvars.ai_difficulty_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cvars() -> Cvars {
+        Cvars {
+            ai_difficulty_gap: 5,
+            ai_difficulty_hysteresis: 2,
+            ai_difficulty_max: 0.8,
+            ai_difficulty_min: 0.2,
+            ai_difficulty_step: 0.1,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_score_band() {
+        let cvars = cvars();
+        assert_eq!(score_band(&cvars, ScoreBand::Even, 4), ScoreBand::Even);
+        assert_eq!(score_band(&cvars, ScoreBand::Even, 5), ScoreBand::Ahead);
+        assert_eq!(score_band(&cvars, ScoreBand::Even, -4), ScoreBand::Even);
+        assert_eq!(score_band(&cvars, ScoreBand::Even, -5), ScoreBand::Behind);
+
+        // Leaving needs the gap to drop below enter - hysteresis.
+        assert_eq!(score_band(&cvars, ScoreBand::Ahead, 4), ScoreBand::Ahead);
+        assert_eq!(score_band(&cvars, ScoreBand::Ahead, 3), ScoreBand::Even);
+        assert_eq!(score_band(&cvars, ScoreBand::Behind, -4), ScoreBand::Behind);
+        assert_eq!(score_band(&cvars, ScoreBand::Behind, -3), ScoreBand::Even);
+
+        // Big swings skip Even.
+        assert_eq!(score_band(&cvars, ScoreBand::Ahead, -5), ScoreBand::Behind);
+        assert_eq!(score_band(&cvars, ScoreBand::Behind, 5), ScoreBand::Ahead);
+    }
+
+    #[test]
+    fn test_no_oscillation() {
+        let cvars = cvars();
+        let mut band = ScoreBand::Even;
+        let mut bands = Vec::new();
+        for gap in [5, 4, 5, 4, 5, 4] {
+            band = score_band(&cvars, band, gap);
+            bands.push(band);
+        }
+        assert!(bands.iter().all(|&b| b == ScoreBand::Ahead), "{bands:?}");
+
+        let mut band = ScoreBand::Even;
+        for gap in [4, 3, 4, 3, 4, 3] {
+            band = score_band(&cvars, band, gap);
+            assert_eq!(band, ScoreBand::Even);
+        }
+    }
+
+    #[test]
+    fn test_adjust_bounds() {
+        let cvars = cvars();
+        let mut difficulty = 0.5;
+        for _ in 0..10 {
+            difficulty = adjust_difficulty(&cvars, ScoreBand::Behind, difficulty);
+        }
+        assert_eq!(difficulty, 0.8);
+        for _ in 0..10 {
+            difficulty = adjust_difficulty(&cvars, ScoreBand::Ahead, difficulty);
+        }
+        assert_eq!(difficulty, 0.2);
+        assert_eq!(adjust_difficulty(&cvars, ScoreBand::Even, 0.5), 0.5);
+
+        // Out of range values get pulled back in even when Even.
+        assert_eq!(adjust_difficulty(&cvars, ScoreBand::Even, 1.0), 0.8);
+    }
+}
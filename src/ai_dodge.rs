@@ -0,0 +1,216 @@
+//! Bots getting out of the way of incoming projectiles.
+//!
+//! Everything here is pure, `sys_ai_dodge` feeds it projectiles and applies the results.
+//!
+//! LATER Use a spatial grid for the projectile scan once there is one.
+
+use vek::LineSegment2;
+
+use crate::prelude::*;
+
+/// Which way to move to get away from the projectile, None if it's not a threat.
+///
+/// Bullets are ignored because there's too many of them to check and rail hits instantly.
+/// CBs are avoided by getting away from where they'll explode,
+/// everything else by moving perpendicular to its path.
+pub fn projectile_threat(
+    cvars: &Cvars,
+    game_time: f64,
+    projectile: &Projectile,
+    pos: Vec2f,
+) -> Option<Vec2f> {
+    match projectile.weapon {
+        Weapon::Mg | Weapon::Rail => None,
+        Weapon::Cb => {
+            let fuse = projectile.explode_time - game_time;
+            if fuse > cvars.ai_dodge_lookahead {
+                return None;
+            }
+            let explode_pos = projectile.pos + projectile.vel * fuse.max(0.0);
+            let radius = cvars.g_cluster_bomb_explosion_radius + cvars.g_hitcircle_radius;
+            if explode_pos.distance(pos) >= radius {
+                return None;
+            }
+            // Right on top of it - any direction is fine.
+            Some((pos - explode_pos).try_normalized().unwrap_or(v!(1 0)))
+        }
+        _ => {
+            if projectile.vel == Vec2f::zero() {
+                return None;
+            }
+            let path = LineSegment2 {
+                start: projectile.pos,
+                end: projectile.pos + projectile.vel * cvars.ai_dodge_lookahead,
+            };
+            let nearest = path.projected_point(pos);
+            if nearest.distance(pos) >= cvars.ai_dodge_danger_radius {
+                return None;
+            }
+            // Stay on the side we're already on.
+            let perp = v!(-projectile.vel.y, projectile.vel.x).normalized();
+            if (pos - nearest).dot(perp) < 0.0 {
+                Some(-perp)
+            } else {
+                Some(perp)
+            }
+        }
+    }
+}
+
+/// Which way to move to shake off a homing missile locked onto us.
+///
+/// Moving across the line from the missile, against the way it's already drifting,
+/// makes it turn hardest, ideally out of its seeker cone.
+pub fn hm_lock_threat(hm: &Projectile, pos: Vec2f) -> Vec2f {
+    let to_us = pos - hm.pos;
+    let perp = v!(-to_us.y, to_us.x).try_normalized().unwrap_or(v!(1 0));
+    if hm.vel.dot(perp) > 0.0 {
+        -perp
+    } else {
+        perp
+    }
+}
+
+/// How long a bot takes to react to a threat, None if it never dodges.
+pub fn reaction_time(cvars: &Cvars, difficulty: f64) -> Option<f64> {
+    if difficulty < cvars.ai_dodge_difficulty_min {
+        return None;
+    }
+    Some((1.0 - difficulty).max(0.0) * cvars.ai_dodge_reaction_time_max)
+}
+
+/// Movement input to get moving in `dir`.
+///
+/// Vehicles can only accelerate forward or backward so this picks whichever is closer
+/// and steers to line up with `dir`.
+pub fn dodge_input(angle: f64, dir: Vec2f) -> NetInput {
+    let forward = angle.to_vec2f();
+    let along = forward.dot(dir);
+    let wanted_forward = if along >= 0.0 { dir } else { -dir };
+    // Angles are clockwise so positive means turning right.
+    let cross = forward.x * wanted_forward.y - forward.y * wanted_forward.x;
+    NetInput {
+        up: along >= 0.0,
+        down: along < 0.0,
+        left: cross < -0.1,
+        right: cross > 0.1,
+        ..NetInput::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projectile(weapon: Weapon, pos: Vec2f, vel: Vec2f) -> Projectile {
+        Projectile {
+            weapon,
+            pos,
+            vel,
+            angle: vel.to_angle(),
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: Index::DANGLING,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        }
+    }
+
+    #[test]
+    fn test_projectile_threat() {
+        let cvars = Cvars {
+            ai_dodge_danger_radius: 40.0,
+            ai_dodge_lookahead: 1.0,
+            ..Cvars::default()
+        };
+
+        // Coming straight at us from the left.
+        let rocket = projectile(Weapon::Rockets, v!(0 0), v!(600 0));
+        let dir = projectile_threat(&cvars, 0.0, &rocket, v!(300 10)).unwrap();
+        assert!((dir - v!(0 1)).magnitude() < 1e-9);
+        let dir = projectile_threat(&cvars, 0.0, &rocket, v!(300, -10)).unwrap();
+        assert!((dir - v!(0, -1)).magnitude() < 1e-9);
+
+        // Too far to the side, too far ahead, already passed.
+        assert_eq!(projectile_threat(&cvars, 0.0, &rocket, v!(300 50)), None);
+        assert_eq!(projectile_threat(&cvars, 0.0, &rocket, v!(700 0)), None);
+        assert_eq!(projectile_threat(&cvars, 0.0, &rocket, v!(-50 0)), None);
+
+        // Not worth checking
+        let mg = projectile(Weapon::Mg, v!(0 0), v!(600 0));
+        assert_eq!(projectile_threat(&cvars, 0.0, &mg, v!(300 0)), None);
+        let stopped = projectile(Weapon::Hm, v!(0 0), v!(0 0));
+        assert_eq!(projectile_threat(&cvars, 0.0, &stopped, v!(10 0)), None);
+    }
+
+    #[test]
+    fn test_cb_threat() {
+        let cvars = Cvars {
+            ai_dodge_lookahead: 1.0,
+            g_cluster_bomb_explosion_radius: 20.0,
+            g_hitcircle_radius: 24.0,
+            ..Cvars::default()
+        };
+        let mut cb = projectile(Weapon::Cb, v!(0 0), v!(100 0));
+        cb.explode_time = 0.5;
+
+        // Explodes at 50 0, get away from it.
+        let dir = projectile_threat(&cvars, 0.0, &cb, v!(80 0)).unwrap();
+        assert!((dir - v!(1 0)).magnitude() < 1e-9);
+        assert_eq!(projectile_threat(&cvars, 0.0, &cb, v!(100 0)), None);
+
+        // Not going off soon enough to worry.
+        cb.explode_time = 2.0;
+        assert_eq!(projectile_threat(&cvars, 0.0, &cb, v!(80 0)), None);
+    }
+
+    #[test]
+    fn test_hm_lock_threat() {
+        // Coming from the left and drifting down, cross its path upwards.
+        let hm = projectile(Weapon::Hm, v!(0 0), v!(300 50));
+        let dir = hm_lock_threat(&hm, v!(200 0));
+        assert!((dir - v!(0, -1)).magnitude() < 1e-9);
+
+        let hm = projectile(Weapon::Hm, v!(0 0), v!(300, -50));
+        let dir = hm_lock_threat(&hm, v!(200 0));
+        assert!((dir - v!(0 1)).magnitude() < 1e-9);
+
+        // Heading straight at us, any side is fine.
+        let hm = projectile(Weapon::Hm, v!(0 0), v!(300 0));
+        assert_eq!(hm_lock_threat(&hm, v!(200 0)).y.abs(), 1.0);
+    }
+
+    #[test]
+    fn test_reaction_time() {
+        let cvars = Cvars {
+            ai_dodge_difficulty_min: 0.25,
+            ai_dodge_reaction_time_max: 0.5,
+            ..Cvars::default()
+        };
+        assert_eq!(reaction_time(&cvars, 0.1), None);
+        assert_eq!(reaction_time(&cvars, 0.5), Some(0.25));
+        assert_eq!(reaction_time(&cvars, 1.0), Some(0.0));
+    }
+
+    #[test]
+    fn test_dodge_input() {
+        // Facing right, dodge right - full speed ahead.
+        let input = dodge_input(0.0, v!(1 0));
+        assert!(input.up && !input.down && !input.left && !input.right);
+
+        // Facing right, dodge left - reverse.
+        let input = dodge_input(0.0, v!(-1 0));
+        assert!(!input.up && input.down && !input.left && !input.right);
+
+        // Facing right, dodge down (clockwise) - forward and turn right.
+        let input = dodge_input(0.0, v!(1 1).normalized());
+        assert!(input.up && input.right && !input.left);
+
+        // Facing right, dodge down and left - reverse with the back turning towards it.
+        let input = dodge_input(0.0, v!(-1 1).normalized());
+        assert!(input.down && input.left && !input.right);
+    }
+}
@@ -0,0 +1,153 @@
+//! Spreading expensive AI decisions over multiple frames so many bots don't cause frame time spikes.
+//!
+//! Only re-evaluation (e.g. scanning all projectiles for threats) is sliced,
+//! cheap per-tick steering (e.g. following the current dodge) runs for every bot every tick.
+//! Bots take turns round-robin until the frame's budget (`ai_frame_budget_ms`) runs out.
+//! A bot which would otherwise go longer than `ai_max_decision_interval` without deciding
+//! goes regardless of the budget so the added latency is bounded.
+//!
+//! The budget is measured in wall clock time so with a finite budget
+//! the simulation is no longer deterministic. The default is unlimited.
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct AiSchedule {
+    /// Where in the list of bots the next frame starts.
+    cursor: usize,
+}
+
+impl AiSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `decide` for the bots whose turn it is this frame.
+    ///
+    /// `bots` are handles with the game time of each one's last decision, in a stable order.
+    /// `elapsed` returns seconds spent so far this frame.
+    pub fn run(
+        &mut self,
+        cvars: &Cvars,
+        game_time: f64,
+        dt: f64,
+        bots: &[(Index, f64)],
+        mut elapsed: impl FnMut() -> f64,
+        mut decide: impl FnMut(Index),
+    ) {
+        if bots.is_empty() {
+            return;
+        }
+
+        let budget = cvars.ai_frame_budget_ms / 1000.0;
+        let start = self.cursor % bots.len();
+        // Only advance past bots which got their turn in order,
+        // the rest are first in line next frame.
+        let mut advance = 0;
+        let mut in_order = true;
+        for i in 0..bots.len() {
+            let (handle, last) = bots[(start + i) % bots.len()];
+            // Waiting another frame would exceed the limit.
+            let overdue = game_time + dt - last > cvars.ai_max_decision_interval;
+            if overdue || elapsed() < budget {
+                decide(handle);
+                if in_order {
+                    advance += 1;
+                }
+            } else {
+                in_order = false;
+            }
+        }
+        self.cursor = start + advance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    /// Run `frames` frames where each decision takes `cost` seconds.
+    /// Returns the game times of each bot's decisions.
+    fn simulate(cvars: &Cvars, bot_count: usize, frames: usize, cost: f64) -> Vec<Vec<f64>> {
+        let dt = 1.0 / 30.0;
+        let mut arena = Arena::new();
+        let handles: Vec<_> = (0..bot_count).map(|_| arena.insert(())).collect();
+        let mut decisions = vec![Vec::new(); bot_count];
+        let mut schedule = AiSchedule::new();
+        for frame in 0..frames {
+            let game_time = frame as f64 * dt;
+            let bots: Vec<_> = decisions
+                .iter()
+                .zip(&handles)
+                .map(|(times, &handle)| {
+                    (handle, times.last().copied().unwrap_or(f64::NEG_INFINITY))
+                })
+                .collect();
+            let spent = Cell::new(0.0);
+            let mut decided = Vec::new();
+            schedule.run(
+                cvars,
+                game_time,
+                dt,
+                &bots,
+                || spent.get(),
+                |handle| {
+                    // The slow decision.
+                    spent.set(spent.get() + cost);
+                    decided.push(handle);
+                },
+            );
+            for handle in decided {
+                let index = handles.iter().position(|&h| h == handle).unwrap();
+                decisions[index].push(game_time);
+            }
+        }
+        decisions
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let cvars = Cvars::default();
+        let decisions = simulate(&cvars, 5, 10, 1.0);
+        for times in decisions {
+            assert_eq!(times.len(), 10);
+        }
+    }
+
+    #[test]
+    fn test_round_robin() {
+        // 3 decisions fit into the budget each frame.
+        let cvars = Cvars {
+            ai_frame_budget_ms: 2.5,
+            ai_max_decision_interval: f64::INFINITY,
+            ..Cvars::default()
+        };
+        let decisions = simulate(&cvars, 9, 30, 0.001);
+        // Everyone gets the same number of turns, evenly spaced.
+        for times in &decisions {
+            assert_eq!(times.len(), 10, "{times:?}");
+            for pair in times.windows(2) {
+                assert!((pair[1] - pair[0] - 3.0 / 30.0).abs() < 1e-9, "{pair:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_latency_bound() {
+        let cvars = Cvars {
+            ai_frame_budget_ms: 0.0,
+            ai_max_decision_interval: 0.11,
+            ..Cvars::default()
+        };
+        let decisions = simulate(&cvars, 10, 100, 0.001);
+        for times in decisions {
+            for pair in times.windows(2) {
+                assert!(pair[1] - pair[0] <= 0.11, "{pair:?}");
+            }
+            // Not more often than necessary either.
+            assert!(times.len() < 40, "{}", times.len());
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Finding entities in an area of the world,
+//! e.g. for picking in debug tools or AI looking for groups of enemies.
+//!
+//! Vehicles and wrecks are matched by their hitbox, projectiles as points.
+//!
+//! LATER Use a spatial grid once there is one, this scans everything.
+
+use crate::prelude::*;
+
+/// Which kinds of entities to look for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntityKinds {
+    pub vehicles: bool,
+    pub projectiles: bool,
+    pub wrecks: bool,
+}
+
+impl EntityKinds {
+    pub const ALL: Self = Self {
+        vehicles: true,
+        projectiles: true,
+        wrecks: true,
+    };
+
+    pub const VEHICLES: Self = Self {
+        vehicles: true,
+        projectiles: false,
+        wrecks: false,
+    };
+}
+
+/// An entity found by a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityRef {
+    Vehicle(Index),
+    Projectile(Index),
+    /// Index into `GameState::wrecks`.
+    Wreck(usize),
+}
+
+/// What tools like an inspector or editor need to show about an entity.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityDescriptor {
+    pub kind: &'static str,
+    pub pos: Vec2f,
+    /// Stable ID for vehicles and projectiles (see `stable_id`), index in the list for wrecks.
+    /// Only unique among entities of the same kind.
+    pub id: u32,
+}
+
+impl EntityRef {
+    pub fn describe(self, gs: &GameState) -> EntityDescriptor {
+        match self {
+            EntityRef::Vehicle(handle) => EntityDescriptor {
+                kind: "vehicle",
+                pos: gs.vehicles[handle].pos,
+                id: gs.vehicle_ids.id(handle).soft_unwrap(),
+            },
+            EntityRef::Projectile(handle) => EntityDescriptor {
+                kind: "projectile",
+                pos: gs.projectiles[handle].pos,
+                id: gs.projectile_ids.id(handle).soft_unwrap(),
+            },
+            EntityRef::Wreck(index) => EntityDescriptor {
+                kind: "wreck",
+                pos: gs.wrecks[index].pos,
+                id: index as u32,
+            },
+        }
+    }
+}
+
+/// Entities touching the axis-aligned rectangle from `min` to `max`.
+pub fn entities_in_rect(
+    cvars: &Cvars,
+    gs: &GameState,
+    min: Vec2f,
+    max: Vec2f,
+    kinds: EntityKinds,
+) -> Vec<EntityRef> {
+    let contains =
+        |point: Vec2f| min.x <= point.x && point.x <= max.x && min.y <= point.y && point.y <= max.y;
+    query(
+        cvars,
+        gs,
+        kinds,
+        |hitbox, pos, angle| hitbox.overlaps_rect(pos, angle, min, max),
+        contains,
+    )
+}
+
+/// Entities touching the circle.
+pub fn entities_in_circle(
+    cvars: &Cvars,
+    gs: &GameState,
+    center: Vec2f,
+    radius: f64,
+    kinds: EntityKinds,
+) -> Vec<EntityRef> {
+    let contains = |point: Vec2f| point.distance_squared(center) <= radius * radius;
+    query(
+        cvars,
+        gs,
+        kinds,
+        |hitbox, pos, angle| contains(hitbox.closest_point(pos, angle, center)),
+        contains,
+    )
+}
+
+/// Same as `entities_in_rect` but described for tools outside the game.
+pub fn describe_entities_in_rect(
+    cvars: &Cvars,
+    gs: &GameState,
+    min: Vec2f,
+    max: Vec2f,
+    kinds: EntityKinds,
+) -> Vec<EntityDescriptor> {
+    entities_in_rect(cvars, gs, min, max, kinds)
+        .into_iter()
+        .map(|entity| entity.describe(gs))
+        .collect()
+}
+
+fn query(
+    cvars: &Cvars,
+    gs: &GameState,
+    kinds: EntityKinds,
+    hitbox_touches: impl Fn(Hitbox, Vec2f, f64) -> bool,
+    point_touches: impl Fn(Vec2f) -> bool,
+) -> Vec<EntityRef> {
+    let mut found = Vec::new();
+    if kinds.vehicles {
+        for (handle, vehicle) in &gs.vehicles {
+            if hitbox_touches(vehicle.hitbox, vehicle.pos, vehicle.angle) {
+                found.push(EntityRef::Vehicle(handle));
+            }
+        }
+    }
+    if kinds.projectiles {
+        for (handle, projectile) in &gs.projectiles {
+            if point_touches(projectile.pos) {
+                found.push(EntityRef::Projectile(handle));
+            }
+        }
+    }
+    if kinds.wrecks {
+        for (index, wreck) in gs.wrecks.iter().enumerate() {
+            let hitbox = cvars.g_vehicle_hitbox(wreck.veh_type);
+            if hitbox_touches(hitbox, wreck.pos, wreck.angle) {
+                found.push(EntityRef::Wreck(index));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tank at (100, 100), a projectile at (200, 100) and a wreck at (300, 100).
+    ///
+    /// The IDs are different from the slots to make sure they're not mixed up.
+    fn world(cvars: &Cvars) -> (GameState, Index, Index) {
+        let mut gs = GameState::new();
+        let vehicle = Vehicle::new(
+            cvars,
+            v!(100 100),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        let vehicle_handle = gs.vehicles.insert(vehicle);
+        gs.vehicle_ids.insert(vehicle_handle, 7);
+        let projectile = Projectile {
+            weapon: Weapon::Rockets,
+            pos: v!(200 100),
+            vel: v!(100 0),
+            angle: 0.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: Index::DANGLING,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let projectile_handle = gs.projectiles.insert(projectile);
+        gs.projectile_ids.insert(projectile_handle, 8);
+        gs.wrecks.push(Wreck {
+            pos: v!(300 100),
+            angle: 0.0,
+            veh_type: VehicleType::Tank,
+        });
+        (gs, vehicle_handle, projectile_handle)
+    }
+
+    #[test]
+    fn test_kinds() {
+        let cvars = Cvars::default();
+        let (gs, vehicle, projectile) = world(&cvars);
+        let everything = [
+            EntityRef::Vehicle(vehicle),
+            EntityRef::Projectile(projectile),
+            EntityRef::Wreck(0),
+        ];
+
+        let found = entities_in_rect(&cvars, &gs, v!(0 0), v!(400 200), EntityKinds::ALL);
+        assert_eq!(found, everything);
+        let found = entities_in_rect(&cvars, &gs, v!(0 0), v!(400 200), EntityKinds::VEHICLES);
+        assert_eq!(found, [EntityRef::Vehicle(vehicle)]);
+        let kinds = EntityKinds {
+            projectiles: true,
+            wrecks: true,
+            ..EntityKinds::default()
+        };
+        let found = entities_in_circle(&cvars, &gs, v!(200 100), 1000.0, kinds);
+        assert_eq!(found, everything[1..]);
+        let found = entities_in_circle(&cvars, &gs, v!(200 100), 1000.0, EntityKinds::default());
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_partial_overlap() {
+        let cvars = Cvars::default();
+        let (gs, vehicle, _) = world(&cvars);
+        let hitbox = gs.vehicles[vehicle].hitbox;
+        let front = 100.0 + hitbox.maxs.x;
+
+        // Only the very front of the tank is in the rect.
+        let found = entities_in_rect(
+            &cvars,
+            &gs,
+            v!(front - 1.0, 0),
+            v!(front + 10.0, 200),
+            EntityKinds::ALL,
+        );
+        assert_eq!(found, [EntityRef::Vehicle(vehicle)]);
+        let found = entities_in_circle(&cvars, &gs, v!(front + 5.0, 100), 6.0, EntityKinds::ALL);
+        assert_eq!(found, [EntityRef::Vehicle(vehicle)]);
+
+        // Just missed it.
+        let found = entities_in_rect(
+            &cvars,
+            &gs,
+            v!(front + 1.0, 0),
+            v!(front + 10.0, 200),
+            EntityKinds::ALL,
+        );
+        assert!(found.is_empty());
+        let found = entities_in_circle(&cvars, &gs, v!(front + 5.0, 100), 4.0, EntityKinds::ALL);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_empty() {
+        let cvars = Cvars::default();
+        let gs = GameState::new();
+        let found = entities_in_rect(&cvars, &gs, v!(0 0), v!(1000 1000), EntityKinds::ALL);
+        assert!(found.is_empty());
+
+        let (gs, _, _) = world(&cvars);
+        let found = entities_in_rect(&cvars, &gs, v!(500 500), v!(600 600), EntityKinds::ALL);
+        assert!(found.is_empty());
+        let found = entities_in_circle(&cvars, &gs, v!(500 500), 10.0, EntityKinds::ALL);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_describe() {
+        let cvars = Cvars::default();
+        let (gs, _, _) = world(&cvars);
+        let descriptors =
+            describe_entities_in_rect(&cvars, &gs, v!(0 0), v!(250 150), EntityKinds::ALL);
+        assert_eq!(
+            descriptors,
+            [
+                EntityDescriptor {
+                    kind: "vehicle",
+                    pos: v!(100 100),
+                    id: 7,
+                },
+                EntityDescriptor {
+                    kind: "projectile",
+                    pos: v!(200 100),
+                    id: 8,
+                },
+            ]
+        );
+    }
+}
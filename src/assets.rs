@@ -7,7 +7,10 @@ use std::path::Path;
 
 use macroquad::prelude::*;
 
-use crate::prelude::*;
+use crate::{
+    map::{Surface, TILE_SIZE},
+    prelude::*,
+};
 
 #[derive(Debug)]
 pub struct Assets {
@@ -23,12 +26,22 @@ pub struct Assets {
     pub texs_tiles: Vec<Texture2D>,
     pub texs_vehicles: Vec<Texture2D>,
     pub texs_wrecks: Vec<Texture2D>,
+    /// Optional turrets for wrecks, one per vehicle type.
+    /// The normal turret is darkened instead if missing, see `r_wreck_turret_brightness`.
+    pub texs_wreck_turrets: Vec<Option<Texture2D>>,
     pub texs_weapon_icons: Vec<Texture2D>,
     pub tex_rocket: Texture2D,
     pub tex_hm: Texture2D,
     pub tex_gm: Texture2D,
     pub tex_explosion: Texture2D,
     pub tex_explosion_cyan: Texture2D,
+    /// Drawn instead of textures which failed to load or are missing
+    /// so the problem is obvious instead of invisible.
+    pub tex_missing: Texture2D,
+    /// Names of the tile textures, must match the surfaces in `texture_list` in order.
+    pub tile_names: Vec<&'static str>,
+    /// Textures which failed to load, see `Assets::problems`.
+    pub load_errors: Vec<String>,
 }
 
 impl Assets {
@@ -150,35 +163,58 @@ impl Assets {
         add_map_hidden!("maps/extra2/World War (2).map");
         add_map_hidden!("maps/testing/test1.map");
 
+        let mut load_errors = Vec::new();
+        let image_missing = Image::gen_image_color(TILE_SIZE as u16, TILE_SIZE as u16, MAGENTA);
+        let tex_missing = Texture2D::from_image(&image_missing);
         macro_rules! tex {
             ($path:expr $(,)?) => {
-                Texture2D::from_file_with_format(&asset!($path), None)
+                match Image::from_file_with_format(&asset!($path), None) {
+                    Ok(image) if image.width() > 0 && image.height() > 0 => {
+                        Texture2D::from_image(&image)
+                    }
+                    Ok(_) => {
+                        load_errors.push(format!("{}: empty image", $path));
+                        tex_missing.clone()
+                    }
+                    Err(e) => {
+                        load_errors.push(format!("{}: {}", $path, e));
+                        tex_missing.clone()
+                    }
+                }
             };
         }
-        let texs_tiles = vec![
-            tex!("data/tiles/g1.bmp"),
-            tex!("data/tiles/g2.bmp"),
-            tex!("data/tiles/g3.bmp"),
-            tex!("data/tiles/g_stripes.bmp"),
-            tex!("data/tiles/bunker1.bmp"),
-            tex!("data/tiles/ice1.bmp"),
-            tex!("data/tiles/ice.bmp"),
-            tex!("data/tiles/ice_side.bmp"),
-            tex!("data/tiles/ice_corner.bmp"),
-            tex!("data/tiles/g_spawn.bmp"),
-            tex!("data/tiles/road.bmp"),
-            tex!("data/tiles/water.bmp"),
-            tex!("data/tiles/snow.bmp"),
-            tex!("data/tiles/snow2.bmp"),
-            tex!("data/tiles/bunker2.bmp"),
-            tex!("data/tiles/base.bmp"),
-            tex!("data/tiles/water_side.bmp"),
-            tex!("data/tiles/water_corner.bmp"),
-            tex!("data/tiles/desert.bmp"),
-            tex!("data/tiles/d_rock.bmp"),
-            tex!("data/tiles/g2d.bmp"),
-            tex!("data/tiles/water_middle.bmp"),
-        ];
+        macro_rules! tile {
+            ($path:expr $(,)?) => {{
+                let name = Path::new($path).file_stem().unwrap().to_str().unwrap();
+                (name, tex!($path))
+            }};
+        }
+        let (tile_names, texs_tiles): (Vec<_>, Vec<_>) = [
+            tile!("data/tiles/g1.bmp"),
+            tile!("data/tiles/g2.bmp"),
+            tile!("data/tiles/g3.bmp"),
+            tile!("data/tiles/g_stripes.bmp"),
+            tile!("data/tiles/bunker1.bmp"),
+            tile!("data/tiles/ice1.bmp"),
+            tile!("data/tiles/ice.bmp"),
+            tile!("data/tiles/ice_side.bmp"),
+            tile!("data/tiles/ice_corner.bmp"),
+            tile!("data/tiles/g_spawn.bmp"),
+            tile!("data/tiles/road.bmp"),
+            tile!("data/tiles/water.bmp"),
+            tile!("data/tiles/snow.bmp"),
+            tile!("data/tiles/snow2.bmp"),
+            tile!("data/tiles/bunker2.bmp"),
+            tile!("data/tiles/base.bmp"),
+            tile!("data/tiles/water_side.bmp"),
+            tile!("data/tiles/water_corner.bmp"),
+            tile!("data/tiles/desert.bmp"),
+            tile!("data/tiles/d_rock.bmp"),
+            tile!("data/tiles/g2d.bmp"),
+            tile!("data/tiles/water_middle.bmp"),
+        ]
+        .into_iter()
+        .unzip();
         let texs_vehicles = vec![
             tex!("data/vehicles/tank_chassis_flames.png"),
             tex!("data/vehicles/tank_turret_flames.png"),
@@ -192,6 +228,36 @@ impl Assets {
             tex!("data/wrecks/hovercraft.png"),
             tex!("data/wrecks/hummer.png"),
         ];
+        // These are not bundled, only modders who want them need to provide them.
+        macro_rules! tex_optional {
+            ($path:expr $(,)?) => {{
+                #[cfg(target_arch = "wasm32")]
+                let loaded: Option<Vec<u8>> = None;
+                #[cfg(not(target_arch = "wasm32"))]
+                let loaded = macroquad::file::load_file($path).await.ok();
+                loaded.and_then(|bytes| {
+                    cnt_loaded += 1;
+                    match Image::from_file_with_format(&bytes, None) {
+                        Ok(image) if image.width() > 0 && image.height() > 0 => {
+                            Some(Texture2D::from_image(&image))
+                        }
+                        Ok(_) => {
+                            load_errors.push(format!("{}: empty image", $path));
+                            None
+                        }
+                        Err(e) => {
+                            load_errors.push(format!("{}: {}", $path, e));
+                            None
+                        }
+                    }
+                })
+            }};
+        }
+        let texs_wreck_turrets = vec![
+            tex_optional!("data/wrecks/tank_turret.png"),
+            tex_optional!("data/wrecks/hovercraft_turret.png"),
+            tex_optional!("data/wrecks/hummer_turret.png"),
+        ];
         let texs_weapon_icons = vec![
             tex!("data/weapon_icons/mg.png"),
             tex!("data/weapon_icons/rail.png"),
@@ -226,12 +292,142 @@ impl Assets {
             texs_tiles,
             texs_vehicles,
             texs_wrecks,
+            texs_wreck_turrets,
             texs_weapon_icons,
             tex_rocket,
             tex_hm,
             tex_gm,
             tex_explosion,
             tex_explosion_cyan,
+            tex_missing,
+            tile_names,
+            load_errors,
         }
     }
+
+    /// Everything wrong with the loaded assets, empty if all is fine.
+    ///
+    /// Nothing here is fatal, missing textures are drawn as `tex_missing`.
+    pub fn problems(&self, surfaces: &[Surface]) -> Vec<String> {
+        let mut problems = self.load_errors.clone();
+        problems.extend(tile_problems(&self.tile_names, surfaces));
+        let counts = [
+            (
+                "texs_vehicles",
+                self.texs_vehicles.len(),
+                VehicleType::COUNT * 2,
+            ),
+            ("texs_wrecks", self.texs_wrecks.len(), VehicleType::COUNT),
+            (
+                "texs_wreck_turrets",
+                self.texs_wreck_turrets.len(),
+                VehicleType::COUNT,
+            ),
+            (
+                "texs_weapon_icons",
+                self.texs_weapon_icons.len(),
+                Weapon::COUNT,
+            ),
+        ];
+        for (name, actual, expected) in counts {
+            if actual != expected {
+                problems.push(format!(
+                    "{name}: expected {expected} textures, got {actual}"
+                ));
+            }
+        }
+        // Mixing dedicated and darkened turrets would look inconsistent.
+        let wreck_turrets = self.texs_wreck_turrets.iter().flatten().count();
+        if wreck_turrets != 0 && wreck_turrets != self.texs_wreck_turrets.len() {
+            problems.push(format!(
+                "texs_wreck_turrets: expected all or none, got {wreck_turrets} of {}",
+                self.texs_wreck_turrets.len()
+            ));
+        }
+        problems
+    }
+
+    pub fn tile(&self, surface_index: usize) -> &Texture2D {
+        self.texs_tiles
+            .get(surface_index)
+            .unwrap_or(&self.tex_missing)
+    }
+
+    pub fn chassis(&self, veh_type: VehicleType) -> &Texture2D {
+        self.texs_vehicles
+            .get(veh_type as usize * 2)
+            .unwrap_or(&self.tex_missing)
+    }
+
+    pub fn turret(&self, veh_type: VehicleType) -> &Texture2D {
+        self.texs_vehicles
+            .get(veh_type as usize * 2 + 1)
+            .unwrap_or(&self.tex_missing)
+    }
+
+    pub fn wreck(&self, veh_type: VehicleType) -> &Texture2D {
+        self.texs_wrecks
+            .get(veh_type as usize)
+            .unwrap_or(&self.tex_missing)
+    }
+
+    /// The dedicated wreck turret if there is one.
+    pub fn wreck_turret(&self, veh_type: VehicleType) -> Option<&Texture2D> {
+        self.texs_wreck_turrets
+            .get(veh_type as usize)
+            .and_then(Option::as_ref)
+    }
+
+    pub fn weapon_icon(&self, weapon: Weapon) -> &Texture2D {
+        self.texs_weapon_icons
+            .get(weapon as usize)
+            .unwrap_or(&self.tex_missing)
+    }
+}
+
+/// Tiles are indexed by surface so they have to match texture_list.txt exactly.
+pub fn tile_problems(tile_names: &[&str], surfaces: &[Surface]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if tile_names.len() != surfaces.len() {
+        problems.push(format!(
+            "texs_tiles: expected {} textures (one per surface), got {}",
+            surfaces.len(),
+            tile_names.len(),
+        ));
+    }
+    for (i, (&tile_name, surface)) in tile_names.iter().zip(surfaces).enumerate() {
+        if tile_name != surface.name {
+            problems.push(format!(
+                "texs_tiles[{i}]: expected {:?}, got {tile_name:?}",
+                surface.name,
+            ));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map;
+
+    #[test]
+    fn test_tile_problems() {
+        let surfaces = map::parse_texture_list("g1 0 1 1\nroad 0 1.4 1.4\nwater 3 1 0.5\n");
+        assert!(tile_problems(&["g1", "road", "water"], &surfaces).is_empty());
+
+        let problems = tile_problems(&["g1", "road"], &surfaces);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("expected 3"));
+
+        let problems = tile_problems(&["g1", "water", "road"], &surfaces);
+        assert_eq!(
+            problems,
+            vec![
+                r#"texs_tiles[1]: expected "road", got "water""#,
+                r#"texs_tiles[2]: expected "water", got "road""#,
+            ]
+        );
+    }
 }
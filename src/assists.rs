@@ -0,0 +1,170 @@
+//! Credit for everyone who helped destroy a vehicle, not just whoever landed the last hit.
+//!
+//! Each vehicle keeps a small `DamageLedger` of recent damage by attacker.
+//! `ServerFrameCtx::damage` records into it and asks it for the assists when the vehicle dies.
+//! A respawned vehicle is a new entity so it starts with an empty ledger.
+
+use crate::prelude::*;
+
+/// One attacker's recent damage to a vehicle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contribution {
+    /// Player's stable ID, see `stable_id`.
+    pub attacker: u32,
+    pub damage: f64,
+    /// The whole contribution expires `g_assist_window` after the attacker's last hit.
+    pub last_time: f64,
+}
+
+/// Recent damage to one vehicle, at most `g_assist_contributors_max` attackers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DamageLedger {
+    pub contributions: Vec<Contribution>,
+    /// Damage from the smallest contributors, merged to make room when the ledger was full.
+    /// Nobody gets an assist for it.
+    pub merged: f64,
+}
+
+impl DamageLedger {
+    /// Self-damage should not be recorded.
+    pub fn record(&mut self, cvars: &Cvars, attacker: u32, time: f64, damage: f64) {
+        self.expire(cvars, time);
+
+        if let Some(contribution) = self
+            .contributions
+            .iter_mut()
+            .find(|contribution| contribution.attacker == attacker)
+        {
+            contribution.damage += damage;
+            contribution.last_time = time;
+            return;
+        }
+
+        if self.contributions.len() >= cvars.g_assist_contributors_max.max(1) {
+            let (smallest, _) = self
+                .contributions
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.damage.total_cmp(&b.damage))
+                .unwrap();
+            self.merged += self.contributions.swap_remove(smallest).damage;
+        }
+        self.contributions.push(Contribution {
+            attacker,
+            damage,
+            last_time: time,
+        });
+    }
+
+    /// Forget contributions whose last hit is older than `g_assist_window`.
+    pub fn expire(&mut self, cvars: &Cvars, time: f64) {
+        self.contributions
+            .retain(|contribution| time - contribution.last_time <= cvars.g_assist_window);
+        if self.contributions.is_empty() {
+            self.merged = 0.0;
+        }
+    }
+
+    /// Stable IDs of players who get an assist when `killer` destroys the vehicle at `time`,
+    /// most damage first.
+    pub fn assists(&self, cvars: &Cvars, killer: Option<u32>, time: f64) -> Vec<u32> {
+        let mut assists: Vec<_> = self
+            .contributions
+            .iter()
+            .filter(|contribution| {
+                Some(contribution.attacker) != killer
+                    && time - contribution.last_time <= cvars.g_assist_window
+                    && contribution.damage >= cvars.g_assist_min_damage
+            })
+            .collect();
+        assists.sort_by(|a, b| b.damage.total_cmp(&a.damage));
+        assists
+            .into_iter()
+            .map(|contribution| contribution.attacker)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assist_split() {
+        let cvars = Cvars {
+            g_assist_min_damage: 20.0,
+            g_assist_window: 10.0,
+            ..Cvars::default()
+        };
+        let (killer, helper, chipper) = (1, 2, 3);
+        let mut ledger = DamageLedger::default();
+        ledger.record(&cvars, helper, 0.0, 25.0);
+        ledger.record(&cvars, killer, 1.0, 30.0);
+        ledger.record(&cvars, helper, 2.0, 15.0);
+        ledger.record(&cvars, chipper, 2.0, 5.0);
+        ledger.record(&cvars, killer, 3.0, 30.0);
+
+        // 60/40 - the killer doesn't also get an assist, the chipper didn't do enough.
+        assert_eq!(ledger.contributions[0].damage, 40.0);
+        assert_eq!(ledger.assists(&cvars, Some(killer), 3.0), [helper]);
+        // Killed by someone who left or by the victim themself.
+        assert_eq!(ledger.assists(&cvars, None, 3.0), [killer, helper]);
+    }
+
+    #[test]
+    fn test_assist_window() {
+        let cvars = Cvars {
+            g_assist_min_damage: 20.0,
+            g_assist_window: 10.0,
+            ..Cvars::default()
+        };
+        let (killer, old, recent) = (1, 2, 3);
+        let mut ledger = DamageLedger::default();
+        ledger.record(&cvars, old, 0.0, 50.0);
+        ledger.record(&cvars, recent, 5.0, 20.0);
+        ledger.record(&cvars, killer, 10.0, 30.0);
+        assert_eq!(ledger.assists(&cvars, Some(killer), 10.0), [old, recent]);
+        assert_eq!(ledger.assists(&cvars, Some(killer), 10.5), [recent]);
+
+        // Expired contributions start over.
+        ledger.record(&cvars, old, 20.5, 10.0);
+        assert_eq!(ledger.contributions.len(), 1);
+        assert_eq!(ledger.contributions[0].damage, 10.0);
+        assert!(ledger.assists(&cvars, Some(killer), 20.5).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_cap() {
+        let cvars = Cvars {
+            g_assist_contributors_max: 4,
+            g_assist_min_damage: 20.0,
+            g_assist_window: 10.0,
+            ..Cvars::default()
+        };
+        let mut ledger = DamageLedger::default();
+        // Bots 0..4 chip 1..4 damage, then a bigger one joins and a few more.
+        for bot in 0..4 {
+            ledger.record(&cvars, bot, 0.0, (bot + 1) as f64);
+        }
+        ledger.record(&cvars, 10, 1.0, 30.0);
+        ledger.record(&cvars, 11, 1.0, 2.5);
+        ledger.record(&cvars, 12, 1.0, 0.5);
+
+        let mut attackers: Vec<_> = ledger.contributions.iter().map(|c| c.attacker).collect();
+        attackers.sort_unstable();
+        // 0 (1 dmg) went first, then 1 (2 dmg), then 11 (2.5 dmg).
+        // The newest one always gets a slot even if it's the smallest.
+        assert_eq!(attackers, [2, 3, 10, 12]);
+        assert_eq!(ledger.merged, 1.0 + 2.0 + 2.5);
+        let total: f64 = ledger.contributions.iter().map(|c| c.damage).sum();
+        assert_eq!(
+            total + ledger.merged,
+            1.0 + 2.0 + 3.0 + 4.0 + 30.0 + 2.5 + 0.5
+        );
+
+        // Existing contributors keep accumulating without evicting anyone.
+        ledger.record(&cvars, 12, 2.0, 25.0);
+        assert_eq!(ledger.contributions.len(), 4);
+        assert_eq!(ledger.assists(&cvars, None, 2.0), [10, 12]);
+    }
+}
@@ -0,0 +1,548 @@
+//! Data for the audio layer.
+//!
+//! RecWars doesn't play any sounds yet. This prepares the continuous parameters
+//! a sound backend needs every frame (e.g. to modulate looping engine sounds)
+//! so the backend itself can stay dumb and just apply them.
+
+use crate::{
+    map::SurfaceKind,
+    prelude::*,
+    rendering::{self, CameraTarget},
+    scraping::ScrapeSound,
+};
+
+/// State of one vehicle's engine - used to change pitch and volume of a looping engine sound.
+#[derive(Debug, Clone)]
+pub struct EngineSound {
+    pub vehicle_handle: Index,
+    pub veh_type: VehicleType,
+    /// From -1 (full reverse) to 1 (full forward).
+    pub throttle: f64,
+    /// From 0 (standing still) to 1 (top speed).
+    pub speed: f64,
+    /// From 0 (at the edge of the audible radius) to 1 (at the listener's position).
+    ///
+    /// Precomputed here so the backend only needs to multiply the volume.
+    pub attenuation: f64,
+    /// From -1 (left) to 1 (right), see `pan`.
+    pub pan: f64,
+    /// E.g. to switch to a different engine sound on water.
+    pub surface: SurfaceKind,
+}
+
+/// A one-shot turret traverse sound for the audio backend to play.
+#[derive(Debug, Clone)]
+pub struct TurretSound {
+    pub vehicle_handle: Index,
+    pub pos: Vec2f,
+    /// From 0 (barely moving) to 1 (at `g_turret_turn_speed_deg`).
+    ///
+    /// With turret inertia (`g_turret_turn_accel_deg`) it follows the wind-up and wind-down.
+    pub intensity: f64,
+    pub start_time: f64,
+    /// Relative to the listener, filled in by `Client::take_turret_sounds`.
+    pub pan: f64,
+    pub attenuation: f64,
+    /// How long ago it was produced, also filled in by `Client::take_turret_sounds`.
+    pub age: f64,
+}
+
+/// The looping lock warning tone should start or stop, see `ClientFrameCtx::sys_lock_warning`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockWarningSound {
+    /// A homing missile locked onto the listener's vehicle.
+    Start,
+    /// No homing missile is locked onto it anymore.
+    Stop,
+}
+
+/// A dry click because the listener's shot was blocked by a wall, see `weapons::muzzle_blocked`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FireBlockedSound {
+    /// E.g. a lighter click for the machine gun.
+    pub weapon: Weapon,
+    pub start_time: f64,
+}
+
+impl ClientFrameCtx<'_> {
+    /// Produce turret sounds from the synced `Vehicle::turret_turn_rate`,
+    /// at most one per `snd_turret_interval` per vehicle.
+    pub fn sys_turret_sounds(&mut self) {
+        if !self.cvars.snd_turret {
+            return;
+        }
+
+        let rate_max = self.cvars.g_turret_turn_speed_deg.to_radians();
+        for (vehicle_handle, vehicle) in self.gs.vehicles.iter() {
+            if vehicle.destroyed() || vehicle.turret_turn_rate == 0.0 {
+                continue;
+            }
+            let due = match self.cg.turret_sound_times.get(&vehicle_handle) {
+                Some(&time) => self.gs.game_time - time >= self.cvars.snd_turret_interval,
+                None => true,
+            };
+            if !due {
+                continue;
+            }
+
+            let intensity = vehicle.turret_turn_rate.abs() / rate_max;
+            self.cg.turret_sounds.push(TurretSound {
+                vehicle_handle,
+                pos: vehicle.pos,
+                intensity: intensity.min(1.0),
+                start_time: self.gs.game_time,
+                pan: 0.0,
+                attenuation: 1.0,
+                age: 0.0,
+            });
+            self.cg
+                .turret_sound_times
+                .insert(vehicle_handle, self.gs.game_time);
+        }
+    }
+
+    /// Produce lock warning events when the first homing missile locks onto the listener's vehicle
+    /// and when the last one loses it.
+    ///
+    /// Locks come from the server in `ServerMessage::HmLock`.
+    pub fn sys_lock_warning(&mut self) {
+        let player_handle = self.cg.views[0].camera_player();
+        let locked = self
+            .gs
+            .players
+            .get(player_handle)
+            .and_then(|player| player.vehicle)
+            .and_then(|handle| self.gs.vehicles.get(handle))
+            .is_some_and(|vehicle| !vehicle.destroyed() && !vehicle.hms.is_empty());
+        if locked == self.cg.lock_warning {
+            return;
+        }
+
+        self.cg.lock_warning = locked;
+        if self.cvars.snd_lock_warning {
+            let sound = if locked {
+                LockWarningSound::Start
+            } else {
+                LockWarningSound::Stop
+            };
+            self.cg.lock_warning_sounds.push(sound);
+        }
+    }
+
+    /// Click when the listener's vehicle tries to fire with its muzzle inside a wall,
+    /// at most once per `snd_fire_blocked_interval` while the trigger is held.
+    ///
+    /// Blocked shots come from the server in the synced `Vehicle::fire_blocked_time`.
+    pub fn sys_fire_blocked_sound(&mut self) {
+        if !self.cvars.snd_fire_blocked {
+            return;
+        }
+
+        let player_handle = self.cg.views[0].camera_player();
+        let Some(player) = self.gs.players.get(player_handle) else {
+            return;
+        };
+        let Some(vehicle) = player
+            .vehicle
+            .and_then(|handle| self.gs.vehicles.get(handle))
+        else {
+            return;
+        };
+        let blocked_time = vehicle.fire_blocked_time;
+        if blocked_time <= self.cg.fire_blocked_sound_time + self.cvars.snd_fire_blocked_interval {
+            return;
+        }
+
+        self.cg.fire_blocked_sound_time = blocked_time;
+        self.cg.fire_blocked_sounds.push(FireBlockedSound {
+            weapon: player.cur_weapon,
+            start_time: blocked_time,
+        });
+    }
+}
+
+impl Client {
+    /// Engine state of all vehicles the local player can hear.
+    pub fn audio_state(&self, cvars: &Cvars) -> Vec<EngineSound> {
+        let player_handle = self.listener_player();
+        let listener_pos = listener_pos(&self.map, &self.gs, player_handle);
+        let local_vehicle = self
+            .gs
+            .players
+            .get(player_handle)
+            .and_then(|player| player.vehicle);
+        let view_half_width = self.viewport_size.x / 2.0;
+        engine_sounds(
+            cvars,
+            &self.gs,
+            local_vehicle,
+            listener_pos,
+            view_half_width,
+        )
+    }
+
+    /// One-shot scraping sounds produced since the last call, see `scraping`.
+    ///
+    /// Their pan and attenuation are relative to the listener at the time of this call,
+    /// their age to the game time.
+    pub fn take_scrape_sounds(&mut self, cvars: &Cvars) -> Vec<ScrapeSound> {
+        let listener_pos = listener_pos(&self.map, &self.gs, self.listener_player());
+        let view_half_width = self.viewport_size.x / 2.0;
+        let mut sounds = std::mem::take(&mut self.cg.scrape_sounds);
+        for sound in &mut sounds {
+            sound.pan = pan(listener_pos, sound.pos, view_half_width);
+            sound.attenuation = attenuation(cvars, listener_pos, sound.pos);
+            sound.age = self.gs.game_time - sound.start_time;
+        }
+        sounds
+    }
+
+    /// One-shot turret traverse sounds produced since the last call, see `sys_turret_sounds`.
+    ///
+    /// Relative to the listener the same way as `take_scrape_sounds`.
+    pub fn take_turret_sounds(&mut self, cvars: &Cvars) -> Vec<TurretSound> {
+        let listener_pos = listener_pos(&self.map, &self.gs, self.listener_player());
+        let view_half_width = self.viewport_size.x / 2.0;
+        let mut sounds = std::mem::take(&mut self.cg.turret_sounds);
+        for sound in &mut sounds {
+            sound.pan = pan(listener_pos, sound.pos, view_half_width);
+            sound.attenuation = attenuation(cvars, listener_pos, sound.pos);
+            sound.age = self.gs.game_time - sound.start_time;
+        }
+        sounds
+    }
+
+    /// Lock warning tone events produced since the last call, see `sys_lock_warning`.
+    pub fn take_lock_warning_sounds(&mut self) -> Vec<LockWarningSound> {
+        std::mem::take(&mut self.cg.lock_warning_sounds)
+    }
+
+    /// Blocked shot clicks produced since the last call, see `sys_fire_blocked_sound`.
+    pub fn take_fire_blocked_sounds(&mut self) -> Vec<FireBlockedSound> {
+        std::mem::take(&mut self.cg.fire_blocked_sounds)
+    }
+
+    /// We hear whoever we see - the camera follows whoever our input goes to.
+    fn listener_player(&self) -> Index {
+        // LATER Splitscreen - mix both listeners?
+        self.cg.views[0].camera_player()
+    }
+}
+
+/// Where the player hears sounds from - the same place the camera follows.
+///
+/// The map's center if the camera has nothing to follow.
+pub fn listener_pos(map: &Map, gs: &GameState, player_handle: Index) -> Vec2f {
+    match rendering::camera_target(gs, player_handle) {
+        Some(CameraTarget::Vehicle(handle)) => gs.vehicles[handle].pos,
+        Some(CameraTarget::Projectile(handle)) => gs.projectiles[handle].pos,
+        None => map.maxs() / 2.0,
+    }
+}
+
+/// From -1 (left) to 1 (right).
+///
+/// Sounds at the edge of the view or further are completely on one side.
+/// Only the horizontal offset matters, there's no way to tell above from below with 2 speakers.
+pub fn pan(listener_pos: Vec2f, source_pos: Vec2f, view_half_width: f64) -> f64 {
+    if view_half_width <= 0.0 {
+        return 0.0;
+    }
+    ((source_pos.x - listener_pos.x) / view_half_width).clamped(-1.0, 1.0)
+}
+
+/// From 0 (at the edge of `snd_audible_radius` or further) to 1 (at the listener's position).
+///
+/// Shaped by `snd_rolloff`.
+pub fn attenuation(cvars: &Cvars, listener_pos: Vec2f, source_pos: Vec2f) -> f64 {
+    let dist = source_pos.distance(listener_pos);
+    let linear = (1.0 - dist / cvars.snd_audible_radius).clamped(0.0, 1.0);
+    linear.powf(cvars.snd_rolloff)
+}
+
+/// Collect engine sounds of vehicles within `snd_audible_radius` of the listener.
+///
+/// At most `snd_engine_sounds_max` are returned, closest first.
+/// The local vehicle is always included (and always first) even if it's too far
+/// so the player never loses their own engine sound while guiding a missile.
+pub fn engine_sounds(
+    cvars: &Cvars,
+    gs: &GameState,
+    local_vehicle: Option<Index>,
+    listener_pos: Vec2f,
+    view_half_width: f64,
+) -> Vec<EngineSound> {
+    let mut sounds = Vec::new();
+    for (vehicle_handle, vehicle) in gs.vehicles.iter() {
+        if vehicle.destroyed() {
+            continue;
+        }
+
+        let is_local = local_vehicle == Some(vehicle_handle);
+        let dist = vehicle.pos.distance(listener_pos);
+        if !is_local && dist > cvars.snd_audible_radius {
+            continue;
+        }
+
+        // Orphaned vehicles and those of players who just left have no owner.
+        let Some(owner) = gs.players.get(vehicle.owner) else {
+            continue;
+        };
+        let input = owner.input;
+        let throttle = input.up() - input.down();
+
+        let speed_max = cvars.g_vehicle_movement_stats(vehicle.veh_type).speed_max;
+        let speed_max = if speed_max.is_finite() {
+            speed_max
+        } else {
+            cvars.snd_engine_speed_max
+        };
+        let speed = (vehicle.vel.magnitude() / speed_max).min(1.0);
+
+        let sound = EngineSound {
+            vehicle_handle,
+            veh_type: vehicle.veh_type,
+            throttle,
+            speed,
+            attenuation: attenuation(cvars, listener_pos, vehicle.pos),
+            pan: pan(listener_pos, vehicle.pos, view_half_width),
+            surface: vehicle.surface.kind,
+        };
+        sounds.push((is_local, dist, sound));
+    }
+
+    sounds.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.total_cmp(&b.1)));
+    sounds.truncate(cvars.snd_engine_sounds_max);
+    sounds.into_iter().map(|(_, _, sound)| sound).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    use crate::{net::LocalConnection, test_utils};
+
+    #[test]
+    fn test_audible_radius() {
+        let cvars = Cvars {
+            snd_audible_radius: 100.0,
+            ..Cvars::default()
+        };
+        let mut gs = GameState::new();
+        let near = test_utils::add_tank(&cvars, &mut gs, v!(50 0), 0.0).1;
+        let _far = test_utils::add_tank(&cvars, &mut gs, v!(150 0), 0.0).1;
+
+        let sounds = engine_sounds(&cvars, &gs, None, v!(0 0), 400.0);
+        assert_eq!(sounds.len(), 1);
+        assert_eq!(sounds[0].vehicle_handle, near);
+        assert_eq!(sounds[0].attenuation, 0.5);
+    }
+
+    #[test]
+    fn test_cap_keeps_local() {
+        let cvars = Cvars {
+            snd_audible_radius: 1000.0,
+            snd_engine_sounds_max: 2,
+            ..Cvars::default()
+        };
+        let mut gs = GameState::new();
+        let closest = test_utils::add_tank(&cvars, &mut gs, v!(10 0), 0.0).1;
+        let _middle = test_utils::add_tank(&cvars, &mut gs, v!(20 0), 0.0).1;
+        // Outside the radius but still included because it's local.
+        let local = test_utils::add_tank(&cvars, &mut gs, v!(2000 0), 0.0).1;
+
+        let sounds = engine_sounds(&cvars, &gs, Some(local), v!(0 0), 400.0);
+        assert_eq!(sounds.len(), 2);
+        assert_eq!(sounds[0].vehicle_handle, local);
+        assert_eq!(sounds[0].attenuation, 0.0);
+        assert_eq!(sounds[1].vehicle_handle, closest);
+    }
+
+    #[test]
+    fn test_normalization() {
+        let cvars = Cvars {
+            g_tank_speed_max: f64::INFINITY,
+            snd_engine_speed_max: 200.0,
+            ..Cvars::default()
+        };
+        let mut gs = GameState::new();
+        let handle = test_utils::add_tank(&cvars, &mut gs, v!(0 0), 0.0).1;
+        let owner = gs.vehicles[handle].owner;
+        gs.vehicles[handle].vel = v!(100 0);
+        gs.players[owner].input.down = true;
+
+        let sounds = engine_sounds(&cvars, &gs, Some(handle), v!(0 0), 400.0);
+        assert_eq!(sounds[0].throttle, -1.0);
+        assert_eq!(sounds[0].speed, 0.5);
+        assert_eq!(sounds[0].attenuation, 1.0);
+
+        gs.vehicles[handle].vel = v!(0 1000);
+        gs.players[owner].input.down = false;
+        gs.players[owner].input.up = true;
+        let sounds = engine_sounds(&cvars, &gs, Some(handle), v!(0 0), 400.0);
+        assert_eq!(sounds[0].throttle, 1.0);
+        assert_eq!(sounds[0].speed, 1.0);
+    }
+
+    #[test]
+    fn test_listener_follows_camera() {
+        let cvars = Cvars::default();
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut gs = GameState::new();
+        let vehicle_handle = test_utils::add_tank(&cvars, &mut gs, v!(100 100), 0.0).1;
+        let player_handle = gs.vehicles[vehicle_handle].owner;
+        assert_eq!(listener_pos(&map, &gs, player_handle), v!(100 100));
+
+        let gm = Projectile {
+            weapon: Weapon::Gm,
+            pos: v!(500 100),
+            vel: Vec2f::zero(),
+            angle: 0.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: player_handle,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let gm_handle = gs.projectiles.insert(gm);
+        gs.players[player_handle].guided_missile = Some(gm_handle);
+        assert_eq!(listener_pos(&map, &gs, player_handle), v!(500 100));
+
+        // The vehicle is now far to the left of the missile cam.
+        let sounds = engine_sounds(&cvars, &gs, Some(vehicle_handle), v!(500 100), 400.0);
+        assert_eq!(sounds[0].pan, -1.0);
+
+        // Nothing to follow.
+        let spectator = Player::new("spectator".to_owned(), ClientType::Local);
+        let spectator_handle = gs.players.insert(spectator);
+        assert_eq!(listener_pos(&map, &gs, spectator_handle), v!(64 64));
+    }
+
+    #[test]
+    fn test_turret_sounds() {
+        let cvars = Cvars {
+            g_turret_turn_speed_deg: 100.0,
+            snd_turret: true,
+            snd_turret_interval: 0.1,
+            ..Cvars::default()
+        };
+        let mut gs = GameState::new();
+        let turning = test_utils::add_tank(&cvars, &mut gs, v!(0 0), 0.0).1;
+        let _still = test_utils::add_tank(&cvars, &mut gs, v!(100 0), 0.0).1;
+        gs.vehicles[turning].turret_turn_rate = -50.0f64.to_radians();
+
+        let (sender, _server_receiver) = mpsc::channel();
+        let (_server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let owner = gs.vehicles[turning].owner;
+        let mut cg = ClientGame::new(Box::new(conn), owner);
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+
+        // One every 0.1 s, not every frame.
+        for frame in 0..30 {
+            ctx.gs.game_time = frame as f64 / 100.0;
+            ctx.sys_turret_sounds();
+        }
+        let sounds = &ctx.cg.turret_sounds;
+        assert_eq!(sounds.len(), 3);
+        assert!(sounds.iter().all(|sound| sound.vehicle_handle == turning));
+        assert!((sounds[0].intensity - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lock_warning_sounds() {
+        let cvars = Cvars {
+            snd_lock_warning: true,
+            ..Cvars::default()
+        };
+        let mut gs = GameState::new();
+        let vehicle_handle = test_utils::add_tank(&cvars, &mut gs, v!(0 0), 0.0).1;
+        let owner = gs.vehicles[vehicle_handle].owner;
+        let hm = Projectile {
+            weapon: Weapon::Hm,
+            pos: v!(500 0),
+            vel: Vec2f::zero(),
+            angle: PI,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: Index::DANGLING,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let hm1 = gs.projectiles.insert(hm.clone());
+        let hm2 = gs.projectiles.insert(hm);
+
+        let (sender, _server_receiver) = mpsc::channel();
+        let (_server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut cg = ClientGame::new(Box::new(conn), owner);
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+        let vehicle_index = Some(vehicle_handle.slot());
+
+        // Each tick: the locks received, then the events so far.
+        let ticks = [
+            (vec![], vec![]),
+            (vec![(hm1, vehicle_index)], vec![LockWarningSound::Start]),
+            // Still one tone for two missiles.
+            (vec![(hm2, vehicle_index)], vec![LockWarningSound::Start]),
+            (vec![(hm1, None)], vec![LockWarningSound::Start]),
+            (
+                vec![(hm2, None)],
+                vec![LockWarningSound::Start, LockWarningSound::Stop],
+            ),
+            (
+                vec![],
+                vec![LockWarningSound::Start, LockWarningSound::Stop],
+            ),
+        ];
+        for (locks, sounds) in ticks {
+            for (hm_handle, target) in locks {
+                ctx.handle_hm_lock(hm_handle.slot(), target);
+            }
+            ctx.sys_lock_warning();
+            assert_eq!(ctx.cg.lock_warning_sounds, sounds);
+        }
+        assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+        assert_eq!(ctx.gs.projectiles[hm1].target, None);
+    }
+
+    #[test]
+    fn test_pan() {
+        let listener = v!(100 100);
+        assert_eq!(pan(listener, v!(100 500), 200.0), 0.0);
+        assert_eq!(pan(listener, v!(0 100), 200.0), -0.5);
+        assert_eq!(pan(listener, v!(200 0), 200.0), 0.5);
+        assert_eq!(pan(listener, v!(-1000 100), 200.0), -1.0);
+        assert_eq!(pan(listener, v!(1000 100), 200.0), 1.0);
+        assert_eq!(pan(listener, v!(1000 100), 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_attenuation_rolloff() {
+        let mut cvars = Cvars {
+            snd_audible_radius: 100.0,
+            snd_rolloff: 1.0,
+            ..Cvars::default()
+        };
+        let listener = v!(0 0);
+        assert_eq!(attenuation(&cvars, listener, v!(0 0)), 1.0);
+        assert_eq!(attenuation(&cvars, listener, v!(50 0)), 0.5);
+        assert_eq!(attenuation(&cvars, listener, v!(100 0)), 0.0);
+        assert_eq!(attenuation(&cvars, listener, v!(0, -1000)), 0.0);
+
+        cvars.snd_rolloff = 2.0;
+        assert_eq!(attenuation(&cvars, listener, v!(0 0)), 1.0);
+        assert_eq!(attenuation(&cvars, listener, v!(50 0)), 0.25);
+        assert_eq!(attenuation(&cvars, listener, v!(200 0)), 0.0);
+    }
+}
@@ -0,0 +1,206 @@
+//! Names and colors for bots.
+//!
+//! Deterministic from the match seed and bot index
+//! so replays and rematches with the same seed show the same cast.
+//! A bot's identity only depends on the seed and the bots before it,
+//! adding more bots doesn't change the existing ones.
+//!
+//! LATER Use the colors in the kill feed and on the minimap once they exist.
+
+use crate::prelude::*;
+
+pub const BOT_NAMES: [&str; 20] = [
+    "Dr. Dead",
+    "Sir Hurt",
+    "Mr. Pain",
+    "PhD. Torture",
+    "Mrs. Chestwound",
+    "Ms. Dismember",
+    "Don Lobotomy",
+    "Lt. Dead",
+    "Sgt. Dead",
+    "Private Dead",
+    "Colonel Dead",
+    "Captain Dead",
+    "Major Dead",
+    "Commander Dead",
+    "Díotóir",
+    "Fireman",
+    "Goldfinger",
+    "Silverfinger",
+    "Bronzefinger",
+    "President Dead",
+];
+
+/// Colors of teams, bots avoid them in team modes.
+///
+/// LATER Make team colors configurable.
+pub const TEAM_COLORS: [Rgb; 2] = [Rgb::new(1.0, 0.0, 0.0), Rgb::new(0.0, 0.2, 1.0)];
+
+/// Minimum difference in hue (degrees) between a bot's color and reserved colors.
+pub const MIN_HUE_DISTANCE: f32 = 30.0;
+
+/// The golden angle - consecutive hues end up far apart no matter how many there are.
+const HUE_STEP: f32 = 137.50776;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub name: String,
+    pub color: Rgb,
+}
+
+/// Color which can be sent over the network, unlike macroquad's `Color`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Rgb {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Rgb {
+    pub const fn new(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b }
+    }
+
+    /// `hue` in degrees, `saturation` and `value` between 0 and 1.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let chroma = value * saturation;
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Self::new(r + m, g + m, b + m)
+    }
+
+    /// Hue in degrees, None for grays.
+    pub fn hue(self) -> Option<f32> {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        if delta <= 0.0 {
+            return None;
+        }
+        let h = if max == self.r {
+            ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / delta + 2.0
+        } else {
+            (self.r - self.g) / delta + 4.0
+        };
+        Some(h * 60.0)
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(rgb: Rgb) -> Self {
+        Color::new(rgb.r, rgb.g, rgb.b, 1.0)
+    }
+}
+
+/// Distance between two hues in degrees, going the short way around the circle.
+pub fn hue_distance(a: f32, b: f32) -> f32 {
+    let diff = (a - b).rem_euclid(360.0);
+    diff.min(360.0 - diff)
+}
+
+/// Identities for the first `count` bots, avoiding hues close to `reserved`.
+pub fn identities(seed: u64, count: usize, reserved: &[Rgb]) -> Vec<Identity> {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+    let mut names = BOT_NAMES.to_vec();
+    names.shuffle(&mut rng);
+    let mut hue: f32 = rng.gen_range(0.0..360.0);
+
+    let reserved_hues: Vec<_> = reserved.iter().filter_map(|color| color.hue()).collect();
+    let allowed = |hue: f32| {
+        reserved_hues
+            .iter()
+            .all(|&reserved| hue_distance(hue, reserved) >= MIN_HUE_DISTANCE)
+    };
+
+    let mut identities = Vec::with_capacity(count);
+    for i in 0..count {
+        let name = match names.get(i) {
+            Some(name) => (*name).to_owned(),
+            None => format!("Bot {}", i + 1),
+        };
+
+        // Limited in case the reserved colors cover the whole circle.
+        for _ in 0..100 {
+            if allowed(hue) {
+                break;
+            }
+            hue = (hue + HUE_STEP) % 360.0;
+        }
+        // Alternate brightness so bots with similar hues are still distinguishable.
+        let value = if i % 2 == 0 { 1.0 } else { 0.7 };
+        let color = Rgb::from_hsv(hue, 0.8, value);
+        identities.push(Identity { name, color });
+
+        hue = (hue + HUE_STEP) % 360.0;
+    }
+    identities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identities_deterministic() {
+        let a = identities(42, 30, &[]);
+        let b = identities(42, 30, &[]);
+        assert_eq!(a, b);
+
+        // More bots don't change the existing ones.
+        let fewer = identities(42, 5, &[]);
+        assert_eq!(fewer[..], a[..5]);
+
+        let other_seed = identities(43, 30, &[]);
+        assert_ne!(a, other_seed);
+    }
+
+    #[test]
+    fn test_identities_unique() {
+        for seed in 0..10 {
+            let ids = identities(seed, 30, &[]);
+            for (i, a) in ids.iter().enumerate() {
+                for b in &ids[i + 1..] {
+                    assert_ne!(a.name, b.name);
+                    assert_ne!(a.color, b.color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_identities_avoid_reserved() {
+        for seed in 0..10 {
+            for id in identities(seed, 30, &TEAM_COLORS) {
+                let hue = id.color.hue().unwrap();
+                for team in TEAM_COLORS {
+                    let dist = hue_distance(hue, team.hue().unwrap());
+                    // Small margin for float rounding in the HSV conversion.
+                    assert!(dist >= MIN_HUE_DISTANCE - 0.01, "{} {dist}", id.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hsv_roundtrip() {
+        for hue in [0.0, 45.0, 137.5, 200.0, 359.0] {
+            let rgb = Rgb::from_hsv(hue, 0.8, 0.7);
+            let back = rgb.hue().unwrap();
+            assert!(hue_distance(hue, back) < 0.01, "{hue} {back}");
+        }
+        assert_eq!(Rgb::new(0.5, 0.5, 0.5).hue(), None);
+        assert_eq!(hue_distance(350.0, 10.0), 20.0);
+    }
+}
@@ -0,0 +1,250 @@
+//! Recording short bursts of downscaled frames for sharing clips.
+//!
+//! Saving every frame as a screenshot is too slow to do while playing
+//! (see `Client::save_screenshot`) so the frames are downscaled
+//! and kept in memory while recording and only written out when the burst ends.
+//! Turn them into a GIF or WebM with an external tool, e.g.:
+//! `ffmpeg -framerate 30 -pattern_type glob -i 'captures/*/*.tga' clip.webm`.
+
+use std::collections::VecDeque;
+
+/// One downscaled frame in RGBA8, top row first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedFrame {
+    pub game_time: f64,
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+/// Ring buffer of captured frames with a hard cap on total memory.
+///
+/// When the cap is reached, the oldest frames are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct Capture {
+    frames: VecDeque<CapturedFrame>,
+    bytes: usize,
+    bytes_max: usize,
+    frames_left: usize,
+    every: usize,
+    frame_counter: usize,
+    /// How many frames were dropped to stay under the cap during this burst.
+    pub dropped: usize,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start recording `frames` frames, one every `every` rendered frames.
+    ///
+    /// Discards anything left over from a previous burst.
+    pub fn start(&mut self, frames: usize, every: usize, bytes_max: usize) {
+        *self = Self {
+            bytes_max,
+            frames_left: frames,
+            every: every.max(1),
+            ..Self::default()
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.frames_left > 0
+    }
+
+    /// Whether the current frame should be captured. Call exactly once per rendered frame.
+    pub fn wants_frame(&mut self) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        let due = self.frame_counter % self.every == 0;
+        self.frame_counter += 1;
+        due
+    }
+
+    pub fn push(&mut self, frame: CapturedFrame) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+
+        let size = frame.rgba.len();
+        if size > self.bytes_max {
+            self.dropped += 1;
+            return;
+        }
+        while self.bytes + size > self.bytes_max {
+            let oldest = self.frames.pop_front().unwrap();
+            self.bytes -= oldest.rgba.len();
+            self.dropped += 1;
+        }
+        self.bytes += size;
+        self.frames.push_back(frame);
+    }
+
+    /// Stop recording and return the captured frames, oldest first.
+    pub fn end(&mut self) -> Vec<CapturedFrame> {
+        self.frames_left = 0;
+        self.bytes = 0;
+        self.frames.drain(..).collect()
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Shrink an RGBA8 image by an integer `scale` by averaging each `scale`x`scale` block.
+///
+/// Leftover rows and columns which don't fill a whole block are dropped.
+/// Returns the new buffer with its width and height.
+pub fn box_downscale(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+) -> (Vec<u8>, usize, usize) {
+    assert_eq!(rgba.len(), width * height * 4);
+    let scale = scale.max(1);
+    let new_width = width / scale;
+    let new_height = height / scale;
+    let count = (scale * scale) as u32;
+
+    let mut out = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let mut sums = [0u32; 4];
+            for sy in y * scale..(y + 1) * scale {
+                let row = sy * width;
+                for sx in x * scale..(x + 1) * scale {
+                    let i = (row + sx) * 4;
+                    for (sum, &value) in sums.iter_mut().zip(&rgba[i..i + 4]) {
+                        *sum += u32::from(value);
+                    }
+                }
+            }
+            for sum in sums {
+                out.push(((sum + count / 2) / count) as u8);
+            }
+        }
+    }
+    (out, new_width, new_height)
+}
+
+/// Reverse the order of rows of an RGBA8 image in place.
+///
+/// The screen is read back bottom row first.
+pub fn flip_vertical(rgba: &mut [u8], width: usize) {
+    let row_len = width * 4;
+    let rows = rgba.len() / row_len;
+    for y in 0..rows / 2 {
+        let (top, bottom) = rgba.split_at_mut((rows - 1 - y) * row_len);
+        top[y * row_len..(y + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(game_time: f64, bytes: usize) -> CapturedFrame {
+        CapturedFrame {
+            game_time,
+            width: bytes / 4,
+            height: 1,
+            rgba: vec![0; bytes],
+        }
+    }
+
+    #[test]
+    fn test_capture_every() {
+        let mut capture = Capture::new();
+        assert!(!capture.wants_frame());
+
+        capture.start(3, 2, 1000);
+        let mut captured = Vec::new();
+        for i in 0..10 {
+            if capture.wants_frame() {
+                captured.push(i);
+                capture.push(frame(i as f64, 4));
+            }
+        }
+        assert_eq!(captured, [0, 2, 4]);
+        assert!(!capture.is_active());
+
+        let frames = capture.end();
+        let times: Vec<_> = frames.iter().map(|f| f.game_time).collect();
+        assert_eq!(times, [0.0, 2.0, 4.0]);
+        assert_eq!(capture.bytes(), 0);
+    }
+
+    #[test]
+    fn test_capture_bounded() {
+        let mut capture = Capture::new();
+        capture.start(100, 1, 100);
+        for i in 0..10 {
+            capture.push(frame(i as f64, 40));
+            assert!(capture.bytes() <= 100);
+        }
+        assert_eq!(capture.frames(), 2);
+        assert_eq!(capture.bytes(), 80);
+        assert_eq!(capture.dropped, 8);
+
+        // A frame which doesn't fit at all doesn't evict everything else.
+        capture.push(frame(10.0, 200));
+        assert_eq!(capture.frames(), 2);
+        assert_eq!(capture.dropped, 9);
+
+        // Oldest are dropped first.
+        let frames = capture.end();
+        let times: Vec<_> = frames.iter().map(|f| f.game_time).collect();
+        assert_eq!(times, [8.0, 9.0]);
+
+        // Restarting resets the counters.
+        capture.start(1, 1, 100);
+        assert_eq!(capture.dropped, 0);
+        assert_eq!(capture.frames(), 0);
+    }
+
+    #[test]
+    fn test_box_downscale() {
+        // 4x2 image: left 2x2 block is black and white, right block is a single color.
+        #[rustfmt::skip]
+        let rgba = [
+            0, 0, 0, 255,         255, 255, 255, 255,   10, 20, 30, 40,   10, 20, 30, 40,
+            255, 255, 255, 255,   0, 0, 0, 255,         10, 20, 30, 40,   10, 20, 30, 40,
+        ];
+        let (out, w, h) = box_downscale(&rgba, 4, 2, 2);
+        assert_eq!((w, h), (2, 1));
+        assert_eq!(out, [128, 128, 128, 255, 10, 20, 30, 40]);
+
+        // Scale 1 is a copy.
+        let (out, w, h) = box_downscale(&rgba, 4, 2, 1);
+        assert_eq!((w, h), (4, 2));
+        assert_eq!(out, rgba);
+    }
+
+    #[test]
+    fn test_box_downscale_remainder() {
+        // 5x3 gradient, the last column and row don't fill a 2x2 block.
+        let mut rgba = Vec::new();
+        for y in 0..3u8 {
+            for x in 0..5u8 {
+                rgba.extend([x * 10, y * 10, 0, 255]);
+            }
+        }
+        let (out, w, h) = box_downscale(&rgba, 5, 3, 2);
+        assert_eq!((w, h), (2, 1));
+        assert_eq!(out, [5, 5, 0, 255, 25, 5, 0, 255]);
+    }
+
+    #[test]
+    fn test_flip_vertical() {
+        let mut rgba: Vec<u8> = (0..24).collect();
+        flip_vertical(&mut rgba, 2);
+        let expected: Vec<u8> = (16..24).chain(8..16).chain(0..8).collect();
+        assert_eq!(rgba, expected);
+    }
+}
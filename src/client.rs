@@ -7,9 +7,21 @@ use macroquad::prelude::*;
 use time::{format_description, OffsetDateTime};
 
 use crate::{
-    debug::{self, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_TEXTS_WORLD},
+    audio::{FireBlockedSound, LockWarningSound, TurretSound},
+    capture::{self, Capture, CapturedFrame},
+    debug,
+    decals::Decals,
+    effects_budget::{EffectsBudget, EffectsDegrade},
+    metrics::Metrics,
     net::{self, Connection},
+    observer::{ActionKind, Director},
+    particles::Particles,
     prelude::*,
+    presets::PRESET_NAMES,
+    scraping::{ScrapeSound, Spark},
+    smoothing::{self, EntitySmoothing, Sample},
+    surface_effects::SurfaceEffect,
+    timed_effects::TimedEffects,
 };
 
 pub struct Client {
@@ -37,17 +49,23 @@ pub struct Client {
     pub gamelogic_fps: Fps,
     pub gamelogic_durations: Durations,
     pub screenshot_durations: Durations,
+    pub capture: Capture,
+    /// Last seen value of `cl_capture` so we know when it's toggled.
+    pub capture_requested: bool,
+    pub capture_durations: Durations,
     /// Rendering consists of 2 steps:
     /// - Calling macroquad's draw functions
     /// - Calling next_frame() to actually render the frame
     pub render_fps: Fps,
     pub draw_calls_durations: Durations,
     pub engine_durations: Durations,
+    pub perf_warning: PerfWarning,
 
     /// Size of one player's view - either the whole screen or (a bit less than) half of it.
     pub viewport_size: Vec2f,
     pub client_mode: ClientMode,
     pub last_key: Option<KeyCode>,
+    pub focus: FocusDetector,
     pub console: MacroquadConsole,
 }
 
@@ -58,27 +76,177 @@ pub struct ClientGame {
     pub input2_prev: ClientInput,
 
     pub conn: Box<dyn Connection<ServerMessage>>,
-    pub tmp_local_player_handle: Index, // LATER Proper splitscreen mode
+    /// One per local player, the first is player 1 who can possess bots.
+    pub views: Vec<LocalPlayerView>,
+    /// Last value of `d_possess` sent to the server.
+    pub possess_requested: i32,
+    /// Last value of `g_preset` sent to the server or received from it.
+    pub preset_requested: String,
+    /// The server switched presets, waiting for `Client::apply_received_preset`.
+    pub preset_received: Option<String>,
+    /// Where the camera goes with `cl_observer`.
+    pub director: Director,
 
     pub paused: bool,
-
-    pub rail_beams: Vec<RailBeam>,
-    pub explosions: Vec<Explosion>,
-
-    pub notifications: Vec<Notification>,
+    /// Same as `Client::real_time` - HUD effects run on this clock
+    /// so they don't slow down with `d_speed`, world effects use game time.
+    pub real_time: f64,
+    /// Whether the window has focus, see `Client::focus_changed`.
+    pub focused: bool,
+
+    pub rail_beams: TimedEffects<RailBeam>,
+    pub explosions: TimedEffects<Explosion>,
+    /// Sequence number for the next explosion.
+    pub explosion_seq: u64,
+    /// Bullet chips and scorch marks on walls, see `decals`.
+    pub decals: Decals,
+    pub surface_effects: Vec<SurfaceEffect>,
+    pub sparks: Vec<Spark>,
+    pub particles: Particles,
+    /// What effects can cost during this update, recomputed at the start of each.
+    pub effects_budget: EffectsBudget,
+    pub effects_degrade: EffectsDegrade,
+    /// Waiting for the audio backend, see `Client::take_scrape_sounds`.
+    pub scrape_sounds: Vec<ScrapeSound>,
+    /// When each vehicle last made a scraping sound.
+    pub scrape_sound_times: FnvHashMap<Index, f64>,
+    /// Waiting for the audio backend, see `Client::take_turret_sounds`.
+    pub turret_sounds: Vec<TurretSound>,
+    /// When each vehicle last made a turret sound.
+    pub turret_sound_times: FnvHashMap<Index, f64>,
+    /// Whether a homing missile is locked onto the listener's vehicle, see `sys_lock_warning`.
+    pub lock_warning: bool,
+    /// Waiting for the audio backend, see `Client::take_lock_warning_sounds`.
+    pub lock_warning_sounds: Vec<LockWarningSound>,
+    /// Waiting for the audio backend, see `Client::take_fire_blocked_sounds`.
+    pub fire_blocked_sounds: Vec<FireBlockedSound>,
+    /// When the listener's vehicle last clicked, see `sys_fire_blocked_sound`.
+    pub fire_blocked_sound_time: f64,
+
+    /// Only populated if `cl_entity_smoothing` is on, see `smoothing`.
+    pub vehicle_smoothing: FnvHashMap<Index, EntitySmoothing>,
+    pub projectile_smoothing: FnvHashMap<Index, EntitySmoothing>,
 
     /// Last received server fps and durations info. Might be a few frames old.
     pub server_timings: CommonTimings,
+    /// Server game time of the newest update in the messages being handled,
+    /// see `ClientFrameCtx::effect_start_time`.
+    pub server_time: f64,
+    /// Last received position in the server's rewind history, see `history`.
+    pub history: HistoryStatus,
+
+    pub metrics: Metrics,
+}
+
+impl ClientGame {
+    pub fn new(conn: Box<dyn Connection<ServerMessage>>, player1_handle: Index) -> Self {
+        Self {
+            input1: ClientInput::empty(),
+            input1_prev: ClientInput::empty(),
+            input2: ClientInput::empty(),
+            input2_prev: ClientInput::empty(),
+
+            conn,
+            views: vec![LocalPlayerView::new(player1_handle)],
+            possess_requested: -1,
+            // The real value is set from cvars in `Client::new`.
+            preset_requested: Cvars::default().g_preset,
+            preset_received: None,
+            director: Director::new(),
+
+            paused: false,
+            real_time: 0.0,
+            focused: true,
+
+            // The real limits are set from cvars during cleanup.
+            rail_beams: TimedEffects::new(Cvars::default().cl_railgun_trails_max),
+            explosions: TimedEffects::new(Cvars::default().r_explosions_max),
+            explosion_seq: 0,
+            decals: Decals::new(Cvars::default().cl_decals_max),
+            surface_effects: Vec::new(),
+            sparks: Vec::new(),
+            particles: Particles::new(),
+            effects_budget: EffectsBudget::new(&Cvars::default(), EffectsQuality::High),
+            effects_degrade: EffectsDegrade::new(),
+            scrape_sounds: Vec::new(),
+            scrape_sound_times: FnvHashMap::default(),
+            turret_sounds: Vec::new(),
+            turret_sound_times: FnvHashMap::default(),
+            lock_warning: false,
+            lock_warning_sounds: Vec::new(),
+            fire_blocked_sounds: Vec::new(),
+            fire_blocked_sound_time: f64::NEG_INFINITY,
+
+            vehicle_smoothing: FnvHashMap::default(),
+            projectile_smoothing: FnvHashMap::default(),
+
+            server_timings: CommonTimings::default(),
+            server_time: 0.0,
+            history: HistoryStatus::default(),
+
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// Release all held keys.
+    ///
+    /// The previous input is kept so systems relying on edges see the keys being released
+    /// and anything still held when input resumes is a fresh press.
+    pub fn clear_input(&mut self) {
+        self.input1_prev = self.input1;
+        self.input1 = ClientInput::empty();
+        self.input2_prev = self.input2;
+        self.input2 = ClientInput::empty();
+    }
+
+    /// Returns true if the window just lost focus.
+    pub fn update_focus(&mut self, focused: bool) -> bool {
+        let lost = self.focused && !focused;
+        self.focused = focused;
+        if lost {
+            self.clear_input();
+        }
+        lost
+    }
+
+    /// Add an explosion, numbered in creation order so drawing is deterministic,
+    /// see `Explosion::draw_order`.
+    pub fn add_explosion(
+        &mut self,
+        cvars: &Cvars,
+        pos: Vec2f,
+        scale: f64,
+        start_time: f64,
+        bfg: bool,
+        team: Option<u32>,
+    ) {
+        let mut explosion = Explosion::new(pos, scale, start_time, bfg, self.explosion_seq);
+        explosion.team = team;
+        self.explosion_seq += 1;
+        self.explosions
+            .push(explosion, start_time, cvars.r_explosion_duration);
+    }
+
+    /// Record this frame's input. Everything is released while the window is unfocused.
+    pub fn set_input(&mut self, input1: ClientInput, input2: ClientInput) {
+        if !self.focused {
+            self.clear_input();
+            return;
+        }
+
+        self.input1_prev = self.input1;
+        self.input1 = input1;
+        self.input2_prev = self.input2;
+        self.input2 = input2;
+    }
 }
 
 #[derive(Debug)]
 pub enum ClientMode {
-    Singleplayer {
-        player_handle: Index,
-    },
+    /// The players are in `ClientGame::views`.
+    Singleplayer,
     Splitscreen {
         render_targets: (RenderTarget, RenderTarget),
-        player_handles: (Index, Index),
     },
 }
 
@@ -92,24 +260,8 @@ impl Client {
         player1_handle: Index,
         player2_handle: Option<Index>,
     ) -> Self {
-        let cg = ClientGame {
-            input1: ClientInput::empty(),
-            input1_prev: ClientInput::empty(),
-            input2: ClientInput::empty(),
-            input2_prev: ClientInput::empty(),
-
-            conn,
-            tmp_local_player_handle: player1_handle,
-
-            paused: false,
-
-            rail_beams: Vec::new(),
-            explosions: Vec::new(),
-
-            notifications: Vec::new(),
-
-            server_timings: CommonTimings::default(),
-        };
+        let mut cg = ClientGame::new(conn, player1_handle);
+        cg.preset_requested = cvars.g_preset.clone();
 
         dbg_logf!("Window inner size: {}x{}", screen_width(), screen_height());
         let (viewport_size, client_mode) = if let Some(player2_handle) = player2_handle {
@@ -118,18 +270,16 @@ impl Client {
             let viewport_left = render_target(viewport_size.x as u32, viewport_size.y as u32);
             let viewport_right = render_target(viewport_size.x as u32, viewport_size.y as u32);
 
+            cg.views.push(LocalPlayerView::new(player2_handle));
             let client_mode = ClientMode::Splitscreen {
                 render_targets: (viewport_left, viewport_right),
-                player_handles: (player1_handle, player2_handle),
             };
 
             (viewport_size, client_mode)
         } else {
             let viewport_size = Vec2f::new(screen_width() as f64, screen_height() as f64);
 
-            let client_mode = ClientMode::Singleplayer {
-                player_handle: player1_handle,
-            };
+            let client_mode = ClientMode::Singleplayer;
 
             (viewport_size, client_mode)
         };
@@ -153,13 +303,18 @@ impl Client {
             gamelogic_fps: Fps::new(),
             gamelogic_durations: Durations::new(),
             screenshot_durations: Durations::new(),
+            capture: Capture::new(),
+            capture_requested: false,
+            capture_durations: Durations::new(),
             render_fps: Fps::new(),
             draw_calls_durations: Durations::new(),
             engine_durations: Durations::new(),
+            perf_warning: PerfWarning::new(),
 
             viewport_size,
             client_mode,
             last_key: None,
+            focus: FocusDetector::new(),
             console: MacroquadConsole::new(),
         }
     }
@@ -183,51 +338,68 @@ impl Client {
         self.real_time_prev = self.real_time;
         self.real_time = real_time;
         self.real_time_delta = self.real_time - self.real_time_prev;
+        self.cg.real_time = self.real_time;
+
+        // Before anything spawns effects
+        let draw_avg = self.draw_calls_durations.get_stats().map(|(avg, _)| avg);
+        if self
+            .cg
+            .effects_degrade
+            .update(cvars, draw_avg, self.real_time)
+        {
+            self.cg.metrics.effects_degraded.inc();
+        }
+        let quality = self.cg.effects_degrade.quality(cvars);
+        self.cg.effects_budget = EffectsBudget::new(cvars, quality);
 
         // We have to also send and receive outside gamelogic so pausing and unpausing works.
         let mut ctx = self.ctx(cvars);
         ctx.sys_net_send();
         ctx.sys_net_receive();
-        if !self.cg.paused {
-            let dt_update = self.real_time_delta * cvars.d_speed;
-            self.gamelogic(cvars, dt_update);
+        self.gamelogic(cvars);
+
+        if cvars.d_audio {
+            for sound in self.audio_state(cvars) {
+                dbg_textf!(
+                    "engine {:?} {:?}: throttle {:.2} speed {:.2} attenuation {:.2}",
+                    sound.vehicle_handle,
+                    sound.veh_type,
+                    sound.throttle,
+                    sound.speed,
+                    sound.attenuation
+                );
+            }
         }
 
         let end = macroquad::time::get_time();
         self.update_durations
             .add(cvars.d_timing_samples, end - start);
+        self.cg.metrics.record_update(end - start);
+
+        let update_avg = self.update_durations.get_stats().map(|(avg, _)| avg);
+        let draw_avg = self.draw_calls_durations.get_stats().map(|(avg, _)| avg);
+        self.perf_warning
+            .update(cvars, update_avg, draw_avg, self.real_time);
+        self.cg.metrics.perf_degraded = self.perf_warning.degraded;
     }
 
     /// The main game loop.
-    fn gamelogic(&mut self, cvars: &Cvars, dt_update: f64) {
+    fn gamelogic(&mut self, cvars: &Cvars) {
         // LATER Slow down time to prevent death spirals.
         // LATER Extrapolation (after client / server split).
         //  Gamecode should not know about it.
         //  Construct FrameData with the throwaway gs, gamelogic_tick_movement that only calls the movement systems?
         //  Don't accidentally call functions which modify state outside gs.
 
-        if dt_update > 5.0 {
-            dbg_logf!("WARNING: large dt_update: {dt_update}");
-        }
-
-        match cvars.sys_tickrate_mode {
-            TickrateMode::Variable => {
-                let game_time_target = self.gs.game_time + dt_update;
-                self.gamelogic_tick(cvars, game_time_target);
-            }
-            TickrateMode::Fixed => {
-                let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
-                let game_time_target = self.gs.game_time + self.game_time_carry + dt_update;
-
-                while self.gs.game_time + dt < game_time_target {
-                    self.gamelogic_tick(cvars, self.gs.game_time + dt);
-                }
-
-                self.game_time_carry = game_time_target - self.gs.game_time;
-                if cvars.d_tickrate_fixed_carry {
-                    dbg_logf!("Remaining time: {}", self.game_time_carry);
-                }
-            }
+        let tick_times = gamelogic_tick_times(
+            cvars,
+            self.gs.game_time,
+            &mut self.game_time_carry,
+            self.real_time_delta,
+            self.cg.paused,
+        );
+        for game_time in tick_times {
+            self.gamelogic_tick(cvars, game_time);
         }
     }
 
@@ -257,7 +429,7 @@ impl Client {
             );
         }
 
-        debug::clear_expired();
+        debug::clear_expired(self.gs.dt);
 
         dbg_textf!("{}", env!("GIT_VERSION"));
         dbg_textd!(self.gs.game_time);
@@ -269,15 +441,31 @@ impl Client {
         ctx.sys_net_send();
         ctx.sys_net_receive();
 
+        ctx.sys_observer();
+
+        ctx.sys_surface_under();
+        ctx.sys_surface_effects();
+        ctx.sys_scraping();
+        ctx.sys_boost_exhaust();
+        ctx.sys_track_scrub();
+        ctx.sys_turret_sounds();
+        ctx.sys_lock_warning();
+        ctx.sys_fire_blocked_sound();
+
         ctx.sys_debug_examples(v!(25 300));
 
         dbg_textf!("vehicle count: {}", self.gs.vehicles.len());
         dbg_textf!("projectile count: {}", self.gs.projectiles.len());
-        dbg_textf!("explosion count: {}", self.cg.explosions.len());
+        dbg_textf!(
+            "explosion count: {} ({} evicted)",
+            self.cg.explosions.len(),
+            self.cg.explosions.evicted
+        );
 
         let end = macroquad::time::get_time();
         self.gamelogic_durations
             .add(cvars.d_timing_samples, end - start);
+        self.cg.metrics.gamelogic_ticks.inc();
     }
 
     pub fn cl_input(&mut self, cvars: &Cvars) {
@@ -290,21 +478,126 @@ impl Client {
             self.last_key = Some(key_code);
         }
 
-        self.cg.input1_prev = self.cg.input1;
-        self.cg.input1 = get_input1();
-        self.cg.input2_prev = self.cg.input2;
-        self.cg.input2 = get_input2();
+        self.cg
+            .set_input(get_input1(&self.focus), get_input2(&self.focus));
 
         if !self.cg.input1_prev.pause && self.cg.input1.pause {
             let msg = ClientMessage::Pause;
             self.ctx(cvars).net_send(msg);
         }
+        if !self.cg.input1_prev.history_rewind && self.cg.input1.history_rewind {
+            let msg = ClientMessage::History(HistoryCommand::Rewind);
+            self.ctx(cvars).net_send(msg);
+        }
+        if !self.cg.input1_prev.history_resume && self.cg.input1.history_resume {
+            let msg = ClientMessage::History(HistoryCommand::Resume);
+            self.ctx(cvars).net_send(msg);
+        }
+
+        if cvars.d_possess != self.cg.possess_requested {
+            self.cg.possess_requested = cvars.d_possess;
+            let index = u32::try_from(cvars.d_possess).ok();
+            let msg = ClientMessage::Possess(index);
+            self.ctx(cvars).net_send(msg);
+        }
+
+        if cvars.g_preset != self.cg.preset_requested {
+            self.cg.preset_requested = cvars.g_preset.clone();
+            if PRESET_NAMES.contains(&cvars.g_preset.as_str()) {
+                let msg = ClientMessage::Preset(cvars.g_preset.clone());
+                self.ctx(cvars).net_send(msg);
+            } else {
+                dbg_logf!(
+                    "WARNING: Unknown preset {}, expected one of {:?}",
+                    cvars.g_preset,
+                    PRESET_NAMES
+                );
+            }
+        }
+    }
+
+    /// Apply the balance preset the server switched to so the HUD matches, see `presets`.
+    ///
+    /// Needs mutable cvars so it runs outside `update`.
+    pub fn apply_received_preset(&mut self, cvars: &mut Cvars) {
+        let Some(name) = self.cg.preset_received.take() else {
+            return;
+        };
+        match cvars.apply_preset(&name) {
+            Ok(()) => {
+                dbg_logf!("Server switched to preset {name}");
+                self.cg.preset_requested = name;
+            }
+            Err(e) => dbg_logf!("WARNING: {e}"),
+        }
+    }
+
+    /// Call when the window gains or loses focus.
+    ///
+    /// We might not get key up events while unfocused
+    /// so all keys are released until focus returns
+    /// instead of the vehicle driving into a wall forever.
+    ///
+    /// MQ doesn't expose focus events so it's a guess from `FocusDetector`.
+    pub fn focus_changed(&mut self, cvars: &Cvars, focused: bool) {
+        if !self.cg.update_focus(focused) {
+            return;
+        }
+
+        // Don't wait for the next update, it might not come until focus returns.
+        let mut ctx = self.ctx(cvars);
+        ctx.sys_net_send();
+        if cvars.cl_pause_on_focus_loss && !ctx.cg.paused {
+            ctx.net_send(ClientMessage::Pause);
+        }
     }
 
     pub fn post_render(&mut self, cvars: &Cvars) {
         if cvars.cl_screenshots {
             self.save_screenshot(cvars);
         }
+        if cvars.cl_capture != self.capture_requested {
+            self.capture_requested = cvars.cl_capture;
+            if cvars.cl_capture {
+                self.capture_durations = Durations::new();
+                self.capture.start(
+                    cvars.cl_capture_frames,
+                    cvars.cl_capture_every,
+                    cvars.cl_capture_bytes_max,
+                );
+            } else if self.capture.is_active() {
+                self.save_capture(cvars);
+            }
+        }
+        if self.capture.wants_frame() {
+            self.capture_frame(cvars);
+            if !self.capture.is_active() {
+                self.save_capture(cvars);
+            }
+        }
+        if !cvars.cl_metrics_path.is_empty()
+            && self.real_time - self.cg.metrics.written_time >= cvars.cl_metrics_interval
+        {
+            self.save_metrics(cvars);
+        }
+    }
+
+    /// All metrics in the Prometheus text exposition format, see `metrics`.
+    pub fn metrics_text(&mut self) -> String {
+        self.cg.metrics.text(&self.gs)
+    }
+
+    fn save_metrics(&mut self, cvars: &Cvars) {
+        self.cg.metrics.written_time = self.real_time;
+        let text = self.metrics_text();
+
+        // Write to a temp file and rename so readers never see a partial file.
+        let path = &cvars.cl_metrics_path;
+        let tmp_path = format!("{path}.tmp");
+        let res = std::fs::write(&tmp_path, text).and_then(|_| std::fs::rename(&tmp_path, path));
+        if let Err(e) = res {
+            dbg_logf!("Failed to write metrics to {path:?}: {e}");
+        }
     }
 
     fn save_screenshot(&mut self, cvars: &Cvars) {
@@ -365,21 +658,113 @@ impl Client {
         self.screenshot_durations
             .add(cvars.d_timing_samples, t2 - t1);
     }
+
+    fn capture_frame(&mut self, cvars: &Cvars) {
+        let t1 = get_time();
+
+        // Same cost as in save_screenshot but the rest is much cheaper
+        // because we only downscale and keep the frame in memory.
+        let img = get_screen_data();
+        let (width, height) = (img.width as usize, img.height as usize);
+        let (mut rgba, width, height) =
+            capture::box_downscale(&img.bytes, width, height, cvars.cl_capture_scale);
+        capture::flip_vertical(&mut rgba, width);
+        self.capture.push(CapturedFrame {
+            game_time: self.gs.game_time,
+            width,
+            height,
+            rgba,
+        });
+
+        let t2 = get_time();
+        self.capture_durations.add(cvars.d_timing_samples, t2 - t1);
+    }
+
+    fn save_capture(&mut self, cvars: &Cvars) {
+        let dropped = self.capture.dropped;
+        let frames = self.capture.end();
+
+        let format =
+            format_description::parse("[year]-[month]-[day]--[hour]-[minute]-[second]").unwrap();
+        let dt = OffsetDateTime::now_utc().format(&format).unwrap();
+
+        for (index, frame) in frames.iter().enumerate() {
+            let path = cvars
+                .cl_capture_path
+                .replace("{date_time}", &dt)
+                .replace("{index}", &format!("{:04}", index))
+                .replace("{game_time}", &format!("{:.03}", frame.game_time));
+
+            let dir = Path::new(&path).parent().unwrap();
+            std::fs::create_dir_all(dir).soft_unwrap();
+
+            image::save_buffer(
+                &path,
+                &frame.rgba,
+                frame.width as u32,
+                frame.height as u32,
+                image::ColorType::Rgba8,
+            )
+            .soft_unwrap();
+        }
+        dbg_logf!(
+            "Saved {} captured frames ({} dropped to stay under cl_capture_bytes_max)",
+            frames.len(),
+            dropped
+        );
+    }
 }
 
 impl ClientFrameCtx<'_> {
     pub fn sys_cl_cleanup(&mut self) {
-        self.cg.rail_beams.retain(|beam| {
-            beam.start_time + self.cvars.cl_railgun_trail_duration > self.gs.game_time
+        self.cg.rail_beams.max = self.cvars.cl_railgun_trails_max;
+        self.cg.rail_beams.cleanup(self.gs.game_time);
+        self.cg.explosions.max = self.cvars.r_explosions_max;
+        self.cg.explosions.cleanup(self.gs.game_time);
+        let decals_max = self.cg.effects_budget.decals_max;
+        self.cg.decals.new_frame(decals_max, self.gs.game_time);
+        self.cg.surface_effects.retain(|effect| {
+            self.gs.game_time - effect.start_time < effect.kind.duration(self.cvars)
         });
-        self.cg.explosions.retain(|explosion| {
-            let age = self.gs.game_time - explosion.start_time;
-            let progress = age / self.cvars.r_explosion_duration;
-            progress <= 1.0
+        self.cg.sparks.retain(|spark| {
+            self.gs.game_time - spark.start_time < self.cvars.cl_scrape_sparks_duration
         });
-        self.cg.notifications.retain(|notification| {
-            self.gs.game_time - notification.start_time < self.cvars.hud_notifications_duration
+        self.cg.particles.cleanup(self.gs.game_time);
+        for view in &mut self.cg.views {
+            view.cleanup(self.cvars, self.cg.real_time, self.gs.dt);
+        }
+        // In case there's no audio backend taking them.
+        self.cg
+            .scrape_sounds
+            .retain(|sound| self.gs.game_time - sound.start_time < self.cvars.snd_scrape_interval);
+        self.cg
+            .scrape_sound_times
+            .retain(|&handle, _| self.gs.vehicles.contains(handle));
+        self.cg
+            .turret_sounds
+            .retain(|sound| self.gs.game_time - sound.start_time < self.cvars.snd_turret_interval);
+        self.cg
+            .turret_sound_times
+            .retain(|&handle, _| self.gs.vehicles.contains(handle));
+        self.cg.fire_blocked_sounds.retain(|sound| {
+            self.gs.game_time - sound.start_time < self.cvars.snd_fire_blocked_interval
         });
+        // Only the latest one says whether the tone should be playing now.
+        let lock_warning_old = self.cg.lock_warning_sounds.len().saturating_sub(1);
+        self.cg.lock_warning_sounds.drain(..lock_warning_old);
+        if self.cvars.cl_entity_smoothing {
+            self.cg
+                .vehicle_smoothing
+                .retain(|&handle, _| self.gs.vehicles.contains(handle));
+            self.cg
+                .projectile_smoothing
+                .retain(|&handle, _| self.gs.projectiles.contains(handle));
+        } else {
+            self.cg.vehicle_smoothing.clear();
+            self.cg.projectile_smoothing.clear();
+        }
+        self.sys_smoke_cleanup();
+        self.sys_flare_cleanup();
     }
 
     pub fn sys_net_send(&mut self) {
@@ -404,6 +789,15 @@ impl ClientFrameCtx<'_> {
 
     pub fn sys_net_receive(&mut self) {
         let (msgs, closed) = self.cg.conn.receive();
+
+        // After a hitch the server runs several catch-up ticks and they all arrive at once.
+        // Effects are aged relative to the last of them.
+        for msg in &msgs {
+            if let ServerMessage::Update(update) = msg {
+                self.cg.server_time = update.game_time;
+            }
+        }
+
         for msg in msgs {
             match msg {
                 ServerMessage::Version(_) => todo!(),
@@ -413,15 +807,38 @@ impl ClientFrameCtx<'_> {
                 ServerMessage::Update(update) => self.handle_update(update),
 
                 ServerMessage::Paused(paused) => self.cg.paused = paused,
+                ServerMessage::Possessed(index) => {
+                    self.cg.views[0].possessed =
+                        index.and_then(|index| self.gs.players.slot_to_index(index));
+                }
 
                 ServerMessage::AddPlayer(init) => self.init_player(init),
                 ServerMessage::SpawnVehicle(init) => self.init_vehicle(init),
-                ServerMessage::SpawnProjectile(init) => self.init_projectile(init),
-                ServerMessage::SpawnExplosion(init) => self.init_explosion(init),
+                ServerMessage::SpawnProjectile(init) => {
+                    self.cg.metrics.projectiles_spawned.inc();
+                    let index = init.index;
+                    let start_time = self.effect_start_time(init.spawn_time);
+                    self.init_projectile(init);
+                    let handle = self.gs.projectiles.slot_to_index(index).unwrap();
+                    self.spawn_launch_effects(handle, start_time);
+                }
+                ServerMessage::SpawnExplosion(init) => {
+                    self.cg.metrics.explosions_created.inc();
+                    self.init_explosion(init);
+                }
+                ServerMessage::SpawnSmoke(init) => self.init_smoke(init),
+                ServerMessage::SpawnFlares(init) => self.init_flares(init),
+                ServerMessage::SpawnMine(mut init) => {
+                    init.spawn_time = self.effect_start_time(init.spawn_time);
+                    self.init_mine(init);
+                }
 
                 ServerMessage::RailBeam(mut beam) => {
-                    beam.start_time = self.gs.game_time; // LATER don't sent start_time from server
-                    self.cg.rail_beams.push(beam);
+                    beam.start_time = self.effect_start_time(beam.start_time);
+                    let start_time = beam.start_time;
+                    self.cg
+                        .rail_beams
+                        .push(beam, start_time, self.cvars.cl_railgun_trail_duration);
                 }
 
                 ServerMessage::RemovePlayer { index } => {
@@ -431,12 +848,51 @@ impl ClientFrameCtx<'_> {
                     dbg_logf!("Player {name:?} removed");
                     // LATER Chat notification
                 }
-                ServerMessage::DestroyProjectile { index } => {
+                ServerMessage::DestroyProjectile { index, impact } => {
                     // LATER Explosion here instead of SpawnExplosion?
                     let old = self.gs.projectiles.remove_by_slot(index);
                     soft_assert!(old.is_some());
+                    let decal_source = old
+                        .as_ref()
+                        .map(|(_, projectile)| (projectile.weapon, projectile.vel));
+                    if let Some((handle, projectile)) = old {
+                        self.gs.projectile_ids.remove(handle);
+                        if let Some(HmTarget::Vehicle(target)) = projectile.target {
+                            if let Some(vehicle) = self.gs.vehicles.get_mut(target) {
+                                vehicle.hms.retain(|&hm| hm != handle);
+                            }
+                        }
+                        // Until the next update says so.
+                        for (_, player) in self.gs.players.iter_mut() {
+                            if player.guided_missile == Some(handle) {
+                                player.guided_missile = None;
+                            }
+                        }
+                    }
+                    self.cg.metrics.projectiles_removed.inc();
+                    if let Some(pos) = impact {
+                        self.spawn_impact_effects(pos, self.gs.game_time);
+                        if let Some((weapon, vel)) = decal_source {
+                            self.spawn_impact_decal(weapon, pos, vel);
+                        }
+                    }
+                }
+                ServerMessage::DestroyMine { index } => {
+                    let old = self.gs.mines.remove_by_slot(index);
+                    soft_assert!(old.is_some());
+                }
+                ServerMessage::HmLock { index, target } => self.handle_hm_lock(index, target),
+                ServerMessage::Sink { index, game_time } => {
+                    let vehicle_handle = self.gs.vehicles.slot_to_index(index).unwrap();
+                    let start_time = self.effect_start_time(game_time);
+                    let sinking = Sinking::new(start_time, self.cvars.g_sink_time);
+                    self.gs.vehicles[vehicle_handle].sinking = Some(sinking);
                 }
                 ServerMessage::Kill(kill) => self.handle_kill(kill),
+                ServerMessage::AiChat(chat) => self.handle_ai_chat(chat),
+                ServerMessage::Phase(phase) => self.gs.phase = phase,
+                ServerMessage::History(status) => self.cg.history = status,
+                ServerMessage::Preset(name) => self.cg.preset_received = Some(name),
             }
         }
 
@@ -445,12 +901,76 @@ impl ClientFrameCtx<'_> {
         }
     }
 
+    /// Client game time when an effect the server created at `server_time` started.
+    ///
+    /// Effects from earlier catch-up ticks are already partway through
+    /// instead of all starting at once when the batch arrives.
+    /// The two clocks don't have to match, only the age is carried over.
+    pub fn effect_start_time(&self, server_time: f64) -> f64 {
+        let age = (self.cg.server_time - server_time).max(0.0);
+        self.gs.game_time - age
+    }
+
     fn init_explosion(&mut self, init: ExplosionInit) {
-        let ExplosionInit { pos, scale, bfg } = init;
-        // LATER Setting start_time to client game_time means the animation plays from the start
-        // but also that the explosion is delayed compared to the server. Is this what we want?
-        let explosion = Explosion::new(pos, scale, self.gs.game_time, bfg);
-        self.cg.explosions.push(explosion);
+        let ExplosionInit {
+            pos,
+            scale,
+            bfg,
+            game_time,
+            team,
+        } = init;
+        // Explosions always show up but leave less room for cosmetic effects.
+        self.cg.effects_budget.spend(1);
+        let start_time = self.effect_start_time(game_time);
+        self.cg
+            .add_explosion(self.cvars, pos, scale, start_time, bfg, team);
+        self.spawn_explosion_splash(pos, scale, start_time);
+    }
+
+    fn init_smoke(&mut self, init: SmokeInit) {
+        let SmokeInit {
+            pos,
+            radius,
+            owner,
+            game_time,
+        } = init;
+        let start_time = self.effect_start_time(game_time);
+        let owner = self
+            .gs
+            .players
+            .slot_to_index(owner)
+            .unwrap_or(Index::DANGLING);
+        // The client doesn't know about the cooldown otherwise, needed for the HUD.
+        if let Some(player) = self.gs.players.get_mut(owner) {
+            player.smoke_time = start_time;
+        }
+        let smoke = Smoke::new(pos, radius, start_time, owner);
+        self.gs.smokes.push(smoke);
+    }
+
+    fn init_flares(&mut self, init: FlaresInit) {
+        let FlaresInit {
+            pos,
+            vels,
+            owner,
+            game_time,
+        } = init;
+        let start_time = self.effect_start_time(game_time);
+        let owner = self
+            .gs
+            .players
+            .slot_to_index(owner)
+            .unwrap_or(Index::DANGLING);
+        // The client doesn't know about the count and cooldown otherwise, needed for the HUD.
+        let vehicle_handle = self.gs.players.get(owner).and_then(|player| player.vehicle);
+        if let Some(vehicle) = vehicle_handle.and_then(|handle| self.gs.vehicles.get_mut(handle)) {
+            vehicle.flares = vehicle.flares.saturating_sub(1);
+            vehicle.flare_time = start_time;
+        }
+        for vel in vels {
+            let flare = Flare::new(pos, vel, start_time, owner);
+            self.gs.flares.insert(flare);
+        }
     }
 
     pub fn handle_update(&mut self, update: Update) {
@@ -463,6 +983,7 @@ impl ClientFrameCtx<'_> {
             player_inputs,
             vehicles,
             projectiles,
+            emplacements,
             debug_texts,
             debug_texts_world,
             debug_shapes,
@@ -473,10 +994,20 @@ impl ClientFrameCtx<'_> {
             dbg_logf!("handle_update f: {} gt: {:.03}", frame_num, game_time);
         }
 
-        for InputUpdate { index, net_input } in player_inputs {
+        for InputUpdate {
+            index,
+            net_input,
+            cur_weapon,
+            guided_missile,
+        } in player_inputs
+        {
+            let guided_missile =
+                guided_missile.and_then(|slot| self.gs.projectiles.slot_to_index(slot));
             let (_handle, player) = self.gs.players.get_by_slot_mut(index).unwrap();
             player.input_prev = player.input;
             player.input = net_input;
+            player.cur_weapon = cur_weapon;
+            player.guided_missile = guided_missile;
         }
 
         for VehicleUpdate {
@@ -490,15 +1021,48 @@ impl ClientFrameCtx<'_> {
                 },
             turret_angle_current,
             turret_angle_wanted,
+            turret_turn_rate,
+            wall_contacts,
+            scrape_speed,
+            weapon_ready_time,
+            fire_blocked_time,
+            boost_until,
+            boost_ready_time,
+            hp_fraction,
         } in vehicles
         {
-            let (_handle, vehicle) = self.gs.vehicles.get_by_slot_mut(index).unwrap();
+            let (handle, vehicle) = self.gs.vehicles.get_by_slot_mut(index).unwrap();
+            if self.cvars.cl_entity_smoothing {
+                let sample = Sample {
+                    pos,
+                    angle,
+                    time: self.gs.game_time,
+                };
+                smoothing::push_sample(self.cvars, &mut self.cg.vehicle_smoothing, handle, sample);
+            }
             vehicle.pos = pos;
             vehicle.vel = vel;
+            vehicle.prev_angle = vehicle.angle;
             vehicle.angle = angle;
             vehicle.turn_rate = turn_rate;
+            vehicle.prev_turret_angle = vehicle.turret_angle_current;
             vehicle.turret_angle_current = turret_angle_current;
             vehicle.turret_angle_wanted = turret_angle_wanted;
+            vehicle.turret_turn_rate = turret_turn_rate;
+            vehicle.wall_contacts = wall_contacts;
+            vehicle.scrape_speed = scrape_speed;
+            vehicle.weapon_ready_time = weapon_ready_time;
+            vehicle.fire_blocked_time = fire_blocked_time;
+            vehicle.boost_until = boost_until;
+            vehicle.boost_ready_time = boost_ready_time;
+            if self.cvars.cl_observer && hp_fraction < vehicle.hp_fraction {
+                let kind = ActionKind::Damage {
+                    victim: vehicle.owner,
+                    amount: vehicle.hp_fraction - hp_fraction,
+                };
+                self.cg.director.record_action(self.gs.game_time, kind);
+            }
+            vehicle.hp_fraction = hp_fraction;
         }
 
         for ProjectileUpdate {
@@ -512,64 +1076,453 @@ impl ClientFrameCtx<'_> {
                 },
         } in projectiles
         {
-            let (_handle, projectile) = self.gs.projectiles.get_by_slot_mut(index).unwrap();
+            let (handle, projectile) = self.gs.projectiles.get_by_slot_mut(index).unwrap();
+            if self.cvars.cl_entity_smoothing {
+                let sample = Sample {
+                    pos,
+                    angle,
+                    time: self.gs.game_time,
+                };
+                smoothing::push_sample(
+                    self.cvars,
+                    &mut self.cg.projectile_smoothing,
+                    handle,
+                    sample,
+                );
+            }
             projectile.pos = pos;
             projectile.vel = vel;
             projectile.angle = angle;
             projectile.turn_rate = turn_rate;
         }
 
-        DEBUG_TEXTS.with_borrow_mut(|texts| {
-            texts.extend(debug_texts);
-        });
-        DEBUG_TEXTS_WORLD.with_borrow_mut(|texts| {
-            texts.extend(debug_texts_world);
-        });
-        DEBUG_SHAPES.with_borrow_mut(|shapes| {
-            shapes.extend(debug_shapes);
-        });
+        for EmplacementUpdate {
+            index,
+            turret_angle_current,
+            turret_angle_wanted,
+            hp_fraction,
+            controller,
+        } in emplacements
+        {
+            let (_, emplacement) = self.gs.emplacements.get_by_slot_mut(index).unwrap();
+            emplacement.turret_angle_current = turret_angle_current;
+            emplacement.turret_angle_wanted = turret_angle_wanted;
+            emplacement.hp_fraction = hp_fraction;
+            emplacement.controller =
+                controller.map(|index| self.gs.players.slot_to_index(index).unwrap());
+        }
+
+        debug::extend_overlays(debug_texts, debug_texts_world, debug_shapes);
 
         self.cg.server_timings = server_timings;
     }
 
+    /// Mirror the server's `Projectile::target` and `Vehicle::hms`
+    /// so the lock warning and missile indicators work on clients too.
+    pub fn handle_hm_lock(&mut self, index: u32, target: Option<u32>) {
+        let hm_handle = self.gs.projectiles.slot_to_index(index).unwrap();
+        let hm = &mut self.gs.projectiles[hm_handle];
+        // The old target might be gone if its owner left.
+        if let Some(HmTarget::Vehicle(old_target)) = hm.target {
+            if let Some(vehicle) = self.gs.vehicles.get_mut(old_target) {
+                vehicle.hms.retain(|&h| h != hm_handle);
+            }
+        }
+        let target = target.map(|index| self.gs.vehicles.slot_to_index(index).unwrap());
+        hm.target = target.map(HmTarget::Vehicle);
+        if let Some(vehicle_handle) = target {
+            self.gs.vehicles[vehicle_handle].hms.push(hm_handle);
+        }
+    }
+
     pub fn handle_kill(&mut self, kill: Kill) {
-        let Kill { attacker, victim } = kill;
+        let Kill {
+            attacker,
+            victim,
+            assists,
+        } = kill;
 
         // LATER Check client and server scores are the same at the end of match
         // LATER Merge with DestroyVehicle?
 
-        let attacker_handle = self.gs.players.slot_to_index(attacker).unwrap();
+        let attacker_handle =
+            attacker.map(|attacker| self.gs.players.slot_to_index(attacker).unwrap());
         let victim_handle = self.gs.players.slot_to_index(victim).unwrap();
+        let victim_vehicle = self.gs.players[victim_handle].vehicle.unwrap();
+        let assist_handles: Vec<_> = assists
+            .into_iter()
+            .map(|assist| self.gs.players.slot_to_index(assist).unwrap())
+            .collect();
+
+        // Where it happened, for callouts.
+        let place = if self.cvars.hud_grid_label {
+            let pos = self.gs.vehicles[victim_vehicle].pos;
+            let label = self
+                .map
+                .world_pos_to_grid_label(self.cvars.hud_grid_label_cell_size, pos);
+            format!(" at {label}")
+        } else {
+            String::new()
+        };
 
-        let attacker = &mut self.gs.players[attacker_handle];
-        if victim_handle == self.cg.tmp_local_player_handle && attacker_handle != victim_handle {
-            self.cg.notifications.push(Notification::new(
-                format!("You were killed by {}", attacker.name),
-                self.cvars.hud_notifications_color_death,
-                self.gs.game_time,
-            ));
-        }
+        // Appended after the place so the helpers are credited in the feed too.
+        let assists_suffix = if assist_handles.is_empty() {
+            String::new()
+        } else {
+            let names: Vec<_> = assist_handles
+                .iter()
+                .map(|&handle| self.gs.players[handle].name.as_str())
+                .collect();
+            format!(" + assist: {}", names.join(", "))
+        };
 
-        let victim = &mut self.gs.players[victim_handle];
-        if attacker_handle == self.cg.tmp_local_player_handle {
-            if attacker_handle == victim_handle {
-                self.cg.notifications.push(Notification::new(
-                    "You committed suicide".to_owned(),
+        // Sinking gets its own wording, there was no explosion to show what happened.
+        let sunk = self.gs.vehicles[victim_vehicle].sinking.is_some();
+        let victim_pos = self.gs.vehicles[victim_vehicle].pos;
+        let outside_zone = self
+            .gs
+            .zone
+            .is_some_and(|zone| !zone.contains(self.gs.game_time, victim_pos));
+
+        // Each local player only hears about their own kills and deaths.
+        for view in &mut self.cg.views {
+            if victim_handle == view.player_handle && attacker_handle != Some(victim_handle) {
+                let text = match attacker_handle {
+                    Some(attacker_handle) => {
+                        let verb = if sunk { "sunk" } else { "killed" };
+                        format!(
+                            "You were {verb} by {}{place}",
+                            self.gs.players[attacker_handle].name
+                        )
+                    }
+                    None if sunk => format!("You sank{place}"),
+                    None if outside_zone => format!("You were caught outside the zone{place}"),
+                    None => format!("You were killed by a player who left{place}"),
+                };
+                view.notifications.push(Notification::new(
+                    text + &assists_suffix,
                     self.cvars.hud_notifications_color_death,
-                    self.gs.game_time,
+                    self.cg.real_time,
                 ));
-            } else {
-                self.cg.notifications.push(Notification::new(
-                    format!("You killed {}", victim.name),
+            }
+
+            if attacker_handle == Some(view.player_handle) {
+                if attacker_handle == Some(victim_handle) {
+                    let text = if sunk {
+                        format!("You sank yourself{place}")
+                    } else {
+                        format!("You committed suicide{place}")
+                    };
+                    view.notifications.push(Notification::new(
+                        text,
+                        self.cvars.hud_notifications_color_death,
+                        self.cg.real_time,
+                    ));
+                } else {
+                    view.notifications.push(Notification::new(
+                        format!(
+                            "You {} {}{place}{assists_suffix}",
+                            if sunk { "sank" } else { "killed" },
+                            self.gs.players[victim_handle].name
+                        ),
+                        self.cvars.hud_notifications_color_kill,
+                        self.cg.real_time,
+                    ));
+                }
+            }
+
+            if assist_handles.contains(&view.player_handle) {
+                view.notifications.push(Notification::new(
+                    format!("Assist on {}{place}", self.gs.players[victim_handle].name),
                     self.cvars.hud_notifications_color_kill,
-                    self.gs.game_time,
+                    self.cg.real_time,
                 ));
             }
         }
 
-        let vehicle = &mut self.gs.vehicles[victim.vehicle.unwrap()];
+        if self.cvars.cl_observer {
+            let kind = ActionKind::Kill {
+                attacker: attacker_handle,
+                victim: victim_handle,
+            };
+            self.cg.director.record_action(self.gs.game_time, kind);
+        }
+
+        let vehicle = &mut self.gs.vehicles[victim_vehicle];
         vehicle.hp_fraction = 0.0;
 
         self.update_score_kill(attacker_handle, victim_handle);
+        self.update_score_assists(&assist_handles);
+    }
+
+    fn handle_ai_chat(&mut self, chat: AiChat) {
+        let Some(player_handle) = self.gs.players.slot_to_index(chat.player) else {
+            return; // Removed in the meantime
+        };
+        let text = format!("{}: {}", self.gs.players[player_handle].name, chat.text);
+        for view in &mut self.cg.views {
+            view.notifications.push(Notification::new(
+                text.clone(),
+                self.cvars.hud_notifications_color_ai_chat,
+                self.cg.real_time,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    use crate::{
+        net::{self, LocalConnection},
+        possess, test_utils,
+    };
+
+    /// Send this frame's input to the server player the way the server does,
+    /// return whether up is held and next_weapon was pressed during the tick.
+    fn tick(
+        cg: &mut ClientGame,
+        gs: &mut GameState,
+        player_handle: Index,
+        keys: ClientInput,
+    ) -> (bool, bool) {
+        cg.set_input(keys, ClientInput::empty());
+        let net_input = cg.input1.merged(cg.input2).to_net_input();
+        possess::route_input(gs, player_handle, net_input);
+        let player = &mut gs.players[player_handle];
+        let pressed = !player.input_prev.next_weapon && player.input.next_weapon;
+        let up = player.input.up;
+        player.input_prev = player.input;
+        (up, pressed)
+    }
+
+    #[test]
+    fn test_add_explosion() {
+        let cvars = Cvars::default();
+        let (sender, _server_receiver) = mpsc::channel();
+        let (_server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut gs = GameState::new();
+        let player = Player::new("test".to_owned(), ClientType::Local);
+        let player_handle = gs.players.insert(player);
+        let mut cg = ClientGame::new(Box::new(conn), player_handle);
+
+        // Self-destruct - both at the same place and time.
+        cg.add_explosion(&cvars, v!(100 100), 3.0, 5.0, false, None);
+        cg.add_explosion(&cvars, v!(100 100), 1.0, 5.0, false, None);
+        cg.add_explosion(&cvars, v!(200 200), 1.0, 6.0, true, Some(1));
+        let seqs: Vec<_> = cg.explosions.iter().map(|e| e.item.seq).collect();
+        assert_eq!(seqs, [0, 1, 2]);
+
+        // Numbers keep going up after old ones are removed.
+        cg.explosions.clear();
+        cg.add_explosion(&cvars, v!(100 100), 1.0, 7.0, false, None);
+        assert_eq!(cg.explosions.iter().next().unwrap().item.seq, 3);
+    }
+
+    #[test]
+    fn test_focus_loss_releases_keys() {
+        let (sender, _server_receiver) = mpsc::channel();
+        let (_server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut gs = GameState::new();
+        let player = Player::new("test".to_owned(), ClientType::Local);
+        let player_handle = gs.players.insert(player);
+        let mut cg = ClientGame::new(Box::new(conn), player_handle);
+
+        let held = ClientInput {
+            up: true,
+            next_weapon: true,
+            ..ClientInput::empty()
+        };
+        assert_eq!(tick(&mut cg, &mut gs, player_handle, held), (true, true));
+        assert_eq!(tick(&mut cg, &mut gs, player_handle, held), (true, false));
+
+        // The key up never arrives so the keys still look held.
+        assert!(cg.update_focus(false));
+        assert!(!cg.update_focus(false));
+        for _ in 0..3 {
+            assert_eq!(tick(&mut cg, &mut gs, player_handle, held), (false, false));
+        }
+
+        // Still held after focus returns - exactly one new press.
+        assert!(!cg.update_focus(true));
+        assert_eq!(tick(&mut cg, &mut gs, player_handle, held), (true, true));
+        assert_eq!(tick(&mut cg, &mut gs, player_handle, held), (true, false));
+
+        let empty = ClientInput::empty();
+        assert_eq!(tick(&mut cg, &mut gs, player_handle, empty), (false, false));
+    }
+
+    /// The server ran 3 catch-up ticks after a hitch, each with an explosion,
+    /// and the client gets them all at once.
+    #[test]
+    fn test_catch_up_effects_aged() {
+        let cvars = Cvars::default();
+        let (sender, _server_receiver) = mpsc::channel();
+        let (server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut gs = GameState::new();
+        let player = Player::new("test".to_owned(), ClientType::Local);
+        let player_handle = gs.players.insert(player);
+        let mut cg = ClientGame::new(Box::new(conn), player_handle);
+        // The clocks don't need to match.
+        gs.game_time = 10.0;
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        for tick in 1..=3 {
+            let game_time = 5.0 + tick as f64 * dt;
+            let init = ExplosionInit {
+                pos: v!(100 100),
+                scale: 1.0,
+                bfg: false,
+                game_time,
+                team: None,
+            };
+            let msg = net::serialize(ServerMessage::SpawnExplosion(init));
+            server_sender.send(msg).unwrap();
+            let update = Update {
+                frame_num: tick,
+                game_time,
+                game_time_prev: game_time - dt,
+                dt,
+                player_inputs: Vec::new(),
+                vehicles: Vec::new(),
+                projectiles: Vec::new(),
+                emplacements: Vec::new(),
+                debug_texts: Vec::new(),
+                debug_texts_world: Vec::new(),
+                debug_shapes: Vec::new(),
+                server_timings: CommonTimings::default(),
+            };
+            server_sender
+                .send(net::serialize(ServerMessage::Update(update)))
+                .unwrap();
+        }
+
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+        ctx.sys_net_receive();
+
+        // The oldest is already 2 ticks into its animation, the newest just started.
+        assert_eq!(ctx.cg.explosions.len(), 3);
+        let alive = ctx.cg.explosions.iter_alive(ctx.gs.game_time);
+        for ((_, progress), ticks) in alive.zip([2.0, 1.0, 0.0]) {
+            let expected = ticks * dt / cvars.r_explosion_duration;
+            assert!(
+                (progress - expected).abs() < 1e-9,
+                "progress {progress}, expected {expected}"
+            );
+        }
+    }
+
+    /// An explosion is drawn for exactly `r_explosion_duration`,
+    /// its animation progressing with game time.
+    #[test]
+    fn test_explosion_lifetime() {
+        let cvars = Cvars {
+            r_explosion_duration: 0.5,
+            sys_tickrate_fixed_fps: 10.0,
+            ..Cvars::default()
+        };
+        let (sender, _server_receiver) = mpsc::channel();
+        let (server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut gs = GameState::new();
+        let player = Player::new("test".to_owned(), ClientType::Local);
+        let player_handle = gs.players.insert(player);
+        let mut cg = ClientGame::new(Box::new(conn), player_handle);
+        gs.game_time = 3.0;
+
+        let init = ExplosionInit {
+            pos: v!(100 100),
+            scale: 1.0,
+            bfg: false,
+            game_time: 0.0,
+            team: None,
+        };
+        let msg = net::serialize(ServerMessage::SpawnExplosion(init));
+        server_sender.send(msg).unwrap();
+
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let mut progresses = Vec::new();
+        for tick in 0..8 {
+            gs.game_time = 3.0 + tick as f64 * dt;
+            let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+            ctx.sys_cl_cleanup();
+            ctx.sys_net_receive();
+            let alive: Vec<_> = ctx.cg.explosions.iter_alive(ctx.gs.game_time).collect();
+            if let [(_, progress)] = alive[..] {
+                progresses.push((progress * 10.0).round() / 10.0);
+            }
+        }
+        assert_eq!(progresses, [0.0, 0.2, 0.4, 0.6, 0.8, 1.0]);
+        assert!(cg.explosions.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "debug_overlays")]
+    fn test_effect_clocks() {
+        use crate::debug::DEBUG_SHAPES;
+
+        // World effects age with game time, HUD effects with real time.
+        // With d_speed the two go at different rates.
+        let cvars = Cvars {
+            cl_railgun_trail_duration: 1.0,
+            hud_notifications_duration: 1.0,
+            r_explosion_duration: 1.0,
+            ..Cvars::default()
+        };
+        let map = test_utils::parse_map("0 0\n0 0\n");
+
+        // game time, real time, then whether beam, explosion, debug shape, notification remain
+        let cases = [
+            (0.5, 0.5, [true, true, true, true]),
+            // Slow motion
+            (0.9, 1.8, [true, true, true, false]),
+            // Fast forward
+            (1.2, 0.6, [false, false, false, true]),
+        ];
+        for (game_time, real_time, expected) in cases {
+            let (sender, _server_receiver) = mpsc::channel();
+            let (_server_sender, receiver) = mpsc::channel();
+            let conn = LocalConnection::new(sender, receiver);
+            let mut gs = GameState::new();
+            let player = Player::new("test".to_owned(), ClientType::Local);
+            let player_handle = gs.players.insert(player);
+            let mut cg = ClientGame::new(Box::new(conn), player_handle);
+
+            let beam = RailBeam::new(v!(0 0), v!(100 0), 0.0, None);
+            cg.rail_beams
+                .push(beam, 0.0, cvars.cl_railgun_trail_duration);
+            cg.add_explosion(&cvars, v!(100 100), 1.0, 0.0, false, None);
+            DEBUG_SHAPES.take();
+            debug::details::debug_cross(v!(100 100), 1.0, WHITE);
+            cg.views[0]
+                .notifications
+                .push(Notification::new("test".to_owned(), WHITE.into(), 0.0));
+
+            gs.game_time = game_time;
+            gs.dt = game_time;
+            cg.real_time = real_time;
+            debug::clear_expired(gs.dt);
+            let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+            ctx.sys_cl_cleanup();
+
+            let remaining = [
+                !cg.rail_beams.is_empty(),
+                !cg.explosions.is_empty(),
+                DEBUG_SHAPES.with_borrow(|shapes| !shapes.is_empty()),
+                !cg.views[0].notifications.is_empty(),
+            ];
+            assert_eq!(
+                remaining, expected,
+                "game time {game_time}, real time {real_time}"
+            );
+        }
     }
 }
@@ -5,16 +5,27 @@ impl FrameCtx<'_> {
     // They are not common code though, move them somewhere else.
 
     pub fn init_player(&mut self, init: PlayerInit) {
-        let PlayerInit { index, name, score } = init;
+        let PlayerInit {
+            index,
+            id,
+            name,
+            score,
+            color,
+            team,
+        } = init;
         let mut player = Player::new(name, ClientType::Local);
         player.score = score;
-        let (_player_handle, old) = self.gs.players.insert_at_slot(index, player);
+        player.color = color;
+        player.team = team;
+        let (player_handle, old) = self.gs.players.insert_at_slot(index, player);
         assert!(old.is_none());
+        self.gs.player_ids.insert(player_handle, id);
     }
 
     pub fn init_vehicle(&mut self, init: VehicleInit) {
         let VehicleInit {
             index,
+            id,
             physics:
                 EntityPhysics {
                     pos,
@@ -37,6 +48,7 @@ impl FrameCtx<'_> {
         vehicle.turret_angle_wanted = turret_angle_wanted;
 
         let (vehicle_handle, _old) = self.gs.vehicles.insert_at_slot(index, vehicle);
+        self.gs.vehicle_ids.insert(vehicle_handle, id);
 
         self.gs.players[owner].vehicle = Some(vehicle_handle);
     }
@@ -44,6 +56,7 @@ impl FrameCtx<'_> {
     pub fn init_projectile(&mut self, init: ProjectileInit) {
         let ProjectileInit {
             index,
+            id,
             weapon,
             physics:
                 EntityPhysics {
@@ -54,44 +67,194 @@ impl FrameCtx<'_> {
                 },
             explode_time,
             owner,
+            flight,
+            spawn_time: _,
+            team,
+            target,
         } = init;
 
-        let owner = self.gs.players.slot_to_index(owner).unwrap();
+        // The owner might have already left if g_orphan_projectiles is Keep.
+        let owner = self
+            .gs
+            .players
+            .slot_to_index(owner)
+            .unwrap_or(Index::DANGLING);
+        // Only locks onto vehicles are sent, for the lock warning.
+        let target = target
+            .and_then(|index| self.gs.vehicles.slot_to_index(index))
+            .map(HmTarget::Vehicle);
         let projectile = Projectile {
             weapon,
             pos,
             vel,
             angle,
             turn_rate,
+            // Client time, the server's is only used to age launch effects.
+            spawn_time: self.gs.game_time,
             explode_time,
             owner,
-            target: None, // LATER Simulate homing missiles on client too?
+            target, // LATER Simulate homing missiles on client too?
+            flight,
+            team,
+            seeker: Seeker::default(),
         };
-        let (_projectile_handle, old) = self.gs.projectiles.insert_at_slot(index, projectile);
+        let (projectile_handle, old) = self.gs.projectiles.insert_at_slot(index, projectile);
         soft_assert!(old.is_none());
+        self.gs.projectile_ids.insert(projectile_handle, id);
+        if let Some(HmTarget::Vehicle(vehicle_handle)) = target {
+            self.gs.vehicles[vehicle_handle].hms.push(projectile_handle);
+        }
     }
 
-    pub fn remove_player(&mut self, player_handle: Index) {
+    pub fn init_mine(&mut self, init: MineInit) {
+        let MineInit {
+            index,
+            pos,
+            spawn_time,
+            owner,
+            team,
+        } = init;
+
+        // The owner might have already left if g_orphan_projectiles is Keep.
+        let owner = self
+            .gs
+            .players
+            .slot_to_index(owner)
+            .unwrap_or(Index::DANGLING);
+        let mine = Mine::new(pos, spawn_time, owner, team);
+        let (_, old) = self.gs.mines.insert_at_slot(index, mine);
+        soft_assert!(old.is_none());
+    }
+
+    pub fn init_emplacement(&mut self, init: EmplacementInit) {
+        let EmplacementInit {
+            index,
+            pos,
+            angle,
+            weapon,
+            turret_angle_current,
+            hp_fraction,
+        } = init;
+
+        let mut emplacement = Emplacement::new(self.cvars, pos, angle, weapon);
+        emplacement.turret_angle_current = turret_angle_current;
+        emplacement.turret_angle_wanted = turret_angle_current;
+        emplacement.hp_fraction = hp_fraction;
+        let (_, old) = self.gs.emplacements.insert_at_slot(index, emplacement);
+        soft_assert!(old.is_none());
+    }
+
+    /// Look up the surface under each vehicle once so later systems can use the cached value.
+    pub fn sys_surface_under(&mut self) {
+        for (_, vehicle) in self.gs.vehicles.iter_mut() {
+            vehicle.surface = self.map.surface_under(vehicle.pos);
+        }
+    }
+
+    /// Remove smoke screens which have fully faded.
+    pub fn sys_smoke_cleanup(&mut self) {
+        let game_time = self.gs.game_time;
+        let duration = self.cvars.g_smoke_duration;
         self.gs
-            .projectiles
-            .retain(|_, proj| proj.owner != player_handle);
-        // LATER This ignores gs.rail_hits because we're gonna change that anyway.
-        self.gs.vehicles.retain(|_, veh| veh.owner != player_handle);
+            .smokes
+            .retain(|smoke| game_time - smoke.start_time < duration);
+    }
+
+    /// Remove flares which have burned out.
+    ///
+    /// Homing missiles targeting them notice they're gone in `sys_hm_turning`.
+    pub fn sys_flare_cleanup(&mut self) {
+        let game_time = self.gs.game_time;
+        let duration = self.cvars.g_flare_duration;
+        self.gs
+            .flares
+            .retain(|_, flare| game_time - flare.start_time < duration);
+    }
+
+    /// Remove the player and their vehicle.
+    ///
+    /// Their projectiles and mines are removed or kept according to `g_orphan_projectiles`.
+    /// Kept projectiles and mines still refer to the removed player as their owner
+    /// so code using `Projectile::owner` must not assume it exists.
+    pub fn remove_player(&mut self, player_handle: Index) {
+        if self.cvars.g_orphan_projectiles == OrphanProjectiles::Remove {
+            let removed: FnvHashSet<_> = self
+                .gs
+                .projectiles
+                .iter()
+                .filter(|(_, proj)| proj.owner == player_handle)
+                .map(|(handle, _)| handle)
+                .collect();
+            for &handle in &removed {
+                self.gs.projectiles.remove(handle);
+                self.gs.projectile_ids.remove(handle);
+                self.gs.rail_hits.remove(&handle);
+            }
+            // Other vehicles might be targeted by the player's homing missiles.
+            for (_, vehicle) in self.gs.vehicles.iter_mut() {
+                vehicle.hms.retain(|hm| !removed.contains(hm));
+            }
+
+            self.gs.mines.retain(|_, mine| mine.owner != player_handle);
+        }
+
+        if let Some(emplacement_handle) = self.gs.players[player_handle].emplacement {
+            if let Some(emplacement) = self.gs.emplacements.get_mut(emplacement_handle) {
+                emplacement.controller = None;
+                emplacement.input = NetInput::empty();
+            }
+        }
+
+        let vehicles: Vec<_> = self
+            .gs
+            .vehicles
+            .iter()
+            .filter(|(_, veh)| veh.owner == player_handle)
+            .map(|(handle, _)| handle)
+            .collect();
+        for handle in vehicles {
+            self.gs.vehicles.remove(handle);
+            self.gs.vehicle_ids.remove(handle);
+        }
+        // Homing missiles targeting the removed vehicle notice it's gone in sys_hm_turning.
+
         self.gs.players.remove(player_handle);
+        self.gs.player_ids.remove(player_handle);
     }
 
-    pub fn update_score_kill(&mut self, attacker_handle: Index, victim_handle: Index) {
-        let attacker = &mut self.gs.players[attacker_handle];
-        if attacker_handle == victim_handle {
-            attacker.score.suicides += 1;
-        } else {
-            attacker.score.kills += 1;
+    /// Update score after a kill.
+    ///
+    /// `attacker_handle` is None if the attacker has already left the game
+    /// or there never was one, the kill is credited to the world then.
+    pub fn update_score_kill(&mut self, attacker_handle: Option<Index>, victim_handle: Index) {
+        match attacker_handle {
+            Some(attacker_handle) => {
+                if let Some(attacker) = self.gs.players.soft_get_mut(attacker_handle) {
+                    if attacker_handle == victim_handle {
+                        attacker.score.suicides += 1;
+                    } else {
+                        attacker.score.kills += 1;
+                    }
+                }
+            }
+            None => self.gs.world_score.kills += 1,
         }
 
-        let victim = &mut self.gs.players[victim_handle];
+        let Some(victim) = self.gs.players.soft_get_mut(victim_handle) else {
+            return;
+        };
         victim.score.deaths += 1; // All deaths, including suicides
     }
 
+    /// Update score of everyone who helped destroy a vehicle without the last hit.
+    pub fn update_score_assists(&mut self, assist_handles: &[Index]) {
+        for &handle in assist_handles {
+            if let Some(player) = self.gs.players.soft_get_mut(handle) {
+                player.score.assists += 1;
+            }
+        }
+    }
+
     pub fn sys_debug_examples(&self, offset: Vec2f) {
         if !self.cvars.d_examples {
             return;
@@ -114,3 +277,133 @@ impl FrameCtx<'_> {
         dbg_rot!(v!(offset.x, offset.y + 175.0), 30.0_f64.to_radians());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn add_projectile(gs: &mut GameState, weapon: Weapon, owner: Index) -> Index {
+        gs.projectiles.insert(Projectile {
+            weapon,
+            pos: v!(100 100),
+            vel: v!(100 0),
+            angle: 0.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        })
+    }
+
+    /// Bot leaves with a rocket, a homing missile locked onto another vehicle and a guided missile in flight.
+    ///
+    /// All three are headed for the other vehicle.
+    fn remove_bot_mid_flight(
+        g_orphan_projectiles: OrphanProjectiles,
+    ) -> (Cvars, Server, [Index; 4]) {
+        let cvars = Cvars {
+            bots_max: 0,
+            g_orphan_projectiles,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::server(&cvars, test_utils::open_map(10, 10, ""));
+        let gs = &mut server.gs;
+
+        let (bot, _) = test_utils::add_tank(&cvars, gs, v!(100 100), 0.0);
+        let (_, other_vehicle) = test_utils::add_tank(&cvars, gs, v!(300 100), 0.0);
+
+        let rocket = add_projectile(gs, Weapon::Rockets, bot);
+        let hm = add_projectile(gs, Weapon::Hm, bot);
+        gs.projectiles[hm].target = Some(HmTarget::Vehicle(other_vehicle));
+        gs.vehicles[other_vehicle].hms.push(hm);
+        let gm = add_projectile(gs, Weapon::Gm, bot);
+        gs.players[bot].guided_missile = Some(gm);
+
+        server.ctx(&cvars).remove_player(bot);
+
+        (cvars, server, [rocket, hm, gm, other_vehicle])
+    }
+
+    #[test]
+    fn test_remove_player_orphans_removed() {
+        let (_, server, [rocket, hm, gm, other_vehicle]) =
+            remove_bot_mid_flight(OrphanProjectiles::Remove);
+        let gs = &server.gs;
+        assert_eq!(gs.players.len(), 1);
+        assert_eq!(gs.vehicles.len(), 1);
+        assert!(!gs.projectiles.contains(rocket));
+        assert!(!gs.projectiles.contains(hm));
+        assert!(!gs.projectiles.contains(gm));
+        assert!(gs.vehicles[other_vehicle].hms.is_empty());
+    }
+
+    #[test]
+    fn test_remove_player_orphans_kept() {
+        let (cvars, mut server, [rocket, hm, gm, other_vehicle]) =
+            remove_bot_mid_flight(OrphanProjectiles::Keep);
+        let gs = &server.gs;
+        assert_eq!(gs.players.len(), 1);
+        assert_eq!(gs.vehicles.len(), 1);
+        assert!(gs.projectiles.contains(rocket));
+        assert!(gs.projectiles.contains(hm));
+        assert!(gs.projectiles.contains(gm));
+        assert_eq!(gs.vehicles[other_vehicle].hms, vec![hm]);
+        let owner = gs.projectiles[rocket].owner;
+        assert!(!gs.players.contains(owner));
+
+        // They keep flying and the kill is credited to the world.
+        let victim = gs.vehicles[other_vehicle].owner;
+        server.gs.vehicles[other_vehicle].hp_fraction = 0.01;
+        for _ in 0..200 {
+            if server.gs.vehicles[other_vehicle].destroyed() {
+                break;
+            }
+            test_utils::tick(&mut server, &cvars);
+        }
+        let gs = &server.gs;
+        assert!(gs.vehicles[other_vehicle].destroyed());
+        assert!(gs.projectiles.len() < 3);
+        assert_eq!(gs.players[victim].score.deaths, 1);
+        assert_eq!(gs.players[victim].score.kills, 0);
+        assert_eq!(gs.world_score.kills, 1);
+    }
+
+    #[test]
+    fn test_init_projectile_lock() {
+        let cvars = Cvars::default();
+        let map = test_utils::load_map("maps/Atrium.map");
+        let mut gs = GameState::new();
+        let (shooter, _) = test_utils::add_tank(&cvars, &mut gs, v!(100 100), 0.0);
+        let (_, target) = test_utils::add_tank(&cvars, &mut gs, v!(300 100), 0.0);
+
+        let init = ProjectileInit {
+            index: 0,
+            id: 0,
+            weapon: Weapon::Hm,
+            physics: EntityPhysics {
+                pos: v!(150 100),
+                vel: v!(100 0),
+                angle: 0.0,
+                turn_rate: 0.0,
+            },
+            explode_time: f64::MAX,
+            owner: shooter.slot(),
+            flight: None,
+            spawn_time: 0.0,
+            team: None,
+            target: Some(target.slot()),
+        };
+        let mut ctx = FrameCtx::new(&cvars, &map, &mut gs);
+        ctx.init_projectile(init);
+
+        let (hm, projectile) = gs.projectiles.iter().next().unwrap();
+        assert_eq!(projectile.target, Some(HmTarget::Vehicle(target)));
+        assert_eq!(gs.vehicles[target].hms, vec![hm]);
+    }
+}
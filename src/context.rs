@@ -21,7 +21,7 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use crate::prelude::*;
+use crate::{debug, prelude::*};
 
 /// Context for running gamelogic common to client and server.
 #[repr(C)]
@@ -33,6 +33,7 @@ pub struct FrameCtx<'a> {
 
 impl<'a> FrameCtx<'a> {
     pub fn new(cvars: &'a Cvars, map: &'a Map, gs: &'a mut GameState) -> FrameCtx<'a> {
+        debug::set_strict(cvars.d_strict);
         FrameCtx { cvars, map, gs }
     }
 }
@@ -53,6 +54,7 @@ impl<'a> ClientFrameCtx<'a> {
         gs: &'a mut GameState,
         cg: &'a mut ClientGame,
     ) -> ClientFrameCtx<'a> {
+        debug::set_strict(cvars.d_strict);
         ClientFrameCtx { cvars, map, gs, cg }
     }
 }
@@ -73,6 +75,7 @@ impl<'a> ServerFrameCtx<'a> {
         gs: &'a mut GameState,
         sg: &'a mut ServerGame,
     ) -> ServerFrameCtx<'a> {
+        debug::set_strict(cvars.d_strict);
         ServerFrameCtx { cvars, map, gs, sg }
     }
 }
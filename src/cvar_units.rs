@@ -0,0 +1,229 @@
+//! Angle cvars as text in degrees or radians.
+//!
+//! Angles are always stored in radians so gamelogic doesn't convert anything.
+//! When set by name (console, command line, presets), the value can end with `deg` or `rad`,
+//! e.g. `g_turret_misalign_angle 90deg`. Without a suffix, `sys_angle_unit` decides.
+//!
+//! The cvars macro has no way to tag fields so the angles are listed in `ANGLE_CVARS` by hand.
+//! A test checks it against the naming conventions.
+//! Cvars ending with `_deg` are already in degrees and are not in the list.
+
+use cvars::SetGet;
+
+use crate::prelude::*;
+
+/// Cvars which hold an angle or angular speed / acceleration in radians.
+pub const ANGLE_CVARS: &[&str] = &[
+    "ai_hold_fire_angle",
+    "g_armor_arc_front",
+    "g_armor_arc_rear",
+    "g_emplacement_ai_fire_angle",
+    "g_flare_angle_spread",
+    "g_guided_missile_turn_rate_friction_const",
+    "g_guided_missile_turn_rate_increase",
+    "g_guided_missile_turn_rate_max",
+    "g_homing_missile_angle_detect",
+    "g_homing_missile_seeker_fov",
+    "g_homing_missile_turn_rate_friction_const",
+    "g_homing_missile_turn_rate_increase",
+    "g_homing_missile_turn_rate_max",
+    "g_hovercraft_pivot_turn_rate",
+    "g_hovercraft_turn_rate_friction_const",
+    "g_hovercraft_turn_rate_increase",
+    "g_hovercraft_turn_rate_max",
+    "g_hummer_pivot_turn_rate",
+    "g_hummer_turn_rate_friction_const",
+    "g_hummer_turn_rate_increase",
+    "g_hummer_turn_rate_max",
+    "g_machine_gun_angle_spread",
+    "g_tank_pivot_turn_rate",
+    "g_tank_turn_rate_friction_const",
+    "g_tank_turn_rate_increase",
+    "g_tank_turn_rate_max",
+    "g_turret_misalign_angle",
+    "r_launch_backblast_spread",
+];
+
+pub fn is_angle(cvar_name: &str) -> bool {
+    ANGLE_CVARS.contains(&cvar_name)
+}
+
+/// Parse an angle with an optional unit suffix into radians.
+pub fn parse_angle(default_unit: AngleUnit, str_value: &str) -> Result<f64, String> {
+    let str_value = str_value.trim();
+    let (number, unit) = if let Some(number) = str_value.strip_suffix("deg") {
+        (number, AngleUnit::Deg)
+    } else if let Some(number) = str_value.strip_suffix("rad") {
+        (number, AngleUnit::Rad)
+    } else {
+        (str_value, default_unit)
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid angle {str_value:?}: {e}"))?;
+    Ok(match unit {
+        AngleUnit::Rad => value,
+        AngleUnit::Deg => value.to_radians(),
+    })
+}
+
+impl Cvars {
+    /// Like `set_str` but angle cvars accept a unit, see the module docs.
+    pub fn set_str_units(&mut self, cvar_name: &str, str_value: &str) -> Result<(), String> {
+        self.set_str_default_unit(cvar_name, str_value, self.sys_angle_unit)
+    }
+
+    /// Like `set_str_units` but ignores `sys_angle_unit`
+    /// for text which always uses the same unit, e.g. presets.
+    pub fn set_str_default_unit(
+        &mut self,
+        cvar_name: &str,
+        str_value: &str,
+        default_unit: AngleUnit,
+    ) -> Result<(), String> {
+        if !is_angle(cvar_name) {
+            return self.set_str(cvar_name, str_value);
+        }
+        let radians = parse_angle(default_unit, str_value)?;
+        // Rust prints floats so they parse back to exactly the same value.
+        self.set_str(cvar_name, &radians.to_string())
+    }
+
+    /// Like `get_string` but angle cvars are shown in both units, preferred unit first.
+    pub fn get_string_units(&self, cvar_name: &str) -> Result<String, String> {
+        if !is_angle(cvar_name) {
+            return self.get_string(cvar_name);
+        }
+        let radians: f64 = self.get_string(cvar_name)?.parse().unwrap();
+        let degrees = radians.to_degrees();
+        Ok(match self.sys_angle_unit {
+            AngleUnit::Rad => format!("{radians}rad ({degrees}deg)"),
+            AngleUnit::Deg => format!("{degrees}deg ({radians}rad)"),
+        })
+    }
+}
+
+/// Gives the console (which only knows `SetGet`) access to units.
+pub struct WithUnits<'a>(pub &'a mut Cvars);
+
+impl SetGet for WithUnits<'_> {
+    fn get_string(&self, cvar_name: &str) -> Result<String, String> {
+        self.0.get_string_units(cvar_name)
+    }
+
+    fn set_str(&mut self, cvar_name: &str, str_value: &str) -> Result<(), String> {
+        self.0.set_str_units(cvar_name, str_value)
+    }
+
+    fn cvar_count(&self) -> usize {
+        self.0.cvar_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_angle_units() {
+        let mut cvars = Cvars::default();
+        cvars
+            .set_str_units("g_turret_misalign_angle", "90deg")
+            .unwrap();
+        assert!((cvars.g_turret_misalign_angle - PI / 2.0).abs() < 1e-12);
+        cvars
+            .set_str_units("g_turret_misalign_angle", "1rad")
+            .unwrap();
+        assert_eq!(cvars.g_turret_misalign_angle, 1.0);
+        cvars
+            .set_str_units("g_turret_misalign_angle", "0.5")
+            .unwrap();
+        assert_eq!(cvars.g_turret_misalign_angle, 0.5);
+
+        cvars.sys_angle_unit = AngleUnit::Deg;
+        cvars.set_str_units("g_tank_turn_rate_max", "180").unwrap();
+        assert!((cvars.g_tank_turn_rate_max - PI).abs() < 1e-12);
+        cvars.set_str_units("g_tank_turn_rate_max", "2rad").unwrap();
+        assert_eq!(cvars.g_tank_turn_rate_max, 2.0);
+        cvars.set_str_units("g_tank_turn_rate_max", "inf").unwrap();
+        assert_eq!(cvars.g_tank_turn_rate_max, f64::INFINITY);
+
+        // Presets ignore the preferred unit.
+        cvars
+            .set_str_default_unit("g_machine_gun_angle_spread", "0.02", AngleUnit::Rad)
+            .unwrap();
+        assert_eq!(cvars.g_machine_gun_angle_spread, 0.02);
+
+        // Not angles
+        assert!(cvars.set_str_units("g_armor", "90deg").is_err());
+        cvars.set_str_units("g_armor", "90").unwrap();
+        assert_eq!(cvars.g_armor, 90.0);
+        assert!(cvars
+            .set_str_units("g_turret_misalign_angle", "90 degrees")
+            .is_err());
+        assert!(cvars.set_str_units("g_nope", "90deg").is_err());
+    }
+
+    #[test]
+    fn test_angle_units_round_trip() {
+        for unit in [AngleUnit::Rad, AngleUnit::Deg] {
+            let mut cvars = Cvars {
+                sys_angle_unit: unit,
+                ..Cvars::default()
+            };
+            for &cvar_name in ANGLE_CVARS {
+                let before = cvars.get_string(cvar_name).unwrap();
+                let text = cvars.get_string_units(cvar_name).unwrap();
+                // The preferred unit comes first and can be typed back in.
+                let preferred = text.split(' ').next().unwrap();
+                cvars.set_str_units(cvar_name, preferred).unwrap();
+                let after: f64 = cvars.get_string(cvar_name).unwrap().parse().unwrap();
+                let before: f64 = before.parse().unwrap();
+                assert!(
+                    after == before || (after - before).abs() < 1e-12,
+                    "{cvar_name} {before} {text} {after}"
+                );
+            }
+        }
+
+        let cvars = Cvars {
+            g_turret_misalign_angle: PI,
+            sys_angle_unit: AngleUnit::Deg,
+            ..Cvars::default()
+        };
+        let text = cvars.get_string_units("g_turret_misalign_angle").unwrap();
+        assert_eq!(text, format!("180deg ({PI}rad)"));
+        assert_eq!(cvars.get_string_units("g_armor").unwrap(), "50");
+    }
+
+    /// Every f64 cvar which sounds like an angle in radians should be in the list and nothing else.
+    #[test]
+    fn test_angle_cvars_naming() {
+        let source = include_str!("cvars.rs");
+        let mut expected = Vec::new();
+        for line in source.lines() {
+            let Some((name, _)) = line.trim().split_once(": f64 =") else {
+                continue;
+            };
+            let sounds_like_angle = name.contains("_angle")
+                || name.contains("_turn_rate")
+                || name.contains("_arc_front")
+                || name.contains("_arc_rear")
+                || name.ends_with("_angle_spread")
+                || name.ends_with("_fov")
+                || name.ends_with("_backblast_spread");
+            let not_angle = name.ends_with("_deg") || name.ends_with("_friction_linear");
+            if sounds_like_angle && !not_angle {
+                expected.push(name);
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(ANGLE_CVARS, expected);
+
+        let cvars = Cvars::default();
+        for &cvar_name in ANGLE_CVARS {
+            assert!(cvars.get_string(cvar_name).is_ok(), "{cvar_name}");
+        }
+    }
+}
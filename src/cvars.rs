@@ -6,7 +6,10 @@ use cvars::cvars;
 use macroquad::prelude::Color;
 use strum_macros::{Display, EnumString};
 
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    weapons::{WeaponOrder, WeaponSet},
+};
 
 cvars! {
     #![cvars(sorted)]
@@ -19,11 +22,60 @@ cvars! {
     //! g_      gameplay (some of it runs only on the server but this can change with better clientside prediction)
     //! hud_    heads-up display
     //! r_      rendering
+    //! snd_    sound
     //! sv_     server administration + performance (not gameplay even if it only runs on the server)
     //! sys_    low level / "engine"
 
     /// Master switch for AI - disable if you want stationary targets
     ai: bool = true,
+    /// Bots boost when dodging a projectile
+    ai_boost: bool = false,
+    /// Difficulty of new bots from 0 (harmless) to 1
+    ai_difficulty: f64 = 0.5,
+    /// How many points a bot has to be ahead of or behind the best human to change its difficulty
+    ai_difficulty_gap: i32 = 5,
+    /// How much the gap has to shrink before the bot is no longer considered ahead or behind
+    ai_difficulty_hysteresis: i32 = 2,
+    /// How often (in seconds) difficulty is reevaluated
+    ai_difficulty_interval: f64 = 10.0,
+    /// Dynamic difficulty never goes above this
+    ai_difficulty_max: f64 = 1.0,
+    /// Dynamic difficulty never goes below this
+    ai_difficulty_min: f64 = 0.0,
+    /// How much difficulty changes per evaluation
+    ai_difficulty_step: f64 = 0.1,
+    /// Bots get out of the way of incoming projectiles
+    ai_dodge: bool = true,
+    /// Projectiles whose path passes closer than this are a threat
+    ai_dodge_danger_radius: f64 = 40.0,
+    /// Bots below this difficulty never dodge
+    ai_dodge_difficulty_min: f64 = 0.25,
+    /// How long to keep dodging after the last threat was seen
+    ai_dodge_duration: f64 = 0.5,
+    /// How far ahead (in seconds) to predict projectile paths
+    ai_dodge_lookahead: f64 = 1.0,
+    /// Reaction time of the weakest bots, scales down to 0 at difficulty 1
+    ai_dodge_reaction_time_max: f64 = 0.5,
+    /// Adjust each bot's difficulty based on its score relative to the best human
+    ai_dynamic_difficulty: bool = false,
+    /// Bots pop flares when a homing missile locks onto them, after their reaction time
+    ai_flares: bool = true,
+    /// Milliseconds per frame for expensive AI decisions, bots take turns when it runs out, see `ai_schedule`
+    ai_frame_budget_ms: f64 = f64::INFINITY,
+    /// Bots don't shoot when a teammate is within this angle of where their turret points
+    ai_hold_fire_angle: f64 = 10.0f64.to_radians(),
+    /// Each bot makes expensive decisions at least this often (in seconds) regardless of `ai_frame_budget_ms`
+    ai_max_decision_interval: f64 = 0.25,
+    /// Relative chance of new bots to hunt enemies, see `ai_role`
+    ai_role_attacker_weight: f64 = 2.0,
+    /// Relative chance of new bots to go for the objective in objective modes, see `ai_role`
+    ai_role_objective_weight: f64 = 1.0,
+    /// Bots steer away from teammates closer than this so they don't drive through each other
+    ai_separation_radius: f64 = 80.0,
+    /// How much closer (in pixels) a guider revealed by `g_gm_reveal` seems when bots pick a target
+    ai_target_revealed_bonus: f64 = 500.0,
+    /// After switching, bots keep their weapon for this many times `g_weapon_switch_time`
+    ai_weapon_switch_hold: f64 = 4.0,
 
     /// Final override for the max number of bots
     bots_max: usize = 20,
@@ -32,11 +84,48 @@ cvars! {
     /// Desired number of bots based on the number of tiles (map size)
     bots_tiles_per_bot: f32 = 100.0,
 
+    /// Record a burst of downscaled frames into memory, they're saved when it ends, see `capture`
+    cl_capture: bool = false,
+    /// Hard cap on memory used by captured frames, the oldest are dropped
+    cl_capture_bytes_max: usize = 256 * 1024 * 1024,
+    /// Capture one frame out of this many rendered frames
+    cl_capture_every: usize = 2,
+    /// How many frames to capture in one burst
+    cl_capture_frames: usize = 150,
+    cl_capture_path: String = "captures/{date_time}/f{index}-gt{game_time}.tga".to_owned(),
+    /// Integer downscale factor of captured frames
+    cl_capture_scale: usize = 2,
+
     cl_cluster_bomb_size: f64 = 1.5,
 
+    /// Projectiles hitting walls leave bullet chips and scorch marks, see `decals`
+    cl_decals: bool = true,
+    cl_decals_bullet_duration: f64 = 10.0,
+    /// Most new bullet chips per frame so a stream of MG fire doesn't flood the buffer
+    cl_decals_bullets_per_frame: usize = 2,
+    /// Skip a new decal when one of the same kind is closer than this
+    cl_decals_dedup_radius: f64 = 3.0,
+    /// Max decals at once, the oldest disappear first whatever their kind
+    cl_decals_max: usize = 300,
+    /// How far decals are pulled back out of the wall along the projectile's direction
+    cl_decals_offset: f64 = 3.0,
+    cl_decals_scorch_duration: f64 = 30.0,
+
+    /// Smooth out vehicles and projectiles between authoritative updates, see `smoothing`
+    cl_entity_smoothing: bool = false,
+
+    /// Keys held without any key events for this long mean the window lost focus, see `FocusDetector`.
+    /// Needs OS key repeat, 0 disables.
+    cl_focus_loss_timeout: f64 = 2.0,
+
     cl_machine_gun_trail_length: f64 = 10.0,
     cl_machine_gun_trail_thickness: f64 = 1.5,
 
+    /// Seconds between writes to cl_metrics_path
+    cl_metrics_interval: f64 = 10.0,
+    /// Write Prometheus metrics to this file periodically, empty to disable
+    cl_metrics_path: String = "".to_owned(),
+
     cl_name1: String = "Player 1".to_owned(),
     cl_name2: String = "Player 2".to_owned(),
 
@@ -44,14 +133,63 @@ cvars! {
     cl_net_connect_retry_print_every_n: u32 = 100,
     cl_net_server_addr: String = "127.0.0.1:26000".to_owned(),
 
-    cl_railgun_trail_duration: f64 = 0.05,
+    /// Exhibition mode for bot matches - the camera follows the action
+    /// and a sidebar shows everyone's stats
+    cl_observer: bool = false,
+    /// How much taking a full HP of damage counts as action
+    cl_observer_damage_weight: f64 = 2.0,
+    /// Follow this player (index as in d_possess) and pause automatic switching
+    /// for cl_observer_override_time, -1 for automatic
+    cl_observer_follow: i32 = -1,
+    /// How much a kill counts as action
+    cl_observer_kill_weight: f64 = 1.0,
+    cl_observer_override_time: f64 = 30.0,
+    /// Minimum time between automatic camera switches
+    cl_observer_switch_interval: f64 = 5.0,
+    /// Only actions this recent count
+    cl_observer_window: f64 = 8.0,
+
+    /// Cosmetic particles start getting scaled down once the particle buffer is this full
+    cl_particles_cosmetic_fraction: f64 = 0.75,
+    /// Max particles alive at once, gameplay particles replace cosmetic ones beyond this
+    cl_particles_max: usize = 2000,
+
+    /// Pause when the window loses focus
+    cl_pause_on_focus_loss: bool = false,
+
+    /// Warn the player when update and draw together are too slow for too long
+    cl_perf_warning: bool = true,
+    /// The frame budget is 1 / this
+    cl_perf_warning_fps: f64 = 60.0,
+    /// The warning clears after the average gets under this fraction of the budget
+    cl_perf_warning_hysteresis: f64 = 0.8,
+    /// How long things have to stay fast to clear the warning
+    cl_perf_warning_recover_time: f64 = 3.0,
+    /// How long things have to stay slow to show the warning
+    cl_perf_warning_time: f64 = 2.0,
+
+    /// Railgun trails fade out over this long instead of disappearing at once
+    cl_railgun_trail_duration: f64 = 0.5,
     cl_railgun_trail_thickness: f64 = 1.5,
+    /// Most railgun trails shown at once, the oldest disappear first
+    cl_railgun_trails_max: usize = 100,
+
+    /// Sparks where vehicles scrape along walls
+    cl_scrape_sparks: bool = false,
+    cl_scrape_sparks_duration: f64 = 0.25,
+    /// Sparks per second for each corner touching a wall
+    cl_scrape_sparks_rate: f64 = 30.0,
+    cl_scrape_sparks_speed: f64 = 120.0,
+    /// Slower contacts (e.g. just bumping into a wall) don't scrape
+    cl_scrape_speed_min: f64 = 40.0,
 
     cl_screenshot_path: String = "screenshots/{date_time}--f{frame_num}-gt{game_time}.tga".to_owned(),
     cl_screenshots: bool = false,
 
     cl_spawn_indicator_animation_time: f64 = 0.3,
     cl_spawn_indicator_blinking_period: f64 = 0.3,
+    /// Lines and square pointing at your vehicle after spawning
+    cl_spawn_indicator_color: CVec3 = CVec3::new(0.0, 0.89, 0.19),
     cl_spawn_indicator_duration: f64 = 1.5,
     cl_spawn_indicator_square_side_begin: f32 = 800.0,
     cl_spawn_indicator_square_side_end: f32 = 40.0,
@@ -60,6 +198,16 @@ cvars! {
     /// Two player local multiplayer
     cl_splitscreen: bool = false,
 
+    /// Tire / track marks, dust and spray depending on the surface
+    cl_surface_effects: bool = true,
+    /// How often a moving vehicle leaves a mark or a puff
+    cl_surface_effects_interval: f64 = 0.05,
+    cl_surface_effects_marks_duration: f64 = 5.0,
+    /// Most surface effects at once at high `r_effects_quality`, the oldest disappear first
+    cl_surface_effects_max: usize = 1000,
+    cl_surface_effects_particles_duration: f64 = 0.6,
+    cl_surface_effects_speed_min: f64 = 20.0,
+
     con_background_alpha: f32 = 0.8,
     con_height_fraction: f32 = 0.45,
     con_history_line_font_size: f32 = 16.0,
@@ -71,9 +219,25 @@ cvars! {
     con_prompt_label_x: f32 = 8.0,
     con_prompt_label_y_offset: f32 = 22.0,
 
+    /// Bots announce changes of their high-level state as notifications
+    d_ai_chat: bool = false,
+    /// Minimum time between announcements of one bot
+    d_ai_chat_interval: f64 = 3.0,
+    /// Only announce this player's state (index as in d_possess), -1 for all bots
+    d_ai_chat_player: i32 = -1,
+    /// Print bot difficulty changes
+    d_ai_difficulty: bool = false,
+    /// Draw a line from each bot to the enemy it's after
+    d_ai_targets: bool = false,
+    /// Print engine sound parameters of audible vehicles
+    d_audio: bool = false,
+    /// Allow the `debug_spawn` commands which spawn, remove and move entities at will
+    d_cheats: bool = false,
     /// Master switch for debug output - the d_draw_* group.
     d_draw: bool = true,
     d_draw_arrows: bool = true,
+    /// Where vehicles hit walls and the walls' normals
+    d_draw_contacts: bool = false,
     d_draw_crosses: bool = true,
     d_draw_crosses_half_len: f64 = 5.0,
     /// Sometimes useful if you have trouble finding the crosses.
@@ -84,10 +248,16 @@ cvars! {
     d_draw_lines: bool = true,
     /// This sometimes makes it easier to see the lines if they're very short.
     d_draw_lines_ends_half_length: f64 = 5.0,
+    /// Tile grid, surface kinds, spawns and markers over the map for debugging maps
+    d_draw_map_overlay: bool = false,
+    /// Only label tiles with their coords and surface when at most this many are visible, text is slow
+    d_draw_map_overlay_labels_max: usize = 500,
     d_draw_perf_client: bool = true,
     d_draw_perf_server: bool = true,
     d_draw_rots: bool = true,
     d_draw_rots_size: f64 = 16.0,
+    /// Debug and perf text
+    d_draw_text_color: CVec3 = CVec3::new(0.9, 0.16, 0.22),
     d_draw_text_shadow_alpha: f32 = 0.7,
     d_draw_texts: bool = true,
     d_draw_texts_line_height: f64 = 14.0,
@@ -102,10 +272,16 @@ cvars! {
     d_fps_period: f64 = 1.0,
     d_fps_x: f64 = -350.0,
     d_fps_y: f64 = -15.0,
+    /// How far back each press of the rewind key goes, see `history`.
+    d_history_rewind_seconds: f64 = 0.5,
+    /// How much history to keep for rewinding, needs the `history` feature.
+    d_history_seconds: f64 = 5.0,
     /// Display the last pressed key. Useful for debugging MQ's issues with keyboard layouts.
     d_last_key: bool = false,
     d_log_kills: bool = true,
     d_log_updates_cl: bool = false,
+    /// Index of a bot to control instead of your own player, -1 to go back. For debugging AI.
+    d_possess: i32 = -1,
     d_projectiles: bool = false,
     /// The seed to initialize the RNG.
     ///
@@ -118,6 +294,11 @@ cvars! {
     d_seed: u64 = 0,
     /// Change speed of everything in the game
     d_speed: f64 = 1.0,
+    /// Panic when an entity handle is stale instead of logging the error and skipping the entity.
+    ///
+    /// On by default in debug builds to surface bugs early,
+    /// off in release builds so players don't crash.
+    d_strict: bool = cfg!(debug_assertions),
     d_tickrate_fixed_carry: bool = false,
     d_timing_samples: usize = 60,
     d_tracing: bool = false,
@@ -130,12 +311,34 @@ cvars! {
     /// Same as dbg but for ints.
     dbgi: i32 = 0,
 
+    /// Lobbed projectiles on the way down can hit walls and vehicles again below this height.
+    ///
+    /// The arc's apex is set per weapon (g_*_arc), 0 means it's fired in a straight line.
+    g_airborne_height: f64 = 0.1,
+
+    /// Random wrecks scattered around at the start of the match in addition to those placed by the map
+    g_ambient_wrecks: u32 = 0,
+
     /// Hit points. Recommended values are between 1 and 500, original RecWar used 100 as default.
     ///
     /// Note that the actual number of hitpoints depends on vehicle type, this is just the base value.
     /// By default, the tank uses this value, other vehicles scale it by some multiplier.
     g_armor: f64 = 50.0,
-
+    /// Hits coming from within this angle of the vehicle's facing use g_*_armor_front
+    g_armor_arc_front: f64 = 45.0f64.to_radians(),
+    /// Hits coming from within this angle of the vehicle's back use g_*_armor_rear, the rest is side
+    g_armor_arc_rear: f64 = 45.0f64.to_radians(),
+
+    /// Max attackers remembered per vehicle for assists, the smallest are merged when it's full
+    g_assist_contributors_max: usize = 8,
+    /// Damage a player has to deal to get an assist when someone else destroys the vehicle
+    g_assist_min_damage: f64 = 20.0,
+    /// Damage only counts towards an assist if the attacker hit again within this many seconds before the kill
+    g_assist_window: f64 = 10.0,
+
+    g_bfg_altfire: AltFire = AltFire::None,
+    g_bfg_arc: f64 = 0.0,
+    g_bfg_arc_time: f64 = 1.5,
     g_bfg_beam_damage_per_sec: f64 = 25.0,
     g_bfg_beam_range: f64 = 125.0,
     g_bfg_damage_direct: f64 = 0.0,
@@ -148,8 +351,25 @@ cvars! {
     g_bfg_speed: f64 = 150.0,
     g_bfg_vehicle_velocity_factor: f64 = 1.0,
 
+    /// How long the boost can't be used again after it runs out
+    g_boost_cooldown: f64 = 8.0,
+    /// How long one boost lasts
+    g_boost_duration: f64 = 2.0,
+    /// Multiplier for acceleration and max speed while boosting
+    g_boost_factor: f64 = 1.5,
+    /// Multiplier for how well velocity follows the hull when turning while boosting,
+    /// less than 1 makes it slide in corners
+    g_boost_turn_effectiveness: f64 = 0.8,
+
+    g_cluster_bomb_altfire: AltFire = AltFire::None,
+    g_cluster_bomb_arc: f64 = 1.0,
+    /// How long until the bomblets land, 0 means when their fuse runs out.
+    g_cluster_bomb_arc_time: f64 = 0.0,
     g_cluster_bomb_count: i32 = 40,
     g_cluster_bomb_damage_direct: f64 = 0.0, // best guess - same as rockets
+    /// Cluster bombs younger than this ignore the DetonateCb alt-fire
+    /// so they don't go off in the shooter's face
+    g_cluster_bomb_detonate_min_age: f64 = 0.1,
     g_cluster_bomb_explosion_damage: f64 = 25.0,
     g_cluster_bomb_explosion_radius: f64 = 20.0,
     g_cluster_bomb_explosion_scale: f64 = 0.5,
@@ -166,10 +386,73 @@ cvars! {
     g_cluster_bomb_time_spread: f64 = 0.2,
     g_cluster_bomb_vehicle_velocity_factor: f64 = 1.0,
 
+    /// Vehicles below this fraction of their HP are crippled - slower and worse at turning.
+    ///
+    /// Disabled while g_cripple_mobility_factor and g_cripple_turn_factor are both 1.
+    g_cripple_hp_threshold: f64 = 0.25,
+    /// Multiplier for acceleration and max speed of crippled vehicles.
+    g_cripple_mobility_factor: f64 = 1.0,
+    /// Multiplier for turn rate of crippled vehicles.
+    g_cripple_turn_factor: f64 = 1.0,
+
+    /// How far from the center of a base tile the cow counts as captured
+    g_ctc_base_radius: f64 = 64.0,
+
+    /// Unmanned emplacements fire when their target is within this angle of the barrel
+    g_emplacement_ai_fire_angle: f64 = 5.0f64.to_radians(),
+    /// Unmanned emplacements only go after vehicles closer than this
+    g_emplacement_ai_range: f64 = 400.0,
+    /// How far from the emplacement's center shots start,
+    /// keep it above `g_emplacement_radius` so unmanned emplacements don't hit themselves
+    g_emplacement_barrel_length: f64 = 20.0,
+    g_emplacement_hp: f64 = 150.0,
+    /// How close a vehicle has to be to take the controls, it's let go when it gets further
+    g_emplacement_interact_range: f64 = 50.0,
+    /// Projectiles hit emplacements closer than this to their center
+    g_emplacement_radius: f64 = 12.0,
+    /// Spawn the gun emplacements placed by the map
+    g_emplacements: bool = true,
+
+    /// Fraction of a weapon or mine explosion's damage dealt at the edge of its radius,
+    /// falls off linearly from full damage at the center, 1 means no falloff.
+    /// Self destructs use `g_self_destruct_damage_edge` instead.
+    g_explosion_damage_edge: f64 = 0.0,
+    /// Speed given to vehicles at the center of an explosion, falls off to 0 at the edge of its radius
+    g_explosion_knockback: f64 = 0.0,
+    /// Walls shield vehicles and mines from explosions behind them
+    g_explosion_los: bool = false,
+
+    g_ffa_score_assist: i32 = 0,
     g_ffa_score_death: i32 = -1,
     g_ffa_score_kill: i32 = 1,
 
+    /// Only keep a buffered fire press while fire is still held, see `g_fire_buffer_window`
+    g_fire_buffer_require_held: bool = false,
+    /// Pressing fire this long or less before the weapon is ready fires as soon as it is
+    g_fire_buffer_window: f64 = 0.2,
+
+    /// Spread of the flares in one deployment around straight back
+    g_flare_angle_spread: f64 = 40.0f64.to_radians(),
+    /// Flares per deployment
+    g_flare_burst: usize = 3,
+    g_flare_cooldown: f64 = 1.0,
+    /// Deployments per life
+    g_flare_count: u32 = 3,
+    /// How long flares exist, they only fool missiles during `g_flare_spoof_duration`
+    g_flare_duration: f64 = 2.0,
+    /// How far behind the vehicle's center.
+    g_flare_offset: f64 = 20.0,
+    g_flare_speed: f64 = 80.0,
+    /// Homing missiles prefer flares in their seeker cone over vehicles for this long after deployment
+    g_flare_spoof_duration: f64 = 1.0,
+
+    /// Players guiding a missile are shown to enemies within `g_gm_reveal_range`
+    /// and bots prefer them as targets so guiding isn't free
+    g_gm_reveal: bool = true,
+    g_gm_reveal_range: f64 = 800.0,
+
     g_guided_missile_accel_forward: f64 = 2000.0,
+    g_guided_missile_altfire: AltFire = AltFire::None,
     g_guided_missile_damage_direct: f64 = 0.0,
     g_guided_missile_explosion_damage: f64 = 56.0, // exact from orig RW
     g_guided_missile_explosion_radius: f64 = 40.0,
@@ -208,6 +491,9 @@ cvars! {
     g_hardpoint_hovercraft_railgun_x: f64 = 19.0,
     g_hardpoint_hovercraft_railgun_y: f64 = 0.0,
     g_hardpoint_hovercraft_rockets: Hardpoint = Hardpoint::Turret,
+    /// Second rocket launcher for bursts (g_rockets_burst_count), mirrors the first one by default
+    g_hardpoint_hovercraft_rockets_alt_x: f64 = 19.0,
+    g_hardpoint_hovercraft_rockets_alt_y: f64 = -0.0,
     g_hardpoint_hovercraft_rockets_x: f64 = 19.0,
     g_hardpoint_hovercraft_rockets_y: f64 = 0.0,
 
@@ -230,6 +516,8 @@ cvars! {
     g_hardpoint_hummer_railgun_x: f64 = 10.0,
     g_hardpoint_hummer_railgun_y: f64 = 9.0,
     g_hardpoint_hummer_rockets: Hardpoint = Hardpoint::Turret,
+    g_hardpoint_hummer_rockets_alt_x: f64 = 0.0,
+    g_hardpoint_hummer_rockets_alt_y: f64 = -0.0,
     g_hardpoint_hummer_rockets_x: f64 = 0.0,
     g_hardpoint_hummer_rockets_y: f64 = 0.0,
 
@@ -252,22 +540,36 @@ cvars! {
     g_hardpoint_tank_railgun_x: f64 = 35.0,
     g_hardpoint_tank_railgun_y: f64 = 0.0,
     g_hardpoint_tank_rockets: Hardpoint = Hardpoint::Turret,
+    g_hardpoint_tank_rockets_alt_x: f64 = 35.0,
+    g_hardpoint_tank_rockets_alt_y: f64 = -0.0,
     g_hardpoint_tank_rockets_x: f64 = 35.0,
     g_hardpoint_tank_rockets_y: f64 = 0.0,
 
     g_hitcircle_radius: f64 = 24.0, // LATER proper hitbox
 
     g_homing_missile_accel_forward: f64 = 2000.0,
+    g_homing_missile_altfire: AltFire = AltFire::None,
+    /// Acquiring a new target also needs it within this angle of the missile's heading,
+    /// tracking only needs it within `g_homing_missile_seeker_fov`.
     g_homing_missile_angle_detect: f64 = 40.0f64.to_radians(), // LATER (also other places) use Deg
-    g_homing_missile_angle_forget: f64 = 50.0f64.to_radians(),
     g_homing_missile_damage_direct: f64 = 0.0,
     g_homing_missile_explosion_damage: f64 = 56.0, // assumed same as GM
     g_homing_missile_explosion_radius: f64 = 40.0,
     g_homing_missile_explosion_scale: f64 = 1.0,
     g_homing_missile_friction_const: f64 = 0.0,
     g_homing_missile_friction_linear: f64 = 0.99,
+    /// What a missile does when `g_homing_missile_search_time` runs out without a new target
+    g_homing_missile_lost: HmLost = HmLost::Detonate,
+    /// How long a wall can block the view of the target before the seeker loses it
+    g_homing_missile_occlusion_grace: f64 = f64::INFINITY,
     g_homing_missile_reload_ammo: u32 = 1,
     g_homing_missile_reload_time: f64 = 1.5,
+    /// How long a missile which lost its target flies straight looking for a new one
+    g_homing_missile_search_time: f64 = f64::INFINITY,
+    /// Full width of the cone in front of the missile where the seeker can track targets
+    g_homing_missile_seeker_fov: f64 = 100.0f64.to_radians(),
+    /// Targets further away can't be acquired and tracked ones are lost
+    g_homing_missile_seeker_range: f64 = f64::INFINITY,
     g_homing_missile_speed_initial: f64 = 100.0,
     g_homing_missile_speed_max: f64 = f64::INFINITY,
     g_homing_missile_turn_effectiveness: f64 = 1.0,
@@ -279,13 +581,18 @@ cvars! {
 
     g_hovercraft_accel_backward: f64 = 400.0,
     g_hovercraft_accel_forward: f64 = 400.0,
+    g_hovercraft_armor_front: f64 = 1.0,
+    g_hovercraft_armor_rear: f64 = 1.0,
     g_hovercraft_armor_scale: f64 = 0.65,
+    g_hovercraft_armor_side: f64 = 1.0,
     g_hovercraft_friction_const: f64 = 0.0,
     g_hovercraft_friction_linear: f64 = 0.6,
     g_hovercraft_maxs_x: f64 = 22.0,
     g_hovercraft_maxs_y: f64 = 14.0,
     g_hovercraft_mins_x: f64 = -22.0,
     g_hovercraft_mins_y: f64 = -14.0,
+    g_hovercraft_pivot_turn_rate: f64 = 0.0,
+    g_hovercraft_self_destruct: SelfDestruct = SelfDestruct::Submunitions,
     g_hovercraft_speed_max: f64 = f64::INFINITY,
     g_hovercraft_steering_car: f64 = 0.0,
     g_hovercraft_turn_effectiveness: f64 = 0.0,
@@ -297,16 +604,26 @@ cvars! {
     g_hovercraft_turret_offset_chassis_y: f64 = 5.0,
     g_hovercraft_turret_offset_turret_x: f64 = -8.0,
     g_hovercraft_turret_offset_turret_y: f64 = 0.0,
+    g_hovercraft_turret_slaved: TurretSlaved = TurretSlaved::Free,
+    /// Sinks and dies when its center ends up on water, e.g. pushed there by `g_explosion_knockback`
+    g_hovercraft_water_lethal: bool = false,
+    /// Weapons the hovercraft can use, `all` or a comma-separated list
+    g_hovercraft_weapons: WeaponSet = WeaponSet::default(),
 
     g_hummer_accel_backward: f64 = 600.0,
     g_hummer_accel_forward: f64 = 600.0,
+    g_hummer_armor_front: f64 = 1.0,
+    g_hummer_armor_rear: f64 = 1.0,
     g_hummer_armor_scale: f64 = 0.625,
+    g_hummer_armor_side: f64 = 1.0,
     g_hummer_friction_const: f64 = 11.0,
     g_hummer_friction_linear: f64 = 0.8,
     g_hummer_maxs_x: f64 = 20.0,
     g_hummer_maxs_y: f64 = 9.0,
     g_hummer_mins_x: f64 = -20.0,
     g_hummer_mins_y: f64 = -9.0,
+    g_hummer_pivot_turn_rate: f64 = 0.0,
+    g_hummer_self_destruct: SelfDestruct = SelfDestruct::Rockets,
     g_hummer_speed_max: f64 = f64::INFINITY,
     g_hummer_steering_car: f64 = 200.0,
     g_hummer_turn_effectiveness: f64 = 1.0,
@@ -318,7 +635,15 @@ cvars! {
     g_hummer_turret_offset_chassis_y: f64 = 0.0,
     g_hummer_turret_offset_turret_x: f64 = 0.0,
     g_hummer_turret_offset_turret_y: f64 = 0.0,
+    g_hummer_turret_slaved: TurretSlaved = TurretSlaved::Free,
+    g_hummer_water_lethal: bool = false,
+    /// Weapons the hummer can use, `all` or a comma-separated list
+    g_hummer_weapons: WeaponSet = WeaponSet::default(),
 
+    /// When a vehicle dies in lethal water this soon after knockback, the attacker gets the kill
+    g_knockback_credit_time: f64 = 5.0,
+
+    g_machine_gun_altfire: AltFire = AltFire::None,
     g_machine_gun_angle_spread: f64 = 0.015,
     g_machine_gun_damage: f64 = 2.5, // exact from orig RW
     g_machine_gun_refire: f64 = 0.050,
@@ -330,9 +655,59 @@ cvars! {
     /// The map to play on. Set to empty string for random.
     g_map: String = "".to_owned(),
 
+    /// Mines can't be set off until this long after they're dropped
+    g_mine_arm_time: f64 = 1.0,
+    /// Minimum time between dropping 2 mines
+    g_mine_cooldown: f64 = 0.5,
+    g_mine_explosion_damage: f64 = 56.0,
+    g_mine_explosion_radius: f64 = 40.0,
+    g_mine_explosion_scale: f64 = 1.0,
+    /// How many mines each player can have on the map, dropping another removes their oldest
+    g_mine_max: usize = 5,
+    /// How far behind the vehicle's center mines are dropped
+    g_mine_offset: f64 = 25.0,
+    /// The owner doesn't set off their own mine for this long after dropping it
+    g_mine_owner_immunity: f64 = 5.0,
+    /// A vehicle's hit circle closer than this to an armed mine sets it off
+    g_mine_trigger_radius: f64 = 10.0,
+
+    /// Turret weapons also can't fire when a wall is between the turret pivot and the muzzle,
+    /// not just when the muzzle itself is inside a wall
+    g_muzzle_check_strict: bool = false,
+
+    /// What happens to a player's projectiles when the player leaves
+    g_orphan_projectiles: OrphanProjectiles = OrphanProjectiles::Remove,
+
+    /// How fast HP above full (overheal) wears off, in fractions of full HP per second
+    g_overheal_decay: f64 = 0.05,
+    /// Max HP as a fraction of full HP, armor can go above 1 up to this
+    g_overheal_max: f64 = 1.5,
+    /// Extra HP (fraction of full) vehicles get on spawn, capped by `g_overheal_max`
+    g_overheal_spawn: f64 = 0.0,
+
+    /// How often (in seconds) the damage multiplier grows during `OvertimeMode::Escalation`
+    g_overtime_escalation_interval: f64 = 30.0,
+    /// The overtime damage multiplier never goes above this
+    g_overtime_escalation_max: f64 = 4.0,
+    /// How much the damage multiplier grows each `g_overtime_escalation_interval`
+    g_overtime_escalation_step: f64 = 0.25,
+    /// What happens when the time runs out and the lead is tied
+    g_overtime_mode: OvertimeMode = OvertimeMode::SuddenDeath,
+
+    /// Vehicles slower than this (px/s) pivot in place if they have a `pivot_turn_rate`.
+    g_pivot_speed_max: f64 = 5.0,
+
     g_players_max: usize = 64,
     g_players_min: usize = 4,
 
+    /// The last balance preset applied, see `presets`. Set it in the console to switch mid-match.
+    g_preset: String = "rebalanced".to_owned(),
+
+    /// Round positions and angles to multiples of 1 / this after each tick
+    /// so float drift can't desync long replays. 0 disables.
+    g_quantize_state: f64 = 0.0,
+
+    g_railgun_altfire: AltFire = AltFire::None,
     g_railgun_damage: f64 = 47.0, // exact from orig RW
     g_railgun_push: f64 = 300.0,
     g_railgun_reload_ammo: u32 = 1,
@@ -341,7 +716,23 @@ cvars! {
     g_railgun_vehicle_velocity_factor: f64 = 0.0,
 
     g_respawn_delay: f64 = 0.5, // LATER this used to be 2 s, did RW use 2 s?
-
+    /// Keep the turret angle (relative to the chassis) after respawning.
+    g_respawn_keep_turret: bool = false,
+    /// Keep the selected weapon after respawning, otherwise switch to MG.
+    ///
+    /// If the new vehicle can't use it (see `g_tank_weapons` and friends),
+    /// it gets MG or the first weapon in `g_weapon_order` it can use.
+    g_respawn_keep_weapon: bool = true,
+
+    g_rockets_altfire: AltFire = AltFire::None,
+    /// Mortar mode - set to e.g. 0.5 to lob rockets over walls.
+    g_rockets_arc: f64 = 0.0,
+    g_rockets_arc_time: f64 = 0.5,
+    /// Rockets fired automatically after each trigger pull, 1 means no bursts
+    g_rockets_burst_count: u32 = 1,
+    /// Time between rockets within a burst.
+    /// The next burst starts after g_rockets_refire (counted from its first rocket) and after this one finishes.
+    g_rockets_burst_interval: f64 = 0.1,
     g_rockets_damage_direct: f64 = 25.0,
     g_rockets_explosion_damage: f64 = 0.0, // pretty sure from orig RW testing
     g_rockets_explosion_radius: f64 = 20.0,
@@ -352,20 +743,56 @@ cvars! {
     g_rockets_speed: f64 = 600.0,
     g_rockets_vehicle_velocity_factor: f64 = 1.0,
 
+    /// Most samples when checking a rotating vehicle's corners against walls
+    g_rotation_sweep_samples_max: usize = 16,
+    /// Corners move at most this far between samples when a rotating vehicle is near a wall
+    g_rotation_sweep_step: f64 = 4.0,
+
+    /// With `SelfDestruct::Blast`, multiplies the damage, radius and explosion scale
+    g_self_destruct_blast_factor: f64 = 1.5,
     g_self_destruct_damage_center: f64 = 150.0,
     g_self_destruct_damage_edge: f64 = 0.0,
     g_self_destruct_explosion_scale: f64 = 2.0, // LATER radius
     g_self_destruct_radius: f64 = 175.0,
 
+    /// With `SelfDestruct::Submunitions`, how many cluster bombs are thrown out in a ring
+    g_self_destruct_submunitions_count: usize = 12,
+    g_self_destruct_submunitions_speed: f64 = 250.0,
+    /// Fuse of the submunitions, randomized by `g_cluster_bomb_time_spread` like normal cluster bombs
+    g_self_destruct_submunitions_time: f64 = 0.4,
+    /// How long a vehicle takes to sink in lethal water before it dies
+    g_sink_time: f64 = 1.0,
+
+    g_smoke_cooldown: f64 = 10.0,
+    g_smoke_duration: f64 = 5.0,
+    /// How far behind the vehicle's center.
+    g_smoke_offset: f64 = 30.0,
+    g_smoke_radius: f64 = 50.0,
+
+    /// Vehicles materialize for this long after spawning, they can't shoot or be damaged meanwhile.
+    /// 0 means they appear instantly like in the original RecWar.
+    g_spawn_effect_duration: f64 = 0.0,
+    /// Movement input is ignored for this long after spawning so vehicles don't lurch while materializing
+    g_spawn_input_lock: f64 = 0.0,
+
+    /// Scale max speed by the surface's speed like in the original RecWar
+    g_surface_speed: bool = false,
+
     g_tank_accel_backward: f64 = 550.0,
     g_tank_accel_forward: f64 = 550.0,
+    g_tank_armor_front: f64 = 1.0,
+    g_tank_armor_rear: f64 = 1.0,
     g_tank_armor_scale: f64 = 1.0,
+    g_tank_armor_side: f64 = 1.0,
     g_tank_friction_const: f64 = 50.0,
     g_tank_friction_linear: f64 = 0.9,
     g_tank_maxs_x: f64 = 19.0,
     g_tank_maxs_y: f64 = 12.0,
     g_tank_mins_x: f64 = -19.0,
     g_tank_mins_y: f64 = -12.0,
+    /// About the top speed of the regular turning model so pivoting is snappier but not faster
+    g_tank_pivot_turn_rate: f64 = 2.5,
+    g_tank_self_destruct: SelfDestruct = SelfDestruct::Blast,
     g_tank_speed_max: f64 = f64::INFINITY,
     g_tank_steering_car: f64 = 0.0,
     g_tank_turn_effectiveness: f64 = 1.0,
@@ -377,10 +804,53 @@ cvars! {
     g_tank_turret_offset_chassis_y: f64 = 0.0,
     g_tank_turret_offset_turret_x: f64 = -14.0,
     g_tank_turret_offset_turret_y: f64 = 0.0,
-
+    g_tank_turret_slaved: TurretSlaved = TurretSlaved::Free,
+    g_tank_water_lethal: bool = false,
+    /// Weapons the tank can use, `all` or a comma-separated list
+    g_tank_weapons: WeaponSet = WeaponSet::default(),
+
+    /// Length of a match in seconds, 0 for no limit
+    g_time_limit: f64 = 0.0,
+
+    /// Driving with the turret turned further than this from the hull's facing is penalized.
+    g_turret_misalign_angle: f64 = 90.0f64.to_radians(),
+    /// Multiplies acceleration and max speed while misaligned, 1.0 disables the penalty.
+    g_turret_misalign_speed_factor: f64 = 1.0,
+    /// With `TurretSlaved::Velocity`, manual turret input disables tracking for this long
+    g_turret_slaved_override_time: f64 = 2.0,
+    /// With `TurretSlaved::Velocity`, what the turret does while reversing
+    g_turret_slaved_reverse: SlavedReverse = SlavedReverse::Track,
+    /// With `TurretSlaved::Velocity`, the turret only tracks above this speed
+    g_turret_slaved_speed_min: f64 = 20.0,
+    /// How fast the turret speeds up (deg/s^2) - infinite means no turret inertia
+    g_turret_turn_accel_deg: f64 = f64::INFINITY,
+    /// Turret turn rate lost per second (deg/s) no matter how fast it turns
+    g_turret_turn_friction_const_deg: f64 = 0.0,
+    /// Fraction of the turret turn rate lost per second
+    g_turret_turn_friction_linear: f64 = 0.0,
+    /// Max turret turn rate (deg/s)
     g_turret_turn_speed_deg: f64 = 120.0,
     g_turret_turn_step_angle_deg: f64 = 45.0,
 
+    /// Comma-separated order of weapons for prev/next weapon, must list each weapon exactly once
+    g_weapon_order: WeaponOrder = WeaponOrder::default(),
+    /// How long after switching weapons before any weapon can fire, 0 for instant
+    g_weapon_switch_time: f64 = 0.0,
+
+    /// Wrecks stop projectiles like walls so they can be used as cover
+    g_wrecks_block_projectiles: bool = false,
+
+    /// Safe area which shrinks over time, vehicles outside take damage - mostly for bot matches
+    g_zone: bool = false,
+    g_zone_damage_per_sec: f64 = 10.0,
+    /// The zone never shrinks below this
+    g_zone_radius_min: f64 = 300.0,
+    /// How long it takes to shrink from covering the whole map to `g_zone_radius_min`
+    g_zone_shrink_duration: f64 = 120.0,
+    /// How long after the match starts the zone starts shrinking
+    g_zone_start_delay: f64 = 30.0,
+
+    hud_ammo_color: CVec3 = CVec3::new(0.99, 0.98, 0.0),
     /// Original RecWar had 4.
     hud_ammo_height: f64 = 4.0,
     /// Original RecWar had 99.
@@ -388,20 +858,120 @@ cvars! {
     hud_ammo_x: f64 = 30.0,
     hud_ammo_y: f64 = -30.0,
 
+    /// Fills up as the boost cooldown runs out, bright when ready or boosting.
+    hud_boost_color_active: CVec3 = CVec3::new(0.4, 0.75, 1.0),
+    hud_boost_color_charging: CVec3 = CVec3::new(0.51, 0.51, 0.51),
+    hud_boost_color_ready: CVec3 = CVec3::new(0.0, 0.89, 0.19),
+    hud_boost_size: f64 = 9.0,
+    hud_boost_x: f64 = 15.0,
+    hud_boost_y: f64 = -30.0,
+
+    /// How many of your cluster bombs alt-fire would detonate
+    hud_cb_count_color: CVec3 = CVec3::new(1.0, 0.63, 0.0),
+
+    /// Compass rose with the hull and turret facing for callouts, hidden in missile cam
+    hud_compass: bool = false,
+    hud_compass_color: CVec3 = CVec3::new(0.51, 0.51, 0.51),
+    hud_compass_north_color: CVec3 = CVec3::new(0.78, 0.78, 0.78),
+    hud_compass_radius: f64 = 16.0,
+    hud_compass_turret_color: CVec3 = CVec3::new(1.0, 0.63, 0.0),
+    hud_compass_x: f64 = -50.0,
+    hud_compass_y: f64 = 80.0,
+
+    /// Smoke and flares, bright when they can be used
+    hud_cooldown_color_ready: CVec3 = CVec3::new(0.78, 0.78, 0.78),
+    hud_cooldown_color_waiting: CVec3 = CVec3::new(0.51, 0.51, 0.51),
+
+    /// Marker showing where the current weapon shoots
+    hud_crosshair: bool = true,
+    hud_crosshair_color: CVec3 = CVec3::WHITE,
+    /// Distance from the vehicle's center
+    hud_crosshair_distance: f64 = 80.0,
+    hud_crosshair_shape: CrosshairShape = CrosshairShape::Cross,
+    hud_crosshair_size: f64 = 6.0,
+    hud_crosshair_thickness: f64 = 1.0,
+
+    /// How long the weapon icon blinks after a shot was blocked by a wall
+    hud_fire_blocked_blink: f64 = 0.4,
+    /// Blinks per second
+    hud_fire_blocked_blink_rate: f64 = 10.0,
+
+    /// Flares left, shown while the current weapon's alt-fire deploys them
+    hud_flares_x: f64 = 30.0,
+    hud_flares_y: f64 = -5.0,
+
+    /// Length of the arrow at the screen edge pointing to an off-screen guider, see `g_gm_reveal`
+    hud_gm_reveal_arrow_length: f64 = 16.0,
+    hud_gm_reveal_color: CVec3 = CVec3::RED,
+    /// Distance of the arrow from the screen edge
+    hud_gm_reveal_margin: f64 = 20.0,
+    /// Circle around an on-screen guider
+    hud_gm_reveal_radius: f64 = 30.0,
+    hud_gm_reveal_thickness: f64 = 2.0,
+
+    /// Map grid cell the vehicle is in, e.g. "E7", hidden in missile cam.
+    /// Also appended to kill notifications.
+    hud_grid_label: bool = false,
+    /// Width and height of a grid cell in tiles, bigger is coarser for huge maps
+    hud_grid_label_cell_size: usize = 1,
+    hud_grid_label_x: f64 = -70.0,
+    hud_grid_label_y: f64 = 125.0,
+
+    /// The HP bar goes from empty through half to full, see `Palette::hp_color`
+    hud_hp_color_empty: CVec3 = CVec3::RED,
+    hud_hp_color_full: CVec3 = CVec3::GREEN,
+    hud_hp_color_half: CVec3 = CVec3::YELLOW,
+    /// How many times per second the HP bar flashes while crippled
+    hud_hp_cripple_flash_rate: f64 = 4.0,
+    hud_hp_flash_color: CVec3 = CVec3::WHITE,
     /// Original RecWar had 9.
     hud_hp_height: f64 = 9.0,
+    /// HP above full is drawn after the normal bar in this color
+    hud_hp_overheal_color: CVec3 = CVec3::CYAN,
     /// Original RecWar had 99.
     hud_hp_width: f64 = 100.0,
     hud_hp_x: f64 = 30.0,
     hud_hp_y: f64 = -50.0,
 
+    /// Blinking text while a homing missile is locked onto your vehicle
+    hud_lock_warning: bool = true,
+    hud_lock_warning_color: CVec3 = CVec3::RED,
+    /// How many times per second the lock warning blinks
+    hud_lock_warning_flash_rate: f64 = 4.0,
+    hud_lock_warning_font_size: f64 = 24.0,
+    hud_lock_warning_x: f64 = 30.0,
+    hud_lock_warning_y: f64 = -80.0,
+
+    /// Shown while the turret misalignment penalty is active.
+    hud_misalign_color: CVec3 = CVec3::new(1.0, 0.63, 0.0),
+    hud_misalign_size: f64 = 9.0,
+    hud_misalign_x: f64 = 15.0,
+    hud_misalign_y: f64 = -50.0,
+
+    /// Guided missile speed bar and the arrow pointing back home
+    hud_missile_color: CVec3 = CVec3::new(0.4, 0.75, 1.0),
+    /// Length of the arrow pointing from the guided missile back to your vehicle.
+    hud_missile_home_arrow_length: f64 = 20.0,
+    hud_missile_home_x: f64 = 230.0,
+    hud_missile_home_y: f64 = -28.0,
+    hud_missile_indicator_color: CVec3 = CVec3::new(0.0, 0.89, 0.19),
     hud_missile_indicator_dash_length: f64 = 3.3,
     hud_missile_indicator_radius: f64 = 18.0,
+    /// While guiding, show missile speed instead of ammo and the way back to your vehicle.
+    hud_missile_mode: bool = true,
+    /// Guided missile speed at which the speed bar is full.
+    hud_missile_speed_max: f64 = 1000.0,
+    /// Dims the weapon icon while guiding, 0 hides it.
+    hud_missile_weapon_icon_alpha: f64 = 0.3,
 
     hud_names: bool = true,
     hud_names_alpha: f64 = 1.0,
     hud_names_brightness: f64 = 255.0,
+    /// Draw bot names in their color (see `bot_identity`), otherwise everyone uses hud_names_brightness
+    hud_names_colors: bool = true,
     hud_names_font_size: f64 = 16.0,
+    /// Size of the square in the player's color next to the name, 0 to disable
+    hud_names_marker_size: f64 = 6.0,
     hud_names_shadow_alpha: f32 = 1.0,
     hud_names_shadow_x: f32 = 1.0,
     hud_names_shadow_y: f32 = 1.0,
@@ -409,6 +979,7 @@ cvars! {
     hud_names_y: f64 = 30.0,
 
     hud_notifications_alpha_old: f32 = 0.5,
+    hud_notifications_color_ai_chat: CVec3 = CVec3::YELLOW,
     hud_notifications_color_death: CVec3 = CVec3::RED,
     hud_notifications_color_kill: CVec3 = CVec3::BLUE2,
     hud_notifications_duration: f64 = 3.0,
@@ -422,6 +993,20 @@ cvars! {
     hud_notifications_y_from_top: f32 = 150.0,
     hud_notifications_y_offset: f32 = -40.0,
 
+    /// Sidebar with every player's stats, see cl_observer
+    hud_observer_font_size: f64 = 16.0,
+    hud_observer_hp_background_color: CVec3 = CVec3::new(0.31, 0.31, 0.31),
+    hud_observer_hp_height: f64 = 4.0,
+    /// The player with the most points
+    hud_observer_leader_color: CVec3 = CVec3::YELLOW,
+    hud_observer_line_height: f64 = 36.0,
+    hud_observer_width: f64 = 200.0,
+    hud_observer_x: f64 = -210.0,
+    hud_observer_y: f64 = 20.0,
+
+    hud_pause_color: CVec3 = CVec3::new(0.9, 0.16, 0.22),
+    /// How much to darken the view while paused, 0 to disable
+    hud_pause_dim_alpha: f32 = 0.4,
     hud_pause_font_size: f64 = 64.0,
     hud_pause_shadow_x: f32 = 2.0,
     hud_pause_shadow_y: f32 = 2.0,
@@ -430,6 +1015,30 @@ cvars! {
     hud_perf_client_y: f64 = -105.0,
     hud_perf_server_x: f64 = -500.0,
     hud_perf_server_y: f64 = -105.0,
+    hud_perf_warning_color: CVec3 = CVec3::new(1.0, 0.63, 0.0),
+    hud_perf_warning_color_detail: CVec3 = CVec3::new(0.78, 0.78, 0.78),
+    hud_perf_warning_font_size: f64 = 16.0,
+    hud_perf_warning_x: f64 = -220.0,
+    hud_perf_warning_y: f64 = 20.0,
+
+    /// Overtime and who won at the top of the screen
+    hud_phase_banner: bool = true,
+    hud_phase_banner_color: CVec3 = CVec3::YELLOW,
+    hud_phase_banner_font_size: f64 = 32.0,
+    /// From the top of the view
+    hud_phase_banner_y: f64 = 60.0,
+
+    hud_possessed_color: CVec3 = CVec3::new(0.99, 0.98, 0.0),
+    hud_possessed_font_size: f64 = 24.0,
+    hud_possessed_x: f64 = 20.0,
+    hud_possessed_y: f64 = 60.0,
+
+    /// Show how far the BFG beam reaches and where cluster bombs land for the current weapon
+    hud_range_hints: bool = false,
+    hud_range_hints_alpha: f64 = 0.3,
+    hud_range_hints_color: CVec3 = CVec3::WHITE,
+    /// Size of the cluster bomb spread ellipse in standard deviations
+    hud_range_hints_sigmas: f64 = 2.0,
 
     hud_ranking_font_size: f64 = 16.0,
     /// Original RW uses 1
@@ -447,16 +1056,29 @@ cvars! {
     hud_score_x: f64 = 30.0,
     hud_score_y: f64 = -70.0,
 
+    /// Everyone except you, see `hud_text_color`
+    hud_scoreboard_color_others: CVec3 = CVec3::new(0.8, 0.8, 0.8),
     hud_scoreboard_font_size: f64 = 16.0,
     hud_scoreboard_line_height: f64 = 18.0,
     /// NB: these shadows absolutely murder performance in firefox (chromum is ok)
     hud_scoreboard_shadow_x: f32 = 1.0,
     hud_scoreboard_shadow_y: f32 = 1.0,
+    hud_scoreboard_width_assists: f32 = 60.0,
     hud_scoreboard_width_deaths: f32 = 50.0,
     hud_scoreboard_width_kills: f32 = 50.0,
     hud_scoreboard_width_name: f32 = 150.0,
     hud_scoreboard_width_points: f32 = 50.0,
 
+    hud_smoke_height: f64 = 4.0,
+    hud_smoke_width: f64 = 100.0,
+    hud_smoke_x: f64 = 30.0,
+    hud_smoke_y: f64 = -20.0,
+
+    /// Score, ranking, compass, grid label, scoreboard and observer sidebar text
+    hud_text_color: CVec3 = CVec3::WHITE,
+
+    /// Weapon icon tint when firing is blocked
+    hud_weapon_blocked_color: CVec3 = CVec3::new(1.0, 0.2, 0.2),
     hud_weapon_icon_shadow_alpha: f64 = 0.5,
     hud_weapon_icon_shadow_x: f32 = 2.0,
     hud_weapon_icon_shadow_y: f32 = 2.0,
@@ -466,21 +1088,225 @@ cvars! {
     /// This is in a way the opposite of smoothing
     r_align_to_pixels_background: bool = true,
     r_align_to_pixels_text: bool = true,
+    /// Opacity of the capture area drawn around bases in objective modes
+    r_base_areas_alpha: f64 = 0.15,
+    /// Same as macroquad's `GREEN`
+    r_bfg_color: CVec3 = CVec3::new(0.0, 0.89, 0.19),
+    /// Exhaust particles per tick while boosting (fewer when there are many particles)
+    r_boost_exhaust_count: usize = 2,
+    r_boost_exhaust_duration: f64 = 0.3,
+    r_boost_exhaust_size: f64 = 2.0,
+    r_boost_exhaust_speed: f64 = 60.0,
+    r_cluster_bomb_color: CVec3 = CVec3::CYAN,
     r_cluster_bombs: bool = true,
+    /// Opacity of fresh decals, they fade out over their lifetime
+    r_decals_alpha: f64 = 0.6,
+    r_decals_bullet_color: CVec3 = CVec3::new(0.1, 0.1, 0.1),
+    r_decals_bullet_size: f64 = 2.0,
+    r_decals_scorch_color: CVec3 = CVec3::new(0.05, 0.05, 0.05),
+    r_decals_scorch_size: f64 = 7.0,
+    /// Lower `r_effects_quality` when rendering is slow for a while, see `EffectsDegrade`
+    r_effects_auto_degrade: bool = true,
+    /// Rendering must get faster than this fraction of the threshold to reset the slow period
+    r_effects_degrade_hysteresis: f64 = 0.8,
+    /// Average time to issue draw calls above which rendering counts as slow
+    r_effects_degrade_threshold_ms: f64 = 12.0,
+    /// How long rendering has to be slow to drop a quality level
+    r_effects_degrade_time: f64 = 3.0,
+    /// Preset for particle counts, decal budgets, explosion LOD and shadows,
+    /// the individual cvars multiply on top of it
+    r_effects_quality: EffectsQuality = EffectsQuality::High,
+    /// Most new cosmetic effects per update at high quality - keeps big CB barrages in check
+    r_effects_spawns_max: usize = 200,
+    /// Color of the emplacements' round base
+    r_emplacement_base_color: CVec3 = CVec3::new(0.35, 0.35, 0.3),
+    r_emplacement_base_radius: f64 = 12.0,
+    /// Manned emplacements get a ring in this color
+    r_emplacement_manned_color: CVec3 = CVec3::new(1.0, 1.0, 0.0),
     r_explosion_duration: f64 = 0.5,
+    /// Draw small explosions as circles instead of sprites - cheaper during big CB barrages
+    r_explosion_lod: bool = false,
+    /// Circles drawn instead of explosion sprites, see `r_explosion_lod`
+    r_explosion_lod_color: CVec3 = CVec3::new(1.0, 0.63, 0.0),
+    r_explosion_lod_color_bfg: CVec3 = CVec3::new(0.4, 0.75, 1.0),
+    /// Explosions smaller than this scale use the cheap version if `r_explosion_lod` is on
+    r_explosion_lod_scale: f64 = 0.3,
+    /// Draw all normal explosions before all cyan ones to minimize texture switches
+    r_explosions_group_by_sheet: bool = false,
+    /// Most explosions shown at once, the oldest disappear first
+    r_explosions_max: usize = 500,
+    /// Draw the newest explosions first (below older ones) instead of oldest first
     // After trying true for a while, I think false looks better:
     // - CB looks smoother. With true it sometimes looked like it had 2 stages
     //   because the later explosions were suddenly revealed after the first ones disappeared.
     // - Rockets look better if hitting the same spot.
     r_explosions_reverse_order: bool = false,
+    /// How far past the last sample a smoothed entity keeps moving if the next sample is late
+    r_extrapolation_ms: f64 = 100.0,
+    r_flare_color: CVec3 = CVec3::new(1.0, 0.95, 0.6),
+    r_flare_radius: f64 = 2.5,
+    /// Number of fading sparks trailing each flare
+    r_flare_trail: usize = 4,
+    /// Seconds between the trailing sparks
+    r_flare_trail_interval: f64 = 0.04,
     r_guided_missile_offset_x: f64 = 5.0,
     r_guided_missile_offset_y: f64 = 0.0,
     r_homing_missile_offset_x: f64 = 5.0,
     r_homing_missile_offset_y: f64 = 0.0,
+    /// Effect of projectiles hitting surfaces which aren't walls or water
+    /// and aren't marked dust or spray in texture_list.txt
+    r_impact_default: ImpactEffect = ImpactEffect::Dust,
+    /// Dust particles per impact on sand and dirt (fewer when there are many particles)
+    r_impact_dust_count: usize = 5,
+    r_impact_dust_duration: f64 = 0.4,
+    r_impact_dust_size: f64 = 3.0,
+    r_impact_dust_speed: f64 = 20.0,
+    /// Sparks, dust or splashes where projectiles hit depending on the surface
+    r_impact_effects: bool = true,
+    /// Spark particles per impact on walls
+    r_impact_sparks_count: usize = 4,
+    r_impact_sparks_duration: f64 = 0.15,
+    r_impact_sparks_size: f64 = 1.0,
+    r_impact_sparks_speed: f64 = 120.0,
+    /// Droplets per impact on water
+    r_impact_splash_count: usize = 6,
+    r_impact_splash_duration: f64 = 0.35,
+    /// How long the ring around explosions on water takes to spread out
+    r_impact_splash_ring_duration: f64 = 0.6,
+    /// Diameter of the ring around explosions on water, multiplied by explosion scale
+    r_impact_splash_ring_size: f64 = 60.0,
+    r_impact_splash_size: f64 = 1.5,
+    r_impact_splash_speed: f64 = 60.0,
+    /// Draw smoothed entities this far in the past so there's usually a sample on both sides
+    r_interpolation_delay_ms: f64 = 0.0,
+    /// Backblast particles per missile launch (fewer when there are many particles)
+    r_launch_backblast_count: usize = 8,
+    r_launch_backblast_duration: f64 = 0.25,
+    r_launch_backblast_size: f64 = 1.5,
+    r_launch_backblast_speed: f64 = 150.0,
+    /// Half angle of the backblast cone
+    r_launch_backblast_spread: f64 = 25.0f64.to_radians(),
+    /// Backblast and muzzle smoke when launching rockets and missiles
+    r_launch_effects: bool = true,
+    /// Screen shake added when the local player launches a missile, 1 is the max
+    r_launch_shake: f64 = 0.15,
+    r_launch_smoke_duration: f64 = 0.5,
+    r_launch_smoke_size_begin: f64 = 3.0,
+    r_launch_smoke_size_end: f64 = 12.0,
+    /// Both the line and the tracer's tail, unless overridden by `r_team_colored_projectiles`
+    r_mg_color: CVec3 = CVec3::YELLOW,
+    /// How to draw machine gun bullets - a cheap line or a tracer with a bright head and fading tail
+    r_mg_style: MgStyle = MgStyle::Line,
+    r_mg_tracer_head_color: CVec3 = CVec3::new(1.0, 1.0, 0.8),
+    r_mg_tracer_head_size: f64 = 2.0,
+    /// Number of tail segments, each more transparent than the one before
+    r_mg_tracer_segments: usize = 3,
+    /// The tail is as long as the distance the bullet travels in this time
+    r_mg_tracer_tail_time: f64 = 0.01,
+    /// Mines which aren't armed yet are drawn with this alpha
+    r_mine_alpha_unarmed: f64 = 0.4,
+    /// Mines which don't belong to a team, see `r_team_colored_projectiles`
+    r_mine_color: CVec3 = CVec3::new(0.6, 0.6, 0.6),
+    r_mine_radius: f64 = 5.0,
+    /// Show where the guided missile would hit if the player stopped steering
+    r_missile_prediction: bool = false,
+    r_missile_prediction_color: CVec3 = CVec3::new(0.9, 0.16, 0.22),
+    /// Don't look further ahead than this
+    r_missile_prediction_distance: f64 = 1500.0,
+    /// Fill color for areas outside the map if r_out_of_bounds_tile is not set
+    r_out_of_bounds_color: CVec3 = CVec3::BLACK,
+    /// Index of the tile texture to draw outside the map, negative means use r_out_of_bounds_color
+    r_out_of_bounds_tile: i32 = -1,
+    r_particles_color_backblast: CVec3 = CVec3::new(1.0, 0.6, 0.2),
+    r_particles_color_boost_exhaust: CVec3 = CVec3::new(0.4, 0.7, 1.0),
+    r_particles_color_impact_dust: CVec3 = CVec3::new(0.75, 0.65, 0.45),
+    r_particles_color_impact_spark: CVec3 = CVec3::new(1.0, 0.9, 0.5),
+    r_particles_color_launch_smoke: CVec3 = CVec3::new(0.7, 0.7, 0.7),
+    /// Water impacts and the rings they leave
+    r_particles_color_splash: CVec3 = CVec3::new(0.85, 0.95, 1.0),
+    r_particles_color_track_scrub: CVec3 = CVec3::new(0.55, 0.45, 0.3),
+    /// How much bigger lobbed projectiles get per unit of height
+    r_projectile_arc_scale: f64 = 1.0,
+    r_railgun_color: CVec3 = CVec3::BLUE,
     r_rockets_offset_x: f64 = 5.0,
     r_rockets_offset_y: f64 = 0.0,
+    /// Sparks from vehicles scraping walls
+    r_scrape_sparks_color: CVec3 = CVec3::new(1.0, 0.8, 0.3),
+    /// How much screen shake wears off per second
+    r_shake_decay: f64 = 1.5,
+    /// Camera offset at full shake in pixels
+    r_shake_offset_max: f64 = 6.0,
+    /// Draw spawn points, bases and named markers from the map's metadata
+    r_show_spawns: bool = false,
+    r_show_spawns_alpha: f64 = 0.6,
+    r_show_spawns_font_size: f64 = 14.0,
+    /// Size of the chevron at each spawn point
+    r_show_spawns_size: f64 = 8.0,
+    /// Size of a vehicle at the end of sinking, it also fades out
+    r_sinking_scale: f64 = 0.5,
+    r_smoke_alpha: f64 = 0.7,
+    r_smoke_color: CVec3 = CVec3::new(0.6, 0.6, 0.6),
     r_smoothing: bool = false,
+    /// Smoothed entities further than this from their expected position snap instead (e.g. respawns)
+    r_smoothing_snap_distance: f64 = 100.0,
+    /// How vehicles look while materializing, see `g_spawn_effect_duration`
+    r_spawn_effect: SpawnEffect = SpawnEffect::Scale,
+    r_spawn_effect_ring_color: CVec3 = CVec3::WHITE,
+    /// Radius of the ring at the start, it shrinks to nothing
+    r_spawn_effect_ring_radius: f64 = 60.0,
+    r_spawn_effect_ring_thickness: f64 = 2.0,
     r_splitscreen_gap: f64 = 8.0,
+    r_surface_dust_color: CVec3 = CVec3::new(0.7, 0.6, 0.4),
+    r_surface_dust_radius: f64 = 8.0,
+    r_surface_marks_alpha: f64 = 0.35,
+    r_surface_marks_color: CVec3 = CVec3::BLACK,
+    /// Distance of each mark from the vehicle's center line
+    r_surface_marks_spacing: f64 = 9.0,
+    r_surface_marks_width: f64 = 3.0,
+    r_surface_spray_color: CVec3 = CVec3::new(0.8, 0.9, 1.0),
+    r_surface_spray_radius: f64 = 4.0,
+    /// In team modes, draw MG, rail and BFG in the shooter's team color and ring their explosions with it
+    r_team_colored_projectiles: bool = false,
+    /// Opacity of the ring under explosions from `r_team_colored_projectiles`
+    r_team_explosion_ring_alpha: f64 = 0.5,
+    /// Dirt particles per tick from the tracks of a vehicle pivoting in place (fewer when there are many particles)
+    r_track_scrub_count: usize = 2,
+    r_track_scrub_duration: f64 = 0.4,
+    r_track_scrub_size: f64 = 2.0,
+    r_track_scrub_speed: f64 = 40.0,
+    /// Draw the turret on wrecks, frozen at the angle it had when the vehicle died
+    r_wreck_turret: bool = true,
+    /// How dark the turret on wrecks is if there's no dedicated wreck turret texture - 0 is black, 1 is unchanged
+    r_wreck_turret_brightness: f64 = 0.4,
+    /// Opacity of the ring around the `g_zone` safe area
+    r_zone_alpha: f64 = 0.5,
+    r_zone_color: CVec3 = CVec3::RED,
+    r_zone_thickness: f64 = 4.0,
+
+    /// Vehicles further than this from the camera can't be heard
+    snd_audible_radius: f64 = 800.0,
+    /// Max number of engine sounds, the local player's vehicle is always included
+    snd_engine_sounds_max: usize = 8,
+    /// Speed considered full for engine pitch if the vehicle type has no speed limit
+    snd_engine_speed_max: f64 = 300.0,
+    /// Click events when your shot is blocked because the muzzle is inside a wall
+    snd_fire_blocked: bool = false,
+    /// Min time between click events while the trigger is held
+    snd_fire_blocked_interval: f64 = 0.25,
+    /// Start and stop events for a looping tone while a homing missile is locked onto you
+    snd_lock_warning: bool = false,
+    /// Volume falloff with distance - 1 is linear, higher drops off faster near the listener
+    snd_rolloff: f64 = 1.0,
+    /// Scraping sound events when vehicles slide along walls
+    snd_scrape: bool = false,
+    /// Min time between scraping sound events from one vehicle
+    snd_scrape_interval: f64 = 0.2,
+    /// Speed along the wall at which scraping is at full intensity
+    snd_scrape_speed_max: f64 = 300.0,
+    /// Turret traverse sound events while turrets turn
+    snd_turret: bool = false,
+    /// Min time between turret sound events from one vehicle
+    snd_turret_interval: f64 = 0.1,
 
     /// LATER fix - Does not work in MQ: https://github.com/not-fl3/macroquad/issues/264
     sv_auto_pause_on_minimize: bool = true,
@@ -489,6 +1315,11 @@ cvars! {
 
     sv_net_listen_addr: String = "127.0.0.1:26000".to_owned(),
 
+    /// How many times rule hooks can be called per tick, the rest is skipped
+    sv_rule_hooks_max_calls: u32 = 1000,
+
+    /// Unit of angle cvars typed without a `deg` or `rad` suffix, see `cvar_units`
+    sys_angle_unit: AngleUnit = AngleUnit::Rad,
     /// LATER Without extrapolation, this needs to be significantly higher than framerate to avoid judder.
     ///     Assuming rendering at 60 fps:
     ///     With 30 updates, it's easily visible on vehicle movement.
@@ -504,14 +1335,9 @@ impl Cvars {
     }
 
     /// Create a new Cvars object with an approximation of the original RecWar settings.
-    #[allow(dead_code)] // LATER allow using balance presets again
+    #[allow(dead_code)]
     pub fn new_rec_war() -> Self {
-        Self {
-            // This is 15625 tiles - should be more than enough, biggest original maps have 59.
-            // Can't use infinity - it would break the math.
-            g_railgun_speed: 1_000_000.0,
-            ..Self::default()
-        }
+        Self::preset("original").unwrap()
     }
 
     /// Reset this Cvars object to the default RecWars settings.
@@ -687,6 +1513,24 @@ impl Cvars {
         }
     }
 
+    /// Where the second rocket launcher is relative to the first one's part, see `g_rockets_burst_count`.
+    pub fn g_hardpoint_rockets_alt(&self, veh_type: VehicleType) -> Vec2f {
+        match veh_type {
+            VehicleType::Tank => Vec2f::new(
+                self.g_hardpoint_tank_rockets_alt_x,
+                self.g_hardpoint_tank_rockets_alt_y,
+            ),
+            VehicleType::Hovercraft => Vec2f::new(
+                self.g_hardpoint_hovercraft_rockets_alt_x,
+                self.g_hardpoint_hovercraft_rockets_alt_y,
+            ),
+            VehicleType::Hummer => Vec2f::new(
+                self.g_hardpoint_hummer_rockets_alt_x,
+                self.g_hardpoint_hummer_rockets_alt_y,
+            ),
+        }
+    }
+
     pub fn g_vehicle_hitbox(&self, veh_type: VehicleType) -> Hitbox {
         match veh_type {
             VehicleType::Tank => Hitbox {
@@ -713,6 +1557,21 @@ impl Cvars {
         self.g_armor * scale
     }
 
+    /// Damage multiplier for hits coming from the given side of the vehicle.
+    pub fn g_vehicle_armor(&self, veh_type: VehicleType, arc: HitArc) -> f64 {
+        match (veh_type, arc) {
+            (VehicleType::Tank, HitArc::Front) => self.g_tank_armor_front,
+            (VehicleType::Tank, HitArc::Side) => self.g_tank_armor_side,
+            (VehicleType::Tank, HitArc::Rear) => self.g_tank_armor_rear,
+            (VehicleType::Hovercraft, HitArc::Front) => self.g_hovercraft_armor_front,
+            (VehicleType::Hovercraft, HitArc::Side) => self.g_hovercraft_armor_side,
+            (VehicleType::Hovercraft, HitArc::Rear) => self.g_hovercraft_armor_rear,
+            (VehicleType::Hummer, HitArc::Front) => self.g_hummer_armor_front,
+            (VehicleType::Hummer, HitArc::Side) => self.g_hummer_armor_side,
+            (VehicleType::Hummer, HitArc::Rear) => self.g_hummer_armor_rear,
+        }
+    }
+
     pub fn g_vehicle_movement_stats(&self, veh_type: VehicleType) -> MovementStats {
         match veh_type {
             VehicleType::Tank => MovementStats {
@@ -720,6 +1579,8 @@ impl Cvars {
                 accel_forward: self.g_tank_accel_forward,
                 friction_const: self.g_tank_friction_const,
                 friction_linear: self.g_tank_friction_linear,
+                pivot_speed_max: self.g_pivot_speed_max,
+                pivot_turn_rate: self.g_tank_pivot_turn_rate,
                 speed_max: self.g_tank_speed_max,
                 steering_car: self.g_tank_steering_car,
                 turn_effectiveness: self.g_tank_turn_effectiveness,
@@ -733,6 +1594,8 @@ impl Cvars {
                 accel_forward: self.g_hovercraft_accel_forward,
                 friction_const: self.g_hovercraft_friction_const,
                 friction_linear: self.g_hovercraft_friction_linear,
+                pivot_speed_max: self.g_pivot_speed_max,
+                pivot_turn_rate: self.g_hovercraft_pivot_turn_rate,
                 speed_max: self.g_hovercraft_speed_max,
                 steering_car: self.g_hovercraft_steering_car,
                 turn_effectiveness: self.g_hovercraft_turn_effectiveness,
@@ -746,6 +1609,8 @@ impl Cvars {
                 accel_forward: self.g_hummer_accel_forward,
                 friction_const: self.g_hummer_friction_const,
                 friction_linear: self.g_hummer_friction_linear,
+                pivot_speed_max: self.g_pivot_speed_max,
+                pivot_turn_rate: self.g_hummer_pivot_turn_rate,
                 speed_max: self.g_hummer_speed_max,
                 steering_car: self.g_hummer_steering_car,
                 turn_effectiveness: self.g_hummer_turn_effectiveness,
@@ -757,6 +1622,38 @@ impl Cvars {
         }
     }
 
+    pub fn g_vehicle_self_destruct(&self, veh_type: VehicleType) -> SelfDestruct {
+        match veh_type {
+            VehicleType::Tank => self.g_tank_self_destruct,
+            VehicleType::Hovercraft => self.g_hovercraft_self_destruct,
+            VehicleType::Hummer => self.g_hummer_self_destruct,
+        }
+    }
+
+    pub fn g_vehicle_turret_slaved(&self, veh_type: VehicleType) -> TurretSlaved {
+        match veh_type {
+            VehicleType::Tank => self.g_tank_turret_slaved,
+            VehicleType::Hovercraft => self.g_hovercraft_turret_slaved,
+            VehicleType::Hummer => self.g_hummer_turret_slaved,
+        }
+    }
+
+    pub fn g_vehicle_water_lethal(&self, veh_type: VehicleType) -> bool {
+        match veh_type {
+            VehicleType::Tank => self.g_tank_water_lethal,
+            VehicleType::Hovercraft => self.g_hovercraft_water_lethal,
+            VehicleType::Hummer => self.g_hummer_water_lethal,
+        }
+    }
+
+    pub fn g_vehicle_weapons(&self, veh_type: VehicleType) -> &WeaponSet {
+        match veh_type {
+            VehicleType::Tank => &self.g_tank_weapons,
+            VehicleType::Hovercraft => &self.g_hovercraft_weapons,
+            VehicleType::Hummer => &self.g_hummer_weapons,
+        }
+    }
+
     /// Where the turret-chassis connection is on the chassis.
     /// E.g. (0, 0) means the turret rotates around the vehicle's origin.
     pub fn g_vehicle_turret_offset_chassis(&self, veh_type: VehicleType) -> Vec2f {
@@ -795,6 +1692,31 @@ impl Cvars {
         }
     }
 
+    /// Apex of the lobbed arc, 0 for direct fire.
+    pub fn g_weapon_arc(&self, weapon: Weapon) -> f64 {
+        match weapon {
+            Weapon::Mg => 0.0,
+            Weapon::Rail => 0.0,
+            Weapon::Cb => self.g_cluster_bomb_arc,
+            Weapon::Rockets => self.g_rockets_arc,
+            Weapon::Hm => 0.0,
+            Weapon::Gm => 0.0,
+            Weapon::Bfg => self.g_bfg_arc,
+        }
+    }
+
+    pub fn g_weapon_arc_time(&self, weapon: Weapon) -> f64 {
+        match weapon {
+            Weapon::Mg => 0.0,
+            Weapon::Rail => 0.0,
+            Weapon::Cb => self.g_cluster_bomb_arc_time,
+            Weapon::Rockets => self.g_rockets_arc_time,
+            Weapon::Hm => 0.0,
+            Weapon::Gm => 0.0,
+            Weapon::Bfg => self.g_bfg_arc_time,
+        }
+    }
+
     pub fn g_weapon_damage_direct(&self, weapon: Weapon) -> f64 {
         match weapon {
             Weapon::Mg => self.g_machine_gun_damage,
@@ -849,6 +1771,8 @@ impl Cvars {
             accel_forward: self.g_homing_missile_accel_forward,
             friction_const: self.g_homing_missile_friction_const,
             friction_linear: self.g_homing_missile_friction_linear,
+            pivot_speed_max: 0.0,
+            pivot_turn_rate: 0.0,
             speed_max: self.g_homing_missile_speed_max,
             steering_car: 0.0,
             turn_effectiveness: self.g_homing_missile_turn_effectiveness,
@@ -865,6 +1789,8 @@ impl Cvars {
             accel_forward: self.g_guided_missile_accel_forward,
             friction_const: self.g_guided_missile_friction_const,
             friction_linear: self.g_guided_missile_friction_linear,
+            pivot_speed_max: 0.0,
+            pivot_turn_rate: 0.0,
             speed_max: self.g_guided_missile_speed_max,
             steering_car: 0.0,
             turn_effectiveness: self.g_guided_missile_turn_effectiveness,
@@ -875,6 +1801,18 @@ impl Cvars {
         }
     }
 
+    pub fn g_weapon_altfire(&self, weapon: Weapon) -> AltFire {
+        match weapon {
+            Weapon::Mg => self.g_machine_gun_altfire,
+            Weapon::Rail => self.g_railgun_altfire,
+            Weapon::Cb => self.g_cluster_bomb_altfire,
+            Weapon::Rockets => self.g_rockets_altfire,
+            Weapon::Hm => self.g_homing_missile_altfire,
+            Weapon::Gm => self.g_guided_missile_altfire,
+            Weapon::Bfg => self.g_bfg_altfire,
+        }
+    }
+
     pub fn g_weapon_refire(&self, weapon: Weapon) -> f64 {
         match weapon {
             Weapon::Mg => self.g_machine_gun_refire,
@@ -991,6 +1929,163 @@ pub enum Hardpoint {
     Turret,
 }
 
+/// What the secondary fire button does with the current weapon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum AltFire {
+    None,
+    /// Detonate the guided missile the player is currently controlling.
+    DetonateGm,
+    /// Detonate all of the player's cluster bombs in the air.
+    DetonateCb,
+    /// Deploy a smoke screen behind the vehicle, see `g_smoke_*`.
+    Smoke,
+    /// Throw out flares which distract homing missiles, see `g_flare_*`.
+    Flares,
+    /// Drop a mine behind the vehicle like the mine key, see `g_mine_*`.
+    DropMine,
+}
+
+/// What else happens when a vehicle self destructs, see `g_<vehicle>_self_destruct`.
+///
+/// The vehicle always explodes and damages everything around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum SelfDestruct {
+    /// A bigger explosion, see `g_self_destruct_blast_factor`.
+    Blast,
+    /// A ring of cluster bombs, see `g_self_destruct_submunitions_*`.
+    Submunitions,
+    /// Rockets forward and backward.
+    Rockets,
+}
+
+/// What controls the turret, see `g_<vehicle>_turret_slaved`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum TurretSlaved {
+    /// Only the player turns it.
+    Free,
+    /// Locked facing forward, turret input does nothing.
+    Hull,
+    /// Follows the direction of travel, turret input overrides it for a while.
+    Velocity,
+}
+
+/// Preset for how expensive cosmetic effects can be, see `EffectsBudget`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum EffectsQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl EffectsQuality {
+    /// One level down, `None` if already the lowest.
+    pub fn lower(self) -> Option<Self> {
+        match self {
+            EffectsQuality::Low => None,
+            EffectsQuality::Medium => Some(EffectsQuality::Low),
+            EffectsQuality::High => Some(EffectsQuality::Medium),
+        }
+    }
+}
+
+/// What a turret slaved to velocity does while reversing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum SlavedReverse {
+    /// Face opposite to the direction of travel so it doesn't flip around.
+    Track,
+    /// Stay where it is until driving forward again.
+    Hold,
+}
+
+/// How to draw the crosshair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum CrosshairShape {
+    Cross,
+    Circle,
+    Dot,
+}
+
+/// What it looks like when a projectile hits a surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum ImpactEffect {
+    None,
+    Sparks,
+    Dust,
+    Splash,
+}
+
+/// How vehicles look while materializing after spawning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum SpawnEffect {
+    None,
+    /// Grow from nothing to full size.
+    Scale,
+    /// Fade in from fully transparent.
+    Fade,
+    /// A ring shrinks onto the vehicle.
+    Ring,
+}
+
+/// How to draw machine gun bullets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum MgStyle {
+    /// A single line of fixed length.
+    Line,
+    /// Like the original RecWar - bright head, fading tail which gets longer with speed.
+    Tracer,
+}
+
+/// What to do with projectiles whose owner has left the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum OrphanProjectiles {
+    /// Remove them immediately, without exploding.
+    Remove,
+    /// Let them fly and explode as usual.
+    /// Kills are credited to the world, see `GameState::world_score`.
+    Keep,
+}
+
+/// What happens when the time limit is reached and the lead is tied, see `match_flow`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display, Deserialize, Serialize)]
+#[strum(ascii_case_insensitive)]
+pub enum OvertimeMode {
+    /// The match ends in a draw.
+    Off,
+    /// The next kill of an enemy wins.
+    SuddenDeath,
+    /// Play on while damage keeps growing until someone takes the lead,
+    /// see `g_overtime_escalation_interval`.
+    Escalation,
+}
+
+/// What a homing missile does after searching for a new target for `g_homing_missile_search_time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum HmLost {
+    /// Explode where it is.
+    Detonate,
+    /// Keep flying straight without looking for targets until it hits something.
+    Inert,
+}
+
+/// How to read and show angle cvars as text. They're always stored in radians.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum AngleUnit {
+    Rad,
+    Deg,
+}
+
 /// Various options how to handle different physics/gamelogic and rendering framerates.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
 #[strum(ascii_case_insensitive)]
@@ -1022,6 +2117,12 @@ pub struct MovementStats {
     pub accel_forward: f64,
     pub friction_const: f64,
     pub friction_linear: f64,
+    /// Below this speed, vehicles with a `pivot_turn_rate` turn in place.
+    pub pivot_speed_max: f64,
+    /// Tracked vehicles can spin their tracks in opposite directions
+    /// and pivot in place at this rate (rad/s) when (almost) stationary.
+    /// Zero disables pivoting and uses the regular turning model.
+    pub pivot_turn_rate: f64,
     pub speed_max: f64,
     pub steering_car: f64,
     pub turn_effectiveness: f64,
@@ -1031,4 +2132,11 @@ pub struct MovementStats {
     pub turn_rate_max: f64,
 }
 
+impl MovementStats {
+    /// Whether a vehicle moving at `vel` turns in place instead of using the regular turning model.
+    pub fn pivots(&self, vel: Vec2f) -> bool {
+        self.pivot_turn_rate > 0.0 && vel.magnitude() < self.pivot_speed_max
+    }
+}
+
 //pub fn load_cvars
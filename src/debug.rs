@@ -42,6 +42,11 @@
 //! LATER How does sending logs from sv to cl interact with cl vs sv framerates?
 //! LATER Add usage examples
 
+// Implementation note: without the `debug_overlays` feature, the thread locals
+// which store texts and shapes don't exist and the text and shape macros expand
+// to a closure which is never called. This way the arguments are still type checked
+// and count as used but the formatting code doesn't end up in the binary.
+
 // Implementation note: the macros should be usable
 // in expression position, e.g. in match statements - see tests.
 // This means they shouldn't end with semicolons
@@ -68,7 +73,7 @@ use crate::{
 #[macro_export]
 macro_rules! dbg_logf {
     () => {
-        dbg_logf!("")
+        $crate::dbg_logf!("")
     };
     ($($t:tt)*) => {{
         let msg = format!($($t)*);
@@ -81,7 +86,7 @@ macro_rules! dbg_logf {
 macro_rules! dbg_logd {
     ($($e:expr),*) => {{
         let s = $crate::__format_pairs!($($e),*);
-        dbg_logf!("[{}:{}] {}", file!(), line!(), s);
+        $crate::dbg_logf!("[{}:{}] {}", file!(), line!(), s);
     }};
 }
 
@@ -91,14 +96,19 @@ macro_rules! dbg_logd {
 #[macro_export]
 macro_rules! dbg_textf {
     () => {
-        dbg_textf!("")
+        $crate::dbg_textf!("")
     };
     ($($t:tt)*) => {{
-        let msg = format!($($t)*);
-        let text = format!("{} {}", $crate::debug::endpoint_name(), msg);
-        $crate::debug::DEBUG_TEXTS.with_borrow_mut(|texts| {
-            texts.push(text);
-        });
+        #[cfg(feature = "debug_overlays")]
+        {
+            let msg = format!($($t)*);
+            let text = format!("{} {}", $crate::debug::endpoint_name(), msg);
+            $crate::debug::DEBUG_TEXTS.with_borrow_mut(|texts| {
+                texts.push(text);
+            });
+        }
+        #[cfg(not(feature = "debug_overlays"))]
+        let _ = || format!($($t)*);
     }};
 }
 
@@ -107,10 +117,9 @@ macro_rules! dbg_textf {
 /// Useful for printing debug info each frame.
 #[macro_export]
 macro_rules! dbg_textd {
-    ($($e:expr),*) => {{
-        let s = $crate::__format_pairs!($($e),*);
-        dbg_textf!("[{}:{}] {}", file!(), line!(), s);
-    }};
+    ($($e:expr),*) => {
+        $crate::dbg_textf!("[{}:{}] {}", file!(), line!(), $crate::__format_pairs!($($e),*))
+    };
 }
 
 /// Print text onto the screen at the given world coordinates.
@@ -119,11 +128,18 @@ macro_rules! dbg_textd {
 #[macro_export]
 macro_rules! dbg_world_textf {
     ($pos:expr, $($t:tt)*) => {{
-        let msg = format!($($t)*);
-        let text = $crate::debug::details::WorldText::new($pos, msg);
-        $crate::debug::DEBUG_TEXTS_WORLD.with_borrow_mut(|texts| {
-            texts.push(text);
-        });
+        #[cfg(feature = "debug_overlays")]
+        {
+            let msg = format!($($t)*);
+            let text = $crate::debug::details::WorldText::new($pos, msg);
+            $crate::debug::DEBUG_TEXTS_WORLD.with_borrow_mut(|texts| {
+                texts.push(text);
+            });
+        }
+        #[cfg(not(feature = "debug_overlays"))]
+        let _ = || {
+            let _ = (&$pos, format!($($t)*));
+        };
     }};
 }
 
@@ -132,10 +148,15 @@ macro_rules! dbg_world_textf {
 /// Useful for printing debug info next to game entities each frame.
 #[macro_export]
 macro_rules! dbg_world_textd {
-    ($pos:expr,$($e:expr),*) => {{
-        let s = $crate::__format_pairs!($($e),*);
-        dbg_world_textf!($pos, "[{}:{}] {}", file!(), line!(), s);
-    }};
+    ($pos:expr,$($e:expr),*) => {
+        $crate::dbg_world_textf!(
+            $pos,
+            "[{}:{}] {}",
+            file!(),
+            line!(),
+            $crate::__format_pairs!($($e),*)
+        )
+    };
 }
 
 /// Private helper to print the name and value of each given variable.
@@ -166,8 +187,15 @@ macro_rules! __format_pairs {
 #[macro_export]
 macro_rules! dbg_line {
     ($begin:expr, $end:expr, $time:expr, $color:expr) => {{
-        #[allow(trivial_numeric_casts)]
-        $crate::debug::details::debug_line($begin, $end, $time as fl, $color);
+        #[cfg(feature = "debug_overlays")]
+        {
+            #[allow(trivial_numeric_casts)]
+            $crate::debug::details::debug_line($begin, $end, $time as $crate::prelude::fl, $color);
+        }
+        #[cfg(not(feature = "debug_overlays"))]
+        let _ = || {
+            let _ = (&$begin, &$end, &$time, &$color);
+        };
     }};
     ($begin:expr, $end:expr, $time:expr) => {
         $crate::dbg_line!($begin, $end, $time, $crate::debug::endpoint_color())
@@ -184,8 +212,15 @@ macro_rules! dbg_line {
 #[macro_export]
 macro_rules! dbg_arrow {
     ($begin:expr, $dir:expr, $time:expr, $color:expr) => {{
-        #[allow(trivial_numeric_casts)]
-        $crate::debug::details::debug_arrow($begin, $dir, $time as fl, $color);
+        #[cfg(feature = "debug_overlays")]
+        {
+            #[allow(trivial_numeric_casts)]
+            $crate::debug::details::debug_arrow($begin, $dir, $time as $crate::prelude::fl, $color);
+        }
+        #[cfg(not(feature = "debug_overlays"))]
+        let _ = || {
+            let _ = (&$begin, &$dir, &$time, &$color);
+        };
     }};
     ($begin:expr, $dir:expr, $time:expr) => {
         $crate::dbg_arrow!($begin, $dir, $time, $crate::debug::endpoint_color())
@@ -202,8 +237,15 @@ macro_rules! dbg_arrow {
 #[macro_export]
 macro_rules! dbg_cross {
     ($point:expr, $time:expr, $color:expr) => {{
-        #[allow(trivial_numeric_casts)]
-        $crate::debug::details::debug_cross($point, $time as fl, $color);
+        #[cfg(feature = "debug_overlays")]
+        {
+            #[allow(trivial_numeric_casts)]
+            $crate::debug::details::debug_cross($point, $time as $crate::prelude::fl, $color);
+        }
+        #[cfg(not(feature = "debug_overlays"))]
+        let _ = || {
+            let _ = (&$point, &$time, &$color);
+        };
     }};
     ($point:expr, $time:expr) => {
         $crate::dbg_cross!($point, $time, $crate::debug::endpoint_color())
@@ -217,8 +259,20 @@ macro_rules! dbg_cross {
 #[macro_export]
 macro_rules! dbg_rot {
     ($point:expr, $rot:expr, $time:expr, $scale:expr) => {{
-        #[allow(trivial_numeric_casts)]
-        $crate::debug::details::debug_rot($point, $rot, $time as fl, $scale as fl);
+        #[cfg(feature = "debug_overlays")]
+        {
+            #[allow(trivial_numeric_casts)]
+            $crate::debug::details::debug_rot(
+                $point,
+                $rot,
+                $time as $crate::prelude::fl,
+                $scale as $crate::prelude::fl,
+            );
+        }
+        #[cfg(not(feature = "debug_overlays"))]
+        let _ = || {
+            let _ = (&$point, &$rot, &$time, &$scale);
+        };
     }};
     ($point:expr, $rot:expr, $time:expr) => {
         $crate::dbg_rot!($point, $rot, 0.0, 1.0)
@@ -242,14 +296,14 @@ macro_rules! static_assert {
 macro_rules! soft_assert {
     // The matchers are the same as in stdlib's assert.
     ($cond:expr $(,)?) => {
-        soft_assert!($cond, stringify!($cond))
+        $crate::soft_assert!($cond, stringify!($cond))
     };
     ($cond:expr, $($arg:tt)+) => {
         // Using a match to extend lifetimes, see soft_assert_eq for more details.
         match (&$cond) {
             cond_val => {
                 if !*cond_val {
-                    dbg_logf!("[ERROR]: soft_assert failed: {}, {}:{}:{}", format!($($arg)+), file!(), line!(), column!());
+                    $crate::dbg_logf!("[ERROR]: soft_assert failed: {}, {}:{}:{}", format!($($arg)+), file!(), line!(), column!());
                 }
             }
         }
@@ -266,7 +320,7 @@ macro_rules! soft_assert_eq {
     //  Consider changing format to match stdlib 1.73.0:
     //  https://blog.rust-lang.org/2023/10/05/Rust-1.73.0.html#cleaner-panic-messages
     ($left:expr, $right:expr $(,)?) => {
-        soft_assert_eq!($left, $right, "`{} == {}`", stringify!($left), stringify!($right))
+        $crate::soft_assert_eq!($left, $right, "`{} == {}`", stringify!($left), stringify!($right))
     };
     ($left:expr, $right:expr, $($arg:tt)+) => {
         // This is based on the impl of `assert_eq!` in stdlib.
@@ -274,7 +328,7 @@ macro_rules! soft_assert_eq {
         match (&$left, &$right) {
             (left_val, right_val) => {
                 if !(*left_val == *right_val) {
-                    dbg_logf!("[ERROR]: soft_assert_eq failed: {}, left: {:?}, right {:?}, {}:{}:{}",
+                    $crate::dbg_logf!("[ERROR]: soft_assert_eq failed: {}, left: {:?}, right {:?}, {}:{}:{}",
                         format!($($arg)+), &*left_val, &*right_val, file!(), line!(), column!()
                     )
                 }
@@ -298,13 +352,13 @@ macro_rules! soft_assert_ne {
     //   left: `1`,
     //  right: `1`: test', src/main.rs:7:5
     ($left:expr, $right:expr $(,)?) => {
-        soft_assert_ne!($left, $right, "`{} != {}`", stringify!($left), stringify!($right))
+        $crate::soft_assert_ne!($left, $right, "`{} != {}`", stringify!($left), stringify!($right))
     };
     ($left:expr, $right:expr, $($arg:tt)+) => {
         match (&$left, &$right) {
             (left_val, right_val) => {
                 if !(*left_val != *right_val) {
-                    dbg_logf!("[ERROR]: soft_assert_ne failed: {}, left: {:?}, right {:?}, {}:{}:{}",
+                    $crate::dbg_logf!("[ERROR]: soft_assert_ne failed: {}, left: {:?}, right {:?}, {}:{}:{}",
                         format!($($arg)+), &*left_val, &*right_val, file!(), line!(), column!()
                     )
                 }
@@ -324,13 +378,13 @@ macro_rules! soft_assert_ne {
 macro_rules! soft_unreachable {
     () => {
         {
-            dbg_logf!("[ERROR]: soft_unreachable {}:{}:{}", file!(), line!(), column!());
+            $crate::dbg_logf!("[ERROR]: soft_unreachable {}:{}:{}", file!(), line!(), column!());
             return Default::default();
         }
     };
     ($($arg:tt)+) => {
         {
-            dbg_logf!("[ERROR]: soft_unreachable: {}, {}:{}:{}", format!($($arg)+), file!(), line!(), column!());
+            $crate::dbg_logf!("[ERROR]: soft_unreachable: {}, {}:{}:{}", format!($($arg)+), file!(), line!(), column!());
             return Default::default();
         }
     };
@@ -384,7 +438,59 @@ impl<T: Default, E: Debug> SoftUnwrap for Result<T, E> {
     }
 }
 
-// LATER soft accessors for Vecs and generational arenas
+/// Soft accessors for generational arenas.
+///
+/// Entity handles can go stale when the entity is removed
+/// while something else still refers to it (e.g. a projectile's owner disconnected).
+/// Instead of panicking, these log the handle, the entity type and the caller
+/// to stdout and the debug text buffer and return `None` so the caller can skip the entity.
+///
+/// With `d_strict` they panic instead so the bug gets noticed during development.
+pub trait SoftArena<T> {
+    #[track_caller]
+    fn soft_get(&self, handle: Index) -> Option<&T>;
+
+    #[track_caller]
+    fn soft_get_mut(&mut self, handle: Index) -> Option<&mut T>;
+}
+
+impl<T> SoftArena<T> for Arena<T> {
+    fn soft_get(&self, handle: Index) -> Option<&T> {
+        let ret = self.get(handle);
+        if ret.is_none() {
+            stale_handle::<T>(handle);
+        }
+        ret
+    }
+
+    fn soft_get_mut(&mut self, handle: Index) -> Option<&mut T> {
+        let ret = self.get_mut(handle);
+        if ret.is_none() {
+            stale_handle::<T>(handle);
+        }
+        ret
+    }
+}
+
+#[track_caller]
+fn stale_handle<T>(handle: Index) {
+    let loc = std::panic::Location::caller();
+    let msg = format!(
+        "stale handle {:?} to {}, {}:{}:{}",
+        handle,
+        std::any::type_name::<T>(),
+        loc.file(),
+        loc.line(),
+        loc.column()
+    );
+    if strict() {
+        panic!("{msg} (d_strict is on)");
+    }
+    dbg_logf!("[ERROR]: {msg}");
+    dbg_textf!("[ERROR]: {msg}");
+}
+
+// LATER soft accessors for Vecs
 
 /// Extension trait for debugging iterators.
 ///
@@ -399,6 +505,7 @@ pub trait DbgIterator: Iterator + Sized {
     ///
     /// # Examples
     /// ```rust
+    /// # use rec_wars::prelude::*;
     /// for x in [1, 2, 3].iter().dbg_count_log("element count") {}
     /// ```
     fn dbg_count_log(self, msg: impl AsRef<str>) -> DbgCounter<Self, Box<dyn FnMut(usize, bool)>> {
@@ -422,6 +529,7 @@ pub trait DbgIterator: Iterator + Sized {
     ///
     /// # Examples
     /// ```rust
+    /// # use rec_wars::prelude::*;
     /// for x in [1, 2, 3].iter().dbg_count_text("element count") {}
     /// ```
     fn dbg_count_text(self, msg: impl AsRef<str>) -> DbgCounter<Self, Box<dyn FnMut(usize, bool)>> {
@@ -522,6 +630,11 @@ thread_local! {
 
     static DEBUG_GAME_TIME: Cell<fl> = const { Cell::new(-1.0) };
 
+    static DEBUG_STRICT: Cell<bool> = const { Cell::new(cfg!(debug_assertions)) };
+}
+
+#[cfg(feature = "debug_overlays")]
+thread_local! {
     pub static DEBUG_TEXTS: RefCell<Vec<String>> = RefCell::new(Vec::new());
     pub static DEBUG_TEXTS_WORLD: RefCell<Vec<WorldText>> = RefCell::new(Vec::new());
     pub static DEBUG_SHAPES: RefCell<Vec<DebugShape>> = RefCell::new(Vec::new());
@@ -565,10 +678,57 @@ pub fn game_time() -> fl {
     DEBUG_GAME_TIME.get()
 }
 
-pub fn clear_expired() {
+/// Whether soft error handling (e.g. `SoftArena`) should panic instead.
+/// Set from the `d_strict` cvar.
+pub fn set_strict(strict: bool) {
+    DEBUG_STRICT.set(strict);
+}
+
+pub fn strict() -> bool {
+    DEBUG_STRICT.get()
+}
+
+/// Age debug shapes by `dt` of game time and remove those which expired.
+#[cfg(feature = "debug_overlays")]
+pub fn clear_expired(dt: fl) {
     DEBUG_TEXTS.with_borrow_mut(|texts| texts.clear());
     DEBUG_TEXTS_WORLD.with_borrow_mut(|texts| texts.clear());
-    DEBUG_SHAPES.with_borrow_mut(|shapes| shapes.retain(|shape| shape.time > 0.0));
+    DEBUG_SHAPES.with_borrow_mut(|shapes| {
+        for shape in shapes.iter_mut() {
+            shape.time -= dt;
+        }
+        shapes.retain(|shape| shape.time > 0.0);
+    });
+}
+
+#[cfg(not(feature = "debug_overlays"))]
+pub fn clear_expired(_dt: fl) {}
+
+/// Take the texts and shapes added since the last call, e.g. to send them to clients.
+#[cfg(feature = "debug_overlays")]
+pub fn take_overlays() -> (Vec<String>, Vec<WorldText>, Vec<DebugShape>) {
+    (
+        DEBUG_TEXTS.take(),
+        DEBUG_TEXTS_WORLD.take(),
+        DEBUG_SHAPES.take(),
+    )
+}
+
+#[cfg(not(feature = "debug_overlays"))]
+pub fn take_overlays() -> (Vec<String>, Vec<WorldText>, Vec<DebugShape>) {
+    (Vec::new(), Vec::new(), Vec::new())
+}
+
+/// Add texts and shapes received from the server to the local ones.
+pub fn extend_overlays(texts: Vec<String>, texts_world: Vec<WorldText>, shapes: Vec<DebugShape>) {
+    #[cfg(feature = "debug_overlays")]
+    {
+        DEBUG_TEXTS.with_borrow_mut(|all| all.extend(texts));
+        DEBUG_TEXTS_WORLD.with_borrow_mut(|all| all.extend(texts_world));
+        DEBUG_SHAPES.with_borrow_mut(|all| all.extend(shapes));
+    }
+    #[cfg(not(feature = "debug_overlays"))]
+    let _ = (texts, texts_world, shapes);
 }
 
 #[cfg(test)]
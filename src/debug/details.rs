@@ -6,7 +6,9 @@
 
 use vek::Vec2;
 
-use crate::{debug::DEBUG_SHAPES, prelude::*};
+#[cfg(feature = "debug_overlays")]
+use crate::debug::DEBUG_SHAPES;
+use crate::prelude::*;
 
 #[macro_export]
 macro_rules! __println {
@@ -66,30 +68,35 @@ pub struct ColorDef {
 }
 
 /// Helper function, prefer `dbg_line!()` instead.
+#[cfg(feature = "debug_overlays")]
 pub fn debug_line(begin: Vec2f, end: Vec2f, time: f64, color: Color) {
     let shape = Shape::Line { begin, end };
     debug_shape(shape, time, color);
 }
 
 /// Helper function, prefer `dbg_arrow!()` instead.
+#[cfg(feature = "debug_overlays")]
 pub fn debug_arrow(begin: Vec2f, dir: Vec2f, time: f64, color: Color) {
     let shape = Shape::Arrow { begin, dir };
     debug_shape(shape, time, color);
 }
 
 /// Helper function, prefer `dbg_cross!()` instead.
+#[cfg(feature = "debug_overlays")]
 pub fn debug_cross(point: Vec2f, time: f64, color: Color) {
     let shape = Shape::Cross { point };
     debug_shape(shape, time, color);
 }
 
 /// Helper function, prefer `dbg_rot!()` instead.
+#[cfg(feature = "debug_overlays")]
 pub fn debug_rot(point: Vec2f, rot: f64, time: f64, scale: f64) {
     let shape = Shape::Rot { point, rot, scale };
     // Color is not used
     debug_shape(shape, time, WHITE);
 }
 
+#[cfg(feature = "debug_overlays")]
 fn debug_shape(shape: Shape, time: f64, color: Color) {
     DEBUG_SHAPES.with_borrow_mut(|shapes| {
         let shape = DebugShape { shape, time, color };
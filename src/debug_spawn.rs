@@ -0,0 +1,375 @@
+//! Commands for setting up test scenarios - entities at exact positions, wrecks where you want them.
+//!
+//! There's no console to type them into, they're methods on `ServerFrameCtx`
+//! with wrappers on `Sim` for embedders and tests.
+//! They go through the same spawn paths as the gamelogic so the results behave like organic entities
+//! and return stable ids (see `stable_id`) because handles get reused.
+//!
+//! Everything here requires `d_cheats`, otherwise the commands log a warning and do nothing.
+
+use crate::prelude::*;
+
+impl ServerFrameCtx<'_> {
+    fn cheats_allowed(&self, command: &str) -> bool {
+        if !self.cvars.d_cheats {
+            dbg_logf!("WARNING: {command} requires d_cheats");
+        }
+        self.cvars.d_cheats
+    }
+
+    /// Spawn a vehicle owned by a new placeholder player, optionally already destroyed.
+    ///
+    /// The player has no input so the vehicle stays where it was put
+    /// and a destroyed one stays a wreck.
+    /// Returns the vehicle's id.
+    pub fn debug_spawn_vehicle(
+        &mut self,
+        pos: Vec2f,
+        angle: f64,
+        veh_type: VehicleType,
+        destroyed: bool,
+        team: Option<u32>,
+    ) -> Option<u32> {
+        if !self.cheats_allowed("debug_spawn_vehicle") {
+            return None;
+        }
+
+        let name = format!("debug {}", self.sg.debug_players.len() + 1);
+        let mut player = Player::new(name, ClientType::Local);
+        player.team = team;
+        let player_handle = self.gs.players.insert(player);
+        let player_id = self.gs.player_ids.assign(player_handle);
+        self.sg.debug_players.insert(player_handle);
+
+        let player = &self.gs.players[player_handle];
+        let player_init = PlayerInit {
+            index: player_handle.slot(),
+            id: player_id,
+            name: player.name.clone(),
+            score: player.score.clone(),
+            color: player.color,
+            team: player.team,
+        };
+        self.net_send_all(ServerMessage::AddPlayer(player_init));
+
+        let vehicle_handle = self.spawn_vehicle_at(player_handle, pos, angle, veh_type);
+        if destroyed {
+            self.damage(player_handle, vehicle_handle, f64::MAX, None, None);
+        }
+        self.gs.vehicle_ids.id(vehicle_handle)
+    }
+
+    /// Spawn a projectile as if `owner` (a player id) fired it from `pos`.
+    ///
+    /// CBs get the base `g_cluster_bomb_time` fuse, guided missiles aren't guided by anyone.
+    /// Returns the projectile's id.
+    pub fn debug_spawn_projectile(
+        &mut self,
+        weapon: Weapon,
+        pos: Vec2f,
+        vel: Vec2f,
+        owner: u32,
+    ) -> Option<u32> {
+        if !self.cheats_allowed("debug_spawn_projectile") {
+            return None;
+        }
+        let Some(owner_handle) = self.gs.player_ids.handle(owner) else {
+            dbg_logf!("WARNING: debug_spawn_projectile: no player with id {owner}");
+            return None;
+        };
+
+        let explode_time = if weapon == Weapon::Cb {
+            self.gs.game_time + self.cvars.g_cluster_bomb_time
+        } else {
+            f64::MAX
+        };
+        let projectile = Projectile {
+            weapon,
+            pos,
+            vel,
+            angle: vel.to_angle().rem_euclid(2.0 * PI),
+            turn_rate: 0.0,
+            spawn_time: self.gs.game_time,
+            explode_time,
+            owner: owner_handle,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let handle = self.gs.projectiles.insert(projectile);
+        self.projectiles_spawned(vec![handle]);
+        self.gs.projectile_ids.id(handle)
+    }
+
+    /// Remove projectiles, mines and vehicles from `debug_spawn_vehicle` within `radius` of `pos`.
+    ///
+    /// Projectiles and mines disappear without exploding.
+    /// Vehicles of real players and bots are left alone, move them with `debug_set_vehicle_state`.
+    /// Returns how many entities were removed.
+    pub fn debug_remove_at(&mut self, pos: Vec2f, radius: f64) -> usize {
+        if !self.cheats_allowed("debug_remove_at") {
+            return 0;
+        }
+
+        let projectiles: FnvHashSet<_> = self
+            .gs
+            .projectiles
+            .iter()
+            .filter(|(_, proj)| proj.pos.distance(pos) <= radius)
+            .map(|(handle, _)| handle)
+            .collect();
+        for &handle in &projectiles {
+            self.gs.projectiles.remove(handle);
+            self.gs.projectile_ids.remove(handle);
+            self.gs.rail_hits.remove(&handle);
+            let msg = ServerMessage::DestroyProjectile {
+                index: handle.slot(),
+                impact: None,
+            };
+            self.net_send_all(msg);
+        }
+        for (_, vehicle) in self.gs.vehicles.iter_mut() {
+            vehicle.hms.retain(|hm| !projectiles.contains(hm));
+        }
+        for (_, player) in self.gs.players.iter_mut() {
+            if player
+                .guided_missile
+                .is_some_and(|gm| projectiles.contains(&gm))
+            {
+                player.guided_missile = None;
+            }
+        }
+
+        let mines: Vec<_> = self
+            .gs
+            .mines
+            .iter()
+            .filter(|(_, mine)| mine.pos.distance(pos) <= radius)
+            .map(|(handle, _)| handle)
+            .collect();
+        for &handle in &mines {
+            self.gs.mines.remove(handle);
+            let msg = ServerMessage::DestroyMine {
+                index: handle.slot(),
+            };
+            self.net_send_all(msg);
+        }
+
+        let players: Vec<_> = self
+            .sg
+            .debug_players
+            .iter()
+            .copied()
+            .filter(|&player_handle| {
+                // Stale if the player was removed some other way.
+                let Some(player) = self.gs.players.get(player_handle) else {
+                    return false;
+                };
+                let vehicle = player.vehicle.and_then(|h| self.gs.vehicles.get(h));
+                vehicle.is_some_and(|vehicle| vehicle.pos.distance(pos) <= radius)
+            })
+            .collect();
+        for &player_handle in &players {
+            self.sg.debug_players.remove(&player_handle);
+            self.release_player(player_handle);
+            self.remove_player(player_handle);
+            let msg = ServerMessage::RemovePlayer {
+                index: player_handle.slot(),
+            };
+            self.net_send_all(msg);
+        }
+
+        projectiles.len() + mines.len() + players.len()
+    }
+
+    /// Move the vehicle of `player` (a player id) and set its health in hit points.
+    ///
+    /// Health 0 or less destroys the vehicle the same way as self-destruct.
+    /// Wrecks can be moved but not brought back to life.
+    /// Returns the vehicle's id.
+    pub fn debug_set_vehicle_state(
+        &mut self,
+        player: u32,
+        hp: f64,
+        pos: Vec2f,
+        angle: f64,
+    ) -> Option<u32> {
+        if !self.cheats_allowed("debug_set_vehicle_state") {
+            return None;
+        }
+        let Some(player_handle) = self.gs.player_ids.handle(player) else {
+            dbg_logf!("WARNING: debug_set_vehicle_state: no player with id {player}");
+            return None;
+        };
+
+        let vehicle_handle = self.gs.players[player_handle].vehicle;
+        let Some(vehicle_handle) = vehicle_handle.filter(|&h| self.gs.vehicles.contains(h)) else {
+            dbg_logf!("WARNING: debug_set_vehicle_state: player {player} has no vehicle");
+            return None;
+        };
+        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+        vehicle.pos = pos;
+        vehicle.angle = angle.rem_euclid(2.0 * PI);
+        if !vehicle.destroyed() {
+            if hp > 0.0 {
+                vehicle.hp_fraction = hp / self.cvars.g_vehicle_hp(vehicle.veh_type);
+            } else {
+                self.damage(player_handle, vehicle_handle, f64::MAX, None, None);
+            }
+        }
+        self.gs.vehicle_ids.id(vehicle_handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn cvars(cheats: bool) -> Cvars {
+        Cvars {
+            d_cheats: cheats,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_debug_scenario() {
+        let cvars = cvars(true);
+        let mut server = test_utils::server(&cvars, test_utils::open_map(8, 4, ""));
+        let mut ctx = server.ctx(&cvars);
+
+        // Three wrecks in a row and a tank facing down.
+        for x in [100.0, 150.0, 200.0] {
+            let pos = Vec2f::new(x, 64.0);
+            ctx.debug_spawn_vehicle(pos, 0.0, VehicleType::Hovercraft, true, None)
+                .unwrap();
+        }
+        let tank_id = ctx
+            .debug_spawn_vehicle(v!(300 150), PI / 2.0, VehicleType::Tank, false, Some(1))
+            .unwrap();
+
+        let gs = &ctx.gs;
+        assert_eq!(gs.players.len(), 4);
+        assert_eq!(gs.vehicles.len(), 4);
+        let wrecks = gs.vehicles.iter().filter(|(_, veh)| veh.destroyed());
+        assert_eq!(wrecks.count(), 3);
+        let tank_handle = gs.vehicle_ids.handle(tank_id).unwrap();
+        let tank = &gs.vehicles[tank_handle];
+        assert_eq!(tank.pos, v!(300 150));
+        assert_eq!(tank.angle, PI / 2.0);
+        assert_eq!(tank.veh_type, VehicleType::Tank);
+        assert_eq!(tank.hp_fraction, 1.0);
+        let tank_owner = tank.owner;
+        assert_eq!(gs.players[tank_owner].team, Some(1));
+        assert_eq!(gs.players[tank_owner].vehicle, Some(tank_handle));
+
+        // Nobody respawns the wrecks.
+        ctx.gs.game_time = 10.0;
+        ctx.sys_respawning();
+        let wrecks = ctx.gs.vehicles.iter().filter(|(_, veh)| veh.destroyed());
+        assert_eq!(wrecks.count(), 3);
+
+        let owner_id = ctx.gs.player_ids.id(tank_owner).unwrap();
+        let vehicle_id = ctx.debug_set_vehicle_state(owner_id, 50.0, v!(320 160), 0.5);
+        assert_eq!(vehicle_id, Some(tank_id));
+        let tank = &ctx.gs.vehicles[tank_handle];
+        assert_eq!(tank.pos, v!(320 160));
+        assert_eq!(tank.angle, 0.5);
+        assert_eq!(
+            tank.hp_fraction,
+            50.0 / cvars.g_vehicle_hp(VehicleType::Tank)
+        );
+
+        let rocket_id = ctx
+            .debug_spawn_projectile(Weapon::Rockets, v!(50 200), v!(0 100), owner_id)
+            .unwrap();
+        let rocket_handle = ctx.gs.projectile_ids.handle(rocket_id).unwrap();
+        let rocket = &ctx.gs.projectiles[rocket_handle];
+        assert_eq!(rocket.weapon, Weapon::Rockets);
+        assert_eq!(rocket.pos, v!(50 200));
+        assert_eq!(rocket.vel, v!(0 100));
+        assert_eq!(rocket.angle, PI / 2.0);
+        assert_eq!(rocket.owner, tank_owner);
+        assert_eq!(rocket.team, Some(1));
+
+        // Hp 0 destroys through the normal path.
+        ctx.debug_set_vehicle_state(owner_id, 0.0, v!(320 160), 0.5);
+        assert!(ctx.gs.vehicles[tank_handle].destroyed());
+        assert_eq!(ctx.gs.players[tank_owner].score.deaths, 1);
+    }
+
+    #[test]
+    fn test_debug_remove_at() {
+        let cvars = cvars(true);
+        let mut server = test_utils::server(&cvars, test_utils::open_map(8, 4, ""));
+        let mut ctx = server.ctx(&cvars);
+        let human = ctx
+            .gs
+            .players
+            .insert(Player::new("human".to_owned(), ClientType::Local));
+        let human_id = ctx.gs.player_ids.assign(human);
+        ctx.spawn_vehicle_at(human, v!(110 64), 0.0, VehicleType::Tank);
+
+        ctx.debug_spawn_vehicle(v!(100 64), 0.0, VehicleType::Tank, true, None);
+        ctx.debug_spawn_vehicle(v!(400 64), 0.0, VehicleType::Tank, true, None);
+        ctx.debug_spawn_projectile(Weapon::Mg, v!(90 70), v!(0 0), human_id);
+        ctx.debug_spawn_projectile(Weapon::Mg, v!(400 200), v!(0 0), human_id);
+
+        let removed = ctx.debug_remove_at(v!(100 64), 30.0);
+        assert_eq!(removed, 2);
+        // The human's vehicle stays.
+        assert_eq!(ctx.gs.players.len(), 2);
+        assert_eq!(ctx.gs.vehicles.len(), 2);
+        assert_eq!(ctx.gs.projectiles.len(), 1);
+        assert_eq!(ctx.gs.vehicle_ids.len(), 2);
+        assert_eq!(ctx.gs.projectile_ids.len(), 1);
+        assert_eq!(ctx.sg.debug_players.len(), 1);
+    }
+
+    #[test]
+    fn test_debug_needs_cheats() {
+        let cvars = cvars(false);
+        let mut server = test_utils::server(&cvars, test_utils::open_map(8, 4, ""));
+        let mut ctx = server.ctx(&cvars);
+        let human = ctx
+            .gs
+            .players
+            .insert(Player::new("human".to_owned(), ClientType::Local));
+        let human_id = ctx.gs.player_ids.assign(human);
+        ctx.spawn_vehicle_at(human, v!(100 64), 0.0, VehicleType::Tank);
+
+        let vehicle = ctx.debug_spawn_vehicle(v!(200 64), 0.0, VehicleType::Tank, false, None);
+        assert_eq!(vehicle, None);
+        let projectile = ctx.debug_spawn_projectile(Weapon::Mg, v!(90 64), v!(0 0), human_id);
+        assert_eq!(projectile, None);
+        assert_eq!(ctx.debug_remove_at(v!(100 64), 100.0), 0);
+        let state = ctx.debug_set_vehicle_state(human_id, 0.0, v!(300 64), 1.0);
+        assert_eq!(state, None);
+
+        assert_eq!(ctx.gs.players.len(), 1);
+        assert_eq!(ctx.gs.vehicles.len(), 1);
+        assert!(ctx.gs.projectiles.is_empty());
+        let (_, vehicle) = ctx.gs.vehicles.iter().next().unwrap();
+        assert_eq!(vehicle.pos, v!(100 64));
+        assert!(!vehicle.destroyed());
+    }
+
+    #[test]
+    fn test_debug_set_vehicle_state_no_vehicle() {
+        let cvars = cvars(true);
+        let mut server = test_utils::server(&cvars, test_utils::open_map(8, 4, ""));
+        let mut ctx = server.ctx(&cvars);
+        let spectator = ctx
+            .gs
+            .players
+            .insert(Player::new("spectator".to_owned(), ClientType::Local));
+        let spectator_id = ctx.gs.player_ids.assign(spectator);
+
+        let state = ctx.debug_set_vehicle_state(spectator_id, 50.0, v!(100 64), 0.0);
+        assert_eq!(state, None);
+        assert!(ctx.gs.vehicles.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Marks left on walls by projectile impacts - bullet chips from MGs, scorch marks from rockets and GMs.
+//!
+//! All kinds share one buffer capped at `cl_decals_max` (scaled by `EffectsBudget`),
+//! when it's full the oldest decal goes first whatever its kind.
+//! A sustained MG stream would fill it in no time so a new decal is skipped
+//! when one of the same kind is within `cl_decals_dedup_radius`
+//! and bullet chips are limited to `cl_decals_bullets_per_frame`.
+
+use crate::{prelude::*, timed_effects::TimedEffects};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecalKind {
+    BulletChip,
+    Scorch,
+}
+
+impl DecalKind {
+    /// None for weapons which leave no mark.
+    pub fn for_weapon(weapon: Weapon) -> Option<Self> {
+        match weapon {
+            Weapon::Mg => Some(DecalKind::BulletChip),
+            Weapon::Rockets | Weapon::Gm => Some(DecalKind::Scorch),
+            Weapon::Rail | Weapon::Cb | Weapon::Hm | Weapon::Bfg => None,
+        }
+    }
+
+    pub fn lifetime(self, cvars: &Cvars) -> f64 {
+        match self {
+            DecalKind::BulletChip => cvars.cl_decals_bullet_duration,
+            DecalKind::Scorch => cvars.cl_decals_scorch_duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decal {
+    pub kind: DecalKind,
+    pub pos: Vec2f,
+    pub angle: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Decals {
+    pub effects: TimedEffects<Decal>,
+    /// Bullet chips added since the last `cleanup`.
+    bullets_this_frame: usize,
+}
+
+impl Decals {
+    pub fn new(max: usize) -> Self {
+        Self {
+            effects: TimedEffects::new(max),
+            bullets_this_frame: 0,
+        }
+    }
+
+    /// Returns whether the decal was added.
+    pub fn add(&mut self, cvars: &Cvars, decal: Decal, start_time: f64) -> bool {
+        if decal.kind == DecalKind::BulletChip
+            && self.bullets_this_frame >= cvars.cl_decals_bullets_per_frame
+        {
+            return false;
+        }
+        let radius2 = cvars.cl_decals_dedup_radius * cvars.cl_decals_dedup_radius;
+        let duplicate = self.effects.iter_alive(start_time).any(|(other, _)| {
+            other.kind == decal.kind && other.pos.distance_squared(decal.pos) <= radius2
+        });
+        if duplicate {
+            return false;
+        }
+
+        if decal.kind == DecalKind::BulletChip {
+            self.bullets_this_frame += 1;
+        }
+        self.effects
+            .push(decal, start_time, decal.kind.lifetime(cvars));
+        true
+    }
+
+    /// Once per frame - removes expired decals and resets the per-frame cap.
+    pub fn new_frame(&mut self, max: usize, time: f64) {
+        self.effects.max = max;
+        self.effects.cleanup(time);
+        self.bullets_this_frame = 0;
+    }
+}
+
+impl ClientFrameCtx<'_> {
+    /// Mark the wall where a projectile flying with `vel` hit it at `pos`.
+    ///
+    /// Impacts away from walls (vehicles, fuses running out) leave nothing.
+    pub fn spawn_impact_decal(&mut self, weapon: Weapon, pos: Vec2f, vel: Vec2f) {
+        if !self.cvars.cl_decals {
+            return;
+        }
+        let Some(kind) = DecalKind::for_weapon(weapon) else {
+            return;
+        };
+        // Walls are hit slightly inside, this finds the wall, not the tile in front of it.
+        if !self.map.is_wall(pos) {
+            return;
+        }
+        // Pulled back out onto the wall's face so it's not hidden under the wall.
+        let dir = vel.normalized();
+        let pos = pos - dir * self.cvars.cl_decals_offset;
+        // Tilted a bit, no need for real randomness in a cosmetic effect.
+        let angle = dir.to_angle() + (pos.x * 1.7 + pos.y * 2.3).sin() * 0.5;
+        let decal = Decal { kind, pos, angle };
+        self.cg.decals.add(self.cvars, decal, self.gs.game_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cvars() -> Cvars {
+        Cvars {
+            cl_decals_bullet_duration: 5.0,
+            cl_decals_bullets_per_frame: 2,
+            cl_decals_dedup_radius: 3.0,
+            cl_decals_scorch_duration: 10.0,
+            ..Cvars::default()
+        }
+    }
+
+    fn decal(kind: DecalKind, x: f64) -> Decal {
+        Decal {
+            kind,
+            pos: Vec2f::new(x, 0.0),
+            angle: 0.0,
+        }
+    }
+
+    fn kinds_and_xs(decals: &Decals) -> Vec<(DecalKind, f64)> {
+        decals
+            .effects
+            .iter()
+            .map(|effect| (effect.item.kind, effect.item.pos.x))
+            .collect()
+    }
+
+    #[test]
+    fn test_decals_dedup() {
+        let cvars = cvars();
+        let mut decals = Decals::new(100);
+        assert!(decals.add(&cvars, decal(DecalKind::BulletChip, 0.0), 0.0));
+        decals.new_frame(100, 0.1);
+        // Too close to the same kind.
+        assert!(!decals.add(&cvars, decal(DecalKind::BulletChip, 2.0), 0.1));
+        // A different kind or further away is fine.
+        assert!(decals.add(&cvars, decal(DecalKind::Scorch, 2.0), 0.1));
+        assert!(decals.add(&cvars, decal(DecalKind::BulletChip, 4.0), 0.1));
+
+        // Expired decals don't count.
+        decals.new_frame(100, 6.0);
+        assert!(decals.add(&cvars, decal(DecalKind::BulletChip, 0.0), 6.0));
+        assert_eq!(decals.effects.len(), 2);
+    }
+
+    #[test]
+    fn test_decals_bullets_per_frame() {
+        let cvars = cvars();
+        let mut decals = Decals::new(100);
+        for x in [0.0, 10.0, 20.0] {
+            decals.add(&cvars, decal(DecalKind::BulletChip, x), 0.0);
+        }
+        // Scorch marks aren't limited.
+        assert!(decals.add(&cvars, decal(DecalKind::Scorch, 30.0), 0.0));
+        assert_eq!(decals.effects.len(), 3);
+
+        decals.new_frame(100, 0.1);
+        assert!(decals.add(&cvars, decal(DecalKind::BulletChip, 20.0), 0.1));
+        assert_eq!(decals.effects.len(), 4);
+    }
+
+    #[test]
+    fn test_decals_eviction_mixed() {
+        let cvars = Cvars {
+            cl_decals_bullets_per_frame: 100,
+            ..cvars()
+        };
+        let mut decals = Decals::new(3);
+        decals.add(&cvars, decal(DecalKind::Scorch, 0.0), 0.0);
+        decals.add(&cvars, decal(DecalKind::BulletChip, 10.0), 0.0);
+        decals.add(&cvars, decal(DecalKind::BulletChip, 20.0), 0.0);
+        // Oldest first even though scorch marks last longer.
+        decals.add(&cvars, decal(DecalKind::BulletChip, 30.0), 0.0);
+        assert_eq!(
+            kinds_and_xs(&decals),
+            [
+                (DecalKind::BulletChip, 10.0),
+                (DecalKind::BulletChip, 20.0),
+                (DecalKind::BulletChip, 30.0),
+            ]
+        );
+
+        decals.add(&cvars, decal(DecalKind::Scorch, 40.0), 0.0);
+        assert_eq!(
+            kinds_and_xs(&decals),
+            [
+                (DecalKind::BulletChip, 20.0),
+                (DecalKind::BulletChip, 30.0),
+                (DecalKind::Scorch, 40.0),
+            ]
+        );
+
+        // Lowering the limit evicts the oldest too.
+        decals.new_frame(1, 0.0);
+        assert_eq!(kinds_and_xs(&decals), [(DecalKind::Scorch, 40.0)]);
+    }
+}
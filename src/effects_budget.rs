@@ -0,0 +1,337 @@
+//! How much effort goes into cosmetic effects, decided in one place.
+//!
+//! `r_effects_quality` picks a preset and the individual cvars
+//! (`cl_particles_max`, `r_launch_backblast_count`, ...) are multiplied on top of it.
+//! The client computes an `EffectsBudget` at the start of each update
+//! and every site spawning effects asks it instead of reading its own cvars
+//! so new kinds of effects respect the quality setting automatically.
+//!
+//! When rendering is too slow for too long, `EffectsDegrade` lowers the quality
+//! one level at a time, see `r_effects_auto_degrade`.
+
+use crate::prelude::*;
+
+/// Multipliers and switches for one quality level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Preset {
+    particles: f64,
+    surface_effects: f64,
+    decals: f64,
+    explosion_lod_scale: f64,
+    shadows: bool,
+    spawns: f64,
+}
+
+fn preset(quality: EffectsQuality) -> Preset {
+    match quality {
+        EffectsQuality::Low => Preset {
+            particles: 0.25,
+            surface_effects: 0.25,
+            decals: 0.25,
+            explosion_lod_scale: 0.75,
+            shadows: false,
+            spawns: 0.25,
+        },
+        EffectsQuality::Medium => Preset {
+            particles: 0.5,
+            surface_effects: 0.5,
+            decals: 0.5,
+            explosion_lod_scale: 0.0,
+            shadows: true,
+            spawns: 0.5,
+        },
+        EffectsQuality::High => Preset {
+            particles: 1.0,
+            surface_effects: 1.0,
+            decals: 1.0,
+            explosion_lod_scale: 0.0,
+            shadows: true,
+            spawns: 1.0,
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectsBudget {
+    pub quality: EffectsQuality,
+    /// Multiplier for how many particles each effect spawns.
+    pub particle_scale: f64,
+    /// Size of the particle buffer.
+    pub particles_max: usize,
+    /// Marks, dust and spray at most, the oldest disappear first.
+    pub surface_effects_max: usize,
+    /// Decals on walls at most, the oldest disappear first.
+    pub decals_max: usize,
+    /// Explosions smaller than this are drawn as a cheap circle.
+    pub explosion_lod_scale: f64,
+    /// Whether to draw shadows (currently only cluster bombs have them).
+    pub shadows: bool,
+    /// How many more effects can be spawned during this update, see `take`.
+    pub spawns_left: usize,
+}
+
+impl EffectsBudget {
+    pub fn new(cvars: &Cvars, quality: EffectsQuality) -> Self {
+        let preset = preset(quality);
+        let cvar_lod_scale = if cvars.r_explosion_lod {
+            cvars.r_explosion_lod_scale
+        } else {
+            0.0
+        };
+        Self {
+            quality,
+            particle_scale: preset.particles,
+            particles_max: (cvars.cl_particles_max as f64 * preset.particles) as usize,
+            surface_effects_max: (cvars.cl_surface_effects_max as f64 * preset.surface_effects)
+                as usize,
+            decals_max: (cvars.cl_decals_max as f64 * preset.decals) as usize,
+            explosion_lod_scale: cvar_lod_scale.max(preset.explosion_lod_scale),
+            shadows: preset.shadows,
+            spawns_left: (cvars.r_effects_spawns_max as f64 * preset.spawns) as usize,
+        }
+    }
+
+    /// Scale a particle count from cvars.
+    ///
+    /// Never goes to 0 unless `count` is 0 so effects thin out instead of disappearing.
+    pub fn particle_count(&self, count: usize) -> usize {
+        if count == 0 {
+            return 0;
+        }
+        ((count as f64 * self.particle_scale).round() as usize).max(1)
+    }
+
+    /// How many of `wanted` new cosmetic effects can be spawned, they're counted as spawned.
+    pub fn take(&mut self, wanted: usize) -> usize {
+        let allowed = wanted.min(self.spawns_left);
+        self.spawns_left -= allowed;
+        allowed
+    }
+
+    /// Count effects which are spawned no matter what, e.g. explosions,
+    /// so fewer cosmetic ones come after them.
+    pub fn spend(&mut self, count: usize) {
+        self.spawns_left = self.spawns_left.saturating_sub(count);
+    }
+
+    pub fn explosion_uses_lod(&self, scale: f64) -> bool {
+        scale < self.explosion_lod_scale
+    }
+}
+
+/// Lowers effects quality when rendering is too slow, see `r_effects_auto_degrade`.
+///
+/// Rendering has to stay slow for `r_effects_degrade_time` to drop a level
+/// and then get under `r_effects_degrade_hysteresis` of the threshold to reset the timer
+/// so an average hovering right around the threshold doesn't keep restarting it.
+/// Never raises the quality back, the player can change `r_effects_quality` if they want.
+#[derive(Debug, Clone, Default)]
+pub struct EffectsDegrade {
+    /// The quality can't be higher than this after degrading.
+    pub cap: Option<EffectsQuality>,
+    /// Real time when rendering started being too slow.
+    pub slow_since: Option<f64>,
+    /// Real time and the new quality of each degradation.
+    pub history: Vec<(f64, EffectsQuality)>,
+}
+
+impl EffectsDegrade {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn quality(&self, cvars: &Cvars) -> EffectsQuality {
+        match self.cap {
+            Some(cap) => cap.min(cvars.r_effects_quality),
+            None => cvars.r_effects_quality,
+        }
+    }
+
+    /// Feed it the rolling average of draw durations in seconds,
+    /// returns whether it just dropped a level.
+    pub fn update(&mut self, cvars: &Cvars, draw_avg: Option<f64>, real_time: f64) -> bool {
+        if !cvars.r_effects_auto_degrade {
+            self.slow_since = None;
+            return false;
+        }
+        let Some(draw_avg) = draw_avg else {
+            return false;
+        };
+
+        let threshold = cvars.r_effects_degrade_threshold_ms / 1000.0;
+        if draw_avg > threshold {
+            let slow_since = *self.slow_since.get_or_insert(real_time);
+            if real_time - slow_since >= cvars.r_effects_degrade_time {
+                // Start over so the next level has to be just as bad for just as long.
+                self.slow_since = None;
+                if let Some(lower) = self.quality(cvars).lower() {
+                    self.cap = Some(lower);
+                    self.history.push((real_time, lower));
+                    return true;
+                }
+            }
+        } else if draw_avg < threshold * cvars.r_effects_degrade_hysteresis {
+            self.slow_since = None;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_per_level() {
+        let cvars = Cvars::default();
+        let high = EffectsBudget::new(&cvars, EffectsQuality::High);
+        let medium = EffectsBudget::new(&cvars, EffectsQuality::Medium);
+        let low = EffectsBudget::new(&cvars, EffectsQuality::Low);
+
+        assert_eq!(high.particles_max, cvars.cl_particles_max);
+        assert_eq!(high.surface_effects_max, cvars.cl_surface_effects_max);
+        assert_eq!(high.decals_max, cvars.cl_decals_max);
+        assert_eq!(high.spawns_left, cvars.r_effects_spawns_max);
+        assert_eq!(high.particle_count(8), 8);
+        assert!(high.shadows);
+        assert!(!high.explosion_uses_lod(cvars.g_cluster_bomb_explosion_scale));
+
+        assert_eq!(medium.particles_max, cvars.cl_particles_max / 2);
+        assert_eq!(medium.particle_count(8), 4);
+        assert!(medium.shadows);
+
+        assert_eq!(low.particles_max, cvars.cl_particles_max / 4);
+        assert_eq!(low.particle_count(8), 2);
+        assert_eq!(low.particle_count(1), 1);
+        assert_eq!(low.particle_count(0), 0);
+        assert!(!low.shadows);
+        assert!(low.explosion_uses_lod(cvars.g_cluster_bomb_explosion_scale));
+        assert!(!low.explosion_uses_lod(cvars.g_homing_missile_explosion_scale));
+
+        // Every level is at most as expensive as the one above.
+        for (lower, higher) in [(&low, &medium), (&medium, &high)] {
+            assert!(lower.particles_max <= higher.particles_max);
+            assert!(lower.surface_effects_max <= higher.surface_effects_max);
+            assert!(lower.decals_max <= higher.decals_max);
+            assert!(lower.spawns_left <= higher.spawns_left);
+            assert!(lower.explosion_lod_scale >= higher.explosion_lod_scale);
+        }
+    }
+
+    #[test]
+    fn test_cvars_multiply_preset() {
+        let cvars = Cvars {
+            cl_particles_max: 1000,
+            cl_surface_effects_max: 400,
+            r_effects_spawns_max: 100,
+            ..Cvars::default()
+        };
+        let budget = EffectsBudget::new(&cvars, EffectsQuality::Medium);
+        assert_eq!(budget.particles_max, 500);
+        assert_eq!(budget.surface_effects_max, 200);
+        assert_eq!(budget.spawns_left, 50);
+
+        // The cvar threshold applies on top of the preset's, whichever is higher wins.
+        let cvars = Cvars {
+            r_explosion_lod: true,
+            r_explosion_lod_scale: 1.5,
+            ..Cvars::default()
+        };
+        let budget = EffectsBudget::new(&cvars, EffectsQuality::Low);
+        assert!(budget.explosion_uses_lod(1.0));
+        let budget = EffectsBudget::new(&cvars, EffectsQuality::High);
+        assert!(budget.explosion_uses_lod(1.0));
+    }
+
+    #[test]
+    fn test_explosion_lod() {
+        let mut cvars = Cvars {
+            r_explosion_lod: true,
+            r_explosion_lod_scale: 0.5,
+            ..Cvars::default()
+        };
+        let budget = EffectsBudget::new(&cvars, EffectsQuality::High);
+        assert!(budget.explosion_uses_lod(0.25));
+        assert!(!budget.explosion_uses_lod(0.5));
+        assert!(!budget.explosion_uses_lod(1.0));
+
+        cvars.r_explosion_lod = false;
+        let budget = EffectsBudget::new(&cvars, EffectsQuality::High);
+        assert!(!budget.explosion_uses_lod(0.25));
+    }
+
+    #[test]
+    fn test_spawn_cap() {
+        let cvars = Cvars {
+            r_effects_spawns_max: 10,
+            ..Cvars::default()
+        };
+        let mut budget = EffectsBudget::new(&cvars, EffectsQuality::High);
+        assert_eq!(budget.take(4), 4);
+        budget.spend(3);
+        assert_eq!(budget.take(4), 3);
+        assert_eq!(budget.take(1), 0);
+        budget.spend(5);
+        assert_eq!(budget.spawns_left, 0);
+    }
+
+    #[test]
+    fn test_auto_degrade() {
+        let cvars = Cvars {
+            r_effects_auto_degrade: true,
+            r_effects_degrade_hysteresis: 0.8,
+            r_effects_degrade_threshold_ms: 10.0,
+            r_effects_degrade_time: 3.0,
+            r_effects_quality: EffectsQuality::High,
+            ..Cvars::default()
+        };
+        let slow = Some(0.015);
+        let borderline = Some(0.009);
+        let fast = Some(0.005);
+
+        let mut degrade = EffectsDegrade::new();
+        assert!(!degrade.update(&cvars, None, 0.0));
+        assert!(!degrade.update(&cvars, slow, 0.0));
+        assert!(!degrade.update(&cvars, slow, 2.0));
+        // Between the hysteresis and the threshold - the timer keeps going.
+        assert!(!degrade.update(&cvars, borderline, 2.5));
+        assert!(degrade.update(&cvars, slow, 3.0));
+        assert_eq!(degrade.quality(&cvars), EffectsQuality::Medium);
+        assert_eq!(degrade.history, [(3.0, EffectsQuality::Medium)]);
+
+        // The next level needs another full period.
+        assert!(!degrade.update(&cvars, slow, 3.1));
+        assert!(!degrade.update(&cvars, slow, 5.0));
+        // Fast enough resets it.
+        assert!(!degrade.update(&cvars, fast, 5.5));
+        assert!(!degrade.update(&cvars, slow, 6.0));
+        assert!(!degrade.update(&cvars, slow, 8.9));
+        assert!(degrade.update(&cvars, slow, 9.0));
+        assert_eq!(degrade.quality(&cvars), EffectsQuality::Low);
+
+        // Nowhere further to go.
+        assert!(!degrade.update(&cvars, slow, 12.0));
+        assert!(!degrade.update(&cvars, slow, 20.0));
+        assert_eq!(degrade.history.len(), 2);
+
+        // The cap only limits the cvar, it doesn't raise it.
+        let mut degrade = EffectsDegrade::new();
+        degrade.cap = Some(EffectsQuality::Medium);
+        let low = Cvars {
+            r_effects_quality: EffectsQuality::Low,
+            ..Cvars::default()
+        };
+        assert_eq!(degrade.quality(&low), EffectsQuality::Low);
+
+        // Disabled
+        let off = Cvars {
+            r_effects_auto_degrade: false,
+            r_effects_degrade_threshold_ms: 10.0,
+            ..Cvars::default()
+        };
+        let mut degrade = EffectsDegrade::new();
+        assert!(!degrade.update(&off, slow, 0.0));
+        assert!(!degrade.update(&off, slow, 100.0));
+        assert_eq!(degrade.quality(&off), EffectsQuality::High);
+    }
+}
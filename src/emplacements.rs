@@ -0,0 +1,550 @@
+//! Stationary guns placed by the map with `emplacement` metadata lines.
+//!
+//! An emplacement has a fixed base, a turret and one weapon. Unmanned ones go after
+//! the vehicle bots would pick (see `target_score`) within `g_emplacement_ai_range`
+//! and fire once the barrel is within `g_emplacement_ai_fire_angle` of it.
+//! A player whose vehicle is within `g_emplacement_interact_range` can take the controls
+//! with the interact key, their turret and fire keys then go to the emplacement
+//! while the vehicle stands still. Pressing it again lets go,
+//! so does the vehicle dying or getting pushed out of range.
+//!
+//! Shots of unmanned emplacements have no owner (`Index::DANGLING`),
+//! their kills count like those of a player who already left.
+//! Manned ones shoot as their controller.
+//!
+//! Destroyed emplacements stay until the next match, they don't respawn.
+
+use vek::LineSegment2;
+
+use crate::{
+    prelude::*,
+    sys_ai::target_score,
+    systems::{rail_was_inside, turn_turret, weapon_projectiles},
+};
+
+impl ServerFrameCtx<'_> {
+    /// Place the map's emplacements unless `g_emplacements` is off.
+    ///
+    /// Part of match setup, clients get them in `Init`.
+    pub fn spawn_emplacements(&mut self) {
+        self.gs.emplacements.clear();
+        if !self.cvars.g_emplacements {
+            return;
+        }
+        for spot in self.map.emplacements() {
+            let emplacement = Emplacement::new(self.cvars, spot.pos, spot.angle, spot.weapon);
+            self.gs.emplacements.insert(emplacement);
+        }
+    }
+
+    /// Take or leave the controls with the interact key.
+    pub fn sys_emplacement_control(&mut self) {
+        // Letting go first so the same press doesn't take the controls right back.
+        let mut released = Vec::new();
+        for emplacement_handle in self.gs.emplacements.collect_handles() {
+            let Some(player_handle) = self.gs.emplacements[emplacement_handle].controller else {
+                continue;
+            };
+            let pressed = self
+                .gs
+                .players
+                .get(player_handle)
+                .is_some_and(interact_pressed);
+            if pressed || !self.can_man(player_handle, emplacement_handle) {
+                self.release_emplacement(emplacement_handle);
+                released.push(player_handle);
+            }
+        }
+
+        for player_handle in self.gs.players.collect_handles() {
+            let player = &self.gs.players[player_handle];
+            if player.emplacement.is_some()
+                || !interact_pressed(player)
+                || released.contains(&player_handle)
+            {
+                continue;
+            }
+            let vehicle = player
+                .vehicle
+                .and_then(|handle| self.gs.vehicles.get(handle));
+            let Some(vehicle_pos) = vehicle.map(|vehicle| vehicle.pos) else {
+                continue;
+            };
+            let nearest = self
+                .gs
+                .emplacements
+                .iter()
+                .filter(|(handle, emplacement)| {
+                    emplacement.controller.is_none() && self.can_man(player_handle, *handle)
+                })
+                .min_by(|(_, a), (_, b)| {
+                    let a = a.pos.distance_squared(vehicle_pos);
+                    let b = b.pos.distance_squared(vehicle_pos);
+                    a.total_cmp(&b)
+                })
+                .map(|(handle, _)| handle);
+            if let Some(emplacement_handle) = nearest {
+                self.man_emplacement(player_handle, emplacement_handle);
+            }
+        }
+    }
+
+    /// The player is alive and close enough and the emplacement is still standing.
+    fn can_man(&self, player_handle: Index, emplacement_handle: Index) -> bool {
+        let emplacement = &self.gs.emplacements[emplacement_handle];
+        let Some(player) = self.gs.players.get(player_handle) else {
+            return false;
+        };
+        let Some(vehicle) = player
+            .vehicle
+            .and_then(|handle| self.gs.vehicles.get(handle))
+        else {
+            return false;
+        };
+        !emplacement.destroyed()
+            && !vehicle.destroyed()
+            && vehicle.sinking.is_none()
+            && vehicle.pos.distance(emplacement.pos) <= self.cvars.g_emplacement_interact_range
+    }
+
+    fn man_emplacement(&mut self, player_handle: Index, emplacement_handle: Index) {
+        let player = &mut self.gs.players[player_handle];
+        player.emplacement = Some(emplacement_handle);
+        // The vehicle stops now, not when the next input arrives, see `possess::route_input`.
+        player.input = NetInput {
+            interact: player.input.interact,
+            ..NetInput::empty()
+        };
+
+        let emplacement = &mut self.gs.emplacements[emplacement_handle];
+        emplacement.controller = Some(player_handle);
+        emplacement.target = None;
+        emplacement.input = NetInput::empty();
+        emplacement.input_prev = NetInput::empty();
+    }
+
+    /// The controller (if any) gets their vehicle back.
+    pub fn release_emplacement(&mut self, emplacement_handle: Index) {
+        let emplacement = &mut self.gs.emplacements[emplacement_handle];
+        let Some(player_handle) = emplacement.controller.take() else {
+            return;
+        };
+        emplacement.input = NetInput::empty();
+        // They might have just left.
+        if let Some(player) = self.gs.players.get_mut(player_handle) {
+            player.emplacement = None;
+        }
+    }
+
+    /// Unmanned emplacements pick a target, turn toward it and fire when the barrel is on it.
+    pub fn sys_emplacement_ai(&mut self) {
+        for (_, emplacement) in self.gs.emplacements.iter_mut() {
+            if emplacement.controller.is_some() || emplacement.destroyed() {
+                continue;
+            }
+
+            emplacement.target = emplacement_target(
+                self.cvars,
+                self.map,
+                &self.gs.vehicles,
+                self.gs.game_time,
+                emplacement.pos,
+            );
+            let Some(target) = emplacement.target else {
+                emplacement.input = NetInput::empty();
+                continue;
+            };
+
+            let target_angle = (self.gs.vehicles[target].pos - emplacement.pos).to_angle();
+            emplacement.turret_angle_wanted =
+                (target_angle - emplacement.angle).rem_euclid(2.0 * PI);
+            let shot_angle = emplacement.angle + emplacement.turret_angle_current;
+            let aim_error = shot_angle.delta_angle(target_angle).abs();
+            emplacement.input = NetInput {
+                fire: aim_error <= self.cvars.g_emplacement_ai_fire_angle,
+                ..NetInput::empty()
+            };
+        }
+    }
+
+    /// Turret turning and shooting for both manned and unmanned emplacements.
+    ///
+    /// Manned ones turn in steps like vehicle turrets, unmanned ones get the angle directly.
+    pub fn sys_emplacements(&mut self) {
+        let mut new_projectiles = Vec::new();
+        for (_, emplacement) in self.gs.emplacements.iter_mut() {
+            if emplacement.destroyed() {
+                emplacement.turret_turn_rate = 0.0;
+                continue;
+            }
+            let weapon = emplacement.weapon;
+            emplacement
+                .weapon_state
+                .tick(self.cvars, weapon, self.gs.game_time);
+
+            let input = emplacement.input;
+            let input_prev = emplacement.input_prev;
+            let step = self.cvars.g_turret_turn_step_angle_deg.to_radians();
+            if input.turret_left && !input_prev.turret_left {
+                emplacement.turret_angle_wanted -= step;
+            }
+            if input.turret_right && !input_prev.turret_right {
+                emplacement.turret_angle_wanted += step;
+            }
+            emplacement.turret_angle_wanted = emplacement.turret_angle_wanted.rem_euclid(2.0 * PI);
+            turn_turret(
+                self.cvars,
+                &mut emplacement.turret_angle_current,
+                emplacement.turret_angle_wanted,
+                &mut emplacement.turret_turn_rate,
+                self.gs.dt,
+            );
+
+            if !input.fire || !emplacement.weapon_state.can_fire(self.gs.game_time) {
+                continue;
+            }
+            if weapons::emplacement_muzzle_blocked(self.cvars, self.map, emplacement) {
+                continue;
+            }
+            emplacement
+                .weapon_state
+                .fire(self.cvars, weapon, self.gs.game_time);
+
+            let (shot_angle, shot_origin) =
+                weapons::emplacement_angle_origin(self.cvars, emplacement);
+            let owner = emplacement.controller.unwrap_or(Index::DANGLING);
+            let projectiles = weapon_projectiles(
+                self.cvars,
+                &mut self.sg.rng,
+                self.gs.range_uniform11,
+                weapon,
+                shot_angle,
+                shot_origin,
+                Vec2f::zero(),
+                owner,
+                self.gs.game_time,
+            );
+            for projectile in projectiles {
+                new_projectiles.push(self.gs.projectiles.insert(projectile));
+            }
+        }
+
+        self.projectiles_spawned(new_projectiles);
+    }
+
+    /// Like `projectile_hit_vehicle` but emplacements have no armor, knockback or BFG beam damage.
+    ///
+    /// Returns whether the projectile was stopped (and removed).
+    pub fn projectile_hit_emplacement(
+        &mut self,
+        proj_handle: Index,
+        emplacement_handle: Index,
+        step: LineSegment2<f64>,
+    ) -> bool {
+        let emplacement = &self.gs.emplacements[emplacement_handle];
+        let projectile = &self.gs.projectiles[proj_handle];
+        let weapon = projectile.weapon;
+        let is_rail = weapon == Weapon::Rail;
+
+        // The controller can't shoot their own emplacement, same as their own vehicle.
+        if emplacement.destroyed() || emplacement.controller == Some(projectile.owner) {
+            return false;
+        }
+
+        let radius2 = self.cvars.g_emplacement_radius * self.cvars.g_emplacement_radius;
+        let nearest_point = step.projected_point(emplacement.pos);
+        let hit = nearest_point.distance_squared(emplacement.pos) <= radius2;
+
+        if is_rail {
+            let ends_inside = hit && step.end.distance_squared(emplacement.pos) <= radius2;
+            let kind = TargetKind::Emplacement(emplacement_handle);
+            if rail_was_inside(&mut self.gs.rail_hits, proj_handle, kind, ends_inside) {
+                return false;
+            }
+        }
+        if !hit {
+            return false;
+        }
+
+        let dmg = self.cvars.g_weapon_damage_direct(weapon);
+        self.damage_emplacement(emplacement_handle, dmg);
+        if is_rail {
+            return false;
+        }
+        self.projectile_impact(proj_handle, nearest_point, true);
+        true
+    }
+
+    /// The emplacement part of `explosion_damage`, they're bolted down so there's no knockback.
+    pub fn explosion_damage_emplacements(
+        &mut self,
+        expl_pos: Vec2f,
+        damage_center: f64,
+        damage_edge: f64,
+        radius: f64,
+    ) {
+        for emplacement_handle in self.gs.emplacements.collect_handles() {
+            let emplacement = &self.gs.emplacements[emplacement_handle];
+            if emplacement.destroyed() {
+                continue;
+            }
+            let center_dist = (emplacement.pos - expl_pos).magnitude();
+            let dist = (center_dist - self.cvars.g_emplacement_radius).max(0.0);
+            if dist < radius && !self.explosion_blocked(expl_pos, emplacement.pos) {
+                let expl_damage = lerp_ranges(0.0, radius, damage_center, damage_edge, dist);
+                self.damage_emplacement(emplacement_handle, expl_damage);
+            }
+        }
+    }
+
+    /// Emplacements don't belong to anyone so there's no score, assists or rule hooks.
+    pub fn damage_emplacement(&mut self, emplacement_handle: Index, dmg_amount: f64) {
+        let Some(emplacement) = self.gs.emplacements.soft_get_mut(emplacement_handle) else {
+            return;
+        };
+        if emplacement.destroyed() {
+            return;
+        }
+
+        let dmg_amount = dmg_amount * self.gs.damage_multiplier;
+        emplacement.hp_fraction -= dmg_amount / self.cvars.g_emplacement_hp;
+        // Same tolerance as vehicles, see `damage`.
+        if emplacement.hp_fraction > 0.001 {
+            return;
+        }
+
+        emplacement.hp_fraction = 0.0;
+        emplacement.target = None;
+        let pos = emplacement.pos;
+        self.release_emplacement(emplacement_handle);
+        self.spawn_explosion(pos, 1.0, false, None);
+    }
+}
+
+fn interact_pressed(player: &Player) -> bool {
+    player.input.interact && !player.input_prev.interact
+}
+
+/// The vehicle an unmanned emplacement at `pos` should shoot at,
+/// None if there's nobody in range and in sight.
+///
+/// Emplacements are neutral so any living vehicle will do.
+pub fn emplacement_target(
+    cvars: &Cvars,
+    map: &Map,
+    vehicles: &Arena<Vehicle>,
+    game_time: f64,
+    pos: Vec2f,
+) -> Option<Index> {
+    let mut best = None;
+    let mut best_score = f64::NEG_INFINITY;
+    for (vehicle_handle, vehicle) in vehicles.iter() {
+        if vehicle.destroyed() || vehicle.materializing(cvars, game_time) {
+            continue;
+        }
+        let distance = (vehicle.pos - pos).magnitude();
+        if distance > cvars.g_emplacement_ai_range || map.is_wall_trace(pos, vehicle.pos).is_some()
+        {
+            continue;
+        }
+        let score = target_score(cvars, distance, false);
+        if score > best_score {
+            best = Some(vehicle_handle);
+            best_score = score;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{possess, test_utils};
+
+    fn cvars() -> Cvars {
+        Cvars {
+            bots_max: 0,
+            g_spawn_effect_duration: 0.0,
+            ..Cvars::default()
+        }
+    }
+
+    fn run(server: &mut Server, cvars: &Cvars, seconds: f64) {
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let end = server.gs.game_time + seconds;
+        while server.gs.game_time < end {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(cvars, game_time);
+        }
+    }
+
+    fn only_emplacement(gs: &GameState) -> (Index, &Emplacement) {
+        assert_eq!(gs.emplacements.len(), 1);
+        gs.emplacements.iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_emplacements_from_map() {
+        let map = test_utils::open_map(8, 5, "emplacement 1 2 mg 180\nemplacement 6 2 rail 0\n");
+        let server = test_utils::server(&cvars(), map.clone());
+        let mut spots: Vec<_> = server
+            .gs
+            .emplacements
+            .iter()
+            .map(|(_, emplacement)| (emplacement.pos, emplacement.weapon))
+            .collect();
+        spots.sort_by(|a, b| a.0.x.total_cmp(&b.0.x));
+        assert_eq!(
+            spots,
+            [(v!(96 160), Weapon::Mg), (v!(416 160), Weapon::Rail)]
+        );
+
+        let cvars = Cvars {
+            g_emplacements: false,
+            ..cvars()
+        };
+        let server = test_utils::server(&cvars, map);
+        assert!(server.gs.emplacements.is_empty());
+    }
+
+    #[test]
+    fn test_emplacement_ai_engages() {
+        let cvars = Cvars {
+            g_emplacement_ai_range: 130.0,
+            ..cvars()
+        };
+        // Facing away from the victim so it has to turn first.
+        let mut server = test_utils::server(
+            &cvars,
+            test_utils::open_map(8, 5, "emplacement 1 2 mg 180\n"),
+        );
+        let mut ctx = server.ctx(&cvars);
+        let victim = test_utils::add_tank(ctx.cvars, ctx.gs, v!(200 160), 0.0).1;
+        let too_far = test_utils::add_tank(ctx.cvars, ctx.gs, v!(96 300), 0.0).1;
+
+        ctx.sys_emplacement_ai();
+        let (_, emplacement) = only_emplacement(ctx.gs);
+        assert_eq!(emplacement.target, Some(victim));
+        assert!((emplacement.turret_angle_wanted - PI).abs() < 1e-9);
+        assert!(!emplacement.input.fire);
+
+        // Half a turn takes 1.5 s with the default turret speed.
+        run(&mut server, &cvars, 2.0);
+        let (_, emplacement) = only_emplacement(&server.gs);
+        let aim_error = emplacement
+            .turret_angle_current
+            .delta_angle(emplacement.turret_angle_wanted);
+        assert!(aim_error.abs() < 1e-9);
+        assert!(server.gs.vehicles[victim].hp_fraction < 1.0);
+        assert_eq!(server.gs.vehicles[too_far].hp_fraction, 1.0);
+    }
+
+    #[test]
+    fn test_emplacement_destroyed() {
+        let cvars = Cvars {
+            g_emplacement_hp: 1.0,
+            ..cvars()
+        };
+        let mut server =
+            test_utils::server(&cvars, test_utils::open_map(8, 5, "emplacement 1 2 mg 0\n"));
+        let ctx = server.ctx(&cvars);
+        let (player_handle, _) = test_utils::add_tank(ctx.cvars, ctx.gs, v!(350 160), PI);
+        let player = &mut ctx.gs.players[player_handle];
+        player.cur_weapon = Weapon::Rail;
+        player.input.fire = true;
+
+        run(&mut server, &cvars, 0.5);
+        let (emplacement_handle, emplacement) = only_emplacement(&server.gs);
+        assert!(emplacement.destroyed());
+        assert_eq!(emplacement.target, None);
+        let state = emplacement.weapon_state;
+
+        // Wrecked, it's no longer a target and doesn't shoot.
+        let ctx = server.ctx(&cvars);
+        let targets = ctx.projectile_targets();
+        let kind = TargetKind::Emplacement(emplacement_handle);
+        assert!(targets.iter().all(|target| target.kind != kind));
+        ctx.gs.emplacements[emplacement_handle].input.fire = true;
+        run(&mut server, &cvars, 1.0);
+        assert_eq!(
+            server.gs.emplacements[emplacement_handle].weapon_state,
+            state
+        );
+    }
+
+    #[test]
+    fn test_emplacement_control() {
+        let cvars = cvars();
+        let mut server = test_utils::server(
+            &cvars,
+            test_utils::open_map(8, 5, "emplacement 1 2 rockets 0\n"),
+        );
+        let mut ctx = server.ctx(&cvars);
+        let vehicle_handle = test_utils::add_tank(ctx.cvars, ctx.gs, v!(130 160), 0.0).1;
+        let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+        let (emplacement_handle, _) = only_emplacement(ctx.gs);
+
+        let press = |ctx: &mut ServerFrameCtx, input: NetInput| {
+            let player = &mut ctx.gs.players[player_handle];
+            player.input_prev = player.input;
+            possess::route_input(ctx.gs, player_handle, input);
+            ctx.sys_emplacement_control();
+        };
+        let interact = NetInput {
+            interact: true,
+            ..NetInput::empty()
+        };
+
+        press(&mut ctx, interact);
+        assert_eq!(
+            ctx.gs.players[player_handle].emplacement,
+            Some(emplacement_handle)
+        );
+        let emplacement = &ctx.gs.emplacements[emplacement_handle];
+        assert_eq!(emplacement.controller, Some(player_handle));
+
+        // Turret and fire go to the emplacement, the vehicle gets nothing.
+        let input = NetInput {
+            up: true,
+            turret_right: true,
+            fire: true,
+            ..NetInput::empty()
+        };
+        press(&mut ctx, input);
+        assert!(!ctx.gs.players[player_handle].input.up);
+        ctx.gs.dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        ctx.sys_emplacement_ai();
+        ctx.sys_emplacements();
+        let step = cvars.g_turret_turn_step_angle_deg.to_radians();
+        assert_eq!(
+            ctx.gs.emplacements[emplacement_handle].turret_angle_wanted,
+            step
+        );
+        let owners: Vec<_> = ctx
+            .gs
+            .projectiles
+            .iter()
+            .map(|(_, proj)| proj.owner)
+            .collect();
+        assert_eq!(owners, [player_handle]);
+
+        // Pressing it again lets go.
+        press(&mut ctx, NetInput::empty());
+        press(&mut ctx, interact);
+        assert_eq!(ctx.gs.players[player_handle].emplacement, None);
+        assert_eq!(ctx.gs.emplacements[emplacement_handle].controller, None);
+
+        // Driving away does too.
+        press(&mut ctx, NetInput::empty());
+        press(&mut ctx, interact);
+        assert_eq!(
+            ctx.gs.players[player_handle].emplacement,
+            Some(emplacement_handle)
+        );
+        ctx.gs.vehicles[vehicle_handle].pos = v!(300 160);
+        press(&mut ctx, NetInput::empty());
+        assert_eq!(ctx.gs.players[player_handle].emplacement, None);
+        assert_eq!(ctx.gs.emplacements[emplacement_handle].controller, None);
+    }
+}
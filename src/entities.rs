@@ -8,7 +8,14 @@
 //! This is not a violation of the ECS pattern,
 //! because they don't modify game state - they're not behavior.
 
-use crate::prelude::*;
+use strum_macros::{Display, EnumString};
+
+use crate::{
+    assists::DamageLedger,
+    bot_identity::Rgb,
+    map::{self, SurfaceFlags, SurfaceKind},
+    prelude::*,
+};
 
 #[derive(Debug, Clone)]
 pub struct Player {
@@ -27,8 +34,27 @@ pub struct Player {
     pub death_time: f64,
     pub vehicle: Option<Index>,
     pub guided_missile: Option<Index>,
+    /// The gun emplacement the player is manning, see `emplacements`.
+    ///
+    /// Their input goes there instead of the vehicle, see `possess::route_input`.
+    pub emplacement: Option<Index>,
     pub cur_weapon: Weapon,
+    /// Remembered from the old vehicle when respawning, used by the new one.
+    pub respawn_prefs: RespawnPrefs,
+    /// When the player last deployed smoke, for the cooldown.
+    pub smoke_time: f64,
+    /// When the player last dropped a mine, for the cooldown.
+    pub mine_time: f64,
     pub score: Score,
+    /// Whose entities this player's input is going to instead, see `possess`.
+    pub possessing: Option<Possession>,
+    /// Bots get a color for nameplates, see `bot_identity`.
+    pub color: Option<Rgb>,
+    /// Index into `TEAM_COLORS` in team modes, None otherwise.
+    ///
+    /// Players can't change teams yet.
+    /// Projectiles keep a copy (`Projectile::team`) which would have to be updated if they could.
+    pub team: Option<u32>,
 }
 
 impl Player {
@@ -43,10 +69,61 @@ impl Player {
             death_time: 0.0,
             vehicle: None,
             guided_missile: None,
+            emplacement: None,
             cur_weapon: Weapon::Mg,
+            respawn_prefs: RespawnPrefs::default(),
+            smoke_time: f64::NEG_INFINITY,
+            mine_time: f64::NEG_INFINITY,
             score: Score::default(),
+            possessing: None,
+            color: None,
+            team: None,
+        }
+    }
+}
+
+/// What carries over from a destroyed vehicle to the player's next one,
+/// see `g_respawn_keep_weapon` and `g_respawn_keep_turret`.
+///
+/// LATER Ammo carryover would go here too.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RespawnPrefs {
+    pub weapon: Option<Weapon>,
+    /// Relative to the chassis.
+    pub turret_angle: Option<f64>,
+}
+
+impl RespawnPrefs {
+    /// What the cvars say to keep from the player's last vehicle.
+    pub fn remember(cvars: &Cvars, weapon: Weapon, turret_angle: f64) -> Self {
+        Self {
+            weapon: cvars.g_respawn_keep_weapon.then_some(weapon),
+            turret_angle: cvars.g_respawn_keep_turret.then_some(turret_angle),
         }
     }
+
+    /// The kept weapon if `veh_type` can use it, otherwise MG
+    /// or the first weapon in `g_weapon_order` it can use.
+    ///
+    /// Falls back to MG if it can't use anything.
+    pub fn weapon(&self, cvars: &Cvars, veh_type: VehicleType) -> Weapon {
+        let available = cvars.g_vehicle_weapons(veh_type);
+        self.weapon
+            .into_iter()
+            .chain([Weapon::Mg])
+            .chain(cvars.g_weapon_order.weapons().iter().copied())
+            .find(|&weapon| available.contains(weapon))
+            .unwrap_or(Weapon::Mg)
+    }
+}
+
+/// A human controlling a bot's player for debugging AI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Possession {
+    /// The bot's player.
+    pub target: Index,
+    /// Temporary Ai driving the human's own player in the meantime.
+    pub stand_in_ai: Index,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -82,11 +159,15 @@ pub struct Score {
     pub kills: i32,
     pub deaths: i32,
     pub suicides: i32,
+    /// Helped destroy a vehicle without the last hit, see `assists`.
+    pub assists: i32,
 }
 
 impl Score {
     pub fn points(&self, cvars: &Cvars) -> i32 {
-        self.kills * cvars.g_ffa_score_kill + self.deaths * cvars.g_ffa_score_death
+        self.kills * cvars.g_ffa_score_kill
+            + self.deaths * cvars.g_ffa_score_death
+            + self.assists * cvars.g_ffa_score_assist
     }
 }
 
@@ -113,19 +194,152 @@ pub struct Ai {
     pub movement: i32,
     pub turning: i32,
     pub firing: bool,
+    /// The enemy player this bot is after, see `sys_ai_targets`.
+    pub target: Option<Index>,
+    /// Assigned when the bot is added, see `ai_role`.
+    pub role: AiRole,
+    /// From 0 (harmless) to 1, changes over time with `ai_dynamic_difficulty`.
+    pub difficulty: f64,
+    pub score_band: ScoreBand,
+    /// When the bot first noticed the current threat, for reaction time.
+    pub threat_time: Option<f64>,
+    pub dodge_dir: Vec2f,
+    pub dodge_until: f64,
+    /// When the bot first noticed a homing missile locked onto it, for reaction time.
+    pub hm_lock_time: Option<f64>,
+    /// When the bot last re-evaluated threats, see `ai_schedule`.
+    pub decision_time: f64,
+    /// A human is controlling the player, the AI doesn't touch its input.
+    pub possessed: bool,
+    /// Updated by `sys_ai_chat` after the other AI systems.
+    pub state: AiState,
+    /// The last state announced with `d_ai_chat`.
+    pub announced: AiState,
+    pub announce_time: f64,
 }
 
 impl Ai {
-    pub fn new(player_index: Index) -> Self {
+    pub fn new(player_index: Index, difficulty: f64) -> Self {
         Self {
             player: player_index,
             movement: 0,
             turning: 0,
             firing: false,
+            target: None,
+            role: AiRole::Attacker,
+            difficulty,
+            score_band: ScoreBand::Even,
+            threat_time: None,
+            dodge_dir: Vec2f::zero(),
+            dodge_until: f64::NEG_INFINITY,
+            hm_lock_time: None,
+            decision_time: f64::NEG_INFINITY,
+            possessed: false,
+            state: AiState::Wandering,
+            announced: AiState::Wandering,
+            announce_time: f64::NEG_INFINITY,
+        }
+    }
+}
+
+/// What a bot prefers to do in objective modes, in other modes all bots are attackers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiRole {
+    /// Hunt enemies.
+    Attacker,
+    /// Go for the cow.
+    Objective,
+}
+
+/// What a bot is doing at a high level, derived from the more detailed `Ai` fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiState {
+    Dead,
+    Wandering,
+    Engaging,
+    Reloading,
+    Dodging,
+    Evading,
+}
+
+impl AiState {
+    /// What the bot says with `d_ai_chat` when it enters this state, None to stay quiet.
+    pub fn announcement(self) -> Option<&'static str> {
+        match self {
+            AiState::Dead => None, // The kill notification says enough
+            AiState::Wandering => Some("wandering around"),
+            AiState::Engaging => Some("opening fire"),
+            AiState::Reloading => Some("reloading"),
+            AiState::Dodging => Some("dodging incoming fire"),
+            AiState::Evading => Some("homing missile locked on, evading"),
+        }
+    }
+}
+
+/// The surface under a vehicle's center, looked up once per tick
+/// so movement, effects and audio don't each have to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceUnder {
+    /// Index into texture_list.txt
+    pub index: usize,
+    pub kind: SurfaceKind,
+    pub flags: SurfaceFlags,
+    /// Multiplier for max speed
+    pub speed: f64,
+}
+
+impl Default for SurfaceUnder {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            kind: SurfaceKind::Normal,
+            flags: SurfaceFlags::default(),
+            speed: 1.0,
+        }
+    }
+}
+
+/// Who last knocked a vehicle around with an explosion, see `g_explosion_knockback`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Push {
+    pub attacker: Index,
+    pub time: f64,
+}
+
+/// A vehicle going under in lethal water, it dies when the animation ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sinking {
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+impl Sinking {
+    pub fn new(start_time: f64, duration: f64) -> Self {
+        Self {
+            start_time,
+            end_time: start_time + duration,
+        }
+    }
+
+    /// From 0 when it starts to sink to 1 when it's gone.
+    pub fn progress(&self, game_time: f64) -> f64 {
+        let duration = self.end_time - self.start_time;
+        if duration <= 0.0 {
+            return 1.0;
         }
+        ((game_time - self.start_time) / duration).clamped(0.0, 1.0)
     }
 }
 
+/// Follow-up rockets of a burst, fired alternately from each launcher.
+#[derive(Debug, Clone, Copy)]
+pub struct Burst {
+    pub remaining: u32,
+    pub next_shot_time: f64,
+    /// Whether the next rocket comes from the second launcher.
+    pub alt_side: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct Vehicle {
     pub pos: Vec2f,
@@ -137,17 +351,56 @@ pub struct Vehicle {
     /// Angle from vehicle, see Coord system for more
     pub turret_angle_current: f64,
     pub turret_angle_wanted: f64,
+    /// Angular velocity of the turret relative to the chassis (rad/s), see `turret_turning`.
+    pub turret_turn_rate: f64,
+    /// Turret input stops `TurretSlaved::Velocity` tracking until this game time.
+    pub turret_override_until: f64,
+    /// `angle` before the last update from the server, rendering interpolates from it.
+    pub prev_angle: f64,
+    /// `turret_angle_current` before the last update from the server, see `prev_angle`.
+    pub prev_turret_angle: f64,
     /// HP between 0 and 1 - saving the fraction here instead of absolute hit points so armor cvars can be adjusted during a match.
     pub hp_fraction: f64,
     /// Each weapon has a separate reload status even if they all reload at the same time.
     /// I plan to generalize this and have a cvar to choose between multiple reload mechanisms.
-    pub ammos: Vec<Ammo>,
+    pub weapon_states: Vec<WeaponState>,
     /// Game time when this vehicle was spawned.
     pub spawn_time: f64,
     /// Index of the player who owns this vehicle.
     pub owner: Index,
     /// Indices of homing missiles targeting this vehicle.
     pub hms: Vec<Index>,
+    /// Updated at the start of each tick by `sys_surface_under`.
+    pub surface: SurfaceUnder,
+    /// Corners which hit a wall during the last `sys_vehicle_movement`, empty if none did.
+    pub wall_contacts: Vec<WallContact>,
+    /// Speed along the wall when it hit, 0 when not touching any.
+    pub scrape_speed: f64,
+    /// Rockets still to be fired automatically after the trigger pull, see `g_rockets_burst_count`.
+    pub pending_burst: Option<Burst>,
+    /// No weapon can fire until this game time after switching, see `g_weapon_switch_time`.
+    pub weapon_ready_time: f64,
+    /// A fire press shortly before the weapon was ready, see `g_fire_buffer_window`.
+    ///
+    /// Only for the weapon it was pressed with, bursts and alt fire are never buffered.
+    pub buffered_fire: Option<Weapon>,
+    /// When a shot was last blocked because the muzzle was inside a wall, see `weapons::muzzle_blocked`.
+    pub fire_blocked_time: f64,
+    /// Flare deployments left in this life, see `g_flare_count`.
+    pub flares: u32,
+    /// When flares were last deployed, for the cooldown.
+    pub flare_time: f64,
+    /// Recent knockback, the attacker gets credit if this vehicle dies in water soon after.
+    pub last_push: Option<Push>,
+    /// Who damaged this vehicle recently, they get assists if it dies.
+    pub damage_ledger: DamageLedger,
+    pub sinking: Option<Sinking>,
+    /// Timed changes to movement stats, e.g. from boosting.
+    pub modifiers: StatModifiers,
+    /// Boosting until this game time, see `g_boost_duration`.
+    pub boost_until: f64,
+    /// Boost can't be used again before this game time, see `g_boost_cooldown`.
+    pub boost_ready_time: f64,
 }
 
 impl Vehicle {
@@ -161,15 +414,12 @@ impl Vehicle {
         owner: Index,
     ) -> Vehicle {
         let hitbox = cvars.g_vehicle_hitbox(veh_type);
-        let ammos = vec![
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Mg)),
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Rail)),
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Cb)),
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Rockets)),
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Hm)),
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Gm)),
-            Ammo::Loaded(0.0, cvars.g_weapon_reload_ammo(Weapon::Bfg)),
-        ];
+        let weapon_states = (0..Weapon::COUNT)
+            .map(|i| {
+                let weapon = Weapon::from_repr(i).unwrap();
+                WeaponState::new(cvars.g_weapon_reload_ammo(weapon))
+            })
+            .collect();
 
         Vehicle {
             pos,
@@ -180,32 +430,432 @@ impl Vehicle {
             hitbox,
             turret_angle_current: 0.0,
             turret_angle_wanted: 0.0,
+            turret_turn_rate: 0.0,
+            turret_override_until: f64::NEG_INFINITY,
+            prev_angle: angle,
+            prev_turret_angle: 0.0,
             hp_fraction: 1.0,
-            ammos,
+            weapon_states,
             spawn_time,
             owner,
             hms: Vec::new(),
+            surface: SurfaceUnder::default(),
+            wall_contacts: Vec::new(),
+            scrape_speed: 0.0,
+            pending_burst: None,
+            weapon_ready_time: 0.0,
+            buffered_fire: None,
+            fire_blocked_time: f64::NEG_INFINITY,
+            flares: cvars.g_flare_count,
+            flare_time: f64::NEG_INFINITY,
+            last_push: None,
+            damage_ledger: DamageLedger::default(),
+            sinking: None,
+            modifiers: StatModifiers::new(),
+            boost_until: f64::NEG_INFINITY,
+            boost_ready_time: f64::NEG_INFINITY,
         }
     }
 
     pub fn destroyed(&self) -> bool {
         self.hp_fraction <= 0.0
     }
+
+    /// Hull and turret angle `t` of the way from the previous update to the last one.
+    ///
+    /// Both go the short way around so they don't spin when crossing 0/360 deg.
+    pub fn interpolated_angles(&self, t: f64) -> (f64, f64) {
+        (
+            self.prev_angle.lerp_angle(self.angle, t),
+            self.prev_turret_angle
+                .lerp_angle(self.turret_angle_current, t),
+        )
+    }
+
+    /// From 0 when it spawns to 1 when it's fully materialized, see `g_spawn_effect_duration`.
+    pub fn spawn_progress(&self, cvars: &Cvars, game_time: f64) -> f64 {
+        let duration = cvars.g_spawn_effect_duration;
+        if duration <= 0.0 {
+            return 1.0;
+        }
+        ((game_time - self.spawn_time) / duration).clamped(0.0, 1.0)
+    }
+
+    /// Still materializing after spawning - can't shoot or be damaged.
+    pub fn materializing(&self, cvars: &Cvars, game_time: f64) -> bool {
+        game_time < self.spawn_time + cvars.g_spawn_effect_duration
+    }
+
+    /// Movement input is ignored right after spawning, see `g_spawn_input_lock`.
+    pub fn input_locked(&self, cvars: &Cvars, game_time: f64) -> bool {
+        game_time < self.spawn_time + cvars.g_spawn_input_lock
+    }
+
+    /// Sank in lethal water - there's no wreck left to show.
+    pub fn sunk(&self) -> bool {
+        self.destroyed() && self.sinking.is_some()
+    }
+
+    /// HP above full, 0 if not overhealed.
+    pub fn overheal(&self) -> f64 {
+        (self.hp_fraction - 1.0).max(0.0)
+    }
+
+    /// Heal up to full HP, never into overheal and never removes existing overheal.
+    pub fn repair(&mut self, amount: f64) {
+        if self.hp_fraction < 1.0 {
+            self.hp_fraction = (self.hp_fraction + amount).min(1.0);
+        }
+    }
+
+    /// Add HP, also above full up to `g_overheal_max`.
+    pub fn add_armor(&mut self, cvars: &Cvars, amount: f64) {
+        let max = cvars.g_overheal_max.max(1.0);
+        if self.hp_fraction < max {
+            self.hp_fraction = (self.hp_fraction + amount).min(max);
+        }
+    }
+
+    /// Speed along the hull's facing, negative when reversing.
+    pub fn forward_speed(&self) -> f64 {
+        self.vel.dot(self.angle.to_vec2f())
+    }
+
+    /// Where a turret slaved to velocity should point relative to the hull, `None` to stay put.
+    ///
+    /// While reversing, it either faces opposite to the direction of travel
+    /// or holds, see `g_turret_slaved_reverse`. Never flips around just because the vehicle
+    /// started backing up.
+    pub fn velocity_turret_angle(&self, cvars: &Cvars) -> Option<f64> {
+        if self.vel.magnitude() < cvars.g_turret_slaved_speed_min {
+            return None;
+        }
+        let travel = if self.forward_speed() >= 0.0 {
+            self.vel.to_angle()
+        } else {
+            match cvars.g_turret_slaved_reverse {
+                SlavedReverse::Track => (-self.vel).to_angle(),
+                SlavedReverse::Hold => return None,
+            }
+        };
+        Some((travel - self.angle).rem_euclid(2.0 * PI))
+    }
+
+    /// How far the turret is turned away from the hull's facing, in [0, PI].
+    pub fn turret_misalignment(&self) -> f64 {
+        let angle = self.turret_angle_current.rem_euclid(2.0 * PI);
+        if angle > PI {
+            2.0 * PI - angle
+        } else {
+            angle
+        }
+    }
+
+    /// Multiplier for acceleration and max speed.
+    ///
+    /// Driving with the turret turned far away is harder,
+    /// this gives hull-mounted weapons a niche.
+    pub fn movement_factor(&self, cvars: &Cvars) -> f64 {
+        if self.turret_misalignment() > cvars.g_turret_misalign_angle {
+            cvars.g_turret_misalign_speed_factor
+        } else {
+            1.0
+        }
+    }
+
+    /// Engine damage, see `g_cripple_hp_threshold`.
+    pub fn crippled(&self, cvars: &Cvars) -> bool {
+        let enabled = cvars.g_cripple_mobility_factor != 1.0 || cvars.g_cripple_turn_factor != 1.0;
+        enabled && !self.destroyed() && self.hp_fraction < cvars.g_cripple_hp_threshold
+    }
+
+    /// Whether the boost is active, see `g_boost_duration`.
+    pub fn boosting(&self, game_time: f64) -> bool {
+        game_time < self.boost_until
+    }
+
+    /// From 0 to 1 - how much boost is left while boosting, otherwise how much the cooldown recharged.
+    pub fn boost_charge(&self, cvars: &Cvars, game_time: f64) -> f64 {
+        let fraction = if self.boosting(game_time) {
+            (self.boost_until - game_time) / cvars.g_boost_duration
+        } else {
+            1.0 - (self.boost_ready_time - game_time) / cvars.g_boost_cooldown
+        };
+        // Also handles the durations being 0 and changing mid-boost.
+        if fraction.is_nan() {
+            1.0
+        } else {
+            fraction.clamped(0.0, 1.0)
+        }
+    }
+
+    /// The vehicle type's movement stats with this vehicle's current modifiers applied.
+    ///
+    /// Computed each tick so modifiers go away as soon as their cause does
+    /// (e.g. HP going back above the cripple threshold).
+    pub fn movement_stats(&self, cvars: &Cvars, game_time: f64) -> MovementStats {
+        let mut stats = cvars.g_vehicle_movement_stats(self.veh_type);
+
+        let factor = self.movement_factor(cvars);
+        stats.accel_forward *= factor;
+        stats.accel_backward *= factor;
+        stats.speed_max *= factor;
+
+        if cvars.g_surface_speed {
+            stats.speed_max *= self.surface.speed;
+        }
+
+        if self.crippled(cvars) {
+            StatModifier {
+                mobility: cvars.g_cripple_mobility_factor,
+                turn: cvars.g_cripple_turn_factor,
+                turn_effectiveness: 1.0,
+                until: f64::INFINITY,
+            }
+            .apply(&mut stats);
+        }
+
+        self.modifiers.apply(&mut stats, game_time);
+
+        stats
+    }
+}
+
+/// Multipliers for movement stats which last until a given game time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatModifier {
+    /// Multiplier for acceleration and max speed.
+    pub mobility: f64,
+    /// Multiplier for turn rate.
+    pub turn: f64,
+    /// Multiplier for how much of the velocity turns along with the hull.
+    pub turn_effectiveness: f64,
+    /// Active while the game time is lower than this.
+    pub until: f64,
 }
 
-#[derive(Debug, Clone, Copy, FromRepr, Deserialize, Serialize)]
+impl StatModifier {
+    pub fn active(&self, game_time: f64) -> bool {
+        game_time < self.until
+    }
+
+    pub fn apply(&self, stats: &mut MovementStats) {
+        stats.accel_forward *= self.mobility;
+        stats.accel_backward *= self.mobility;
+        stats.speed_max *= self.mobility;
+        stats.turn_rate_increase *= self.turn;
+        stats.turn_rate_max *= self.turn;
+        stats.pivot_turn_rate *= self.turn;
+        stats.turn_effectiveness *= self.turn_effectiveness;
+    }
+}
+
+/// All the timed modifiers of a vehicle, they compose multiplicatively.
+#[derive(Debug, Clone, Default)]
+pub struct StatModifiers {
+    pub modifiers: Vec<StatModifier>,
+}
+
+impl StatModifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, modifier: StatModifier) {
+        self.modifiers.push(modifier);
+    }
+
+    pub fn remove_expired(&mut self, game_time: f64) {
+        self.modifiers.retain(|modifier| modifier.active(game_time));
+    }
+
+    /// Apply the modifiers active at `game_time`, even if expired ones haven't been removed yet.
+    pub fn apply(&self, stats: &mut MovementStats, game_time: f64) {
+        for modifier in &self.modifiers {
+            if modifier.active(game_time) {
+                modifier.apply(stats);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumCount, FromRepr, Deserialize, Serialize)]
 pub enum VehicleType {
     Tank,
     Hovercraft,
     Hummer,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Ammo {
-    /// Refire delay end time, ammo count remaining
-    Loaded(f64, u32),
-    /// Start time, end time
-    Reloading(f64, f64),
+/// A destroyed vehicle placed as scenery when the match starts.
+///
+/// Not a `Vehicle` because those always belong to a player.
+/// Wrecks have no owner so they never respawn, score or get targeted by bots.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Wreck {
+    pub pos: Vec2f,
+    pub angle: f64,
+    pub veh_type: VehicleType,
+}
+
+/// Where the map places a gun emplacement, see `emplacements`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmplacementSpot {
+    pub pos: Vec2f,
+    pub angle: f64,
+    pub weapon: Weapon,
+}
+
+/// What a `Targetable` is.
+///
+/// LATER The cow and props once they exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetKind {
+    Vehicle(Index),
+    /// Index into `GameState::wrecks`.
+    Wreck(usize),
+    Emplacement(Index),
+}
+
+/// Anything projectiles can hit, see `ServerFrameCtx::projectile_targets`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Targetable {
+    pub kind: TargetKind,
+    pub pos: Vec2f,
+    pub radius: f64,
+    /// Hits are passed to the target kind's handler which decides what damage means for it.
+    pub takes_damage: bool,
+    /// Projectiles stop when they hit it.
+    ///
+    /// Railguns still go through targets which take damage.
+    pub blocks_projectiles: bool,
+}
+
+/// Which side of a vehicle a hit came from, see `Cvars::g_vehicle_armor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitArc {
+    Front,
+    Side,
+    Rear,
+}
+
+impl HitArc {
+    /// Classify a hit traveling in direction `dir` into a vehicle facing `vehicle_angle`.
+    ///
+    /// Hits exactly on the boundary belong to the front or rear arc, not side.
+    /// None if the direction is unknown (zero).
+    pub fn classify(cvars: &Cvars, vehicle_angle: f64, dir: Vec2f) -> Option<HitArc> {
+        if dir == Vec2f::zero() {
+            return None;
+        }
+        // Where the hit came from relative to the facing, in [0, PI].
+        let from = (-dir).to_angle();
+        let off_facing = (from - vehicle_angle).rem_euclid(2.0 * PI);
+        let off_facing = if off_facing > PI {
+            2.0 * PI - off_facing
+        } else {
+            off_facing
+        };
+        if off_facing <= cvars.g_armor_arc_front {
+            Some(HitArc::Front)
+        } else if off_facing >= PI - cvars.g_armor_arc_rear {
+            Some(HitArc::Rear)
+        } else {
+            Some(HitArc::Side)
+        }
+    }
+}
+
+/// Ammo, refire and reload status of one weapon of a vehicle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeaponState {
+    /// Rounds left before reloading, 0 while reloading.
+    pub rounds_loaded: u32,
+    /// Refire delay end time. Only meaningful when not reloading.
+    pub next_fire_time: f64,
+    /// Start and end time if reloading.
+    pub reload: Option<Reload>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reload {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl WeaponState {
+    pub fn new(rounds: u32) -> Self {
+        Self {
+            rounds_loaded: rounds,
+            next_fire_time: 0.0,
+            reload: None,
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reload.is_some()
+    }
+
+    /// Loaded and the refire delay is over.
+    pub fn can_fire(&self, now: f64) -> bool {
+        !self.is_reloading() && self.rounds_loaded > 0 && now >= self.next_fire_time
+    }
+
+    /// When it can fire next - the end of the reload or the refire delay.
+    pub fn ready_time(&self) -> f64 {
+        match self.reload {
+            Some(reload) => reload.end,
+            None => self.next_fire_time,
+        }
+    }
+
+    /// Fire a shot which starts the refire delay.
+    pub fn fire(&mut self, cvars: &Cvars, weapon: Weapon, now: f64) {
+        self.next_fire_time = now + cvars.g_weapon_refire(weapon);
+        self.consume_round(cvars, weapon, now);
+    }
+
+    /// Use up a round without touching the refire delay (e.g. rest of a burst).
+    /// Starts reloading when it was the last one.
+    pub fn consume_round(&mut self, cvars: &Cvars, weapon: Weapon, now: f64) {
+        self.rounds_loaded -= 1;
+        if self.rounds_loaded == 0 {
+            self.start_reload(cvars, weapon, now);
+        }
+    }
+
+    pub fn start_reload(&mut self, cvars: &Cvars, weapon: Weapon, now: f64) {
+        self.rounds_loaded = 0;
+        self.reload = Some(Reload {
+            start: now,
+            end: now + cvars.g_weapon_reload_time(weapon),
+        });
+    }
+
+    /// Finish reloading if it's time.
+    ///
+    /// The reload end time is decided when it starts
+    /// but the number of rounds is read when it finishes.
+    pub fn tick(&mut self, cvars: &Cvars, weapon: Weapon, now: f64) {
+        let Some(reload) = self.reload else {
+            return;
+        };
+        if now >= reload.end {
+            self.reload = None;
+            self.rounds_loaded = cvars.g_weapon_reload_ammo(weapon);
+            self.next_fire_time = now;
+        }
+    }
+
+    /// How full the HUD ammo bar is - remaining rounds or reload progress.
+    pub fn fraction(&self, cvars: &Cvars, weapon: Weapon, now: f64) -> f64 {
+        match self.reload {
+            Some(Reload { start, end }) => (now - start) / (end - start),
+            None => {
+                let max = cvars.g_weapon_reload_ammo(weapon);
+                self.rounds_loaded as f64 / max as f64
+            }
+        }
+    }
 }
 
 /// A projectile fired by a vehicle.
@@ -222,16 +872,122 @@ pub struct Projectile {
     pub vel: Vec2f,
     pub angle: f64,
     pub turn_rate: f64,
+    /// Game time when this projectile was fired
+    pub spawn_time: f64,
     /// Game time when this projectile will explode
     pub explode_time: f64,
     /// Handle of the player who owns this projectile.
     pub owner: Index,
-    /// If this is a homing projectile, this is the handle of the target vehicle.
-    pub target: Option<Index>,
+    /// If this is a homing projectile, this is what it's going after.
+    pub target: Option<HmTarget>,
+    /// Lobbed projectiles fly over walls and vehicles, see `Flight`.
+    pub flight: Option<Flight>,
+    /// Copy of the owner's `Player::team` at spawn so rendering doesn't have to look it up
+    /// and it's still available after the owner leaves.
+    pub team: Option<u32>,
+    /// What the homing missile's seeker is doing, unused by other weapons.
+    pub seeker: Seeker,
+}
+
+impl Projectile {
+    pub fn airborne(&self, cvars: &Cvars, game_time: f64) -> bool {
+        match self.flight {
+            Some(flight) => flight.airborne(cvars, game_time),
+            None => false,
+        }
+    }
+
+    pub fn height(&self, game_time: f64) -> f64 {
+        self.flight.map_or(0.0, |flight| flight.height(game_time))
+    }
+}
+
+/// What a homing missile is going after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HmTarget {
+    /// Also in the vehicle's `hms`.
+    Vehicle(Index),
+    /// Handle into `GameState::flares`.
+    Flare(Index),
+}
+
+/// State of a homing missile's seeker, see `sys_hm_turning`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seeker {
+    /// Tracking `Projectile::target` or looking for one if it has none (e.g. right after launch).
+    Active {
+        /// Game time when a wall started blocking the view of the target vehicle.
+        /// It's only lost after `g_homing_missile_occlusion_grace`.
+        occluded_since: Option<f64>,
+    },
+    /// Lost its target at the given game time, flying straight and looking for a new one.
+    /// After `g_homing_missile_search_time` it gives up according to `g_homing_missile_lost`.
+    Searching(f64),
+    /// Gave up, it flies straight until it hits something.
+    Inert,
+}
+
+impl Default for Seeker {
+    fn default() -> Self {
+        Seeker::Active {
+            occluded_since: None,
+        }
+    }
+}
+
+/// Fake ballistic arc of a lobbed projectile.
+///
+/// The game is top-down so there's no real 3rd dimension,
+/// the height only decides what the projectile can hit and how big it's drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Flight {
+    pub start_time: f64,
+    /// How long until it's back on the ground.
+    pub duration: f64,
+    /// Height at the top of the arc, 1.0 doubles the size of the sprite by default.
+    pub apex: f64,
+}
+
+impl Flight {
+    /// 0.0 at launch, 1.0 when landed.
+    pub fn progress(self, game_time: f64) -> f64 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        ((game_time - self.start_time) / self.duration).clamp(0.0, 1.0)
+    }
+
+    /// Parabola peaking at `apex` halfway through the flight.
+    pub fn height(self, game_time: f64) -> f64 {
+        let progress = self.progress(game_time);
+        4.0 * self.apex * progress * (1.0 - progress)
+    }
+
+    /// Flies over everything while rising and until it comes down to `g_airborne_height`.
+    pub fn airborne(self, cvars: &Cvars, game_time: f64) -> bool {
+        let progress = self.progress(game_time);
+        progress < 0.5 || self.height(game_time) > cvars.g_airborne_height
+    }
 }
 
 /// Weapon type - currently hardcoded.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumCount, FromRepr, Deserialize, Serialize)]
+///
+/// The declaration order is used for indexing (e.g. `Vehicle::weapon_states`),
+/// the order players cycle through is `g_weapon_order`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumCount,
+    FromRepr,
+    EnumString,
+    Display,
+    Deserialize,
+    Serialize,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
 pub enum Weapon {
     Mg,
     Rail,
@@ -256,4 +1012,668 @@ impl Hitbox {
         let back_right = pos + Vec2f::new(self.mins.x, self.maxs.y).rotated_z(angle);
         [back_left, front_left, front_right, back_right]
     }
+
+    /// Convex hull of the hitbox moving from `old_pos` to `new_pos` (without rotating).
+    ///
+    /// The points are ordered along the hull's edge, without duplicates or collinear points.
+    pub fn swept_corners(self, old_pos: Vec2f, new_pos: Vec2f, angle: f64) -> Vec<Vec2f> {
+        let mut points = Vec::with_capacity(8);
+        points.extend(self.corners(old_pos, angle));
+        points.extend(self.corners(new_pos, angle));
+        convex_hull(points)
+    }
+
+    /// How far the hitbox can rotate from `old_angle` towards `new_angle` at `pos`
+    /// before a corner enters a wall, turning the shorter way around.
+    ///
+    /// Checking only `new_angle` would let fast spinning vehicles rotate through
+    /// the corner of a wall in one frame. When nothing is within reach of the corners,
+    /// this is a single circle check. Otherwise the rotation is sampled
+    /// so corners move at most `step` between samples, with at most `samples_max` samples.
+    ///
+    /// Returns `new_angle` if the whole rotation is clear, otherwise the last clear sample
+    /// which is `old_angle` if even the first one is blocked.
+    pub fn swept_rotation(
+        self,
+        map: &Map,
+        pos: Vec2f,
+        old_angle: f64,
+        new_angle: f64,
+        step: f64,
+        samples_max: usize,
+    ) -> f64 {
+        let radius = self
+            .corners(Vec2f::zero(), 0.0)
+            .iter()
+            .map(|corner| corner.magnitude())
+            .fold(0.0, f64::max);
+        if !map.circle_touches_wall(pos, radius) {
+            return new_angle;
+        }
+
+        let delta = old_angle.delta_angle(new_angle);
+        let samples = (delta.abs() * radius / step).ceil() as usize;
+        let samples = samples.clamp(1, samples_max.max(1));
+        let mut clear = old_angle;
+        for i in 1..=samples {
+            let angle = if i == samples {
+                new_angle
+            } else {
+                old_angle.lerp_angle(new_angle, i as f64 / samples as f64)
+            };
+            if self
+                .corners(pos, angle)
+                .iter()
+                .any(|&corner| map.is_wall(corner))
+            {
+                return clear;
+            }
+            clear = angle;
+        }
+        new_angle
+    }
+
+    /// Whether the point is inside (or on the edge of) the hitbox.
+    pub fn contains_point(self, pos: Vec2f, angle: f64, point: Vec2f) -> bool {
+        let local = (point - pos).rotated_z(-angle);
+        self.mins.x <= local.x
+            && local.x <= self.maxs.x
+            && self.mins.y <= local.y
+            && local.y <= self.maxs.y
+    }
+
+    /// The point on (or inside) the hitbox nearest to `point`.
+    ///
+    /// Returns `point` itself if it's already inside.
+    pub fn closest_point(self, pos: Vec2f, angle: f64, point: Vec2f) -> Vec2f {
+        let local = (point - pos).rotated_z(-angle);
+        let clamped = Vec2f::new(
+            local.x.clamped(self.mins.x, self.maxs.x),
+            local.y.clamped(self.mins.y, self.maxs.y),
+        );
+        pos + clamped.rotated_z(angle)
+    }
+
+    /// Whether the hitbox touches the axis-aligned rectangle from `min` to `max`.
+    pub fn overlaps_rect(self, pos: Vec2f, angle: f64, min: Vec2f, max: Vec2f) -> bool {
+        // Separating axis test - both shapes are boxes
+        // so the only candidates are the world axes and the hitbox's own.
+        fn overlap(points: &[Vec2f], axis: fn(Vec2f) -> f64, lo: f64, hi: f64) -> bool {
+            let (min, max) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), &p| {
+                (min.min(axis(p)), max.max(axis(p)))
+            });
+            min <= hi && lo <= max
+        }
+
+        let corners = self.corners(pos, angle);
+        let rect_local = [min, v!(max.x, min.y), max, v!(min.x, max.y)]
+            .map(|corner| (corner - pos).rotated_z(-angle));
+        overlap(&corners, |p| p.x, min.x, max.x)
+            && overlap(&corners, |p| p.y, min.y, max.y)
+            && overlap(&rect_local, |p| p.x, self.mins.x, self.maxs.x)
+            && overlap(&rect_local, |p| p.y, self.mins.y, self.maxs.y)
+    }
+
+    /// Whether the line segment from `a` to `b` touches the hitbox.
+    pub fn intersects_segment(self, pos: Vec2f, angle: f64, a: Vec2f, b: Vec2f) -> bool {
+        // Slab test in the hitbox's local coords where it's an AABB.
+        let a = (a - pos).rotated_z(-angle);
+        let b = (b - pos).rotated_z(-angle);
+        let dir = b - a;
+
+        let mut t_min = 0.0_f64;
+        let mut t_max = 1.0_f64;
+        let axes = [
+            (a.x, dir.x, self.mins.x, self.maxs.x),
+            (a.y, dir.y, self.mins.y, self.maxs.y),
+        ];
+        for (start, delta, min, max) in axes {
+            if delta == 0.0 {
+                if start < min || start > max {
+                    return false;
+                }
+            } else {
+                let t1 = (min - start) / delta;
+                let t2 = (max - start) / delta;
+                t_min = t_min.max(t1.min(t2));
+                t_max = t_max.min(t1.max(t2));
+                if t_min > t_max {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Whether any wall is in the way when moving from `old_pos` to `new_pos`.
+    ///
+    /// Tests the whole area swept by the hitbox (see `swept_corners`) so fast vehicles
+    /// (or slow ones with a huge dt) can't skip over thin walls
+    /// and walls narrower than the hitbox can't slip between the corners.
+    pub fn swept_hits_wall(self, map: &Map, old_pos: Vec2f, new_pos: Vec2f, angle: f64) -> bool {
+        map.convex_touches_wall(&self.swept_corners(old_pos, new_pos, angle))
+    }
+
+    /// Which corners hit a wall when moving from `old_pos` to `new_pos` and where.
+    ///
+    /// Traces the path of each corner. This can miss walls narrower than the hitbox
+    /// which only the edges hit, use `swept_hits_wall` to decide whether the move is blocked.
+    pub fn swept_wall_contacts(
+        self,
+        map: &Map,
+        old_pos: Vec2f,
+        new_pos: Vec2f,
+        angle: f64,
+    ) -> Vec<WallContact> {
+        let old_corners = self.corners(old_pos, angle);
+        let new_corners = self.corners(new_pos, angle);
+        let mut contacts = Vec::new();
+        for (corner, (old, new)) in old_corners.into_iter().zip(new_corners).enumerate() {
+            if let Some(point) = map.is_wall_trace(old, new) {
+                contacts.push(WallContact {
+                    corner,
+                    point,
+                    normal: map::wall_normal(old, point),
+                });
+            }
+        }
+        contacts
+    }
+}
+
+/// A hitbox corner touching a wall.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct WallContact {
+    /// Index into the array returned by `Hitbox::corners`.
+    pub corner: usize,
+    /// Where the corner hit the wall, slightly inside it.
+    pub point: Vec2f,
+    /// Points out of the wall, zero if the corner was already inside.
+    pub normal: Vec2f,
+}
+
+/// Andrew's monotone chain.
+fn convex_hull(mut points: Vec<Vec2f>) -> Vec<Vec2f> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross =
+        |o: Vec2f, a: Vec2f, b: Vec2f| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+
+    let mut hull: Vec<Vec2f> = Vec::with_capacity(points.len() + 1);
+    // Lower half
+    for &p in &points {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    // Upper half - must not pop any points of the lower half
+    let lower_len = hull.len() + 1;
+    for &p in points.iter().rev().skip(1) {
+        while hull.len() >= lower_len && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    hull.pop(); // The first point is repeated at the end.
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HITBOX: Hitbox = Hitbox {
+        mins: v!(-10, -5),
+        maxs: v!(10 5),
+    };
+    const POINT: Hitbox = Hitbox {
+        mins: v!(0 0),
+        maxs: v!(0 0),
+    };
+
+    #[test]
+    fn test_corners() {
+        let corners = HITBOX.corners(v!(100 100), 0.0);
+        assert_eq!(corners, [v!(90 95), v!(110 95), v!(110 105), v!(90 105)]);
+
+        let corners = POINT.corners(v!(100 100), 1.0);
+        assert!(corners.iter().all(|&corner| corner == v!(100 100)));
+    }
+
+    #[test]
+    fn test_swept_corners() {
+        // Moving along an axis, the middle corners are inside or on the edge.
+        let hull = HITBOX.swept_corners(v!(0 0), v!(100 0), 0.0);
+        assert_eq!(hull.len(), 4);
+        for p in [v!(-10, -5), v!(110, -5), v!(110 5), v!(-10 5)] {
+            assert!(hull.contains(&p));
+        }
+
+        // Moving diagonally adds 2 points.
+        let hull = HITBOX.swept_corners(v!(0 0), v!(100 100), 0.0);
+        assert_eq!(hull.len(), 6);
+        assert!(!hull.contains(&v!(10 5)));
+        assert!(!hull.contains(&v!(90 95)));
+
+        // Not moving
+        let hull = HITBOX.swept_corners(v!(0 0), v!(0 0), 0.0);
+        assert_eq!(hull.len(), 4);
+
+        // Degenerate
+        let hull = POINT.swept_corners(v!(0 0), v!(100 0), 0.0);
+        assert_eq!(hull, vec![v!(0 0), v!(100 0)]);
+        let hull = POINT.swept_corners(v!(0 0), v!(0 0), 0.0);
+        assert_eq!(hull, vec![v!(0 0)]);
+    }
+
+    #[test]
+    fn test_contains_point() {
+        assert!(HITBOX.contains_point(v!(0 0), 0.0, v!(0 0)));
+        assert!(HITBOX.contains_point(v!(0 0), 0.0, v!(10 5)));
+        assert!(HITBOX.contains_point(v!(0 0), 0.0, v!(9 0)));
+        assert!(!HITBOX.contains_point(v!(0 0), 0.0, v!(0 9)));
+
+        // Rotated 90 degrees, the long side is now along Y.
+        assert!(!HITBOX.contains_point(v!(0 0), PI / 2.0, v!(9 0)));
+        assert!(HITBOX.contains_point(v!(0 0), PI / 2.0, v!(0 9)));
+
+        assert!(POINT.contains_point(v!(5 5), 0.0, v!(5 5)));
+        assert!(!POINT.contains_point(v!(5 5), 0.0, v!(5 6)));
+    }
+
+    #[test]
+    fn test_closest_point() {
+        fn close(a: Vec2f, b: Vec2f) -> bool {
+            (a - b).magnitude() < 1e-9
+        }
+
+        // Inside
+        assert_eq!(HITBOX.closest_point(v!(0 0), 0.0, v!(3 2)), v!(3 2));
+        // Near an edge
+        assert!(close(HITBOX.closest_point(v!(0 0), 0.0, v!(3 20)), v!(3 5)));
+        assert!(close(
+            HITBOX.closest_point(v!(0 0), 0.0, v!(30, -2)),
+            v!(10, -2)
+        ));
+        // Near a corner
+        assert!(close(
+            HITBOX.closest_point(v!(0 0), 0.0, v!(20 20)),
+            v!(10 5)
+        ));
+        assert!(close(
+            HITBOX.closest_point(v!(100 100), 0.0, v!(80 80)),
+            v!(90 95)
+        ));
+
+        // Rotated 90 degrees, the long side is now along Y.
+        assert!(close(
+            HITBOX.closest_point(v!(0 0), PI / 2.0, v!(0 20)),
+            v!(0 10)
+        ));
+        assert!(close(
+            HITBOX.closest_point(v!(0 0), PI / 2.0, v!(20 20)),
+            v!(5 10)
+        ));
+    }
+
+    #[test]
+    fn test_overlaps_rect() {
+        // Fully inside, fully containing, partial.
+        assert!(HITBOX.overlaps_rect(v!(0 0), 0.0, v!(-50, -50), v!(50 50)));
+        assert!(HITBOX.overlaps_rect(v!(0 0), 0.0, v!(-1, -1), v!(1 1)));
+        assert!(HITBOX.overlaps_rect(v!(0 0), 0.0, v!(8 0), v!(50 50)));
+        // Just past the long side.
+        assert!(!HITBOX.overlaps_rect(v!(0 0), 0.0, v!(11, -50), v!(50 50)));
+        // Rotated 90 degrees, the long side is now along Y.
+        assert!(!HITBOX.overlaps_rect(v!(0 0), PI / 2.0, v!(6, -50), v!(50 50)));
+        assert!(HITBOX.overlaps_rect(v!(0 0), PI / 2.0, v!(-50 8), v!(50 50)));
+        // Rotated 45 degrees - the bounding boxes overlap near the corner but the shapes don't.
+        assert!(!HITBOX.overlaps_rect(v!(0 0), PI / 4.0, v!(6, -20), v!(20, -6)));
+        assert!(HITBOX.overlaps_rect(v!(0 0), PI / 4.0, v!(6 6), v!(20 20)));
+    }
+
+    #[test]
+    fn test_intersects_segment() {
+        // Crossing
+        assert!(HITBOX.intersects_segment(v!(0 0), 0.0, v!(-20 0), v!(20 0)));
+        // Fully inside
+        assert!(HITBOX.intersects_segment(v!(0 0), 0.0, v!(-1 0), v!(1 0)));
+        // Ends before reaching it
+        assert!(!HITBOX.intersects_segment(v!(0 0), 0.0, v!(-30 0), v!(-20 0)));
+        // Parallel, outside
+        assert!(!HITBOX.intersects_segment(v!(0 0), 0.0, v!(-20 10), v!(20 10)));
+        // Rotated - misses the short side but hits the long side.
+        assert!(!HITBOX.intersects_segment(v!(0 0), 0.0, v!(-20 8), v!(20 8)));
+        assert!(HITBOX.intersects_segment(v!(0 0), PI / 2.0, v!(-20 8), v!(20 8)));
+        // Zero length
+        assert!(HITBOX.intersects_segment(v!(0 0), 0.0, v!(1 1), v!(1 1)));
+        assert!(!HITBOX.intersects_segment(v!(0 0), 0.0, v!(20 20), v!(20 20)));
+
+        assert!(POINT.intersects_segment(v!(0 0), 0.0, v!(-1 0), v!(1 0)));
+        assert!(!POINT.intersects_segment(v!(0 0), 0.0, v!(-1 1), v!(1 1)));
+    }
+
+    #[test]
+    fn test_movement_factor() {
+        let cvars = Cvars {
+            g_turret_misalign_angle: PI / 2.0,
+            g_turret_misalign_speed_factor: 0.8,
+            ..Cvars::default()
+        };
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+
+        assert_eq!(vehicle.movement_factor(&cvars), 1.0);
+
+        // Within the threshold on both sides
+        vehicle.turret_angle_current = 1.5;
+        assert_eq!(vehicle.movement_factor(&cvars), 1.0);
+        vehicle.turret_angle_current = 2.0 * PI - 1.5;
+        assert_eq!(vehicle.movement_factor(&cvars), 1.0);
+
+        // Beyond
+        vehicle.turret_angle_current = 1.6;
+        assert_eq!(vehicle.movement_factor(&cvars), 0.8);
+        vehicle.turret_angle_current = PI;
+        assert_eq!(vehicle.movement_factor(&cvars), 0.8);
+        vehicle.turret_angle_current = -1.6;
+        assert_eq!(vehicle.movement_factor(&cvars), 0.8);
+
+        // Disabled by default
+        assert_eq!(vehicle.movement_factor(&Cvars::default()), 1.0);
+    }
+
+    #[test]
+    fn test_interpolated_angles() {
+        let cvars = Cvars::default();
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            1.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        assert_eq!(vehicle.interpolated_angles(0.5), (1.0, 0.0));
+
+        // The turret crosses the seam, the hull doesn't.
+        vehicle.prev_turret_angle = 350.0f64.to_radians();
+        vehicle.turret_angle_current = 10.0f64.to_radians();
+        vehicle.angle = 2.0;
+        let (hull, turret) = vehicle.interpolated_angles(0.25);
+        assert!((hull - 1.25).abs() < 1e-9, "{hull}");
+        assert!((turret - 355.0f64.to_radians()).abs() < 1e-9, "{turret}");
+        let (hull, turret) = vehicle.interpolated_angles(1.0);
+        assert!((hull - 2.0).abs() < 1e-9, "{hull}");
+        assert!((turret - 10.0f64.to_radians()).abs() < 1e-9, "{turret}");
+    }
+
+    #[test]
+    fn test_stat_modifiers_compose() {
+        let cvars = Cvars {
+            g_cripple_hp_threshold: 0.5,
+            g_cripple_mobility_factor: 0.5,
+            g_cripple_turn_factor: 0.25,
+            ..Cvars::default()
+        };
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        let base = cvars.g_vehicle_movement_stats(VehicleType::Tank);
+        vehicle.hp_fraction = 0.25;
+        vehicle.modifiers.add(StatModifier {
+            mobility: 4.0,
+            turn: 1.0,
+            turn_effectiveness: 0.5,
+            until: 10.0,
+        });
+        vehicle.modifiers.add(StatModifier {
+            mobility: 2.0,
+            turn: 2.0,
+            turn_effectiveness: 1.0,
+            until: 5.0,
+        });
+
+        // Crippled and both boosts
+        let stats = vehicle.movement_stats(&cvars, 0.0);
+        assert_eq!(stats.speed_max, base.speed_max * 0.5 * 4.0 * 2.0);
+        assert_eq!(stats.accel_forward, base.accel_forward * 0.5 * 4.0 * 2.0);
+        assert_eq!(stats.turn_rate_max, base.turn_rate_max * 0.25 * 2.0);
+        assert_eq!(stats.turn_effectiveness, base.turn_effectiveness * 0.5);
+
+        // One expired, even before it's removed
+        let stats = vehicle.movement_stats(&cvars, 5.0);
+        assert_eq!(stats.speed_max, base.speed_max * 0.5 * 4.0);
+        assert_eq!(stats.turn_rate_max, base.turn_rate_max * 0.25);
+
+        // Repaired, the boost stays
+        vehicle.hp_fraction = 1.0;
+        let stats = vehicle.movement_stats(&cvars, 5.0);
+        assert_eq!(stats.speed_max, base.speed_max * 4.0);
+    }
+
+    #[test]
+    fn test_stat_modifiers_expiry() {
+        let mut modifiers = StatModifiers::new();
+        let modifier = StatModifier {
+            mobility: 2.0,
+            turn: 1.0,
+            turn_effectiveness: 1.0,
+            until: 1.5,
+        };
+        modifiers.add(modifier);
+        assert!(modifier.active(1.4999));
+        assert!(!modifier.active(1.5));
+
+        modifiers.remove_expired(1.4999);
+        assert_eq!(modifiers.modifiers.len(), 1);
+        modifiers.remove_expired(1.5);
+        assert!(modifiers.modifiers.is_empty());
+    }
+
+    #[test]
+    fn test_boost_charge() {
+        let cvars = Cvars {
+            g_boost_cooldown: 4.0,
+            g_boost_duration: 2.0,
+            ..Cvars::default()
+        };
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        assert_eq!(vehicle.boost_charge(&cvars, 0.0), 1.0);
+
+        vehicle.boost_until = 12.0;
+        vehicle.boost_ready_time = 16.0;
+        assert_eq!(vehicle.boost_charge(&cvars, 10.0), 1.0);
+        assert_eq!(vehicle.boost_charge(&cvars, 11.0), 0.5);
+        assert_eq!(vehicle.boost_charge(&cvars, 12.0), 0.0);
+        assert_eq!(vehicle.boost_charge(&cvars, 15.0), 0.75);
+        assert_eq!(vehicle.boost_charge(&cvars, 17.0), 1.0);
+    }
+
+    #[test]
+    fn test_flight_airborne() {
+        let cvars = Cvars {
+            g_airborne_height: 0.1,
+            ..Cvars::default()
+        };
+        let flight = Flight {
+            start_time: 10.0,
+            duration: 1.0,
+            apex: 1.0,
+        };
+
+        assert_eq!(flight.height(10.5), 1.0);
+        assert_eq!(flight.height(11.0), 0.0);
+        assert_eq!(flight.height(12.0), 0.0);
+
+        // Launched from the ground but it's on the way up.
+        assert!(flight.airborne(&cvars, 10.0));
+        assert!(flight.airborne(&cvars, 10.5));
+        assert!(flight.airborne(&cvars, 10.95));
+        // Low enough to hit things.
+        assert!(!flight.airborne(&cvars, 10.98));
+        assert!(!flight.airborne(&cvars, 11.0));
+        assert!(!flight.airborne(&cvars, 11.5));
+    }
+
+    #[test]
+    fn test_hit_arc() {
+        let cvars = Cvars {
+            g_armor_arc_front: 45.0f64.to_radians(),
+            g_armor_arc_rear: 60.0f64.to_radians(),
+            ..Cvars::default()
+        };
+        // Direction of a hit coming from `deg` degrees (world angle).
+        let from = |deg: f64| -deg.to_radians().to_vec2f();
+        let classify = |facing_deg: f64, from_deg: f64| {
+            HitArc::classify(&cvars, facing_deg.to_radians(), from(from_deg))
+        };
+
+        assert_eq!(classify(0.0, 0.0), Some(HitArc::Front));
+        assert_eq!(classify(0.0, 90.0), Some(HitArc::Side));
+        assert_eq!(classify(0.0, 270.0), Some(HitArc::Side));
+        assert_eq!(classify(0.0, 180.0), Some(HitArc::Rear));
+
+        // Boundaries
+        assert_eq!(classify(0.0, 44.999), Some(HitArc::Front));
+        assert_eq!(classify(0.0, 45.001), Some(HitArc::Side));
+        assert_eq!(classify(0.0, -44.999), Some(HitArc::Front));
+        assert_eq!(classify(0.0, -45.001), Some(HitArc::Side));
+        assert_eq!(classify(0.0, 119.999), Some(HitArc::Side));
+        assert_eq!(classify(0.0, 120.001), Some(HitArc::Rear));
+        assert_eq!(classify(0.0, 240.001), Some(HitArc::Side));
+        assert_eq!(classify(0.0, 239.999), Some(HitArc::Rear));
+
+        // Wrapping around 0 / 360 deg
+        assert_eq!(classify(350.0, 20.0), Some(HitArc::Front));
+        assert_eq!(classify(10.0, 340.0), Some(HitArc::Front));
+        assert_eq!(classify(10.0, 300.0), Some(HitArc::Side));
+        assert_eq!(classify(-170.0, 0.0), Some(HitArc::Rear));
+        assert_eq!(classify(720.0 + 90.0, 90.0), Some(HitArc::Front));
+
+        assert_eq!(HitArc::classify(&cvars, 0.0, Vec2f::zero()), None);
+    }
+
+    #[test]
+    fn test_repair_and_armor_caps() {
+        let cvars = Cvars {
+            g_overheal_max: 1.5,
+            ..Cvars::default()
+        };
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+
+        vehicle.hp_fraction = 0.5;
+        vehicle.repair(0.3);
+        assert_eq!(vehicle.hp_fraction, 0.8);
+        vehicle.repair(0.3);
+        assert_eq!(vehicle.hp_fraction, 1.0);
+        assert_eq!(vehicle.overheal(), 0.0);
+
+        vehicle.add_armor(&cvars, 0.3);
+        assert!((vehicle.overheal() - 0.3).abs() < 1e-12);
+        vehicle.add_armor(&cvars, 0.3);
+        assert_eq!(vehicle.hp_fraction, 1.5);
+
+        // Repair doesn't take away overheal.
+        vehicle.repair(0.3);
+        assert_eq!(vehicle.hp_fraction, 1.5);
+
+        // Armor doesn't cut HP which is above the max already, e.g. after lowering it.
+        let lower = Cvars {
+            g_overheal_max: 1.2,
+            ..Cvars::default()
+        };
+        vehicle.add_armor(&lower, 0.1);
+        assert_eq!(vehicle.hp_fraction, 1.5);
+        vehicle.hp_fraction = 1.1;
+        vehicle.add_armor(&lower, 0.5);
+        assert_eq!(vehicle.hp_fraction, 1.2);
+    }
+
+    #[test]
+    fn test_weapon_state_refire() {
+        let cvars = Cvars::default();
+        let refire = cvars.g_weapon_refire(Weapon::Mg);
+        let mut state = WeaponState::new(cvars.g_weapon_reload_ammo(Weapon::Mg));
+        assert!(state.can_fire(0.0));
+
+        state.fire(&cvars, Weapon::Mg, 1.0);
+        assert_eq!(
+            state.rounds_loaded,
+            cvars.g_weapon_reload_ammo(Weapon::Mg) - 1
+        );
+        assert!(!state.can_fire(1.0 + refire * 0.5));
+        assert!(state.can_fire(1.0 + refire));
+
+        // Rest of a burst doesn't delay the next shot.
+        state.consume_round(&cvars, Weapon::Mg, 1.0);
+        assert!(state.can_fire(1.0 + refire));
+    }
+
+    #[test]
+    fn test_weapon_state_reload() {
+        let cvars = Cvars {
+            g_rockets_reload_ammo: 2,
+            g_rockets_reload_time: 1.5,
+            ..Cvars::default()
+        };
+        let mut state = WeaponState::new(2);
+        state.fire(&cvars, Weapon::Rockets, 0.0);
+        assert!(!state.is_reloading());
+        assert_eq!(state.fraction(&cvars, Weapon::Rockets, 0.0), 0.5);
+
+        // Empty magazine
+        state.fire(&cvars, Weapon::Rockets, 10.0);
+        assert_eq!(state.rounds_loaded, 0);
+        assert_eq!(
+            state.reload,
+            Some(Reload {
+                start: 10.0,
+                end: 11.5
+            })
+        );
+        assert!(!state.can_fire(20.0));
+        assert_eq!(state.fraction(&cvars, Weapon::Rockets, 10.75), 0.5);
+
+        state.tick(&cvars, Weapon::Rockets, 11.4);
+        assert!(state.is_reloading());
+
+        // Reload completion can fire right away.
+        state.tick(&cvars, Weapon::Rockets, 11.5);
+        assert!(!state.is_reloading());
+        assert_eq!(state.rounds_loaded, 2);
+        assert!(state.can_fire(11.5));
+        assert_eq!(state.fraction(&cvars, Weapon::Rockets, 11.5), 1.0);
+    }
 }
@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+
 use crate::prelude::*;
 
 /// Things that change during the game
@@ -19,27 +21,56 @@ pub struct GameState {
     /// Delta time since last gamelogic frame in seconds.
     pub dt: f64,
 
-    /// Game time after which the match ends
+    /// Game time after which the match ends, infinite if there's no `g_time_limit`
     pub time_limit: f64,
     pub game_mode: GameMode,
+    /// Where the match is between start and end, see `match_flow`.
+    pub phase: Phase,
+    /// All damage is multiplied by this, grows during `OvertimeMode::Escalation`.
+    pub damage_multiplier: f64,
 
     pub ais: Arena<Ai>,
     pub players: Arena<Player>,
     pub vehicles: Arena<Vehicle>,
     pub projectiles: Arena<Projectile>,
 
-    /// Map of projectile handles to vehicle handles.
-    /// Prevents rail hitting the same vehicle twice
+    /// Stable IDs of entities in the arenas above, see `stable_id`.
+    /// Must be updated whenever an entity is added or removed.
+    pub player_ids: StableIds,
+    pub vehicle_ids: StableIds,
+    pub projectile_ids: StableIds,
+
+    /// Targets (vehicles and emplacements) each railgun beam currently ends inside of.
+    /// Prevents rail hitting the same target twice
     /// when one segment ends inside the hitbox and the next starts inside it the next frame.
-    /// This can for now only happen with railguns since all other projectiles get removed on hit.
-    /// LATER This is still not perfect since one segment can hit multiple vehicles in any order
-    /// and there's no guarantee the last vehicle is the one where the beam ends.
-    /// LATER This is a can of worms:
-    ///     1) Make sure (add test) one beam can kill the player and hit him again if he's unlucky enough to respawn in its path.
-    ///     2) Remove the entry after the projectile exits the hitbox - e.g. guided missiles that can pass through several times.
-    ///     3) Make sure the HashMap doesn't grow indefinitely in case we forgot to remove in some cases.
-    ///     4) Why is this even a hashmap? Keep this as SmallVec/Set on projectile?
-    pub rail_hits: FnvHashMap<Index, Index>,
+    /// A target is removed once the beam exits it so a vehicle which respawns in its path gets hit again.
+    /// Entries are removed together with the projectile.
+    pub rail_hits: FnvHashMap<Index, FnvHashSet<TargetKind>>,
+
+    /// Smoke screens currently on the map.
+    /// Unlike explosions they affect gameplay so they're here and not in ClientGame.
+    pub smokes: Vec<Smoke>,
+
+    /// Flares currently in the air, homing missiles can target them so they need handles.
+    pub flares: Arena<Flare>,
+
+    /// Mines on the ground, see `mines`. Clients are told about each one so they need handles.
+    pub mines: Arena<Mine>,
+
+    /// Stationary guns placed by the map, see `emplacements`.
+    /// Destroyed ones stay as scenery until the next match.
+    pub emplacements: Arena<Emplacement>,
+
+    /// Scenery, see `ServerFrameCtx::spawn_wrecks`.
+    pub wrecks: Vec<Wreck>,
+
+    /// The shrinking safe area if `g_zone` is on.
+    pub zone: Option<Zone>,
+
+    /// Kills nobody else gets credit for - the zone, sinking with no recent push
+    /// and projectiles or mines whose owner has left (`OrphanProjectiles::Keep`).
+    /// Only `kills` is used.
+    pub world_score: Score,
 }
 
 impl GameState {
@@ -53,13 +84,33 @@ impl GameState {
 
             time_limit: 10.0 * 60.0,
             game_mode: GameMode::Ffa(Ffa { kill_limit: 20 }),
+            phase: Phase::Playing,
+            damage_multiplier: 1.0,
 
             ais: Arena::new(),
             players: Arena::new(),
             vehicles: Arena::new(),
             projectiles: Arena::new(),
 
+            player_ids: StableIds::new(),
+            vehicle_ids: StableIds::new(),
+            projectile_ids: StableIds::new(),
+
             rail_hits: FnvHashMap::default(),
+
+            smokes: Vec::new(),
+
+            flares: Arena::new(),
+
+            mines: Arena::new(),
+
+            emplacements: Arena::new(),
+
+            wrecks: Vec::new(),
+
+            zone: None,
+
+            world_score: Score::default(),
         }
     }
 }
@@ -95,19 +146,45 @@ pub struct Ctc {
     pub capture_limit: i32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Phase {
+    Playing,
+    /// The time ran out with the lead tied.
+    Overtime {
+        start_time: f64,
+        mode: OvertimeMode,
+    },
+    /// Decided, no more changes to the winner.
+    RoundEnd {
+        time: f64,
+        /// None for a draw.
+        winner: Option<Winner>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Winner {
+    /// Player's stable ID, see `stable_id`.
+    Player(u32),
+    Team(u32),
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RailBeam {
     pub begin: Vec2f,
     pub end: Vec2f,
     pub start_time: f64,
+    /// See `Projectile::team`.
+    pub team: Option<u32>,
 }
 
 impl RailBeam {
-    pub fn new(begin: Vec2f, end: Vec2f, start_time: f64) -> Self {
+    pub fn new(begin: Vec2f, end: Vec2f, start_time: f64, team: Option<u32>) -> Self {
         Self {
             begin,
             end,
             start_time,
+            team,
         }
     }
 }
@@ -118,15 +195,262 @@ pub struct Explosion {
     pub scale: f64,
     pub start_time: f64,
     pub bfg: bool,
+    /// Creation order, see `ClientGame::add_explosion`.
+    pub seq: u64,
+    /// See `ExplosionInit::team`.
+    pub team: Option<u32>,
 }
 
 impl Explosion {
-    pub fn new(pos: Vec2f, scale: f64, start_time: f64, bfg: bool) -> Self {
+    pub fn new(pos: Vec2f, scale: f64, start_time: f64, bfg: bool, seq: u64) -> Self {
         Self {
             pos,
             scale,
             start_time,
             bfg,
+            seq,
+            team: None,
+        }
+    }
+
+    /// Which of the two is drawn first (below the other).
+    ///
+    /// Oldest first, newest first with `reverse`.
+    /// Explosions created at the same time (e.g. self-destruct)
+    /// go by creation order so they don't z-fight depending on where they are in the Vec.
+    pub fn draw_order(&self, other: &Explosion, reverse: bool) -> Ordering {
+        let order = self
+            .start_time
+            .total_cmp(&other.start_time)
+            .then(self.seq.cmp(&other.seq));
+        if reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    }
+}
+
+/// Blocks homing missiles' view but projectiles fly through it.
+#[derive(Debug, Clone)]
+pub struct Smoke {
+    pub pos: Vec2f,
+    pub radius: f64,
+    pub start_time: f64,
+    /// Handle of the player who deployed it.
+    pub owner: Index,
+}
+
+impl Smoke {
+    pub fn new(pos: Vec2f, radius: f64, start_time: f64, owner: Index) -> Self {
+        Self {
+            pos,
+            radius,
+            start_time,
+            owner,
+        }
+    }
+}
+
+/// Distracts homing missiles, see `g_flare_*`.
+///
+/// Moves in a straight line so the client doesn't need position updates.
+#[derive(Debug, Clone)]
+pub struct Flare {
+    /// Where it was deployed.
+    pub pos: Vec2f,
+    pub vel: Vec2f,
+    pub start_time: f64,
+    /// Handle of the player who deployed it.
+    pub owner: Index,
+}
+
+impl Flare {
+    pub fn new(pos: Vec2f, vel: Vec2f, start_time: f64, owner: Index) -> Self {
+        Self {
+            pos,
+            vel,
+            start_time,
+            owner,
         }
     }
+
+    pub fn pos_at(&self, game_time: f64) -> Vec2f {
+        self.pos + self.vel * (game_time - self.start_time)
+    }
+
+    /// Still hot enough to pull homing missiles away from vehicles.
+    pub fn spoofing(&self, cvars: &Cvars, game_time: f64) -> bool {
+        game_time - self.start_time < cvars.g_flare_spoof_duration
+    }
+}
+
+/// Dropped behind a vehicle with the mine key, see `mines`.
+///
+/// Doesn't move so the client only needs to know when it's dropped and when it's gone.
+#[derive(Debug, Clone)]
+pub struct Mine {
+    pub pos: Vec2f,
+    pub spawn_time: f64,
+    /// Handle of the player who dropped it.
+    pub owner: Index,
+    /// Copy of the owner's team, see `Projectile::team`.
+    pub team: Option<u32>,
+}
+
+impl Mine {
+    pub fn new(pos: Vec2f, spawn_time: f64, owner: Index, team: Option<u32>) -> Self {
+        Self {
+            pos,
+            spawn_time,
+            owner,
+            team,
+        }
+    }
+
+    /// Can be set off by vehicles, see `g_mine_arm_time`.
+    pub fn armed(&self, cvars: &Cvars, game_time: f64) -> bool {
+        game_time - self.spawn_time >= cvars.g_mine_arm_time
+    }
+
+    /// The owner can still drive over it safely, see `g_mine_owner_immunity`.
+    pub fn owner_immune(&self, cvars: &Cvars, game_time: f64) -> bool {
+        game_time - self.spawn_time < cvars.g_mine_owner_immunity
+    }
+}
+
+/// A stationary gun placed by the map, see `emplacements`.
+///
+/// Not a `Vehicle` because those always belong to a player, move and carry every weapon.
+/// Emplacements have a fixed base, a turret and one weapon and are either
+/// unmanned (aimed by the server) or manned by a player standing next to it.
+#[derive(Debug, Clone)]
+pub struct Emplacement {
+    pub pos: Vec2f,
+    /// Facing of the base, never changes.
+    pub angle: f64,
+    /// Relative to `angle`, same as `Vehicle::turret_angle_current`.
+    pub turret_angle_current: f64,
+    pub turret_angle_wanted: f64,
+    /// See `Vehicle::turret_turn_rate`.
+    pub turret_turn_rate: f64,
+    pub weapon: Weapon,
+    pub weapon_state: WeaponState,
+    pub hp_fraction: f64,
+    /// Handle of the player manning it, None when unmanned.
+    pub controller: Option<Index>,
+    /// Only turret and fire are used, set either by the controller or the server's aiming.
+    pub input: NetInput,
+    pub input_prev: NetInput,
+    /// The vehicle an unmanned emplacement is aiming at.
+    pub target: Option<Index>,
+}
+
+impl Emplacement {
+    pub fn new(cvars: &Cvars, pos: Vec2f, angle: f64, weapon: Weapon) -> Self {
+        Self {
+            pos,
+            angle,
+            turret_angle_current: 0.0,
+            turret_angle_wanted: 0.0,
+            turret_turn_rate: 0.0,
+            weapon,
+            weapon_state: WeaponState::new(cvars.g_weapon_reload_ammo(weapon)),
+            hp_fraction: 1.0,
+            controller: None,
+            input: NetInput::empty(),
+            input_prev: NetInput::empty(),
+            target: None,
+        }
+    }
+
+    pub fn destroyed(&self) -> bool {
+        self.hp_fraction <= 0.0
+    }
+}
+
+/// Safe area of `g_zone`, vehicles outside take damage.
+///
+/// Shrinks linearly from `start_*` to `target_*` so the client can compute it
+/// from the game time and only needs to get it once.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Zone {
+    pub start_center: Vec2f,
+    pub start_radius: f64,
+    pub target_center: Vec2f,
+    pub target_radius: f64,
+    pub shrink_start: f64,
+    pub shrink_end: f64,
+}
+
+impl Zone {
+    /// From 0 before it starts shrinking to 1 once it's reached the target.
+    pub fn progress(&self, game_time: f64) -> f64 {
+        if game_time >= self.shrink_end {
+            return 1.0;
+        }
+        let duration = self.shrink_end - self.shrink_start;
+        ((game_time - self.shrink_start) / duration).clamped(0.0, 1.0)
+    }
+
+    /// Returns (center, radius) at `game_time`.
+    pub fn at(&self, game_time: f64) -> (Vec2f, f64) {
+        let progress = self.progress(game_time);
+        let center = Vec2f::lerp(self.start_center, self.target_center, progress);
+        let radius = f64::lerp(self.start_radius, self.target_radius, progress);
+        (center, radius)
+    }
+
+    pub fn contains(&self, game_time: f64, pos: Vec2f) -> bool {
+        let (center, radius) = self.at(game_time);
+        pos.distance_squared(center) <= radius * radius
+    }
+}
+
+/// Whether any smoke screen is between the two points.
+///
+/// LATER Use for bot line of sight once bots actually look for targets.
+pub fn smoke_blocks_view(smokes: &[Smoke], begin: Vec2f, end: Vec2f) -> bool {
+    let segment = end - begin;
+    let len_sq = segment.magnitude_squared();
+    smokes.iter().any(|smoke| {
+        // Closest point of the segment to the center
+        let t = if len_sq == 0.0 {
+            0.0
+        } else {
+            ((smoke.pos - begin).dot(segment) / len_sq).clamped(0.0, 1.0)
+        };
+        let closest = begin + segment * t;
+        closest.distance(smoke.pos) < smoke.radius
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explosion_draw_order() {
+        let explosion =
+            |start_time, seq| Explosion::new(Vec2f::zero(), 1.0, start_time, false, seq);
+        let older = explosion(1.0, 5);
+        let newer = explosion(2.0, 3);
+        assert_eq!(older.draw_order(&newer, false), Ordering::Less);
+        assert_eq!(older.draw_order(&newer, true), Ordering::Greater);
+
+        // Same time - creation order decides.
+        let first = explosion(2.0, 7);
+        let second = explosion(2.0, 8);
+        assert_eq!(first.draw_order(&second, false), Ordering::Less);
+        assert_eq!(first.draw_order(&second, true), Ordering::Greater);
+        assert_eq!(first.draw_order(&first, false), Ordering::Equal);
+
+        let mut explosions = [second.clone(), older.clone(), first.clone(), newer.clone()];
+        explosions.sort_by(|a, b| a.draw_order(b, false));
+        let seqs: Vec<_> = explosions.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [5, 3, 7, 8]);
+        explosions.sort_by(|a, b| a.draw_order(b, true));
+        let seqs: Vec<_> = explosions.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [8, 7, 3, 5]);
+    }
 }
@@ -0,0 +1,283 @@
+//! Rewinding the simulation for debugging.
+//!
+//! Physics bugs are often noticed a moment after they happen.
+//! The server keeps a snapshot after each of the last `d_history_seconds` worth of ticks,
+//! `Server::history_rewind` restores one of them and pauses,
+//! `Server::history_resume` continues from there, discarding the overwritten future.
+//!
+//! This relies on the simulation being deterministic given `GameState` and the RNG.
+//! Remote clients only get synced by the next update after resuming.
+//!
+//! Only compiled with the `history` feature because cloning the whole state every tick is not free.
+//!
+//! Clients rewind with PageUp and resume with PageDown (see `HistoryCommand`), the server needs `d_cheats`.
+//! The position is shown in the debug overlay while rewound,
+//! the snapshot size in the server part of the perf overlay.
+
+use std::mem;
+
+use crate::prelude::*;
+
+/// Everything needed to continue the simulation from a past tick.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub gs: GameState,
+    pub rng: Xoshiro256PlusPlus,
+}
+
+impl Snapshot {
+    /// Rough size in bytes, only counts the entities, not their heap allocations.
+    pub fn approx_size(&self) -> usize {
+        let gs = &self.gs;
+        mem::size_of::<Self>()
+            + gs.ais.len() * mem::size_of::<Ai>()
+            + gs.players.len() * mem::size_of::<Player>()
+            + gs.vehicles.len() * mem::size_of::<Vehicle>()
+            + gs.projectiles.len() * mem::size_of::<Projectile>()
+            + gs.rail_hits
+                .values()
+                .map(|hits| mem::size_of::<Index>() + hits.len() * mem::size_of::<TargetKind>())
+                .sum::<usize>()
+            + gs.smokes.len() * mem::size_of::<Smoke>()
+            + gs.flares.len() * mem::size_of::<Flare>()
+            + gs.mines.len() * mem::size_of::<Mine>()
+            + gs.emplacements.len() * mem::size_of::<Emplacement>()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest first.
+    pub snapshots: VecDeque<Snapshot>,
+    /// Index of the restored snapshot while rewound, None while running live.
+    pub cursor: Option<usize>,
+}
+
+impl History {
+    /// Max number of snapshots to keep.
+    pub fn capacity(cvars: &Cvars) -> usize {
+        (cvars.d_history_seconds * cvars.sys_tickrate_fixed_fps).ceil() as usize
+    }
+
+    /// Approximate size of the newest snapshot in bytes, 0 if there are none.
+    pub fn snapshot_size(&self) -> usize {
+        self.snapshots.back().map_or(0, Snapshot::approx_size)
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        HistoryStatus {
+            cursor: self.cursor.map(|cursor| cursor as u32),
+            snapshots: self.snapshots.len() as u32,
+        }
+    }
+
+    pub fn push(&mut self, capacity: usize, snapshot: Snapshot) {
+        while !self.snapshots.is_empty() && self.snapshots.len() >= capacity {
+            self.snapshots.pop_front();
+        }
+        if capacity > 0 {
+            self.snapshots.push_back(snapshot);
+        }
+    }
+}
+
+impl Server {
+    /// Save the state after a tick.
+    pub fn history_record(&mut self, cvars: &Cvars) {
+        let snapshot = Snapshot {
+            gs: self.gs.clone(),
+            rng: self.sg.rng.clone(),
+        };
+        self.sg.history.push(History::capacity(cvars), snapshot);
+    }
+
+    /// Rewind by `d_history_rewind_seconds` or resume.
+    pub fn history_command(&mut self, cvars: &Cvars, command: HistoryCommand) {
+        match command {
+            HistoryCommand::Rewind => {
+                let ticks = cvars.d_history_rewind_seconds * cvars.sys_tickrate_fixed_fps;
+                if !self.history_rewind(cvars, (ticks.round() as usize).max(1)) {
+                    dbg_logf!("History: nothing to rewind to");
+                }
+            }
+            HistoryCommand::Resume => self.history_resume(cvars),
+        }
+    }
+
+    /// Restore the state from `ticks` ago (relative to the current position in history) and pause.
+    ///
+    /// Returns false if there's no history.
+    pub fn history_rewind(&mut self, cvars: &Cvars, ticks: usize) -> bool {
+        let history = &mut self.sg.history;
+        let Some(last) = history.snapshots.len().checked_sub(1) else {
+            return false;
+        };
+        let current = history.cursor.unwrap_or(last);
+        let target = current.saturating_sub(ticks);
+        history.cursor = Some(target);
+
+        let snapshot = &history.snapshots[target];
+        self.gs = snapshot.gs.clone();
+        self.sg.rng = snapshot.rng.clone();
+        dbg_logf!(
+            "History: frame {} ({}/{}), ~{} KiB per snapshot",
+            self.gs.frame_num,
+            target + 1,
+            last + 1,
+            snapshot.approx_size() / 1024,
+        );
+
+        self.set_paused(cvars, true);
+        let status = self.sg.history.status();
+        self.ctx(cvars).net_send_all(ServerMessage::History(status));
+        true
+    }
+
+    /// Continue from the restored state, the snapshots after it are discarded.
+    pub fn history_resume(&mut self, cvars: &Cvars) {
+        if let Some(cursor) = self.sg.history.cursor.take() {
+            self.sg.history.snapshots.truncate(cursor + 1);
+        }
+        self.set_paused(cvars, false);
+        let status = self.sg.history.status();
+        self.ctx(cvars).net_send_all(ServerMessage::History(status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn snapshot(frame_num: usize) -> Snapshot {
+        let mut gs = GameState::new();
+        gs.frame_num = frame_num;
+        Snapshot {
+            gs,
+            rng: Xoshiro256PlusPlus::seed_from_u64(0),
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut history = History::default();
+        for i in 0..10 {
+            history.push(3, snapshot(i));
+        }
+        let frames: Vec<_> = history.snapshots.iter().map(|s| s.gs.frame_num).collect();
+        assert_eq!(frames, vec![7, 8, 9]);
+
+        // Shrinking the capacity drops the oldest.
+        history.push(2, snapshot(10));
+        let frames: Vec<_> = history.snapshots.iter().map(|s| s.gs.frame_num).collect();
+        assert_eq!(frames, vec![9, 10]);
+
+        history.push(0, snapshot(11));
+        assert!(history.snapshots.is_empty());
+    }
+
+    fn state(server: &Server) -> String {
+        format!("{:?} {:?}", server.gs, server.sg.rng)
+    }
+
+    #[test]
+    fn test_rewind_replays_collision() {
+        let cvars = Cvars {
+            bots_max: 0,
+            d_history_seconds: 1.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+
+        // Sit still for a bit so there's enough history before the collision even if it's quick.
+        for _ in 0..40 {
+            test_utils::tick(&mut server, &cvars);
+        }
+
+        // Drive straight into a wall.
+        server.gs.players[player_handle].input = NetInput {
+            up: true,
+            ..NetInput::empty()
+        };
+
+        let bounced = |server: &Server| {
+            let vehicle = &server.gs.vehicles[vehicle_handle];
+            vehicle.vel.dot(vehicle.angle.to_vec2f()) < 0.0
+        };
+        let mut collision_state = None;
+        for _ in 0..1000 {
+            test_utils::tick(&mut server, &cvars);
+            if bounced(&server) {
+                collision_state = Some(state(&server));
+                break;
+            }
+        }
+        let collision_state = collision_state.expect("no collision");
+        let collision_frame = server.gs.frame_num;
+
+        for _ in 0..5 {
+            test_utils::tick(&mut server, &cvars);
+        }
+        let end_state = state(&server);
+
+        assert!(server.history_rewind(&cvars, 30));
+        assert!(server.sg.paused);
+        assert_eq!(server.gs.frame_num, collision_frame + 5 - 30);
+        assert!(!bounced(&server));
+
+        server.history_resume(&cvars);
+        assert!(!server.sg.paused);
+        for _ in 0..25 {
+            test_utils::tick(&mut server, &cvars);
+        }
+        assert_eq!(server.gs.frame_num, collision_frame);
+        assert_eq!(state(&server), collision_state);
+        for _ in 0..5 {
+            test_utils::tick(&mut server, &cvars);
+        }
+        assert_eq!(state(&server), end_state);
+    }
+
+    #[test]
+    fn test_history_command() {
+        let cvars = Cvars {
+            bots_max: 0,
+            d_history_rewind_seconds: 0.1,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        for _ in 0..20 {
+            test_utils::tick(&mut server, &cvars);
+        }
+        // 15 ticks back from the last of 20 snapshots.
+        server.history_command(&cvars, HistoryCommand::Rewind);
+        let status = HistoryStatus {
+            cursor: Some(4),
+            snapshots: 20,
+        };
+        assert_eq!(server.sg.history.status(), status);
+
+        server.history_command(&cvars, HistoryCommand::Resume);
+        let status = HistoryStatus {
+            cursor: None,
+            snapshots: 5,
+        };
+        assert_eq!(server.sg.history.status(), status);
+    }
+
+    #[test]
+    fn test_rewind_without_history() {
+        let cvars = Cvars {
+            d_history_seconds: 0.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        test_utils::tick(&mut server, &cvars);
+        assert!(!server.history_rewind(&cvars, 1));
+        assert!(!server.sg.paused);
+    }
+}
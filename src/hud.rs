@@ -0,0 +1,509 @@
+//! Screen-space HUD of the local player's vehicle - hit points, ammo, the weapon icon
+//! and the homing missile lock warning, plus the FPS counter and the performance warning.
+//!
+//! Split into three parts so most of it can be tested without a window:
+//! - `HudLayout` - where each element goes, resolved from the `hud_*` anchor cvars and the view
+//! - `HudData` - what to show, assembled from the game state
+//! - `draw_*` - the thin part which actually calls macroquad
+//!
+//! LATER Move the rest of the screen-space HUD here (score, ranking, notifications, perf info, ...).
+
+use macroquad::prelude::*;
+
+use crate::{
+    match_flow,
+    palette::{with_alpha, Palette},
+    prelude::*,
+    rendering::render_text_with_shadow,
+};
+
+/// If x or y are negative, count them from the right or bottom respectively.
+/// Useful to make HUD config cvars work for any screen/view size.
+pub fn hud_pos(rect_pos: Vec2f, rect_size: Vec2f, mut x: f64, mut y: f64) -> Vec2 {
+    if x < 0.0 {
+        x += rect_size.x;
+    }
+    if y < 0.0 {
+        y += rect_size.y;
+    }
+    Vec2::new((rect_pos.x + x) as f32, (rect_pos.y + y) as f32)
+}
+
+/// Screen positions of HUD elements, computed once per frame for each view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HudLayout {
+    /// Top left corner of the HP bar
+    pub hp: Vec2,
+    /// Top left corner of the ammo (or missile speed) bar
+    pub ammo: Vec2,
+    /// Center of the weapon icon, the size depends on the image
+    pub weapon_icon: Vec2,
+}
+
+impl HudLayout {
+    pub fn new(cvars: &Cvars, view_pos: Vec2f, view_size: Vec2f) -> Self {
+        let pos = |x, y| hud_pos(view_pos, view_size, x, y);
+        Self {
+            hp: pos(cvars.hud_hp_x, cvars.hud_hp_y),
+            ammo: pos(cvars.hud_ammo_x, cvars.hud_ammo_y),
+            weapon_icon: pos(cvars.hud_weapon_icon_x, cvars.hud_weapon_icon_y),
+        }
+    }
+}
+
+/// What the ammo bar shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AmmoBar {
+    /// Rounds left or reload progress of the current weapon
+    Ammo { fraction: f64, rounds_loaded: u32 },
+    /// Speed of the guided missile being controlled, see `hud_missile_mode`
+    MissileSpeed { fraction: f64 },
+}
+
+/// Values displayed by the HUD, no references to the game state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HudData {
+    /// Can be above 1, the HP bar itself is capped and the rest is drawn as overheal.
+    pub hp_fraction: f64,
+    /// Absolute HP for debugging
+    pub hp_number: f64,
+    /// The HP bar is white in this frame to show the engine is damaged.
+    pub hp_flash: bool,
+    pub ammo: AmmoBar,
+    pub weapon: Weapon,
+    /// The icon fades while guiding a missile.
+    pub weapon_icon_alpha: f64,
+    /// Fraction of the weapon icon still covered by switching, 0 when ready.
+    pub weapon_switch: f64,
+    /// The icon is red in this frame because a shot was just blocked by a wall.
+    pub weapon_blocked_flash: bool,
+}
+
+impl HudData {
+    /// `guided_missile` is the one being controlled if it's shown in the HUD, see `hud_missile_mode`.
+    pub fn new(
+        cvars: &Cvars,
+        player: &Player,
+        vehicle: &Vehicle,
+        guided_missile: Option<&Projectile>,
+        game_time: f64,
+        real_time: f64,
+    ) -> Self {
+        let hp_flash =
+            vehicle.crippled(cvars) && (real_time * cvars.hud_hp_cripple_flash_rate).fract() < 0.5;
+
+        let ammo = match guided_missile {
+            Some(gm) => AmmoBar::MissileSpeed {
+                fraction: (gm.vel.magnitude() / cvars.hud_missile_speed_max).min(1.0),
+            },
+            None => {
+                let state = vehicle.weapon_states[player.cur_weapon as usize];
+                AmmoBar::Ammo {
+                    fraction: state.fraction(cvars, player.cur_weapon, game_time),
+                    rounds_loaded: state.rounds_loaded,
+                }
+            }
+        };
+
+        let weapon_icon_alpha = if guided_missile.is_some() {
+            cvars.hud_missile_weapon_icon_alpha
+        } else {
+            1.0
+        };
+
+        let swap_remaining = vehicle.weapon_ready_time - game_time;
+        let weapon_switch = if swap_remaining > 0.0 && cvars.g_weapon_switch_time > 0.0 {
+            (swap_remaining / cvars.g_weapon_switch_time).min(1.0)
+        } else {
+            0.0
+        };
+
+        let blocked_ago = game_time - vehicle.fire_blocked_time;
+        let weapon_blocked_flash = blocked_ago < cvars.hud_fire_blocked_blink
+            && (real_time * cvars.hud_fire_blocked_blink_rate).fract() < 0.5;
+
+        Self {
+            hp_fraction: vehicle.hp_fraction,
+            hp_number: vehicle.hp_fraction * cvars.g_vehicle_hp(vehicle.veh_type),
+            hp_flash,
+            ammo,
+            weapon: player.cur_weapon,
+            weapon_icon_alpha,
+            weapon_switch,
+            weapon_blocked_flash,
+        }
+    }
+}
+
+/// Goes from green to red, overheal continues past the end of the normal bar.
+pub fn draw_hp(cvars: &Cvars, palette: &Palette, layout: &HudLayout, data: &HudData) {
+    let color = if data.hp_flash {
+        palette.hp_flash
+    } else {
+        palette.hp_color(data.hp_fraction)
+    };
+    let hp_pos = layout.hp;
+    let hp_width = cvars.hud_hp_width * data.hp_fraction.min(1.0);
+    draw_rectangle(
+        hp_pos.x,
+        hp_pos.y,
+        hp_width as f32,
+        cvars.hud_hp_height as f32,
+        color,
+    );
+    let overheal = (data.hp_fraction - 1.0).max(0.0);
+    if overheal > 0.0 {
+        draw_rectangle(
+            hp_pos.x + hp_width as f32,
+            hp_pos.y,
+            (cvars.hud_hp_width * overheal) as f32,
+            cvars.hud_hp_height as f32,
+            cvars.hud_hp_overheal_color.into(),
+        );
+    }
+    if cvars.d_draw_texts && cvars.d_draw_hud {
+        render_text_with_shadow(
+            cvars,
+            &format!("{}", data.hp_number),
+            hp_pos.x - 25.0,
+            hp_pos.y + cvars.hud_hp_height as f32,
+            16.0,
+            palette.debug_text,
+            1.0,
+            1.0,
+            cvars.d_draw_text_shadow_alpha,
+        );
+    }
+}
+
+pub fn draw_ammo(cvars: &Cvars, palette: &Palette, layout: &HudLayout, data: &HudData) {
+    let ammo_pos = layout.ammo;
+    let (fraction, color) = match data.ammo {
+        AmmoBar::Ammo { fraction, .. } => (fraction, palette.hud_ammo),
+        AmmoBar::MissileSpeed { fraction } => (fraction, palette.hud_missile),
+    };
+    draw_rectangle(
+        ammo_pos.x,
+        ammo_pos.y,
+        (cvars.hud_ammo_width * fraction) as f32,
+        cvars.hud_ammo_height as f32,
+        color,
+    );
+    if let AmmoBar::Ammo { rounds_loaded, .. } = data.ammo {
+        if cvars.d_draw_texts && cvars.d_draw_hud {
+            render_text_with_shadow(
+                cvars,
+                &rounds_loaded.to_string(),
+                ammo_pos.x - 25.0,
+                ammo_pos.y + cvars.hud_ammo_height as f32,
+                16.0,
+                palette.debug_text,
+                1.0,
+                1.0,
+                cvars.d_draw_text_shadow_alpha,
+            );
+        }
+    }
+}
+
+/// The original shadows were part of the image but this is good enough for now.
+/// While switching, the part that's still covered shrinks from the top down.
+pub fn draw_weapon_icon(
+    cvars: &Cvars,
+    palette: &Palette,
+    assets: &Assets,
+    layout: &HudLayout,
+    data: &HudData,
+) {
+    let weap_img = assets.weapon_icon(data.weapon);
+    let weap_icon_pos = layout.weapon_icon - Vec2::new(weap_img.width(), weap_img.height()) / 2.0;
+    let weap_alpha = data.weapon_icon_alpha;
+    draw_texture(
+        weap_img,
+        weap_icon_pos.x + cvars.hud_weapon_icon_shadow_x,
+        weap_icon_pos.y + cvars.hud_weapon_icon_shadow_y,
+        Color::new(
+            0.0,
+            0.0,
+            0.0,
+            (cvars.hud_weapon_icon_shadow_alpha * weap_alpha) as f32,
+        ),
+    );
+    let tint = if data.weapon_blocked_flash {
+        with_alpha(palette.hud_weapon_blocked, weap_alpha as f32)
+    } else {
+        Color::new(1.0, 1.0, 1.0, weap_alpha as f32)
+    };
+    draw_texture(weap_img, weap_icon_pos.x, weap_icon_pos.y, tint);
+    if data.weapon_switch > 0.0 {
+        let height = weap_img.height() * data.weapon_switch as f32;
+        draw_rectangle(
+            weap_icon_pos.x,
+            weap_icon_pos.y + weap_img.height() - height,
+            weap_img.width(),
+            height,
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        );
+    }
+}
+
+/// The text of the `d_fps` counter.
+pub fn fps_text(update_fps: f64, gamelogic_fps: f64, render_fps: f64) -> String {
+    format!(
+        "client update FPS: {:.1}   gamelogic FPS: {:.1}   render FPS: {:.1}",
+        update_fps, gamelogic_fps, render_fps
+    )
+}
+
+pub fn draw_fps(cvars: &Cvars, palette: &Palette, screen_size: Vec2f, text: &str) {
+    let fps_pos = hud_pos(Vec2f::zero(), screen_size, cvars.d_fps_x, cvars.d_fps_y);
+    render_text_with_shadow(
+        cvars,
+        text,
+        fps_pos.x - 120.0, // LATER remove the offset after finding a decent font
+        fps_pos.y,
+        16.0,
+        palette.debug_text,
+        1.0,
+        1.0,
+        cvars.d_draw_text_shadow_alpha,
+    );
+}
+
+/// Whether the `hud_lock_warning` text is visible this frame - it blinks while a homing missile is locked on.
+pub fn lock_warning_visible(cvars: &Cvars, vehicle: &Vehicle, real_time: f64) -> bool {
+    cvars.hud_lock_warning
+        && !vehicle.destroyed()
+        && !vehicle.hms.is_empty()
+        && (real_time * cvars.hud_lock_warning_flash_rate).fract() < 0.5
+}
+
+pub fn draw_lock_warning(
+    cvars: &Cvars,
+    view_pos: Vec2f,
+    view_size: Vec2f,
+    vehicle: &Vehicle,
+    real_time: f64,
+) {
+    if !lock_warning_visible(cvars, vehicle, real_time) {
+        return;
+    }
+    let pos = hud_pos(
+        view_pos,
+        view_size,
+        cvars.hud_lock_warning_x,
+        cvars.hud_lock_warning_y,
+    );
+    render_text_with_shadow(
+        cvars,
+        "missile lock",
+        pos.x,
+        pos.y,
+        cvars.hud_lock_warning_font_size,
+        cvars.hud_lock_warning_color.into(),
+        1.0,
+        1.0,
+        1.0,
+    );
+}
+
+/// Overtime or the round result, centered horizontally at the top of the view.
+pub fn draw_phase_banner(cvars: &Cvars, gs: &GameState, view_pos: Vec2f, view_size: Vec2f) {
+    if !cvars.hud_phase_banner {
+        return;
+    }
+    let Some(text) = match_flow::phase_banner(cvars, gs) else {
+        return;
+    };
+    let size = measure_text(&text, None, cvars.hud_phase_banner_font_size as u16, 1.0);
+    render_text_with_shadow(
+        cvars,
+        &text,
+        (view_size.x as f32 - size.width) / 2.0 + view_pos.x as f32,
+        view_pos.y as f32 + cvars.hud_phase_banner_y as f32,
+        cvars.hud_phase_banner_font_size,
+        cvars.hud_phase_banner_color.into(),
+        1.0,
+        1.0,
+        1.0,
+    );
+}
+
+/// The text of the `cl_perf_warning` indicator - what's wrong and what's causing it.
+pub fn perf_warning_lines(warning: &PerfWarning) -> Option<[&'static str; 2]> {
+    if !warning.degraded {
+        return None;
+    }
+    let cause = match warning.dominant {
+        Some(PerfCost::Update) => "mostly game update",
+        Some(PerfCost::Draw) | None => "mostly drawing - try lower r_effects_quality",
+    };
+    Some(["performance degraded", cause])
+}
+
+pub fn draw_perf_warning(
+    cvars: &Cvars,
+    palette: &Palette,
+    screen_size: Vec2f,
+    warning: &PerfWarning,
+) {
+    let Some([line1, line2]) = perf_warning_lines(warning) else {
+        return;
+    };
+    let pos = hud_pos(
+        Vec2f::zero(),
+        screen_size,
+        cvars.hud_perf_warning_x,
+        cvars.hud_perf_warning_y,
+    );
+    let font_size = cvars.hud_perf_warning_font_size;
+    render_text_with_shadow(
+        cvars,
+        line1,
+        pos.x,
+        pos.y,
+        font_size,
+        palette.hud_perf_warning,
+        1.0,
+        1.0,
+        1.0,
+    );
+    render_text_with_shadow(
+        cvars,
+        line2,
+        pos.x,
+        pos.y + font_size as f32 * 0.8,
+        font_size * 0.8,
+        palette.hud_perf_warning_detail,
+        1.0,
+        1.0,
+        1.0,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_anchors() {
+        let cvars = Cvars {
+            hud_hp_x: 30.0,
+            hud_hp_y: -50.0,
+            hud_ammo_x: -130.0,
+            hud_ammo_y: 20.0,
+            hud_weapon_icon_x: -170.0,
+            hud_weapon_icon_y: -28.0,
+            ..Cvars::default()
+        };
+
+        let small = HudLayout::new(&cvars, v!(0 0), v!(800 600));
+        assert_eq!(small.hp, Vec2::new(30.0, 550.0));
+        assert_eq!(small.ammo, Vec2::new(670.0, 20.0));
+        assert_eq!(small.weapon_icon, Vec2::new(630.0, 572.0));
+
+        // Positive coords stick to the top left, negative to the bottom right.
+        let big = HudLayout::new(&cvars, v!(0 0), v!(1920 1080));
+        assert_eq!(big.hp, Vec2::new(30.0, 1030.0));
+        assert_eq!(big.ammo, Vec2::new(1790.0, 20.0));
+        assert_eq!(big.weapon_icon, Vec2::new(1750.0, 1052.0));
+
+        // Splitscreen - the right view is offset.
+        let right = HudLayout::new(&cvars, v!(964 0), v!(956 1080));
+        assert_eq!(right.hp, Vec2::new(994.0, 1030.0));
+        assert_eq!(right.ammo, Vec2::new(1790.0, 20.0));
+    }
+
+    #[test]
+    fn test_data() {
+        let cvars = Cvars {
+            g_weapon_switch_time: 1.0,
+            ..Cvars::default()
+        };
+        let mut player = Player::new("test".to_owned(), ClientType::Local);
+        player.cur_weapon = Weapon::Rockets;
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        vehicle.hp_fraction = 0.5;
+        vehicle.weapon_ready_time = 10.25;
+
+        let data = HudData::new(&cvars, &player, &vehicle, None, 10.0, 0.0);
+        assert_eq!(data.hp_fraction, 0.5);
+        assert_eq!(data.hp_number, 0.5 * cvars.g_vehicle_hp(VehicleType::Tank));
+        assert_eq!(data.weapon, Weapon::Rockets);
+        assert_eq!(data.weapon_icon_alpha, 1.0);
+        assert_eq!(data.weapon_switch, 0.25);
+        assert!(!data.weapon_blocked_flash);
+        let rounds = cvars.g_rockets_reload_ammo;
+        assert_eq!(
+            data.ammo,
+            AmmoBar::Ammo {
+                fraction: 1.0,
+                rounds_loaded: rounds,
+            }
+        );
+
+        // Guiding a missile - its speed replaces ammo and the icon fades.
+        let gm = Projectile {
+            weapon: Weapon::Gm,
+            pos: v!(0 0),
+            vel: v!(0, -250),
+            angle: -PI / 2.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: Index::DANGLING,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let data = HudData::new(&cvars, &player, &vehicle, Some(&gm), 11.0, 0.0);
+        assert_eq!(
+            data.ammo,
+            AmmoBar::MissileSpeed {
+                fraction: 250.0 / cvars.hud_missile_speed_max,
+            }
+        );
+        assert_eq!(data.weapon_icon_alpha, cvars.hud_missile_weapon_icon_alpha);
+        assert_eq!(data.weapon_switch, 0.0);
+
+        // Blinks for a moment after a blocked shot.
+        vehicle.fire_blocked_time = 11.0;
+        let data = HudData::new(&cvars, &player, &vehicle, None, 11.1, 0.0);
+        assert!(data.weapon_blocked_flash);
+        let data = HudData::new(
+            &cvars,
+            &player,
+            &vehicle,
+            None,
+            11.1,
+            0.75 / cvars.hud_fire_blocked_blink_rate,
+        );
+        assert!(!data.weapon_blocked_flash);
+        let data = HudData::new(&cvars, &player, &vehicle, None, 12.0, 0.0);
+        assert!(!data.weapon_blocked_flash);
+    }
+
+    #[test]
+    fn test_perf_warning_lines() {
+        let mut warning = PerfWarning::new();
+        assert_eq!(perf_warning_lines(&warning), None);
+
+        warning.degraded = true;
+        warning.dominant = Some(PerfCost::Update);
+        let [first, cause] = perf_warning_lines(&warning).unwrap();
+        assert_eq!(first, "performance degraded");
+        assert!(cause.contains("update"));
+
+        warning.dominant = Some(PerfCost::Draw);
+        let [_, cause] = perf_warning_lines(&warning).unwrap();
+        assert!(cause.contains("r_effects_quality"));
+    }
+}
@@ -5,7 +5,11 @@
 //  - Keys that often depend on layout - https://github.com/not-fl3/macroquad/issues/260
 // LATER Configurable input
 
-use macroquad::prelude::*;
+use macroquad::{
+    input::utils::{register_input_subscriber, repeat_all_miniquad_input},
+    miniquad::{self, EventHandler},
+    prelude::*,
+};
 
 use crate::prelude::*;
 
@@ -23,11 +27,17 @@ pub struct ClientInput {
     pub prev_weapon: bool,
     pub next_weapon: bool,
     pub fire: bool,
+    pub fire_alt: bool,
     pub mine: bool,
     pub self_destruct: bool,
     pub horn: bool,
+    pub boost: bool,
+    pub interact: bool,
     pub chat: bool,
     pub pause: bool,
+    /// Debugging, see `history`.
+    pub history_rewind: bool,
+    pub history_resume: bool,
     // ^ when adding fields, also add them to Debug
 }
 
@@ -35,7 +45,7 @@ pub struct ClientInput {
 ///
 /// LATER Include stuff like timestamps.
 /// LATER Maybe treat some keys presses as events?
-#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NetInput {
     pub left: bool,
     pub right: bool,
@@ -46,9 +56,13 @@ pub struct NetInput {
     pub prev_weapon: bool,
     pub next_weapon: bool,
     pub fire: bool,
+    pub fire_alt: bool,
     pub mine: bool,
     pub self_destruct: bool,
     pub horn: bool,
+    pub boost: bool,
+    /// Take or leave the controls of a nearby gun emplacement, see `emplacements`.
+    pub interact: bool,
     // ^ when adding fields, also add them to Debug
 }
 
@@ -94,11 +108,16 @@ impl ClientInput {
             prev_weapon: self.prev_weapon | other.prev_weapon,
             next_weapon: self.next_weapon | other.next_weapon,
             fire: self.fire | other.fire,
+            fire_alt: self.fire_alt | other.fire_alt,
             mine: self.mine | other.mine,
             self_destruct: self.self_destruct | other.self_destruct,
             horn: self.horn | other.horn,
+            boost: self.boost | other.boost,
+            interact: self.interact | other.interact,
             chat: self.chat | other.chat,
             pause: self.pause | other.pause,
+            history_rewind: self.history_rewind | other.history_rewind,
+            history_resume: self.history_resume | other.history_resume,
         }
     }
 
@@ -113,9 +132,12 @@ impl ClientInput {
             prev_weapon: self.prev_weapon,
             next_weapon: self.next_weapon,
             fire: self.fire,
+            fire_alt: self.fire_alt,
             mine: self.mine,
             self_destruct: self.self_destruct,
             horn: self.horn,
+            boost: self.boost,
+            interact: self.interact,
         }
     }
 }
@@ -161,6 +183,7 @@ impl NetInput {
             right: false,
             up: false,
             down: false,
+            boost: false,
             ..*self
         }
     }
@@ -169,7 +192,7 @@ impl NetInput {
 impl Debug for ClientInput {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         static_assert!(
-            std::mem::size_of::<ClientInput>() == 14,
+            std::mem::size_of::<ClientInput>() == 19,
             "number of fields changed without changing Debug impl"
         );
 
@@ -201,6 +224,9 @@ impl Debug for ClientInput {
         if self.fire {
             write!(f, "fire ")?;
         }
+        if self.fire_alt {
+            write!(f, "fire_alt ")?;
+        }
         if self.mine {
             write!(f, "mine ")?;
         }
@@ -210,12 +236,24 @@ impl Debug for ClientInput {
         if self.horn {
             write!(f, "horn ")?;
         }
+        if self.boost {
+            write!(f, "boost ")?;
+        }
+        if self.interact {
+            write!(f, "interact ")?;
+        }
         if self.chat {
             write!(f, "chat ")?;
         }
         if self.pause {
             write!(f, "pause ")?;
         }
+        if self.history_rewind {
+            write!(f, "history_rewind ")?;
+        }
+        if self.history_resume {
+            write!(f, "history_resume ")?;
+        }
         write!(f, "}}")?;
         Ok(())
     }
@@ -224,7 +262,7 @@ impl Debug for ClientInput {
 impl Debug for NetInput {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         static_assert!(
-            std::mem::size_of::<NetInput>() == 12,
+            std::mem::size_of::<NetInput>() == 15,
             "number of fields changed without changing Debug impl"
         );
 
@@ -256,6 +294,9 @@ impl Debug for NetInput {
         if self.fire {
             write!(f, "fire ")?;
         }
+        if self.fire_alt {
+            write!(f, "fire_alt ")?;
+        }
         if self.mine {
             write!(f, "mine ")?;
         }
@@ -265,6 +306,12 @@ impl Debug for NetInput {
         if self.horn {
             write!(f, "horn ")?;
         }
+        if self.boost {
+            write!(f, "boost ")?;
+        }
+        if self.interact {
+            write!(f, "interact ")?;
+        }
         write!(f, "}}")?;
         Ok(())
     }
@@ -295,99 +342,126 @@ impl Debug for NetInput {
 //     }
 // }
 
-pub fn get_input1() -> ClientInput {
+pub fn get_input1(focus: &FocusDetector) -> ClientInput {
     let mut input = ClientInput::empty();
-    if was_input_pressed(&[KeyCode::A]) {
+    if was_input_pressed(focus, &[KeyCode::A]) {
         input.left = true;
     }
-    if was_input_pressed(&[KeyCode::D]) {
+    if was_input_pressed(focus, &[KeyCode::D]) {
         input.right = true;
     }
-    if was_input_pressed(&[KeyCode::W]) {
+    if was_input_pressed(focus, &[KeyCode::W]) {
         input.up = true;
     }
-    if was_input_pressed(&[KeyCode::S]) {
+    if was_input_pressed(focus, &[KeyCode::S]) {
         input.down = true;
     }
-    if was_input_pressed(&[KeyCode::Q]) {
+    if was_input_pressed(focus, &[KeyCode::Q]) {
         input.turret_left = true;
     }
-    if was_input_pressed(&[KeyCode::E]) {
+    if was_input_pressed(focus, &[KeyCode::E]) {
         input.turret_right = true;
     }
-    if was_input_pressed(&[KeyCode::V]) {
+    if was_input_pressed(focus, &[KeyCode::V]) {
         input.prev_weapon = true;
     }
-    if was_input_pressed(&[KeyCode::LeftShift, KeyCode::C]) {
+    if was_input_pressed(focus, &[KeyCode::LeftShift, KeyCode::C]) {
         input.next_weapon = true;
     }
-    if was_input_pressed(&[KeyCode::Space]) {
+    if was_input_pressed(focus, &[KeyCode::Space]) {
         input.fire = true;
     }
-    if was_input_pressed(&[KeyCode::X]) {
+    if was_input_pressed(focus, &[KeyCode::F]) {
+        input.fire_alt = true;
+    }
+    if was_input_pressed(focus, &[KeyCode::X]) {
         input.mine = true;
     }
-    if was_input_pressed(&[KeyCode::G]) {
+    if was_input_pressed(focus, &[KeyCode::G]) {
         input.self_destruct = true;
     }
-    if was_input_pressed(&[KeyCode::R]) {
+    if was_input_pressed(focus, &[KeyCode::R]) {
         input.horn = true;
     }
+    if was_input_pressed(focus, &[KeyCode::B]) {
+        input.boost = true;
+    }
+    if was_input_pressed(focus, &[KeyCode::Z]) {
+        input.interact = true;
+    }
 
     // The rest are shared actions defined on is player 1 only
 
-    if was_input_pressed(&[KeyCode::Enter, KeyCode::T]) {
+    if was_input_pressed(focus, &[KeyCode::Enter, KeyCode::T]) {
         input.chat = true;
     }
-    if was_input_pressed(&[KeyCode::Pause, KeyCode::P]) {
+    if was_input_pressed(focus, &[KeyCode::Pause, KeyCode::P]) {
         input.pause = true;
     }
+    if was_input_pressed(focus, &[KeyCode::PageUp]) {
+        input.history_rewind = true;
+    }
+    if was_input_pressed(focus, &[KeyCode::PageDown]) {
+        input.history_resume = true;
+    }
 
     input
 }
 
-pub fn get_input2() -> ClientInput {
+pub fn get_input2(focus: &FocusDetector) -> ClientInput {
     let mut input = ClientInput::empty();
-    if was_input_pressed(&[KeyCode::Left]) {
+    if was_input_pressed(focus, &[KeyCode::Left]) {
         input.left = true;
     }
-    if was_input_pressed(&[KeyCode::Right]) {
+    if was_input_pressed(focus, &[KeyCode::Right]) {
         input.right = true;
     }
-    if was_input_pressed(&[KeyCode::Up]) {
+    if was_input_pressed(focus, &[KeyCode::Up]) {
         input.up = true;
     }
-    if was_input_pressed(&[KeyCode::Down]) {
+    if was_input_pressed(focus, &[KeyCode::Down]) {
         input.down = true;
     }
-    if was_input_pressed(&[KeyCode::Comma]) {
+    if was_input_pressed(focus, &[KeyCode::Comma]) {
         input.turret_left = true;
     }
-    if was_input_pressed(&[KeyCode::Period]) {
+    if was_input_pressed(focus, &[KeyCode::Period]) {
         input.turret_right = true;
     }
-    if was_input_pressed(&[KeyCode::L]) {
+    if was_input_pressed(focus, &[KeyCode::L]) {
         input.prev_weapon = true;
     }
-    if was_input_pressed(&[
-        KeyCode::Slash, // US layout
-        KeyCode::Minus, // Same key, CZ layout
-        KeyCode::Kp0,
-    ]) {
+    if was_input_pressed(
+        focus,
+        &[
+            KeyCode::Slash, // US layout
+            KeyCode::Minus, // Same key, CZ layout
+            KeyCode::Kp0,
+        ],
+    ) {
         input.next_weapon = true;
     }
-    if was_input_pressed(&[KeyCode::RightShift]) {
+    if was_input_pressed(focus, &[KeyCode::RightShift]) {
         input.fire = true;
     }
-    if was_input_pressed(&[KeyCode::M]) {
+    if was_input_pressed(focus, &[KeyCode::N]) {
+        input.fire_alt = true;
+    }
+    if was_input_pressed(focus, &[KeyCode::M]) {
         input.mine = true;
     }
-    if was_input_pressed(&[KeyCode::J]) {
+    if was_input_pressed(focus, &[KeyCode::J]) {
         input.self_destruct = true;
     }
-    if was_input_pressed(&[KeyCode::K]) {
+    if was_input_pressed(focus, &[KeyCode::K]) {
         input.horn = true;
     }
+    if was_input_pressed(focus, &[KeyCode::H]) {
+        input.boost = true;
+    }
+    if was_input_pressed(focus, &[KeyCode::U]) {
+        input.interact = true;
+    }
 
     // No binds for shared actions like chat, pause, console and esc.
     // They're defined on player 1.
@@ -395,8 +469,11 @@ pub fn get_input2() -> ClientInput {
     input
 }
 
-fn was_input_pressed(key_codes: &[KeyCode]) -> bool {
+fn was_input_pressed(focus: &FocusDetector, key_codes: &[KeyCode]) -> bool {
     for &key_code in key_codes {
+        if focus.is_stale(key_code) {
+            continue;
+        }
         // Check both to avoid skipping input if it's pressed and released within one frame.
         if is_key_pressed(key_code) || is_key_down(key_code) {
             return true;
@@ -404,3 +481,164 @@ fn was_input_pressed(key_codes: &[KeyCode]) -> bool {
     }
     false
 }
+
+/// Guesses when the window loses focus because macroquad doesn't tell us:
+/// https://github.com/not-fl3/macroquad/issues/264
+///
+/// Without focus we don't get key up events so keys would stay held forever.
+/// While a key is really held, the OS keeps sending repeat events,
+/// so keys reported held without any key events for `cl_focus_loss_timeout`
+/// mean the window lost focus. The next key or mouse button event means it's back.
+///
+/// Keys macroquad still thinks are held from before are ignored
+/// until they're pressed or released again.
+pub struct FocusDetector {
+    subscriber: usize,
+    focused: bool,
+    last_event_time: f64,
+    /// Macroquad 0.4.4 can't list held keys so they're tracked from the same events.
+    held_keys: FnvHashSet<KeyCode>,
+    stale_keys: FnvHashSet<KeyCode>,
+}
+
+impl FocusDetector {
+    pub fn new() -> Self {
+        Self {
+            subscriber: register_input_subscriber(),
+            focused: true,
+            last_event_time: 0.0,
+            held_keys: FnvHashSet::default(),
+            stale_keys: FnvHashSet::default(),
+        }
+    }
+
+    /// Call once per frame before reading input, returns whether the window seems focused.
+    pub fn update(&mut self, cvars: &Cvars, real_time: f64) -> bool {
+        let mut events = InputEvents::default();
+        repeat_all_miniquad_input(&mut events, self.subscriber);
+        for key_code in &events.pressed {
+            self.held_keys.insert(*key_code);
+            self.stale_keys.remove(key_code);
+        }
+        for key_code in &events.released {
+            self.held_keys.remove(key_code);
+            self.stale_keys.remove(key_code);
+        }
+
+        // Modifiers don't repeat on some platforms so they're no evidence either way.
+        let keys_held = self
+            .held_keys
+            .iter()
+            .any(|&key_code| !is_modifier(key_code) && !self.is_stale(key_code));
+        let focused = self.detect(
+            cvars.cl_focus_loss_timeout,
+            real_time,
+            events.any,
+            keys_held,
+        );
+        if !focused {
+            self.stale_keys.extend(self.held_keys.iter().copied());
+        }
+        focused
+    }
+
+    fn detect(&mut self, timeout: f64, real_time: f64, any_event: bool, keys_held: bool) -> bool {
+        if any_event {
+            self.focused = true;
+            self.last_event_time = real_time;
+        } else if !keys_held {
+            // Nothing can get stuck.
+            self.last_event_time = real_time;
+        } else if timeout > 0.0 && real_time - self.last_event_time > timeout {
+            self.focused = false;
+        }
+        self.focused
+    }
+
+    /// Whether the key is still held from before focus was lost as far as macroquad knows.
+    pub fn is_stale(&self, key_code: KeyCode) -> bool {
+        self.stale_keys.contains(&key_code)
+    }
+}
+
+impl Default for FocusDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_modifier(key_code: KeyCode) -> bool {
+    matches!(
+        key_code,
+        KeyCode::LeftShift
+            | KeyCode::RightShift
+            | KeyCode::LeftControl
+            | KeyCode::RightControl
+            | KeyCode::LeftAlt
+            | KeyCode::RightAlt
+            | KeyCode::LeftSuper
+            | KeyCode::RightSuper
+    )
+}
+
+/// Input events since the last frame, only those a window without focus doesn't get.
+#[derive(Default)]
+struct InputEvents {
+    any: bool,
+    pressed: Vec<KeyCode>,
+    released: Vec<KeyCode>,
+}
+
+impl EventHandler for InputEvents {
+    fn update(&mut self) {}
+
+    fn draw(&mut self) {}
+
+    fn key_down_event(&mut self, key_code: KeyCode, _keymods: miniquad::KeyMods, _repeat: bool) {
+        self.any = true;
+        self.pressed.push(key_code);
+    }
+
+    fn key_up_event(&mut self, key_code: KeyCode, _keymods: miniquad::KeyMods) {
+        self.any = true;
+        self.released.push(key_code);
+    }
+
+    fn mouse_button_down_event(&mut self, _button: miniquad::MouseButton, _x: f32, _y: f32) {
+        self.any = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_detection() {
+        // Not `new` - there's no macroquad context to subscribe to.
+        let mut focus = FocusDetector {
+            subscriber: 0,
+            focused: true,
+            last_event_time: 0.0,
+            held_keys: FnvHashSet::default(),
+            stale_keys: FnvHashSet::default(),
+        };
+
+        // Held with repeat events.
+        assert!(focus.detect(2.0, 1.0, true, true));
+        assert!(focus.detect(2.0, 2.5, true, true));
+        // Repeat events stopped.
+        assert!(focus.detect(2.0, 4.0, false, true));
+        assert!(!focus.detect(2.0, 4.6, false, true));
+        // Stays lost even when nothing seems held until the next event.
+        assert!(!focus.detect(2.0, 5.0, false, false));
+        assert!(focus.detect(2.0, 6.0, true, false));
+
+        // Nothing held for a long time.
+        assert!(focus.detect(2.0, 10.0, false, false));
+        assert!(focus.detect(2.0, 11.0, false, true));
+
+        // Disabled.
+        assert!(focus.detect(0.0, 100.0, false, true));
+    }
+}
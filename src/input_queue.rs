@@ -0,0 +1,168 @@
+//! Input with the real time it happened at, so catch-up ticks don't see it too early.
+//!
+//! With `TickrateMode::Fixed`, one update can run several ticks.
+//! Input sampled once per update would apply to all of them,
+//! even to ticks which happened before the key was actually pressed,
+//! and presses would be detected on the first of them.
+//! Hosts which know when input happened can pass it to `Server::update_with_inputs`.
+//! Each tick then only sees input from before its own real time, see `tick_real_time`.
+//!
+//! Macroquad samples input once per frame without timestamps
+//! so the native client keeps sending input over the network once per update.
+//! Without queued input, nothing changes.
+
+use crate::{possess, prelude::*};
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimedInput {
+    /// Same clock as `real_time` passed to `Server::update`.
+    pub real_time: f64,
+    pub player_handle: Index,
+    pub input: NetInput,
+}
+
+impl Server {
+    /// Add input to be applied during the following ticks.
+    ///
+    /// Input while paused is thrown away like input from the network.
+    pub fn queue_inputs(&mut self, inputs: Vec<TimedInput>) {
+        if self.sg.paused {
+            return;
+        }
+        self.sg.input_queue.extend(inputs);
+        self.sg
+            .input_queue
+            .make_contiguous()
+            .sort_by(|a, b| a.real_time.total_cmp(&b.real_time));
+    }
+
+    /// Apply queued input from up to `tick_real_time`, the rest waits for later ticks.
+    pub fn apply_queued_inputs(&mut self, tick_real_time: f64) {
+        while let Some(timed) = self.sg.input_queue.front() {
+            if timed.real_time > tick_real_time {
+                break;
+            }
+            let timed = self.sg.input_queue.pop_front().unwrap();
+            // The player might have left in the meantime.
+            if self.gs.players.contains(timed.player_handle) {
+                possess::route_input(&mut self.gs, timed.player_handle, timed.input);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn setup(cvars: &Cvars) -> (Server, Index) {
+        let mut server = test_utils::atrium_server(cvars);
+
+        let mut ctx = server.ctx(cvars);
+        let (player_handle, _) = test_utils::spawn_player(&mut ctx, "test");
+        (server, player_handle)
+    }
+
+    /// Run one update from real time 0 to `real_time` headless,
+    /// return whether each tick saw `fire` and whether it was a new press.
+    fn fire_per_tick(server: &mut Server, cvars: &Cvars, real_time: f64) -> Vec<(bool, bool)> {
+        server.real_time_prev = 0.0;
+        server.real_time = real_time;
+        server.real_time_delta = real_time;
+
+        let mut seen = Vec::new();
+        server.run_ticks(cvars, |server, cvars, tick_real_time, game_time| {
+            server.apply_queued_inputs(tick_real_time);
+            let player = server.gs.players.iter().next().unwrap().1;
+            seen.push((
+                player.input.fire,
+                player.input.fire && !player.input_prev.fire,
+            ));
+            server.simulate_tick(cvars, game_time);
+        });
+        seen
+    }
+
+    #[test]
+    fn test_press_during_catch_up() {
+        let cvars = Cvars {
+            bots_max: 0,
+            sys_tickrate_mode: TickrateMode::Fixed,
+            sys_tickrate_fixed_fps: 10.0,
+            ..Cvars::default()
+        };
+        let (mut server, player_handle) = setup(&cvars);
+
+        // 5 ticks at 0.1, 0.2, ... 0.5, the press is between the 3rd and 4th.
+        server.queue_inputs(vec![TimedInput {
+            real_time: 0.35,
+            player_handle,
+            input: NetInput {
+                fire: true,
+                ..NetInput::empty()
+            },
+        }]);
+        let seen = fire_per_tick(&mut server, &cvars, 0.55);
+        assert_eq!(
+            seen,
+            [
+                (false, false),
+                (false, false),
+                (false, false),
+                (true, true),
+                (true, false),
+            ]
+        );
+        assert!(server.sg.input_queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_later_input_waits() {
+        let cvars = Cvars {
+            bots_max: 0,
+            sys_tickrate_mode: TickrateMode::Fixed,
+            sys_tickrate_fixed_fps: 10.0,
+            ..Cvars::default()
+        };
+        let (mut server, player_handle) = setup(&cvars);
+        let fire = NetInput {
+            fire: true,
+            ..NetInput::empty()
+        };
+
+        // Queued out of order, after the last tick of this update.
+        server.queue_inputs(vec![
+            TimedInput {
+                real_time: 0.6,
+                player_handle,
+                input: NetInput::empty(),
+            },
+            TimedInput {
+                real_time: 0.25,
+                player_handle,
+                input: fire,
+            },
+        ]);
+        let seen = fire_per_tick(&mut server, &cvars, 0.55);
+        assert_eq!(seen.iter().filter(|(fire, _)| *fire).count(), 3);
+        assert_eq!(server.sg.input_queue.len(), 1);
+
+        // Without timestamps, input is the same for every tick.
+        let (mut server, player_handle) = setup(&cvars);
+        server.gs.players[player_handle].input = fire;
+        let seen = fire_per_tick(&mut server, &cvars, 0.55);
+        assert_eq!(seen[0], (true, true));
+        assert!(seen.iter().all(|(fire, _)| *fire));
+
+        // Paused input is dropped.
+        server.sg.paused = true;
+        server.queue_inputs(vec![TimedInput {
+            real_time: 1.0,
+            player_handle,
+            input: fire,
+        }]);
+        assert!(server.sg.input_queue.is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+//! The RecWars game as a library.
+//!
+//! The `rec-wars` binary is a thin launcher over this crate
+//! (parsing the command line and running the client/server loops).
+//! Other frontends can embed the simulation directly through `sim::Sim`
+//! without networking or a window, see `examples/native_headless.rs`.
+//!
+//! The public surface for embedding:
+//! - `sim::Sim` - create a match from `Cvars` and a `Map`, add players, step it forward
+//! - `map::parse_texture_list` and `map::parse_map` - load maps
+//! - `prelude` - entities, components and other types needed to read the game state for rendering
+//!
+//! Everything else is also public but considered internal
+//! and might change without notice.
+
+// Additional warnings that are allow by default (`rustc -W help`)
+#![warn(trivial_casts)]
+#![warn(trivial_numeric_casts)]
+#![warn(unreachable_pub)]
+#![warn(unused)]
+#![warn(clippy::all)]
+#![allow(clippy::comparison_chain)] // Ifs are often cleaner with fewer indents
+#![allow(clippy::iter_skip_next)] // Skip makes intent clearer sometimes
+#![allow(clippy::needless_range_loop)] // False positives
+#![allow(clippy::too_many_arguments)] // I decide what's too many
+
+#[macro_use]
+pub mod debug; // keep first so the macros are available everywhere
+
+pub mod ai_difficulty;
+pub mod ai_dodge;
+pub mod ai_schedule;
+pub mod area_query;
+pub mod assets;
+pub mod assists;
+pub mod audio;
+pub mod bot_identity;
+pub mod capture;
+pub mod client;
+pub mod common;
+pub mod context;
+pub mod cvar_units;
+pub mod cvars;
+pub mod debug_spawn;
+pub mod decals;
+pub mod effects_budget;
+pub mod emplacements;
+pub mod entities;
+pub mod game_state;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod hud;
+pub mod input;
+pub mod input_queue;
+pub mod local_view;
+pub mod map;
+pub mod match_flow;
+pub mod match_report;
+pub mod metrics;
+pub mod mines;
+pub mod net;
+pub mod net_messages;
+pub mod objectives;
+pub mod observer;
+pub mod palette;
+pub mod particles;
+pub mod possess;
+pub mod prelude;
+pub mod presets;
+pub mod profile;
+pub mod rendering;
+pub mod rule_hooks;
+pub mod scraping;
+pub mod server;
+pub mod sim;
+#[cfg(feature = "sim_hash")]
+pub mod sim_hash;
+pub mod smoothing;
+pub mod stable_id;
+pub mod surface_effects;
+pub mod sys_ai;
+pub mod systems;
+#[cfg(test)]
+pub mod test_utils;
+pub mod timed_effects;
+pub mod timing;
+pub mod utils;
+pub mod weapons;
+pub mod zone;
@@ -0,0 +1,219 @@
+//! What each local player sees - presentation state which is not part of the simulation.
+//!
+//! Splitscreen has one view per player so effects like screen shake
+//! and "You killed X" only show up for the player they're about.
+
+use crate::prelude::*;
+
+/// Per-viewport state of one local player.
+#[derive(Debug, Clone)]
+pub struct LocalPlayerView {
+    /// The player this view belongs to - their kills and deaths show up here.
+    pub player_handle: Index,
+    /// The bot whose player we're controlling instead of our own, see `possess`.
+    pub possessed: Option<Index>,
+    /// The player the camera follows without controlling them, see `observer`.
+    pub spectating: Option<Index>,
+    /// Screen shake from 0 to 1, wears off over time.
+    pub trauma: f64,
+    pub notifications: Vec<Notification>,
+}
+
+impl LocalPlayerView {
+    pub fn new(player_handle: Index) -> Self {
+        Self {
+            player_handle,
+            possessed: None,
+            spectating: None,
+            trauma: 0.0,
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Whoever our input goes to or whoever we're watching -
+    /// the camera (and the listener) follows them.
+    pub fn camera_player(&self) -> Index {
+        self.possessed
+            .or(self.spectating)
+            .unwrap_or(self.player_handle)
+    }
+
+    pub fn add_trauma(&mut self, amount: f64) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    /// Let shake wear off and old notifications expire.
+    ///
+    /// Shake is part of the world and wears off with game time (`dt`),
+    /// notifications are HUD and expire in `real_time`.
+    pub fn cleanup(&mut self, cvars: &Cvars, real_time: f64, dt: f64) {
+        self.trauma = (self.trauma - cvars.r_shake_decay * dt).max(0.0);
+        self.notifications.retain(|notification| {
+            real_time - notification.start_time < cvars.hud_notifications_duration
+        });
+    }
+}
+
+impl ClientGame {
+    /// The view whose camera follows `player_handle`, if any.
+    pub fn view_following_mut(&mut self, player_handle: Index) -> Option<&mut LocalPlayerView> {
+        self.views
+            .iter_mut()
+            .find(|view| view.camera_player() == player_handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    use crate::{net::LocalConnection, test_utils};
+
+    /// Two local players and a bot, all with vehicles.
+    fn splitscreen(cvars: &Cvars) -> (ClientGame, GameState, [Index; 3]) {
+        let mut gs = GameState::new();
+        let names = ["player 1", "player 2", "bot"];
+        let handles = names.map(|name| {
+            let player = Player::new(name.to_owned(), ClientType::Local);
+            let player_handle = gs.players.insert(player);
+            let vehicle = Vehicle::new(
+                cvars,
+                v!(100 100),
+                0.0,
+                VehicleType::Tank,
+                0.0,
+                player_handle,
+            );
+            let vehicle_handle = gs.vehicles.insert(vehicle);
+            gs.players[player_handle].vehicle = Some(vehicle_handle);
+            player_handle
+        });
+
+        let (sender, _server_receiver) = mpsc::channel();
+        let (_server_sender, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut cg = ClientGame::new(Box::new(conn), handles[0]);
+        cg.views.push(LocalPlayerView::new(handles[1]));
+        (cg, gs, handles)
+    }
+
+    fn texts(view: &LocalPlayerView) -> Vec<&str> {
+        view.notifications
+            .iter()
+            .map(|notification| notification.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_kills_routed_to_views() {
+        let cvars = Cvars::default();
+        let (mut cg, mut gs, [p1, p2, bot]) = splitscreen(&cvars);
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+
+        ctx.handle_kill(Kill {
+            attacker: Some(p1.slot()),
+            victim: bot.slot(),
+            assists: Vec::new(),
+        });
+        assert_eq!(texts(&ctx.cg.views[0]), ["You killed bot"]);
+        assert!(ctx.cg.views[1].notifications.is_empty());
+
+        ctx.handle_kill(Kill {
+            attacker: Some(bot.slot()),
+            victim: p2.slot(),
+            assists: Vec::new(),
+        });
+        assert_eq!(texts(&ctx.cg.views[0]), ["You killed bot"]);
+        assert_eq!(texts(&ctx.cg.views[1]), ["You were killed by bot"]);
+
+        // Both see it, from a different perspective.
+        ctx.handle_kill(Kill {
+            attacker: Some(p2.slot()),
+            victim: p1.slot(),
+            assists: Vec::new(),
+        });
+        assert_eq!(texts(&ctx.cg.views[0])[1], "You were killed by player 2");
+        assert_eq!(texts(&ctx.cg.views[1])[1], "You killed player 1");
+    }
+
+    #[test]
+    fn test_kill_assist_routed_to_views() {
+        let cvars = Cvars::default();
+        let (mut cg, mut gs, [p1, p2, bot]) = splitscreen(&cvars);
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+
+        ctx.handle_kill(Kill {
+            attacker: Some(p1.slot()),
+            victim: bot.slot(),
+            assists: vec![p2.slot()],
+        });
+        assert_eq!(
+            texts(&ctx.cg.views[0]),
+            ["You killed bot + assist: player 2"]
+        );
+        assert_eq!(texts(&ctx.cg.views[1]), ["Assist on bot"]);
+        assert_eq!(ctx.gs.players[p1].score.kills, 1);
+        assert_eq!(ctx.gs.players[p2].score.assists, 1);
+        assert_eq!(ctx.gs.players[p2].score.kills, 0);
+    }
+
+    #[test]
+    fn test_shake_routed_to_views() {
+        let cvars = Cvars::default();
+        let (mut cg, _, [p1, p2, bot]) = splitscreen(&cvars);
+
+        cg.view_following_mut(p2).unwrap().add_trauma(0.5);
+        assert_eq!(cg.views[0].trauma, 0.0);
+        assert_eq!(cg.views[1].trauma, 0.5);
+
+        // Nobody is watching the bot until player 1 possesses it.
+        assert!(cg.view_following_mut(bot).is_none());
+        cg.views[0].possessed = Some(bot);
+        cg.view_following_mut(bot).unwrap().add_trauma(2.0);
+        assert_eq!(cg.views[0].trauma, 1.0);
+        assert!(cg.view_following_mut(p1).is_none());
+
+        cg.views[1].cleanup(&cvars, 0.0, 0.1);
+        assert!(cg.views[1].trauma < 0.5);
+        assert_eq!(cg.views[0].trauma, 1.0);
+    }
+
+    #[test]
+    fn test_camera_player() {
+        let cvars = Cvars::default();
+        let (mut cg, _, [p1, p2, bot]) = splitscreen(&cvars);
+        assert_eq!(cg.views[0].camera_player(), p1);
+
+        // Watching someone else, our own input still goes to our own player.
+        cg.views[0].spectating = Some(p2);
+        assert_eq!(cg.views[0].camera_player(), p2);
+        assert!(cg.view_following_mut(p2).is_some());
+
+        // Possessing takes priority.
+        cg.views[0].possessed = Some(bot);
+        assert_eq!(cg.views[0].camera_player(), bot);
+    }
+
+    #[test]
+    fn test_kill_grid_label() {
+        let cvars = Cvars {
+            hud_grid_label: true,
+            ..Cvars::default()
+        };
+        let (mut cg, mut gs, [p1, _, bot]) = splitscreen(&cvars);
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+
+        // The bot is at (100, 100) which is the second tile in both directions.
+        ctx.handle_kill(Kill {
+            attacker: Some(p1.slot()),
+            victim: bot.slot(),
+            assists: Vec::new(),
+        });
+        assert_eq!(texts(&ctx.cg.views[0]), ["You killed bot at B2"]);
+    }
+}
@@ -9,57 +9,16 @@
 #![allow(clippy::needless_range_loop)] // False positives
 #![allow(clippy::too_many_arguments)] // I decide what's too many
 
-#[macro_use]
-pub mod debug; // keep first so the macros are available everywhere
-
-pub mod assets;
-pub mod client;
-pub mod common;
-pub mod context;
-pub mod cvars;
-pub mod entities;
-pub mod game_state;
-pub mod input;
-pub mod map;
-pub mod net;
-pub mod net_messages;
-pub mod prelude;
-pub mod rendering;
-pub mod server;
-pub mod sys_ai;
-pub mod systems;
-pub mod timing;
-pub mod utils;
-pub mod weapons;
-
 use std::{env, error::Error, panic, process::Command};
 
 use macroquad::prelude::*;
 
-use crate::{net::Connection, prelude::*};
-
-const BOT_NAMES: [&str; 20] = [
-    "Dr. Dead",
-    "Sir Hurt",
-    "Mr. Pain",
-    "PhD. Torture",
-    "Mrs. Chestwound",
-    "Ms. Dismember",
-    "Don Lobotomy",
-    "Lt. Dead",
-    "Sgt. Dead",
-    "Private Dead",
-    "Colonel Dead",
-    "Captain Dead",
-    "Major Dead",
-    "Commander Dead",
-    "Díotóir",
-    "Fireman",
-    "Goldfinger",
-    "Silverfinger",
-    "Bronzefinger",
-    "President Dead",
-];
+use rec_wars::{
+    cvar_units::WithUnits,
+    dbg_logd, dbg_logf, debug, map,
+    net::{self, Connection},
+    prelude::*,
+};
 
 #[derive(Debug)]
 enum Endpoint {
@@ -223,12 +182,20 @@ fn args_to_cvars(cvar_args: &[String]) -> Result<Cvars, String> {
         let str_value = cvars_iter.next().ok_or_else(|| {
             format!("missing value for cvar `{cvar_name}` or incorrect command line option")
         })?;
-        let res = cvars.set_str(cvar_name, str_value);
+        let mut res = cvars.set_str_units(cvar_name, str_value);
+        if res.is_ok() && cvar_name == "g_preset" {
+            // Apply it now so later cvars can override individual values.
+            res = cvars.apply_preset(str_value);
+        }
         match res.as_ref() {
             Ok(_) => {
                 // Intentionally getting the new value from cvars, not just printing the input
                 // so the user can check it was parsed correctly.
-                dbg_logf!("{} = {}", cvar_name, cvars.get_string(cvar_name).unwrap());
+                dbg_logf!(
+                    "{} = {}",
+                    cvar_name,
+                    cvars.get_string_units(cvar_name).unwrap()
+                );
             }
             Err(e) => {
                 let msg = format!("failed to set cvar {cvar_name} to value {str_value}: {e}");
@@ -359,6 +326,12 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
         local_player2_index,
         vehicles,
         projectiles,
+        mines,
+        emplacements,
+        wrecks,
+        zone,
+        phase,
+        world_score,
     } = init;
     assert!(local_player2_index.is_none()); // LATER
 
@@ -372,11 +345,19 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     }
 
     let map = load_map(&assets, &map_path);
+    // Not fatal, missing textures are drawn as magenta squares.
+    for problem in assets.problems(map.surfaces()) {
+        dbg_logf!("WARNING: Asset problem: {problem}");
+    }
     let mut gs = GameState::new();
     gs.frame_num = frame_num;
     gs.game_time = game_time;
     gs.game_time_prev = game_time_prev;
     gs.dt = dt;
+    gs.wrecks = wrecks;
+    gs.zone = zone;
+    gs.phase = phase;
+    gs.world_score = world_score;
 
     let mut ctx = FrameCtx::new(&cvars, &map, &mut gs);
     for player in players {
@@ -388,6 +369,12 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     for projectile in projectiles {
         ctx.init_projectile(projectile);
     }
+    for mine in mines {
+        ctx.init_mine(mine);
+    }
+    for emplacement in emplacements {
+        ctx.init_emplacement(emplacement);
+    }
 
     let player1_handle = gs.players.slot_to_index(local_player1_index).unwrap();
     // LATER After RustCycles has editor integration and has separate matches,
@@ -398,13 +385,16 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
         // Input is outside the game loop because
         // - It needs access to the console and ClientCtx doesn't have it.
         // - Macroquad only updates input once per frame anyway.
+        let focused = client.focus.update(&cvars, get_time());
+        client.focus_changed(&cvars, focused);
         client.cl_input(&cvars);
 
         client.update(&cvars, get_time());
+        client.apply_received_preset(&mut cvars);
 
         client.render(&cvars);
 
-        client.console.update(&mut cvars);
+        client.console.update(&mut WithUnits(&mut cvars));
 
         client.post_render(&cvars);
 
@@ -426,6 +416,7 @@ async fn server_main(mut cvars: Cvars) {
 
     loop {
         server.update(&cvars, get_time());
+        server.apply_preset_requests(&mut cvars);
 
         // Draw something ever frame so we know the server is not stuck.
         clear_background(BLACK);
@@ -1,6 +1,9 @@
 //! Map data (everything static during a match) and coordinate system
 
-use std::ops::Index;
+use std::{
+    collections::hash_map::Entry,
+    ops::{Index, Range},
+};
 
 use vek::approx::AbsDiffEq;
 
@@ -8,46 +11,198 @@ use crate::prelude::*;
 
 pub const TILE_SIZE: f64 = 64.0;
 
+/// Letters for grid columns like spreadsheets: A to Z, then AA, AB, ...
+pub fn grid_column_label(mut col: usize) -> String {
+    let mut label = Vec::new();
+    loop {
+        label.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    label.reverse();
+    String::from_utf8(label).unwrap()
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of `Map::surface_under` calls, to check the result gets cached.
+    pub static SURFACE_LOOKUPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 /// A rectangular tile based map with origin in the top-left corner.
 #[derive(Debug, Clone)]
 pub struct Map {
     pub path: String,
     surfaces: Vec<Surface>,
-    tiles: Vec<Vec<Tile>>,
+    /// Row-major, `width` tiles per row.
+    tiles: Vec<Tile>,
+    width: usize,
+    /// One bit per tile in the same order as `tiles`, set for walls.
+    ///
+    /// `is_wall` runs many times per tick for every projectile and vehicle
+    /// so it shouldn't have to go through `surfaces`.
+    walls: Vec<u64>,
+    /// All tiles which are not walls so `random_nonwall` doesn't have to guess.
+    nonwalls: Vec<Vec2u>,
     spawns: Vec<Vec2u>,
+    /// Team of each spawn in `spawns`, None means any team can use it.
+    spawn_teams: Vec<Option<u32>>,
     bases: Vec<Vec2u>,
+    /// Named points like base_red, base_blue, cow_start.
+    markers: FnvHashMap<String, Vec2u>,
+    /// Decoration placed by the map author.
+    wrecks: Vec<Wreck>,
+    /// Gun emplacements placed by the map author.
+    emplacements: Vec<EmplacementSpot>,
+    /// Problems found while loading optional metadata.
+    warnings: Vec<String>,
 }
 
 impl Map {
     /// The path is only used as an identifier.
-    fn new(tiles: Vec<Vec<Tile>>, surfaces: Vec<Surface>, path: &str) -> Self {
+    fn new(tiles: Vec<Tile>, width: usize, surfaces: Vec<Surface>, path: &str) -> Self {
+        let mut walls = vec![0; tiles.len().div_ceil(64)];
+        let mut nonwalls = Vec::new();
         let mut spawns = Vec::new();
         let mut bases = Vec::new();
-        for (r, row) in tiles.iter().enumerate() {
-            for (c, tile) in row.iter().enumerate() {
-                let kind = surfaces[tile.surface_index].kind;
-                if kind == SurfaceKind::Spawn {
-                    spawns.push(Vec2u::new(c, r));
-                } else if kind == SurfaceKind::Base {
-                    bases.push(Vec2u::new(c, r));
-                }
+        for (i, tile) in tiles.iter().enumerate() {
+            let index = Vec2u::new(i % width, i / width);
+            let kind = surfaces[tile.surface_index].kind;
+            if kind == SurfaceKind::Wall {
+                walls[i / 64] |= 1 << (i % 64);
+                continue;
+            }
+            nonwalls.push(index);
+            if kind == SurfaceKind::Spawn {
+                spawns.push(index);
+            } else if kind == SurfaceKind::Base {
+                bases.push(index);
             }
         }
+        let spawn_teams = vec![None; spawns.len()];
         Map {
             path: path.to_owned(),
             surfaces,
             tiles,
+            width,
+            walls,
+            nonwalls,
             spawns,
+            spawn_teams,
             bases,
+            markers: FnvHashMap::default(),
+            wrecks: Vec::new(),
+            emplacements: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Parse one line of optional metadata, see `parse_map` for the format.
+    ///
+    /// `line_num` is 1-based and only used for error messages.
+    fn parse_metadata(&mut self, line_num: usize, line: &str) -> Result<(), String> {
+        let parts: Vec<_> = line.split_whitespace().collect();
+        let parse_num = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|e| format!("line {line_num}: invalid number {s:?}: {e}"))
+        };
+        match *parts.as_slice() {
+            ["spawn_team", c, r, team] => {
+                let index = Vec2u::new(parse_num(c)?, parse_num(r)?);
+                let team: u32 = team
+                    .parse()
+                    .map_err(|e| format!("line {line_num}: invalid team {team:?}: {e}"))?;
+                let i = self
+                    .spawns
+                    .iter()
+                    .position(|&spawn| spawn == index)
+                    .ok_or_else(|| format!("line {line_num}: no spawn at {c} {r}"))?;
+                self.spawn_teams[i] = Some(team);
+            }
+            ["marker", name, c, r] => {
+                let index = Vec2u::new(parse_num(c)?, parse_num(r)?);
+                if index.x >= self.width() || index.y >= self.height() {
+                    return Err(format!("line {line_num}: marker {name} outside the map"));
+                }
+                match self.markers.entry(name.to_owned()) {
+                    Entry::Occupied(_) => {
+                        return Err(format!("line {line_num}: duplicate marker {name}"));
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(index);
+                    }
+                }
+            }
+            ["wreck", c, r, veh_type, angle] => {
+                let index = Vec2u::new(parse_num(c)?, parse_num(r)?);
+                if index.x >= self.width() || index.y >= self.height() {
+                    return Err(format!("line {line_num}: wreck outside the map"));
+                }
+                let veh_type = match veh_type {
+                    "tank" => VehicleType::Tank,
+                    "hovercraft" => VehicleType::Hovercraft,
+                    "hummer" => VehicleType::Hummer,
+                    _ => {
+                        return Err(format!(
+                            "line {line_num}: unknown vehicle type {veh_type:?}"
+                        ));
+                    }
+                };
+                let angle: f64 = angle
+                    .parse()
+                    .map_err(|e| format!("line {line_num}: invalid angle {angle:?}: {e}"))?;
+                self.wrecks.push(Wreck {
+                    pos: self.tile_center(index),
+                    angle: angle.to_radians(),
+                    veh_type,
+                });
+            }
+            ["emplacement", c, r, weapon, angle] => {
+                let index = Vec2u::new(parse_num(c)?, parse_num(r)?);
+                if index.x >= self.width() || index.y >= self.height() {
+                    return Err(format!("line {line_num}: emplacement outside the map"));
+                }
+                let pos = self.tile_center(index);
+                if self.is_wall(pos) {
+                    return Err(format!("line {line_num}: emplacement inside a wall"));
+                }
+                // Homing and guided missiles need a player to lock on or steer.
+                let weapon = match weapon.parse() {
+                    Ok(
+                        weapon @ (Weapon::Mg
+                        | Weapon::Rail
+                        | Weapon::Cb
+                        | Weapon::Rockets
+                        | Weapon::Bfg),
+                    ) => weapon,
+                    _ => {
+                        return Err(format!(
+                            "line {line_num}: weapon {weapon:?} can't be used by emplacements"
+                        ));
+                    }
+                };
+                let angle: f64 = angle
+                    .parse()
+                    .map_err(|e| format!("line {line_num}: invalid angle {angle:?}: {e}"))?;
+                self.emplacements.push(EmplacementSpot {
+                    pos,
+                    angle: angle.to_radians(),
+                    weapon,
+                });
+            }
+            _ => return Err(format!("line {line_num}: unknown metadata {line:?}")),
         }
+        Ok(())
     }
 
     pub fn height(&self) -> usize {
-        self.tiles.len()
+        self.tiles.len().checked_div(self.width).unwrap_or(0)
     }
 
     pub fn width(&self) -> usize {
-        self.tiles[0].len()
+        self.width
     }
 
     /// Returns (width, height) which is (cols, rows) which is (x, y)
@@ -69,9 +224,14 @@ impl Map {
         self.size().as_() * TILE_SIZE
     }
 
-    /// Returns tile at (c,r). Col is x, row is y
-    pub fn col_row(&self, c: usize, r: usize) -> Tile {
-        self[Vec2u::new(c, r)]
+    /// Returns tile at (c,r) or None if it's outside the map. Col is x, row is y
+    pub fn col_row_checked(&self, c: isize, r: isize) -> Option<Tile> {
+        let c = usize::try_from(c).ok()?;
+        let r = usize::try_from(r).ok()?;
+        if c >= self.width {
+            return None;
+        }
+        self.tiles.get(r * self.width + c).copied()
     }
 
     /// Converts world coords into tile position and offset within it.
@@ -87,10 +247,23 @@ impl Map {
         TilePos { index, offset }
     }
 
+    /// Callout label of the grid cell at `pos`, e.g. "E7".
+    ///
+    /// Columns are letters, rows are numbers starting from 1.
+    /// `cell_size` is in tiles so huge maps can use coarser cells.
+    pub fn world_pos_to_grid_label(&self, cell_size: usize, pos: Vec2f) -> String {
+        let index = self.tile_pos(pos).index / cell_size.max(1);
+        format!("{}{}", grid_column_label(index.x), index.y + 1)
+    }
+
     pub fn tile_center(&self, tile_index: Vec2u) -> Vec2f {
         tile_index.as_() * TILE_SIZE + TILE_SIZE / 2.0
     }
 
+    pub fn surfaces(&self) -> &[Surface] {
+        &self.surfaces
+    }
+
     pub fn surface_of(&self, tile: Tile) -> &Surface {
         &self.surfaces[tile.surface_index]
     }
@@ -100,6 +273,24 @@ impl Map {
         self.surface_at_index(tile_pos.index)
     }
 
+    /// The surface under a vehicle.
+    ///
+    /// Use the cached `Vehicle::surface` instead of calling this, see `sys_surface_under`.
+    pub fn surface_under(&self, pos: Vec2f) -> SurfaceUnder {
+        #[cfg(test)]
+        SURFACE_LOOKUPS.with(|lookups| lookups.set(lookups.get() + 1));
+
+        let tile_pos = self.tile_pos(pos);
+        let index = self[tile_pos.index].surface_index;
+        let surface = &self.surfaces[index];
+        SurfaceUnder {
+            index,
+            kind: surface.kind,
+            flags: surface.flags,
+            speed: surface.speed as f64,
+        }
+    }
+
     pub fn surface_at_index(&self, index: Vec2u) -> &Surface {
         let surface_index = self[index].surface_index;
         &self.surfaces[surface_index]
@@ -121,14 +312,113 @@ impl Map {
             return true;
         }
 
-        let kind = self.surface_at_pos(pos).kind;
-        if kind == SurfaceKind::Wall {
+        // Inside the map so no need to clamp like `tile_pos`.
+        // Dividing by a power of 2 is exact so it can't round up to the next tile.
+        let c = (pos.x / TILE_SIZE) as usize;
+        let r = (pos.y / TILE_SIZE) as usize;
+        self.is_wall_tile(r * self.width + c)
+    }
+
+    /// Does the convex polygon overlap any wall (or the area outside the map)?
+    ///
+    /// Only touching a wall's edge doesn't count.
+    /// Degenerate polygons (a point or a segment) work too.
+    pub fn convex_touches_wall(&self, polygon: &[Vec2f]) -> bool {
+        let init = (Vec2f::broadcast(f64::MAX), Vec2f::broadcast(f64::MIN));
+        let (mins, maxs) = polygon.iter().fold(init, |(mins, maxs), &p| {
+            let mins = Vec2f::new(mins.x.min(p.x), mins.y.min(p.y));
+            let maxs = Vec2f::new(maxs.x.max(p.x), maxs.y.max(p.y));
+            (mins, maxs)
+        });
+        let map_size = self.maxs();
+        if mins.x <= 0.0 || mins.y <= 0.0 || maxs.x >= map_size.x || maxs.y >= map_size.y {
             return true;
         }
 
+        // Separating axis test against each wall tile in the bounding box.
+        // The world axes are checked first, the rest are the polygon's edge normals.
+        let normals: Vec<_> = (0..polygon.len())
+            .map(|i| {
+                let edge = polygon[(i + 1) % polygon.len()] - polygon[i];
+                v!(-edge.y, edge.x)
+            })
+            .filter(|normal| *normal != Vec2f::zero())
+            .collect();
+        let project = |points: &[Vec2f], axis: Vec2f| {
+            points.iter().fold((f64::MAX, f64::MIN), |(min, max), &p| {
+                (min.min(p.dot(axis)), max.max(p.dot(axis)))
+            })
+        };
+
+        let (c_min, c_max) = ((mins.x / TILE_SIZE) as usize, (maxs.x / TILE_SIZE) as usize);
+        let (r_min, r_max) = ((mins.y / TILE_SIZE) as usize, (maxs.y / TILE_SIZE) as usize);
+        for r in r_min..=r_max {
+            'tiles: for c in c_min..=c_max {
+                if !self.is_wall_tile(r * self.width + c) {
+                    continue;
+                }
+                let tile_min = Vec2f::new(c as f64, r as f64) * TILE_SIZE;
+                let tile_max = tile_min + TILE_SIZE;
+                if maxs.x <= tile_min.x
+                    || tile_max.x <= mins.x
+                    || maxs.y <= tile_min.y
+                    || tile_max.y <= mins.y
+                {
+                    continue;
+                }
+                let tile = [
+                    tile_min,
+                    v!(tile_max.x, tile_min.y),
+                    tile_max,
+                    v!(tile_min.x, tile_max.y),
+                ];
+                for &normal in &normals {
+                    let (poly_lo, poly_hi) = project(polygon, normal);
+                    let (tile_lo, tile_hi) = project(&tile, normal);
+                    if poly_hi <= tile_lo || tile_hi <= poly_lo {
+                        continue 'tiles;
+                    }
+                }
+                return true;
+            }
+        }
         false
     }
 
+    /// Is any wall (or the area outside the map) within `radius` of `center`?
+    pub fn circle_touches_wall(&self, center: Vec2f, radius: f64) -> bool {
+        let mins = Vec2f::new(center.x - radius, center.y - radius);
+        let maxs = Vec2f::new(center.x + radius, center.y + radius);
+        let map_size = self.maxs();
+        if mins.x <= 0.0 || mins.y <= 0.0 || maxs.x >= map_size.x || maxs.y >= map_size.y {
+            return true;
+        }
+
+        let (c_min, c_max) = ((mins.x / TILE_SIZE) as usize, (maxs.x / TILE_SIZE) as usize);
+        let (r_min, r_max) = ((mins.y / TILE_SIZE) as usize, (maxs.y / TILE_SIZE) as usize);
+        for r in r_min..=r_max {
+            for c in c_min..=c_max {
+                if !self.is_wall_tile(r * self.width + c) {
+                    continue;
+                }
+                let tile_min = Vec2f::new(c as f64, r as f64) * TILE_SIZE;
+                let closest = Vec2f::new(
+                    center.x.clamped(tile_min.x, tile_min.x + TILE_SIZE),
+                    center.y.clamped(tile_min.y, tile_min.y + TILE_SIZE),
+                );
+                if (closest - center).magnitude_squared() <= radius * radius {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// `i` is the index into the flat row-major `tiles`.
+    fn is_wall_tile(&self, i: usize) -> bool {
+        self.walls[i / 64] & (1 << (i % 64)) != 0
+    }
+
     /// Find first wall collision when traveling from `begin` to `end`.
     /// The returned point is nudged slightly inside the wall.
     /// Area outside the map is considered wall.
@@ -196,13 +486,95 @@ impl Map {
         }
     }
 
+    /// `is_wall_trace` for tools outside the game, also says which tile was hit.
+    pub fn raycast(&self, begin: Vec2f, end: Vec2f) -> Option<RaycastHit> {
+        let point = self.is_wall_trace(begin, end)?;
+        let maxs = self.maxs();
+        // Same edges as `is_wall`.
+        let inside = point.x > 0.0 && point.y > 0.0 && point.x < maxs.x && point.y < maxs.y;
+        let tile = inside.then(|| (point / TILE_SIZE).as_());
+        Some(RaycastHit { point, tile })
+    }
+
+    /// Walls of the whole map for tools outside the game, e.g. pathfinding.
+    ///
+    /// Built from the same data as `is_wall` so it has exactly the game's semantics.
+    pub fn collision_grid(&self) -> CollisionGrid {
+        let mut walls = vec![0; self.tiles.len().div_ceil(8)];
+        for i in 0..self.tiles.len() {
+            if self.is_wall_tile(i) {
+                walls[i / 8] |= 1 << (i % 8);
+            }
+        }
+        CollisionGrid {
+            width: self.width(),
+            height: self.height(),
+            walls,
+        }
+    }
+
+    /// Like `collision_grid` but with the kind of each tile's surface.
+    pub fn surface_grid(&self) -> SurfaceGrid {
+        let kinds = self
+            .tiles
+            .iter()
+            .map(|tile| self.surfaces[tile.surface_index].kind as u8)
+            .collect();
+        SurfaceGrid {
+            width: self.width(),
+            height: self.height(),
+            kinds,
+        }
+    }
+
     pub fn spawns(&self) -> &Vec<Vec2u> {
         &self.spawns
     }
 
     // LATER remove all #[allow(dead_code)] here (or the fns if they turn out useless)
 
-    #[allow(dead_code)]
+    /// Spawns which belong to the given team.
+    pub fn spawns_for_team(&self, team: u32) -> Vec<Vec2u> {
+        self.spawns
+            .iter()
+            .zip(&self.spawn_teams)
+            .filter(|(_, spawn_team)| **spawn_team == Some(team))
+            .map(|(&spawn, _)| spawn)
+            .collect()
+    }
+
+    /// Team of each spawn in `spawns`, None means any team can use it.
+    pub fn spawn_teams(&self) -> &[Option<u32>] {
+        &self.spawn_teams
+    }
+
+    /// Position of a named marker, e.g. base_red, base_blue, cow_start.
+    pub fn marker(&self, name: &str) -> Option<Vec2u> {
+        self.markers.get(name).copied()
+    }
+
+    /// All named markers in no particular order.
+    pub fn markers(&self) -> impl Iterator<Item = (&str, Vec2u)> {
+        self.markers
+            .iter()
+            .map(|(name, &index)| (name.as_str(), index))
+    }
+
+    /// Wrecks placed by the map, see `ServerFrameCtx::spawn_wrecks`.
+    pub fn wrecks(&self) -> &[Wreck] {
+        &self.wrecks
+    }
+
+    /// Gun emplacements placed by the map, see `ServerFrameCtx::spawn_emplacements`.
+    pub fn emplacements(&self) -> &[EmplacementSpot] {
+        &self.emplacements
+    }
+
+    /// Problems found while loading the map's optional metadata.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
     pub fn bases(&self) -> &Vec<Vec2u> {
         &self.bases
     }
@@ -213,16 +585,7 @@ impl Map {
 
     #[allow(dead_code)]
     pub fn count_nonwalls(&self) -> usize {
-        let mut cnt = 0;
-        for c in 0..self.width() {
-            for r in 0..self.height() {
-                let index = Vec2u::new(c, r);
-                if self.surface_at_index(index).kind != SurfaceKind::Wall {
-                    cnt += 1;
-                }
-            }
-        }
-        cnt
+        self.nonwalls.len()
     }
 
     /// Returns (pos, angle).
@@ -239,28 +602,124 @@ impl Map {
         (pos, angle)
     }
 
+    /// Like `random_spawn` but only uses the team's spawns.
+    ///
+    /// Falls back to any spawn if the team has none.
     /// Returns (pos, angle).
-    pub fn random_nonwall(&self, rng: &mut Xoshiro256PlusPlus) -> (Vec2f, f64) {
-        loop {
-            let c = rng.gen_range(0..self.width());
-            let r = rng.gen_range(0..self.height());
-            let index = Vec2u::new(c, r);
-            if self.surface_at_index(index).kind != SurfaceKind::Wall {
-                let pos = self.tile_center(index);
-                let angle = self[index].angle;
-                return (pos, angle);
-            }
+    pub fn random_spawn_team(&self, rng: &mut Xoshiro256PlusPlus, team: u32) -> (Vec2f, f64) {
+        let spawns = self.spawns_for_team(team);
+        if spawns.is_empty() {
+            dbg_logf!("WARNING: no spawns for team {team} in map, using any spawn");
+            return self.random_spawn(rng);
         }
+
+        let index = spawns[rng.gen_range(0..spawns.len())];
+        let pos = self.tile_center(index);
+        let angle = self[index].angle;
+        (pos, angle)
+    }
+
+    /// Returns (pos, angle).
+    ///
+    /// Panics if the whole map is walls.
+    pub fn random_nonwall(&self, rng: &mut Xoshiro256PlusPlus) -> (Vec2f, f64) {
+        assert!(!self.nonwalls.is_empty(), "map {} is all walls", self.path);
+        let index = self.nonwalls[rng.gen_range(0..self.nonwalls.len())];
+        let pos = self.tile_center(index);
+        let angle = self[index].angle;
+        (pos, angle)
     }
 }
 
 impl Index<Vec2u> for Map {
     type Output = Tile;
     fn index(&self, index: Vec2u) -> &Self::Output {
-        &self.tiles[index.y][index.x]
+        assert!(index.x < self.width, "column {} outside the map", index.x);
+        &self.tiles[index.y * self.width + index.x]
+    }
+}
+
+/// Tiles covering the camera's view.
+///
+/// Unlike `TilePos`, the indices are not clamped to the map
+/// so they can be outside the map at the edges (and due to float rounding).
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibleTiles {
+    /// Columns (x)
+    pub cols: Range<isize>,
+    /// Rows (y)
+    pub rows: Range<isize>,
+    /// Offset of the view's top left corner inside the first tile
+    pub offset: Vec2f,
+}
+
+/// Compute which tiles are visible when the view's top left corner is at `top_left` (world coords).
+pub fn visible_tiles(top_left: Vec2f, view_size: Vec2f) -> VisibleTiles {
+    let first = (top_left / TILE_SIZE).floor();
+    let end = ((top_left + view_size) / TILE_SIZE).ceil();
+    let offset = top_left - first * TILE_SIZE;
+    VisibleTiles {
+        cols: first.x as isize..end.x as isize,
+        rows: first.y as isize..end.y as isize,
+        offset,
     }
 }
 
+/// Direction out of the wall which `Map::is_wall_trace` from `begin` hit at `hit`.
+///
+/// Walls are whole tiles so it's along an axis,
+/// except when hitting exactly at a tile's corner where it's diagonal.
+/// Zero if `begin` was already inside the wall.
+pub fn wall_normal(begin: Vec2f, hit: Vec2f) -> Vec2f {
+    let Some(dir) = (hit - begin).try_normalized() else {
+        return Vec2f::zero();
+    };
+    // The hit is nudged slightly into the wall, step back out of it
+    // and see which tile boundary we crossed.
+    let before = hit - dir * 0.02;
+    let crossed = (before / TILE_SIZE).floor() - (hit / TILE_SIZE).floor();
+    crossed.try_normalized().unwrap_or(-dir)
+}
+
+/// Where `Map::raycast` hit a wall.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RaycastHit {
+    /// Nudged slightly inside the wall like `Map::is_wall_trace`.
+    pub point: Vec2f,
+    /// None if the point is outside the map.
+    pub tile: Option<Vec2u>,
+}
+
+/// Which tiles are walls, see `Map::collision_grid`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollisionGrid {
+    pub width: usize,
+    pub height: usize,
+    /// One bit per tile, row-major, set for walls.
+    ///
+    /// Tile `i = row * width + col` is bit `i % 8` (least significant first) of byte `i / 8`.
+    /// The unused bits of the last byte are 0.
+    pub walls: Vec<u8>,
+}
+
+impl CollisionGrid {
+    /// Unlike `Map::is_wall`, panics outside the map.
+    pub fn is_wall(&self, index: Vec2u) -> bool {
+        assert!(index.x < self.width, "column {} outside the map", index.x);
+        let i = index.y * self.width + index.x;
+        self.walls[i / 8] & (1 << (i % 8)) != 0
+    }
+}
+
+/// Surface kind of every tile, see `Map::surface_grid`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SurfaceGrid {
+    pub width: usize,
+    pub height: usize,
+    /// One byte per tile, row-major, the `SurfaceKind` as a number (e.g. 2 for walls).
+    pub kinds: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct TilePos {
     /// Position of the tile in the map
@@ -285,16 +744,57 @@ pub struct Surface {
     pub friction: f32,
     /// Maybe a multiplier for speed
     pub speed: f32,
+    pub flags: SurfaceFlags,
 }
 
 impl Surface {
-    fn new(name: String, kind: SurfaceKind, friction: f32, speed: f32) -> Self {
+    fn new(
+        name: String,
+        kind: SurfaceKind,
+        friction: f32,
+        speed: f32,
+        flags: SurfaceFlags,
+    ) -> Self {
         Self {
             name,
             kind,
             friction,
             speed,
+            flags,
+        }
+    }
+}
+
+/// Which effects vehicles produce when driving on a surface.
+///
+/// Not in the original RecWar's TextureList.txt,
+/// listed by name after the other fields, e.g. `road 0 1.4 1.4 marks`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SurfaceFlags {
+    /// Hard enough to leave tire / track marks
+    pub marks: bool,
+    /// Kicks up dust
+    pub dust: bool,
+    /// Splashes
+    pub spray: bool,
+}
+
+impl SurfaceFlags {
+    pub fn parse<'a>(words: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut flags = Self::default();
+        for word in words {
+            let flag = match word {
+                "marks" => &mut flags.marks,
+                "dust" => &mut flags.dust,
+                "spray" => &mut flags.spray,
+                _ => return Err(format!("unknown flag {word:?}")),
+            };
+            if *flag {
+                return Err(format!("duplicate flag {word:?}"));
+            }
+            *flag = true;
         }
+        Ok(flags)
     }
 }
 
@@ -318,31 +818,66 @@ pub enum SurfaceKind {
 }
 
 /// The path is only used as an identifier.
+///
+/// After the tiles, maps can optionally contain metadata, one item per line
+/// (coordinates are column and row of the tile):
+/// - `spawn_team <col> <row> <team>` - only this team spawns there
+/// - `marker <name> <col> <row>` - a named point, e.g. base_red, base_blue, cow_start
+/// - `wreck <col> <row> <tank|hovercraft|hummer> <angle>` - scenery, angle in degrees clockwise from right
+/// - `emplacement <col> <row> <mg|rail|cb|rockets|bfg> <angle>` - a stationary gun, angle of its base like wrecks
+///
+/// Invalid metadata lines are skipped with a warning, see `Map::warnings`.
+///
+/// Panics if the rows don't all have the same length.
 pub fn parse_map(text: &str, surfaces: Vec<Surface>, path: &str) -> Map {
-    let tiles = text
-        .replace("\r\n", "\n")
-        .split_terminator('\n')
-        .map(|line| {
-            line.split(' ')
-                .map(|tile| {
-                    let val: usize = tile.parse().unwrap();
-                    // rotation is number of turns counterclockwise
-                    // angle is clockwise (see Vec2f for coord system explanation)
-                    // g_spawn: rotation - angle - meaning
-                    // 0    0           right
-                    // 1    -1/2*PI     up
-                    // 2    -PI         left
-                    // 3    -3/2*PI     down
-                    let rotation = val % 4;
-                    Tile {
-                        surface_index: val / 4,
-                        angle: rotation as f64 * -PI / 2.0,
-                    }
-                })
-                .collect()
-        })
-        .collect();
-    Map::new(tiles, surfaces, path)
+    let mut tiles = Vec::new();
+    let mut width = None;
+    let mut metadata = Vec::new();
+    // `lines` also strips the \r from \r\n.
+    for (i, line) in text.lines().enumerate() {
+        let is_tiles = line.starts_with(|c: char| c.is_ascii_digit());
+        if !is_tiles {
+            metadata.push((i + 1, line));
+            continue;
+        }
+
+        let row_start = tiles.len();
+        tiles.extend(line.split(' ').map(|tile| {
+            let val: usize = tile.parse().unwrap();
+            // rotation is number of turns counterclockwise
+            // angle is clockwise (see Vec2f for coord system explanation)
+            // g_spawn: rotation - angle - meaning
+            // 0    0           right
+            // 1    -1/2*PI     up
+            // 2    -PI         left
+            // 3    -3/2*PI     down
+            let rotation = val % 4;
+            Tile {
+                surface_index: val / 4,
+                angle: rotation as f64 * -PI / 2.0,
+            }
+        }));
+        let row_len = tiles.len() - row_start;
+        let width = *width.get_or_insert(row_len);
+        assert_eq!(
+            row_len,
+            width,
+            "map {path}: line {} has {row_len} tiles, expected {width}",
+            i + 1
+        );
+    }
+
+    let mut map = Map::new(tiles, width.unwrap_or(0), surfaces, path);
+    for (line_num, line) in metadata {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Err(e) = map.parse_metadata(line_num, line) {
+            dbg_logf!("WARNING: map {path}: {e}");
+            map.warnings.push(e);
+        }
+    }
+    map
 }
 
 pub fn parse_texture_list(text: &str) -> Vec<Surface> {
@@ -356,8 +891,11 @@ pub fn parse_texture_list(text: &str) -> Vec<Surface> {
             let friction = parts.next().unwrap().parse().unwrap();
             let speed = parts.next().unwrap().parse().unwrap();
 
+            let flags = SurfaceFlags::parse(parts.filter(|part| !part.is_empty()))
+                .unwrap_or_else(|e| panic!("texture list: surface {name}: {e}"));
+
             let kind = SurfaceKind::from_repr(kind_num).unwrap();
-            Surface::new(name.to_owned(), kind, friction, speed)
+            Surface::new(name.to_owned(), kind, friction, speed, flags)
         })
         .collect()
 }
@@ -366,23 +904,37 @@ pub fn parse_texture_list(text: &str) -> Vec<Surface> {
 mod tests {
     use super::*;
 
-    use std::fs;
-
     use walkdir::WalkDir;
 
+    use crate::test_utils;
+
     #[test]
     fn test_loading_tex_list() {
-        let text = fs::read_to_string("data/texture_list.txt").unwrap();
-        let surfaces = parse_texture_list(&text);
-        assert_ne!(surfaces.len(), 0);
+        assert_ne!(test_utils::surfaces().len(), 0);
+    }
+
+    #[test]
+    fn test_surface_flags() {
+        let surfaces = parse_texture_list("a 0 1 1\nb 0 1 1 marks\nc 3 1 0.5 spray dust\n");
+        assert_eq!(surfaces[0].flags, SurfaceFlags::default());
+        assert!(surfaces[1].flags.marks && !surfaces[1].flags.dust);
+        assert!(surfaces[2].flags.spray && surfaces[2].flags.dust && !surfaces[2].flags.marks);
+
+        assert!(SurfaceFlags::parse(["marks", "spray"].into_iter()).is_ok());
+        assert!(SurfaceFlags::parse(["mraks"].into_iter()).is_err());
+        assert!(SurfaceFlags::parse(["dust", "dust"].into_iter()).is_err());
+
+        let surfaces = test_utils::surfaces();
+        let road = surfaces.iter().find(|s| s.name == "road").unwrap();
+        assert!(road.flags.marks);
+        let water = surfaces.iter().find(|s| s.name == "water").unwrap();
+        assert!(water.flags.spray && !water.flags.marks);
     }
 
     #[test]
     fn test_loading_maps() {
         let mut cnt = 0;
 
-        let tex_list_text = fs::read_to_string("data/texture_list.txt").unwrap();
-        let surfaces = parse_texture_list(&tex_list_text);
         for entry in WalkDir::new("maps") {
             let entry = entry.unwrap();
             let is_map = entry.file_name().to_str().unwrap().ends_with(".map");
@@ -391,8 +943,7 @@ mod tests {
             }
 
             dbg!(entry.file_name());
-            let map_text = fs::read_to_string(entry.path()).unwrap();
-            let map = parse_map(&map_text, surfaces.clone(), "");
+            let map = test_utils::load_map(entry.path().to_str().unwrap());
             assert_ne!(map.width(), 0);
             assert_ne!(map.height(), 0);
             cnt += 1;
@@ -402,10 +953,7 @@ mod tests {
 
     #[test]
     fn test_map_a_simple_plan() {
-        let tex_list_text = fs::read_to_string("data/texture_list.txt").unwrap();
-        let surfaces = parse_texture_list(&tex_list_text);
-        let map_text = fs::read_to_string("maps/A simple plan (2).map").unwrap();
-        let map = parse_map(&map_text, surfaces, "");
+        let map = test_utils::load_map("maps/A simple plan (2).map");
         assert_eq!(map.width(), 55);
         assert_eq!(map.height(), 23);
         assert_eq!(map.size(), Vec2u::new(55, 23));
@@ -426,10 +974,7 @@ mod tests {
 
     #[test]
     fn test_collisions_between() {
-        let tex_list_text = fs::read_to_string("data/texture_list.txt").unwrap();
-        let surfaces = parse_texture_list(&tex_list_text);
-        let map_text = fs::read_to_string("maps/Corners (4).map").unwrap();
-        let map = parse_map(&map_text, surfaces, "");
+        let map = test_utils::load_map("maps/Corners (4).map");
 
         let outside = Vec2f::new(-50.0, -50.0);
 
@@ -450,4 +995,585 @@ mod tests {
         assert!(map.is_wall_trace(bottom_left, top_right + up).is_none());
         assert!(map.is_wall_trace(bottom_left, top_right - up).is_some());
     }
+
+    #[test]
+    fn test_visible_tiles() {
+        let view_size = Vec2f::new(640.0, 480.0);
+
+        let visible = visible_tiles(Vec2f::new(0.0, 0.0), view_size);
+        assert_eq!(visible.cols, 0..10);
+        assert_eq!(visible.rows, 0..8);
+        assert_eq!(visible.offset, Vec2f::zero());
+
+        // Camera at max for a 20x10 tile map
+        let max = Vec2f::new(1280.0, 640.0) - view_size;
+        let visible = visible_tiles(max, view_size);
+        assert_eq!(visible.cols, 10..20);
+        assert_eq!(visible.rows, 2..10);
+        assert_eq!(visible.offset, Vec2f::new(0.0, 32.0));
+
+        // One ulp below max
+        let below = max.map(|v| f64::from_bits(v.to_bits() - 1));
+        let visible = visible_tiles(below, view_size);
+        assert_eq!(visible.cols, 9..20);
+        assert_eq!(visible.rows, 2..10);
+        assert!(visible.offset.x < TILE_SIZE);
+        assert!(visible.offset.x > TILE_SIZE - 0.001);
+
+        // Slightly above max (rounding errors) - one column and row past the map.
+        let above = max + 1e-9;
+        let visible = visible_tiles(above, view_size);
+        assert_eq!(visible.cols, 10..21);
+        assert_eq!(visible.rows, 2..11);
+    }
+
+    #[test]
+    fn test_col_row_checked() {
+        let map = test_utils::load_map("maps/Corners (4).map");
+
+        let w = map.width() as isize;
+        let h = map.height() as isize;
+        assert!(map.col_row_checked(0, 0).is_some());
+        assert!(map.col_row_checked(w - 1, h - 1).is_some());
+        assert!(map.col_row_checked(w, 0).is_none());
+        assert!(map.col_row_checked(0, h).is_none());
+        assert!(map.col_row_checked(-1, 0).is_none());
+        assert!(map.col_row_checked(0, -1).is_none());
+    }
+
+    #[test]
+    fn test_swept_hitbox_thin_wall() {
+        let map = test_utils::load_map("maps/Corners (4).map");
+
+        // Column 14 is a one tile thick wall.
+        let before = map.tile_center(Vec2u::new(13, 3));
+        let after = map.tile_center(Vec2u::new(15, 3));
+        assert!(map.is_wall(map.tile_center(Vec2u::new(14, 3))));
+
+        let hitbox = Hitbox {
+            mins: Vec2f::new(-20.0, -10.0),
+            maxs: Vec2f::new(20.0, 10.0),
+        };
+        // Checking only the destination misses the wall.
+        assert!(!hitbox
+            .corners(after, 0.0)
+            .iter()
+            .any(|&corner| map.is_wall(corner)));
+        assert!(hitbox.swept_hits_wall(&map, before, after, 0.0));
+
+        let next_to_before = before + Vec2f::new(0.0, 10.0);
+        assert!(!hitbox.swept_hits_wall(&map, before, next_to_before, 0.0));
+        assert!(!hitbox.swept_hits_wall(&map, before, before, 0.0));
+    }
+
+    #[test]
+    fn test_swept_hitbox_narrow_wall() {
+        // A single wall tile from 320 to 384 on both axes.
+        let map = test_utils::arena(|r, c| if (r, c) == (5, 5) { "16" } else { "0" });
+
+        // Wider than the tile so both side corners pass it without touching.
+        let hitbox = Hitbox {
+            mins: v!(-10, -100),
+            maxs: v!(10 100),
+        };
+        let before = v!(250 352);
+        let after = v!(450 352);
+        let contacts = hitbox.swept_wall_contacts(&map, before, after, 0.0);
+        assert_eq!(contacts, Vec::new());
+        assert!(hitbox.swept_hits_wall(&map, before, after, 0.0));
+
+        // Stopping just before the tile and passing next to it is fine.
+        assert!(!hitbox.swept_hits_wall(&map, before, v!(300 352), 0.0));
+        assert!(!hitbox.swept_hits_wall(&map, v!(250 200), v!(450 200), 0.0));
+
+        // Degenerate hitboxes
+        let point = Hitbox {
+            mins: v!(0 0),
+            maxs: v!(0 0),
+        };
+        assert!(point.swept_hits_wall(&map, before, after, 0.0));
+        assert!(!point.swept_hits_wall(&map, v!(250 300), v!(450 300), 0.0));
+        assert!(point.swept_hits_wall(&map, v!(352 352), v!(352 352), 0.0));
+        assert!(!point.swept_hits_wall(&map, v!(250 250), v!(250 250), 0.0));
+    }
+
+    #[test]
+    fn test_wall_contacts() {
+        // Open area from 64 to 256 on both axes.
+        let map = test_utils::parse_map(
+            "16 16 16 16 16
+16 0 0 0 16
+16 0 36 0 16
+16 0 0 0 16
+16 16 16 16 16
+",
+        );
+
+        let hitbox = Hitbox {
+            mins: Vec2f::new(-20.0, -10.0),
+            maxs: Vec2f::new(20.0, 10.0),
+        };
+        let contacts = |old_pos: Vec2f, new_pos: Vec2f| {
+            let contacts = hitbox.swept_wall_contacts(&map, old_pos, new_pos, 0.0);
+            let corners: Vec<_> = contacts.iter().map(|contact| contact.corner).collect();
+            let normals: Vec<_> = contacts.iter().map(|contact| contact.normal).collect();
+            (corners, normals)
+        };
+
+        // Corners are back_left, front_left, front_right, back_right.
+        let right = contacts(v!(230 160), v!(240 160));
+        assert_eq!(right, (vec![1, 2], vec![v!(-1 0), v!(-1 0)]));
+        let left = contacts(v!(90 160), v!(80 160));
+        assert_eq!(left, (vec![0, 3], vec![v!(1 0), v!(1 0)]));
+        let top = contacts(v!(160 80), v!(160 70));
+        assert_eq!(top, (vec![0, 1], vec![v!(0 1), v!(0 1)]));
+        let bottom = contacts(v!(160 240), v!(160 250));
+        assert_eq!(bottom, (vec![2, 3], vec![v!(0, -1), v!(0, -1)]));
+
+        // Sliding along the wall, the normal still points out of it.
+        let sliding = contacts(v!(230 160), v!(240 170));
+        assert_eq!(sliding, (vec![1, 2], vec![v!(-1 0), v!(-1 0)]));
+
+        // Only the corner that got there.
+        let rotated = hitbox.swept_wall_contacts(&map, v!(230 160), v!(236 160), 0.3);
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(rotated[0].corner, 1);
+        assert!((rotated[0].point.x - 256.0).abs() < 0.1);
+
+        assert!(contacts(v!(160 160), v!(170 160)).0.is_empty());
+    }
+
+    /// Open area from 64 to 256 on both axes with a wall tile from 128 to 192 in the middle.
+    fn pillar_map() -> Map {
+        test_utils::parse_map(
+            "16 16 16 16 16
+16 0 0 0 16
+16 0 16 0 16
+16 0 0 0 16
+16 16 16 16 16
+",
+        )
+    }
+
+    #[test]
+    fn test_circle_touches_wall() {
+        let map = pillar_map();
+        // 32 from the outer walls and 45 from the pillar's corner.
+        assert!(!map.circle_touches_wall(v!(96 96), 31.0));
+        assert!(map.circle_touches_wall(v!(96 96), 33.0));
+        assert!(!map.circle_touches_wall(v!(160 96), 31.0));
+        assert!(map.circle_touches_wall(v!(160 96), 33.0));
+        assert!(!map.circle_touches_wall(v!(224 224), 31.0));
+        assert!(map.circle_touches_wall(v!(96 224), 100.0));
+        // Outside the map counts as wall.
+        let big = Vec2f::new(-1000.0, -1000.0);
+        assert!(map.circle_touches_wall(big, 1.0));
+    }
+
+    #[test]
+    fn test_swept_rotation_corner() {
+        let map = pillar_map();
+        let hitbox = Hitbox {
+            mins: Vec2f::new(-40.0, -10.0),
+            maxs: Vec2f::new(40.0, 10.0),
+        };
+        let in_wall = |pos: Vec2f, angle: f64| {
+            hitbox
+                .corners(pos, angle)
+                .iter()
+                .any(|&corner| map.is_wall(corner))
+        };
+
+        // The front right corner is just above the pillar and swings through its corner
+        // when turning 90 degrees in one frame. Both ends are clear.
+        let pos = v!(110 104);
+        assert!(!in_wall(pos, 0.0));
+        assert!(!in_wall(pos, PI / 2.0));
+
+        // 16 samples (capped), the 4th is blocked.
+        let angle = hitbox.swept_rotation(&map, pos, 0.0, PI / 2.0, 4.0, 16);
+        assert!((angle - 3.0 / 16.0 * PI / 2.0).abs() < 1e-9, "{angle}");
+        assert!(!in_wall(pos, angle));
+
+        // A single sample only checks the end like before.
+        let angle = hitbox.swept_rotation(&map, pos, 0.0, PI / 2.0, 4.0, 1);
+        assert_eq!(angle, PI / 2.0);
+
+        // Nothing within reach.
+        let small = Hitbox {
+            mins: Vec2f::new(-10.0, -5.0),
+            maxs: Vec2f::new(10.0, 5.0),
+        };
+        let angle = small.swept_rotation(&map, v!(96 96), 0.0, 3.0, 4.0, 16);
+        assert_eq!(angle, 3.0);
+    }
+
+    #[test]
+    fn test_swept_rotation_never_in_wall() {
+        let map = pillar_map();
+        let hitbox = Hitbox {
+            mins: Vec2f::new(-25.0, -12.0),
+            maxs: Vec2f::new(25.0, 12.0),
+        };
+        let in_wall = |pos: Vec2f, angle: f64| {
+            hitbox
+                .corners(pos, angle)
+                .iter()
+                .any(|&corner| map.is_wall(corner))
+        };
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut clamped = 0;
+        for _ in 0..2000 {
+            let pos = Vec2f::new(rng.gen_range(64.0..256.0), rng.gen_range(64.0..256.0));
+            let old_angle = rng.gen_range(0.0..2.0 * PI);
+            if in_wall(pos, old_angle) {
+                continue;
+            }
+            let new_angle = (old_angle + rng.gen_range(-PI..PI)).rem_euclid(2.0 * PI);
+            let angle = hitbox.swept_rotation(&map, pos, old_angle, new_angle, 4.0, 16);
+            assert!(!in_wall(pos, angle), "{pos:?} {old_angle} {new_angle}");
+            if angle != new_angle {
+                clamped += 1;
+            }
+        }
+        assert!(clamped > 0);
+    }
+
+    /// 4x4 map, walls around, spawns (tile 36) at (1, 1), (2, 1) and (1, 2).
+    const SMALL_MAP: &str = "16 16 16 16
+16 36 36 16
+16 36 0 16
+16 16 16 16
+";
+
+    fn parse_small_map(metadata: &str) -> Map {
+        test_utils::parse_map(&format!("{SMALL_MAP}{metadata}"))
+    }
+
+    #[test]
+    fn test_map_without_metadata() {
+        let map = parse_small_map("");
+        assert_eq!(map.size(), Vec2u::new(4, 4));
+        assert_eq!(map.spawns().len(), 3);
+        assert!(map.spawns_for_team(0).is_empty());
+        assert_eq!(map.marker("base_red"), None);
+        assert!(map.wrecks().is_empty());
+        assert!(map.emplacements().is_empty());
+        assert!(map.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_map_wrecks() {
+        let map = parse_small_map(
+            "wreck 1 1 tank 0
+wreck 2 2 hummer 90
+wreck 2 1 bicycle 0
+wreck 5 1 tank 0
+wreck 1 2 hovercraft up
+",
+        );
+        assert_eq!(map.warnings().len(), 3, "{:?}", map.warnings());
+        let wrecks = map.wrecks();
+        assert_eq!(wrecks.len(), 2);
+        assert_eq!(wrecks[0].pos, v!(96 96));
+        assert_eq!(wrecks[0].veh_type, VehicleType::Tank);
+        assert_eq!(wrecks[0].angle, 0.0);
+        assert_eq!(wrecks[1].pos, v!(160 160));
+        assert_eq!(wrecks[1].veh_type, VehicleType::Hummer);
+        assert!((wrecks[1].angle - PI / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_map_emplacements() {
+        let map = parse_small_map(
+            "emplacement 1 1 mg 0
+emplacement 2 2 Rail 180
+emplacement 0 0 mg 0
+emplacement 2 1 gm 0
+emplacement 4 1 mg 0
+emplacement 1 2 laser 0
+",
+        );
+        assert_eq!(map.warnings().len(), 4, "{:?}", map.warnings());
+        let emplacements = map.emplacements();
+        assert_eq!(emplacements.len(), 2);
+        assert_eq!(emplacements[0].pos, v!(96 96));
+        assert_eq!(emplacements[0].weapon, Weapon::Mg);
+        assert_eq!(emplacements[0].angle, 0.0);
+        assert_eq!(emplacements[1].pos, v!(160 160));
+        assert_eq!(emplacements[1].weapon, Weapon::Rail);
+        assert!((emplacements[1].angle - PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_map_metadata() {
+        let map = parse_small_map(
+            "spawn_team 1 1 0
+spawn_team 2 1 1
+spawn_team 1 2 1
+marker base_red 1 1
+marker cow_start 2 2
+",
+        );
+        assert!(map.warnings().is_empty(), "{:?}", map.warnings());
+        assert_eq!(map.spawns().len(), 3);
+        assert_eq!(map.spawns_for_team(0), vec![Vec2u::new(1, 1)]);
+        assert_eq!(
+            map.spawns_for_team(1),
+            vec![Vec2u::new(2, 1), Vec2u::new(1, 2)]
+        );
+        assert_eq!(map.marker("base_red"), Some(Vec2u::new(1, 1)));
+        assert_eq!(map.marker("cow_start"), Some(Vec2u::new(2, 2)));
+        assert_eq!(map.marker("base_blue"), None);
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for _ in 0..20 {
+            let (pos, _) = map.random_spawn_team(&mut rng, 0);
+            assert_eq!(pos, map.tile_center(Vec2u::new(1, 1)));
+        }
+    }
+
+    #[test]
+    fn test_map_metadata_fallback() {
+        let map = parse_small_map("spawn_team 1 1 0\n");
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let spawn_centers: Vec<_> = map.spawns().iter().map(|&s| map.tile_center(s)).collect();
+        let mut used = FnvHashSet::default();
+        for _ in 0..50 {
+            let (pos, _) = map.random_spawn_team(&mut rng, 7);
+            assert!(spawn_centers.contains(&pos));
+            used.insert((pos.x as i64, pos.y as i64));
+        }
+        assert!(used.len() > 1);
+    }
+
+    #[test]
+    fn test_map_metadata_warnings() {
+        let map = parse_small_map(
+            "spawn_team 2 2 0
+spawn_team x 1 0
+marker base_red 9 9
+marker cow_start 2 2
+marker cow_start 1 1
+teleporter 1 1
+
+spawn_team 1 1 0
+spawn_team 2 1 -1
+spawn_team 2 1 1.5
+",
+        );
+        let warnings = map.warnings();
+        assert_eq!(warnings.len(), 7, "{warnings:?}");
+        // Lines are 1-based and include the tiles.
+        assert!(warnings[0].starts_with("line 5:"), "{}", warnings[0]);
+        assert!(warnings[0].contains("no spawn"));
+        assert!(warnings[1].starts_with("line 6:"));
+        assert!(warnings[1].contains("invalid number"));
+        assert!(warnings[2].starts_with("line 7:"));
+        assert!(warnings[2].contains("outside"));
+        assert!(warnings[3].starts_with("line 9:"));
+        assert!(warnings[3].contains("duplicate"));
+        assert!(warnings[4].starts_with("line 10:"));
+        assert!(warnings[4].contains("unknown"));
+        assert!(warnings[5].starts_with("line 13:"));
+        assert!(warnings[5].contains("invalid team"));
+        assert!(warnings[6].starts_with("line 14:"));
+        assert!(warnings[6].contains("invalid team"));
+
+        // Valid lines still apply.
+        assert_eq!(map.marker("cow_start"), Some(Vec2u::new(2, 2)));
+        assert_eq!(map.spawns_for_team(0), vec![Vec2u::new(1, 1)]);
+        assert_eq!(map.spawn_teams().iter().flatten().count(), 1);
+    }
+
+    /// Random map with all kinds of surfaces, sizes not multiples of 64 to catch off-by-ones in `walls`.
+    fn random_map(rng: &mut Xoshiro256PlusPlus, surfaces: &[Surface]) -> Map {
+        let width = rng.gen_range(1..100);
+        let height = rng.gen_range(1..100);
+        let mut text = String::new();
+        for _ in 0..height {
+            let row: Vec<_> = (0..width)
+                .map(|_| {
+                    let surface = rng.gen_range(0..surfaces.len());
+                    let rotation = rng.gen_range(0..4);
+                    (surface * 4 + rotation).to_string()
+                })
+                .collect();
+            text.push_str(&row.join(" "));
+            text.push('\n');
+        }
+        parse_map(&text, surfaces.to_vec(), "random")
+    }
+
+    #[test]
+    fn test_precomputed_lookups() {
+        let surfaces = test_utils::surfaces();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for _ in 0..20 {
+            let map = random_map(&mut rng, &surfaces);
+
+            // The straightforward way
+            let is_wall_naive = |pos: Vec2f| {
+                let inside =
+                    pos.x > 0.0 && pos.y > 0.0 && pos.x < map.maxs().x && pos.y < map.maxs().y;
+                !inside || map.surface_at_pos(pos).kind == SurfaceKind::Wall
+            };
+            let mut nonwalls = Vec::new();
+            let mut spawns = Vec::new();
+            for r in 0..map.height() {
+                for c in 0..map.width() {
+                    let index = Vec2u::new(c, r);
+                    let kind = map.surface_at_index(index).kind;
+                    if kind != SurfaceKind::Wall {
+                        nonwalls.push(index);
+                    }
+                    if kind == SurfaceKind::Spawn {
+                        spawns.push(index);
+                    }
+                    let tile = map.col_row_checked(c as isize, r as isize).unwrap();
+                    assert_eq!(tile.surface_index, map[index].surface_index);
+                }
+            }
+            assert_eq!(map.count_nonwalls(), nonwalls.len());
+            assert_eq!(map.spawns(), &spawns);
+            assert!(map.col_row_checked(map.width() as isize, 0).is_none());
+            assert!(map.col_row_checked(0, map.height() as isize).is_none());
+
+            for _ in 0..1000 {
+                let pos = Vec2f::new(
+                    rng.gen_range(-100.0..map.maxs().x + 100.0),
+                    rng.gen_range(-100.0..map.maxs().y + 100.0),
+                );
+                assert_eq!(map.is_wall(pos), is_wall_naive(pos), "{pos}");
+                // Exactly on tile edges
+                let edge = (pos / TILE_SIZE).floor() * TILE_SIZE;
+                assert_eq!(map.is_wall(edge), is_wall_naive(edge), "{edge}");
+            }
+
+            if !nonwalls.is_empty() {
+                for _ in 0..100 {
+                    let (pos, _) = map.random_nonwall(&mut rng);
+                    assert!(!map.is_wall(pos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_collision_grid() {
+        let surfaces = test_utils::surfaces();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for _ in 0..20 {
+            let map = random_map(&mut rng, &surfaces);
+            let grid = map.collision_grid();
+            let surface_grid = map.surface_grid();
+            assert_eq!((grid.width, grid.height), (map.width(), map.height()));
+            assert_eq!(grid.walls.len(), (map.width() * map.height()).div_ceil(8));
+            assert_eq!(surface_grid.kinds.len(), map.width() * map.height());
+
+            for _ in 0..100 {
+                let index = Vec2u::new(
+                    rng.gen_range(0..map.width()),
+                    rng.gen_range(0..map.height()),
+                );
+                let i = index.y * map.width() + index.x;
+                assert_eq!(grid.is_wall(index), map.is_wall(map.tile_center(index)));
+                let kind = map.surface_at_index(index).kind;
+                assert_eq!(surface_grid.kinds[i], kind as u8);
+            }
+        }
+    }
+
+    #[test]
+    fn test_raycast() {
+        let surfaces = test_utils::surfaces();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for _ in 0..20 {
+            let map = random_map(&mut rng, &surfaces);
+            let random_pos = |rng: &mut Xoshiro256PlusPlus| {
+                Vec2f::new(
+                    rng.gen_range(-100.0..map.maxs().x + 100.0),
+                    rng.gen_range(-100.0..map.maxs().y + 100.0),
+                )
+            };
+            for _ in 0..100 {
+                let begin = random_pos(&mut rng);
+                let end = random_pos(&mut rng);
+                let hit = map.raycast(begin, end);
+                assert_eq!(hit.map(|hit| hit.point), map.is_wall_trace(begin, end));
+                if let Some(RaycastHit {
+                    point,
+                    tile: Some(tile),
+                }) = hit
+                {
+                    assert_eq!(tile, map.tile_pos(point).index);
+                    assert_eq!(map.surface_at_index(tile).kind, SurfaceKind::Wall);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2 has 2 tiles, expected 3")]
+    fn test_map_ragged_rows() {
+        test_utils::parse_map("0 0 0\n0 0\n");
+    }
+
+    #[test]
+    fn test_grid_column_label() {
+        assert_eq!(grid_column_label(0), "A");
+        assert_eq!(grid_column_label(4), "E");
+        assert_eq!(grid_column_label(25), "Z");
+        assert_eq!(grid_column_label(26), "AA");
+        assert_eq!(grid_column_label(27), "AB");
+        assert_eq!(grid_column_label(51), "AZ");
+        assert_eq!(grid_column_label(52), "BA");
+        assert_eq!(grid_column_label(701), "ZZ");
+        assert_eq!(grid_column_label(702), "AAA");
+    }
+
+    #[test]
+    fn test_world_pos_to_grid_label() {
+        // Wider than the alphabet
+        let row = vec!["0"; 30].join(" ");
+        let text = vec![row; 12].join("\n");
+        let map = test_utils::parse_map(&text);
+        assert_eq!(map.size(), Vec2u::new(30, 12));
+
+        assert_eq!(map.world_pos_to_grid_label(1, v!(1 1)), "A1");
+        assert_eq!(
+            map.world_pos_to_grid_label(1, map.tile_center(Vec2u::new(4, 6))),
+            "E7"
+        );
+        assert_eq!(
+            map.world_pos_to_grid_label(1, map.tile_center(Vec2u::new(26, 0))),
+            "AA1"
+        );
+        assert_eq!(
+            map.world_pos_to_grid_label(1, map.tile_center(Vec2u::new(29, 11))),
+            "AD12"
+        );
+        // Outside is clamped to the edge.
+        assert_eq!(map.world_pos_to_grid_label(1, v!(-100, 100_000)), "A12");
+
+        // Coarser cells
+        assert_eq!(
+            map.world_pos_to_grid_label(4, map.tile_center(Vec2u::new(3, 3))),
+            "A1"
+        );
+        assert_eq!(
+            map.world_pos_to_grid_label(4, map.tile_center(Vec2u::new(4, 6))),
+            "B2"
+        );
+        assert_eq!(
+            map.world_pos_to_grid_label(4, map.tile_center(Vec2u::new(29, 11))),
+            "H3"
+        );
+        // 0 means the same as 1 instead of dividing by 0.
+        assert_eq!(
+            map.world_pos_to_grid_label(0, map.tile_center(Vec2u::new(4, 6))),
+            "E7"
+        );
+    }
 }
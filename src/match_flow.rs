@@ -0,0 +1,410 @@
+//! How a match ends - `g_time_limit` and what happens when it's reached with the lead tied.
+//!
+//! `Phase::Playing` -> `Phase::Overtime` (only on a tie) -> `Phase::RoundEnd`.
+//! The server decides in `sys_match_phase` and tells clients with `ServerMessage::Phase`.
+//! In sudden death the deciding event is a kill so that check is in `overtime_kill`
+//! which `damage` calls.
+//!
+//! LATER Kill limits, restarting after `RoundEnd`.
+
+use crate::prelude::*;
+
+/// Same order as `TEAM_COLORS`.
+pub const TEAM_NAMES: [&str; 2] = ["Red", "Blue"];
+
+/// Teams are ranked instead of players.
+pub fn team_mode(gs: &GameState) -> bool {
+    matches!(gs.game_mode, GameMode::Tw(_))
+}
+
+/// Who's in first place right now, None when it's tied or nobody is playing.
+///
+/// In team modes, teams are ranked by their members' total points
+/// and players without a team don't count.
+pub fn leader(cvars: &Cvars, gs: &GameState) -> Option<Winner> {
+    let teams = team_mode(gs);
+    let mut standings: Vec<(Winner, i32)> = Vec::new();
+    for (player_handle, player) in gs.players.iter() {
+        let entry = if teams {
+            let Some(team) = player.team else {
+                continue;
+            };
+            Winner::Team(team)
+        } else {
+            let Some(id) = gs.player_ids.id(player_handle) else {
+                continue;
+            };
+            Winner::Player(id)
+        };
+        let points = player.score.points(cvars);
+        match standings.iter_mut().find(|(other, _)| *other == entry) {
+            Some((_, total)) => *total += points,
+            None => standings.push((entry, points)),
+        }
+    }
+
+    let best = standings.iter().map(|&(_, points)| points).max()?;
+    let mut leaders = standings.iter().filter(|&&(_, points)| points == best);
+    let (first, _) = *leaders.next()?;
+    if leaders.next().is_some() {
+        None
+    } else {
+        Some(first)
+    }
+}
+
+/// Damage multiplier during `OvertimeMode::Escalation` which started at `start_time`.
+///
+/// Grows by `g_overtime_escalation_step` after each full `g_overtime_escalation_interval`.
+pub fn escalation_multiplier(cvars: &Cvars, start_time: f64, time: f64) -> f64 {
+    if cvars.g_overtime_escalation_interval <= 0.0 {
+        return 1.0;
+    }
+    let steps = ((time - start_time) / cvars.g_overtime_escalation_interval)
+        .floor()
+        .max(0.0);
+    let multiplier = 1.0 + steps * cvars.g_overtime_escalation_step;
+    multiplier.min(cvars.g_overtime_escalation_max)
+}
+
+/// The text of the `hud_phase_banner`, None while playing normally.
+pub fn phase_banner(cvars: &Cvars, gs: &GameState) -> Option<String> {
+    match gs.phase {
+        Phase::Playing => None,
+        Phase::Overtime {
+            mode: OvertimeMode::Escalation,
+            start_time,
+        } => {
+            let multiplier = escalation_multiplier(cvars, start_time, gs.game_time);
+            Some(format!("OVERTIME - damage x{multiplier:.2}"))
+        }
+        Phase::Overtime { .. } => Some("OVERTIME - next kill wins".to_owned()),
+        Phase::RoundEnd { winner: None, .. } => Some("DRAW".to_owned()),
+        Phase::RoundEnd {
+            winner: Some(Winner::Team(team)),
+            ..
+        } => {
+            let name = TEAM_NAMES.get(team as usize).copied().unwrap_or("Unknown");
+            Some(format!("{name} team wins"))
+        }
+        Phase::RoundEnd {
+            winner: Some(Winner::Player(id)),
+            ..
+        } => {
+            let name = gs
+                .player_ids
+                .handle(id)
+                .and_then(|handle| gs.players.get(handle))
+                .map_or("A player who left", |player| &player.name);
+            Some(format!("{name} wins"))
+        }
+    }
+}
+
+impl ServerFrameCtx<'_> {
+    /// End the match when the time runs out or go into overtime if it's tied.
+    pub fn sys_match_phase(&mut self) {
+        match self.gs.phase {
+            Phase::Playing => {
+                if self.gs.game_time < self.gs.time_limit {
+                    return;
+                }
+                let leader = leader(self.cvars, self.gs);
+                if leader.is_some() || self.cvars.g_overtime_mode == OvertimeMode::Off {
+                    self.end_round(leader);
+                } else {
+                    self.set_phase(Phase::Overtime {
+                        start_time: self.gs.game_time,
+                        mode: self.cvars.g_overtime_mode,
+                    });
+                }
+            }
+            Phase::Overtime {
+                mode: OvertimeMode::Escalation,
+                start_time,
+            } => {
+                // Whatever broke the tie this tick decides it.
+                if let Some(winner) = leader(self.cvars, self.gs) {
+                    self.end_round(Some(winner));
+                    return;
+                }
+                self.gs.damage_multiplier =
+                    escalation_multiplier(self.cvars, start_time, self.gs.game_time);
+            }
+            // Sudden death is decided by `overtime_kill`.
+            Phase::Overtime { .. } | Phase::RoundEnd { .. } => {}
+        }
+    }
+
+    /// In sudden death, the first kill of an enemy wins the match.
+    ///
+    /// Suicides and team kills don't decide anything.
+    pub fn overtime_kill(&mut self, attacker_handle: Option<Index>, victim_handle: Index) {
+        let Phase::Overtime {
+            mode: OvertimeMode::SuddenDeath,
+            ..
+        } = self.gs.phase
+        else {
+            return;
+        };
+        let Some(attacker_handle) = attacker_handle else {
+            return;
+        };
+        let Some(attacker) = self.gs.players.soft_get(attacker_handle) else {
+            return;
+        };
+        if attacker_handle == victim_handle {
+            return;
+        }
+
+        let winner = if team_mode(self.gs) {
+            let victim_team = self
+                .gs
+                .players
+                .soft_get(victim_handle)
+                .and_then(|victim| victim.team);
+            let Some(team) = attacker.team.filter(|&team| Some(team) != victim_team) else {
+                return;
+            };
+            Winner::Team(team)
+        } else {
+            let Some(id) = self.gs.player_ids.id(attacker_handle) else {
+                return;
+            };
+            Winner::Player(id)
+        };
+        self.end_round(Some(winner));
+    }
+
+    fn end_round(&mut self, winner: Option<Winner>) {
+        self.gs.damage_multiplier = 1.0;
+        self.sg.match_stats.end(self.gs.game_time);
+        self.set_phase(Phase::RoundEnd {
+            time: self.gs.game_time,
+            winner,
+        });
+    }
+
+    fn set_phase(&mut self, phase: Phase) {
+        dbg_logf!("Match phase: {phase:?}");
+        self.gs.phase = phase;
+        self.net_send_all(ServerMessage::Phase(phase));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn tied_server(cvars: &Cvars, mode: GameMode) -> (Server, Vec<Index>) {
+        let mut server = test_utils::atrium_server(cvars);
+        server.gs.game_mode = mode;
+
+        let mut ctx = server.ctx(cvars);
+        let mut players = Vec::new();
+        for (i, name) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            let mut player = Player::new(name.to_owned(), ClientType::Local);
+            if team_mode(ctx.gs) {
+                player.team = Some(i as u32 % 2);
+            }
+            let player_handle = ctx.gs.players.insert(player);
+            ctx.gs.player_ids.assign(player_handle);
+            ctx.spawn_vehicle(player_handle, true);
+            players.push(player_handle);
+        }
+        (server, players)
+    }
+
+    fn kill(ctx: &mut ServerFrameCtx, attacker: Index, victim: Index) {
+        let vehicle_handle = ctx.gs.players[victim].vehicle.unwrap();
+        ctx.damage(attacker, vehicle_handle, f64::MAX, Some(Weapon::Rail), None);
+    }
+
+    fn cvars(mode: OvertimeMode) -> Cvars {
+        Cvars {
+            g_overtime_escalation_interval: 10.0,
+            g_overtime_escalation_max: 2.0,
+            g_overtime_escalation_step: 0.5,
+            g_overtime_mode: mode,
+            g_time_limit: 60.0,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_leader() {
+        let cvars = cvars(OvertimeMode::Off);
+        let ffa = GameMode::Ffa(Ffa { kill_limit: 20 });
+        let (mut server, players) = tied_server(&cvars, ffa);
+        let mut ctx = server.ctx(&cvars);
+        assert_eq!(leader(&cvars, ctx.gs), None);
+        kill(&mut ctx, players[0], players[1]);
+        let id = ctx.gs.player_ids.id(players[0]).unwrap();
+        assert_eq!(leader(&cvars, ctx.gs), Some(Winner::Player(id)));
+
+        let tw = GameMode::Tw(Tw { kill_limit: 20 });
+        let (mut server, players) = tied_server(&cvars, tw);
+        let mut ctx = server.ctx(&cvars);
+        // Team 0 is a and c, team 1 b and d.
+        kill(&mut ctx, players[0], players[1]);
+        kill(&mut ctx, players[3], players[2]);
+        assert_eq!(leader(&cvars, ctx.gs), None);
+        kill(&mut ctx, players[2], players[3]);
+        assert_eq!(leader(&cvars, ctx.gs), Some(Winner::Team(0)));
+    }
+
+    #[test]
+    fn test_time_limit() {
+        // Not tied - no overtime.
+        let cvars = cvars(OvertimeMode::SuddenDeath);
+        let (mut server, players) = tied_server(&cvars, GameMode::Ffa(Ffa { kill_limit: 20 }));
+        let mut ctx = server.ctx(&cvars);
+        kill(&mut ctx, players[1], players[2]);
+        ctx.gs.game_time = 59.0;
+        ctx.sys_match_phase();
+        assert_eq!(ctx.gs.phase, Phase::Playing);
+        ctx.gs.game_time = 60.0;
+        ctx.sys_match_phase();
+        let id = ctx.gs.player_ids.id(players[1]).unwrap();
+        assert_eq!(
+            ctx.gs.phase,
+            Phase::RoundEnd {
+                time: 60.0,
+                winner: Some(Winner::Player(id)),
+            }
+        );
+        assert_eq!(ctx.sg.match_stats.end_time, Some(60.0));
+        assert_eq!(phase_banner(&cvars, ctx.gs).unwrap(), "b wins");
+
+        // Tied without overtime
+        let cvars = Cvars {
+            g_overtime_mode: OvertimeMode::Off,
+            ..cvars
+        };
+        let (mut server, _) = tied_server(&cvars, GameMode::Ffa(Ffa { kill_limit: 20 }));
+        let mut ctx = server.ctx(&cvars);
+        ctx.gs.game_time = 60.0;
+        ctx.sys_match_phase();
+        let winner = None;
+        assert_eq!(ctx.gs.phase, Phase::RoundEnd { time: 60.0, winner });
+        assert_eq!(phase_banner(&cvars, ctx.gs).unwrap(), "DRAW");
+    }
+
+    #[test]
+    fn test_sudden_death() {
+        let cvars = cvars(OvertimeMode::SuddenDeath);
+        let (mut server, players) = tied_server(&cvars, GameMode::Ffa(Ffa { kill_limit: 20 }));
+        let mut ctx = server.ctx(&cvars);
+        kill(&mut ctx, players[0], players[1]);
+        kill(&mut ctx, players[1], players[0]);
+
+        ctx.gs.game_time = 60.0;
+        ctx.sys_match_phase();
+        let overtime = Phase::Overtime {
+            start_time: 60.0,
+            mode: OvertimeMode::SuddenDeath,
+        };
+        assert_eq!(ctx.gs.phase, overtime);
+        assert_eq!(
+            phase_banner(&cvars, ctx.gs).unwrap(),
+            "OVERTIME - next kill wins"
+        );
+
+        // Suicides don't count, damage isn't multiplied.
+        ctx.gs.game_time = 200.0;
+        kill(&mut ctx, players[2], players[2]);
+        ctx.sys_match_phase();
+        assert_eq!(ctx.gs.phase, overtime);
+        assert_eq!(ctx.gs.damage_multiplier, 1.0);
+
+        // Whoever gets the kill wins, even from behind.
+        kill(&mut ctx, players[2], players[3]);
+        let id = ctx.gs.player_ids.id(players[2]).unwrap();
+        assert_eq!(
+            ctx.gs.phase,
+            Phase::RoundEnd {
+                time: 200.0,
+                winner: Some(Winner::Player(id)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_sudden_death_teams() {
+        let cvars = cvars(OvertimeMode::SuddenDeath);
+        let (mut server, players) = tied_server(&cvars, GameMode::Tw(Tw { kill_limit: 20 }));
+        let mut ctx = server.ctx(&cvars);
+        ctx.gs.game_time = 60.0;
+        ctx.sys_match_phase();
+        assert!(matches!(ctx.gs.phase, Phase::Overtime { .. }));
+
+        // Team kill - b and d are both on team 1.
+        kill(&mut ctx, players[1], players[3]);
+        assert!(matches!(ctx.gs.phase, Phase::Overtime { .. }));
+
+        kill(&mut ctx, players[3], players[0]);
+        let winner = Some(Winner::Team(1));
+        assert_eq!(ctx.gs.phase, Phase::RoundEnd { time: 60.0, winner });
+        assert_eq!(phase_banner(&cvars, ctx.gs).unwrap(), "Blue team wins");
+    }
+
+    #[test]
+    fn test_escalation_multiplier() {
+        let cvars = cvars(OvertimeMode::Escalation);
+        let values: Vec<_> = [60.0, 69.9, 70.0, 79.9, 80.0, 500.0]
+            .iter()
+            .map(|&time| escalation_multiplier(&cvars, 60.0, time))
+            .collect();
+        assert_eq!(values, [1.0, 1.0, 1.5, 1.5, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_escalation() {
+        let cvars = cvars(OvertimeMode::Escalation);
+        let (mut server, players) = tied_server(&cvars, GameMode::Tw(Tw { kill_limit: 20 }));
+        let mut ctx = server.ctx(&cvars);
+        ctx.gs.game_time = 60.0;
+        ctx.sys_match_phase();
+        assert!(matches!(
+            ctx.gs.phase,
+            Phase::Overtime {
+                mode: OvertimeMode::Escalation,
+                ..
+            }
+        ));
+
+        // Damage ramps up over time.
+        let vehicle_handle = ctx.gs.players[players[1]].vehicle.unwrap();
+        let hp = cvars.g_vehicle_hp(ctx.gs.vehicles[vehicle_handle].veh_type);
+        ctx.gs.game_time = 75.0;
+        ctx.sys_match_phase();
+        assert_eq!(ctx.gs.damage_multiplier, 1.5);
+        assert_eq!(
+            phase_banner(&cvars, ctx.gs).unwrap(),
+            "OVERTIME - damage x1.50"
+        );
+        ctx.damage(players[0], vehicle_handle, hp * 0.2, Some(Weapon::Mg), None);
+        let hp_fraction = ctx.gs.vehicles[vehicle_handle].hp_fraction;
+        assert!((hp_fraction - 0.7).abs() < 1e-9, "{hp_fraction}");
+
+        // Still tied - play on, the multiplier is capped.
+        ctx.gs.game_time = 1000.0;
+        ctx.sys_match_phase();
+        assert_eq!(ctx.gs.damage_multiplier, 2.0);
+
+        // The kill breaks the tie and ends it on the next tick.
+        kill(&mut ctx, players[0], players[1]);
+        ctx.sys_match_phase();
+        let winner = Some(Winner::Team(0));
+        assert_eq!(
+            ctx.gs.phase,
+            Phase::RoundEnd {
+                time: 1000.0,
+                winner
+            }
+        );
+        assert_eq!(ctx.gs.damage_multiplier, 1.0);
+    }
+}
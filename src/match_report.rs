@@ -0,0 +1,487 @@
+//! Per-player statistics over the course of a match for a results screen.
+//!
+//! The server accumulates them while the match is running,
+//! `Server::match_report` turns them into a serializable report at any point,
+//! including after `Server::end_match`.
+//!
+//! Time series use a fixed number of buckets so memory doesn't grow with match length.
+//! When a match outlasts the buckets, neighboring buckets are merged and their width doubles.
+//! Deaths and players who left are capped for the same reason, the oldest go first.
+//!
+//! LATER Accuracy per weapon once shots and hits are tracked.
+//! LATER Send the report to clients and draw it on a results screen.
+
+use crate::prelude::*;
+
+/// Number of buckets in each timeline.
+pub const BUCKETS: usize = 16;
+
+/// Initial width of each bucket in seconds.
+pub const BUCKET_WIDTH: f64 = 60.0;
+
+/// Most deaths kept for the report.
+pub const MAX_DEATHS: usize = 1000;
+
+/// Most players who already left kept for the report.
+pub const MAX_LEFT_PLAYERS: usize = 16;
+
+/// How values recorded into the same bucket combine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Aggregate {
+    /// Total, e.g. damage dealt.
+    Sum,
+    /// Most recent value, e.g. score which is sampled every frame.
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Timeline {
+    pub aggregate: Aggregate,
+    /// Seconds covered by each bucket.
+    pub bucket_width: f64,
+    pub values: Vec<f64>,
+}
+
+impl Timeline {
+    pub fn new(aggregate: Aggregate) -> Self {
+        Self {
+            aggregate,
+            bucket_width: BUCKET_WIDTH,
+            values: vec![0.0; BUCKETS],
+        }
+    }
+
+    pub fn record(&mut self, time: f64, value: f64) {
+        // Negative times saturate to the first bucket.
+        let mut bucket = (time / self.bucket_width) as usize;
+        while bucket >= BUCKETS {
+            self.merge();
+            bucket = (time / self.bucket_width) as usize;
+        }
+        match self.aggregate {
+            Aggregate::Sum => self.values[bucket] += value,
+            Aggregate::Last => self.values[bucket] = value,
+        }
+    }
+
+    /// Halve the resolution to make room for later times.
+    fn merge(&mut self) {
+        for i in 0..BUCKETS / 2 {
+            let (a, b) = (self.values[2 * i], self.values[2 * i + 1]);
+            self.values[i] = match self.aggregate {
+                Aggregate::Sum => a + b,
+                Aggregate::Last => b,
+            };
+        }
+        for value in &mut self.values[BUCKETS / 2..] {
+            *value = 0.0;
+        }
+        self.bucket_width *= 2.0;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeathRecord {
+    pub time: f64,
+    /// Player's stable ID, see `stable_id`.
+    pub victim: u32,
+    /// None for the world, see `GameState::world_score`.
+    pub attacker: Option<u32>,
+    /// Stable IDs of players who got an assist, most damage first.
+    pub assists: Vec<u32>,
+    /// None for self-destruct.
+    pub weapon: Option<Weapon>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerStats {
+    /// Kept so the report can include players who already left.
+    pub name: String,
+    pub points: Timeline,
+    pub damage_dealt: Timeline,
+    /// Game time when the player left, None while they're still playing.
+    pub left_time: Option<f64>,
+    /// Latest difficulty of bots (see `Ai::difficulty`), None for humans.
+    pub difficulty: Option<f64>,
+}
+
+impl PlayerStats {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            points: Timeline::new(Aggregate::Last),
+            damage_dealt: Timeline::new(Aggregate::Sum),
+            left_time: None,
+            difficulty: None,
+        }
+    }
+}
+
+/// Server-side accumulator.
+#[derive(Debug, Clone, Default)]
+pub struct MatchStats {
+    /// By player's stable ID so players who left don't get mixed up
+    /// with new ones in the same slot.
+    pub players: FnvHashMap<u32, PlayerStats>,
+    /// In chronological order, at most `MAX_DEATHS`.
+    pub deaths: VecDeque<DeathRecord>,
+    /// Older deaths which didn't fit into `deaths`.
+    pub deaths_dropped: usize,
+    /// Set by `end`, nothing is recorded after that.
+    pub end_time: Option<f64>,
+}
+
+impl MatchStats {
+    pub fn ended(&self) -> bool {
+        self.end_time.is_some()
+    }
+
+    /// Stop recording, the report stays available.
+    pub fn end(&mut self, time: f64) {
+        if !self.ended() {
+            self.end_time = Some(time);
+        }
+    }
+
+    pub fn record_points(&mut self, player_id: u32, name: &str, time: f64, points: i32) {
+        if self.ended() {
+            return;
+        }
+        self.players
+            .entry(player_id)
+            .or_insert_with(|| PlayerStats::new(name.to_owned()))
+            .points
+            .record(time, points as f64);
+    }
+
+    /// Only recorded for players already seen by `record_points`.
+    pub fn record_difficulty(&mut self, player_id: u32, difficulty: f64) {
+        if self.ended() {
+            return;
+        }
+        if let Some(stats) = self.players.get_mut(&player_id) {
+            stats.difficulty = Some(difficulty);
+        }
+    }
+
+    /// Damage is only recorded for players already seen by `record_points`.
+    pub fn record_damage(&mut self, attacker_id: u32, time: f64, damage: f64) {
+        if self.ended() {
+            return;
+        }
+        if let Some(stats) = self.players.get_mut(&attacker_id) {
+            stats.damage_dealt.record(time, damage);
+        }
+    }
+
+    pub fn record_death(&mut self, death: DeathRecord) {
+        if self.ended() {
+            return;
+        }
+        self.deaths.push_back(death);
+        if self.deaths.len() > MAX_DEATHS {
+            self.deaths.pop_front();
+            self.deaths_dropped += 1;
+        }
+    }
+
+    /// Keep the player's stats for the report, forget whoever left first
+    /// if there are more than `MAX_LEFT_PLAYERS`.
+    pub fn record_left(&mut self, player_id: u32, time: f64) {
+        if self.ended() {
+            return;
+        }
+        let Some(stats) = self.players.get_mut(&player_id) else {
+            return;
+        };
+        if stats.left_time.is_some() {
+            return;
+        }
+        stats.left_time = Some(time);
+
+        let mut left: Vec<_> = self
+            .players
+            .iter()
+            .filter_map(|(&id, stats)| stats.left_time.map(|time| (time, id)))
+            .collect();
+        if left.len() > MAX_LEFT_PLAYERS {
+            left.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            for &(_, id) in &left[..left.len() - MAX_LEFT_PLAYERS] {
+                self.players.remove(&id);
+            }
+        }
+    }
+
+    pub fn report(&self, duration: f64) -> MatchReport {
+        let mut players: Vec<_> = self
+            .players
+            .iter()
+            .map(|(&id, stats)| PlayerReport {
+                id,
+                name: stats.name.clone(),
+                points: stats.points.clone(),
+                damage_dealt: stats.damage_dealt.clone(),
+                difficulty: stats.difficulty,
+            })
+            .collect();
+        players.sort_by_key(|player| player.id);
+
+        MatchReport {
+            duration,
+            players,
+            deaths: self.deaths.iter().cloned().collect(),
+            deaths_dropped: self.deaths_dropped,
+        }
+    }
+}
+
+/// Everything a results screen needs to draw charts.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchReport {
+    /// Seconds of game time.
+    pub duration: f64,
+    /// Sorted by player ID, which is the order they joined.
+    pub players: Vec<PlayerReport>,
+    /// The last `MAX_DEATHS`.
+    pub deaths: Vec<DeathRecord>,
+    /// How many earlier deaths are missing from `deaths`.
+    pub deaths_dropped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerReport {
+    /// Player's stable ID, see `stable_id`.
+    pub id: u32,
+    pub name: String,
+    pub points: Timeline,
+    pub damage_dealt: Timeline,
+    /// Bots only, see `PlayerStats::difficulty`.
+    pub difficulty: Option<f64>,
+}
+
+impl ServerFrameCtx<'_> {
+    /// Sample everyone's score and bots' difficulty for the report and notice who left.
+    pub fn sys_match_stats(&mut self) {
+        let left: Vec<_> = self
+            .sg
+            .match_stats
+            .players
+            .iter()
+            .filter(|(&id, stats)| {
+                stats.left_time.is_none() && self.gs.player_ids.handle(id).is_none()
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        for id in left {
+            self.sg.match_stats.record_left(id, self.gs.game_time);
+        }
+
+        for (player_handle, player) in self.gs.players.iter() {
+            let player_id = self.gs.player_ids.id(player_handle);
+            soft_assert!(player_id.is_some());
+            let Some(player_id) = player_id else {
+                continue;
+            };
+            let points = player.score.points(self.cvars);
+            self.sg
+                .match_stats
+                .record_points(player_id, &player.name, self.gs.game_time, points);
+            if let ClientType::Ai(ai_handle) = player.client {
+                if let Some(ai) = self.gs.ais.get(ai_handle) {
+                    self.sg
+                        .match_stats
+                        .record_difficulty(player_id, ai.difficulty);
+                }
+            }
+        }
+    }
+}
+
+impl Server {
+    /// Stop recording stats, the report stays available.
+    ///
+    /// The round ending (see `match_flow`) does this too.
+    pub fn end_match(&mut self) {
+        self.sg.match_stats.end(self.gs.game_time);
+    }
+
+    /// LATER Send to clients for a results screen.
+    pub fn match_report(&self) -> MatchReport {
+        let duration = self.sg.match_stats.end_time.unwrap_or(self.gs.game_time);
+        self.sg.match_stats.report(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    #[test]
+    fn test_timeline_sum() {
+        let mut timeline = Timeline::new(Aggregate::Sum);
+        timeline.record(0.0, 1.0);
+        timeline.record(59.9, 2.0);
+        timeline.record(60.0, 4.0);
+        timeline.record(-1.0, 8.0);
+        assert_eq!(timeline.values[0], 11.0);
+        assert_eq!(timeline.values[1], 4.0);
+        assert_eq!(timeline.values[2..], [0.0; BUCKETS - 2]);
+    }
+
+    #[test]
+    fn test_timeline_merge() {
+        let mut sum = Timeline::new(Aggregate::Sum);
+        let mut last = Timeline::new(Aggregate::Last);
+        for minute in 0..BUCKETS {
+            let time = minute as f64 * BUCKET_WIDTH;
+            sum.record(time, 1.0);
+            last.record(time, minute as f64);
+        }
+        assert_eq!(sum.bucket_width, BUCKET_WIDTH);
+
+        // One past the end
+        let time = BUCKETS as f64 * BUCKET_WIDTH;
+        sum.record(time, 1.0);
+        last.record(time, BUCKETS as f64);
+
+        assert_eq!(sum.bucket_width, 2.0 * BUCKET_WIDTH);
+        assert_eq!(sum.values.len(), BUCKETS);
+        assert_eq!(sum.values[..BUCKETS / 2], [2.0; BUCKETS / 2]);
+        assert_eq!(sum.values[BUCKETS / 2], 1.0);
+        assert_eq!(sum.values.iter().sum::<f64>(), BUCKETS as f64 + 1.0);
+
+        assert_eq!(last.values[0], 1.0);
+        assert_eq!(last.values[BUCKETS / 2 - 1], BUCKETS as f64 - 1.0);
+        assert_eq!(last.values[BUCKETS / 2], BUCKETS as f64);
+
+        // Way past the end merges repeatedly.
+        sum.record(100.0 * BUCKETS as f64 * BUCKET_WIDTH, 1.0);
+        assert_eq!(sum.values.len(), BUCKETS);
+        assert_eq!(sum.values.iter().sum::<f64>(), BUCKETS as f64 + 2.0);
+    }
+
+    #[test]
+    fn test_match_stats_events() {
+        let (a, b, gone) = (0, 1, 2);
+
+        let mut stats = MatchStats::default();
+        stats.record_points(a, "a", 0.0, 0);
+        stats.record_points(b, "b", 0.0, 0);
+
+        stats.record_damage(a, 10.0, 50.0);
+        stats.record_damage(a, 20.0, 30.0);
+        stats.record_damage(a, 70.0, 5.0);
+        stats.record_damage(b, 130.0, 7.0);
+        stats.record_damage(gone, 10.0, 100.0);
+        stats.record_death(DeathRecord {
+            time: 20.0,
+            victim: b,
+            attacker: Some(a),
+            assists: Vec::new(),
+            weapon: Some(Weapon::Rail),
+        });
+        stats.record_points(a, "a", 20.0, 1);
+        stats.record_points(b, "b", 20.0, -1);
+        stats.record_death(DeathRecord {
+            time: 70.0,
+            victim: a,
+            attacker: None,
+            assists: Vec::new(),
+            weapon: None,
+        });
+        stats.record_points(a, "a", 70.0, 0);
+
+        let report = stats.report(130.0);
+        assert_eq!(report.duration, 130.0);
+        let ids: Vec<_> = report.players.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![a, b]);
+
+        let ra = &report.players[0];
+        assert_eq!(ra.name, "a");
+        assert_eq!(ra.damage_dealt.values[..3], [80.0, 5.0, 0.0]);
+        assert_eq!(ra.points.values[..2], [1.0, 0.0]);
+        let rb = &report.players[1];
+        assert_eq!(rb.damage_dealt.values[..3], [0.0, 0.0, 7.0]);
+        assert_eq!(rb.points.values[0], -1.0);
+
+        let times: Vec<_> = report.deaths.iter().map(|d| d.time).collect();
+        assert_eq!(times, vec![20.0, 70.0]);
+        assert_eq!(report.deaths[0].weapon, Some(Weapon::Rail));
+        assert_eq!(report.deaths[1].attacker, None);
+    }
+
+    #[test]
+    fn test_match_stats_bounded() {
+        let mut stats = MatchStats::default();
+        for i in 0..MAX_DEATHS + 5 {
+            stats.record_death(DeathRecord {
+                time: i as f64,
+                victim: 0,
+                attacker: None,
+                assists: Vec::new(),
+                weapon: None,
+            });
+        }
+        let report = stats.report(0.0);
+        assert_eq!(report.deaths.len(), MAX_DEATHS);
+        assert_eq!(report.deaths[0].time, 5.0);
+        assert_eq!(report.deaths_dropped, 5);
+
+        let players = MAX_LEFT_PLAYERS as u32 + 2;
+        for id in 0..players {
+            stats.record_points(id, "p", 0.0, 0);
+        }
+        // Everyone leaves except the last one, in order.
+        for id in 0..players - 1 {
+            stats.record_left(id, id as f64);
+        }
+        let report = stats.report(0.0);
+        let ids: Vec<_> = report.players.iter().map(|p| p.id).collect();
+        assert_eq!(ids, (1..players).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_end_match() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::atrium_server(&cvars);
+
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = test_utils::add_player(&mut ctx, "test");
+        let player_id = ctx.gs.player_ids.id(player_handle).unwrap();
+        ctx.spawn_vehicle(player_handle, true);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 0.7));
+        let bot = Player::new("bot".to_owned(), ClientType::Ai(ai_handle));
+        let bot_handle = ctx.gs.players.insert(bot);
+        ctx.gs.ais[ai_handle].player = bot_handle;
+        let bot_id = ctx.gs.player_ids.assign(bot_handle);
+        ctx.sys_match_stats();
+
+        // Suicide
+        ctx.gs.game_time = 5.0;
+        ctx.damage(player_handle, vehicle_handle, f64::MAX, None, None);
+        assert_eq!(ctx.sg.match_stats.deaths.len(), 1);
+
+        server.end_match();
+        let report = server.match_report();
+        assert_eq!(report.duration, 5.0);
+        assert_eq!(report.players.len(), 2);
+        assert_eq!(report.players[0].id, player_id);
+        // Bots' difficulty is in the report, humans have none.
+        assert_eq!(report.players[0].difficulty, None);
+        assert_eq!(report.players[1].id, bot_id);
+        assert_eq!(report.players[1].difficulty, Some(0.7));
+        assert_eq!(report.deaths[0].victim, player_id);
+        assert_eq!(report.deaths[0].attacker, Some(player_id));
+
+        // Nothing changes after the end.
+        server.gs.game_time = 100.0;
+        server.ctx(&cvars).sys_match_stats();
+        server.end_match();
+        let report_after = server.match_report();
+        assert_eq!(report_after.duration, 5.0);
+        assert_eq!(report_after.players[0].points, report.players[0].points);
+    }
+}
@@ -0,0 +1,223 @@
+//! Counters and gauges for monitoring long-running instances (e.g. an unattended kiosk).
+//!
+//! Rendered in the Prometheus text exposition format.
+//! Set `cl_metrics_path` to write them to a file every `cl_metrics_interval` seconds,
+//! node_exporter's textfile collector (or any tiny HTTP handler) can serve it to a scraper.
+//!
+//! LATER Memory usage - needs platform specific code or a crate.
+
+use std::fmt::Write;
+
+use crate::prelude::*;
+
+/// Monotonic counter which saturates instead of overflowing or wrapping around to 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counter(u64);
+
+impl Counter {
+    pub fn inc(&mut self) {
+        self.add(1);
+    }
+
+    pub fn add(&mut self, n: u64) {
+        self.0 = self.0.saturating_add(n);
+    }
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    pub frames_rendered: Counter,
+    pub gamelogic_ticks: Counter,
+    pub projectiles_spawned: Counter,
+    pub projectiles_removed: Counter,
+    pub explosions_created: Counter,
+    /// Times `EffectsDegrade` lowered the effects quality.
+    pub effects_degraded: Counter,
+    /// Worst `Client::update` duration in seconds since the last `text` call.
+    pub update_max: f64,
+    /// Worst `Client::render` duration in seconds since the last `text` call.
+    pub draw_max: f64,
+    /// Whether `PerfWarning` is currently shown, the page can e.g. suggest lowering effects quality.
+    pub perf_degraded: bool,
+    /// Real time when the metrics were last written to `cl_metrics_path`.
+    pub written_time: f64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_update(&mut self, duration: f64) {
+        self.update_max = self.update_max.max(duration);
+    }
+
+    pub fn record_draw(&mut self, duration: f64) {
+        self.draw_max = self.draw_max.max(duration);
+    }
+
+    /// All metrics in the Prometheus text exposition format.
+    ///
+    /// Resets the worst durations so each call covers the interval since the previous one.
+    pub fn text(&mut self, gs: &GameState) -> String {
+        let mut out = String::new();
+
+        let counters = [
+            ("frames_rendered", "Frames rendered.", self.frames_rendered),
+            ("gamelogic_ticks", "Gamelogic ticks.", self.gamelogic_ticks),
+            (
+                "projectiles_spawned",
+                "Projectiles spawned.",
+                self.projectiles_spawned,
+            ),
+            (
+                "projectiles_removed",
+                "Projectiles removed.",
+                self.projectiles_removed,
+            ),
+            (
+                "explosions_created",
+                "Explosions created.",
+                self.explosions_created,
+            ),
+            (
+                "effects_degraded",
+                "Automatic effects quality reductions.",
+                self.effects_degraded,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            let name = format!("recwars_{name}_total");
+            write_header(&mut out, &name, help, "counter");
+            writeln!(out, "{name} {}", counter.get()).unwrap();
+        }
+
+        write_header(
+            &mut out,
+            "recwars_entities",
+            "Entities currently alive.",
+            "gauge",
+        );
+        let entities = [
+            ("player", gs.players.len()),
+            ("vehicle", gs.vehicles.len()),
+            ("projectile", gs.projectiles.len()),
+            ("smoke", gs.smokes.len()),
+        ];
+        for (kind, count) in entities {
+            writeln!(out, "recwars_entities{{type=\"{kind}\"}} {count}").unwrap();
+        }
+
+        write_header(
+            &mut out,
+            "recwars_perf_degraded",
+            "1 while the performance warning is shown.",
+            "gauge",
+        );
+        writeln!(
+            out,
+            "recwars_perf_degraded {}",
+            u8::from(self.perf_degraded)
+        )
+        .unwrap();
+
+        let durations = [
+            (
+                "update_max_ms",
+                "Worst update duration since the last scrape.",
+                self.update_max,
+            ),
+            (
+                "draw_max_ms",
+                "Worst draw duration since the last scrape.",
+                self.draw_max,
+            ),
+        ];
+        for (name, help, seconds) in durations {
+            let name = format!("recwars_{name}");
+            write_header(&mut out, &name, help, "gauge");
+            writeln!(out, "{name} {}", seconds * 1000.0).unwrap();
+        }
+        self.update_max = 0.0;
+        self.draw_max = 0.0;
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_saturates() {
+        let mut counter = Counter(u64::MAX - 1);
+        counter.inc();
+        assert_eq!(counter.get(), u64::MAX);
+        counter.inc();
+        counter.add(100);
+        assert_eq!(counter.get(), u64::MAX);
+    }
+
+    #[test]
+    fn test_metrics_text() {
+        let mut metrics = Metrics::new();
+        let gs = GameState::new();
+        for i in 0..10_000 {
+            metrics.frames_rendered.inc();
+            metrics.gamelogic_ticks.add(2);
+            if i % 10 == 0 {
+                metrics.projectiles_spawned.inc();
+            }
+            metrics.record_update(i as f64 / 1_000_000.0);
+        }
+
+        let text = metrics.text(&gs);
+        let mut values = FnvHashMap::default();
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut words = comment.split(' ');
+                let keyword = words.next().unwrap();
+                assert!(keyword == "HELP" || keyword == "TYPE", "{line}");
+                assert!(words.next().unwrap().starts_with("recwars_"), "{line}");
+                if keyword == "TYPE" {
+                    let kind = words.next().unwrap();
+                    assert!(kind == "counter" || kind == "gauge", "{line}");
+                }
+                continue;
+            }
+            let (name, value) = line.rsplit_once(' ').unwrap();
+            assert!(name.starts_with("recwars_"), "{line}");
+            let value: f64 = value.parse().unwrap();
+            values.insert(name.to_owned(), value);
+        }
+
+        assert_eq!(values["recwars_frames_rendered_total"], 10_000.0);
+        assert_eq!(values["recwars_gamelogic_ticks_total"], 20_000.0);
+        assert_eq!(values["recwars_projectiles_spawned_total"], 1_000.0);
+        assert_eq!(values["recwars_explosions_created_total"], 0.0);
+        assert_eq!(values["recwars_effects_degraded_total"], 0.0);
+        assert_eq!(values["recwars_entities{type=\"vehicle\"}"], 0.0);
+        assert!((values["recwars_update_max_ms"] - 9.999).abs() < 1e-9);
+        assert_eq!(values["recwars_perf_degraded"], 0.0);
+
+        // Counters keep going, the worst durations start over.
+        metrics.frames_rendered.inc();
+        let text = metrics.text(&gs);
+        assert!(text.contains("\nrecwars_frames_rendered_total 10001\n"));
+        assert!(text.contains("\nrecwars_update_max_ms 0\n"));
+
+        metrics.perf_degraded = true;
+        let text = metrics.text(&gs);
+        assert!(text.contains("\nrecwars_perf_degraded 1\n"));
+    }
+}
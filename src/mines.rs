@@ -0,0 +1,297 @@
+//! Mines dropped behind vehicles with the mine key.
+//!
+//! A mine arms `g_mine_arm_time` after it's dropped, then any vehicle
+//! within `g_mine_trigger_radius` sets it off. The owner can drive over their own mines
+//! only for `g_mine_owner_immunity`, forgotten mines are as dangerous to them as to anyone.
+//! Explosions of other weapons set off mines in their radius too,
+//! mine explosions don't, so one mine can't clear a whole minefield.
+//!
+//! Mines are not a `Weapon` - they have their own key and shouldn't be in the weapon cycle.
+
+use crate::prelude::*;
+
+impl ServerFrameCtx<'_> {
+    /// Drop a mine behind the vehicle when the mine key is pressed.
+    pub fn sys_mine_laying(&mut self) {
+        for player_handle in self.gs.players.collect_handles() {
+            let player = &self.gs.players[player_handle];
+            if player.input_prev.mine || !player.input.mine {
+                continue;
+            }
+            let Some(vehicle_handle) = player.vehicle else {
+                continue;
+            };
+            let vehicle = &self.gs.vehicles[vehicle_handle];
+            if vehicle.destroyed() || vehicle.materializing(self.cvars, self.gs.game_time) {
+                continue;
+            }
+            self.drop_mine(player_handle, vehicle_handle);
+        }
+    }
+
+    /// Does nothing during `g_mine_cooldown`.
+    /// Removes the player's oldest mines if they'd have more than `g_mine_max`.
+    ///
+    /// Public because alt-fire can drop mines too, see `AltFire::DropMine`.
+    pub fn drop_mine(&mut self, player_handle: Index, vehicle_handle: Index) {
+        if self.cvars.g_mine_max == 0 {
+            return;
+        }
+        let mine_time = self.gs.players[player_handle].mine_time;
+        if self.gs.game_time < mine_time + self.cvars.g_mine_cooldown {
+            return;
+        }
+        let mut owned: Vec<_> = self
+            .gs
+            .mines
+            .iter()
+            .filter(|(_, mine)| mine.owner == player_handle)
+            .map(|(handle, mine)| (handle, mine.spawn_time))
+            .collect();
+        owned.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        let excess = (owned.len() + 1).saturating_sub(self.cvars.g_mine_max);
+        for &(mine_handle, _) in &owned[..excess] {
+            self.gs.mines.remove(mine_handle);
+            let msg = ServerMessage::DestroyMine {
+                index: mine_handle.slot(),
+            };
+            self.net_send_all(msg);
+        }
+
+        let player = &mut self.gs.players[player_handle];
+        player.mine_time = self.gs.game_time;
+        let team = player.team;
+
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        let pos = vehicle.pos - vehicle.angle.to_vec2f() * self.cvars.g_mine_offset;
+        let mine = Mine::new(pos, self.gs.game_time, player_handle, team);
+        let mine_handle = self.gs.mines.insert(mine);
+
+        let init = MineInit {
+            index: mine_handle.slot(),
+            pos,
+            spawn_time: self.gs.game_time,
+            owner: player_handle.slot(),
+            team,
+        };
+        self.net_send_all(ServerMessage::SpawnMine(init));
+    }
+
+    /// Set off armed mines with a vehicle in range.
+    pub fn sys_mine_triggers(&mut self) {
+        for mine_handle in self.gs.mines.collect_handles() {
+            let mine = &self.gs.mines[mine_handle];
+            if !mine.armed(self.cvars, self.gs.game_time) {
+                continue;
+            }
+            let owner_immune = mine.owner_immune(self.cvars, self.gs.game_time);
+            let triggered = self.gs.vehicles.iter().any(|(_, vehicle)| {
+                let dist = vehicle.pos.distance(mine.pos) - self.cvars.g_hitcircle_radius;
+                let immune = owner_immune && vehicle.owner == mine.owner;
+                !vehicle.destroyed() && !immune && dist <= self.cvars.g_mine_trigger_radius
+            });
+            if triggered {
+                self.detonate_mine(mine_handle);
+            }
+        }
+    }
+
+    /// Set off all mines within `radius` of another weapon's explosion, armed or not.
+    pub fn detonate_mines_near(&mut self, pos: Vec2f, radius: f64) {
+        let mine_handles: Vec<_> = self
+            .gs
+            .mines
+            .iter()
+            .filter(|(_, mine)| {
+                mine.pos.distance(pos) < radius && !self.explosion_blocked(pos, mine.pos)
+            })
+            .map(|(handle, _)| handle)
+            .collect();
+        for mine_handle in mine_handles {
+            self.detonate_mine(mine_handle);
+        }
+    }
+
+    fn detonate_mine(&mut self, mine_handle: Index) {
+        let mine = self.gs.mines.remove(mine_handle).unwrap();
+        let msg = ServerMessage::DestroyMine {
+            index: mine_handle.slot(),
+        };
+        self.net_send_all(msg);
+
+        self.spawn_explosion(
+            mine.pos,
+            self.cvars.g_mine_explosion_scale,
+            false,
+            mine.team,
+        );
+        self.explosion_damage(
+            mine.owner,
+            None,
+            mine.pos,
+            self.cvars.g_mine_explosion_damage,
+            self.cvars.g_mine_explosion_damage * self.cvars.g_explosion_damage_edge,
+            self.cvars.g_mine_explosion_radius,
+            None,
+            true,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn cvars() -> Cvars {
+        Cvars {
+            g_mine_arm_time: 1.0,
+            g_mine_cooldown: 0.5,
+            g_mine_max: 2,
+            g_mine_offset: 25.0,
+            g_mine_owner_immunity: 5.0,
+            g_spawn_effect_duration: 0.0,
+            ..Cvars::default()
+        }
+    }
+
+    /// A player driving right at (100, 128) and another parked far away.
+    fn mine_server(cvars: &Cvars) -> (Server, Index, Index) {
+        let mut server = test_utils::server(cvars, test_utils::open_map(8, 4, ""));
+        let mut ctx = server.ctx(cvars);
+        let mut players = Vec::new();
+        for (name, pos) in [("layer", v!(100 128)), ("other", v!(450 128))] {
+            let player_handle = test_utils::add_player(&mut ctx, name);
+            test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, pos, 0.0);
+            players.push(player_handle);
+        }
+        (server, players[0], players[1])
+    }
+
+    fn press_mine(ctx: &mut ServerFrameCtx, player_handle: Index, time: f64) {
+        ctx.gs.game_time = time;
+        let player = &mut ctx.gs.players[player_handle];
+        player.input_prev.mine = false;
+        player.input.mine = true;
+        ctx.sys_mine_laying();
+    }
+
+    fn move_vehicle(ctx: &mut ServerFrameCtx, player_handle: Index, pos: Vec2f) {
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        ctx.gs.vehicles[vehicle_handle].pos = pos;
+    }
+
+    fn hp(ctx: &ServerFrameCtx, player_handle: Index) -> f64 {
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        ctx.gs.vehicles[vehicle_handle].hp_fraction
+    }
+
+    #[test]
+    fn test_mine_laying() {
+        let cvars = cvars();
+        let (mut server, layer, _) = mine_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+
+        press_mine(&mut ctx, layer, 1.0);
+        let mines: Vec<_> = ctx.gs.mines.iter().map(|(_, mine)| mine.clone()).collect();
+        assert_eq!(mines.len(), 1);
+        assert_eq!(mines[0].pos, v!(75 128));
+        assert_eq!(mines[0].owner, layer);
+
+        // Holding the key doesn't drop more, neither does pressing it during the cooldown.
+        ctx.gs.players[layer].input_prev.mine = true;
+        ctx.sys_mine_laying();
+        press_mine(&mut ctx, layer, 1.25);
+        assert_eq!(ctx.gs.mines.len(), 1);
+
+        // Over the cap, the oldest one goes.
+        move_vehicle(&mut ctx, layer, v!(200 128));
+        press_mine(&mut ctx, layer, 2.0);
+        move_vehicle(&mut ctx, layer, v!(300 128));
+        press_mine(&mut ctx, layer, 3.0);
+        let mut xs: Vec<_> = ctx.gs.mines.iter().map(|(_, mine)| mine.pos.x).collect();
+        xs.sort_by(f64::total_cmp);
+        assert_eq!(xs, [175.0, 275.0]);
+    }
+
+    #[test]
+    fn test_mine_trigger() {
+        let cvars = cvars();
+        let (mut server, layer, other) = mine_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        press_mine(&mut ctx, layer, 0.0);
+
+        // Not armed yet.
+        move_vehicle(&mut ctx, other, v!(80 128));
+        ctx.gs.game_time = 0.5;
+        ctx.sys_mine_triggers();
+        assert_eq!(ctx.gs.mines.len(), 1);
+        assert_eq!(hp(&ctx, other), 1.0);
+
+        // The owner is immune for now, the other player is not.
+        ctx.gs.game_time = 1.0;
+        move_vehicle(&mut ctx, other, v!(450 128));
+        ctx.sys_mine_triggers();
+        assert_eq!(ctx.gs.mines.len(), 1);
+
+        move_vehicle(&mut ctx, other, v!(80 128));
+        ctx.sys_mine_triggers();
+        assert!(ctx.gs.mines.is_empty());
+        assert!(hp(&ctx, other) < 1.0);
+    }
+
+    #[test]
+    fn test_mine_owner_immunity_expires() {
+        let cvars = cvars();
+        let (mut server, layer, _) = mine_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        press_mine(&mut ctx, layer, 0.0);
+
+        ctx.gs.game_time = 4.9;
+        ctx.sys_mine_triggers();
+        assert_eq!(ctx.gs.mines.len(), 1);
+
+        ctx.gs.game_time = 5.0;
+        ctx.sys_mine_triggers();
+        assert!(ctx.gs.mines.is_empty());
+        assert!(hp(&ctx, layer) < 1.0);
+    }
+
+    #[test]
+    fn test_mines_destroyed_by_explosions() {
+        let cvars = cvars();
+        let (mut server, layer, _) = mine_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        // 2 mines next to each other, not armed yet.
+        press_mine(&mut ctx, layer, 0.0);
+        move_vehicle(&mut ctx, layer, v!(120 128));
+        press_mine(&mut ctx, layer, 0.5);
+        move_vehicle(&mut ctx, layer, v!(450 200));
+
+        // A mine's explosion doesn't set off its neighbor.
+        let first = ctx.gs.mines.iter().find(|(_, mine)| mine.pos.x == 75.0);
+        let first = first.unwrap().0;
+        ctx.detonate_mine(first);
+        assert_eq!(ctx.gs.mines.len(), 1);
+
+        // Other weapons' explosions do.
+        ctx.detonate_mines_near(v!(80 140), 30.0);
+        assert!(ctx.gs.mines.is_empty());
+    }
+
+    #[test]
+    fn test_orphan_mines() {
+        for (orphans, left) in [(OrphanProjectiles::Remove, 0), (OrphanProjectiles::Keep, 1)] {
+            let cvars = Cvars {
+                g_orphan_projectiles: orphans,
+                ..cvars()
+            };
+            let (mut server, layer, _) = mine_server(&cvars);
+            let mut ctx = server.ctx(&cvars);
+            press_mine(&mut ctx, layer, 0.0);
+            ctx.remove_player(layer);
+            assert_eq!(ctx.gs.mines.len(), left);
+        }
+    }
+}
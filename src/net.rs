@@ -59,6 +59,18 @@ where
     }
 }
 
+/// Never accepts any connections, for running the simulation without networking.
+pub struct NullListener;
+
+impl<M> Listener<M> for NullListener
+where
+    M: DeserializeOwned,
+{
+    fn accept_conn(&mut self) -> io::Result<Box<dyn Connection<M>>> {
+        Err(io::Error::new(ErrorKind::WouldBlock, "no connections"))
+    }
+}
+
 // Note we use the TcpListener from std here, not a custom type,
 // no point adding an extra type.
 impl<M> Listener<M> for TcpListener
@@ -8,6 +8,7 @@
 //! LATER These will form the basis of demo recording and replay.
 
 use crate::{
+    bot_identity::Rgb,
     debug::details::{DebugShape, WorldText},
     prelude::*,
 };
@@ -21,6 +22,20 @@ pub enum ClientMessage {
     Pause,
     Join,
     Observe,
+    /// Control another player (by index) instead of your own, None to go back, for debugging AI.
+    Possess(Option<u32>),
+    /// Rewind or resume the simulation for debugging, see `history`.
+    History(HistoryCommand),
+    /// Switch all balance cvars to the named preset, see `presets`.
+    Preset(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum HistoryCommand {
+    /// Go back `d_history_rewind_seconds` from the current position and pause.
+    Rewind,
+    /// Continue from the current position, the rewound future is lost.
+    Resume,
 }
 
 /// Description of the client or server version to determine compatibility.
@@ -98,10 +113,16 @@ pub enum ServerMessage {
     /// Pause state changed.
     Paused(bool),
 
+    /// Whose player the client's input now controls, None for its own.
+    Possessed(Option<u32>),
+
     AddPlayer(PlayerInit),
     SpawnVehicle(VehicleInit),
     SpawnProjectile(ProjectileInit),
     SpawnExplosion(ExplosionInit),
+    SpawnSmoke(SmokeInit),
+    SpawnFlares(FlaresInit),
+    SpawnMine(MineInit),
 
     RailBeam(RailBeam),
 
@@ -112,9 +133,44 @@ pub enum ServerMessage {
     /// Remove the projectile and create the associated effects (explosions, sounds, ...).
     DestroyProjectile {
         index: u32,
+        /// Where it hit the ground or a wall, None when it hit a vehicle.
+        impact: Option<Vec2f>,
+    },
+
+    /// Remove the mine, its explosion (if any) is sent separately.
+    DestroyMine {
+        index: u32,
+    },
+
+    /// A homing missile locked onto a vehicle or lost it, for the lock warning.
+    ///
+    /// Not sent when the missile is destroyed, clients clean up on `DestroyProjectile`.
+    /// Clients connecting mid-flight get existing locks in `ProjectileInit::target`.
+    HmLock {
+        index: u32,
+        /// Vehicle index, None when the missile lost it or switched to a flare.
+        target: Option<u32>,
+    },
+
+    /// The vehicle drove or got pushed into lethal water and is going under.
+    Sink {
+        index: u32,
+        game_time: f64,
     },
 
     Kill(Kill),
+
+    /// The match went into overtime or ended, see `match_flow`.
+    Phase(Phase),
+
+    /// A bot's announcement with `d_ai_chat`.
+    AiChat(AiChat),
+
+    /// The position in the server's rewind history changed, see `history`.
+    History(HistoryStatus),
+
+    /// The server switched to this balance preset, clients apply it too, see `presets`.
+    Preset(String),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -130,18 +186,31 @@ pub struct Init {
     pub local_player2_index: Option<u32>,
     pub vehicles: Vec<VehicleInit>,
     pub projectiles: Vec<ProjectileInit>,
+    pub mines: Vec<MineInit>,
+    pub emplacements: Vec<EmplacementInit>,
+    pub wrecks: Vec<Wreck>,
+    pub zone: Option<Zone>,
+    pub phase: Phase,
+    /// See `GameState::world_score`.
+    pub world_score: Score,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PlayerInit {
     pub index: u32,
+    /// See `stable_id`.
+    pub id: u32,
     pub name: String,
     pub score: Score,
+    pub color: Option<Rgb>,
+    pub team: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct VehicleInit {
     pub index: u32,
+    /// See `stable_id`.
+    pub id: u32,
     pub physics: EntityPhysics,
     pub veh_type: VehicleType,
     pub turret_angle_current: f64,
@@ -153,17 +222,69 @@ pub struct VehicleInit {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProjectileInit {
     pub index: u32,
+    /// See `stable_id`.
+    pub id: u32,
     pub weapon: Weapon,
     pub physics: EntityPhysics,
     pub explode_time: f64,
     pub owner: u32,
+    pub flight: Option<Flight>,
+    /// Server game time of the tick that fired it, for launch effects.
+    pub spawn_time: f64,
+    pub team: Option<u32>,
+    /// Vehicle index the homing missile is locked onto, same as in `HmLock`.
+    pub target: Option<u32>,
 }
 
+/// Effects carry the server game time of the tick that created them
+/// so the client can age them correctly after catch-up ticks,
+/// see `ClientFrameCtx::effect_start_time`.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ExplosionInit {
     pub pos: Vec2f,
     pub scale: f64,
     pub bfg: bool,
+    pub game_time: f64,
+    /// Team of the projectile which caused it, see `Projectile::team`.
+    pub team: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SmokeInit {
+    pub pos: Vec2f,
+    pub radius: f64,
+    pub owner: u32,
+    pub game_time: f64,
+}
+
+/// One deployment - all the flares start at the same place.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FlaresInit {
+    pub pos: Vec2f,
+    pub vels: Vec<Vec2f>,
+    pub owner: u32,
+    pub game_time: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MineInit {
+    pub index: u32,
+    pub pos: Vec2f,
+    /// Server game time, the client converts it when spawning.
+    pub spawn_time: f64,
+    pub owner: u32,
+    pub team: Option<u32>,
+}
+
+/// Emplacements are only created during match setup so they're only in `Init`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmplacementInit {
+    pub index: u32,
+    pub pos: Vec2f,
+    pub angle: f64,
+    pub weapon: Weapon,
+    pub turret_angle_current: f64,
+    pub hp_fraction: f64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -175,6 +296,7 @@ pub struct Update {
     pub player_inputs: Vec<InputUpdate>,
     pub vehicles: Vec<VehicleUpdate>,
     pub projectiles: Vec<ProjectileUpdate>,
+    pub emplacements: Vec<EmplacementUpdate>,
     pub debug_texts: Vec<String>,
     pub debug_texts_world: Vec<WorldText>,
     pub debug_shapes: Vec<DebugShape>,
@@ -185,6 +307,10 @@ pub struct Update {
 pub struct InputUpdate {
     pub index: u32,
     pub net_input: NetInput,
+    /// Changes in response to input so it's sent along with it.
+    pub cur_weapon: Weapon,
+    /// Projectile index, clients need it to reveal guiders, see `g_gm_reveal`.
+    pub guided_missile: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -193,6 +319,14 @@ pub struct VehicleUpdate {
     pub physics: EntityPhysics,
     pub turret_angle_current: f64,
     pub turret_angle_wanted: f64,
+    pub turret_turn_rate: f64,
+    pub wall_contacts: Vec<WallContact>,
+    pub scrape_speed: f64,
+    pub weapon_ready_time: f64,
+    pub fire_blocked_time: f64,
+    pub boost_until: f64,
+    pub boost_ready_time: f64,
+    pub hp_fraction: f64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -201,10 +335,36 @@ pub struct ProjectileUpdate {
     pub physics: EntityPhysics,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmplacementUpdate {
+    pub index: u32,
+    pub turret_angle_current: f64,
+    pub turret_angle_wanted: f64,
+    pub hp_fraction: f64,
+    /// Player index of whoever is manning it.
+    pub controller: Option<u32>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Kill {
-    pub attacker: u32,
+    /// None for the world, e.g. if the attacker has already left the game.
+    pub attacker: Option<u32>,
     pub victim: u32,
+    /// Players who helped, most damage first.
+    pub assists: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiChat {
+    pub player: u32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HistoryStatus {
+    /// Index of the restored snapshot, None while running live.
+    pub cursor: Option<u32>,
+    pub snapshots: u32,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
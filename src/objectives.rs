@@ -0,0 +1,132 @@
+//! Bases, spawns and named markers from the map, as used by the gamelogic and shown in the world.
+//!
+//! The capture radius lives only here - the capture check and the renderer
+//! both go through `base_radius` so what's drawn is exactly what counts.
+//!
+//! LATER The cow itself, `base_at` is what its capture should check.
+
+use crate::{
+    bot_identity::{Rgb, TEAM_COLORS},
+    prelude::*,
+};
+
+/// Color of spawns and bases which belong to no team.
+pub const NEUTRAL_RGB: Rgb = Rgb::new(0.8, 0.8, 0.8);
+
+/// A base tile and the area around it where the cow counts as captured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BaseArea {
+    pub index: Vec2u,
+    pub pos: Vec2f,
+    pub radius: f64,
+    /// From the `base_red` / `base_blue` markers, None if neither is on this base.
+    pub team: Option<u32>,
+}
+
+impl BaseArea {
+    pub fn contains(&self, pos: Vec2f) -> bool {
+        self.pos.distance_squared(pos) <= self.radius * self.radius
+    }
+}
+
+/// How far from the center of a base tile the cow counts as captured.
+pub fn base_radius(cvars: &Cvars) -> f64 {
+    cvars.g_ctc_base_radius
+}
+
+/// All bases on the map.
+pub fn base_areas(cvars: &Cvars, map: &Map) -> Vec<BaseArea> {
+    let radius = base_radius(cvars);
+    map.bases()
+        .iter()
+        .map(|&index| BaseArea {
+            index,
+            pos: map.tile_center(index),
+            radius,
+            team: base_team(map, index),
+        })
+        .collect()
+}
+
+/// The base whose area contains `pos`, if any.
+pub fn base_at(cvars: &Cvars, map: &Map, pos: Vec2f) -> Option<BaseArea> {
+    base_areas(cvars, map)
+        .into_iter()
+        .find(|base| base.contains(pos))
+}
+
+/// Team indices match `TEAM_COLORS` - red first.
+fn base_team(map: &Map, index: Vec2u) -> Option<u32> {
+    ["base_red", "base_blue"]
+        .iter()
+        .position(|&name| map.marker(name) == Some(index))
+        .map(|team| team as u32)
+}
+
+/// Bases only matter for gameplay in objective modes, otherwise they're just decoration.
+pub fn objective_mode(gs: &GameState) -> bool {
+    matches!(gs.game_mode, GameMode::Ctc(_))
+}
+
+/// Color of a spawn or base in the world - the team's color or neutral.
+pub fn team_marker_rgb(team: Option<u32>) -> Rgb {
+    team.and_then(|team| TEAM_COLORS.get(team as usize).copied())
+        .unwrap_or(NEUTRAL_RGB)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    /// Open 5x5 area with 2 bases (surface 15, tile 60) and the red marker on the left one.
+    fn bases_map() -> Map {
+        let map_text = "0 0 0 0 0
+0 60 0 60 0
+0 0 0 0 0
+marker base_red 1 1
+";
+        test_utils::parse_map(map_text)
+    }
+
+    #[test]
+    fn test_base_radius_wiring() {
+        let map = bases_map();
+        let pos = map.tile_center(Vec2u::new(1, 1)) + v!(50 0);
+
+        let small = Cvars {
+            g_ctc_base_radius: 40.0,
+            ..Cvars::default()
+        };
+        assert!(base_at(&small, &map, pos).is_none());
+        let areas = base_areas(&small, &map);
+        assert_eq!(areas.len(), 2);
+        assert!(areas.iter().all(|area| area.radius == 40.0));
+
+        // The same cvar moves both the capture check and what gets drawn.
+        let big = Cvars {
+            g_ctc_base_radius: 60.0,
+            ..Cvars::default()
+        };
+        let base = base_at(&big, &map, pos).unwrap();
+        assert_eq!(base.index, Vec2u::new(1, 1));
+        assert_eq!(base.radius, 60.0);
+        let areas = base_areas(&big, &map);
+        assert!(areas.iter().all(|area| area.radius == base_radius(&big)));
+    }
+
+    #[test]
+    fn test_team_colors() {
+        let map = bases_map();
+        let areas = base_areas(&Cvars::default(), &map);
+        assert_eq!(areas[0].team, Some(0));
+        assert_eq!(areas[1].team, None);
+
+        assert_eq!(team_marker_rgb(Some(0)), TEAM_COLORS[0]);
+        assert_eq!(team_marker_rgb(Some(1)), TEAM_COLORS[1]);
+        assert_eq!(team_marker_rgb(None), NEUTRAL_RGB);
+        // More teams than colors
+        assert_eq!(team_marker_rgb(Some(7)), NEUTRAL_RGB);
+    }
+}
@@ -0,0 +1,318 @@
+//! Exhibition mode for watching bot matches, e.g. on a projector.
+//!
+//! With `cl_observer` the camera of player 1 automatically follows whoever
+//! has been in the most action lately and `rendering` draws a sidebar
+//! with every player's stats.
+//!
+//! The client only knows about kills and HP changes so that's what counts as action.
+//! Damage is credited to whoever took it - being shot at means being in a fight.
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionKind {
+    Kill {
+        /// None if the attacker left or it was a suicide.
+        attacker: Option<Index>,
+        victim: Index,
+    },
+    Damage {
+        victim: Index,
+        /// Fraction of full HP.
+        amount: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Action {
+    pub time: f64,
+    pub kind: ActionKind,
+}
+
+/// How interesting each player has been during the last `cl_observer_window` seconds,
+/// returns the most interesting one for whom `eligible` is true.
+///
+/// Recent actions count more, they fade out linearly over the window.
+/// Ties go to the player involved in the most recent action.
+pub fn most_action(
+    cvars: &Cvars,
+    actions: &VecDeque<Action>,
+    game_time: f64,
+    eligible: impl Fn(Index) -> bool,
+) -> Option<Index> {
+    let window = cvars.cl_observer_window;
+    let mut scores: Vec<(Index, f64, f64)> = Vec::new();
+    let mut add = |player_handle: Index, score: f64, time: f64| {
+        if !eligible(player_handle) {
+            return;
+        }
+        match scores
+            .iter_mut()
+            .find(|(handle, _, _)| *handle == player_handle)
+        {
+            Some((_, total, last)) => {
+                *total += score;
+                *last = last.max(time);
+            }
+            None => scores.push((player_handle, score, time)),
+        }
+    };
+
+    for action in actions {
+        let age = game_time - action.time;
+        if age < 0.0 || age >= window {
+            continue;
+        }
+        let recency = 1.0 - age / window;
+        match action.kind {
+            ActionKind::Kill {
+                attacker: Some(attacker),
+                victim,
+            } if attacker != victim => {
+                add(
+                    attacker,
+                    cvars.cl_observer_kill_weight * recency,
+                    action.time,
+                );
+            }
+            ActionKind::Kill { .. } => {}
+            ActionKind::Damage { victim, amount } => {
+                let score = cvars.cl_observer_damage_weight * amount * recency;
+                add(victim, score, action.time);
+            }
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter(|&(_, score, _)| score > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(a.2.total_cmp(&b.2)))
+        .map(|(handle, _, _)| handle)
+}
+
+/// Decides whom the observer camera follows.
+#[derive(Debug, Clone)]
+pub struct Director {
+    /// Recent actions, oldest first.
+    pub actions: VecDeque<Action>,
+    pub following: Option<Index>,
+    /// No automatic switching before this game time so the camera doesn't jump around.
+    pub next_switch_time: f64,
+    /// Manually chosen, no automatic switching before this game time.
+    pub override_until: f64,
+    /// Last value of `cl_observer_follow` we reacted to.
+    pub follow_requested: i32,
+}
+
+impl Director {
+    pub fn new() -> Self {
+        Self {
+            actions: VecDeque::new(),
+            following: None,
+            next_switch_time: f64::NEG_INFINITY,
+            override_until: f64::NEG_INFINITY,
+            follow_requested: -1,
+        }
+    }
+
+    pub fn record_action(&mut self, time: f64, kind: ActionKind) {
+        self.actions.push_back(Action { time, kind });
+    }
+
+    /// Follow `player_handle` and pause automatic switching for `cl_observer_override_time`.
+    pub fn follow(&mut self, cvars: &Cvars, game_time: f64, player_handle: Index) {
+        self.following = Some(player_handle);
+        self.override_until = game_time + cvars.cl_observer_override_time;
+    }
+
+    /// Forget old actions and switch to the most interesting player if it's time.
+    ///
+    /// `candidates` are the players the camera can follow, in a stable order.
+    /// When nothing is happening, the camera stays put
+    /// or goes to the first candidate if the current one is gone.
+    pub fn update(&mut self, cvars: &Cvars, game_time: f64, candidates: &[Index]) {
+        while let Some(action) = self.actions.front() {
+            if game_time - action.time < cvars.cl_observer_window {
+                break;
+            }
+            self.actions.pop_front();
+        }
+
+        let valid = self.following.is_some_and(|h| candidates.contains(&h));
+        if valid && (game_time < self.override_until || game_time < self.next_switch_time) {
+            return;
+        }
+
+        let best = most_action(cvars, &self.actions, game_time, |h| candidates.contains(&h));
+        if let Some(best) = best {
+            if self.following != Some(best) {
+                self.following = Some(best);
+                self.next_switch_time = game_time + cvars.cl_observer_switch_interval;
+            }
+        } else if !valid {
+            self.following = candidates.first().copied();
+        }
+    }
+}
+
+impl Default for Director {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientFrameCtx<'_> {
+    /// Point player 1's camera at the action, see `Director`.
+    pub fn sys_observer(&mut self) {
+        if !self.cvars.cl_observer {
+            self.cg.director.actions.clear();
+            self.cg.views[0].spectating = None;
+            return;
+        }
+
+        let game_time = self.gs.game_time;
+        let director = &mut self.cg.director;
+        if self.cvars.cl_observer_follow != director.follow_requested {
+            director.follow_requested = self.cvars.cl_observer_follow;
+            let requested = u32::try_from(self.cvars.cl_observer_follow)
+                .ok()
+                .and_then(|index| self.gs.players.slot_to_index(index));
+            match requested {
+                Some(player_handle) => director.follow(self.cvars, game_time, player_handle),
+                // Back to automatic.
+                None => director.override_until = f64::NEG_INFINITY,
+            }
+        }
+
+        // Only players with a vehicle can be followed,
+        // dead ones too so the camera doesn't jump away right after a kill.
+        let candidates: Vec<_> = self
+            .gs
+            .players
+            .iter()
+            .filter(|(_, player)| player.vehicle.is_some())
+            .map(|(handle, _)| handle)
+            .collect();
+        director.update(self.cvars, game_time, &candidates);
+        self.cg.views[0].spectating = director.following;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handles<const N: usize>() -> [Index; N] {
+        let mut arena = Arena::new();
+        std::array::from_fn(|_| arena.insert(()))
+    }
+
+    fn cvars() -> Cvars {
+        Cvars {
+            cl_observer_damage_weight: 1.0,
+            cl_observer_kill_weight: 1.0,
+            cl_observer_override_time: 20.0,
+            cl_observer_switch_interval: 5.0,
+            cl_observer_window: 10.0,
+            ..Cvars::default()
+        }
+    }
+
+    fn kill(time: f64, attacker: Index, victim: Index) -> Action {
+        Action {
+            time,
+            kind: ActionKind::Kill {
+                attacker: Some(attacker),
+                victim,
+            },
+        }
+    }
+
+    fn damage(time: f64, victim: Index, amount: f64) -> Action {
+        Action {
+            time,
+            kind: ActionKind::Damage { victim, amount },
+        }
+    }
+
+    #[test]
+    fn test_most_action() {
+        let cvars = cvars();
+        let [a, b, c] = handles();
+        let all = |_| true;
+
+        assert_eq!(most_action(&cvars, &VecDeque::new(), 0.0, all), None);
+
+        // The killer, not the victim.
+        let actions = VecDeque::from([kill(1.0, a, b)]);
+        assert_eq!(most_action(&cvars, &actions, 1.0, all), Some(a));
+        // Until it's too old.
+        assert_eq!(most_action(&cvars, &actions, 10.999, all), Some(a));
+        assert_eq!(most_action(&cvars, &actions, 11.0, all), None);
+
+        // Recent damage beats an older kill.
+        let actions = VecDeque::from([kill(1.0, a, b), damage(9.0, c, 0.5)]);
+        assert_eq!(most_action(&cvars, &actions, 9.0, all), Some(c));
+        assert_eq!(most_action(&cvars, &actions, 2.0, all), Some(a));
+
+        // Damage adds up.
+        let actions = VecDeque::from([
+            damage(5.0, b, 0.4),
+            damage(5.0, c, 0.5),
+            damage(5.0, b, 0.4),
+        ]);
+        assert_eq!(most_action(&cvars, &actions, 5.0, all), Some(b));
+
+        // Ties go to the most recent.
+        let actions = VecDeque::from([damage(5.0, a, 0.5), damage(5.0, b, 0.5)]);
+        assert_eq!(most_action(&cvars, &actions, 5.0, all), Some(b));
+
+        // Suicides and ineligible players don't count.
+        let actions = VecDeque::from([kill(5.0, a, a), kill(5.0, b, c)]);
+        assert_eq!(most_action(&cvars, &actions, 5.0, all), Some(b));
+        assert_eq!(most_action(&cvars, &actions, 5.0, |h| h != b), None);
+    }
+
+    #[test]
+    fn test_director_switching() {
+        let cvars = cvars();
+        let [a, b, c] = handles();
+        let candidates = [a, b, c];
+        let mut director = Director::new();
+
+        // Nothing happening - anyone is better than nobody.
+        director.update(&cvars, 0.0, &candidates);
+        assert_eq!(director.following, Some(a));
+
+        director.record_action(1.0, kill(1.0, b, c).kind);
+        director.update(&cvars, 1.0, &candidates);
+        assert_eq!(director.following, Some(b));
+
+        // Not again before the interval is over.
+        director.record_action(2.0, kill(2.0, c, a).kind);
+        director.update(&cvars, 2.0, &candidates);
+        assert_eq!(director.following, Some(b));
+        director.update(&cvars, 6.0, &candidates);
+        assert_eq!(director.following, Some(c));
+
+        // Manual override pauses switching.
+        director.follow(&cvars, 7.0, a);
+        director.record_action(8.0, kill(8.0, b, c).kind);
+        director.update(&cvars, 8.0, &candidates);
+        assert_eq!(director.following, Some(a));
+        director.record_action(25.0, kill(25.0, b, c).kind);
+        director.update(&cvars, 26.0, &candidates);
+        assert_eq!(director.following, Some(a));
+        director.update(&cvars, 27.0, &candidates);
+        assert_eq!(director.following, Some(b));
+
+        // Old actions are forgotten, the camera stays.
+        director.update(&cvars, 100.0, &candidates);
+        assert!(director.actions.is_empty());
+        assert_eq!(director.following, Some(b));
+
+        // Unless the player is gone.
+        director.update(&cvars, 101.0, &[a, c]);
+        assert_eq!(director.following, Some(a));
+    }
+}
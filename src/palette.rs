@@ -0,0 +1,221 @@
+//! Named game colors, initialized from cvars.
+//!
+//! Rendering asks the palette instead of hardcoding colors at each call site
+//! so they can all be changed through cvars (by name in the console or in presets),
+//! e.g. for colorblind players.
+//!
+//! Building a palette is just copying a few numbers so it's done each frame,
+//! changing a cvar takes effect immediately.
+//!
+//! Deliberately left out:
+//! - Texture tints - white (no tint), wreck brightness and fade-out alpha modulate sprites.
+//! - Black shadows, outlines, letterboxing, the pause dim and translucent panels behind HUD elements.
+//!   They're for contrast so they should stay black whatever the other colors are.
+//! - Colors which already come from their own cvars or from teams and players
+//!   (zone, crosshair, notifications, names, team markers, ...).
+//! - The map overlay (`d_draw_map_overlay`) and `r_show_spawns`, they're for debugging maps.
+//! - Debug shapes, each carries its own color, see `debug`.
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    // World
+    pub bfg: Color,
+    pub cb: Color,
+    pub decal_bullet: Color,
+    pub decal_scorch: Color,
+    pub explosion_lod: Color,
+    pub explosion_lod_bfg: Color,
+    pub flare: Color,
+    pub mg: Color,
+    pub mg_tracer_head: Color,
+    pub missile_prediction: Color,
+    pub particle_backblast: Color,
+    pub particle_boost_exhaust: Color,
+    pub particle_impact_dust: Color,
+    pub particle_impact_spark: Color,
+    pub particle_launch_smoke: Color,
+    pub particle_splash: Color,
+    pub particle_track_scrub: Color,
+    pub rail_beam: Color,
+    pub scrape_spark: Color,
+    pub smoke: Color,
+    pub surface_dust: Color,
+    pub surface_marks: Color,
+    pub surface_spray: Color,
+
+    // HUD
+    pub hud_ammo: Color,
+    pub hud_boost_active: Color,
+    pub hud_boost_charging: Color,
+    pub hud_boost_ready: Color,
+    pub hud_cb_count: Color,
+    pub hud_compass: Color,
+    pub hud_compass_north: Color,
+    pub hud_compass_turret: Color,
+    pub hud_cooldown_ready: Color,
+    pub hud_cooldown_waiting: Color,
+    pub hp_empty: Color,
+    pub hp_half: Color,
+    pub hp_full: Color,
+    pub hp_flash: Color,
+    pub hud_misalign: Color,
+    pub hud_missile: Color,
+    pub hud_missile_indicator: Color,
+    pub hud_observer_hp_background: Color,
+    pub hud_pause: Color,
+    pub hud_perf_warning: Color,
+    pub hud_perf_warning_detail: Color,
+    pub hud_possessed: Color,
+    pub hud_range_hints: Color,
+    pub hud_scoreboard_others: Color,
+    pub hud_text: Color,
+    pub hud_weapon_blocked: Color,
+    pub spawn_indicator: Color,
+
+    // Debugging
+    pub debug_text: Color,
+}
+
+impl Palette {
+    pub fn new(cvars: &Cvars) -> Self {
+        Self {
+            bfg: cvars.r_bfg_color.into(),
+            cb: cvars.r_cluster_bomb_color.into(),
+            decal_bullet: cvars.r_decals_bullet_color.into(),
+            decal_scorch: cvars.r_decals_scorch_color.into(),
+            explosion_lod: cvars.r_explosion_lod_color.into(),
+            explosion_lod_bfg: cvars.r_explosion_lod_color_bfg.into(),
+            flare: cvars.r_flare_color.into(),
+            mg: cvars.r_mg_color.into(),
+            mg_tracer_head: cvars.r_mg_tracer_head_color.into(),
+            missile_prediction: cvars.r_missile_prediction_color.into(),
+            particle_backblast: cvars.r_particles_color_backblast.into(),
+            particle_boost_exhaust: cvars.r_particles_color_boost_exhaust.into(),
+            particle_impact_dust: cvars.r_particles_color_impact_dust.into(),
+            particle_impact_spark: cvars.r_particles_color_impact_spark.into(),
+            particle_launch_smoke: cvars.r_particles_color_launch_smoke.into(),
+            particle_splash: cvars.r_particles_color_splash.into(),
+            particle_track_scrub: cvars.r_particles_color_track_scrub.into(),
+            rail_beam: cvars.r_railgun_color.into(),
+            scrape_spark: cvars.r_scrape_sparks_color.into(),
+            smoke: cvars.r_smoke_color.into(),
+            surface_dust: cvars.r_surface_dust_color.into(),
+            surface_marks: cvars.r_surface_marks_color.into(),
+            surface_spray: cvars.r_surface_spray_color.into(),
+
+            hud_ammo: cvars.hud_ammo_color.into(),
+            hud_boost_active: cvars.hud_boost_color_active.into(),
+            hud_boost_charging: cvars.hud_boost_color_charging.into(),
+            hud_boost_ready: cvars.hud_boost_color_ready.into(),
+            hud_cb_count: cvars.hud_cb_count_color.into(),
+            hud_compass: cvars.hud_compass_color.into(),
+            hud_compass_north: cvars.hud_compass_north_color.into(),
+            hud_compass_turret: cvars.hud_compass_turret_color.into(),
+            hud_cooldown_ready: cvars.hud_cooldown_color_ready.into(),
+            hud_cooldown_waiting: cvars.hud_cooldown_color_waiting.into(),
+            hp_empty: cvars.hud_hp_color_empty.into(),
+            hp_half: cvars.hud_hp_color_half.into(),
+            hp_full: cvars.hud_hp_color_full.into(),
+            hp_flash: cvars.hud_hp_flash_color.into(),
+            hud_misalign: cvars.hud_misalign_color.into(),
+            hud_missile: cvars.hud_missile_color.into(),
+            hud_missile_indicator: cvars.hud_missile_indicator_color.into(),
+            hud_observer_hp_background: cvars.hud_observer_hp_background_color.into(),
+            hud_pause: cvars.hud_pause_color.into(),
+            hud_perf_warning: cvars.hud_perf_warning_color.into(),
+            hud_perf_warning_detail: cvars.hud_perf_warning_color_detail.into(),
+            hud_possessed: cvars.hud_possessed_color.into(),
+            hud_range_hints: cvars.hud_range_hints_color.into(),
+            hud_scoreboard_others: cvars.hud_scoreboard_color_others.into(),
+            hud_text: cvars.hud_text_color.into(),
+            hud_weapon_blocked: cvars.hud_weapon_blocked_color.into(),
+            spawn_indicator: cvars.cl_spawn_indicator_color.into(),
+
+            debug_text: cvars.d_draw_text_color.into(),
+        }
+    }
+
+    /// Hit points to color - from `hp_empty` at 0 through `hp_half` to `hp_full` at 1.
+    ///
+    /// Values outside 0..1 (overheal, rounding below 0) are clamped.
+    pub fn hp_color(&self, hp_fraction: f64) -> Color {
+        let hp_fraction = hp_fraction.clamped(0.0, 1.0) as f32;
+        if hp_fraction < 0.5 {
+            lerp_color(self.hp_empty, self.hp_half, hp_fraction * 2.0)
+        } else {
+            lerp_color(self.hp_half, self.hp_full, (hp_fraction - 0.5) * 2.0)
+        }
+    }
+}
+
+/// The same color with a different opacity, most effects fade out.
+pub fn with_alpha(color: Color, alpha: f32) -> Color {
+    Color { a: alpha, ..color }
+}
+
+/// Component-wise including alpha, `t` between 0 and 1.
+pub fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    Color::new(
+        lerp(from.r, to.r),
+        lerp(from.g, to.g),
+        lerp(from.b, to.b),
+        lerp(from.a, to.a),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hp_color() {
+        let palette = Palette::new(&Cvars::default());
+        let hp_color = |hp_fraction| palette.hp_color(hp_fraction);
+        assert_eq!(hp_color(0.0), Color::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(hp_color(0.25), Color::new(1.0, 0.5, 0.0, 1.0));
+        assert_eq!(hp_color(0.5), Color::new(1.0, 1.0, 0.0, 1.0));
+        assert_eq!(hp_color(0.75), Color::new(0.5, 1.0, 0.0, 1.0));
+        assert_eq!(hp_color(1.0), Color::new(0.0, 1.0, 0.0, 1.0));
+        // Overheal and slightly negative values stay valid.
+        assert_eq!(hp_color(1.5), hp_color(1.0));
+        assert_eq!(hp_color(100.0), hp_color(1.0));
+        assert_eq!(hp_color(-0.1), hp_color(0.0));
+        for hp_fraction in [-1.0, 0.25, 0.75, 1.2, f64::INFINITY] {
+            let color = hp_color(hp_fraction);
+            for c in [color.r, color.g, color.b] {
+                assert!((0.0..=1.0).contains(&c), "{hp_fraction} {color:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_defaults_match_macroquad_colors() {
+        // The colors used to be hardcoded, defaults shouldn't change how anything looks.
+        let palette = Palette::new(&Cvars::default());
+        assert_eq!(palette.explosion_lod, ORANGE);
+        assert_eq!(palette.explosion_lod_bfg, SKYBLUE);
+        assert_eq!(palette.hud_boost_charging, GRAY);
+        assert_eq!(palette.hud_cooldown_ready, LIGHTGRAY);
+        assert_eq!(palette.hud_observer_hp_background, DARKGRAY);
+        assert_eq!(palette.hud_text, WHITE);
+        assert_eq!(palette.debug_text, RED);
+        assert_eq!(palette.hud_ammo, YELLOW);
+        assert_eq!(palette.spawn_indicator, GREEN);
+    }
+
+    #[test]
+    fn test_palette_from_cvar_names() {
+        let mut cvars = Cvars::default();
+        cvars.set_str("r_railgun_color", "1 0 1").unwrap();
+        cvars.set_str("hud_hp_color_half", "0 0 1").unwrap();
+        let palette = Palette::new(&cvars);
+        assert_eq!(palette.rail_beam, Color::new(1.0, 0.0, 1.0, 1.0));
+        assert_eq!(palette.hp_color(0.5), Color::new(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(palette.hp_color(0.25), Color::new(0.5, 0.0, 0.5, 1.0));
+        // Untouched colors keep their defaults.
+        assert_eq!(palette.mg, Palette::new(&Cvars::default()).mg);
+    }
+}
@@ -0,0 +1,619 @@
+//! Short-lived particles drawn by the client - missile launch effects, boost exhaust,
+//! track scrub and impacts.
+//!
+//! All kinds share one buffer capped at `cl_particles_max` scaled by `EffectsBudget`.
+//! Cosmetic particles get scaled down as it fills up
+//! and gameplay-relevant ones can push them out when it's full.
+
+use crate::{
+    effects_budget::EffectsBudget,
+    map::{Surface, SurfaceKind},
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ParticlePriority {
+    /// Purely decorative, the first to go when the buffer is full.
+    Cosmetic,
+    /// Tells the player something about the game (LATER e.g. damage smoke).
+    Gameplay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleKind {
+    /// Hot exhaust thrown out behind a launcher.
+    Backblast,
+    /// Expanding puff at the muzzle.
+    LaunchSmoke,
+    /// Flames behind a boosting vehicle.
+    BoostExhaust,
+    /// Projectile hitting a wall.
+    ImpactSpark,
+    /// Projectile hitting sand or dirt.
+    ImpactDust,
+    /// Projectile hitting water.
+    ImpactSplash,
+    /// Spreading ring around an explosion on water, drawn as an outline.
+    SplashRing,
+    /// Dirt thrown up by tracks spinning in opposite directions.
+    TrackScrub,
+}
+
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub kind: ParticleKind,
+    pub priority: ParticlePriority,
+    pub pos: Vec2f,
+    pub vel: Vec2f,
+    pub start_time: f64,
+    pub duration: f64,
+    pub size_begin: f64,
+    pub size_end: f64,
+}
+
+impl Particle {
+    pub fn drifted_pos(&self, game_time: f64) -> Vec2f {
+        self.pos + self.vel * (game_time - self.start_time)
+    }
+
+    /// From 0 (just spawned) to 1 (about to disappear).
+    pub fn progress(&self, game_time: f64) -> f64 {
+        ((game_time - self.start_time) / self.duration).clamped(0.0, 1.0)
+    }
+
+    pub fn size_at(&self, game_time: f64) -> f64 {
+        let progress = self.progress(game_time);
+        self.size_begin + (self.size_end - self.size_begin) * progress
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Particles {
+    particles: Vec<Particle>,
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+
+    /// How many of `wanted` cosmetic particles to actually spawn.
+    ///
+    /// All of them until the buffer is `cl_particles_cosmetic_fraction` full,
+    /// then fewer and fewer, none when it's full.
+    pub fn cosmetic_budget(&self, cvars: &Cvars, budget: &EffectsBudget, wanted: usize) -> usize {
+        let max = budget.particles_max;
+        let soft_max = (max as f64 * cvars.cl_particles_cosmetic_fraction) as usize;
+        let len = self.particles.len();
+        if len < soft_max {
+            wanted
+        } else if len >= max {
+            0
+        } else {
+            wanted * (max - len) / (max - soft_max)
+        }
+    }
+
+    /// Add the particle if there's room.
+    ///
+    /// When the buffer is full, gameplay particles replace the oldest cosmetic one.
+    /// Returns whether the particle was added.
+    pub fn spawn(&mut self, budget: &EffectsBudget, particle: Particle) -> bool {
+        if self.particles.len() < budget.particles_max {
+            self.particles.push(particle);
+            return true;
+        }
+        if particle.priority == ParticlePriority::Cosmetic {
+            return false;
+        }
+
+        // Particles are added in order so the first cosmetic one is the oldest.
+        let oldest_cosmetic = self
+            .particles
+            .iter()
+            .position(|p| p.priority == ParticlePriority::Cosmetic);
+        match oldest_cosmetic {
+            Some(index) => {
+                self.particles.remove(index);
+                self.particles.push(particle);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cleanup(&mut self, game_time: f64) {
+        self.particles
+            .retain(|particle| game_time - particle.start_time < particle.duration);
+    }
+}
+
+/// Which effect a projectile hitting `surface` makes.
+///
+/// Walls spark, water and surfaces marked `spray` splash, those marked `dust` kick up dust.
+/// Everything else uses `r_impact_default`.
+pub fn impact_effect(cvars: &Cvars, surface: &Surface) -> ImpactEffect {
+    if surface.kind == SurfaceKind::Wall {
+        ImpactEffect::Sparks
+    } else if surface.kind == SurfaceKind::Water || surface.flags.spray {
+        ImpactEffect::Splash
+    } else if surface.flags.dust {
+        ImpactEffect::Dust
+    } else {
+        cvars.r_impact_default
+    }
+}
+
+/// Spawn a burst of particles around `pos` for the impact `effect`.
+///
+/// Returns how many were spawned after the budget thinned them out.
+pub fn spawn_impact(
+    cvars: &Cvars,
+    particles: &mut Particles,
+    budget: &mut EffectsBudget,
+    effect: ImpactEffect,
+    pos: Vec2f,
+    start_time: f64,
+) -> usize {
+    // Sparks keep their size, dust spreads out, droplets fall apart.
+    let (kind, count, duration, size, growth, speed) = match effect {
+        ImpactEffect::None => return 0,
+        ImpactEffect::Sparks => (
+            ParticleKind::ImpactSpark,
+            cvars.r_impact_sparks_count,
+            cvars.r_impact_sparks_duration,
+            cvars.r_impact_sparks_size,
+            1.0,
+            cvars.r_impact_sparks_speed,
+        ),
+        ImpactEffect::Dust => (
+            ParticleKind::ImpactDust,
+            cvars.r_impact_dust_count,
+            cvars.r_impact_dust_duration,
+            cvars.r_impact_dust_size,
+            2.0,
+            cvars.r_impact_dust_speed,
+        ),
+        ImpactEffect::Splash => (
+            ParticleKind::ImpactSplash,
+            cvars.r_impact_splash_count,
+            cvars.r_impact_splash_duration,
+            cvars.r_impact_splash_size,
+            0.5,
+            cvars.r_impact_splash_speed,
+        ),
+    };
+
+    let wanted = budget.particle_count(count);
+    let allowed = particles.cosmetic_budget(cvars, budget, wanted);
+    let count = budget.take(allowed);
+    for i in 0..count {
+        // All around the impact, no need for real randomness in a cosmetic effect.
+        let angle = i as f64 / count as f64 * 2.0 * PI + start_time * 7.0;
+        let speed = speed * (0.5 + 0.5 * (i as f64 * 2.4).sin().abs());
+        particles.spawn(
+            budget,
+            Particle {
+                kind,
+                priority: ParticlePriority::Cosmetic,
+                pos,
+                vel: angle.to_vec2f() * speed,
+                start_time,
+                duration,
+                size_begin: size,
+                size_end: size * growth,
+            },
+        );
+    }
+    count
+}
+
+impl ClientFrameCtx<'_> {
+    /// Sparks, dust or a splash where a projectile hit, depending on the surface there.
+    pub fn spawn_impact_effects(&mut self, pos: Vec2f, start_time: f64) {
+        if !self.cvars.r_impact_effects {
+            return;
+        }
+        // Walls are hit slightly inside so this finds the wall, not the tile in front of it.
+        let effect = impact_effect(self.cvars, self.map.surface_at_pos(pos));
+        spawn_impact(
+            self.cvars,
+            &mut self.cg.particles,
+            &mut self.cg.effects_budget,
+            effect,
+            pos,
+            start_time,
+        );
+    }
+
+    /// Ring spreading out from explosions on water.
+    pub fn spawn_explosion_splash(&mut self, pos: Vec2f, scale: f64, start_time: f64) {
+        if !self.cvars.r_impact_effects || self.map.surface_at_pos(pos).kind != SurfaceKind::Water {
+            return;
+        }
+        let allowed = self
+            .cg
+            .particles
+            .cosmetic_budget(self.cvars, &self.cg.effects_budget, 1);
+        if self.cg.effects_budget.take(allowed) > 0 {
+            self.cg.particles.spawn(
+                &self.cg.effects_budget,
+                Particle {
+                    kind: ParticleKind::SplashRing,
+                    priority: ParticlePriority::Cosmetic,
+                    pos,
+                    vel: Vec2f::zero(),
+                    start_time,
+                    duration: self.cvars.r_impact_splash_ring_duration,
+                    size_begin: 0.0,
+                    size_end: self.cvars.r_impact_splash_ring_size * scale,
+                },
+            );
+        }
+    }
+
+    /// Backblast behind the launcher and a smoke puff at the muzzle when a missile is fired.
+    ///
+    /// `start_time` is when it was fired, see `effect_start_time`.
+    pub fn spawn_launch_effects(&mut self, projectile_handle: Index, start_time: f64) {
+        if !self.cvars.r_launch_effects {
+            return;
+        }
+        let projectile = &self.gs.projectiles[projectile_handle];
+        if !matches!(projectile.weapon, Weapon::Rockets | Weapon::Hm | Weapon::Gm) {
+            return;
+        }
+        let owner = projectile.owner;
+        let Some(vehicle_handle) = self.gs.players.get(owner).and_then(|p| p.vehicle) else {
+            return;
+        };
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        // The projectile is already at the muzzle, the launcher decides which way the blast goes.
+        let muzzle = projectile.pos;
+        let (_, backblast_dir) = weapons::launch_blast(self.cvars, vehicle, projectile.weapon);
+
+        let budget = &self.cg.effects_budget;
+        let wanted = budget.particle_count(self.cvars.r_launch_backblast_count);
+        let allowed = self
+            .cg
+            .particles
+            .cosmetic_budget(self.cvars, budget, wanted);
+        let count = self.cg.effects_budget.take(allowed);
+        let spread = self.cvars.r_launch_backblast_spread;
+        for i in 0..count {
+            // Evenly fanned out across the cone, no need for real randomness in a cosmetic effect.
+            let t = if count > 1 {
+                i as f64 / (count - 1) as f64 * 2.0 - 1.0
+            } else {
+                0.0
+            };
+            let speed =
+                self.cvars.r_launch_backblast_speed * (0.6 + 0.4 * (i as f64 * 2.4).sin().abs());
+            self.cg.particles.spawn(
+                &self.cg.effects_budget,
+                Particle {
+                    kind: ParticleKind::Backblast,
+                    priority: ParticlePriority::Cosmetic,
+                    pos: muzzle,
+                    vel: backblast_dir.rotated_z(spread * t) * speed,
+                    start_time,
+                    duration: self.cvars.r_launch_backblast_duration,
+                    size_begin: self.cvars.r_launch_backblast_size,
+                    size_end: self.cvars.r_launch_backblast_size,
+                },
+            );
+        }
+        let smoke_allowed =
+            self.cg
+                .particles
+                .cosmetic_budget(self.cvars, &self.cg.effects_budget, 1);
+        if self.cg.effects_budget.take(smoke_allowed) > 0 {
+            self.cg.particles.spawn(
+                &self.cg.effects_budget,
+                Particle {
+                    kind: ParticleKind::LaunchSmoke,
+                    priority: ParticlePriority::Cosmetic,
+                    pos: muzzle,
+                    vel: vehicle.vel,
+                    start_time,
+                    duration: self.cvars.r_launch_smoke_duration,
+                    size_begin: self.cvars.r_launch_smoke_size_begin,
+                    size_end: self.cvars.r_launch_smoke_size_end,
+                },
+            );
+        }
+
+        if let Some(view) = self.cg.view_following_mut(owner) {
+            view.add_trauma(self.cvars.r_launch_shake);
+        }
+    }
+
+    /// Exhaust thrown out behind vehicles while they're boosting.
+    ///
+    /// Spawned from the synced `Vehicle::boost_until` like sparks from wall contacts.
+    pub fn sys_boost_exhaust(&mut self) {
+        let game_time = self.gs.game_time;
+        for (_, vehicle) in self.gs.vehicles.iter() {
+            if vehicle.destroyed() || !vehicle.boosting(game_time) {
+                continue;
+            }
+
+            let budget = &self.cg.effects_budget;
+            let wanted = budget.particle_count(self.cvars.r_boost_exhaust_count);
+            let allowed = self
+                .cg
+                .particles
+                .cosmetic_budget(self.cvars, budget, wanted);
+            let count = self.cg.effects_budget.take(allowed);
+            let forward = vehicle.angle.to_vec2f();
+            let back = -forward;
+            let rear = vehicle.pos + forward * vehicle.hitbox.mins.x;
+            for i in 0..count {
+                // Wobble around the rear, no need for real randomness in a cosmetic effect.
+                let wobble = (game_time * 37.0 + i as f64 * 2.4).sin() * 0.4;
+                self.cg.particles.spawn(
+                    &self.cg.effects_budget,
+                    Particle {
+                        kind: ParticleKind::BoostExhaust,
+                        priority: ParticlePriority::Cosmetic,
+                        pos: rear,
+                        vel: vehicle.vel
+                            + back.rotated_z(wobble) * self.cvars.r_boost_exhaust_speed,
+                        start_time: game_time,
+                        duration: self.cvars.r_boost_exhaust_duration,
+                        size_begin: self.cvars.r_boost_exhaust_size,
+                        size_end: self.cvars.r_boost_exhaust_size * 2.0,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Dirt thrown off the tracks of vehicles pivoting in place.
+    ///
+    /// Spawned from the synced `Vehicle::turn_rate` which is the pivot rate while pivoting.
+    pub fn sys_track_scrub(&mut self) {
+        let game_time = self.gs.game_time;
+        for (_, vehicle) in self.gs.vehicles.iter() {
+            if vehicle.destroyed() || vehicle.turn_rate == 0.0 {
+                continue;
+            }
+            let stats = vehicle.movement_stats(self.cvars, game_time);
+            if !stats.pivots(vehicle.vel) {
+                continue;
+            }
+
+            let budget = &self.cg.effects_budget;
+            let wanted = budget.particle_count(self.cvars.r_track_scrub_count);
+            let allowed = self
+                .cg
+                .particles
+                .cosmetic_budget(self.cvars, budget, wanted);
+            let count = self.cg.effects_budget.take(allowed);
+            let forward = vehicle.angle.to_vec2f();
+            let right = forward.rotated_z(PI / 2.0);
+            let spin = vehicle.turn_rate.signum();
+            for i in 0..count {
+                // Alternate between the tracks. Turning clockwise, the right track runs backwards
+                // so it throws dirt forwards and the left one the other way.
+                let (side, offset) = if i % 2 == 0 {
+                    (1.0, vehicle.hitbox.maxs.y)
+                } else {
+                    (-1.0, vehicle.hitbox.mins.y)
+                };
+                let wobble = (game_time * 29.0 + i as f64 * 2.4).sin() * 0.5;
+                let along = (game_time * 13.0 + i as f64).sin() * vehicle.hitbox.maxs.x;
+                self.cg.particles.spawn(
+                    &self.cg.effects_budget,
+                    Particle {
+                        kind: ParticleKind::TrackScrub,
+                        priority: ParticlePriority::Cosmetic,
+                        pos: vehicle.pos + right * offset + forward * along,
+                        vel: (forward * side * spin).rotated_z(wobble)
+                            * self.cvars.r_track_scrub_speed,
+                        start_time: game_time,
+                        duration: self.cvars.r_track_scrub_duration,
+                        size_begin: self.cvars.r_track_scrub_size,
+                        size_end: self.cvars.r_track_scrub_size * 1.5,
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    use crate::{map, net::LocalConnection, test_utils};
+
+    fn high_budget(cvars: &Cvars) -> EffectsBudget {
+        EffectsBudget::new(cvars, EffectsQuality::High)
+    }
+
+    fn particle(priority: ParticlePriority, start_time: f64) -> Particle {
+        Particle {
+            kind: ParticleKind::Backblast,
+            priority,
+            pos: Vec2f::zero(),
+            vel: Vec2f::zero(),
+            start_time,
+            duration: 1.0,
+            size_begin: 1.0,
+            size_end: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_cosmetic_budget() {
+        let cvars = Cvars {
+            cl_particles_max: 100,
+            cl_particles_cosmetic_fraction: 0.5,
+            ..Cvars::default()
+        };
+        let budget = high_budget(&cvars);
+        let mut particles = Particles::new();
+        assert_eq!(particles.cosmetic_budget(&cvars, &budget, 10), 10);
+
+        for _ in 0..75 {
+            assert!(particles.spawn(&budget, particle(ParticlePriority::Cosmetic, 0.0)));
+        }
+        assert_eq!(particles.cosmetic_budget(&cvars, &budget, 10), 5);
+
+        for _ in 0..25 {
+            assert!(particles.spawn(&budget, particle(ParticlePriority::Cosmetic, 0.0)));
+        }
+        assert_eq!(particles.cosmetic_budget(&cvars, &budget, 10), 0);
+    }
+
+    #[test]
+    fn test_priority_admission() {
+        let cvars = Cvars {
+            cl_particles_max: 3,
+            ..Cvars::default()
+        };
+        let budget = high_budget(&cvars);
+        let mut particles = Particles::new();
+        assert!(particles.spawn(&budget, particle(ParticlePriority::Gameplay, 0.0)));
+        assert!(particles.spawn(&budget, particle(ParticlePriority::Cosmetic, 1.0)));
+        assert!(particles.spawn(&budget, particle(ParticlePriority::Cosmetic, 2.0)));
+
+        // Full - cosmetic is rejected, gameplay replaces the oldest cosmetic one.
+        assert!(!particles.spawn(&budget, particle(ParticlePriority::Cosmetic, 3.0)));
+        assert!(particles.spawn(&budget, particle(ParticlePriority::Gameplay, 4.0)));
+        assert_eq!(particles.len(), 3);
+        let start_times: Vec<_> = particles.iter().map(|p| p.start_time).collect();
+        assert_eq!(start_times, [0.0, 2.0, 4.0]);
+
+        assert!(particles.spawn(&budget, particle(ParticlePriority::Gameplay, 5.0)));
+        // Nothing cosmetic left to push out.
+        assert!(!particles.spawn(&budget, particle(ParticlePriority::Gameplay, 6.0)));
+        assert!(particles
+            .iter()
+            .all(|p| p.priority == ParticlePriority::Gameplay));
+
+        particles.cleanup(5.5);
+        let start_times: Vec<_> = particles.iter().map(|p| p.start_time).collect();
+        assert_eq!(start_times, [5.0]);
+    }
+
+    #[test]
+    fn test_impact_effect() {
+        let surfaces = map::parse_texture_list(
+            "g1 0 1 1 marks\nbunker1 2 1 1\nwater 3 1 0.5 spray\ndesert 0 1.1 1 dust\n\
+             snow 4 0.75 0.6 marks\npuddle 0 1 1 spray\n",
+        );
+        let effects = |cvars: &Cvars| -> Vec<_> {
+            surfaces
+                .iter()
+                .map(|surface| impact_effect(cvars, surface))
+                .collect()
+        };
+
+        let cvars = Cvars::default();
+        use ImpactEffect::{Dust, Sparks, Splash};
+        assert_eq!(effects(&cvars), [Dust, Sparks, Splash, Dust, Dust, Splash]);
+
+        // Only surfaces without their own effect fall back to the cvar.
+        let cvars = Cvars {
+            r_impact_default: ImpactEffect::None,
+            ..Cvars::default()
+        };
+        let none = ImpactEffect::None;
+        assert_eq!(effects(&cvars), [none, Sparks, Splash, Dust, none, Splash]);
+    }
+
+    #[test]
+    fn test_impact_budget() {
+        let cvars = Cvars {
+            cl_particles_max: 100,
+            r_effects_spawns_max: 100,
+            r_impact_sparks_count: 8,
+            ..Cvars::default()
+        };
+        let mut particles = Particles::new();
+        let mut budget = high_budget(&cvars);
+        let effect = ImpactEffect::Sparks;
+        let count = spawn_impact(&cvars, &mut particles, &mut budget, effect, v!(0 0), 0.0);
+        assert_eq!(count, 8);
+        assert_eq!(particles.len(), 8);
+        assert!(particles
+            .iter()
+            .all(|p| p.kind == ParticleKind::ImpactSpark));
+
+        // Lower quality spawns fewer.
+        let mut particles = Particles::new();
+        let mut budget = EffectsBudget::new(&cvars, EffectsQuality::Low);
+        let count = spawn_impact(&cvars, &mut particles, &mut budget, effect, v!(0 0), 0.0);
+        assert_eq!(count, 2);
+
+        // Nothing once the frame's spawns are used up.
+        let mut particles = Particles::new();
+        let mut budget = high_budget(&cvars);
+        budget.spend(95);
+        let count = spawn_impact(&cvars, &mut particles, &mut budget, effect, v!(0 0), 0.0);
+        assert_eq!(count, 5);
+        let count = spawn_impact(&cvars, &mut particles, &mut budget, effect, v!(0 0), 0.0);
+        assert_eq!(count, 0);
+        assert_eq!(particles.len(), 5);
+
+        assert_eq!(
+            spawn_impact(
+                &cvars,
+                &mut particles,
+                &mut high_budget(&cvars),
+                ImpactEffect::None,
+                v!(0 0),
+                0.0
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_track_scrub() {
+        let cvars = Cvars::default();
+        let mut gs = GameState::new();
+        let (player, tank) = test_utils::add_tank(&cvars, &mut gs, v!(100 100), 0.0);
+        let (sender, _r) = mpsc::channel();
+        let (_s, receiver) = mpsc::channel();
+        let conn = LocalConnection::new(sender, receiver);
+        let mut cg = ClientGame::new(Box::new(conn), player);
+        let map = test_utils::parse_map("0 0\n0 0\n");
+        let mut ctx = ClientFrameCtx::new(&cvars, &map, &mut gs, &mut cg);
+
+        // Not turning.
+        ctx.sys_track_scrub();
+        assert!(ctx.cg.particles.is_empty());
+
+        // Turning while driving uses the regular model.
+        ctx.gs.vehicles[tank].turn_rate = 1.0;
+        ctx.gs.vehicles[tank].vel = v!(100 0);
+        ctx.sys_track_scrub();
+        assert!(ctx.cg.particles.is_empty());
+
+        ctx.gs.vehicles[tank].vel = Vec2f::zero();
+        ctx.sys_track_scrub();
+        assert_eq!(ctx.cg.particles.len(), cvars.r_track_scrub_count);
+        assert!(ctx
+            .cg
+            .particles
+            .iter()
+            .all(|p| p.kind == ParticleKind::TrackScrub));
+    }
+}
@@ -0,0 +1,307 @@
+//! Taking control of a bot for debugging AI.
+//!
+//! The human's input goes to the bot's player instead of their own,
+//! so everything that follows from input works the same as for any other player -
+//! driving, shooting, guiding the bot's missile, respawning after the bot dies.
+//! Meanwhile the human's own player is driven by a stand-in Ai
+//! (including any missile the human was guiding).
+//!
+//! LATER Allow possessing other humans' players.
+
+use crate::prelude::*;
+
+/// The player whose input `player_handle`'s client controls.
+pub fn input_target(gs: &GameState, player_handle: Index) -> Index {
+    match gs.players[player_handle].possessing {
+        Some(possession) => possession.target,
+        None => player_handle,
+    }
+}
+
+/// Apply input received from `player_handle`'s client.
+///
+/// Both the client's player and the possessed bot can be gone by the time the input arrives,
+/// in which case it's dropped.
+///
+/// A player manning a gun emplacement controls it instead of their vehicle,
+/// only `interact` stays with the player so they can let go.
+pub fn route_input(gs: &mut GameState, player_handle: Index, input: NetInput) {
+    let Some(player) = gs.players.soft_get(player_handle) else {
+        return;
+    };
+    let target = player.possessing.map_or(player_handle, |p| p.target);
+    let Some(target) = gs.players.soft_get_mut(target) else {
+        return;
+    };
+    let emplacement = target
+        .emplacement
+        .and_then(|handle| gs.emplacements.soft_get_mut(handle));
+    if let Some(emplacement) = emplacement {
+        emplacement.input = input;
+        target.input = NetInput {
+            interact: input.interact,
+            ..NetInput::empty()
+        };
+    } else {
+        target.input = input;
+    }
+}
+
+impl ServerFrameCtx<'_> {
+    /// Redirect `player_handle`'s input to `target`, possessing yourself goes back to normal.
+    ///
+    /// Returns false if `target` is not a bot or is already possessed by someone else.
+    pub fn possess(&mut self, player_handle: Index, target: Index) -> bool {
+        if target == player_handle {
+            self.unpossess(player_handle);
+            return true;
+        }
+
+        let ClientType::Ai(target_ai) = self.gs.players[target].client else {
+            return false;
+        };
+        let already = self.gs.players[player_handle].possessing.map(|p| p.target);
+        if self.gs.ais[target_ai].possessed && already != Some(target) {
+            return false;
+        }
+
+        self.end_possession(player_handle);
+        self.gs.ais[target_ai].possessed = true;
+        let stand_in = Ai::new(player_handle, self.cvars.ai_difficulty);
+        let stand_in_ai = self.gs.ais.insert(stand_in);
+        self.gs.players[player_handle].possessing = Some(Possession {
+            target,
+            stand_in_ai,
+        });
+        // Until the stand-in takes over.
+        self.gs.players[player_handle].input = NetInput::empty();
+
+        let name = &self.gs.players[target].name;
+        dbg_logf!("{} possessed {name:?}", self.gs.players[player_handle].name);
+        self.notify_possessed(player_handle);
+        true
+    }
+
+    /// Give control back to the player's own entities.
+    pub fn unpossess(&mut self, player_handle: Index) {
+        if self.end_possession(player_handle) {
+            self.notify_possessed(player_handle);
+        }
+    }
+
+    /// End any possession involving the player before removing it.
+    ///
+    /// Doesn't notify the player itself since it's going away (its client might already be gone).
+    pub fn release_player(&mut self, player_handle: Index) {
+        self.end_possession(player_handle);
+
+        let possessors: Vec<_> = self
+            .gs
+            .players
+            .iter()
+            .filter(|(_, player)| player.possessing.map(|p| p.target) == Some(player_handle))
+            .map(|(handle, _)| handle)
+            .collect();
+        for possessor in possessors {
+            self.unpossess(possessor);
+        }
+    }
+
+    /// Returns false if the player wasn't possessing anyone.
+    fn end_possession(&mut self, player_handle: Index) -> bool {
+        let Some(possession) = self.gs.players[player_handle].possessing.take() else {
+            return false;
+        };
+
+        self.gs.ais.remove(possession.stand_in_ai);
+        self.gs.players[player_handle].input = NetInput::empty();
+
+        let Some(target) = self.gs.players.soft_get_mut(possession.target) else {
+            return true;
+        };
+        target.input = NetInput::empty();
+        if let ClientType::Ai(ai_handle) = target.client {
+            // Start from scratch, don't keep whatever it was doing before.
+            let Some(ai) = self.gs.ais.soft_get_mut(ai_handle) else {
+                return true;
+            };
+            *ai = Ai {
+                score_band: ai.score_band,
+                role: ai.role,
+                ..Ai::new(ai.player, ai.difficulty)
+            };
+        }
+        true
+    }
+
+    fn notify_possessed(&mut self, player_handle: Index) {
+        let player = &self.gs.players[player_handle];
+        if let ClientType::Remote(client_handle) = player.client {
+            let index = player.possessing.map(|p| p.target.slot());
+            self.net_send_one(ServerMessage::Possessed(index), client_handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    /// Nothing the AI would ever press all at once.
+    fn human_input() -> NetInput {
+        NetInput {
+            up: true,
+            down: true,
+            left: true,
+            right: true,
+            turret_left: true,
+            turret_right: true,
+            prev_weapon: true,
+            next_weapon: true,
+            fire: true,
+            fire_alt: true,
+            mine: true,
+            self_destruct: false,
+            horn: true,
+            boost: true,
+            interact: true,
+        }
+    }
+
+    fn receivers(gs: &GameState) -> Vec<Index> {
+        gs.players
+            .iter()
+            .filter(|(_, player)| player.input == human_input())
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    fn bot_count(gs: &GameState) -> usize {
+        gs.players
+            .iter()
+            .filter(|(_, player)| matches!(player.client, ClientType::Ai(_)))
+            .count()
+    }
+
+    #[test]
+    fn test_possess_routing() {
+        let cvars = Cvars {
+            bots_max: 2,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (human, _) = test_utils::spawn_player(&mut ctx, "human");
+        test_utils::tick(&mut server, &cvars);
+        assert_eq!(bot_count(&server.gs), 2);
+        let bot = server.gs.ais.iter().next().unwrap().1.player;
+
+        route_input(&mut server.gs, human, human_input());
+        assert_eq!(receivers(&server.gs), vec![human]);
+
+        assert!(server.ctx(&cvars).possess(human, bot));
+        assert_eq!(input_target(&server.gs, human), bot);
+        route_input(&mut server.gs, human, human_input());
+        assert_eq!(receivers(&server.gs), vec![bot]);
+
+        // The bot's AI keeps its hands off, the stand-in drives the human's player,
+        // the stand-in doesn't count as a bot.
+        for _ in 0..10 {
+            route_input(&mut server.gs, human, human_input());
+            test_utils::tick(&mut server, &cvars);
+            assert_eq!(receivers(&server.gs), vec![bot]);
+        }
+        assert_eq!(bot_count(&server.gs), 2);
+        assert_eq!(server.gs.ais.len(), 3);
+
+        // Back to normal
+        assert!(server.ctx(&cvars).possess(human, human));
+        assert_eq!(input_target(&server.gs, human), human);
+        assert_eq!(server.gs.ais.len(), 2);
+        assert!(server.gs.ais.iter().all(|(_, ai)| !ai.possessed));
+        route_input(&mut server.gs, human, human_input());
+        assert_eq!(receivers(&server.gs), vec![human]);
+        test_utils::tick(&mut server, &cvars);
+        assert_ne!(server.gs.players[bot].input, human_input());
+    }
+
+    #[test]
+    fn test_possess_rejected() {
+        let cvars = Cvars {
+            bots_max: 1,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let a = ctx
+            .gs
+            .players
+            .insert(Player::new("a".to_owned(), ClientType::Local));
+        let b = ctx
+            .gs
+            .players
+            .insert(Player::new("b".to_owned(), ClientType::Local));
+        ctx.spawn_vehicle(a, true);
+        ctx.spawn_vehicle(b, true);
+        test_utils::tick(&mut server, &cvars);
+        let bot = server.gs.ais.iter().next().unwrap().1.player;
+
+        let mut ctx = server.ctx(&cvars);
+        // Not a bot
+        assert!(!ctx.possess(a, b));
+        assert!(ctx.possess(a, bot));
+        // Taken
+        assert!(!ctx.possess(b, bot));
+        assert_eq!(input_target(ctx.gs, b), b);
+        // Possessing the same bot again changes nothing.
+        assert!(ctx.possess(a, bot));
+        assert_eq!(ctx.gs.ais.len(), 2);
+    }
+
+    #[test]
+    fn test_possessed_bot_removed() {
+        let mut cvars = Cvars {
+            bots_max: 1,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (human, _) = test_utils::spawn_player(&mut ctx, "human");
+        test_utils::tick(&mut server, &cvars);
+        let bot = server.gs.ais.iter().next().unwrap().1.player;
+        assert!(server.ctx(&cvars).possess(human, bot));
+
+        cvars.bots_max = 0;
+        test_utils::tick(&mut server, &cvars);
+        assert!(!server.gs.players.contains(bot));
+        assert_eq!(server.gs.players[human].possessing, None);
+        assert_eq!(input_target(&server.gs, human), human);
+        assert!(server.gs.ais.is_empty());
+    }
+
+    #[test]
+    fn test_route_input_stale() {
+        let cvars = Cvars {
+            bots_max: 1,
+            d_strict: false,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (human, _) = test_utils::spawn_player(&mut ctx, "human");
+        test_utils::tick(&mut server, &cvars);
+        let bot = server.gs.ais.iter().next().unwrap().1.player;
+        assert!(server.ctx(&cvars).possess(human, bot));
+
+        // The bot is gone without going through release_player.
+        server.gs.players.remove(bot);
+        route_input(&mut server.gs, human, human_input());
+        assert_eq!(receivers(&server.gs), Vec::<Index>::new());
+
+        // The human is gone too.
+        server.gs.players.remove(human);
+        route_input(&mut server.gs, human, human_input());
+    }
+}
@@ -52,17 +52,21 @@ pub use thunderdome::{Arena, Index};
 pub use vek::{Clamp, Lerp, Slerp, Wrap};
 
 pub use crate::{
+    ai_difficulty::ScoreBand,
     assets::Assets,
     client::{Client, ClientGame},
     context::{ClientFrameCtx, FrameCtx, ServerFrameCtx},
     cvars::*,
-    debug::{DbgIterator, SoftUnwrap},
+    debug::{DbgIterator, SoftArena, SoftUnwrap},
     entities::*,
     game_state::*,
     input::*,
+    local_view::LocalPlayerView,
     map::Map,
+    match_report::{DeathRecord, MatchReport, MatchStats},
     net_messages::*,
     server::{Server, ServerGame},
+    stable_id::StableIds,
     timing::*,
     utils::lerp_ranges,
     weapons,
@@ -91,7 +95,9 @@ pub use crate::v;
 /// # Usage
 ///
 /// ```rust
+/// # use rec_wars::prelude::*;
 /// v!(1 2)
+/// # ;
 /// ```
 #[macro_export]
 macro_rules! v {
@@ -149,6 +155,14 @@ pub trait F64Ext {
 
     /// 2D rotation matrix
     fn to_mat2f(self) -> Mat2f;
+
+    /// Interpolate between two angles the short way around the circle.
+    ///
+    /// The result is normalized to 0..=360 deg.
+    fn lerp_angle(self, to: f64, t: f64) -> f64;
+
+    /// Round to the nearest multiple of `1 / steps_per_unit`.
+    fn quantized(self, steps_per_unit: f64) -> f64;
 }
 
 impl F64Ext for f64 {
@@ -159,6 +173,14 @@ impl F64Ext for f64 {
     fn to_mat2f(self) -> Mat2f {
         Mat2f::rotation_z(self)
     }
+
+    fn lerp_angle(self, to: f64, t: f64) -> f64 {
+        (self + self.delta_angle(to) * t).rem_euclid(2.0 * PI)
+    }
+
+    fn quantized(self, steps_per_unit: f64) -> f64 {
+        (self * steps_per_unit).round() / steps_per_unit
+    }
 }
 
 pub trait ArenaExt {
@@ -241,4 +263,33 @@ mod tests {
         let b = 0.69;
         assert_eq!(v!(-s.x, a + b), Vec2f::new(-42.0, 420.69));
     }
+
+    #[test]
+    fn test_lerp_angle() {
+        let eps = 1e-9;
+        let from = 350.0f64.to_radians();
+        let to = 10.0f64.to_radians();
+
+        // Across the seam - must not go the long way through 180 deg.
+        let mid = from.lerp_angle(to, 0.5);
+        assert!(mid < eps || mid > 2.0 * PI - eps, "{mid}");
+        let quarter = from.lerp_angle(to, 0.25);
+        assert!((quarter - 355.0f64.to_radians()).abs() < eps, "{quarter}");
+        let back = to.lerp_angle(from, 0.25);
+        assert!((back - 5.0f64.to_radians()).abs() < eps, "{back}");
+
+        assert!((from.lerp_angle(to, 0.0) - from).abs() < eps);
+        assert!((from.lerp_angle(to, 1.0) - to).abs() < eps);
+
+        // Not across the seam
+        let mid = 1.0f64.lerp_angle(2.0, 0.5);
+        assert!((mid - 1.5).abs() < eps, "{mid}");
+    }
+
+    #[test]
+    fn test_quantized() {
+        assert_eq!(1.23456f64.quantized(1000.0), 1.235);
+        assert_eq!((1.0f64 + 1e-9).quantized(1000.0), 1.0);
+        assert_eq!((-2.0004f64).quantized(1000.0), -2.0);
+    }
 }
@@ -0,0 +1,243 @@
+//! Named weapon balance presets.
+//!
+//! Each preset sets every balance cvar listed in `BALANCE`,
+//! so switching between presets never leaves behind values from the previous one.
+//! Cvars not in the table (networking, rendering, debug, ...) are never touched.
+//!
+//! - `original` - as close to the original RecWar as we know
+//! - `rebalanced` - the current defaults
+//! - `chaos` - huge explosions, fast reloads, not meant to be balanced
+//!
+//! # Changing presets mid-match
+//!
+//! Some cvars are copied into entities when they spawn, those entities keep the old values:
+//! - projectile speed and spread - the velocity is set when fired
+//! - CB fuse (`g_cluster_bomb_time*`) and arcs (`g_*_arc*`) - `Projectile::explode_time` and `flight`
+//! - reload and refire times - `WeaponState` stores when it'll be ready,
+//!   the new value is used the next time the weapon fires or starts reloading
+//!
+//! Everything else is read continuously and takes effect immediately:
+//! - damage, explosion damage, radius and scale - read on impact
+//! - reload ammo - read when a reload finishes
+//! - armor - vehicles store HP as a fraction so their absolute HP changes immediately
+//! - BFG beam, self destruct, smoke
+//! - surface speed - read every tick
+//!
+//! # Switching from the console
+//!
+//! Setting `g_preset` on the client sends `ClientMessage::Preset` to the server.
+//! The server applies it to its cvars and broadcasts `ServerMessage::Preset`
+//! so clients apply it too and their cvars agree with the server's.
+//! On the command line, `g_preset` is applied immediately
+//! so cvars after it can still override individual values.
+
+use crate::prelude::*;
+
+pub const PRESET_NAMES: [&str; 3] = ["original", "rebalanced", "chaos"];
+
+/// Cvar name and its value in each preset, in the order of `PRESET_NAMES`.
+///
+/// Angles are in radians unless they end with `deg`, see `cvar_units`.
+///
+/// Most weapon values were measured from RecWar and were never changed,
+/// so original and rebalanced share them.
+/// They only differ where RecWars changes the game on purpose:
+/// - armor and respawn delay - lower in RecWars
+/// - railgun speed - hitscan in RecWar, a very fast projectile in RecWars (see README)
+/// - surface speed - RecWar scales max speed by the surface, RecWars doesn't by default
+#[rustfmt::skip]
+const BALANCE: &[(&str, [&str; 3])] = &[
+    //                                          original   rebalanced   chaos
+    ("g_armor",                                 ["100",    "50",        "150"]),
+    ("g_respawn_delay",                         ["2",      "0.5",       "0.25"]),
+    ("g_surface_speed",                         ["true",   "false",     "false"]),
+
+    ("g_machine_gun_angle_spread",              ["0.015",  "0.015",     "0.05"]),
+    ("g_machine_gun_damage",                    ["2.5",    "2.5",       "4"]),
+    ("g_machine_gun_refire",                    ["0.05",   "0.05",      "0.025"]),
+    ("g_machine_gun_reload_ammo",               ["50",     "50",        "200"]),
+    ("g_machine_gun_reload_time",               ["1",      "1",         "0.25"]),
+    ("g_machine_gun_speed",                     ["1000",   "1000",      "1500"]),
+
+    ("g_railgun_damage",                        ["47",     "47",        "60"]),
+    ("g_railgun_push",                          ["300",    "300",       "1500"]),
+    ("g_railgun_reload_ammo",                   ["1",      "1",         "3"]),
+    ("g_railgun_reload_time",                   ["1",      "1",         "0.3"]),
+    // 15625 tiles per second, as good as hitscan, infinity would break the math.
+    ("g_railgun_speed",                         ["1000000", "2500",     "2500"]),
+
+    ("g_cluster_bomb_count",                    ["40",     "40",        "100"]),
+    ("g_cluster_bomb_damage_direct",            ["0",      "0",         "0"]),
+    ("g_cluster_bomb_explosion_damage",         ["25",     "25",        "25"]),
+    ("g_cluster_bomb_explosion_radius",         ["20",     "20",        "40"]),
+    ("g_cluster_bomb_explosion_scale",          ["0.5",    "0.5",       "1"]),
+    ("g_cluster_bomb_reload_ammo",              ["1",      "1",         "2"]),
+    ("g_cluster_bomb_reload_time",              ["1.5",    "1.5",       "0.5"]),
+    ("g_cluster_bomb_speed",                    ["400",    "400",       "500"]),
+    ("g_cluster_bomb_speed_spread_forward",     ["50",     "50",        "150"]),
+    ("g_cluster_bomb_speed_spread_sideways",    ["50",     "50",        "150"]),
+    ("g_cluster_bomb_time",                     ["0.8",    "0.8",       "1"]),
+    ("g_cluster_bomb_time_spread",              ["0.2",    "0.2",       "0.5"]),
+
+    ("g_rockets_damage_direct",                 ["25",     "25",        "30"]),
+    ("g_rockets_explosion_damage",              ["0",      "0",         "20"]),
+    ("g_rockets_explosion_radius",              ["20",     "20",        "40"]),
+    ("g_rockets_explosion_scale",               ["0.5",    "0.5",       "1.5"]),
+    ("g_rockets_refire",                        ["0.2",    "0.2",       "0.08"]),
+    ("g_rockets_reload_ammo",                   ["6",      "6",         "20"]),
+    ("g_rockets_reload_time",                   ["1.5",    "1.5",       "0.5"]),
+    ("g_rockets_speed",                         ["600",    "600",       "800"]),
+
+    ("g_homing_missile_damage_direct",          ["0",      "0",         "0"]),
+    ("g_homing_missile_explosion_damage",       ["56",     "56",        "80"]),
+    ("g_homing_missile_explosion_radius",       ["40",     "40",        "100"]),
+    ("g_homing_missile_explosion_scale",        ["1",      "1",         "2.5"]),
+    ("g_homing_missile_reload_ammo",            ["1",      "1",         "3"]),
+    ("g_homing_missile_reload_time",            ["1.5",    "1.5",       "0.5"]),
+    ("g_homing_missile_speed_initial",          ["100",    "100",       "200"]),
+
+    ("g_guided_missile_damage_direct",          ["0",      "0",         "0"]),
+    ("g_guided_missile_explosion_damage",       ["56",     "56",        "100"]),
+    ("g_guided_missile_explosion_radius",       ["40",     "40",        "120"]),
+    ("g_guided_missile_explosion_scale",        ["1",      "1",         "3"]),
+    ("g_guided_missile_reload_ammo",            ["1",      "1",         "2"]),
+    ("g_guided_missile_reload_time",            ["1.5",    "1.5",       "0.5"]),
+    ("g_guided_missile_speed_initial",          ["100",    "100",       "200"]),
+
+    ("g_bfg_beam_damage_per_sec",               ["25",     "25",        "50"]),
+    ("g_bfg_beam_range",                        ["125",    "125",       "250"]),
+    ("g_bfg_damage_direct",                     ["0",      "0",         "0"]),
+    ("g_bfg_explosion_damage",                  ["100",    "100",       "150"]),
+    ("g_bfg_explosion_radius",                  ["40",     "40",        "150"]),
+    ("g_bfg_explosion_scale",                   ["1",      "1",         "4"]),
+    ("g_bfg_reload_ammo",                       ["1",      "1",         "2"]),
+    ("g_bfg_reload_time",                       ["2.5",    "2.5",       "1"]),
+    ("g_bfg_speed",                             ["150",    "150",       "250"]),
+
+    ("g_self_destruct_damage_center",           ["150",    "150",       "300"]),
+    ("g_self_destruct_damage_edge",             ["0",      "0",         "50"]),
+    ("g_self_destruct_explosion_scale",         ["2",      "2",         "5"]),
+    ("g_self_destruct_radius",                  ["175",    "175",       "400"]),
+];
+
+impl Cvars {
+    /// Default cvars with the named balance preset applied.
+    pub fn preset(name: &str) -> Option<Cvars> {
+        let mut cvars = Cvars::default();
+        cvars.apply_preset(name).ok()?;
+        Some(cvars)
+    }
+
+    /// Set all balance cvars to the values from the named preset.
+    ///
+    /// Can be used mid-match, see the module docs for what takes effect when.
+    pub fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+        let column = PRESET_NAMES
+            .iter()
+            .position(|&preset| preset == name)
+            .ok_or_else(|| format!("unknown preset {name}"))?;
+        for (cvar_name, values) in BALANCE {
+            self.set_str_default_unit(cvar_name, values[column], AngleUnit::Rad)
+                .map_err(|e| format!("preset {name}: failed to set {cvar_name}: {e}"))?;
+        }
+        self.g_preset = name.to_owned();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    #[test]
+    fn test_presets_valid() {
+        for name in PRESET_NAMES {
+            assert!(Cvars::preset(name).is_some(), "{name}");
+        }
+        assert!(Cvars::preset("nope").is_none());
+
+        // Rebalanced is the defaults, check the table didn't get out of sync.
+        let defaults = Cvars::default();
+        let rebalanced = Cvars::preset("rebalanced").unwrap();
+        for (cvar_name, _) in BALANCE {
+            assert_eq!(
+                rebalanced.get_string(cvar_name),
+                defaults.get_string(cvar_name),
+                "{cvar_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_preset_requests() {
+        let mut cvars = Cvars {
+            bots_max: 0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+
+        server.sg.preset_requests.push("original".to_owned());
+        server.apply_preset_requests(&mut cvars);
+        assert_eq!(cvars.g_preset, "original");
+        assert_eq!(cvars.g_armor, 100.0);
+        assert!(cvars.g_surface_speed);
+        assert_eq!(cvars.bots_max, 0);
+        assert!(server.sg.preset_requests.is_empty());
+
+        // Unknown presets are ignored, nothing changes.
+        server.sg.preset_requests.push("nope".to_owned());
+        server.apply_preset_requests(&mut cvars);
+        assert_eq!(cvars.g_preset, "original");
+        assert_eq!(cvars.g_armor, 100.0);
+    }
+
+    #[test]
+    fn test_preset_mid_match_reload() {
+        let mut cvars = Cvars {
+            bots_max: 0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+        ctx.gs.players[player_handle].cur_weapon = Weapon::Rockets;
+        ctx.gs.players[player_handle].input.fire = true;
+
+        let rockets = Weapon::Rockets as usize;
+        let fire_last_rocket = |server: &mut Server, cvars: &Cvars| {
+            let game_time = server.gs.game_time;
+            let state = &mut server.gs.vehicles[vehicle_handle].weapon_states[rockets];
+            state.rounds_loaded = 1;
+            state.next_fire_time = game_time;
+            server.ctx(cvars).sys_shooting();
+            let state = server.gs.vehicles[vehicle_handle].weapon_states[rockets];
+            state.reload.expect("not reloading").end
+        };
+
+        let old_end = fire_last_rocket(&mut server, &cvars);
+        assert_eq!(old_end, cvars.g_rockets_reload_time);
+
+        cvars.apply_preset("chaos").unwrap();
+        assert_ne!(cvars.g_rockets_reload_time, 1.5);
+        assert_eq!(cvars.bots_max, 0);
+
+        // The reload that already started keeps its end time.
+        server.gs.game_time = old_end - 0.1;
+        server.ctx(&cvars).sys_vehicle_logic();
+        let state = server.gs.vehicles[vehicle_handle].weapon_states[rockets];
+        assert_eq!(state.reload.unwrap().end, old_end);
+
+        // Finishing the reload uses the new ammo count.
+        server.gs.game_time = old_end;
+        server.ctx(&cvars).sys_vehicle_logic();
+        let state = server.gs.vehicles[vehicle_handle].weapon_states[rockets];
+        assert!(!state.is_reloading());
+        assert_eq!(state.rounds_loaded, cvars.g_rockets_reload_ammo);
+
+        // The next reload uses the new time.
+        let new_end = fire_last_rocket(&mut server, &cvars);
+        assert_eq!(new_end, old_end + cvars.g_rockets_reload_time);
+    }
+}
@@ -0,0 +1,291 @@
+//! Player profile - the name and cvar overrides as text so they can survive reloads.
+//!
+//! Rust never touches storage directly, whoever keeps the text
+//! (the page in localStorage, a file on desktop) just passes it back on startup.
+//! Importing is lenient - every field is checked and applied on its own
+//! so one bad entry doesn't throw away the rest, the errors say what was skipped.
+//!
+//! The current layout (`PROFILE_VERSION`) is one entry per line:
+//!
+//! ```text
+//! profile_version 2
+//! name Player 1
+//! cvar r_smoothing false
+//! cvar g_turret_misalign_angle 0.1
+//! ```
+//!
+//! Version 1 had no version line and only stored cvars as `<name> <value>`,
+//! the player's name was the `cl_name1` cvar. It's migrated when imported.
+//!
+//! Angles are stored in radians regardless of `sys_angle_unit`.
+//!
+//! LATER Vehicle preference and key bindings once they exist.
+//! LATER Expose export and import to the page once the web build has a JS API.
+
+use crate::prelude::*;
+
+pub const PROFILE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Profile {
+    pub name: Option<String>,
+    /// Cvar names and values as text, applied in order.
+    pub cvars: Vec<(String, String)>,
+}
+
+/// An entry which was skipped when importing or applying a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileError {
+    /// 1-based, 0 for errors which aren't about a single line.
+    pub line: usize,
+    /// What the entry was setting, e.g. `name` or a cvar name.
+    pub field: String,
+    pub message: String,
+}
+
+impl ProfileError {
+    fn new(line: usize, field: &str, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            field: field.to_owned(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.field, self.message)
+    }
+}
+
+impl Profile {
+    /// The player's name and the current values of `cvar_names`.
+    ///
+    /// Names which aren't cvars are skipped.
+    pub fn capture(cvars: &Cvars, cvar_names: &[&str]) -> Self {
+        let cvars_text = cvar_names
+            .iter()
+            .filter_map(|&cvar_name| {
+                let value = cvars.get_string(cvar_name).ok()?;
+                Some((cvar_name.to_owned(), value))
+            })
+            .collect();
+        Self {
+            name: Some(cvars.cl_name1.clone()),
+            cvars: cvars_text,
+        }
+    }
+
+    /// Text in the current layout, see the module docs.
+    pub fn export(&self) -> String {
+        let mut text = format!("profile_version {PROFILE_VERSION}\n");
+        if let Some(name) = &self.name {
+            text.push_str(&format!("name {name}\n"));
+        }
+        for (cvar_name, value) in &self.cvars {
+            text.push_str(&format!("cvar {cvar_name} {value}\n"));
+        }
+        text
+    }
+
+    /// Parse text from any version, migrating older layouts.
+    ///
+    /// Entries which can't be parsed are skipped and returned as errors,
+    /// cvar names and values are only checked by `apply`.
+    pub fn import(text: &str) -> (Self, Vec<ProfileError>) {
+        let mut profile = Profile::default();
+        let mut errors = Vec::new();
+
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+        let mut version = 1;
+        if let Some(&(line_num, line)) = lines.peek() {
+            if let Some(rest) = line.strip_prefix("profile_version ") {
+                lines.next();
+                match rest.trim().parse() {
+                    Ok(v) if v <= PROFILE_VERSION => version = v,
+                    // Newer versions only add things, read what we understand.
+                    Ok(v) => {
+                        let message = format!(
+                            "{v} is newer than {PROFILE_VERSION}, reading it as {PROFILE_VERSION}"
+                        );
+                        errors.push(ProfileError::new(line_num, "profile_version", message));
+                        version = PROFILE_VERSION;
+                    }
+                    Err(e) => {
+                        let message = format!("invalid version {rest:?}: {e}");
+                        errors.push(ProfileError::new(line_num, "profile_version", message));
+                        version = PROFILE_VERSION;
+                    }
+                }
+            }
+        }
+
+        for (line_num, line) in lines {
+            let line = if version == 1 {
+                migrate_v1(line)
+            } else {
+                line.to_owned()
+            };
+            let (field, rest) = line.split_once(' ').unwrap_or((&line, ""));
+            let rest = rest.trim();
+            match field {
+                "name" => {
+                    if rest.is_empty() {
+                        errors.push(ProfileError::new(line_num, "name", "empty name"));
+                    } else {
+                        profile.name = Some(rest.to_owned());
+                    }
+                }
+                "cvar" => match rest.split_once(' ') {
+                    Some((cvar_name, value)) => {
+                        profile
+                            .cvars
+                            .push((cvar_name.to_owned(), value.trim().to_owned()));
+                    }
+                    None => {
+                        let field = if rest.is_empty() { "cvar" } else { rest };
+                        errors.push(ProfileError::new(line_num, field, "missing value"));
+                    }
+                },
+                _ => errors.push(ProfileError::new(line_num, field, "unknown field")),
+            }
+        }
+
+        (profile, errors)
+    }
+
+    /// Set the name and cvars, skipping and returning the ones which fail.
+    ///
+    /// Errors from here don't know about lines so `line` is 0.
+    pub fn apply(&self, cvars: &mut Cvars) -> Vec<ProfileError> {
+        if let Some(name) = &self.name {
+            cvars.cl_name1 = name.clone();
+        }
+        self.cvars
+            .iter()
+            .filter_map(|(cvar_name, value)| {
+                cvars
+                    .set_str_default_unit(cvar_name, value, AngleUnit::Rad)
+                    .err()
+                    .map(|e| ProfileError::new(0, cvar_name, e))
+            })
+            .collect()
+    }
+}
+
+/// Version 1 lines are `<cvar> <value>`, the name was `cl_name1`.
+fn migrate_v1(line: &str) -> String {
+    match line.strip_prefix("cl_name1 ") {
+        Some(name) => format!("name {name}"),
+        None => format!("cvar {line}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_round_trip() {
+        let cvars = Cvars {
+            cl_name1: "Sir Hurt the 2nd".to_owned(),
+            g_armor: 75.0,
+            r_decals_alpha: 0.25,
+            sys_angle_unit: AngleUnit::Deg,
+            g_turret_misalign_angle: 0.3,
+            ..Cvars::default()
+        };
+        let names = [
+            "g_armor",
+            "g_turret_misalign_angle",
+            "r_decals_alpha",
+            "sys_angle_unit",
+            "nope",
+        ];
+        let profile = Profile::capture(&cvars, &names);
+        assert_eq!(profile.cvars.len(), 4);
+
+        let text = profile.export();
+        let (imported, errors) = Profile::import(&text);
+        assert_eq!(errors, []);
+        assert_eq!(imported, profile);
+        assert_eq!(imported.export(), text);
+
+        let mut applied = Cvars::default();
+        assert_eq!(imported.apply(&mut applied), []);
+        assert_eq!(applied.cl_name1, "Sir Hurt the 2nd");
+        assert_eq!(applied.g_armor, 75.0);
+        assert_eq!(applied.r_decals_alpha, 0.25);
+        assert_eq!(applied.sys_angle_unit, AngleUnit::Deg);
+        // Radians even though degrees are preferred.
+        assert_eq!(applied.g_turret_misalign_angle, 0.3);
+    }
+
+    #[test]
+    fn test_profile_bad_entries_skipped() {
+        let text = "\
+profile_version 2
+name
+cvar g_armor 80
+cvar g_nope 1
+cvar g_respawn_delay soon
+cvar r_smoothing
+favorite_color red
+cvar g_turret_misalign_angle 90deg
+";
+        let (profile, errors) = Profile::import(text);
+        let fields: Vec<_> = errors.iter().map(|e| (e.line, e.field.as_str())).collect();
+        assert_eq!(
+            fields,
+            [(2, "name"), (6, "r_smoothing"), (7, "favorite_color")]
+        );
+        assert_eq!(profile.name, None);
+
+        let mut cvars = Cvars::default();
+        let errors = profile.apply(&mut cvars);
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["g_nope", "g_respawn_delay"]);
+        assert_eq!(cvars.cl_name1, Cvars::default().cl_name1);
+        assert_eq!(cvars.g_armor, 80.0);
+        assert_eq!(cvars.g_respawn_delay, Cvars::default().g_respawn_delay);
+        assert!((cvars.g_turret_misalign_angle - PI / 2.0).abs() < 1e-12);
+
+        // Not even a version.
+        let (profile, errors) = Profile::import("profile_version two\nname Fireman\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "profile_version");
+        assert_eq!(profile.name.as_deref(), Some("Fireman"));
+
+        assert_eq!(Profile::import(""), (Profile::default(), vec![]));
+    }
+
+    #[test]
+    fn test_profile_migration() {
+        let v1 = "cl_name1 Mr. Pain\ng_armor 80\n\nr_smoothing false\n";
+        let (profile, errors) = Profile::import(v1);
+        assert_eq!(errors, []);
+        assert_eq!(
+            profile,
+            Profile {
+                name: Some("Mr. Pain".to_owned()),
+                cvars: vec![
+                    ("g_armor".to_owned(), "80".to_owned()),
+                    ("r_smoothing".to_owned(), "false".to_owned()),
+                ],
+            }
+        );
+        assert!(profile.export().starts_with("profile_version 2\n"));
+
+        // Newer versions are read as far as possible.
+        let (profile, errors) = Profile::import("profile_version 3\nname Fireman\nvehicle tank\n");
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["profile_version", "vehicle"]);
+        assert_eq!(profile.name.as_deref(), Some("Fireman"));
+    }
+}
@@ -4,28 +4,109 @@ use std::{cmp::Reverse, str};
 
 use macroquad::prelude::*;
 
+#[cfg(feature = "debug_overlays")]
+use crate::debug::{details::UniqueLines, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_TEXTS_WORLD};
 use crate::{
+    bot_identity::{Rgb, TEAM_COLORS},
     client::ClientMode,
-    debug::{details::UniqueLines, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_TEXTS_WORLD},
-    map::{SurfaceKind, TILE_SIZE},
+    decals::DecalKind,
+    hud::{self, hud_pos, HudData, HudLayout},
+    map::{visible_tiles, SurfaceKind, VisibleTiles, TILE_SIZE},
+    objectives,
+    palette::{with_alpha, Palette},
+    particles::ParticleKind,
     prelude::*,
+    smoothing,
+    surface_effects::SurfaceEffectKind,
 };
 
 // LATER clean up at least some of the casts here
 
+/// Sprites in each explosion sheet.
+const EXPLOSION_FRAMES: usize = 13;
+/// Sprites are square, this is both width and height in pixels.
+const EXPLOSION_SPRITE_SIZE: f64 = 100.0;
+
+/// Smoke screens are drawn as a few overlapping puffs,
+/// these are their offsets as a fraction of the radius.
+const SMOKE_PUFFS: [(f64, f64); 5] = [(0.0, 0.0), (0.4, 0.0), (-0.4, 0.0), (0.0, 0.4), (0.0, -0.4)];
+
+/// The entity the camera follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraTarget {
+    Vehicle(Index),
+    Projectile(Index),
+}
+
+/// What the player's camera follows - their guided missile while they're controlling one,
+/// otherwise their vehicle.
+///
+/// Also where they hear from, see `audio::listener_pos`.
+///
+/// None if they have neither, e.g. a player who hasn't spawned yet or has left.
+pub fn camera_target(gs: &GameState, player_handle: Index) -> Option<CameraTarget> {
+    let player = gs.players.get(player_handle)?;
+    match player.guided_missile {
+        Some(gm_handle) => Some(CameraTarget::Projectile(gm_handle)),
+        None => player.vehicle.map(CameraTarget::Vehicle),
+    }
+}
+
+/// Camera offset for screen shake.
+///
+/// Grows with the square of trauma so small amounts stay subtle.
+pub fn shake_offset(cvars: &Cvars, trauma: f64, time: f64) -> Vec2f {
+    let amount = trauma * trauma * cvars.r_shake_offset_max;
+    // Unrelated frequencies so it doesn't look like a loop.
+    Vec2f::new((time * 47.0).sin(), (time * 61.0).cos()) * amount
+}
+
+/// Segments of the same alpha from all MG tracers, drawn together.
+#[derive(Debug, Clone, PartialEq)]
+struct TracerBatch {
+    alpha: f32,
+    segments: Vec<(Vec2f, Vec2f)>,
+}
+
+/// How long an MG tracer's tail is.
+///
+/// Proportional to the bullet's actual speed
+/// so shots which inherited the vehicle's velocity look faster.
+fn mg_tail_length(cvars: &Cvars, vel: Vec2f) -> f64 {
+    vel.magnitude() * cvars.r_mg_tracer_tail_time
+}
+
+/// Split the tails of all the bullets (screen position and velocity) into segments
+/// and group them by alpha, most opaque (nearest the head) first.
+fn mg_tracer_batches(cvars: &Cvars, bullets: &[(Vec2f, Vec2f)]) -> Vec<TracerBatch> {
+    let count = cvars.r_mg_tracer_segments;
+    let mut batches: Vec<_> = (0..count)
+        .map(|i| TracerBatch {
+            alpha: 1.0 - i as f32 / count as f32,
+            segments: Vec::with_capacity(bullets.len()),
+        })
+        .collect();
+    for &(pos, vel) in bullets {
+        if vel == Vec2f::zero() {
+            continue;
+        }
+        let step = -vel.normalized() * mg_tail_length(cvars, vel) / count as f64;
+        for (i, batch) in batches.iter_mut().enumerate() {
+            let begin = pos + step * i as f64;
+            batch.segments.push((begin, begin + step));
+        }
+    }
+    batches
+}
+
 impl Client {
     pub fn render(&mut self, cvars: &Cvars) {
         self.render_fps.tick(cvars.d_fps_period, self.real_time);
         let start = get_time();
 
         match &self.client_mode {
-            ClientMode::Singleplayer { player_handle } => {
-                self.render_viewport(cvars, *player_handle)
-            }
-            ClientMode::Splitscreen {
-                render_targets,
-                player_handles,
-            } => {
+            ClientMode::Singleplayer => self.render_viewport(cvars, &self.cg.views[0]),
+            ClientMode::Splitscreen { render_targets } => {
                 let rect = Rect::new(
                     0.0,
                     0.0,
@@ -42,12 +123,12 @@ impl Client {
                 camera.render_target = Some(render_targets.0.clone());
                 set_camera(&camera);
                 clear_background(BLANK);
-                self.render_viewport(cvars, player_handles.0);
+                self.render_viewport(cvars, &self.cg.views[0]);
 
                 camera.render_target = Some(render_targets.1.clone());
                 set_camera(&camera);
                 clear_background(BLANK);
-                self.render_viewport(cvars, player_handles.1);
+                self.render_viewport(cvars, &self.cg.views[1]);
 
                 set_default_camera();
                 draw_texture(&render_targets.0.texture, 0.0, 0.0, WHITE);
@@ -61,9 +142,11 @@ impl Client {
         let end = get_time();
         self.draw_calls_durations
             .add(cvars.d_timing_samples, end - start);
+        self.cg.metrics.record_draw(end - start);
+        self.cg.metrics.frames_rendered.inc();
     }
 
-    fn render_viewport(&self, cvars: &Cvars, local_player_handle: Index) {
+    fn render_viewport(&self, cvars: &Cvars, view: &LocalPlayerView) {
         // This is one long function. A lot of people will tell you that's bad™
         // because they've heard it from other people who think long functions are bad™.
         // Most of those people haven't written a game bigger than snake. Carmack says it's ok so it's ok:
@@ -76,13 +159,38 @@ impl Client {
             cg,
             ..
         } = self;
+        let palette = Palette::new(cvars);
+
+        // How far we are from the last tick to the next one, angles are interpolated by it.
+        let tick_fraction = match cvars.sys_tickrate_mode {
+            TickrateMode::Fixed => (self.game_time_carry * cvars.sys_tickrate_fixed_fps).min(1.0),
+            TickrateMode::Variable => 1.0,
+        };
+
+        // Where to draw entities - smoothed if the client tracks them, raw state otherwise.
+        let render_time = smoothing::render_time(cvars, gs.game_time);
+        let vehicle_transform = |handle: Index| {
+            let vehicle = &gs.vehicles[handle];
+            match cg.vehicle_smoothing.get(&handle) {
+                Some(smoothing) => smoothing.transform(cvars, render_time),
+                None => (vehicle.pos, vehicle.interpolated_angles(tick_fraction).0),
+            }
+        };
+        let projectile_pos = |handle: Index| match cg.projectile_smoothing.get(&handle) {
+            Some(smoothing) => smoothing.transform(cvars, render_time).0,
+            None => gs.projectiles[handle].pos,
+        };
 
+        // The camera follows whoever our input goes to.
+        let local_player_handle = view.camera_player();
         let player = &gs.players[local_player_handle];
-        let player_vehicle = &gs.vehicles[player.vehicle.unwrap()];
-        let player_entity_pos = if let Some(gm_handle) = player.guided_missile {
-            gs.projectiles[gm_handle].pos
-        } else {
-            player_vehicle.pos
+        // Spectated players might not have a vehicle (yet).
+        let player_vehicle = player.vehicle.and_then(|handle| gs.vehicles.get(handle));
+        let alive_vehicle = player_vehicle.filter(|vehicle| !vehicle.destroyed());
+        let player_entity_pos = match camera_target(gs, local_player_handle) {
+            Some(CameraTarget::Vehicle(handle)) => vehicle_transform(handle).0,
+            Some(CameraTarget::Projectile(handle)) => projectile_pos(handle),
+            None => map.maxs() / 2.0,
         };
 
         // Don't put the camera so close to the edge that it would render area outside the map.
@@ -102,7 +210,8 @@ impl Client {
         let camera_center = player_entity_pos.clamped(camera_center_min, camera_center_max);
 
         // Position of the camera's top left corner in world coords.
-        let camera_top_left = camera_center - camera_center_min;
+        let mut camera_top_left = camera_center - camera_center_min;
+        camera_top_left += shake_offset(cvars, view.trauma, gs.game_time);
         // Add this to world coords to get screen coords.
         // Forgetting to do this is a recurring source of bugs.
         // I've considered making a special type for screen coords (e.g. struct Vec2screen(Vec2f);)
@@ -112,33 +221,35 @@ impl Client {
         // - Which type are sizes? Another type? E.g. `center = corner + size/2` makes sense in both screen and world coords.
         let camera_offset = -camera_top_left + view_pos;
 
-        let top_left_tp = map.tile_pos(camera_top_left);
-        let top_left_index = top_left_tp.index;
+        // Tiles outside the map can be visible at the edges
+        // if the map is smaller than the view or due to float rounding.
+        let visible_tiles = visible_tiles(camera_top_left, view_size);
         let bg_offset = if cvars.r_align_to_pixels_background {
-            top_left_tp.offset.floor()
+            visible_tiles.offset.floor()
         } else {
-            top_left_tp.offset
+            visible_tiles.offset
+        };
+        // Screen coords of the tile's top left corner.
+        let tile_scr_pos = |c: isize, r: isize| {
+            let x = (c - visible_tiles.cols.start) as f64 * TILE_SIZE - bg_offset.x;
+            let y = (r - visible_tiles.rows.start) as f64 * TILE_SIZE - bg_offset.y;
+            view_pos + v!(x, y)
         };
 
         // Draw non-walls
-        let mut r = top_left_index.y;
-        let mut y = -bg_offset.y;
-        while y < view_size.y {
-            let mut c = top_left_index.x;
-            let mut x = -bg_offset.x;
-            while x < view_size.x {
-                let tile = map.col_row(c, r);
-
-                if map.surface_of(tile).kind != SurfaceKind::Wall {
-                    let img = &assets.texs_tiles[tile.surface_index];
-                    render_tile(img, view_pos.x + x, view_pos.y + y, tile.angle);
+        for r in visible_tiles.rows.clone() {
+            for c in visible_tiles.cols.clone() {
+                let scr_pos = tile_scr_pos(c, r);
+                match map.col_row_checked(c, r) {
+                    Some(tile) => {
+                        if map.surface_of(tile).kind != SurfaceKind::Wall {
+                            let img = assets.tile(tile.surface_index);
+                            render_tile(img, scr_pos.x, scr_pos.y, tile.angle);
+                        }
+                    }
+                    None => render_out_of_bounds(cvars, assets, scr_pos),
                 }
-
-                c += 1;
-                x += TILE_SIZE;
             }
-            r += 1;
-            y += TILE_SIZE;
         }
 
         // Helper to filter projectiles by weapon.
@@ -160,45 +271,219 @@ impl Client {
                 || scr_pos.y > outside_view_bottom_right.y
         };
 
-        // Draw MGs
-        for (_, mg) in weapon_projectiles(Weapon::Mg) {
-            let scr_pos = mg.pos + camera_offset;
+        // Draw base areas, spawns and markers from the map
+        // Bases always in objective modes since they affect gameplay, the rest is for debugging.
+        if objectives::objective_mode(gs) || cvars.r_show_spawns {
+            for base in objectives::base_areas(cvars, map) {
+                let scr_pos = base.pos + camera_offset;
+                if scr_pos.x + base.radius < view_pos.x
+                    || scr_pos.y + base.radius < view_pos.y
+                    || scr_pos.x - base.radius > view_pos.x + view_size.x
+                    || scr_pos.y - base.radius > view_pos.y + view_size.y
+                {
+                    continue;
+                }
+                let rgb = objectives::team_marker_rgb(base.team);
+                let color = Color::new(rgb.r, rgb.g, rgb.b, cvars.r_base_areas_alpha as f32);
+                draw_circle(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    base.radius as f32,
+                    color,
+                );
+            }
+        }
+        if cvars.r_show_spawns {
+            let alpha = cvars.r_show_spawns_alpha as f32;
+            let size = cvars.r_show_spawns_size;
+            for (&index, &team) in map.spawns().iter().zip(map.spawn_teams()) {
+                let scr_pos = map.tile_center(index) + camera_offset;
+                if cull(scr_pos) {
+                    continue;
+                }
+                let rgb = objectives::team_marker_rgb(team);
+                let color = Color::new(rgb.r, rgb.g, rgb.b, alpha);
+                // Chevron pointing up
+                let tip = scr_pos + v!(0, -size / 2.0);
+                render_line(scr_pos + v!(-size, size / 2.0), tip, 2.0, color);
+                render_line(scr_pos + v!(size, size / 2.0), tip, 2.0, color);
+            }
+            for (name, index) in map.markers() {
+                let scr_pos = map.tile_center(index) + camera_offset;
+                if cull(scr_pos) {
+                    continue;
+                }
+                let color = Color::new(1.0, 1.0, 1.0, alpha);
+                draw_poly_lines(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    4,
+                    size as f32,
+                    0.0,
+                    2.0,
+                    color,
+                );
+                let fs = cvars.r_show_spawns_font_size;
+                let text_size = measure_text(name, None, fs as u16, 1.0);
+                let x = scr_pos.x as f32 - text_size.width / 2.0;
+                let y = (scr_pos.y + size + fs) as f32;
+                render_text_with_shadow(cvars, name, x, y, fs, color, 1.0, 1.0, alpha);
+            }
+        }
+
+        // Draw surface effects
+        // On the ground so below everything else.
+        for effect in &cg.surface_effects {
+            let scr_pos = effect.pos + camera_offset;
             if cull(scr_pos) {
                 continue;
             }
-            // we're drawing from the bullet's position backwards
-            let scr_end = scr_pos - mg.vel.normalized() * cvars.cl_machine_gun_trail_length;
-            render_line(
-                scr_pos,
-                scr_end,
-                cvars.cl_machine_gun_trail_thickness,
-                YELLOW,
-            );
+            let progress = (gs.game_time - effect.start_time) / effect.kind.duration(cvars);
+            let fade = (1.0 - progress).clamped(0.0, 1.0);
+            let forward = effect.angle.to_vec2f();
+            let side = Vec2f::new(-forward.y, forward.x) * cvars.r_surface_marks_spacing;
+            match effect.kind {
+                SurfaceEffectKind::Marks => {
+                    let alpha = (cvars.r_surface_marks_alpha * fade) as f32;
+                    let color = with_alpha(palette.surface_marks, alpha);
+                    let half_length = forward * cvars.r_surface_marks_width;
+                    for offset in [side, -side] {
+                        let center = scr_pos + offset;
+                        render_line(
+                            center - half_length,
+                            center + half_length,
+                            cvars.r_surface_marks_width,
+                            color,
+                        );
+                    }
+                }
+                SurfaceEffectKind::Dust => {
+                    let radius = cvars.r_surface_dust_radius * (1.0 + progress);
+                    let color = with_alpha(palette.surface_dust, (0.5 * fade) as f32);
+                    draw_circle(scr_pos.x as f32, scr_pos.y as f32, radius as f32, color);
+                }
+                SurfaceEffectKind::Spray => {
+                    let radius = cvars.r_surface_spray_radius * (1.0 + progress);
+                    let color = with_alpha(palette.surface_spray, (0.7 * fade) as f32);
+                    for offset in [side, -side] {
+                        let pos = scr_pos + offset * (1.0 + progress);
+                        draw_circle(pos.x as f32, pos.y as f32, radius as f32, color);
+                    }
+                }
+            }
+        }
+
+        // Draw decals
+        // In front of the walls they're on, above the ground but below entities.
+        for (decal, age) in cg.decals.effects.iter_alive(gs.game_time) {
+            let scr_pos = decal.pos + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            let fade = (1.0 - age) as f32;
+            let forward = decal.angle.to_vec2f();
+            match decal.kind {
+                DecalKind::BulletChip => {
+                    let color =
+                        with_alpha(palette.decal_bullet, cvars.r_decals_alpha as f32 * fade);
+                    let half_length = forward * cvars.r_decals_bullet_size * 0.5;
+                    render_line(
+                        scr_pos - half_length,
+                        scr_pos + half_length,
+                        cvars.r_decals_bullet_size,
+                        color,
+                    );
+                }
+                DecalKind::Scorch => {
+                    let color =
+                        with_alpha(palette.decal_scorch, cvars.r_decals_alpha as f32 * fade);
+                    let radius = cvars.r_decals_scorch_size;
+                    draw_circle(scr_pos.x as f32, scr_pos.y as f32, radius as f32, color);
+                    // Smeared towards the wall.
+                    let smear = scr_pos + forward * radius * 0.5;
+                    draw_circle(smear.x as f32, smear.y as f32, (radius * 0.6) as f32, color);
+                }
+            }
+        }
+
+        // Draw MGs
+        match cvars.r_mg_style {
+            MgStyle::Line => {
+                for (handle, mg) in weapon_projectiles(Weapon::Mg) {
+                    let scr_pos = projectile_pos(handle) + camera_offset;
+                    if cull(scr_pos) {
+                        continue;
+                    }
+                    // we're drawing from the bullet's position backwards
+                    let scr_end = scr_pos - mg.vel.normalized() * cvars.cl_machine_gun_trail_length;
+                    render_line(
+                        scr_pos,
+                        scr_end,
+                        cvars.cl_machine_gun_trail_thickness,
+                        team_color(cvars, mg.team, palette.mg),
+                    );
+                }
+            }
+            MgStyle::Tracer => {
+                let mut bullets: Vec<_> = weapon_projectiles(Weapon::Mg)
+                    .map(|(handle, mg)| (mg.team, projectile_pos(handle) + camera_offset, mg.vel))
+                    .filter(|&(_, scr_pos, _)| !cull(scr_pos))
+                    .collect();
+                // Each team color gets its own batches.
+                bullets.sort_by_key(|&(team, _, _)| team);
+                for group in bullets.chunk_by(|a, b| a.0 == b.0) {
+                    let team = group[0].0;
+                    let group: Vec<_> = group.iter().map(|&(_, pos, vel)| (pos, vel)).collect();
+                    // Same alpha together so consecutive draw calls share state.
+                    for batch in mg_tracer_batches(cvars, &group) {
+                        let mut color = team_color(cvars, team, palette.mg);
+                        color.a = batch.alpha;
+                        for (begin, end) in batch.segments {
+                            render_line(begin, end, cvars.cl_machine_gun_trail_thickness, color);
+                        }
+                    }
+                }
+                let size = cvars.r_mg_tracer_head_size;
+                for (_, scr_pos, _) in bullets {
+                    draw_rectangle(
+                        (scr_pos.x - size / 2.0) as f32,
+                        (scr_pos.y - size / 2.0) as f32,
+                        size as f32,
+                        size as f32,
+                        palette.mg_tracer_head,
+                    );
+                }
+            }
         }
 
         // Draw railguns
-        for beam in &cg.rail_beams {
+        for (beam, age) in cg.rail_beams.iter_alive(gs.game_time) {
             let scr_begin = beam.begin + camera_offset;
             let scr_end = beam.end + camera_offset;
-            render_line(
-                scr_begin,
-                scr_end,
-                cvars.cl_railgun_trail_thickness,
-                Color::new(0.0, 0.0, 1.0, 1.0),
-            );
+            let mut color = team_color(cvars, beam.team, palette.rail_beam);
+            color.a *= (1.0 - age) as f32;
+            render_line(scr_begin, scr_end, cvars.cl_railgun_trail_thickness, color);
         }
 
         // Draw rockets, homing and guided missiles
-        for (_, proj) in weapon_projectiles(Weapon::Rockets) {
-            let scr_pos = proj.pos + camera_offset;
+        for (handle, proj) in weapon_projectiles(Weapon::Rockets) {
+            let scr_pos = projectile_pos(handle) + camera_offset;
             if cull(scr_pos) {
                 continue;
             }
             let offset = Vec2f::new(cvars.r_rockets_offset_x, cvars.r_rockets_offset_y);
-            render_tex_offset(&assets.tex_rocket, scr_pos, proj.vel.to_angle(), offset);
+            // Mortar rockets
+            let scale = 1.0 + proj.height(gs.game_time) * cvars.r_projectile_arc_scale;
+            render_tex_offset_scaled(
+                &assets.tex_rocket,
+                scr_pos,
+                proj.vel.to_angle(),
+                offset,
+                scale,
+            );
         }
-        for (_, proj) in weapon_projectiles(Weapon::Hm) {
-            let scr_pos = proj.pos + camera_offset;
+        for (handle, proj) in weapon_projectiles(Weapon::Hm) {
+            let scr_pos = projectile_pos(handle) + camera_offset;
             if cull(scr_pos) {
                 continue;
             }
@@ -208,8 +493,8 @@ impl Client {
             );
             render_tex_offset(&assets.tex_hm, scr_pos, proj.vel.to_angle(), offset);
         }
-        for (_, proj) in weapon_projectiles(Weapon::Gm) {
-            let scr_pos = proj.pos + camera_offset;
+        for (handle, proj) in weapon_projectiles(Weapon::Gm) {
+            let scr_pos = projectile_pos(handle) + camera_offset;
             if cull(scr_pos) {
                 continue;
             }
@@ -221,45 +506,127 @@ impl Client {
         }
 
         // Draw BFGs
-        // client.context.set_fill_style(&"lime".into());
-        // client.context.set_stroke_style(&"lime".into());
-        for (_, bfg) in weapon_projectiles(Weapon::Bfg) {
-            let scr_pos = bfg.pos + camera_offset;
+        for (handle, bfg) in weapon_projectiles(Weapon::Bfg) {
+            let scr_pos = projectile_pos(handle) + camera_offset;
             if cull(scr_pos) {
                 continue;
             }
+            let scale = 1.0 + bfg.height(gs.game_time) * cvars.r_projectile_arc_scale;
+            let color = team_color(cvars, bfg.team, palette.bfg);
             draw_circle(
                 scr_pos.x as f32,
                 scr_pos.y as f32,
-                cvars.g_bfg_radius as f32,
-                GREEN,
+                (cvars.g_bfg_radius * scale) as f32,
+                color,
             );
-            for (_, vehicle) in &self.gs.vehicles {
+            for (vehicle_handle, vehicle) in &self.gs.vehicles {
                 // TODO This should be shared logic in BFG code
                 if vehicle.owner == bfg.owner || vehicle.destroyed() {
                     continue;
                 }
                 // LATER Find nearest point on BFG's path?
-                if weapons::bfg_beam_hit(cvars, map, bfg.pos, vehicle.pos) {
-                    let scr_src = bfg.pos + camera_offset;
-                    let scr_dest = vehicle.pos + camera_offset;
-                    render_line(scr_src, scr_dest, 1.0, GREEN);
+                let beam_target = weapons::bfg_beam_target(bfg.pos, vehicle);
+                if weapons::bfg_beam_hit(cvars, map, bfg.pos, beam_target) {
+                    // Same check as the server but drawn to the smoothed positions.
+                    let (vehicle_pos, vehicle_angle) = vehicle_transform(vehicle_handle);
+                    let dest = vehicle.hitbox.closest_point(
+                        vehicle_pos,
+                        vehicle_angle,
+                        projectile_pos(handle),
+                    );
+                    render_line(scr_pos, dest + camera_offset, 1.0, color);
                 }
             }
         }
 
+        // Draw scenery wrecks, below vehicles
+        for wreck in &gs.wrecks {
+            let scr_pos = wreck.pos + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            render_tex_center(assets.wreck(wreck.veh_type), scr_pos, wreck.angle);
+        }
+
+        // Draw mines, also below vehicles, faded until they're armed
+        for (_, mine) in gs.mines.iter() {
+            let scr_pos = mine.pos + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            let mut color = team_color(cvars, mine.team, cvars.r_mine_color.into());
+            if !mine.armed(cvars, gs.game_time) {
+                color.a = cvars.r_mine_alpha_unarmed as f32;
+            }
+            let radius = cvars.r_mine_radius as f32;
+            draw_circle(scr_pos.x as f32, scr_pos.y as f32, radius, color);
+            draw_circle_lines(
+                scr_pos.x as f32,
+                scr_pos.y as f32,
+                radius,
+                1.0,
+                Color::new(0.0, 0.0, 0.0, color.a),
+            );
+        }
+
+        // Draw emplacements, below vehicles which can drive right next to them
+        for (_, emplacement) in gs.emplacements.iter() {
+            let scr_pos = emplacement.pos + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            let radius = cvars.r_emplacement_base_radius as f32;
+            let base_color: Color = cvars.r_emplacement_base_color.into();
+            draw_circle(scr_pos.x as f32, scr_pos.y as f32, radius, base_color);
+            if emplacement.controller.is_some() && !emplacement.destroyed() {
+                let manned_color = cvars.r_emplacement_manned_color.into();
+                draw_circle_lines(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    radius,
+                    1.0,
+                    manned_color,
+                );
+            }
+            // No turret texture of their own, they borrow the tank's.
+            let color = if emplacement.destroyed() {
+                let brightness = cvars.r_wreck_turret_brightness as f32;
+                Color::new(brightness, brightness, brightness, 1.0)
+            } else {
+                WHITE
+            };
+            render_tex_offset_ex(
+                assets.turret(VehicleType::Tank),
+                scr_pos,
+                emplacement.angle + emplacement.turret_angle_current,
+                cvars.g_vehicle_turret_offset_turret(VehicleType::Tank),
+                1.0,
+                color,
+            );
+        }
+
         // Draw chassis
-        for (_, vehicle) in &gs.vehicles {
-            let scr_pos = vehicle.pos + camera_offset;
+        for (handle, vehicle) in &gs.vehicles {
+            if vehicle.sunk() {
+                continue;
+            }
+            let (pos, angle) = vehicle_transform(handle);
+            let scr_pos = pos + camera_offset;
             if cull(scr_pos) {
                 continue;
             }
             let img = if vehicle.destroyed() {
-                &assets.texs_wrecks[vehicle.veh_type as usize]
+                assets.wreck(vehicle.veh_type)
             } else {
-                &assets.texs_vehicles[vehicle.veh_type as usize * 2]
+                assets.chassis(vehicle.veh_type)
             };
-            render_tex_center(img, scr_pos, vehicle.angle);
+            let (scale, alpha) = vehicle_look(cvars, vehicle, gs.game_time);
+            if scale != 1.0 || alpha != 1.0 {
+                let color = Color::new(1.0, 1.0, 1.0, alpha);
+                render_tex_offset_ex(img, scr_pos, angle, Vec2f::zero(), scale, color);
+            } else {
+                render_tex_center(img, scr_pos, angle);
+            }
             // LATER draw hitboxes
             // if cvars.d_draw && cvars.d_draw_hitboxes {
             //     client.context.set_stroke_style(&"yellow".into());
@@ -277,36 +644,244 @@ impl Client {
         // LATER Draw cow
 
         // Draw turrets
-        for (_, vehicle) in &gs.vehicles {
-            if vehicle.destroyed() {
+        for (handle, vehicle) in &gs.vehicles {
+            if vehicle.sunk() || (vehicle.destroyed() && !cvars.r_wreck_turret) {
                 continue;
             }
 
-            let vehicle_scr_pos = vehicle.pos + camera_offset;
+            let (pos, angle) = vehicle_transform(handle);
+            let vehicle_scr_pos = pos + camera_offset;
             if cull(vehicle_scr_pos) {
                 continue;
             }
 
-            let img = &assets.texs_vehicles[vehicle.veh_type as usize * 2 + 1];
+            let (img, color) = if !vehicle.destroyed() {
+                (assets.turret(vehicle.veh_type), WHITE)
+            } else if let Some(img) = assets.wreck_turret(vehicle.veh_type) {
+                (img, WHITE)
+            } else {
+                let brightness = cvars.r_wreck_turret_brightness as f32;
+                (
+                    assets.turret(vehicle.veh_type),
+                    Color::new(brightness, brightness, brightness, 1.0),
+                )
+            };
+            let (scale, alpha) = vehicle_look(cvars, vehicle, gs.game_time);
+            let color = Color { a: alpha, ..color };
             let offset_chassis =
-                vehicle.angle.to_mat2f() * cvars.g_vehicle_turret_offset_chassis(vehicle.veh_type);
+                angle.to_mat2f() * cvars.g_vehicle_turret_offset_chassis(vehicle.veh_type) * scale;
             let turret_scr_pos = vehicle_scr_pos + offset_chassis;
             let offset_turret = cvars.g_vehicle_turret_offset_turret(vehicle.veh_type);
-            render_tex_offset(
+            let (_, turret_angle) = vehicle.interpolated_angles(tick_fraction);
+            render_tex_offset_ex(
                 img,
                 turret_scr_pos,
-                vehicle.angle + vehicle.turret_angle_current,
+                angle + turret_angle,
                 offset_turret,
+                scale,
+                color,
+            );
+        }
+
+        // Draw spawn rings, above the vehicles they're closing in on
+        if cvars.r_spawn_effect == SpawnEffect::Ring {
+            for (handle, vehicle) in &gs.vehicles {
+                let progress = vehicle.spawn_progress(cvars, gs.game_time);
+                if progress >= 1.0 || vehicle.destroyed() {
+                    continue;
+                }
+                let scr_pos = vehicle_transform(handle).0 + camera_offset;
+                if cull(scr_pos) {
+                    continue;
+                }
+                let radius = cvars.r_spawn_effect_ring_radius * (1.0 - progress);
+                let rgb = cvars.r_spawn_effect_ring_color;
+                draw_circle_lines(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    radius as f32,
+                    cvars.r_spawn_effect_ring_thickness as f32,
+                    Color::new(rgb.x, rgb.y, rgb.z, progress as f32),
+                );
+            }
+        }
+
+        // Draw scraping sparks
+        for spark in &cg.sparks {
+            let scr_pos = spark.pos_at(gs.game_time) + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            let progress = (gs.game_time - spark.start_time) / cvars.cl_scrape_sparks_duration;
+            let fade = (1.0 - progress).clamped(0.0, 1.0);
+            let scr_tail = scr_pos - spark.vel * 0.02;
+            render_line(
+                scr_pos,
+                scr_tail,
+                1.0,
+                with_alpha(palette.scrape_spark, fade as f32),
             );
         }
 
+        // Draw launch effects, boost exhaust, track scrub and impacts
+        for particle in cg.particles.iter() {
+            let scr_pos = particle.drifted_pos(gs.game_time) + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            let fade = 1.0 - particle.progress(gs.game_time);
+            let radius = particle.size_at(gs.game_time) / 2.0;
+            let color = match particle.kind {
+                ParticleKind::Backblast => with_alpha(palette.particle_backblast, fade as f32),
+                ParticleKind::LaunchSmoke => {
+                    with_alpha(palette.particle_launch_smoke, (fade * 0.6) as f32)
+                }
+                ParticleKind::BoostExhaust => {
+                    with_alpha(palette.particle_boost_exhaust, fade as f32)
+                }
+                ParticleKind::ImpactSpark => with_alpha(palette.particle_impact_spark, fade as f32),
+                ParticleKind::ImpactDust => {
+                    with_alpha(palette.particle_impact_dust, (fade * 0.7) as f32)
+                }
+                ParticleKind::ImpactSplash | ParticleKind::SplashRing => {
+                    with_alpha(palette.particle_splash, (fade * 0.8) as f32)
+                }
+                ParticleKind::TrackScrub => {
+                    with_alpha(palette.particle_track_scrub, (fade * 0.7) as f32)
+                }
+            };
+            if particle.kind == ParticleKind::SplashRing {
+                draw_circle_lines(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    radius as f32,
+                    1.5,
+                    color,
+                );
+            } else {
+                draw_circle(scr_pos.x as f32, scr_pos.y as f32, radius as f32, color);
+            }
+        }
+
+        // Range hints for learning the game
+        if let Some(player_vehicle) = alive_vehicle.filter(|_| cvars.hud_range_hints) {
+            let color = with_alpha(palette.hud_range_hints, cvars.hud_range_hints_alpha as f32);
+            let (shot_angle, shot_origin) =
+                weapons::shot_angle_origin(cvars, player_vehicle, player.cur_weapon);
+            match player.cur_weapon {
+                Weapon::Bfg => {
+                    // Where a new ball starts and the ones already flying.
+                    let friendly_bfgs = weapon_projectiles(Weapon::Bfg)
+                        .filter(|(_, bfg)| {
+                            bfg.owner == local_player_handle
+                                || (player.team.is_some() && bfg.team == player.team)
+                        })
+                        .map(|(handle, _)| projectile_pos(handle));
+                    for pos in std::iter::once(shot_origin).chain(friendly_bfgs) {
+                        let scr_pos = pos + camera_offset;
+                        draw_circle_lines(
+                            scr_pos.x as f32,
+                            scr_pos.y as f32,
+                            cvars.g_bfg_beam_range as f32,
+                            1.0,
+                            color,
+                        );
+                    }
+                }
+                Weapon::Cb => {
+                    let landing =
+                        weapons::cb_landing(cvars, shot_angle, shot_origin, player_vehicle.vel);
+                    let scr_mean = landing.mean + camera_offset;
+                    let forward = landing.angle.to_vec2f()
+                        * landing.sigma_forward
+                        * cvars.hud_range_hints_sigmas;
+                    let sideways = landing.angle.to_vec2f().rotated_z(PI / 2.0)
+                        * landing.sigma_sideways
+                        * cvars.hud_range_hints_sigmas;
+                    let segments = 32;
+                    let point = |i: i32| {
+                        let t = i as f64 / segments as f64 * 2.0 * PI;
+                        scr_mean + forward * t.cos() + sideways * t.sin()
+                    };
+                    for i in 0..segments {
+                        render_line(point(i), point(i + 1), 1.0, color);
+                    }
+                    draw_circle(scr_mean.x as f32, scr_mean.y as f32, 2.0, color);
+                }
+                _ => {}
+            }
+        }
+
+        // Draw crosshair
+        // Above vehicles but below explosions and walls, like everything else in the world.
+        let crosshair = cvars.hud_crosshair && player.guided_missile.is_none();
+        if let Some(player_vehicle) = alive_vehicle.filter(|_| crosshair) {
+            let (shot_angle, _) =
+                weapons::shot_angle_origin(cvars, player_vehicle, player.cur_weapon);
+            let pos = player_vehicle.pos + shot_angle.to_vec2f() * cvars.hud_crosshair_distance;
+            let scr_pos = pos + camera_offset;
+            let color: Color = cvars.hud_crosshair_color.into();
+            let size = cvars.hud_crosshair_size;
+            let thickness = cvars.hud_crosshair_thickness;
+            match cvars.hud_crosshair_shape {
+                CrosshairShape::Cross => {
+                    render_line(
+                        scr_pos - v!(size, 0),
+                        scr_pos + v!(size, 0),
+                        thickness,
+                        color,
+                    );
+                    render_line(
+                        scr_pos - v!(0, size),
+                        scr_pos + v!(0, size),
+                        thickness,
+                        color,
+                    );
+                }
+                CrosshairShape::Circle => {
+                    draw_circle_lines(
+                        scr_pos.x as f32,
+                        scr_pos.y as f32,
+                        size as f32,
+                        thickness as f32,
+                        color,
+                    );
+                }
+                CrosshairShape::Dot => {
+                    draw_circle(scr_pos.x as f32, scr_pos.y as f32, size as f32 / 2.0, color);
+                }
+            }
+        }
+
+        // Draw guided missile prediction
+        // Where it'll hit if the player lets go - a faint line and a marker.
+        if let Some(gm) = player
+            .guided_missile
+            .filter(|_| cvars.r_missile_prediction)
+            .and_then(|gm_handle| gs.projectiles.get(gm_handle))
+        {
+            // Variable tickrate doesn't have a fixed dt but this is close enough.
+            let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+            let impact =
+                weapons::predict_gm_impact(cvars, map, gm, dt, cvars.r_missile_prediction_distance);
+            let scr_gm = gm.pos + camera_offset;
+            let scr_impact = impact + camera_offset;
+            let color = palette.missile_prediction;
+            render_line(scr_gm, scr_impact, 1.0, with_alpha(color, 0.25));
+            draw_circle_lines(scr_impact.x as f32, scr_impact.y as f32, 4.0, 1.0, color);
+        }
+
         // Draw explosions
-        let iter: Box<dyn Iterator<Item = &Explosion>> = if cvars.r_explosions_reverse_order {
-            Box::new(cg.explosions.iter().rev())
-        } else {
-            Box::new(cg.explosions.iter())
-        };
-        for explosion in iter {
+        let mut explosions: Vec<_> = cg.explosions.iter_alive(gs.game_time).collect();
+        explosions.sort_by(|(a, _), (b, _)| a.draw_order(b, cvars.r_explosions_reverse_order));
+        if cvars.r_explosions_group_by_sheet {
+            // Fewer texture switches during big CB barrages.
+            // Stable sort so the order within each sheet stays the same.
+            explosions.sort_by_key(|(explosion, _)| explosion.bfg);
+        }
+        let offsets_normal = explosion_source_offsets(false);
+        let offsets_cyan = explosion_source_offsets(true);
+        for (explosion, progress) in explosions {
             let scr_pos = explosion.pos + camera_offset;
             if cull(scr_pos) {
                 continue;
@@ -321,80 +896,163 @@ impl Client {
             //
             // This code produces similar results,
             // though it might display a single sprite for 4 frames slightly more often.
-            let progress = (gs.game_time - explosion.start_time) / cvars.r_explosion_duration;
-            // 13 sprites in the sheet, 100x100 pixels per sprite
-            let frame = (progress * 13.0).floor();
+
+            // Tinting the sprite itself would need a shader, a ring under it is cheaper.
+            if let Some(color) = team_ring_color(cvars, explosion.team) {
+                let radius = EXPLOSION_SPRITE_SIZE / 2.0 * explosion.scale;
+                draw_circle_lines(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    radius as f32,
+                    2.0,
+                    color,
+                );
+            }
+
+            if self.cg.effects_budget.explosion_uses_lod(explosion.scale) {
+                let color = if explosion.bfg {
+                    palette.explosion_lod_bfg
+                } else {
+                    palette.explosion_lod
+                };
+                let radius = EXPLOSION_SPRITE_SIZE / 2.0 * explosion.scale * progress;
+                draw_circle(scr_pos.x as f32, scr_pos.y as f32, radius as f32, color);
+                continue;
+            }
+
+            let frame = explosion_frame(progress);
             let (offset, img);
             if explosion.bfg {
-                offset = (12.0 - frame) * 100.0;
+                offset = offsets_cyan[frame];
                 img = &assets.tex_explosion_cyan;
             } else {
-                offset = frame * 100.0;
+                offset = offsets_normal[frame];
                 img = &assets.tex_explosion;
             };
+            let size = EXPLOSION_SPRITE_SIZE * explosion.scale;
             draw_texture_ex(
                 img,
-                (scr_pos.x - 50.0 * explosion.scale) as f32,
-                (scr_pos.y - 50.0 * explosion.scale) as f32,
+                (scr_pos.x - size / 2.0) as f32,
+                (scr_pos.y - size / 2.0) as f32,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(Vec2::new(
-                        100.0 * explosion.scale as f32,
-                        100.0 * explosion.scale as f32,
+                    dest_size: Some(Vec2::new(size as f32, size as f32)),
+                    source: Some(Rect::new(
+                        offset,
+                        0.0,
+                        EXPLOSION_SPRITE_SIZE as f32,
+                        EXPLOSION_SPRITE_SIZE as f32,
                     )),
-                    source: Some(Rect::new(offset as f32, 0.0, 100.0, 100.0)),
                     ..Default::default()
                 },
             );
         }
 
+        // Draw flares
+        // Each is a hot core with a few sparks where it was recently, all fading with age.
+        for (_, flare) in gs.flares.iter() {
+            let age = gs.game_time - flare.start_time;
+            let fade = (1.0 - age / cvars.g_flare_duration).clamped(0.0, 1.0);
+            for i in (0..=cvars.r_flare_trail).rev() {
+                let t = i as f64 * cvars.r_flare_trail_interval;
+                if t > age {
+                    continue;
+                }
+                let scr_pos = flare.pos_at(gs.game_time - t) + camera_offset;
+                let trail = 1.0 - i as f64 / (cvars.r_flare_trail + 1) as f64;
+                let color = with_alpha(palette.flare, (fade * trail) as f32);
+                draw_circle(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    (cvars.r_flare_radius * trail) as f32,
+                    color,
+                );
+            }
+        }
+
+        // Draw smoke screens
+        // Above explosions so they actually hide what's going on behind them.
+        for smoke in &gs.smokes {
+            let scr_pos = smoke.pos + camera_offset;
+            let progress = (gs.game_time - smoke.start_time) / cvars.g_smoke_duration;
+            let alpha = cvars.r_smoke_alpha * (1.0 - progress).clamped(0.0, 1.0);
+            let color = with_alpha(palette.smoke, alpha as f32);
+            let puff_radius = smoke.radius * 0.6;
+            for (x, y) in SMOKE_PUFFS {
+                let puff_pos = scr_pos + Vec2f::new(x, y) * smoke.radius;
+                draw_circle(
+                    puff_pos.x as f32,
+                    puff_pos.y as f32,
+                    puff_radius as f32,
+                    color,
+                );
+            }
+        }
+
         // Draw walls
         // They are above explosions and turrets, just like in RecWar.
-        let mut r = top_left_index.y;
-        let mut y = -bg_offset.y;
-        while y < view_size.y {
-            let mut c = top_left_index.x;
-            let mut x = -bg_offset.x;
-            while x < view_size.x {
-                let tile = map.col_row(c, r);
+        for r in visible_tiles.rows.clone() {
+            for c in visible_tiles.cols.clone() {
+                let Some(tile) = map.col_row_checked(c, r) else {
+                    continue;
+                };
 
                 if map.surface_of(tile).kind == SurfaceKind::Wall {
-                    let img = &assets.texs_tiles[tile.surface_index];
-                    render_tile(img, view_pos.x + x, view_pos.y + y, tile.angle);
+                    let scr_pos = tile_scr_pos(c, r);
+                    let img = assets.tile(tile.surface_index);
+                    render_tile(img, scr_pos.x, scr_pos.y, tile.angle);
                 }
-
-                c += 1;
-                x += TILE_SIZE;
             }
-            r += 1;
-            y += TILE_SIZE;
+        }
+
+        // Draw the zone's edge, above walls so it's visible everywhere
+        if let Some(zone) = &gs.zone {
+            let (center, radius) = zone.at(gs.game_time);
+            let scr_center = center + camera_offset;
+            let color: Color = cvars.r_zone_color.into();
+            let color = Color {
+                a: cvars.r_zone_alpha as f32,
+                ..color
+            };
+            draw_circle_lines(
+                scr_center.x as f32,
+                scr_center.y as f32,
+                radius as f32,
+                cvars.r_zone_thickness as f32,
+                color,
+            );
         }
 
         // Draw cluster bombs
         // LATER what about shadows (in general)? Should they stack?
         if cvars.r_cluster_bombs {
-            for (_, cb) in weapon_projectiles(Weapon::Cb) {
-                let scr_pos = cb.pos + camera_offset;
+            for (handle, cb) in weapon_projectiles(Weapon::Cb) {
+                let scr_pos = projectile_pos(handle) + camera_offset;
                 if cull(scr_pos) {
                     continue;
                 }
 
-                let corner = scr_pos - cvars.cl_cluster_bomb_size / 2.0;
+                // Bigger and further from its shadow the higher it flies.
+                let scale = 1.0 + cb.height(gs.game_time) * cvars.r_projectile_arc_scale;
+                let size = cvars.cl_cluster_bomb_size * scale;
+                let corner = scr_pos - size / 2.0;
                 // Tecnically, we should draw all shadows first, then all the projectiles,
                 // but actually it barely matters and I think RecWar does it this way too.
-                draw_rectangle(
-                    (corner.x + cvars.g_cluster_bomb_shadow_x) as f32,
-                    (corner.y + cvars.g_cluster_bomb_shadow_y) as f32,
-                    cvars.cl_cluster_bomb_size as f32,
-                    cvars.cl_cluster_bomb_size as f32,
-                    Color::new(0.0, 0.0, 0.0, cvars.g_cluster_bomb_shadow_alpha as f32),
-                );
+                if self.cg.effects_budget.shadows {
+                    draw_rectangle(
+                        (corner.x + cvars.g_cluster_bomb_shadow_x * scale) as f32,
+                        (corner.y + cvars.g_cluster_bomb_shadow_y * scale) as f32,
+                        size as f32,
+                        size as f32,
+                        Color::new(0.0, 0.0, 0.0, cvars.g_cluster_bomb_shadow_alpha as f32),
+                    );
+                }
                 draw_rectangle(
                     corner.x as f32,
                     corner.y as f32,
-                    cvars.cl_cluster_bomb_size as f32,
-                    cvars.cl_cluster_bomb_size as f32,
-                    Color::new(0.0, 1.0, 1.0, 1.0),
+                    size as f32,
+                    size as f32,
+                    palette.cb,
                 );
             }
         }
@@ -403,27 +1061,54 @@ impl Client {
 
         // Names
         if cvars.hud_names {
-            for (_, vehicle) in &gs.vehicles {
-                let scr_pos = vehicle.pos + camera_offset;
+            for (handle, vehicle) in &gs.vehicles {
+                let scr_pos = vehicle_transform(handle).0 + camera_offset;
                 if cull(scr_pos) {
                     // LATER, restrict name length
                     continue;
                 }
 
-                let name = &gs.players[vehicle.owner].name;
+                // Orphaned vehicles have no name to show.
+                let Some(player) = gs.players.get(vehicle.owner) else {
+                    continue;
+                };
+                let name = &player.name;
                 let size = measure_text(name, None, cvars.hud_names_font_size as u16, 1.0);
-                render_text_with_shadow(
-                    cvars,
-                    name,
-                    scr_pos.x as f32 - size.width / 2.0,
-                    (scr_pos.y + cvars.hud_names_y) as f32,
-                    cvars.hud_names_font_size,
-                    Color::new(
+                let x = scr_pos.x as f32 - size.width / 2.0;
+                let y = (scr_pos.y + cvars.hud_names_y) as f32;
+                let identity_color = player.color.filter(|_| cvars.hud_names_colors);
+                let color = match identity_color {
+                    Some(rgb) => {
+                        let mut color = Color::from(rgb);
+                        color.a = cvars.hud_names_alpha as f32;
+                        color
+                    }
+                    None => Color::new(
                         cvars.hud_names_brightness as f32,
                         cvars.hud_names_brightness as f32,
                         cvars.hud_names_brightness as f32,
                         cvars.hud_names_alpha as f32,
                     ),
+                };
+                if let Some(rgb) = identity_color {
+                    let marker = cvars.hud_names_marker_size as f32;
+                    if marker > 0.0 {
+                        draw_rectangle(
+                            x - marker * 2.0,
+                            y - size.offset_y / 2.0 - marker / 2.0,
+                            marker,
+                            marker,
+                            rgb.into(),
+                        );
+                    }
+                }
+                render_text_with_shadow(
+                    cvars,
+                    name,
+                    x,
+                    y,
+                    cvars.hud_names_font_size,
+                    color,
                     cvars.hud_names_shadow_x,
                     cvars.hud_names_shadow_y,
                     cvars.hud_names_shadow_alpha,
@@ -445,96 +1130,223 @@ impl Client {
                     scr_pos.y as f32,
                     cvars.hud_missile_indicator_radius as f32,
                     1.0,
-                    GREEN,
+                    palette.hud_missile_indicator,
                 );
             }
             for &hm_handle in &vehicle.hms {
                 let hm = &gs.projectiles[hm_handle];
                 let dir = (hm.pos - vehicle.pos).normalized();
                 let end = scr_pos + dir * cvars.hud_missile_indicator_radius;
-                render_line(scr_pos, end, 1.0, GREEN);
+                render_line(scr_pos, end, 1.0, palette.hud_missile_indicator);
             }
         }
 
-        // Spawn location indicator
-        let alive_time = gs.game_time - player_vehicle.spawn_time;
-        if alive_time < cvars.cl_spawn_indicator_duration {
-            let vehicle_scr_pos = player_vehicle.pos + camera_offset;
-
-            // Radius here is distance from the square's center to its side.
-            let max_radius = cvars.cl_spawn_indicator_square_side_begin / 2.0;
-            let min_radius = cvars.cl_spawn_indicator_square_side_end / 2.0;
-            let fraction_complete =
-                (alive_time / cvars.cl_spawn_indicator_animation_time).clamp(0.0, 1.0) as f32;
-            let radius = (max_radius - min_radius) * (1.0 - fraction_complete) + min_radius;
-
-            // Horizontal and vertical lines pointing at the vehicle.
-            draw_line(
-                0.0,
-                vehicle_scr_pos.y as f32,
-                vehicle_scr_pos.x as f32 - min_radius,
-                vehicle_scr_pos.y as f32,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-            draw_line(
-                vehicle_scr_pos.x as f32 + min_radius,
-                vehicle_scr_pos.y as f32,
-                self.viewport_size.x as f32,
-                vehicle_scr_pos.y as f32,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-            draw_line(
-                vehicle_scr_pos.x as f32,
-                0.0,
-                vehicle_scr_pos.x as f32,
-                vehicle_scr_pos.y as f32 - min_radius,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-            draw_line(
-                vehicle_scr_pos.x as f32,
-                vehicle_scr_pos.y as f32 + min_radius,
-                vehicle_scr_pos.x as f32,
-                self.viewport_size.y as f32,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
+        // Revealed guiders - circled when on screen, an arrow at the edge points to them otherwise
+        let reveal_color = Color::from(cvars.hud_gm_reveal_color);
+        for (guider_handle, _) in gs.players.iter() {
+            let Some(pos) = weapons::revealed_guider(cvars, gs, local_player_handle, guider_handle)
+            else {
+                continue;
+            };
+            let scr_pos = pos + camera_offset;
+            if scr_pos.clamped(view_pos, view_pos + view_size) == scr_pos {
+                draw_circle_lines(
+                    scr_pos.x as f32,
+                    scr_pos.y as f32,
+                    cvars.hud_gm_reveal_radius as f32,
+                    cvars.hud_gm_reveal_thickness as f32,
+                    reveal_color,
+                );
+                continue;
+            }
+            let margin = Vec2f::broadcast(cvars.hud_gm_reveal_margin);
+            let tip = scr_pos.clamped(view_pos + margin, view_pos + view_size - margin);
+            let dir = (scr_pos - tip).normalized();
+            let length = cvars.hud_gm_reveal_arrow_length;
+            let thickness = cvars.hud_gm_reveal_thickness;
+            render_line(tip - dir * length, tip, thickness, reveal_color);
+            for side in [-1.0, 1.0] {
+                let head = dir.rotated_z(PI + side * PI / 4.0) * length / 2.0;
+                render_line(tip, tip + head, thickness, reveal_color);
+            }
+        }
+
+        // Spawn location indicator
+        if let Some(player_vehicle) = player_vehicle {
+            let alive_time = gs.game_time - player_vehicle.spawn_time;
+            if alive_time < cvars.cl_spawn_indicator_duration {
+                let vehicle_scr_pos = player_vehicle.pos + camera_offset;
+
+                // Radius here is distance from the square's center to its side.
+                let max_radius = cvars.cl_spawn_indicator_square_side_begin / 2.0;
+                let min_radius = cvars.cl_spawn_indicator_square_side_end / 2.0;
+                let fraction_complete =
+                    (alive_time / cvars.cl_spawn_indicator_animation_time).clamp(0.0, 1.0) as f32;
+                let radius = (max_radius - min_radius) * (1.0 - fraction_complete) + min_radius;
+
+                // Horizontal and vertical lines pointing at the vehicle.
+                draw_line(
+                    0.0,
+                    vehicle_scr_pos.y as f32,
+                    vehicle_scr_pos.x as f32 - min_radius,
+                    vehicle_scr_pos.y as f32,
+                    cvars.cl_spawn_indicator_thickness,
+                    palette.spawn_indicator,
+                );
+                draw_line(
+                    vehicle_scr_pos.x as f32 + min_radius,
+                    vehicle_scr_pos.y as f32,
+                    self.viewport_size.x as f32,
+                    vehicle_scr_pos.y as f32,
+                    cvars.cl_spawn_indicator_thickness,
+                    palette.spawn_indicator,
+                );
+                draw_line(
+                    vehicle_scr_pos.x as f32,
+                    0.0,
+                    vehicle_scr_pos.x as f32,
+                    vehicle_scr_pos.y as f32 - min_radius,
+                    cvars.cl_spawn_indicator_thickness,
+                    palette.spawn_indicator,
+                );
+                draw_line(
+                    vehicle_scr_pos.x as f32,
+                    vehicle_scr_pos.y as f32 + min_radius,
+                    vehicle_scr_pos.x as f32,
+                    self.viewport_size.y as f32,
+                    cvars.cl_spawn_indicator_thickness,
+                    palette.spawn_indicator,
+                );
+
+                // Square with the vehicle in the center - first shrinks, then blinks.
+                let period = cvars.cl_spawn_indicator_blinking_period;
+                let still_shrinking = alive_time < cvars.cl_spawn_indicator_animation_time; // Don't blink during the animation
+                let blinking_disabled = period == 0.0;
+                let visible = alive_time % period < period / 2.0;
+                if still_shrinking || blinking_disabled || visible {
+                    // We have to use thickness*2 here: https://github.com/not-fl3/macroquad/issues/271
+                    draw_rectangle_lines(
+                        vehicle_scr_pos.x as f32 - radius,
+                        vehicle_scr_pos.y as f32 - radius,
+                        radius * 2.0,
+                        radius * 2.0,
+                        cvars.cl_spawn_indicator_thickness * 2.0,
+                        palette.spawn_indicator,
+                    );
+                }
+            }
+        }
+
+        // Map overlay for debugging maps
+        if cvars.d_draw_map_overlay {
+            let labels = map_overlay_labels(cvars, &visible_tiles);
+            for r in visible_tiles.rows.clone() {
+                for c in visible_tiles.cols.clone() {
+                    let Some(tile) = map.col_row_checked(c, r) else {
+                        continue;
+                    };
+                    let scr_pos = tile_scr_pos(c, r);
+                    let kind = map.surface_of(tile).kind;
+                    if let Some(color) = map_overlay_color(kind) {
+                        draw_rectangle(
+                            scr_pos.x as f32,
+                            scr_pos.y as f32,
+                            TILE_SIZE as f32,
+                            TILE_SIZE as f32,
+                            color,
+                        );
+                    }
+                    draw_rectangle_lines(
+                        scr_pos.x as f32,
+                        scr_pos.y as f32,
+                        TILE_SIZE as f32,
+                        TILE_SIZE as f32,
+                        1.0,
+                        Color::new(1.0, 1.0, 1.0, 0.3),
+                    );
+                    if labels {
+                        let coords = format!("{c},{r}");
+                        draw_text(
+                            &coords,
+                            scr_pos.x as f32 + 2.0,
+                            scr_pos.y as f32 + 10.0,
+                            12.0,
+                            WHITE,
+                        );
+                        let surface =
+                            format!("{} {}", tile.surface_index, map_overlay_letter(kind));
+                        draw_text(
+                            &surface,
+                            scr_pos.x as f32 + 2.0,
+                            scr_pos.y as f32 + 20.0,
+                            12.0,
+                            WHITE,
+                        );
+                    }
+                }
+            }
 
-            // Square with the vehicle in the center - first shrinks, then blinks.
-            let period = cvars.cl_spawn_indicator_blinking_period;
-            let still_shrinking = alive_time < cvars.cl_spawn_indicator_animation_time; // Don't blink during the animation
-            let blinking_disabled = period == 0.0;
-            let visible = alive_time % period < period / 2.0;
-            if still_shrinking || blinking_disabled || visible {
-                // We have to use thickness*2 here: https://github.com/not-fl3/macroquad/issues/271
+            // Team spawns and markers on top of the tiles.
+            let tile_corner = |index: Vec2u| index.as_::<f64>() * TILE_SIZE + camera_offset;
+            for (&spawn, &team) in map.spawns().iter().zip(map.spawn_teams()) {
+                let Some(team) = team else {
+                    continue;
+                };
+                let corner = tile_corner(spawn);
                 draw_rectangle_lines(
-                    vehicle_scr_pos.x as f32 - radius,
-                    vehicle_scr_pos.y as f32 - radius,
-                    radius * 2.0,
-                    radius * 2.0,
-                    cvars.cl_spawn_indicator_thickness * 2.0,
-                    GREEN,
+                    corner.x as f32,
+                    corner.y as f32,
+                    TILE_SIZE as f32,
+                    TILE_SIZE as f32,
+                    4.0,
+                    map_overlay_team_color(team),
                 );
             }
+            for (name, index) in map.markers() {
+                let center = tile_corner(index) + TILE_SIZE / 2.0;
+                draw_circle(center.x as f32, center.y as f32, 6.0, MAGENTA);
+                if labels {
+                    draw_text(
+                        name,
+                        center.x as f32 + 8.0,
+                        center.y as f32 + 4.0,
+                        14.0,
+                        MAGENTA,
+                    );
+                }
+            }
+
+            let camera_tile = (camera_center / TILE_SIZE).floor();
+            let text = format!(
+                "top_left {:.1} {:.1}, camera tile {} {}",
+                camera_top_left.x, camera_top_left.y, camera_tile.x, camera_tile.y
+            );
+            render_text_with_shadow(
+                cvars,
+                &text,
+                view_pos.x as f32 + 10.0,
+                view_pos.y as f32 + view_size.y as f32 - 10.0,
+                16.0,
+                WHITE,
+                1.0,
+                1.0,
+                cvars.d_draw_text_shadow_alpha,
+            );
         }
 
         // Deduplicate and draw debug shapes
-        DEBUG_SHAPES.with_borrow_mut(|shapes| {
+        #[cfg(feature = "debug_overlays")]
+        DEBUG_SHAPES.with_borrow(|shapes| {
             // Sometimes debug shapes overlap and only the last one gets drawn.
             // This is especially common when both client and server wanna draw.
             // So instead, we convert everything to lines,
             // merge colors if they overlap and only then draw it.
             // This way if cl and sv shapes overlap, they end up yellow (red + green).
             let mut lines = UniqueLines::default();
-            for shape in shapes.iter_mut() {
-                if cvars.d_draw {
+            if cvars.d_draw {
+                for shape in shapes {
                     shape.to_lines(cvars, &mut lines);
                 }
-                // LATER This means debug shapes don't stay on during pause.
-                //  Use spawn time like everything else.
-                shape.time -= gs.dt;
             }
             for (_, line) in lines.0 {
                 let scr_begin = line.begin + camera_offset;
@@ -561,7 +1373,7 @@ impl Client {
             score_pos.x,
             score_pos.y,
             cvars.hud_score_font_size,
-            WHITE,
+            palette.hud_text,
             cvars.hud_score_shadow_x,
             cvars.hud_score_shadow_y,
             1.0,
@@ -613,104 +1425,272 @@ impl Client {
             ranking_pos.x,
             ranking_pos.y,
             cvars.hud_ranking_font_size,
-            WHITE,
+            palette.hud_text,
             cvars.hud_ranking_shadow_x,
             cvars.hud_ranking_shadow_y,
             1.0,
         );
 
-        // Hit points (goes from green to red)
-        // Might wanna use https://crates.io/crates/colorsys if I need more color operations.
-        // Hit points to color (poor man's HSV):
-        // 0.0 = red
-        // 0.0..0.5 -> increase green channel
-        // 0.5 = yellow
-        // 0.5..1.0 -> decrease red channel
-        // 1.0 = green
-        let player_vehicle = &gs.vehicles[player.vehicle.unwrap()];
-        let r = 1.0 - (player_vehicle.hp_fraction.clamped(0.5, 1.0) - 0.5) * 2.0;
-        let g = player_vehicle.hp_fraction.clamped(0.0, 0.5) * 2.0;
-        let rgb = Color::new(r as f32, g as f32, 0.0, 1.0);
-        let hp_pos = hud_pos(view_pos, view_size, cvars.hud_hp_x, cvars.hud_hp_y);
-        draw_rectangle(
-            hp_pos.x,
-            hp_pos.y,
-            (cvars.hud_hp_width * player_vehicle.hp_fraction) as f32,
-            cvars.hud_hp_height as f32,
-            rgb,
-        );
-        if cvars.d_draw_texts && cvars.d_draw_hud {
-            let hp_number =
-                player_vehicle.hp_fraction * cvars.g_vehicle_hp(player_vehicle.veh_type);
-            let hp_text = format!("{}", hp_number);
-            render_text_with_shadow(
+        // Spectating someone who has no vehicle leaves only the general HUD.
+        if let Some(player_vehicle) = player_vehicle {
+            // Guided missile HUD
+            // Ammo doesn't matter while guiding, speed and the way back do.
+            // LATER Fuel bar if missiles get fuel.
+            let guided_missile = player
+                .guided_missile
+                .filter(|_| cvars.hud_missile_mode)
+                .map(|gm_handle| &gs.projectiles[gm_handle]);
+            let hud_layout = HudLayout::new(cvars, view_pos, view_size);
+            let hud_data = HudData::new(
                 cvars,
-                &hp_text,
-                hp_pos.x - 25.0,
-                hp_pos.y + cvars.hud_hp_height as f32,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
+                player,
+                player_vehicle,
+                guided_missile,
+                gs.game_time,
+                cg.real_time,
             );
-        }
 
-        // Ammo
-        let ammo = player_vehicle.ammos[player.cur_weapon as usize];
-        let ammo_fraction = match ammo {
-            Ammo::Loaded(_ready_time, count) => {
-                let max = cvars.g_weapon_reload_ammo(player.cur_weapon);
-                count as f64 / max as f64
+            // Hit points (goes from green to red)
+            hud::draw_hp(cvars, &palette, &hud_layout, &hud_data);
+
+            // Turret misalignment penalty
+            if player_vehicle.movement_factor(cvars) != 1.0 {
+                let misalign_pos = hud_pos(
+                    view_pos,
+                    view_size,
+                    cvars.hud_misalign_x,
+                    cvars.hud_misalign_y,
+                );
+                draw_rectangle(
+                    misalign_pos.x,
+                    misalign_pos.y,
+                    cvars.hud_misalign_size as f32,
+                    cvars.hud_misalign_size as f32,
+                    palette.hud_misalign,
+                );
             }
-            Ammo::Reloading(start, end) => {
-                let max_diff = end - start;
-                let cur_diff = gs.game_time - start;
-                cur_diff / max_diff
+
+            // Boost - drains while boosting, fills up during the cooldown
+            if !player_vehicle.destroyed() {
+                let boost_pos = hud_pos(view_pos, view_size, cvars.hud_boost_x, cvars.hud_boost_y);
+                let size = cvars.hud_boost_size as f32;
+                let charge = player_vehicle.boost_charge(cvars, gs.game_time) as f32;
+                let color = if player_vehicle.boosting(gs.game_time) {
+                    palette.hud_boost_active
+                } else if gs.game_time >= player_vehicle.boost_ready_time {
+                    palette.hud_boost_ready
+                } else {
+                    palette.hud_boost_charging
+                };
+                draw_rectangle(
+                    boost_pos.x,
+                    boost_pos.y,
+                    size,
+                    size,
+                    Color::new(0.0, 0.0, 0.0, 0.5),
+                );
+                draw_rectangle(
+                    boost_pos.x,
+                    boost_pos.y + size * (1.0 - charge),
+                    size,
+                    size * charge,
+                    color,
+                );
             }
-        };
-        let ammo_pos = hud_pos(view_pos, view_size, cvars.hud_ammo_x, cvars.hud_ammo_y);
-        draw_rectangle(
-            ammo_pos.x,
-            ammo_pos.y,
-            (cvars.hud_ammo_width * ammo_fraction) as f32,
-            cvars.hud_ammo_height as f32,
-            YELLOW,
-        );
-        if cvars.d_draw_texts && cvars.d_draw_hud {
-            let ammo_number = match ammo {
-                Ammo::Loaded(_ready_time, count) => count,
-                Ammo::Reloading(_start, _end) => 0,
-            };
-            render_text_with_shadow(
-                cvars,
-                &ammo_number.to_string(),
-                ammo_pos.x - 25.0,
-                ammo_pos.y + cvars.hud_ammo_height as f32,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
+
+            // Ammo or missile speed
+            hud::draw_ammo(cvars, &palette, &hud_layout, &hud_data);
+            if let Some(gm) = guided_missile {
+                let (dist, angle) = missile_home(gm.pos, player_vehicle.pos);
+                let home_pos = hud_pos(
+                    view_pos,
+                    view_size,
+                    cvars.hud_missile_home_x,
+                    cvars.hud_missile_home_y,
+                );
+                let home_pos = Vec2f::new(home_pos.x as f64, home_pos.y as f64);
+                let (tail, tip) =
+                    arrow_points(home_pos, angle, cvars.hud_missile_home_arrow_length);
+                render_line(tail, tip, 2.0, palette.hud_missile);
+                draw_circle(tip.x as f32, tip.y as f32, 3.0, palette.hud_missile);
+                if cvars.d_draw_texts && cvars.d_draw_hud {
+                    render_text_with_shadow(
+                        cvars,
+                        &format!("{dist:.0}"),
+                        (home_pos.x + cvars.hud_missile_home_arrow_length) as f32,
+                        home_pos.y as f32 + 5.0,
+                        16.0,
+                        palette.hud_missile,
+                        1.0,
+                        1.0,
+                        cvars.d_draw_text_shadow_alpha,
+                    );
+                }
+            }
+
+            // Own cluster bombs in the air which alt-fire would detonate
+            if cvars.g_weapon_altfire(player.cur_weapon) == AltFire::DetonateCb
+                && cvars.d_draw_texts
+                && cvars.d_draw_hud
+            {
+                let cb_count = gs
+                    .projectiles
+                    .iter()
+                    .filter(|(_, proj)| {
+                        proj.weapon == Weapon::Cb && proj.owner == local_player_handle
+                    })
+                    .count();
+                if cb_count > 0 {
+                    let cb_pos = hud_pos(view_pos, view_size, cvars.hud_ammo_x, cvars.hud_ammo_y);
+                    render_text_with_shadow(
+                        cvars,
+                        &cb_count.to_string(),
+                        cb_pos.x + cvars.hud_ammo_width as f32 + 10.0,
+                        cb_pos.y + cvars.hud_ammo_height as f32,
+                        16.0,
+                        palette.hud_cb_count,
+                        1.0,
+                        1.0,
+                        cvars.d_draw_text_shadow_alpha,
+                    );
+                }
+            }
+
+            // Smoke cooldown
+            if cvars.g_weapon_altfire(player.cur_weapon) == AltFire::Smoke {
+                let smoke_fraction =
+                    ((gs.game_time - player.smoke_time) / cvars.g_smoke_cooldown).min(1.0);
+                let smoke_color = if smoke_fraction >= 1.0 {
+                    palette.hud_cooldown_ready
+                } else {
+                    palette.hud_cooldown_waiting
+                };
+                let smoke_pos = hud_pos(view_pos, view_size, cvars.hud_smoke_x, cvars.hud_smoke_y);
+                draw_rectangle(
+                    smoke_pos.x,
+                    smoke_pos.y,
+                    (cvars.hud_smoke_width * smoke_fraction) as f32,
+                    cvars.hud_smoke_height as f32,
+                    smoke_color,
+                );
+            }
+
+            // Flares left
+            if cvars.g_weapon_altfire(player.cur_weapon) == AltFire::Flares {
+                let flares = player_vehicle.flares;
+                let flares_pos =
+                    hud_pos(view_pos, view_size, cvars.hud_flares_x, cvars.hud_flares_y);
+                let flares_color = if flares > 0 {
+                    palette.hud_cooldown_ready
+                } else {
+                    palette.hud_cooldown_waiting
+                };
+                render_text_with_shadow(
+                    cvars,
+                    &format!("Flares: {flares}"),
+                    flares_pos.x,
+                    flares_pos.y,
+                    16.0,
+                    flares_color,
+                    1.0,
+                    1.0,
+                    cvars.d_draw_text_shadow_alpha,
+                );
+            }
+
+            // Compass and grid position for callouts, not much use while looking through a missile.
+            if guided_missile.is_none() {
+                if cvars.hud_compass {
+                    let center = hud_pos(
+                        view_pos,
+                        view_size,
+                        cvars.hud_compass_x,
+                        cvars.hud_compass_y,
+                    );
+                    let center = Vec2f::new(center.x as f64, center.y as f64);
+                    let radius = cvars.hud_compass_radius;
+                    draw_circle_lines(
+                        center.x as f32,
+                        center.y as f32,
+                        radius as f32,
+                        1.0,
+                        palette.hud_compass,
+                    );
+                    // The camera never rotates so north is always up.
+                    render_text_with_shadow(
+                        cvars,
+                        "N",
+                        center.x as f32 - 4.0,
+                        (center.y - radius) as f32 - 3.0,
+                        14.0,
+                        palette.hud_compass_north,
+                        1.0,
+                        1.0,
+                        cvars.d_draw_text_shadow_alpha,
+                    );
+                    let hull_angle = player_vehicle.angle;
+                    let turret_angle = hull_angle + player_vehicle.turret_angle_current;
+                    let hull_end = center + hull_angle.to_vec2f() * radius;
+                    render_line(center, hull_end, 2.0, palette.hud_text);
+                    render_line(
+                        center,
+                        center + turret_angle.to_vec2f() * radius * 0.7,
+                        1.0,
+                        palette.hud_compass_turret,
+                    );
+                    let hull = compass_heading(hull_angle);
+                    let turret = compass_heading(turret_angle);
+                    let text = format!(
+                        "{} {hull:03.0} / {} {turret:03.0}",
+                        compass_point(hull),
+                        compass_point(turret),
+                    );
+                    render_text_with_shadow(
+                        cvars,
+                        &text,
+                        (center.x - radius * 2.5) as f32,
+                        (center.y + radius) as f32 + 16.0,
+                        16.0,
+                        palette.hud_text,
+                        1.0,
+                        1.0,
+                        cvars.d_draw_text_shadow_alpha,
+                    );
+                }
+                if cvars.hud_grid_label {
+                    let label = map.world_pos_to_grid_label(
+                        cvars.hud_grid_label_cell_size,
+                        player_vehicle.pos,
+                    );
+                    let label_pos = hud_pos(
+                        view_pos,
+                        view_size,
+                        cvars.hud_grid_label_x,
+                        cvars.hud_grid_label_y,
+                    );
+                    render_text_with_shadow(
+                        cvars,
+                        &label,
+                        label_pos.x,
+                        label_pos.y,
+                        20.0,
+                        palette.hud_text,
+                        1.0,
+                        1.0,
+                        cvars.d_draw_text_shadow_alpha,
+                    );
+                }
+            }
+
+            // Weapon icon
+            hud::draw_weapon_icon(cvars, &palette, assets, &hud_layout, &hud_data);
+
+            // Homing missile lock warning
+            hud::draw_lock_warning(cvars, view_pos, view_size, player_vehicle, cg.real_time);
         }
 
-        // Weapon icon
-        // The original shadows were part of the image but this is good enough for now.
-        let weap_img = &assets.texs_weapon_icons[player.cur_weapon as usize];
-        let weap_icon_pos = hud_pos(
-            view_pos,
-            view_size,
-            cvars.hud_weapon_icon_x,
-            cvars.hud_weapon_icon_y,
-        ) - Vec2::new(weap_img.width(), weap_img.height()) / 2.0;
-        draw_texture(
-            weap_img,
-            weap_icon_pos.x + cvars.hud_weapon_icon_shadow_x,
-            weap_icon_pos.y + cvars.hud_weapon_icon_shadow_y,
-            Color::new(0.0, 0.0, 0.0, cvars.hud_weapon_icon_shadow_alpha as f32),
-        );
-        draw_texture(weap_img, weap_icon_pos.x, weap_icon_pos.y, WHITE);
+        // Overtime and round result
+        hud::draw_phase_banner(cvars, gs, view_pos, view_size);
 
         // Notifications
         let mut notification_y = if cvars.hud_notifications_y_from_center != 0.0 {
@@ -718,9 +1698,9 @@ impl Client {
         } else {
             cvars.hud_notifications_y_from_top
         };
-        let notification_most_recent = cg.notifications.last().map_or(0.0, |n| n.start_time);
-        for notification in cg.notifications.iter().rev() {
-            let age_current = gs.game_time - notification.start_time;
+        let notification_most_recent = view.notifications.last().map_or(0.0, |n| n.start_time);
+        for notification in view.notifications.iter().rev() {
+            let age_current = cg.real_time - notification.start_time;
             let age_grow = cvars.hud_notifications_duration_grow;
             let age_large = age_grow + cvars.hud_notifications_duration_large;
             let age_shrink = age_large + cvars.hud_notifications_duration_shrink;
@@ -778,12 +1758,16 @@ impl Client {
         }
 
         // Scoreboard
-        if player_vehicle.destroyed() {
+        if alive_vehicle.is_none() {
             let width = cvars.hud_scoreboard_width_name
                 + cvars.hud_scoreboard_width_kills
                 + cvars.hud_scoreboard_width_deaths
+                + cvars.hud_scoreboard_width_assists
                 + cvars.hud_scoreboard_width_points;
-            let height = (gs.players.len() + 1) as f32 * cvars.hud_scoreboard_line_height as f32;
+            // Only shown once the world has some kills to avoid confusion.
+            let world_kills = gs.world_score.kills;
+            let lines = gs.players.len() + 1 + usize::from(world_kills > 0);
+            let height = lines as f32 * cvars.hud_scoreboard_line_height as f32;
             let x_start = view_pos.x as f32 + (view_size.x as f32 - width) / 2.0;
             let mut x = x_start.floor();
             let mut y = view_pos.y as f32 + (view_size.y as f32 - height) / 2.0;
@@ -794,26 +1778,29 @@ impl Client {
             let sy = cvars.hud_scoreboard_shadow_y;
 
             // LATER bold header
-            render_text_with_shadow(cvars, "Name", x, y, fs, WHITE, sx, sy, 1.0);
+            render_text_with_shadow(cvars, "Name", x, y, fs, palette.hud_text, sx, sy, 1.0);
             x += cvars.hud_scoreboard_width_name;
-            render_text_with_shadow(cvars, "Kills", x, y, fs, WHITE, sx, sy, 1.0);
+            render_text_with_shadow(cvars, "Kills", x, y, fs, palette.hud_text, sx, sy, 1.0);
             x += cvars.hud_scoreboard_width_kills;
-            render_text_with_shadow(cvars, "Deaths", x, y, fs, WHITE, sx, sy, 1.0);
+            render_text_with_shadow(cvars, "Deaths", x, y, fs, palette.hud_text, sx, sy, 1.0);
             x += cvars.hud_scoreboard_width_deaths;
-            render_text_with_shadow(cvars, "Points", x, y, fs, WHITE, sx, sy, 1.0);
+            render_text_with_shadow(cvars, "Assists", x, y, fs, palette.hud_text, sx, sy, 1.0);
+            x += cvars.hud_scoreboard_width_assists;
+            render_text_with_shadow(cvars, "Points", x, y, fs, palette.hud_text, sx, sy, 1.0);
 
             y += cvars.hud_scoreboard_line_height as f32;
 
             for (player_handle, points) in player_points {
                 let color = if player_handle == local_player_handle {
-                    WHITE
+                    palette.hud_text
                 } else {
-                    Color::new(0.8, 0.8, 0.8, 1.0)
+                    palette.hud_scoreboard_others
                 };
                 let player = &gs.players[player_handle];
                 let name = &player.name;
                 let kills = &player.score.kills.to_string();
                 let deaths = &player.score.deaths.to_string();
+                let assists = &player.score.assists.to_string();
                 let points = &points.to_string();
 
                 x = x_start;
@@ -823,10 +1810,21 @@ impl Client {
                 x += cvars.hud_scoreboard_width_kills;
                 render_text_with_shadow(cvars, deaths, x, y, fs, color, sx, sy, 1.0);
                 x += cvars.hud_scoreboard_width_deaths;
+                render_text_with_shadow(cvars, assists, x, y, fs, color, sx, sy, 1.0);
+                x += cvars.hud_scoreboard_width_assists;
                 render_text_with_shadow(cvars, points, x, y, fs, color, sx, sy, 1.0);
 
                 y += cvars.hud_scoreboard_line_height as f32;
             }
+
+            if world_kills > 0 {
+                let color = palette.hud_scoreboard_others;
+                x = x_start;
+                render_text_with_shadow(cvars, "(world)", x, y, fs, color, sx, sy, 1.0);
+                x += cvars.hud_scoreboard_width_name;
+                let kills = &world_kills.to_string();
+                render_text_with_shadow(cvars, kills, x, y, fs, color, sx, sy, 1.0);
+            }
         }
 
         // Clear background around the map if it's smaller than the screen.
@@ -878,6 +1876,13 @@ impl Client {
 
         // Pause
         if cg.paused {
+            draw_rectangle(
+                view_pos.x as f32,
+                view_pos.y as f32,
+                view_size.x as f32,
+                view_size.y as f32,
+                Color::new(0.0, 0.0, 0.0, cvars.hud_pause_dim_alpha),
+            );
             let paused_size = measure_text("PAUSED", None, cvars.hud_pause_font_size as u16, 1.0);
             render_text_with_shadow(
                 cvars,
@@ -885,14 +1890,36 @@ impl Client {
                 (view_size.x as f32 - paused_size.width) / 2.0 + view_pos.x as f32,
                 (view_size.y as f32 - paused_size.height) / 2.0 + view_pos.y as f32,
                 cvars.hud_pause_font_size,
-                RED,
+                palette.hud_pause,
                 cvars.hud_pause_shadow_x,
                 cvars.hud_pause_shadow_y,
                 1.0,
             );
         }
 
+        // Possession
+        if view.possessed.is_some() {
+            let possessed_pos = hud_pos(
+                view_pos,
+                view_size,
+                cvars.hud_possessed_x,
+                cvars.hud_possessed_y,
+            );
+            render_text_with_shadow(
+                cvars,
+                &format!("Controlling {}", player.name),
+                possessed_pos.x,
+                possessed_pos.y,
+                cvars.hud_possessed_font_size,
+                palette.hud_possessed,
+                1.0,
+                1.0,
+                1.0,
+            );
+        }
+
         // Draw world debug text
+        #[cfg(feature = "debug_overlays")]
         DEBUG_TEXTS_WORLD.with_borrow(|texts| {
             if cvars.d_draw && cvars.d_draw_world_texts {
                 for text in texts.iter() {
@@ -909,7 +1936,7 @@ impl Client {
                         scr_pos.x as f32,
                         scr_pos.y as f32,
                         16.0,
-                        RED,
+                        palette.debug_text,
                         1.0,
                         1.0,
                         cvars.d_draw_text_shadow_alpha,
@@ -919,223 +1946,207 @@ impl Client {
         });
     }
 
+    /// Every player's stats for `cl_observer`, sorted by points.
+    fn render_observer_sidebar(&self, cvars: &Cvars, screen_size: Vec2f) {
+        let Client { assets, gs, cg, .. } = self;
+        let palette = Palette::new(cvars);
+
+        let mut player_points: Vec<_> = gs
+            .players
+            .iter()
+            .map(|(handle, player)| (handle, player.score.points(cvars)))
+            .collect();
+        player_points.sort_by_key(|&(_, points)| Reverse(points));
+        // Nobody leads until someone scores.
+        let leader = player_points
+            .first()
+            .filter(|&&(_, points)| points > 0)
+            .map(|&(handle, _)| handle);
+
+        let pos = hud_pos(
+            Vec2f::zero(),
+            screen_size,
+            cvars.hud_observer_x,
+            cvars.hud_observer_y,
+        );
+        let width = cvars.hud_observer_width as f32;
+        let line_height = cvars.hud_observer_line_height as f32;
+        let fs = cvars.hud_observer_font_size;
+        draw_rectangle(
+            pos.x,
+            pos.y,
+            width,
+            line_height * player_points.len() as f32,
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+
+        let mut y = pos.y;
+        for (player_handle, _) in player_points {
+            let player = &gs.players[player_handle];
+            let x = pos.x + 4.0;
+            let text_y = y + fs as f32;
+
+            if cg.views[0].camera_player() == player_handle {
+                draw_rectangle_lines(pos.x, y, width, line_height, 1.0, palette.hud_text);
+            }
+
+            // Identity color marker like above vehicles.
+            if let Some(rgb) = player.color {
+                let marker = cvars.hud_names_marker_size as f32;
+                draw_rectangle(
+                    x,
+                    text_y - fs as f32 / 2.0 - marker / 2.0,
+                    marker,
+                    marker,
+                    rgb.into(),
+                );
+            }
+            let color: Color = if Some(player_handle) == leader {
+                cvars.hud_observer_leader_color.into()
+            } else {
+                palette.hud_text
+            };
+            let text = format!(
+                "{}  {}/{}",
+                player.name, player.score.kills, player.score.deaths
+            );
+            render_text_with_shadow(cvars, &text, x + 12.0, text_y, fs, color, 1.0, 1.0, 1.0);
+
+            let vehicle = player.vehicle.and_then(|handle| gs.vehicles.get(handle));
+            if let Some(vehicle) = vehicle {
+                let weap_img = assets.weapon_icon(player.cur_weapon);
+                let icon_height = line_height - fs as f32 - 6.0;
+                let icon_width = weap_img.width() / weap_img.height() * icon_height;
+                draw_texture_ex(
+                    weap_img,
+                    pos.x + width - icon_width - 4.0,
+                    text_y + 2.0,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(Vec2::new(icon_width, icon_height)),
+                        ..Default::default()
+                    },
+                );
+
+                let hp = vehicle.hp_fraction.clamped(0.0, 1.0) as f32;
+                let hp_width = width - icon_width - 16.0;
+                let hp_height = cvars.hud_observer_hp_height as f32;
+                let hp_y = text_y + 2.0 + (icon_height - hp_height) / 2.0;
+                let background = palette.hud_observer_hp_background;
+                draw_rectangle(x, hp_y, hp_width, hp_height, background);
+                draw_rectangle(
+                    x,
+                    hp_y,
+                    hp_width * hp,
+                    hp_height,
+                    palette.hp_color(vehicle.hp_fraction),
+                );
+            }
+
+            y += line_height;
+        }
+    }
+
     fn render_shared(&self, cvars: &Cvars) {
         let screen_size = Vec2f::new(screen_width() as f64, screen_height() as f64);
+        let palette = Palette::new(cvars);
+
+        if cvars.cl_observer {
+            self.render_observer_sidebar(cvars, screen_size);
+        }
 
         // Draw FPS
         if cvars.d_fps {
-            let fps_pos = hud_pos(Vec2f::zero(), screen_size, cvars.d_fps_x, cvars.d_fps_y);
-            render_text_with_shadow(
-                cvars,
-                &format!(
-                    "client update FPS: {:.1}   gamelogic FPS: {:.1}   render FPS: {:.1}",
-                    self.update_fps.get_fps(),
-                    self.gamelogic_fps.get_fps(),
-                    self.render_fps.get_fps()
-                ),
-                fps_pos.x - 120.0, // LATER remove the offset after finding a decent font
-                fps_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
+            let text = hud::fps_text(
+                self.update_fps.get_fps(),
+                self.gamelogic_fps.get_fps(),
+                self.render_fps.get_fps(),
             );
+            hud::draw_fps(cvars, &palette, screen_size, &text);
         }
 
+        // Performance warning
+        hud::draw_perf_warning(cvars, &palette, screen_size, &self.perf_warning);
+
         // Draw server perf info
         if cvars.d_draw && cvars.d_draw_perf_server {
-            let mut perf_pos = hud_pos(
+            let timings = &self.cg.server_timings;
+            let mut lines = vec![
+                format!("last {} server frames (in ms):", cvars.d_timing_samples),
+                perf_line(
+                    "update",
+                    timings.update_durations_avg,
+                    timings.update_durations_max,
+                ),
+                perf_line(
+                    "  gamelogic",
+                    timings.gamelogic_durations_avg,
+                    timings.gamelogic_durations_max,
+                ),
+                format!("update FPS: {:.1}", timings.update_fps),
+                format!("gamelogic FPS: {:.1}", timings.gamelogic_fps),
+            ];
+            // Zero when the server is built without the history feature.
+            if timings.history_snapshot_size > 0 {
+                lines.push(format!(
+                    "history snapshot: ~{} KiB",
+                    timings.history_snapshot_size / 1024
+                ));
+            }
+            let perf_pos = hud_pos(
                 Vec2f::zero(),
                 screen_size,
                 cvars.hud_perf_server_x,
                 cvars.hud_perf_server_y,
             );
-            render_text_with_shadow(
-                cvars,
-                &format!("last {} server frames (in ms):", cvars.d_timing_samples),
-                perf_pos.x,
-                perf_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
-            perf_pos.y += 15.0;
-            let text = format!(
-                "update avg: {:.1}, max: {:.1}",
-                self.cg.server_timings.update_durations_avg * 1000.0,
-                self.cg.server_timings.update_durations_max * 1000.0
-            );
-            render_text_with_shadow(
-                cvars,
-                &text,
-                perf_pos.x,
-                perf_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
-            perf_pos.y += 15.0;
-            let text = format!(
-                "  gamelogic avg: {:.1}, max: {:.1}",
-                self.cg.server_timings.gamelogic_durations_avg * 1000.0,
-                self.cg.server_timings.gamelogic_durations_max * 1000.0
-            );
-            render_text_with_shadow(
-                cvars,
-                &text,
-                perf_pos.x,
-                perf_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
-            perf_pos.y += 15.0;
-            let text = format!("update FPS: {:.1}", self.cg.server_timings.update_fps);
-            render_text_with_shadow(
-                cvars,
-                &text,
-                perf_pos.x,
-                perf_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
-            perf_pos.y += 15.0;
-            let text = format!("gamelogic FPS: {:.1}", self.cg.server_timings.gamelogic_fps);
-            render_text_with_shadow(
-                cvars,
-                &text,
-                perf_pos.x,
-                perf_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
+            render_perf_lines(cvars, &palette, perf_pos, &lines);
         }
 
         // Draw client perf info
         if cvars.d_draw && cvars.d_draw_perf_client {
-            let mut perf_pos = hud_pos(
+            let mut lines = vec![format!(
+                "last {} client frames (in ms):",
+                cvars.d_timing_samples
+            )];
+            let durations = [
+                ("update", &self.update_durations),
+                ("  gamelogic", &self.gamelogic_durations),
+                ("render cmds", &self.draw_calls_durations),
+                ("screenshot", &self.screenshot_durations),
+                ("engine+rest", &self.engine_durations),
+            ];
+            for (label, durations) in durations {
+                if let Some((avg, max)) = durations.get_stats() {
+                    lines.push(perf_line(label, avg, max));
+                }
+            }
+            if self.capture.is_active() {
+                if let Some((avg, max)) = self.capture_durations.get_stats() {
+                    lines.push(format!(
+                        "{} ({} frames, {:.1} MB)",
+                        perf_line("capture", avg, max),
+                        self.capture.frames(),
+                        self.capture.bytes() as f64 / (1024.0 * 1024.0)
+                    ));
+                }
+            }
+            let degrades = self.cg.effects_degrade.history.len();
+            lines.push(if degrades > 0 {
+                format!(
+                    "effects: {} (auto-degraded {degrades}x)",
+                    self.cg.effects_budget.quality
+                )
+            } else {
+                format!("effects: {}", self.cg.effects_budget.quality)
+            });
+            let perf_pos = hud_pos(
                 Vec2f::zero(),
                 screen_size,
                 cvars.hud_perf_client_x,
                 cvars.hud_perf_client_y,
             );
-            render_text_with_shadow(
-                cvars,
-                &format!("last {} client frames (in ms):", cvars.d_timing_samples),
-                perf_pos.x,
-                perf_pos.y,
-                16.0,
-                RED,
-                1.0,
-                1.0,
-                cvars.d_draw_text_shadow_alpha,
-            );
-            perf_pos.y += 15.0;
-            if let Some((avg, max)) = self.update_durations.get_stats() {
-                let text = format!("update avg: {:.1}, max: {:.1}", avg * 1000.0, max * 1000.0);
-                render_text_with_shadow(
-                    cvars,
-                    &text,
-                    perf_pos.x,
-                    perf_pos.y,
-                    16.0,
-                    RED,
-                    1.0,
-                    1.0,
-                    cvars.d_draw_text_shadow_alpha,
-                );
-                perf_pos.y += 15.0;
-            }
-            if let Some((avg, max)) = self.gamelogic_durations.get_stats() {
-                let text = format!(
-                    "  gamelogic avg: {:.1}, max: {:.1}",
-                    avg * 1000.0,
-                    max * 1000.0
-                );
-                render_text_with_shadow(
-                    cvars,
-                    &text,
-                    perf_pos.x,
-                    perf_pos.y,
-                    16.0,
-                    RED,
-                    1.0,
-                    1.0,
-                    cvars.d_draw_text_shadow_alpha,
-                );
-                perf_pos.y += 15.0;
-            }
-            if let Some((avg, max)) = self.draw_calls_durations.get_stats() {
-                let text = format!(
-                    "render cmds avg: {:.1}, max: {:.1}",
-                    avg * 1000.0,
-                    max * 1000.0
-                );
-                render_text_with_shadow(
-                    cvars,
-                    &text,
-                    perf_pos.x,
-                    perf_pos.y,
-                    16.0,
-                    RED,
-                    1.0,
-                    1.0,
-                    cvars.d_draw_text_shadow_alpha,
-                );
-                perf_pos.y += 15.0;
-            }
-            if let Some((avg, max)) = self.screenshot_durations.get_stats() {
-                let text = format!(
-                    "screenshot avg: {:.1}, max: {:.1}",
-                    avg * 1000.0,
-                    max * 1000.0
-                );
-                render_text_with_shadow(
-                    cvars,
-                    &text,
-                    perf_pos.x,
-                    perf_pos.y,
-                    16.0,
-                    RED,
-                    1.0,
-                    1.0,
-                    cvars.d_draw_text_shadow_alpha,
-                );
-                perf_pos.y += 15.0;
-            }
-            if let Some((avg, max)) = self.engine_durations.get_stats() {
-                let text = format!(
-                    "engine+rest avg: {:.1}, max: {:.1}",
-                    avg * 1000.0,
-                    max * 1000.0
-                );
-                render_text_with_shadow(
-                    cvars,
-                    &text,
-                    perf_pos.x,
-                    perf_pos.y,
-                    16.0,
-                    RED,
-                    1.0,
-                    1.0,
-                    cvars.d_draw_text_shadow_alpha,
-                );
-            }
+            render_perf_lines(cvars, &palette, perf_pos, &lines);
         }
-
         // Draw last key
         if cvars.d_last_key {
             if let Some(key_code) = self.last_key {
@@ -1145,7 +2156,7 @@ impl Client {
                     300.0,
                     300.0,
                     32.0,
-                    RED,
+                    palette.debug_text,
                     1.0,
                     1.0,
                     cvars.d_draw_text_shadow_alpha,
@@ -1154,25 +2165,105 @@ impl Client {
         }
 
         // Draw debug text
-        let mut y = 25.0;
-        DEBUG_TEXTS.with_borrow(|texts| {
-            if cvars.d_draw && cvars.d_draw_texts {
+        if cvars.d_draw && cvars.d_draw_texts {
+            let mut y = 25.0;
+            let mut draw_line = |text: &str| {
+                render_text_with_shadow(
+                    cvars,
+                    text,
+                    20.0,
+                    y as f32,
+                    16.0,
+                    palette.debug_text,
+                    1.0,
+                    1.0,
+                    cvars.d_draw_text_shadow_alpha,
+                );
+                y += cvars.d_draw_texts_line_height;
+            };
+            if let Some(cursor) = self.cg.history.cursor {
+                let text = format!(
+                    "history: {}/{} (rewound)",
+                    cursor + 1,
+                    self.cg.history.snapshots
+                );
+                draw_line(&text);
+            }
+            #[cfg(feature = "debug_overlays")]
+            DEBUG_TEXTS.with_borrow(|texts| {
                 for text in texts.iter() {
-                    render_text_with_shadow(
-                        cvars,
-                        text,
-                        20.0,
-                        y as f32,
-                        16.0,
-                        RED,
-                        1.0,
-                        1.0,
-                        cvars.d_draw_text_shadow_alpha,
-                    );
-                    y += cvars.d_draw_texts_line_height;
+                    draw_line(text);
                 }
-            }
-        });
+            });
+        }
+    }
+}
+
+/// Which sprite of the sheet to show, clamped to the sheet.
+fn explosion_frame(progress: f64) -> usize {
+    let frame = (progress * EXPLOSION_FRAMES as f64).floor().max(0.0) as usize;
+    frame.min(EXPLOSION_FRAMES - 1)
+}
+
+/// X offsets of all sprites in the sheet in the order they should be shown.
+///
+/// The cyan sheet is stored backwards.
+fn explosion_source_offsets(bfg: bool) -> [f32; EXPLOSION_FRAMES] {
+    let mut offsets = [0.0; EXPLOSION_FRAMES];
+    for (frame, offset) in offsets.iter_mut().enumerate() {
+        let index = if bfg {
+            EXPLOSION_FRAMES - 1 - frame
+        } else {
+            frame
+        };
+        *offset = (index as f64 * EXPLOSION_SPRITE_SIZE) as f32;
+    }
+    offsets
+}
+
+/// Distance and angle from the guided missile back to the vehicle.
+fn missile_home(missile_pos: Vec2f, vehicle_pos: Vec2f) -> (f64, f64) {
+    let diff = vehicle_pos - missile_pos;
+    (diff.magnitude(), diff.to_angle())
+}
+
+/// Compass heading in degrees for a world angle, 0 is north (up), 90 is east.
+fn compass_heading(angle: f64) -> f64 {
+    // Angle 0 points right (east) and increases clockwise because y is down.
+    let heading = (angle.to_degrees() + 90.0).rem_euclid(360.0);
+    // Rounding might push it to exactly 360.
+    if heading >= 360.0 {
+        0.0
+    } else {
+        heading
+    }
+}
+
+/// The nearest of the 8 compass points for a heading in degrees.
+fn compass_point(heading: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    let index = (heading / 45.0).round() as usize % 8;
+    POINTS[index]
+}
+
+/// Tail and tip of an arrow centered on `center` pointing in the direction of `angle`.
+fn arrow_points(center: Vec2f, angle: f64, length: f64) -> (Vec2f, Vec2f) {
+    let half = angle.to_vec2f() * length / 2.0;
+    (center - half, center + half)
+}
+
+/// Scale and alpha of a vehicle which is sinking or materializing after spawning.
+fn vehicle_look(cvars: &Cvars, vehicle: &Vehicle, game_time: f64) -> (f64, f32) {
+    if let Some(sinking) = vehicle.sinking {
+        let progress = sinking.progress(game_time);
+        let scale = 1.0 + (cvars.r_sinking_scale - 1.0) * progress;
+        return (scale, (1.0 - progress) as f32);
+    }
+    let progress = vehicle.spawn_progress(cvars, game_time);
+    match cvars.r_spawn_effect {
+        SpawnEffect::Scale => (progress, 1.0),
+        SpawnEffect::Fade => (1.0, progress as f32),
+        SpawnEffect::None | SpawnEffect::Ring => (1.0, 1.0),
     }
 }
 
@@ -1199,13 +2290,33 @@ fn render_tex_center(img: &Texture2D, pos: Vec2f, angle: f64) {
 ///
 /// See Vec2f for more about the coord system and rotations.
 fn render_tex_offset(img: &Texture2D, pos: Vec2f, angle: f64, offset: Vec2f) {
+    render_tex_offset_scaled(img, pos, angle, offset, 1.0);
+}
+
+/// Like `render_tex_offset` but the texture and offset are scaled by `scale`.
+fn render_tex_offset_scaled(img: &Texture2D, pos: Vec2f, angle: f64, offset: Vec2f, scale: f64) {
+    render_tex_offset_ex(img, pos, angle, offset, scale, WHITE);
+}
+
+fn render_tex_offset_ex(
+    img: &Texture2D,
+    pos: Vec2f,
+    angle: f64,
+    offset: Vec2f,
+    scale: f64,
+    color: Color,
+) {
+    let offset = offset * scale;
+    let width = img.width() * scale as f32;
+    let height = img.height() * scale as f32;
     draw_texture_ex(
         img,
         // This is effectively `pos - (offset + half_size)`, just written differently.
-        (pos.x - offset.x) as f32 - img.width() / 2.0,
-        (pos.y - offset.y) as f32 - img.height() / 2.0,
-        WHITE,
+        (pos.x - offset.x) as f32 - width / 2.0,
+        (pos.y - offset.y) as f32 - height / 2.0,
+        color,
         DrawTextureParams {
+            dest_size: Some(Vec2::new(width, height)),
             rotation: angle as f32,
             pivot: Some(Vec2::new(pos.x as f32, pos.y as f32)),
             ..Default::default()
@@ -1213,6 +2324,24 @@ fn render_tex_offset(img: &Texture2D, pos: Vec2f, angle: f64, offset: Vec2f) {
     );
 }
 
+/// Fill a tile-sized area outside the map
+/// with either the configured tile or a solid color.
+fn render_out_of_bounds(cvars: &Cvars, assets: &Assets, scr_pos: Vec2f) {
+    let tile_tex = usize::try_from(cvars.r_out_of_bounds_tile)
+        .ok()
+        .and_then(|index| assets.texs_tiles.get(index));
+    match tile_tex {
+        Some(img) => render_tile(img, scr_pos.x, scr_pos.y, 0.0),
+        None => draw_rectangle(
+            scr_pos.x as f32,
+            scr_pos.y as f32,
+            TILE_SIZE as f32,
+            TILE_SIZE as f32,
+            cvars.r_out_of_bounds_color.into(),
+        ),
+    }
+}
+
 fn render_tile(img: &Texture2D, x: f64, y: f64, angle: f64) {
     draw_texture_ex(
         img,
@@ -1237,8 +2366,31 @@ fn render_line(src: Vec2f, dest: Vec2f, thickness: f64, color: Color) {
     );
 }
 
+/// Average and max of a duration in seconds, shown in ms.
+fn perf_line(label: &str, avg: f64, max: f64) -> String {
+    format!("{label} avg: {:.1}, max: {:.1}", avg * 1000.0, max * 1000.0)
+}
+
+/// The perf overlays, one line of text below another.
+fn render_perf_lines(cvars: &Cvars, palette: &Palette, mut pos: Vec2, lines: &[String]) {
+    for line in lines {
+        render_text_with_shadow(
+            cvars,
+            line,
+            pos.x,
+            pos.y,
+            16.0,
+            palette.debug_text,
+            1.0,
+            1.0,
+            cvars.d_draw_text_shadow_alpha,
+        );
+        pos.y += 15.0;
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
-fn render_text_with_shadow(
+pub fn render_text_with_shadow(
     cvars: &Cvars,
     text: &str,
     mut x: f32,
@@ -1265,14 +2417,273 @@ fn render_text_with_shadow(
     draw_text(text, x, y, font_size as f32, color);
 }
 
-/// If x or y are negative, count them from the right or bottom respectively.
-/// Useful to make HUD config cvars work for any screen/view size.
-fn hud_pos(rect_pos: Vec2f, rect_size: Vec2f, mut x: f64, mut y: f64) -> Vec2 {
-    if x < 0.0 {
-        x += rect_size.x;
+/// Labeling every tile is slow, only do it when few are visible.
+fn map_overlay_labels(cvars: &Cvars, visible_tiles: &VisibleTiles) -> bool {
+    let cols = (visible_tiles.cols.end - visible_tiles.cols.start).max(0) as usize;
+    let rows = (visible_tiles.rows.end - visible_tiles.rows.start).max(0) as usize;
+    cols * rows <= cvars.d_draw_map_overlay_labels_max
+}
+
+/// Tint of each surface kind in the map overlay, None for normal ground.
+fn map_overlay_color(kind: SurfaceKind) -> Option<Color> {
+    let (r, g, b) = match kind {
+        SurfaceKind::Normal => return None,
+        SurfaceKind::Spawn => (0.0, 1.0, 0.0),
+        SurfaceKind::Wall => (1.0, 0.0, 0.0),
+        SurfaceKind::Water => (0.0, 0.4, 1.0),
+        SurfaceKind::Snow => (1.0, 1.0, 1.0),
+        SurfaceKind::Base => (1.0, 1.0, 0.0),
+    };
+    Some(Color::new(r, g, b, 0.3))
+}
+
+fn map_overlay_letter(kind: SurfaceKind) -> char {
+    match kind {
+        SurfaceKind::Normal => 'N',
+        SurfaceKind::Spawn => 'S',
+        SurfaceKind::Wall => 'W',
+        SurfaceKind::Water => 'A',
+        SurfaceKind::Snow => 'I',
+        SurfaceKind::Base => 'B',
+    }
+}
+
+/// Spawns reserved for a team are outlined in the team's color.
+fn map_overlay_team_color(team: u32) -> Color {
+    match TEAM_COLORS.get(team as usize) {
+        Some(rgb) => Color::new(rgb.r, rgb.g, rgb.b, 1.0),
+        None => ORANGE,
+    }
+}
+
+/// The team's color if `r_team_colored_projectiles` is on and the projectile belongs to a team.
+fn team_rgb(cvars: &Cvars, team: Option<u32>) -> Option<Rgb> {
+    if !cvars.r_team_colored_projectiles {
+        return None;
+    }
+    team.and_then(|team| TEAM_COLORS.get(team as usize).copied())
+}
+
+/// The team's color with `default`'s alpha, see `team_rgb`.
+fn team_color(cvars: &Cvars, team: Option<u32>, default: Color) -> Color {
+    match team_rgb(cvars, team) {
+        Some(rgb) => Color::new(rgb.r, rgb.g, rgb.b, default.a),
+        None => default,
+    }
+}
+
+/// Color of the ring under explosions caused by a team's projectiles, see `team_rgb`.
+fn team_ring_color(cvars: &Cvars, team: Option<u32>) -> Option<Color> {
+    let rgb = team_rgb(cvars, team)?;
+    let alpha = cvars.r_team_explosion_ring_alpha as f32;
+    Some(Color::new(rgb.r, rgb.g, rgb.b, alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_overlay_labels() {
+        let cvars = Cvars {
+            d_draw_map_overlay_labels_max: 500,
+            ..Cvars::default()
+        };
+        // A normal 1:1 view
+        let visible = visible_tiles(v!(10 10), v!(1280 720));
+        assert_eq!(visible.cols.len() * visible.rows.len(), 21 * 12);
+        assert!(map_overlay_labels(&cvars, &visible));
+        // Huge window
+        let visible = visible_tiles(v!(0 0), v!(3840 2160));
+        assert!(!map_overlay_labels(&cvars, &visible));
+        // Exactly at the limit
+        let visible = visible_tiles(v!(0 0), v!(1600 1280));
+        assert_eq!(visible.cols.len() * visible.rows.len(), 500);
+        assert!(map_overlay_labels(&cvars, &visible));
+        // Empty
+        let visible = visible_tiles(v!(0 0), v!(0 0));
+        assert!(map_overlay_labels(&cvars, &visible));
+        let cvars = Cvars {
+            d_draw_map_overlay_labels_max: 0,
+            ..Cvars::default()
+        };
+        assert!(!map_overlay_labels(
+            &cvars,
+            &visible_tiles(v!(0 0), v!(64 64))
+        ));
     }
-    if y < 0.0 {
-        y += rect_size.y;
+
+    #[test]
+    fn test_map_overlay_colors() {
+        assert_eq!(map_overlay_color(SurfaceKind::Normal), None);
+        let kinds = [
+            SurfaceKind::Spawn,
+            SurfaceKind::Wall,
+            SurfaceKind::Water,
+            SurfaceKind::Snow,
+            SurfaceKind::Base,
+        ];
+        let colors: Vec<_> = kinds
+            .iter()
+            .map(|&kind| map_overlay_color(kind).unwrap())
+            .collect();
+        for (i, a) in colors.iter().enumerate() {
+            // Translucent so the tiles are still visible.
+            assert!(a.a > 0.0 && a.a < 1.0);
+            for b in &colors[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        let letters: FnvHashSet<_> = (0..6)
+            .map(|i| map_overlay_letter(SurfaceKind::from_repr(i).unwrap()))
+            .collect();
+        assert_eq!(letters.len(), 6);
+
+        assert_ne!(map_overlay_team_color(0), map_overlay_team_color(1));
+        assert_eq!(map_overlay_team_color(7), ORANGE);
+    }
+
+    #[test]
+    fn test_team_color() {
+        let off = Cvars::default();
+        let on = Cvars {
+            r_team_colored_projectiles: true,
+            ..Cvars::default()
+        };
+        let default = Color::new(1.0, 1.0, 0.0, 0.5);
+
+        assert_eq!(team_color(&off, Some(0), default), default);
+        assert_eq!(team_color(&on, None, default), default);
+        // Unknown teams fall back too.
+        assert_eq!(team_color(&on, Some(7), default), default);
+
+        let red = team_color(&on, Some(0), default);
+        let blue = team_color(&on, Some(1), default);
+        assert_ne!(red, blue);
+        assert_eq!(red, Color::new(1.0, 0.0, 0.0, 0.5));
+
+        assert_eq!(team_ring_color(&off, Some(1)), None);
+        assert_eq!(team_ring_color(&on, None), None);
+        let ring = team_ring_color(&on, Some(1)).unwrap();
+        assert_eq!((ring.r, ring.g, ring.b), (blue.r, blue.g, blue.b));
+        assert_eq!(ring.a, on.r_team_explosion_ring_alpha as f32);
+    }
+
+    #[test]
+    fn test_mg_tail_length() {
+        let cvars = Cvars {
+            r_mg_tracer_tail_time: 0.01,
+            ..Cvars::default()
+        };
+        assert_eq!(mg_tail_length(&cvars, v!(1000 0)), 10.0);
+        assert_eq!(mg_tail_length(&cvars, v!(0, -1000)), 10.0);
+        // Faster when fired from a moving vehicle.
+        assert_eq!(mg_tail_length(&cvars, v!(1200 0)), 12.0);
+        assert_eq!(mg_tail_length(&cvars, Vec2f::zero()), 0.0);
+    }
+
+    #[test]
+    fn test_mg_tracer_batches() {
+        let cvars = Cvars {
+            r_mg_tracer_segments: 4,
+            r_mg_tracer_tail_time: 0.01,
+            ..Cvars::default()
+        };
+        let bullets = [
+            (v!(100 100), v!(800 0)),
+            (v!(0 0), v!(0 1600)),
+            (v!(50 50), Vec2f::zero()),
+        ];
+        let batches = mg_tracer_batches(&cvars, &bullets);
+
+        let alphas: Vec<_> = batches.iter().map(|batch| batch.alpha).collect();
+        assert_eq!(alphas, [1.0, 0.75, 0.5, 0.25]);
+        // One segment per moving bullet in each batch.
+        for batch in &batches {
+            assert_eq!(batch.segments.len(), 2);
+        }
+
+        // The first bullet's tail is 8 px long, going back from the head.
+        assert_eq!(batches[0].segments[0], (v!(100 100), v!(98 100)));
+        assert_eq!(batches[3].segments[0], (v!(94 100), v!(92 100)));
+        // The second one is twice as fast so twice as long.
+        assert_eq!(batches[1].segments[1], (v!(0, -4), v!(0, -8)));
+    }
+
+    #[test]
+    fn test_explosion_frame() {
+        assert_eq!(explosion_frame(0.0), 0);
+        assert_eq!(explosion_frame(0.5), 6);
+        assert_eq!(explosion_frame(0.999), 12);
+        assert_eq!(explosion_frame(1.0), 12);
+        assert_eq!(explosion_frame(-0.1), 0);
+    }
+
+    #[test]
+    fn test_explosion_source_offsets() {
+        let normal = explosion_source_offsets(false);
+        assert_eq!(normal[0], 0.0);
+        assert_eq!(normal[1], 100.0);
+        assert_eq!(normal[12], 1200.0);
+
+        let cyan = explosion_source_offsets(true);
+        assert_eq!(cyan[0], 1200.0);
+        assert_eq!(cyan[12], 0.0);
+        for frame in 0..EXPLOSION_FRAMES {
+            assert_eq!(normal[frame], cyan[EXPLOSION_FRAMES - 1 - frame]);
+        }
+    }
+
+    #[test]
+    fn test_compass() {
+        // Headings near north might come out as just under 360.
+        let heading_close = |angle: f64, expected: f64| {
+            let diff = (compass_heading(angle) - expected).rem_euclid(360.0);
+            diff.min(360.0 - diff) < 1e-9
+        };
+        assert!(heading_close(0.0, 90.0));
+        assert!(heading_close(PI / 2.0, 180.0));
+        assert!(heading_close(PI, 270.0));
+        assert!(heading_close(-PI / 2.0, 0.0));
+        assert!(heading_close(3.0 * PI / 2.0, 0.0));
+        assert!(heading_close(-PI / 4.0, 45.0));
+        let heading = compass_heading(-PI / 2.0 - 1e-15);
+        assert!((0.0..360.0).contains(&heading));
+
+        assert_eq!(compass_point(0.0), "N");
+        assert_eq!(compass_point(22.0), "N");
+        assert_eq!(compass_point(23.0), "NE");
+        assert_eq!(compass_point(90.0), "E");
+        assert_eq!(compass_point(225.0), "SW");
+        assert_eq!(compass_point(337.0), "NW");
+        assert_eq!(compass_point(359.0), "N");
+    }
+
+    #[test]
+    fn test_missile_home() {
+        let (dist, angle) = missile_home(v!(100 100), v!(100 200));
+        assert_eq!(dist, 100.0);
+        assert!((angle.to_vec2f() - v!(0 1)).magnitude() < 1e-9);
+
+        let (dist, angle) = missile_home(v!(30 40), v!(0 0));
+        assert_eq!(dist, 50.0);
+        let dir = angle.to_vec2f();
+        assert!((dir - v!(-0.6, -0.8)).magnitude() < 1e-9);
+
+        let (dist, _angle) = missile_home(v!(10 10), v!(10 10));
+        assert_eq!(dist, 0.0);
+    }
+
+    #[test]
+    fn test_arrow_points() {
+        let (tail, tip) = arrow_points(v!(50 50), 0.0, 20.0);
+        assert_eq!(tail, v!(40 50));
+        assert_eq!(tip, v!(60 50));
+
+        // The arrow points the same way as the direction home.
+        let (_dist, angle) = missile_home(v!(0 0), v!(-100 0));
+        let (tail, tip) = arrow_points(v!(0 0), angle, 10.0);
+        assert!(tip.x < tail.x);
+        assert!((tip.y - tail.y).abs() < 1e-9);
     }
-    Vec2::new((rect_pos.x + x) as f32, (rect_pos.y + y) as f32)
 }
@@ -0,0 +1,230 @@
+//! Callbacks for tweaking game rules without forking the game.
+//!
+//! Implement `RuleHooks` and install it with `Server::set_rule_hooks`.
+//! Without hooks, the only cost is checking an `Option` at each hook point.
+//!
+//! Hooks run inside the simulation so their budget is a number of calls per tick
+//! (`sv_rule_hooks_max_calls`), not wall clock time
+//! which would make the outcome (and `sim_hash`) depend on how fast the machine is.
+//! Calls over the budget are skipped and the game continues as if there were no hooks.
+//!
+//! LATER on_pickup once there are pickups.
+
+use crate::prelude::*;
+
+/// Everything is optional, the default implementations don't change anything.
+pub trait RuleHooks {
+    /// The victim's vehicle was destroyed, the attacker is None if they left the game.
+    fn on_kill(&mut self, _attacker: Option<Index>, _victim: Index, _weapon: Option<Weapon>) {}
+
+    /// Change how much damage is about to be dealt, after armor.
+    ///
+    /// `weapon` is None for self-destruct. Negative values are treated as 0.
+    fn modify_damage(
+        &mut self,
+        _attacker: Index,
+        _victim: Index,
+        _weapon: Option<Weapon>,
+        amount: f64,
+    ) -> f64 {
+        amount
+    }
+}
+
+/// The installed hooks and their budget.
+pub struct RuleHookState {
+    hooks: Box<dyn RuleHooks>,
+    /// Calls during the current tick.
+    calls: u32,
+    /// Calls skipped because of the budget since the hooks were installed.
+    pub skipped: u32,
+}
+
+impl RuleHookState {
+    pub fn new(hooks: Box<dyn RuleHooks>) -> Self {
+        Self {
+            hooks,
+            calls: 0,
+            skipped: 0,
+        }
+    }
+
+    pub fn new_tick(&mut self) {
+        self.calls = 0;
+    }
+
+    /// Whether another call fits into this tick's budget.
+    fn reserve_call(&mut self, cvars: &Cvars) -> bool {
+        if self.calls >= cvars.sv_rule_hooks_max_calls {
+            if self.skipped == 0 {
+                dbg_logf!(
+                    "WARNING: rule hooks called more than {} times in a tick, skipping",
+                    cvars.sv_rule_hooks_max_calls
+                );
+            }
+            self.skipped += 1;
+            return false;
+        }
+        self.calls += 1;
+        true
+    }
+}
+
+impl Server {
+    /// Install (or with None remove) hooks which change game rules.
+    pub fn set_rule_hooks(&mut self, hooks: Option<Box<dyn RuleHooks>>) {
+        self.sg.rule_hooks = hooks.map(RuleHookState::new);
+    }
+}
+
+impl ServerFrameCtx<'_> {
+    pub fn hook_modify_damage(
+        &mut self,
+        attacker: Index,
+        victim: Index,
+        weapon: Option<Weapon>,
+        amount: f64,
+    ) -> f64 {
+        let Some(state) = &mut self.sg.rule_hooks else {
+            return amount;
+        };
+        if !state.reserve_call(self.cvars) {
+            return amount;
+        }
+        state
+            .hooks
+            .modify_damage(attacker, victim, weapon, amount)
+            .max(0.0)
+    }
+
+    pub fn hook_on_kill(&mut self, attacker: Option<Index>, victim: Index, weapon: Option<Weapon>) {
+        let Some(state) = &mut self.sg.rule_hooks else {
+            return;
+        };
+        if state.reserve_call(self.cvars) {
+            state.hooks.on_kill(attacker, victim, weapon);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    use crate::test_utils;
+
+    #[derive(Debug, Default)]
+    struct Counts {
+        damage: u32,
+        kills: Vec<(Option<Index>, Index, Option<Weapon>)>,
+    }
+
+    /// Doubles all damage and records what it saw.
+    struct DoubleDamage(Rc<RefCell<Counts>>);
+
+    impl RuleHooks for DoubleDamage {
+        fn on_kill(&mut self, attacker: Option<Index>, victim: Index, weapon: Option<Weapon>) {
+            self.0.borrow_mut().kills.push((attacker, victim, weapon));
+        }
+
+        fn modify_damage(
+            &mut self,
+            _attacker: Index,
+            _victim: Index,
+            _weapon: Option<Weapon>,
+            amount: f64,
+        ) -> f64 {
+            self.0.borrow_mut().damage += 1;
+            amount * 2.0
+        }
+    }
+
+    /// Server with an attacker and a tank, returns their player handles and the tank.
+    fn setup(cvars: &Cvars) -> (Server, Index, Index, Index) {
+        let mut server = test_utils::atrium_server(cvars);
+
+        let mut ctx = server.ctx(cvars);
+        let (attacker_handle, _) = test_utils::spawn_player(&mut ctx, "attacker");
+        let victim_handle = test_utils::add_player(&mut ctx, "victim");
+        let vehicle_handle =
+            test_utils::add_vehicle(&mut ctx, victim_handle, VehicleType::Tank, v!(500 500), 0.0);
+        (server, attacker_handle, victim_handle, vehicle_handle)
+    }
+
+    fn assert_damage_taken(server: &Server, cvars: &Cvars, vehicle_handle: Index, expected: f64) {
+        let hp = cvars.g_vehicle_hp(VehicleType::Tank);
+        let taken = (1.0 - server.gs.vehicles[vehicle_handle].hp_fraction) * hp;
+        assert!((taken - expected).abs() < 1e-9, "{taken} != {expected}");
+    }
+
+    #[test]
+    fn test_hooks() {
+        let cvars = Cvars {
+            bots_max: 0,
+            g_armor: 1000.0,
+            ..Cvars::default()
+        };
+        let (mut server, attacker, victim, vehicle_handle) = setup(&cvars);
+
+        // No hooks - unchanged.
+        server
+            .ctx(&cvars)
+            .damage(attacker, vehicle_handle, 10.0, Some(Weapon::Mg), None);
+        assert_damage_taken(&server, &cvars, vehicle_handle, 10.0);
+
+        let counts = Rc::new(RefCell::new(Counts::default()));
+        server.set_rule_hooks(Some(Box::new(DoubleDamage(Rc::clone(&counts)))));
+        server
+            .ctx(&cvars)
+            .damage(attacker, vehicle_handle, 10.0, Some(Weapon::Mg), None);
+        assert_damage_taken(&server, &cvars, vehicle_handle, 30.0);
+        assert_eq!(counts.borrow().damage, 1);
+        assert!(counts.borrow().kills.is_empty());
+
+        server
+            .ctx(&cvars)
+            .damage(attacker, vehicle_handle, 1000.0, Some(Weapon::Rail), None);
+        assert!(server.gs.vehicles[vehicle_handle].destroyed());
+        assert_eq!(counts.borrow().damage, 2);
+        assert_eq!(
+            counts.borrow().kills,
+            [(Some(attacker), victim, Some(Weapon::Rail))]
+        );
+
+        server.set_rule_hooks(None);
+        assert!(server.sg.rule_hooks.is_none());
+    }
+
+    #[test]
+    fn test_hooks_budget() {
+        let cvars = Cvars {
+            bots_max: 0,
+            g_armor: 1000.0,
+            sv_rule_hooks_max_calls: 2,
+            ..Cvars::default()
+        };
+        let (mut server, attacker, _, vehicle_handle) = setup(&cvars);
+        let counts = Rc::new(RefCell::new(Counts::default()));
+        server.set_rule_hooks(Some(Box::new(DoubleDamage(Rc::clone(&counts)))));
+
+        for _ in 0..3 {
+            server
+                .ctx(&cvars)
+                .damage(attacker, vehicle_handle, 10.0, Some(Weapon::Mg), None);
+        }
+        // The third call is over the budget so it's not doubled.
+        assert_eq!(counts.borrow().damage, 2);
+        assert_damage_taken(&server, &cvars, vehicle_handle, 50.0);
+        assert_eq!(server.sg.rule_hooks.as_ref().unwrap().skipped, 1);
+
+        // The budget is per tick.
+        test_utils::tick(&mut server, &cvars);
+        server
+            .ctx(&cvars)
+            .damage(attacker, vehicle_handle, 10.0, Some(Weapon::Mg), None);
+        assert_eq!(counts.borrow().damage, 3);
+        assert_damage_taken(&server, &cvars, vehicle_handle, 70.0);
+    }
+}
@@ -0,0 +1,102 @@
+//! Sparks and scraping sounds when vehicles slide along walls.
+//!
+//! Purely cosmetic so, like surface effects, the client spawns them
+//! from the synced `Vehicle::wall_contacts` and `Vehicle::scrape_speed`.
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct Spark {
+    pub pos: Vec2f,
+    pub vel: Vec2f,
+    pub start_time: f64,
+}
+
+impl Spark {
+    pub fn pos_at(&self, game_time: f64) -> Vec2f {
+        self.pos + self.vel * (game_time - self.start_time)
+    }
+}
+
+/// A one-shot scraping sound for the audio backend to play.
+#[derive(Debug, Clone)]
+pub struct ScrapeSound {
+    pub vehicle_handle: Index,
+    pub pos: Vec2f,
+    /// From 0 (barely scraping) to 1 (at `snd_scrape_speed_max` or faster).
+    pub intensity: f64,
+    pub start_time: f64,
+    /// Relative to the listener, filled in by `Client::take_scrape_sounds`.
+    pub pan: f64,
+    pub attenuation: f64,
+    /// How long ago it was produced, also filled in by `Client::take_scrape_sounds`.
+    /// After catch-up ticks some are already stale, the backend can skip those.
+    pub age: f64,
+}
+
+/// Whether the vehicle is sliding along a wall fast enough to make sparks and noise.
+pub fn scraping(cvars: &Cvars, vehicle: &Vehicle) -> bool {
+    !vehicle.destroyed()
+        && !vehicle.wall_contacts.is_empty()
+        && vehicle.scrape_speed >= cvars.cl_scrape_speed_min
+}
+
+impl ClientFrameCtx<'_> {
+    pub fn sys_scraping(&mut self) {
+        let rate = self.cvars.cl_scrape_sparks_rate;
+        let sparks_per_contact =
+            (self.gs.game_time * rate).floor() - (self.gs.game_time_prev * rate).floor();
+
+        for (vehicle_handle, vehicle) in self.gs.vehicles.iter() {
+            if !scraping(self.cvars, vehicle) {
+                continue;
+            }
+
+            if self.cvars.cl_scrape_sparks {
+                for contact in &vehicle.wall_contacts {
+                    let normal = contact.normal;
+                    let along = vehicle.vel - normal * vehicle.vel.dot(normal);
+                    // Thrown the way the vehicle was sliding and a bit away from the wall.
+                    // The synced velocity is already after the bounce which reverses it.
+                    let forward = -along.try_normalized().unwrap_or_default();
+                    let dir = (normal * 0.5 + forward)
+                        .try_normalized()
+                        .unwrap_or_default();
+                    let budget = &mut self.cg.effects_budget;
+                    let count = budget.take(budget.particle_count(sparks_per_contact as usize));
+                    for _ in 0..count {
+                        // Fan them out, no need for real randomness in a cosmetic effect.
+                        let fan = (self.cg.sparks.len() as f64 * 2.4).sin() * 0.5;
+                        self.cg.sparks.push(Spark {
+                            pos: contact.point,
+                            vel: dir.rotated_z(fan) * self.cvars.cl_scrape_sparks_speed,
+                            start_time: self.gs.game_time,
+                        });
+                    }
+                }
+            }
+
+            if self.cvars.snd_scrape {
+                let due = match self.cg.scrape_sound_times.get(&vehicle_handle) {
+                    Some(&time) => self.gs.game_time - time >= self.cvars.snd_scrape_interval,
+                    None => true,
+                };
+                if due {
+                    let intensity = vehicle.scrape_speed / self.cvars.snd_scrape_speed_max;
+                    self.cg.scrape_sounds.push(ScrapeSound {
+                        vehicle_handle,
+                        pos: vehicle.wall_contacts[0].point,
+                        intensity: intensity.min(1.0),
+                        start_time: self.gs.game_time,
+                        pan: 0.0,
+                        attenuation: 1.0,
+                        age: 0.0,
+                    });
+                    self.cg
+                        .scrape_sound_times
+                        .insert(vehicle_handle, self.gs.game_time);
+                }
+            }
+        }
+    }
+}
@@ -5,12 +5,22 @@
 use std::{io::ErrorKind, mem, net::TcpListener};
 
 use crate::{
-    debug::{self, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_TEXTS_WORLD},
+    ai_schedule::AiSchedule,
+    bot_identity::{self, TEAM_COLORS},
+    debug,
+    input_queue::TimedInput,
     net::{self, Connection, Listener, NetworkMessage},
+    possess,
     prelude::*,
-    BOT_NAMES,
+    rule_hooks::RuleHookState,
+    sys_ai::ai_role,
 };
 
+#[cfg(feature = "history")]
+use crate::history::History;
+#[cfg(feature = "sim_hash")]
+use crate::sim_hash;
+
 pub struct Server {
     pub map: Map,
 
@@ -37,6 +47,9 @@ pub struct ServerGame {
 
     pub paused: bool,
 
+    /// Input from the host waiting for its tick, sorted by time, see `input_queue`.
+    pub input_queue: VecDeque<TimedInput>,
+
     /// The RNG for all gamelogic
     pub rng: Xoshiro256PlusPlus,
 
@@ -44,6 +57,30 @@ pub struct ServerGame {
     pub update_durations: Durations,
     pub gamelogic_fps: Fps,
     pub gamelogic_durations: Durations,
+
+    /// Whose turn it is to make expensive AI decisions.
+    pub ai_schedule: AiSchedule,
+
+    /// Accumulated for the results screen, see `match_report`.
+    pub match_stats: MatchStats,
+
+    /// Placeholder players owning vehicles from `debug_spawn_vehicle`, see `debug_spawn`.
+    pub debug_players: FnvHashSet<Index>,
+
+    /// Modding callbacks, see `rule_hooks`.
+    pub rule_hooks: Option<RuleHookState>,
+
+    /// Recent states for rewinding, see `history`.
+    #[cfg(feature = "history")]
+    pub history: History,
+    /// Rewind requests from clients, handled outside ticks by `sys_history_commands`.
+    pub history_commands: Vec<HistoryCommand>,
+    /// Balance presets requested by clients, handled by `apply_preset_requests`.
+    pub preset_requests: Vec<String>,
+
+    /// Running hash of the simulation state after each tick, see `sim_hash`.
+    #[cfg(feature = "sim_hash")]
+    pub match_hash: u64,
 }
 
 #[derive(Debug)]
@@ -73,22 +110,48 @@ impl Server {
         listener.set_nonblocking(true).unwrap();
         dbg_logf!("Listening on {}", &cvars.sv_net_listen_addr);
 
+        Self::with_listener(cvars, map, Box::new(listener))
+    }
+
+    /// Use a custom listener instead of listening on `sv_net_listen_addr`.
+    pub fn with_listener(
+        cvars: &Cvars,
+        map: Map,
+        listener: Box<dyn Listener<ClientMessage>>,
+    ) -> Self {
         let sg = ServerGame {
-            listener: Box::new(listener),
+            listener,
             clients: Arena::new(),
             disconnected: FnvHashSet::default(),
 
             paused: false,
 
+            input_queue: VecDeque::new(),
+
             rng: Xoshiro256PlusPlus::seed_from_u64(cvars.d_seed),
 
             update_fps: Fps::new(),
             update_durations: Durations::new(),
             gamelogic_fps: Fps::new(),
             gamelogic_durations: Durations::new(),
+
+            ai_schedule: AiSchedule::new(),
+            match_stats: MatchStats::default(),
+
+            debug_players: FnvHashSet::default(),
+
+            rule_hooks: None,
+
+            #[cfg(feature = "history")]
+            history: History::default(),
+            history_commands: Vec::new(),
+            preset_requests: Vec::new(),
+
+            #[cfg(feature = "sim_hash")]
+            match_hash: 0,
         };
 
-        Self {
+        let mut server = Self {
             map,
             gs: GameState::new(),
             sg,
@@ -97,15 +160,71 @@ impl Server {
             real_time: 0.0,
             real_time_prev: 0.0,
             real_time_delta: 0.0,
-        }
+        };
+        server.gs.time_limit = if cvars.g_time_limit > 0.0 {
+            cvars.g_time_limit
+        } else {
+            f64::INFINITY
+        };
+        server.ctx(cvars).spawn_wrecks();
+        server.ctx(cvars).spawn_emplacements();
+        server.ctx(cvars).init_zone();
+        server
     }
 
     pub fn ctx<'a>(&'a mut self, cvars: &'a Cvars) -> ServerFrameCtx<'a> {
         ServerFrameCtx::new(cvars, &self.map, &mut self.gs, &mut self.sg)
     }
 
+    /// Pause or unpause the match for everyone, e.g. when a menu is opened.
+    ///
+    /// Clients pause by sending `ClientMessage::Pause` instead.
+    pub fn set_paused(&mut self, cvars: &Cvars, paused: bool) {
+        self.sg.paused = paused;
+        self.ctx(cvars).net_send_all(ServerMessage::Paused(paused));
+    }
+
+    /// Rewind or resume as requested by clients, needs `d_cheats` and the `history` feature.
+    fn sys_history_commands(&mut self, cvars: &Cvars) {
+        let commands = mem::take(&mut self.sg.history_commands);
+        if commands.is_empty() {
+            return;
+        }
+        if !cvars.d_cheats {
+            dbg_logf!("WARNING: Rewinding requires d_cheats");
+            return;
+        }
+
+        #[cfg(feature = "history")]
+        for command in commands {
+            self.history_command(cvars, command);
+        }
+        #[cfg(not(feature = "history"))]
+        dbg_logf!("WARNING: Rewinding requires the history feature");
+    }
+
+    /// Switch balance presets as requested by clients and tell everyone.
+    ///
+    /// Needs mutable cvars so it runs outside `update`, see `presets`.
+    pub fn apply_preset_requests(&mut self, cvars: &mut Cvars) {
+        for name in mem::take(&mut self.sg.preset_requests) {
+            match cvars.apply_preset(&name) {
+                Ok(()) => {
+                    dbg_logf!("Switched to preset {name}");
+                    self.ctx(cvars).net_send_all(ServerMessage::Preset(name));
+                }
+                Err(e) => dbg_logf!("WARNING: {e}"),
+            }
+        }
+    }
+
     /// Run gamelogic frame(s) up to current time (in seconds).
     pub fn update(&mut self, cvars: &Cvars, real_time: f64) {
+        self.update_with_inputs(cvars, real_time, Vec::new());
+    }
+
+    /// Like `update` but each tick only sees the `inputs` from before it, see `input_queue`.
+    pub fn update_with_inputs(&mut self, cvars: &Cvars, real_time: f64, inputs: Vec<TimedInput>) {
         // Recommended reading:
         // https://gafferongames.com/post/fix_your_timestep/
         // https://medium.com/@tglaiel/how-to-make-your-game-run-at-60fps-24c61210fe75
@@ -120,13 +239,11 @@ impl Server {
 
         // We have to also receive outside gamelogic so pausing and unpausing works.
         self.ctx(cvars).sys_net_receive(); // LATER Just receive, handle pause explicitly
+        self.sys_history_commands(cvars);
+        self.queue_inputs(inputs);
 
-        // LATER Remove explicit condition, just don't update time?
-        //  Some systems should run even when paused (e.g. receive)? Move them from tick to update?
-        if !self.sg.paused {
-            let dt_update = self.real_time_delta * cvars.d_speed;
-            self.gamelogic(cvars, dt_update);
-        }
+        // LATER Some systems should run even when paused (e.g. receive)? Move them from tick to update?
+        self.gamelogic(cvars);
 
         let end = macroquad::time::get_time();
         self.sg
@@ -135,35 +252,42 @@ impl Server {
     }
 
     /// The main game loop.
-    fn gamelogic(&mut self, cvars: &Cvars, dt_update: f64) {
+    fn gamelogic(&mut self, cvars: &Cvars) {
         // LATER Slow down time to prevent death spirals.
         // LATER Extrapolation (after client / server split).
         //  Gamecode should not know about it.
         //  Construct FrameData with the throwaway gs, gamelogic_tick_movement that only calls the movement systems?
         //  Don't accidentally call functions which modify state outside gs.
 
-        if dt_update > 5.0 {
-            dbg_logf!("WARNING: large dt_update: {dt_update}");
-        }
-
-        match cvars.sys_tickrate_mode {
-            TickrateMode::Variable => {
-                let game_time_target = self.gs.game_time + dt_update;
-                self.gamelogic_tick(cvars, game_time_target);
-            }
-            TickrateMode::Fixed => {
-                let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
-                let game_time_target = self.gs.game_time + self.game_time_carry + dt_update;
-
-                while self.gs.game_time + dt < game_time_target {
-                    self.gamelogic_tick(cvars, self.gs.game_time + dt);
-                }
+        self.run_ticks(cvars, |server, cvars, tick_real_time, game_time| {
+            server.apply_queued_inputs(tick_real_time);
+            server.gamelogic_tick(cvars, game_time);
+        });
+    }
 
-                self.game_time_carry = game_time_target - self.gs.game_time;
-                if cvars.d_tickrate_fixed_carry {
-                    dbg_logf!("Remaining time: {}", self.game_time_carry);
-                }
-            }
+    /// Call `tick` with the real and game time of each tick in this update.
+    ///
+    /// Separate from `gamelogic` so tests can run it headless.
+    pub fn run_ticks(&mut self, cvars: &Cvars, mut tick: impl FnMut(&mut Self, &Cvars, f64, f64)) {
+        let game_time = self.gs.game_time;
+        let carry = self.game_time_carry;
+        let tick_times = gamelogic_tick_times(
+            cvars,
+            game_time,
+            &mut self.game_time_carry,
+            self.real_time_delta,
+            self.sg.paused,
+        );
+        for tick_game_time in tick_times {
+            let tick_real_time = tick_real_time(
+                cvars,
+                self.real_time_prev,
+                self.real_time,
+                game_time,
+                carry,
+                tick_game_time,
+            );
+            tick(self, cvars, tick_real_time, tick_game_time);
         }
     }
 
@@ -174,6 +298,17 @@ impl Server {
             .gamelogic_fps
             .tick(cvars.d_fps_period, self.real_time);
 
+        self.simulate_tick(cvars, game_time);
+
+        let end = macroquad::time::get_time();
+        self.sg
+            .gamelogic_durations
+            .add(cvars.d_timing_samples, end - start);
+    }
+
+    /// The part of a gamelogic frame which doesn't need a macroquad context
+    /// so it can also run headless (e.g. in tests).
+    pub fn simulate_tick(&mut self, cvars: &Cvars, game_time: f64) {
         // Update time tracking variables (in seconds)
         assert!(
             game_time >= self.gs.game_time,
@@ -187,7 +322,11 @@ impl Server {
         self.gs.dt = self.gs.game_time - self.gs.game_time_prev;
         debug::set_game_time(self.gs.game_time);
 
-        debug::clear_expired();
+        debug::clear_expired(self.gs.dt);
+
+        if let Some(rule_hooks) = &mut self.sg.rule_hooks {
+            rule_hooks.new_tick();
+        }
 
         // LATER Uncomment after making server debug msgs stay until next update.
         // dbg_textf!("{}", env!("GIT_VERSION"));
@@ -199,21 +338,37 @@ impl Server {
         ctx.sys_connect_bots();
         ctx.sys_net_receive();
         ctx.sys_net_disconnect();
+        ctx.sys_ai_difficulty();
         ctx.sys_ai();
+        ctx.sys_ai_targets();
+        ctx.sys_ai_dodge();
+        ctx.sys_ai_flares();
+        ctx.sys_ai_chat();
+
+        ctx.sys_emplacement_control();
+        ctx.sys_emplacement_ai();
 
         ctx.sys_respawning();
 
         ctx.sys_player_weapon();
 
         ctx.sys_vehicle_logic();
+        ctx.sys_overheal_decay();
+        ctx.sys_boost();
 
         // It's probably a good idea to shoot before movement so that when turning
         // the shot angle corresponds to the vehicle angle the player saw last frame.
         // LATER Before turret turning too.
         ctx.sys_shooting();
+        ctx.sys_alt_fire();
+        ctx.sys_mine_laying();
+        ctx.sys_emplacements();
 
+        ctx.sys_surface_under();
         ctx.sys_vehicle_movement();
 
+        ctx.sys_smoke_cleanup();
+        ctx.sys_flare_cleanup();
         ctx.sys_hm_turning();
         ctx.sys_gm_turning();
 
@@ -221,10 +376,21 @@ impl Server {
 
         ctx.sys_projectiles_timeout();
 
+        ctx.sys_mine_triggers();
+
+        ctx.sys_sinking();
+
+        ctx.sys_zone();
+
         ctx.self_destruct();
 
+        ctx.sys_match_stats();
+        ctx.sys_match_phase();
+
         ctx.sys_debug_examples(v!(125, 300));
 
+        ctx.sys_quantize();
+
         ctx.sys_net_send_updates();
         ctx.sys_net_disconnect();
 
@@ -236,11 +402,18 @@ impl Server {
         for (_, player) in self.gs.players.iter_mut() {
             player.input_prev = player.input;
         }
+        for (_, emplacement) in self.gs.emplacements.iter_mut() {
+            emplacement.input_prev = emplacement.input;
+        }
 
-        let end = macroquad::time::get_time();
-        self.sg
-            .gamelogic_durations
-            .add(cvars.d_timing_samples, end - start);
+        #[cfg(feature = "sim_hash")]
+        {
+            let tick_hash = sim_hash::hash_state(&self.gs, &self.sg.rng);
+            self.sg.match_hash = sim_hash::accumulate(self.sg.match_hash, tick_hash);
+        }
+
+        #[cfg(feature = "history")]
+        self.history_record(cvars);
     }
 }
 
@@ -275,7 +448,7 @@ impl ServerFrameCtx<'_> {
         }
     }
 
-    fn net_send_one(&mut self, msg: ServerMessage, client_handle: Index) {
+    pub fn net_send_one(&mut self, msg: ServerMessage, client_handle: Index) {
         let net_msg = net::serialize(msg);
         let client = &mut self.sg.clients[client_handle];
         Self::net_send(
@@ -319,8 +492,10 @@ impl ServerFrameCtx<'_> {
                     let client = RemoteClient::new(conn, Index::DANGLING);
                     let client_handle = self.sg.clients.insert(client);
                     let name = "unconnected".to_owned(); // TODO?
-                    let player = Player::new(name, ClientType::Remote(client_handle));
+                    let mut player = Player::new(name, ClientType::Remote(client_handle));
+                    player.team = self.join_team();
                     let player_handle = self.gs.players.insert(player);
+                    self.gs.player_ids.assign(player_handle);
                     self.sg.clients[client_handle].player_handle = player_handle;
 
                     let index = client_handle.slot();
@@ -335,12 +510,15 @@ impl ServerFrameCtx<'_> {
                     // Send new player to everyone except the new player
                     let player_init = PlayerInit {
                         index: player_handle.slot(),
+                        id: self.gs.player_ids.id(player_handle).soft_unwrap(),
                         name: self.gs.players[player_handle].name.clone(),
                         // Currently we don't need to send score here
                         // because all fields are 0 but in the future
                         // some gamemodes might have a non-zero starting score
                         // (e.g. number of lives in survival modes).
                         score: self.gs.players[player_handle].score.clone(),
+                        color: self.gs.players[player_handle].color,
+                        team: self.gs.players[player_handle].team,
                     };
                     let msg = ServerMessage::AddPlayer(player_init);
                     self.net_send_all_except(msg, client_handle);
@@ -368,8 +546,11 @@ impl ServerFrameCtx<'_> {
             .iter()
             .map(|(handle, player)| PlayerInit {
                 index: handle.slot(),
+                id: self.gs.player_ids.id(handle).soft_unwrap(),
                 name: player.name.clone(),
                 score: player.score.clone(),
+                color: player.color,
+                team: player.team,
             })
             .collect();
 
@@ -379,6 +560,7 @@ impl ServerFrameCtx<'_> {
             .iter()
             .map(|(handle, vehicle)| VehicleInit {
                 index: handle.slot(),
+                id: self.gs.vehicle_ids.id(handle).soft_unwrap(),
                 physics: EntityPhysics {
                     pos: vehicle.pos,
                     vel: vehicle.vel,
@@ -399,6 +581,7 @@ impl ServerFrameCtx<'_> {
             .iter()
             .map(|(handle, projectile)| ProjectileInit {
                 index: handle.slot(),
+                id: self.gs.projectile_ids.id(handle).soft_unwrap(),
                 physics: EntityPhysics {
                     pos: projectile.pos,
                     vel: projectile.vel,
@@ -408,6 +591,40 @@ impl ServerFrameCtx<'_> {
                 weapon: projectile.weapon,
                 explode_time: projectile.explode_time,
                 owner: projectile.owner.slot(),
+                flight: projectile.flight,
+                spawn_time: projectile.spawn_time,
+                team: projectile.team,
+                target: match projectile.target {
+                    Some(HmTarget::Vehicle(vehicle_handle)) => Some(vehicle_handle.slot()),
+                    _ => None,
+                },
+            })
+            .collect();
+
+        let mines = self
+            .gs
+            .mines
+            .iter()
+            .map(|(handle, mine)| MineInit {
+                index: handle.slot(),
+                pos: mine.pos,
+                spawn_time: mine.spawn_time,
+                owner: mine.owner.slot(),
+                team: mine.team,
+            })
+            .collect();
+
+        let emplacements = self
+            .gs
+            .emplacements
+            .iter()
+            .map(|(handle, emplacement)| EmplacementInit {
+                index: handle.slot(),
+                pos: emplacement.pos,
+                angle: emplacement.angle,
+                weapon: emplacement.weapon,
+                turret_angle_current: emplacement.turret_angle_current,
+                hp_fraction: emplacement.hp_fraction,
             })
             .collect();
 
@@ -423,7 +640,30 @@ impl ServerFrameCtx<'_> {
             local_player2_index: None, // LATER(splitscreen)
             vehicles,
             projectiles,
+            mines,
+            emplacements,
+            wrecks: self.gs.wrecks.clone(),
+            zone: self.gs.zone,
+            phase: self.gs.phase,
+            world_score: self.gs.world_score.clone(),
+        }
+    }
+
+    /// The team a new player should join to keep them balanced, None outside team modes.
+    pub fn join_team(&self) -> Option<u32> {
+        let GameMode::Tw(_) = self.gs.game_mode else {
+            return None;
+        };
+        let mut counts = [0; TEAM_COLORS.len()];
+        for (_, player) in self.gs.players.iter() {
+            if let Some(count) = player.team.and_then(|team| counts.get_mut(team as usize)) {
+                *count += 1;
+            }
         }
+        // First team with the fewest players.
+        (0..counts.len())
+            .min_by_key(|&team| counts[team])
+            .map(|team| team as u32)
     }
 
     /// Add bot clients if necessary.
@@ -461,14 +701,24 @@ impl ServerFrameCtx<'_> {
             dbg_logf!("Tiles per bot: {}", tiles / bots_desired_float);
         }
 
-        let bots_current = self.gs.ais.len();
+        // Stand-ins for possessing players are not bots.
+        let mut ai_handles: Vec<_> = self
+            .gs
+            .ais
+            .iter()
+            .filter(|&(ai_handle, ai)| {
+                self.gs.players[ai.player].client == ClientType::Ai(ai_handle)
+            })
+            .map(|(ai_handle, _)| ai_handle)
+            .collect();
+        let bots_current = ai_handles.len();
         if bots_current > bots_desired {
             let to_remove = bots_current - bots_desired;
-            let mut ai_handles = self.gs.ais.collect_handles();
             for _ in 0..to_remove {
                 let ai_handle = ai_handles.pop().unwrap();
                 let player_handle = self.gs.ais[ai_handle].player;
                 let name = self.gs.players[player_handle].name.clone();
+                self.release_player(player_handle);
                 self.remove_player(player_handle);
                 self.gs.ais.remove(ai_handle);
                 let msg = ServerMessage::RemovePlayer {
@@ -482,17 +732,22 @@ impl ServerFrameCtx<'_> {
                 dbg_logf!("Removed bot @{index} {name:?}");
             }
         } else if bots_current < bots_desired {
-            for i in self.gs.ais.len()..bots_desired {
-                let name = if i < BOT_NAMES.len() {
-                    BOT_NAMES[i].to_owned()
-                } else {
-                    format!("Bot {}", i + 1)
+            let reserved: &[_] = match self.gs.game_mode {
+                GameMode::Tw(_) => &TEAM_COLORS,
+                _ => &[],
+            };
+            let identities = bot_identity::identities(self.cvars.d_seed, bots_desired, reserved);
+            for identity in identities.into_iter().skip(bots_current) {
+                let ai = Ai {
+                    role: ai_role(self.cvars, &mut self.sg.rng),
+                    ..Ai::new(Index::DANGLING, self.cvars.ai_difficulty)
                 };
-
-                let ai = Ai::new(Index::DANGLING);
                 let ai_handle = self.gs.ais.insert(ai);
-                let player = Player::new(name, ClientType::Ai(ai_handle));
+                let mut player = Player::new(identity.name, ClientType::Ai(ai_handle));
+                player.color = Some(identity.color);
+                player.team = self.join_team();
                 let player_handle = self.gs.players.insert(player);
+                self.gs.player_ids.assign(player_handle);
                 self.gs.ais[ai_handle].player = player_handle;
 
                 // LATER Use spawns when bot AI actually works
@@ -508,6 +763,9 @@ impl ServerFrameCtx<'_> {
     /// Receive input and commands from remote clients.
     fn sys_net_receive(&mut self) {
         let mut reply_msgs = Vec::new();
+        let mut possess_requests = Vec::new();
+        let mut history_commands = Vec::new();
+        let mut preset_requests = Vec::new();
         for (client_handle, client) in self.sg.clients.iter_mut() {
             let (msgs, closed) = client.conn.receive();
 
@@ -528,8 +786,11 @@ impl ServerFrameCtx<'_> {
                         self.gs.players[client.player_handle].name = name1;
                     }
                     ClientMessage::Input(net_input) => {
-                        let player = &mut self.gs.players[client.player_handle];
-                        player.input = net_input;
+                        // Don't let presses and releases while paused take effect after unpausing.
+                        // Unpausing itself is a separate message.
+                        if !self.sg.paused {
+                            possess::route_input(self.gs, client.player_handle, net_input);
+                        }
                     }
                     ClientMessage::Chat(_) => unimplemented!(), // LATER
                     ClientMessage::Pause => {
@@ -543,6 +804,11 @@ impl ServerFrameCtx<'_> {
                     }
                     ClientMessage::Join => unimplemented!(), // LATER
                     ClientMessage::Observe => unimplemented!(), // LATER
+                    ClientMessage::Possess(index) => {
+                        possess_requests.push((client.player_handle, index));
+                    }
+                    ClientMessage::History(command) => history_commands.push(command),
+                    ClientMessage::Preset(name) => preset_requests.push(name),
                 }
             }
 
@@ -556,6 +822,23 @@ impl ServerFrameCtx<'_> {
         for msg in reply_msgs {
             self.net_send_all(msg);
         }
+
+        self.sg.history_commands.extend(history_commands);
+        self.sg.preset_requests.extend(preset_requests);
+
+        for (player_handle, index) in possess_requests {
+            let Some(index) = index else {
+                self.unpossess(player_handle);
+                continue;
+            };
+            let possessed = match self.gs.players.slot_to_index(index) {
+                Some(target) => self.possess(player_handle, target),
+                None => false,
+            };
+            if !possessed {
+                dbg_logf!("Can't possess player {index}");
+            }
+        }
     }
 
     /// Send updates to all clients.
@@ -567,6 +850,8 @@ impl ServerFrameCtx<'_> {
             .map(|(handle, player)| InputUpdate {
                 index: handle.slot(),
                 net_input: player.input,
+                cur_weapon: player.cur_weapon,
+                guided_missile: player.guided_missile.map(|handle| handle.slot()),
             })
             .collect();
 
@@ -584,6 +869,14 @@ impl ServerFrameCtx<'_> {
                 },
                 turret_angle_current: vehicle.turret_angle_current,
                 turret_angle_wanted: vehicle.turret_angle_wanted,
+                turret_turn_rate: vehicle.turret_turn_rate,
+                wall_contacts: vehicle.wall_contacts.clone(),
+                scrape_speed: vehicle.scrape_speed,
+                weapon_ready_time: vehicle.weapon_ready_time,
+                fire_blocked_time: vehicle.fire_blocked_time,
+                boost_until: vehicle.boost_until,
+                boost_ready_time: vehicle.boost_ready_time,
+                hp_fraction: vehicle.hp_fraction,
             })
             .collect();
 
@@ -602,11 +895,22 @@ impl ServerFrameCtx<'_> {
             })
             .collect();
 
+        let emplacements = self
+            .gs
+            .emplacements
+            .iter()
+            .map(|(handle, emplacement)| EmplacementUpdate {
+                index: handle.slot(),
+                turret_angle_current: emplacement.turret_angle_current,
+                turret_angle_wanted: emplacement.turret_angle_wanted,
+                hp_fraction: emplacement.hp_fraction,
+                controller: emplacement.controller.map(|handle| handle.slot()),
+            })
+            .collect();
+
         // Send debug items, then clear everything on the server (not just expired)
         // so it doesn't get sent again next frame.
-        let debug_texts = DEBUG_TEXTS.take();
-        let debug_texts_world = DEBUG_TEXTS_WORLD.take();
-        let debug_shapes = DEBUG_SHAPES.take();
+        let (debug_texts, debug_texts_world, debug_shapes) = debug::take_overlays();
 
         let update_stats = self.sg.update_durations.get_stats().unwrap_or_default();
         let gamelogic_stats = self.sg.gamelogic_durations.get_stats().unwrap_or_default();
@@ -617,6 +921,10 @@ impl ServerFrameCtx<'_> {
             gamelogic_durations_max: gamelogic_stats.1,
             update_fps: self.sg.update_fps.get_fps(),
             gamelogic_fps: self.sg.gamelogic_fps.get_fps(),
+            #[cfg(feature = "history")]
+            history_snapshot_size: self.sg.history.snapshot_size(),
+            #[cfg(not(feature = "history"))]
+            history_snapshot_size: 0,
         };
 
         let update = Update {
@@ -627,6 +935,7 @@ impl ServerFrameCtx<'_> {
             player_inputs,
             vehicles,
             projectiles,
+            emplacements,
             debug_texts,
             debug_texts_world,
             debug_shapes,
@@ -642,6 +951,7 @@ impl ServerFrameCtx<'_> {
         for client_handle in handles {
             let player_handle = self.sg.clients[client_handle].player_handle;
             let name = self.gs.players[player_handle].name.clone();
+            self.release_player(player_handle);
             self.remove_player(player_handle);
 
             self.sg.clients.remove(client_handle);
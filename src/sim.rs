@@ -0,0 +1,266 @@
+//! The simulation without networking, rendering or a window.
+//!
+//! For embedding RecWars in other frontends, running bot matches headless, etc.
+//! It's the same server-side gamelogic the dedicated server runs,
+//! just driven by the caller instead of wall clock time and network messages.
+
+use std::cell::OnceCell;
+
+use crate::{
+    map::{CollisionGrid, RaycastHit, SurfaceGrid},
+    net::NullListener,
+    possess,
+    prelude::*,
+};
+
+pub struct Sim {
+    server: Server,
+    /// Built on first use, see `invalidate_grids`.
+    collision_grid: OnceCell<CollisionGrid>,
+    surface_grid: OnceCell<SurfaceGrid>,
+}
+
+impl Sim {
+    /// Start a new match on `map`.
+    ///
+    /// Bots join during the first tick according to `bots_max`.
+    pub fn new(cvars: &Cvars, map: Map) -> Self {
+        let server = Server::with_listener(cvars, map, Box::new(NullListener));
+        Self {
+            server,
+            collision_grid: OnceCell::new(),
+            surface_grid: OnceCell::new(),
+        }
+    }
+
+    /// Add a player controlled by the caller through `tick`'s inputs.
+    pub fn add_player(&mut self, cvars: &Cvars, name: &str) -> Index {
+        let mut ctx = self.server.ctx(cvars);
+        let mut player = Player::new(name.to_owned(), ClientType::Local);
+        player.team = ctx.join_team();
+        let player_handle = ctx.gs.players.insert(player);
+        ctx.gs.player_ids.assign(player_handle);
+        ctx.spawn_vehicle(player_handle, true);
+        player_handle
+    }
+
+    /// Advance the simulation by one tick of length `dt` (in seconds).
+    ///
+    /// `inputs` are for players added by `add_player`,
+    /// those not listed keep their previous input.
+    pub fn tick(&mut self, cvars: &Cvars, inputs: &[(Index, NetInput)], dt: f64) {
+        for &(player_handle, input) in inputs {
+            possess::route_input(&mut self.server.gs, player_handle, input);
+        }
+        let game_time = self.server.gs.game_time + dt;
+        self.server.simulate_tick(cvars, game_time);
+    }
+
+    pub fn gs(&self) -> &GameState {
+        &self.server.gs
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.server.map
+    }
+
+    pub fn match_stats(&self) -> &MatchStats {
+        &self.server.sg.match_stats
+    }
+
+    /// Per-player stats of the match so far, or of the whole match after it ended.
+    pub fn match_report(&self) -> MatchReport {
+        self.server.match_report()
+    }
+
+    /// Stop recording stats, e.g. when the caller decides the match is over.
+    pub fn end_match(&mut self) {
+        self.server.end_match();
+    }
+
+    /// Which tiles are walls, for external pathfinding and visualization.
+    ///
+    /// Cheap to call repeatedly, it's only built once.
+    pub fn collision_grid(&self) -> &CollisionGrid {
+        self.collision_grid
+            .get_or_init(|| self.server.map.collision_grid())
+    }
+
+    /// Surface kind of every tile, cached like `collision_grid`.
+    pub fn surface_grid(&self) -> &SurfaceGrid {
+        self.surface_grid
+            .get_or_init(|| self.server.map.surface_grid())
+    }
+
+    /// Throw away the cached grids.
+    ///
+    /// The map doesn't change during a match yet.
+    /// Anything which changes it (e.g. destructible terrain) has to call this.
+    pub fn invalidate_grids(&mut self) {
+        self.collision_grid.take();
+        self.surface_grid.take();
+    }
+
+    /// Line of sight check with exactly the game's semantics, see `Map::raycast`.
+    pub fn raycast(&self, begin: Vec2f, end: Vec2f) -> Option<RaycastHit> {
+        self.server.map.raycast(begin, end)
+    }
+
+    /// Current center and radius of the safe area, None unless `g_zone` is on.
+    pub fn zone(&self) -> Option<(Vec2f, f64)> {
+        let gs = &self.server.gs;
+        gs.zone.map(|zone| zone.at(gs.game_time))
+    }
+
+    /// Whether the match is still going, in overtime or over.
+    pub fn phase(&self) -> Phase {
+        self.server.gs.phase
+    }
+
+    /// See `debug_spawn` - these all need `d_cheats`.
+    pub fn debug_spawn_vehicle(
+        &mut self,
+        cvars: &Cvars,
+        pos: Vec2f,
+        angle: f64,
+        veh_type: VehicleType,
+        destroyed: bool,
+        team: Option<u32>,
+    ) -> Option<u32> {
+        let mut ctx = self.server.ctx(cvars);
+        ctx.debug_spawn_vehicle(pos, angle, veh_type, destroyed, team)
+    }
+
+    pub fn debug_spawn_projectile(
+        &mut self,
+        cvars: &Cvars,
+        weapon: Weapon,
+        pos: Vec2f,
+        vel: Vec2f,
+        owner: u32,
+    ) -> Option<u32> {
+        let mut ctx = self.server.ctx(cvars);
+        ctx.debug_spawn_projectile(weapon, pos, vel, owner)
+    }
+
+    pub fn debug_remove_at(&mut self, cvars: &Cvars, pos: Vec2f, radius: f64) -> usize {
+        let mut ctx = self.server.ctx(cvars);
+        ctx.debug_remove_at(pos, radius)
+    }
+
+    pub fn debug_set_vehicle_state(
+        &mut self,
+        cvars: &Cvars,
+        player: u32,
+        hp: f64,
+        pos: Vec2f,
+        angle: f64,
+    ) -> Option<u32> {
+        let mut ctx = self.server.ctx(cvars);
+        ctx.debug_set_vehicle_state(player, hp, pos, angle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    #[test]
+    fn test_sim_bot_match() {
+        let cvars = Cvars {
+            bots_max: 4,
+            ..Cvars::default()
+        };
+        let map = test_utils::load_map("maps/Atrium.map");
+        let mut sim = Sim::new(&cvars, map);
+        let human = sim.add_player(&cvars, "human");
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let input = NetInput {
+            up: true,
+            ..NetInput::empty()
+        };
+        for _ in 0..150 {
+            sim.tick(&cvars, &[(human, input)], dt);
+        }
+
+        assert_eq!(sim.gs().players.len(), 5);
+        assert_eq!(sim.gs().players[human].input, input);
+        assert!(sim.gs().game_time > 0.99);
+    }
+
+    #[test]
+    fn test_sim_stable_ids() {
+        let mut cvars = Cvars {
+            bots_max: 4,
+            ..Cvars::default()
+        };
+        let map = test_utils::load_map("maps/Atrium.map");
+        let mut sim = Sim::new(&cvars, map);
+        let human = sim.add_player(&cvars, "human");
+        let human_id = sim.gs().player_ids.id(human).unwrap();
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let input = NetInput {
+            fire: true,
+            ..NetInput::empty()
+        };
+        let mut projectile_ids = FnvHashSet::default();
+        for tick in 0..300 {
+            // Bots leaving and joining removes and adds players and vehicles.
+            if tick == 100 {
+                cvars.bots_max = 1;
+            } else if tick == 200 {
+                cvars.bots_max = 4;
+            }
+            sim.tick(&cvars, &[(human, input)], dt);
+
+            let gs = sim.gs();
+            assert_eq!(gs.player_ids.len(), gs.players.len());
+            assert_eq!(gs.vehicle_ids.len(), gs.vehicles.len());
+            assert_eq!(gs.projectile_ids.len(), gs.projectiles.len());
+            for (handle, _) in gs.projectiles.iter() {
+                let id = gs.projectile_ids.id(handle).unwrap();
+                assert_eq!(gs.projectile_ids.handle(id), Some(handle));
+                projectile_ids.insert(id);
+            }
+        }
+
+        let gs = sim.gs();
+        assert_eq!(gs.player_ids.id(human), Some(human_id));
+        // The bots which rejoined got new IDs even if they reused slots.
+        let max_player_id = gs
+            .players
+            .iter()
+            .map(|(handle, _)| gs.player_ids.id(handle).unwrap())
+            .max()
+            .unwrap();
+        assert!(max_player_id >= 7, "{max_player_id}");
+        assert!(projectile_ids.len() > 10);
+    }
+
+    #[test]
+    fn test_sim_grids_cached() {
+        let cvars = Cvars::default();
+        // Column 1 is a wall.
+        let map = test_utils::parse_map("0 16 0\n0 16 0\n");
+        let mut sim = Sim::new(&cvars, map);
+        assert!(sim.collision_grid.get().is_none());
+
+        let grid = sim.collision_grid();
+        assert_eq!((grid.width, grid.height), (3, 2));
+        assert_eq!(grid.walls, [0b010010]);
+        assert!(sim.collision_grid.get().is_some());
+        assert_eq!(sim.surface_grid().kinds, [0, 2, 0, 0, 2, 0]);
+
+        sim.invalidate_grids();
+        assert!(sim.collision_grid.get().is_none());
+        assert!(sim.surface_grid.get().is_none());
+        assert_eq!(sim.collision_grid().walls, [0b010010]);
+
+        let hit = sim.raycast(v!(32 32), v!(160 32)).unwrap();
+        assert_eq!(hit.tile, Some(Vec2u::new(1, 0)));
+    }
+}
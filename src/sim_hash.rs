@@ -0,0 +1,286 @@
+//! Deterministic hashing of the simulation state to catch unintended gameplay changes.
+//!
+//! After each server tick, the canonical state is hashed and folded into a running match hash.
+//! A scripted bot match with a fixed seed then has to end with a known hash.
+//! If a change to gamelogic is intentional, update `EXPECTED_MATCH_HASH` in the same commit.
+//!
+//! Canonical means:
+//! - floats are quantized so tiny differences (e.g. from reordering arithmetic) don't matter
+//! - entities are hashed in the order of their handles, not the arena's iteration order
+//!
+//! Only compiled with the `sim_hash` feature because it's not free.
+
+use std::hash::Hasher;
+
+use fnv::FnvHasher;
+
+use crate::prelude::*;
+
+/// Floats are rounded to this many steps per unit.
+///
+/// Positions are in pixels so this is way below anything visible.
+pub const QUANTIZATION: f64 = 1000.0;
+
+/// Round to a fixed precision so tiny float differences don't change the hash.
+///
+/// Also maps -0.0 to 0. Out of range values and NaN saturate, same as `as` casts.
+pub fn quantize(val: f64) -> i64 {
+    (val * QUANTIZATION).round() as i64
+}
+
+/// Sort entities by a stable ID (their handle) independently of arena iteration order.
+pub fn canonical<'a, T>(entities: impl Iterator<Item = (Index, &'a T)>) -> Vec<(Index, &'a T)>
+where
+    T: 'a,
+{
+    let mut sorted: Vec<_> = entities.collect();
+    sorted.sort_by_key(|(handle, _)| handle.to_bits());
+    sorted
+}
+
+/// Fold the hash of one tick into the running match hash.
+pub fn accumulate(match_hash: u64, tick_hash: u64) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write_u64(match_hash);
+    hasher.write_u64(tick_hash);
+    hasher.finish()
+}
+
+/// Hash everything that affects future gameplay.
+///
+/// The RNG's internal state is private so we hash the next word it'd produce instead.
+pub fn hash_state(gs: &GameState, rng: &Xoshiro256PlusPlus) -> u64 {
+    let mut h = FnvHasher::default();
+
+    h.write_usize(gs.frame_num);
+    h.write_i64(quantize(gs.game_time));
+    h.write_u64(rng.clone().next_u64());
+    h.write_i64(quantize(gs.damage_multiplier));
+
+    h.write_usize(gs.players.len());
+    h.write_usize(gs.vehicles.len());
+    h.write_usize(gs.projectiles.len());
+
+    for (handle, player) in canonical(gs.players.iter()) {
+        h.write_u64(handle.to_bits());
+        h.write_u8(player.cur_weapon as u8);
+        h.write_u64(player.vehicle.map_or(0, |v| v.to_bits()));
+        h.write_i32(player.score.kills);
+        h.write_i32(player.score.deaths);
+        h.write_i32(player.score.suicides);
+        h.write_i32(player.score.assists);
+    }
+    h.write_i32(gs.world_score.kills);
+
+    for (handle, vehicle) in canonical(gs.vehicles.iter()) {
+        h.write_u64(handle.to_bits());
+        h.write_u8(vehicle.veh_type as u8);
+        write_vec(&mut h, vehicle.pos);
+        write_vec(&mut h, vehicle.vel);
+        h.write_i64(quantize(vehicle.angle));
+        h.write_i64(quantize(vehicle.turn_rate));
+        h.write_i64(quantize(vehicle.turret_angle_current));
+        h.write_i64(quantize(vehicle.turret_angle_wanted));
+        h.write_i64(quantize(vehicle.turret_turn_rate));
+        h.write_i64(quantize(vehicle.hp_fraction));
+        h.write_i64(quantize(vehicle.weapon_ready_time));
+        h.write_u8(vehicle.buffered_fire.map_or(0, |weapon| weapon as u8 + 1));
+        h.write_i64(quantize(vehicle.fire_blocked_time));
+        h.write_u32(vehicle.flares);
+        h.write_i64(quantize(vehicle.flare_time));
+        h.write_i64(quantize(vehicle.boost_until));
+        h.write_i64(quantize(vehicle.boost_ready_time));
+        for state in &vehicle.weapon_states {
+            // The refire time is stale while reloading so it's not hashed.
+            match state.reload {
+                None => {
+                    h.write_u8(0);
+                    h.write_i64(quantize(state.next_fire_time));
+                    h.write_u32(state.rounds_loaded);
+                }
+                Some(Reload { start, end }) => {
+                    h.write_u8(1);
+                    h.write_i64(quantize(start));
+                    h.write_i64(quantize(end));
+                }
+            }
+        }
+        match vehicle.last_push {
+            None => h.write_u8(0),
+            Some(Push { attacker, time }) => {
+                h.write_u8(1);
+                h.write_u64(attacker.to_bits());
+                h.write_i64(quantize(time));
+            }
+        }
+        match vehicle.sinking {
+            None => h.write_u8(0),
+            Some(Sinking {
+                start_time,
+                end_time,
+            }) => {
+                h.write_u8(1);
+                h.write_i64(quantize(start_time));
+                h.write_i64(quantize(end_time));
+            }
+        }
+    }
+
+    for (handle, projectile) in canonical(gs.projectiles.iter()) {
+        h.write_u64(handle.to_bits());
+        h.write_u8(projectile.weapon as u8);
+        write_vec(&mut h, projectile.pos);
+        write_vec(&mut h, projectile.vel);
+        h.write_i64(quantize(projectile.angle));
+        h.write_i64(quantize(projectile.explode_time));
+        h.write_u64(projectile.owner.to_bits());
+    }
+
+    h.write_usize(gs.smokes.len());
+    for smoke in &gs.smokes {
+        write_vec(&mut h, smoke.pos);
+        h.write_i64(quantize(smoke.radius));
+        h.write_i64(quantize(smoke.start_time));
+        h.write_u64(smoke.owner.to_bits());
+    }
+
+    h.write_usize(gs.flares.len());
+    for (handle, flare) in canonical(gs.flares.iter()) {
+        h.write_u64(handle.to_bits());
+        write_vec(&mut h, flare.pos);
+        write_vec(&mut h, flare.vel);
+        h.write_i64(quantize(flare.start_time));
+        h.write_u64(flare.owner.to_bits());
+    }
+
+    h.write_usize(gs.mines.len());
+    for (handle, mine) in canonical(gs.mines.iter()) {
+        h.write_u64(handle.to_bits());
+        write_vec(&mut h, mine.pos);
+        h.write_i64(quantize(mine.spawn_time));
+        h.write_u64(mine.owner.to_bits());
+    }
+
+    h.finish()
+}
+
+fn write_vec(h: &mut FnvHasher, v: Vec2f) {
+    h.write_i64(quantize(v.x));
+    h.write_i64(quantize(v.y));
+}
+
+impl Server {
+    /// Running hash of all ticks so far.
+    pub fn match_hash(&self) -> u64 {
+        self.sg.match_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    /// Update this when changing gameplay intentionally.
+    ///
+    /// Run `cargo test --features sim_hash test_match_hash` to get the new value.
+    const EXPECTED_MATCH_HASH: u64 = 10626828946651485377;
+
+    fn run_ticks(server: &mut Server, cvars: &Cvars, ticks: usize) {
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        for _ in 0..ticks {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(cvars, game_time);
+        }
+    }
+
+    fn run_match(seed: u64, ticks: usize) -> u64 {
+        let cvars = Cvars {
+            d_seed: seed,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        run_ticks(&mut server, &cvars, ticks);
+        server.match_hash()
+    }
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(quantize(0.1 + 0.2), quantize(0.3));
+        assert_eq!(quantize(-0.0), quantize(0.0));
+        assert_eq!(quantize(1.0), 1000);
+        assert_eq!(quantize(1.0 + 1e-9), 1000);
+        assert_ne!(quantize(1.0), quantize(1.001));
+        assert_eq!(quantize(-2.5), -2500);
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+
+        let forward = canonical(arena.iter());
+        let backward = canonical(arena.iter().collect::<Vec<_>>().into_iter().rev());
+        assert_eq!(forward, backward);
+        let handles: Vec<_> = forward.iter().map(|(handle, _)| *handle).collect();
+        assert_eq!(handles, vec![a, b, c]);
+    }
+
+    #[test]
+    fn test_hash_state_stable() {
+        let cvars = Cvars::default();
+        let rng = Xoshiro256PlusPlus::seed_from_u64(42);
+
+        let build = || {
+            let mut gs = GameState::new();
+            let player = Player::new("test".to_owned(), ClientType::Local);
+            let player_handle = gs.players.insert(player);
+            let vehicle = Vehicle::new(
+                &cvars,
+                v!(100 100),
+                0.0,
+                VehicleType::Tank,
+                0.0,
+                player_handle,
+            );
+            gs.vehicles.insert(vehicle);
+            gs
+        };
+
+        let mut gs1 = build();
+        let gs2 = build();
+        assert_eq!(hash_state(&gs1, &rng), hash_state(&gs2, &rng));
+
+        let vehicle_handle = gs1.vehicles.iter().next().unwrap().0;
+
+        // Below precision
+        gs1.vehicles[vehicle_handle].pos.x += 1e-6;
+        assert_eq!(hash_state(&gs1, &rng), hash_state(&gs2, &rng));
+
+        // Above precision
+        gs1.vehicles[vehicle_handle].pos.x += 0.01;
+        assert_ne!(hash_state(&gs1, &rng), hash_state(&gs2, &rng));
+
+        // RNG state matters
+        let mut rng2 = rng.clone();
+        rng2.next_u64();
+        assert_ne!(hash_state(&gs2, &rng), hash_state(&gs2, &rng2));
+    }
+
+    #[test]
+    fn test_match_hash_deterministic() {
+        assert_eq!(run_match(7, 200), run_match(7, 200));
+    }
+
+    #[test]
+    fn test_match_hash() {
+        let hash = run_match(7, 2000);
+        assert_eq!(
+            hash, EXPECTED_MATCH_HASH,
+            "match hash changed: {hash} - if the gameplay change is intentional, update EXPECTED_MATCH_HASH"
+        );
+    }
+}
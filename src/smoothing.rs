@@ -0,0 +1,187 @@
+//! Render-side smoothing of entities whose authoritative state updates less often than we render.
+//!
+//! The client keeps the last two samples it received for each entity
+//! and draws it between them (interpolation) or, if the next sample is late,
+//! a bounded distance past the last one (extrapolation).
+//! If a sample lands too far from where we expected it (respawn, teleport),
+//! the history is dropped and the entity snaps to the new position.
+//!
+//! Only the renderer reads this, gamelogic always uses the raw entity state.
+
+use crate::prelude::*;
+
+/// Authoritative transform of an entity at a point in (client) game time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub pos: Vec2f,
+    pub angle: f64,
+    pub time: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntitySmoothing {
+    prev: Option<Sample>,
+    last: Sample,
+}
+
+impl EntitySmoothing {
+    pub fn new(sample: Sample) -> Self {
+        Self {
+            prev: None,
+            last: sample,
+        }
+    }
+
+    /// Record a new authoritative sample.
+    ///
+    /// Snaps (forgets the previous sample) if it's further than `r_smoothing_snap_distance`
+    /// from where we'd have drawn the entity at that time.
+    pub fn push(&mut self, cvars: &Cvars, sample: Sample) {
+        let (predicted, _) = self.transform(cvars, sample.time);
+        if (sample.pos - predicted).magnitude() > cvars.r_smoothing_snap_distance {
+            self.prev = None;
+        } else if sample.time > self.last.time {
+            self.prev = Some(self.last);
+        }
+        // Several samples at the same time (e.g. more updates received in one frame)
+        // just replace the last one.
+        self.last = sample;
+    }
+
+    /// Whether the entity is drawn exactly at the last sample no matter the time.
+    pub fn snapped(&self) -> bool {
+        self.prev.is_none()
+    }
+
+    /// Position and angle to draw the entity with at `time`.
+    pub fn transform(&self, cvars: &Cvars, time: f64) -> (Vec2f, f64) {
+        let Some(prev) = self.prev else {
+            return (self.last.pos, self.last.angle);
+        };
+        let interval = self.last.time - prev.time;
+        if interval <= 0.0 {
+            return (self.last.pos, self.last.angle);
+        }
+
+        // Never go back before the previous sample, only extrapolate a limited time forward.
+        let extrapolation_max = cvars.r_extrapolation_ms / 1000.0;
+        let t = (time - prev.time).clamp(0.0, interval + extrapolation_max) / interval;
+        let pos = prev.pos + (self.last.pos - prev.pos) * t;
+        let angle = prev.angle.lerp_angle(self.last.angle, t);
+        (pos, angle)
+    }
+}
+
+/// Record a sample for the entity, starting to track it if it's new.
+pub fn push_sample(
+    cvars: &Cvars,
+    smoothing: &mut FnvHashMap<Index, EntitySmoothing>,
+    handle: Index,
+    sample: Sample,
+) {
+    smoothing
+        .entry(handle)
+        .and_modify(|entity| entity.push(cvars, sample))
+        .or_insert_with(|| EntitySmoothing::new(sample));
+}
+
+/// Time at which the client renders smoothed entities.
+pub fn render_time(cvars: &Cvars, game_time: f64) -> f64 {
+    game_time - cvars.r_interpolation_delay_ms / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    fn sample(x: f64, angle: f64, time: f64) -> Sample {
+        Sample {
+            pos: Vec2f::new(x, 0.0),
+            angle,
+            time,
+        }
+    }
+
+    fn cvars() -> Cvars {
+        Cvars {
+            r_extrapolation_ms: 100.0,
+            r_smoothing_snap_distance: 50.0,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let cvars = cvars();
+        let mut smoothing = EntitySmoothing::new(sample(0.0, 0.0, 1.0));
+        assert_eq!(smoothing.transform(&cvars, 5.0), (v!(0 0), 0.0));
+
+        smoothing.push(&cvars, sample(10.0, 0.2, 1.2));
+        assert!(!smoothing.snapped());
+        let (pos, angle) = smoothing.transform(&cvars, 1.1);
+        assert!((pos.x - 5.0).abs() < 1e-9);
+        assert!((angle - 0.1).abs() < 1e-9);
+
+        // Never before the previous sample.
+        assert_eq!(smoothing.transform(&cvars, 0.5).0, v!(0 0));
+
+        // Angles go the short way around.
+        let mut smoothing = EntitySmoothing::new(sample(0.0, 2.0 * PI - 0.1, 1.0));
+        smoothing.push(&cvars, sample(0.0, 0.1, 2.0));
+        let (_, angle) = smoothing.transform(&cvars, 1.5);
+        assert!(angle.abs() < 1e-9 || (angle - 2.0 * PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_extrapolation_bounded() {
+        let cvars = cvars();
+        let mut smoothing = EntitySmoothing::new(sample(0.0, 0.0, 1.0));
+        smoothing.push(&cvars, sample(10.0, 0.0, 1.1));
+
+        // Next sample is late, keep moving at the same speed...
+        let (pos, _) = smoothing.transform(&cvars, 1.15);
+        assert!((pos.x - 15.0).abs() < 1e-9);
+        // ...but only up to r_extrapolation_ms.
+        let (pos, _) = smoothing.transform(&cvars, 1.2);
+        assert!((pos.x - 20.0).abs() < 1e-9);
+        let (pos, _) = smoothing.transform(&cvars, 10.0);
+        assert!((pos.x - 20.0).abs() < 1e-9);
+
+        // The late sample arrives where we'd expect it, smoothing continues from there.
+        smoothing.push(&cvars, sample(30.0, 0.0, 1.3));
+        assert!(!smoothing.snapped());
+        let (pos, _) = smoothing.transform(&cvars, 1.2);
+        assert!((pos.x - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_snap() {
+        let cvars = cvars();
+        let mut smoothing = EntitySmoothing::new(sample(0.0, 0.0, 1.0));
+        smoothing.push(&cvars, sample(10.0, 0.0, 1.1));
+
+        // Respawned on the other side of the map.
+        smoothing.push(&cvars, sample(1000.0, 1.0, 1.2));
+        assert!(smoothing.snapped());
+        assert_eq!(smoothing.transform(&cvars, 1.1), (v!(1000 0), 1.0));
+        assert_eq!(smoothing.transform(&cvars, 1.5), (v!(1000 0), 1.0));
+
+        // Smoothing resumes with the next sample.
+        smoothing.push(&cvars, sample(1010.0, 1.0, 1.3));
+        assert!(!smoothing.snapped());
+        let (pos, _) = smoothing.transform(&cvars, 1.25);
+        assert!((pos.x - 1005.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_same_time_replaces() {
+        let cvars = cvars();
+        let mut smoothing = EntitySmoothing::new(sample(0.0, 0.0, 1.0));
+        smoothing.push(&cvars, sample(10.0, 0.0, 2.0));
+        smoothing.push(&cvars, sample(20.0, 0.0, 2.0));
+        let (pos, _) = smoothing.transform(&cvars, 1.5);
+        assert!((pos.x - 10.0).abs() < 1e-9);
+    }
+}
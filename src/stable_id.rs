@@ -0,0 +1,161 @@
+//! IDs which stay the same for an entity's whole life and are never reused.
+//!
+//! Arena `Index`es are fine as internal handles but their slots get reused
+//! and the generation alone doesn't say which entity came first.
+//! Reports and tools outside the game (which can't hold on to handles)
+//! need something they can use to track entities across frames.
+//!
+//! Each kind of entity (players, vehicles, projectiles) counts up from 0 separately,
+//! see `GameState::player_ids` and friends.
+//! The server assigns them and sends them to clients in the init messages.
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct StableIds {
+    next: u32,
+    /// Arena slot to the handle occupying it and its ID.
+    ///
+    /// Keyed by slot so inserting into a reused slot replaces the previous entity
+    /// even when whoever removed it didn't tell us (e.g. `Arena::insert_at_slot` on the client).
+    by_slot: FnvHashMap<u32, (Index, u32)>,
+    by_id: FnvHashMap<u32, Index>,
+}
+
+impl StableIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give a newly created entity the next ID.
+    pub fn assign(&mut self, handle: Index) -> u32 {
+        let id = self.next;
+        self.insert(handle, id);
+        id
+    }
+
+    /// Use an ID assigned somewhere else, e.g. received from the server.
+    pub fn insert(&mut self, handle: Index, id: u32) {
+        if let Some((_, old_id)) = self.by_slot.insert(handle.slot(), (handle, id)) {
+            self.by_id.remove(&old_id);
+        }
+        if let Some(old_handle) = self.by_id.insert(id, handle) {
+            if old_handle != handle {
+                self.by_slot.remove(&old_handle.slot());
+            }
+        }
+        self.next = self.next.max(id + 1);
+    }
+
+    /// Forget a removed entity. Its ID is not given out again.
+    pub fn remove(&mut self, handle: Index) -> Option<u32> {
+        let id = self.id(handle)?;
+        self.by_slot.remove(&handle.slot());
+        self.by_id.remove(&id);
+        Some(id)
+    }
+
+    pub fn id(&self, handle: Index) -> Option<u32> {
+        match self.by_slot.get(&handle.slot()) {
+            Some(&(current, id)) if current == handle => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn handle(&self, id: u32) -> Option<Index> {
+        self.by_id.get(&id).copied()
+    }
+
+    /// Number of entities which currently have an ID.
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_not_reused() {
+        let mut arena = Arena::new();
+        let mut ids = StableIds::new();
+
+        let a = arena.insert(());
+        let b = arena.insert(());
+        assert_eq!(ids.assign(a), 0);
+        assert_eq!(ids.assign(b), 1);
+
+        arena.remove(a);
+        assert_eq!(ids.remove(a), Some(0));
+        assert_eq!(ids.remove(a), None);
+
+        // Same slot, different entity.
+        let c = arena.insert(());
+        assert_eq!(c.slot(), a.slot());
+        assert_eq!(ids.assign(c), 2);
+        assert_eq!(ids.id(a), None);
+        assert_eq!(ids.id(c), Some(2));
+        assert_eq!(ids.handle(0), None);
+        assert_eq!(ids.handle(1), Some(b));
+        assert_eq!(ids.handle(2), Some(c));
+    }
+
+    #[test]
+    fn test_insert_replaces_slot() {
+        let mut server = Arena::new();
+        let mut client = Arena::new();
+        let mut ids = StableIds::new();
+
+        // The client reuses a slot without removing the old entity first.
+        let old = server.insert(());
+        let (old_client, _) = client.insert_at_slot(old.slot(), ());
+        ids.insert(old_client, 5);
+        server.remove(old);
+        let new = server.insert(());
+        let (new_client, _) = client.insert_at_slot(new.slot(), ());
+        ids.insert(new_client, 6);
+
+        assert_eq!(ids.len(), 1);
+        assert_eq!(ids.id(old_client), None);
+        assert_eq!(ids.id(new_client), Some(6));
+        assert_eq!(ids.handle(5), None);
+
+        // Received IDs are never handed out again.
+        let other = client.insert(());
+        assert_eq!(ids.assign(other), 7);
+    }
+
+    #[test]
+    fn test_no_leak() {
+        let mut arena = Arena::new();
+        let mut ids = StableIds::new();
+        let mut alive = VecDeque::new();
+        let mut last_id = None;
+        for i in 0..10_000 {
+            let handle = arena.insert(());
+            let id = ids.assign(handle);
+            assert!(last_id < Some(id));
+            last_id = Some(id);
+            alive.push_back(handle);
+
+            // Keep a few around, remove the rest in a different order than added.
+            if alive.len() > 5 {
+                let handle = if i % 2 == 0 {
+                    alive.pop_front().unwrap()
+                } else {
+                    alive.remove(2).unwrap()
+                };
+                arena.remove(handle);
+                ids.remove(handle).unwrap();
+            }
+            assert_eq!(ids.len(), arena.len());
+            assert_eq!(ids.by_slot.len(), arena.len());
+        }
+        assert_eq!(last_id, Some(9_999));
+    }
+}
@@ -0,0 +1,162 @@
+//! Marks, dust and spray left behind by vehicles depending on what they drive over.
+//!
+//! Purely visual so the client spawns them from the synced vehicle state,
+//! the server never sends them.
+//!
+//! LATER Surface damage (e.g. lava) will need the server side cache too, it's already there.
+
+use crate::{map::SurfaceFlags, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceEffectKind {
+    Marks,
+    Dust,
+    Spray,
+}
+
+impl SurfaceEffectKind {
+    pub fn duration(self, cvars: &Cvars) -> f64 {
+        match self {
+            SurfaceEffectKind::Marks => cvars.cl_surface_effects_marks_duration,
+            SurfaceEffectKind::Dust | SurfaceEffectKind::Spray => {
+                cvars.cl_surface_effects_particles_duration
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SurfaceEffect {
+    pub kind: SurfaceEffectKind,
+    pub pos: Vec2f,
+    pub angle: f64,
+    pub start_time: f64,
+}
+
+/// The surface's flags filtered down to what the vehicle actually produces right now.
+///
+/// Uses the cached `Vehicle::surface` so it must run after `sys_surface_under`.
+pub fn active_effects(cvars: &Cvars, vehicle: &Vehicle) -> SurfaceFlags {
+    if vehicle.destroyed() || vehicle.vel.magnitude() < cvars.cl_surface_effects_speed_min {
+        return SurfaceFlags::default();
+    }
+    let flags = vehicle.surface.flags;
+    SurfaceFlags {
+        // Hovercrafts don't touch the ground.
+        marks: flags.marks && vehicle.veh_type != VehicleType::Hovercraft,
+        dust: flags.dust,
+        spray: flags.spray,
+    }
+}
+
+impl ClientFrameCtx<'_> {
+    pub fn sys_surface_effects(&mut self) {
+        if !self.cvars.cl_surface_effects {
+            return;
+        }
+
+        let interval = self.cvars.cl_surface_effects_interval;
+        let prev = (self.gs.game_time_prev / interval).floor();
+        let cur = (self.gs.game_time / interval).floor();
+        if prev == cur {
+            return;
+        }
+
+        for (_, vehicle) in self.gs.vehicles.iter() {
+            let flags = active_effects(self.cvars, vehicle);
+            let kinds = [
+                (flags.marks, SurfaceEffectKind::Marks),
+                (flags.dust, SurfaceEffectKind::Dust),
+                (flags.spray, SurfaceEffectKind::Spray),
+            ];
+            for (active, kind) in kinds {
+                if active && self.cg.effects_budget.take(1) > 0 {
+                    self.cg.surface_effects.push(SurfaceEffect {
+                        kind,
+                        pos: vehicle.pos,
+                        angle: vehicle.angle,
+                        start_time: self.gs.game_time,
+                    });
+                }
+            }
+        }
+
+        // The oldest are at the front.
+        let max = self.cg.effects_budget.surface_effects_max;
+        let excess = self.cg.surface_effects.len().saturating_sub(max);
+        self.cg.surface_effects.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{map::SURFACE_LOOKUPS, test_utils};
+
+    fn vehicle_on(cvars: &Cvars, veh_type: VehicleType, flags: SurfaceFlags) -> Vehicle {
+        let mut vehicle = Vehicle::new(cvars, v!(0 0), 0.0, veh_type, 0.0, Index::DANGLING);
+        vehicle.vel = v!(100 0);
+        vehicle.surface.flags = flags;
+        vehicle
+    }
+
+    #[test]
+    fn test_active_effects() {
+        let cvars = Cvars {
+            cl_surface_effects_speed_min: 20.0,
+            ..Cvars::default()
+        };
+        let all = SurfaceFlags {
+            marks: true,
+            dust: true,
+            spray: true,
+        };
+
+        let tank = vehicle_on(&cvars, VehicleType::Tank, all);
+        assert_eq!(active_effects(&cvars, &tank), all);
+
+        let hovercraft = vehicle_on(&cvars, VehicleType::Hovercraft, all);
+        let flags = active_effects(&cvars, &hovercraft);
+        assert!(!flags.marks && flags.dust && flags.spray);
+
+        let road = SurfaceFlags {
+            marks: true,
+            ..SurfaceFlags::default()
+        };
+        let hummer = vehicle_on(&cvars, VehicleType::Hummer, road);
+        assert_eq!(active_effects(&cvars, &hummer), road);
+
+        let ice = vehicle_on(&cvars, VehicleType::Tank, SurfaceFlags::default());
+        assert_eq!(active_effects(&cvars, &ice), SurfaceFlags::default());
+
+        // Too slow
+        let mut slow = vehicle_on(&cvars, VehicleType::Tank, all);
+        slow.vel = v!(10 0);
+        assert_eq!(active_effects(&cvars, &slow), SurfaceFlags::default());
+
+        // Wrecks don't leave anything behind.
+        let mut wreck = vehicle_on(&cvars, VehicleType::Tank, all);
+        wreck.hp_fraction = 0.0;
+        assert_eq!(active_effects(&cvars, &wreck), SurfaceFlags::default());
+    }
+
+    #[test]
+    fn test_surface_looked_up_once_per_tick() {
+        let cvars = Cvars {
+            bots_max: 3,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        for _ in 0..10 {
+            SURFACE_LOOKUPS.with(|lookups| lookups.set(0));
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(&cvars, game_time);
+            let lookups = SURFACE_LOOKUPS.with(|lookups| lookups.get());
+            assert_eq!(lookups, server.gs.vehicles.len());
+            assert_ne!(server.gs.vehicles.len(), 0);
+        }
+    }
+}
@@ -1,16 +1,87 @@
-//! Stub. So far bots move and shoot randomly.
+//! Stub. So far bots move and shoot randomly, they only dodge on purpose.
+//! In team modes they at least keep out of each other's way and hold fire on teammates.
 
-use crate::prelude::*;
+use std::mem;
+
+use macroquad::miniquad;
+
+use crate::{
+    ai_difficulty::{adjust_difficulty, score_band},
+    ai_dodge::{dodge_input, hm_lock_threat, projectile_threat, reaction_time},
+    objectives::{base_radius, objective_mode},
+    prelude::*,
+    weapons::{muzzle_blocked, revealed_guider},
+    zone::zone_input,
+};
 
 impl ServerFrameCtx<'_> {
+    /// Rubber-banding - make bots which are ahead of the best human weaker and vice versa.
+    pub fn sys_ai_difficulty(&mut self) {
+        if !self.cvars.ai_dynamic_difficulty {
+            return;
+        }
+
+        let interval = self.cvars.ai_difficulty_interval;
+        let prev = (self.gs.game_time_prev / interval).floor();
+        let cur = (self.gs.game_time / interval).floor();
+        if prev == cur {
+            return;
+        }
+
+        let best_human = self
+            .gs
+            .players
+            .iter()
+            .filter(|(_, player)| !matches!(player.client, ClientType::Ai(_)))
+            .map(|(_, player)| player.score.points(self.cvars))
+            .max();
+        let Some(best_human) = best_human else {
+            return;
+        };
+
+        for (_, ai) in self.gs.ais.iter_mut() {
+            let Some(player) = self.gs.players.soft_get(ai.player) else {
+                continue;
+            };
+            let gap = player.score.points(self.cvars) - best_human;
+            ai.score_band = score_band(self.cvars, ai.score_band, gap);
+            let difficulty = adjust_difficulty(self.cvars, ai.score_band, ai.difficulty);
+            if self.cvars.d_ai_difficulty && difficulty != ai.difficulty {
+                dbg_logf!(
+                    "{} difficulty {:.2} -> {:.2} (gap {gap})",
+                    player.name,
+                    ai.difficulty,
+                    difficulty,
+                );
+            }
+            ai.difficulty = difficulty;
+        }
+    }
+
     pub fn sys_ai(&mut self) {
         if !self.cvars.ai {
             return;
         }
 
-        for (_, ai) in self.gs.ais.iter_mut() {
-            let player = &mut self.gs.players[ai.player];
-            let vehicle = &self.gs.vehicles[player.vehicle.unwrap()];
+        let goal = objective_goal(self.gs, self.map);
+        for ai_handle in self.gs.ais.collect_handles() {
+            let ai = &self.gs.ais[ai_handle];
+            if ai.possessed {
+                continue;
+            }
+
+            let player_handle = ai.player;
+            let Some(player) = self.gs.players.soft_get(player_handle) else {
+                continue;
+            };
+            let Some(vehicle) = player.vehicle.and_then(|h| self.gs.vehicles.soft_get(h)) else {
+                continue;
+            };
+            let separation = separation_dir(self.cvars, self.gs, player_handle, vehicle);
+            let teammate_in_sights =
+                teammate_in_sights(self.cvars, self.gs, player_handle, vehicle);
+            let ai = &mut self.gs.ais[ai_handle];
+            let player = &mut self.gs.players[player_handle];
 
             // keep moving forward if recently spawned
             let age = self.gs.game_time - vehicle.spawn_time;
@@ -27,30 +98,961 @@ impl ServerFrameCtx<'_> {
                 }
             }
 
+            // Don't thrash weapons when switching takes time.
+            // With instant switching this is always true.
+            let hold = self.cvars.ai_weapon_switch_hold * self.cvars.g_weapon_switch_time;
+            let may_switch = self.gs.game_time >= vehicle.weapon_ready_time + hold;
+
             if self.sg.rng.gen_bool(0.03) {
                 ai.turning = self.sg.rng.gen_range(-1..=1);
             }
 
-            if !ai.firing && self.sg.rng.gen_bool(0.01) {
+            // LATER Aim error and reaction time once bots actually aim.
+            let fire_chance = 0.02 * ai.difficulty.clamped(0.0, 1.0);
+            if !ai.firing && self.sg.rng.gen_bool(fire_chance) {
                 ai.firing = true;
             } else if ai.firing && self.sg.rng.gen_bool(0.03) {
                 ai.firing = false;
             }
+            // Point-blank into a wall would only waste time, the shot wouldn't come out.
+            // `ai.firing` is kept so they resume when the muzzle is clear.
+            let blocked = muzzle_blocked(self.cvars, self.map, vehicle, player.cur_weapon, false);
+            // There's no way to turn friendly fire off so only shooting at enemies is safe.
+            let blocked = blocked || teammate_in_sights;
 
-            player.input = NetInput {
+            let mut steering = NetInput {
                 up: ai.movement == 1,
                 down: ai.movement == -1,
                 left: ai.turning == -1,
                 right: ai.turning == 1,
+                ..NetInput::empty()
+            };
+            // Most important last.
+            if ai.role == AiRole::Objective {
+                if let Some(goal) = goal {
+                    let to_goal = goal - vehicle.pos;
+                    if to_goal.magnitude() > base_radius(self.cvars) {
+                        steering = dodge_input(vehicle.angle, to_goal.normalized());
+                    }
+                }
+            }
+            if let Some(dir) = separation {
+                steering = dodge_input(vehicle.angle, dir);
+            }
+            // Getting back into the zone is more important than anything else.
+            if let Some(zone) = &self.gs.zone {
+                if let Some(input) = zone_input(zone, self.gs.game_time, vehicle) {
+                    steering = input;
+                }
+            }
+
+            player.input = NetInput {
+                up: steering.up,
+                down: steering.down,
+                left: steering.left,
+                right: steering.right,
                 turret_left: self.sg.rng.gen_bool(0.01),
                 turret_right: self.sg.rng.gen_bool(0.01),
-                prev_weapon: self.sg.rng.gen_bool(0.02),
-                next_weapon: self.sg.rng.gen_bool(0.01),
-                fire: ai.firing,
+                // Always roll so the RNG sequence doesn't depend on it.
+                prev_weapon: self.sg.rng.gen_bool(0.02) && may_switch,
+                next_weapon: self.sg.rng.gen_bool(0.01) && may_switch,
+                fire: ai.firing && !blocked,
+                fire_alt: false,
                 mine: self.sg.rng.gen_bool(0.001),
                 self_destruct: self.sg.rng.gen_bool(0.0001),
                 horn: self.sg.rng.gen_bool(0.0001),
+                boost: false,
+                interact: false,
+            }
+        }
+    }
+
+    /// Pick the enemy each bot is after, see `choose_target`.
+    ///
+    /// LATER Steer and aim at the target, so far it only affects what bots consider a target.
+    pub fn sys_ai_targets(&mut self) {
+        for ai_handle in self.gs.ais.collect_handles() {
+            let player_handle = self.gs.ais[ai_handle].player;
+            let target = choose_target(self.cvars, self.gs, player_handle);
+            self.gs.ais[ai_handle].target = target;
+
+            if self.cvars.d_ai_targets {
+                let pos = |handle: Index| {
+                    let player = self.gs.players.get(handle)?;
+                    Some(self.gs.vehicles.get(player.vehicle?)?.pos)
+                };
+                if let (Some(begin), Some(end)) = (pos(player_handle), target.and_then(pos)) {
+                    dbg_line!(begin, end);
+                }
+            }
+        }
+    }
+
+    /// Override movement to get out of the way of incoming projectiles.
+    ///
+    /// Runs after `sys_ai` so dodging takes priority over wandering.
+    /// Looking for threats is expensive so bots take turns according to `ai_schedule`,
+    /// following the current dodge happens every tick.
+    pub fn sys_ai_dodge(&mut self) {
+        if !self.cvars.ai || !self.cvars.ai_dodge {
+            return;
+        }
+
+        let start = miniquad::date::now();
+        let mut bots = Vec::new();
+        for (ai_handle, ai) in self.gs.ais.iter_mut() {
+            if ai.possessed {
+                continue;
+            }
+            let Some(player) = self.gs.players.soft_get(ai.player) else {
+                continue;
+            };
+            let Some(vehicle_handle) = player.vehicle else {
+                continue;
+            };
+            let Some(vehicle) = self.gs.vehicles.soft_get(vehicle_handle) else {
+                continue;
+            };
+            if vehicle.destroyed() {
+                ai.threat_time = None;
+                ai.dodge_until = f64::NEG_INFINITY;
+                continue;
+            }
+            bots.push((ai_handle, ai.decision_time));
+        }
+
+        let mut schedule = mem::take(&mut self.sg.ai_schedule);
+        schedule.run(
+            self.cvars,
+            self.gs.game_time,
+            self.gs.dt,
+            &bots,
+            || miniquad::date::now() - start,
+            |ai_handle| self.ai_find_threat(ai_handle),
+        );
+        self.sg.ai_schedule = schedule;
+
+        for (ai_handle, _) in bots {
+            let ai = &self.gs.ais[ai_handle];
+            if self.gs.game_time >= ai.dodge_until {
+                continue;
+            }
+            let player = &mut self.gs.players[ai.player];
+            let vehicle = &self.gs.vehicles[player.vehicle.unwrap()];
+            let dodge = dodge_input(vehicle.angle, ai.dodge_dir);
+            player.input.up = dodge.up;
+            player.input.down = dodge.down;
+            player.input.left = dodge.left;
+            player.input.right = dodge.right;
+            // Fleeing is the only time they know they're in a hurry, there's no chasing yet.
+            player.input.boost = self.cvars.ai_boost;
+        }
+    }
+
+    /// Look for the closest incoming projectile and start dodging after the reaction time.
+    ///
+    /// Only for bots with a live vehicle.
+    fn ai_find_threat(&mut self, ai_handle: Index) {
+        let game_time = self.gs.game_time;
+        let ai = &mut self.gs.ais[ai_handle];
+        ai.decision_time = game_time;
+        let ai_player = ai.player;
+        let vehicle_handle = self.gs.players[ai_player].vehicle.unwrap();
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+
+        // The closest threat matters most.
+        let threat = self
+            .gs
+            .projectiles
+            .iter()
+            .filter(|(_, projectile)| projectile.owner != ai_player)
+            .filter_map(|(projectile_handle, projectile)| {
+                // Missiles locked onto us are a threat even when they're not heading our way yet.
+                let dir = if vehicle.hms.contains(&projectile_handle) {
+                    hm_lock_threat(projectile, vehicle.pos)
+                } else {
+                    projectile_threat(self.cvars, game_time, projectile, vehicle.pos)?
+                };
+                Some((projectile.pos.distance_squared(vehicle.pos), dir))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, dir)| dir);
+
+        match threat {
+            Some(dir) => {
+                let noticed = *ai.threat_time.get_or_insert(game_time);
+                if let Some(delay) = reaction_time(self.cvars, ai.difficulty) {
+                    if game_time - noticed >= delay {
+                        ai.dodge_dir = dir;
+                        ai.dodge_until = game_time + self.cvars.ai_dodge_duration;
+                    }
+                }
+            }
+            None => ai.threat_time = None,
+        }
+    }
+
+    /// Update each bot's `AiState` and with `d_ai_chat` announce the changes.
+    ///
+    /// Runs after the other AI systems so it sees their final decisions.
+    pub fn sys_ai_chat(&mut self) {
+        for chat in self.ai_chat() {
+            let player_handle = self.gs.players.slot_to_index(chat.player).unwrap();
+            dbg_logf!("{}: {}", self.gs.players[player_handle].name, chat.text);
+            self.net_send_all(ServerMessage::AiChat(chat));
+        }
+    }
+
+    /// Returns the announcements to send - at most one per bot per `d_ai_chat_interval`.
+    ///
+    /// If the state changes multiple times during the interval,
+    /// only the latest is announced once the interval is over.
+    fn ai_chat(&mut self) -> Vec<AiChat> {
+        let game_time = self.gs.game_time;
+        let only_player = u32::try_from(self.cvars.d_ai_chat_player).ok();
+        let mut chats = Vec::new();
+        for (_, ai) in self.gs.ais.iter_mut() {
+            let Some(player) = self.gs.players.soft_get(ai.player) else {
+                continue;
+            };
+            let vehicle = player.vehicle.and_then(|h| self.gs.vehicles.soft_get(h));
+            ai.state = match vehicle {
+                Some(vehicle) => ai_state(ai, player, vehicle, game_time),
+                None => AiState::Dead,
+            };
+
+            if !self.cvars.d_ai_chat || ai.possessed {
+                continue;
+            }
+            let slot = ai.player.slot();
+            if only_player.is_some_and(|only| only != slot) {
+                continue;
+            }
+            if ai.state == ai.announced
+                || game_time < ai.announce_time + self.cvars.d_ai_chat_interval
+            {
+                continue;
+            }
+
+            ai.announced = ai.state;
+            if let Some(text) = ai.state.announcement() {
+                ai.announce_time = game_time;
+                chats.push(AiChat {
+                    player: slot,
+                    text: text.to_owned(),
+                });
+            }
+        }
+        chats
+    }
+
+    /// Pop flares when a homing missile is tracking the bot.
+    ///
+    /// Uses the same reaction time as dodging, the rest is up to `deploy_flares`.
+    pub fn sys_ai_flares(&mut self) {
+        if !self.cvars.ai || !self.cvars.ai_flares {
+            return;
+        }
+
+        let game_time = self.gs.game_time;
+        for ai_handle in self.gs.ais.collect_handles() {
+            let ai = &mut self.gs.ais[ai_handle];
+            if ai.possessed {
+                continue;
+            }
+
+            let ai_player = ai.player;
+            let Some(vehicle_handle) = self.gs.players[ai_player].vehicle else {
+                continue;
+            };
+            let vehicle = &self.gs.vehicles[vehicle_handle];
+            if vehicle.destroyed() || vehicle.hms.is_empty() {
+                ai.hm_lock_time = None;
+                continue;
+            }
+
+            let noticed = *ai.hm_lock_time.get_or_insert(game_time);
+            let Some(delay) = reaction_time(self.cvars, ai.difficulty) else {
+                continue;
+            };
+            if game_time - noticed >= delay {
+                self.deploy_flares(ai_player, vehicle_handle);
+            }
+        }
+    }
+}
+
+/// Pick a role for a new bot according to `ai_role_attacker_weight` and `ai_role_objective_weight`.
+///
+/// Always rolls so the RNG sequence doesn't depend on the weights.
+pub fn ai_role(cvars: &Cvars, rng: &mut Xoshiro256PlusPlus) -> AiRole {
+    let r: f64 = rng.gen();
+    let attacker = cvars.ai_role_attacker_weight.max(0.0);
+    let objective = cvars.ai_role_objective_weight.max(0.0);
+    if objective > 0.0 && r * (attacker + objective) >= attacker {
+        AiRole::Objective
+    } else {
+        AiRole::Attacker
+    }
+}
+
+/// Where objective bots are headed, None outside objective modes.
+///
+/// LATER The cow or its carrier once the cow exists, for now where it starts.
+pub fn objective_goal(gs: &GameState, map: &Map) -> Option<Vec2f> {
+    if !objective_mode(gs) {
+        return None;
+    }
+    map.marker("cow_start").map(|index| map.tile_center(index))
+}
+
+/// Which way to go to get away from teammates within `ai_separation_radius`,
+/// None if there are none or the player has no team.
+///
+/// Closer teammates push harder.
+pub fn separation_dir(
+    cvars: &Cvars,
+    gs: &GameState,
+    player_handle: Index,
+    vehicle: &Vehicle,
+) -> Option<Vec2f> {
+    let radius = cvars.ai_separation_radius;
+    let mut push = Vec2f::zero();
+    for (_, other_vehicle) in teammate_vehicles(gs, player_handle) {
+        let away = vehicle.pos - other_vehicle.pos;
+        let distance = away.magnitude();
+        if distance >= radius {
+            continue;
+        }
+        // Exactly on top of each other - any direction will do as long as they pick different ones.
+        let dir = away
+            .try_normalized()
+            .unwrap_or_else(|| vehicle.angle.to_vec2f());
+        push += dir * (1.0 - distance / radius);
+    }
+    push.try_normalized()
+}
+
+/// Whether a teammate is within `ai_hold_fire_angle` of where the turret points.
+pub fn teammate_in_sights(
+    cvars: &Cvars,
+    gs: &GameState,
+    player_handle: Index,
+    vehicle: &Vehicle,
+) -> bool {
+    let aim = (vehicle.angle + vehicle.turret_angle_current).to_vec2f();
+    teammate_vehicles(gs, player_handle).any(|(_, other_vehicle)| {
+        let Some(dir) = (other_vehicle.pos - vehicle.pos).try_normalized() else {
+            return true;
+        };
+        aim.dot(dir) >= cvars.ai_hold_fire_angle.cos()
+    })
+}
+
+/// Living vehicles of the player's teammates, nothing if it has no team.
+fn teammate_vehicles(
+    gs: &GameState,
+    player_handle: Index,
+) -> impl Iterator<Item = (Index, &Vehicle)> {
+    let team = gs.players[player_handle].team;
+    gs.players
+        .iter()
+        .filter(move |&(other_handle, other)| {
+            other_handle != player_handle && team.is_some() && other.team == team
+        })
+        .filter_map(|(_, other)| {
+            let vehicle_handle = other.vehicle?;
+            let vehicle = gs.vehicles.get(vehicle_handle)?;
+            Some((vehicle_handle, vehicle))
+        })
+        .filter(|(_, vehicle)| !vehicle.destroyed())
+}
+
+/// The living enemy with the highest `target_score`, None if there's nobody to go after.
+pub fn choose_target(cvars: &Cvars, gs: &GameState, player_handle: Index) -> Option<Index> {
+    let player = gs.players.get(player_handle)?;
+    let vehicle = gs.vehicles.get(player.vehicle?)?;
+    if vehicle.destroyed() {
+        return None;
+    }
+
+    let mut best = None;
+    let mut best_score = f64::NEG_INFINITY;
+    for (other_handle, other) in gs.players.iter() {
+        if other_handle == player_handle || (player.team.is_some() && player.team == other.team) {
+            continue;
+        }
+        let Some(other_vehicle) = other.vehicle.and_then(|h| gs.vehicles.get(h)) else {
+            continue;
+        };
+        if other_vehicle.destroyed() {
+            continue;
+        }
+        let distance = (other_vehicle.pos - vehicle.pos).magnitude();
+        let revealed = revealed_guider(cvars, gs, player_handle, other_handle).is_some();
+        let score = target_score(cvars, distance, revealed);
+        if score > best_score {
+            best = Some(other_handle);
+            best_score = score;
+        }
+    }
+    best
+}
+
+/// Closer enemies are better, guiders revealed by `g_gm_reveal` get `ai_target_revealed_bonus`.
+pub fn target_score(cvars: &Cvars, distance: f64, revealed: bool) -> f64 {
+    let bonus = if revealed {
+        cvars.ai_target_revealed_bonus
+    } else {
+        0.0
+    };
+    bonus - distance
+}
+
+/// Most urgent first.
+fn ai_state(ai: &Ai, player: &Player, vehicle: &Vehicle, game_time: f64) -> AiState {
+    if vehicle.destroyed() {
+        AiState::Dead
+    } else if ai.hm_lock_time.is_some() {
+        AiState::Evading
+    } else if game_time < ai.dodge_until {
+        AiState::Dodging
+    } else if vehicle.weapon_states[player.cur_weapon as usize].is_reloading() {
+        AiState::Reloading
+    } else if ai.firing {
+        AiState::Engaging
+    } else {
+        AiState::Wandering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    /// Open arena with a single spawn near the bottom so rockets have room to fly in from above.
+    fn open_map() -> Map {
+        test_utils::arena(|r, c| if (r, c) == (8, 5) { "36" } else { "0" })
+    }
+
+    /// Fire a volley of rockets at a bot which doesn't wander, return whether it survived.
+    fn survives_volley(seed: u64, dodge: bool) -> bool {
+        let cvars = Cvars {
+            ai_dodge: dodge,
+            d_seed: seed,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::server(&cvars, open_map());
+        let mut ctx = server.ctx(&cvars);
+
+        let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 1.0));
+        let player = Player::new("bot".to_owned(), ClientType::Ai(ai_handle));
+        let player_handle = ctx.gs.players.insert(player);
+        ctx.gs.ais[ai_handle].player = player_handle;
+        ctx.spawn_vehicle(player_handle, true);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        let target = ctx.gs.vehicles[vehicle_handle].pos;
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        ctx.gs.dt = dt;
+        let mut fired = 0;
+        for _ in 0..(2.0 / dt) as usize {
+            ctx.gs.game_time_prev = ctx.gs.game_time;
+            ctx.gs.game_time += dt;
+
+            if fired < 6 && ctx.gs.game_time >= fired as f64 * 0.2 {
+                let rocket = Projectile {
+                    weapon: Weapon::Rockets,
+                    pos: target - v!(0 380),
+                    vel: v!(0 600),
+                    angle: PI / 2.0,
+                    turn_rate: 0.0,
+                    spawn_time: 0.0,
+                    explode_time: f64::MAX,
+                    owner: Index::DANGLING,
+                    target: None,
+                    flight: None,
+                    team: None,
+                    seeker: Seeker::default(),
+                };
+                ctx.gs.projectiles.insert(rocket);
+                fired += 1;
+            }
+
+            // Only dodging moves it.
+            ctx.gs.players[player_handle].input = NetInput::empty();
+            ctx.sys_ai_dodge();
+            ctx.sys_vehicle_movement();
+            ctx.sys_projectiles();
+        }
+
+        !ctx.gs.vehicles[vehicle_handle].destroyed()
+    }
+
+    #[test]
+    fn test_dodge_rocket_volley() {
+        for seed in 0..4 {
+            assert!(
+                !survives_volley(seed, false),
+                "seed {seed}: standing still survived"
+            );
+            assert!(survives_volley(seed, true), "seed {seed}: dodging bot died");
+        }
+    }
+
+    #[test]
+    fn test_ai_flares_after_reaction_time() {
+        for enabled in [false, true] {
+            let cvars = Cvars {
+                ai_flares: enabled,
+                ..Cvars::default()
+            };
+            let mut server = test_utils::server(&cvars, open_map());
+            let mut ctx = server.ctx(&cvars);
+
+            let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 0.5));
+            let player = Player::new("bot".to_owned(), ClientType::Ai(ai_handle));
+            let player_handle = ctx.gs.players.insert(player);
+            ctx.gs.ais[ai_handle].player = player_handle;
+            ctx.spawn_vehicle(player_handle, true);
+            let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+
+            let delay = reaction_time(&cvars, 0.5).unwrap();
+            assert!(delay > 0.0);
+
+            // Nothing locked on yet.
+            ctx.sys_ai_flares();
+            assert_eq!(ctx.gs.vehicles[vehicle_handle].flares, cvars.g_flare_count);
+
+            ctx.gs.vehicles[vehicle_handle].hms.push(Index::DANGLING);
+            ctx.sys_ai_flares();
+            assert_eq!(ctx.gs.vehicles[vehicle_handle].flares, cvars.g_flare_count);
+            assert_eq!(ctx.gs.flares.len(), 0);
+
+            ctx.gs.game_time += delay;
+            ctx.sys_ai_flares();
+            let expected = if enabled {
+                cvars.g_flare_count - 1
+            } else {
+                cvars.g_flare_count
+            };
+            assert_eq!(ctx.gs.vehicles[vehicle_handle].flares, expected);
+            assert_eq!(
+                ctx.gs.flares.len(),
+                if enabled { cvars.g_flare_burst } else { 0 }
+            );
+        }
+    }
+
+    fn chat_bots(cvars: &Cvars, names: &[&str]) -> (Server, Vec<Index>) {
+        let mut server = test_utils::server(cvars, open_map());
+        let mut ctx = server.ctx(cvars);
+        let mut ai_handles = Vec::new();
+        for name in names {
+            let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 0.5));
+            let player = Player::new(name.to_string(), ClientType::Ai(ai_handle));
+            let player_handle = ctx.gs.players.insert(player);
+            ctx.gs.ais[ai_handle].player = player_handle;
+            ctx.spawn_vehicle(player_handle, true);
+            ai_handles.push(ai_handle);
+        }
+        (server, ai_handles)
+    }
+
+    fn chat_texts(ctx: &mut ServerFrameCtx) -> Vec<String> {
+        ctx.ai_chat()
+            .into_iter()
+            .map(|chat| {
+                let player_handle = ctx.gs.players.slot_to_index(chat.player).unwrap();
+                format!("{}: {}", ctx.gs.players[player_handle].name, chat.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ai_chat_rate_limit() {
+        for enabled in [false, true] {
+            let cvars = Cvars {
+                d_ai_chat: enabled,
+                d_ai_chat_interval: 3.0,
+                ..Cvars::default()
+            };
+            let (mut server, ais) = chat_bots(&cvars, &["Sir Hurt"]);
+            let mut ctx = server.ctx(&cvars);
+            assert!(chat_texts(&mut ctx).is_empty());
+
+            ctx.gs.game_time = 1.0;
+            ctx.gs.ais[ais[0]].firing = true;
+            let expected = if enabled {
+                vec!["Sir Hurt: opening fire".to_owned()]
+            } else {
+                vec![]
+            };
+            assert_eq!(chat_texts(&mut ctx), expected);
+            assert_eq!(ctx.gs.ais[ais[0]].state, AiState::Engaging);
+
+            // Too soon, then only the latest state is announced.
+            ctx.gs.game_time = 2.0;
+            ctx.gs.ais[ais[0]].dodge_until = 2.5;
+            assert!(chat_texts(&mut ctx).is_empty());
+            ctx.gs.ais[ais[0]].firing = false;
+            ctx.gs.ais[ais[0]].dodge_until = f64::NEG_INFINITY;
+            ctx.gs.game_time = 3.5;
+            let player_handle = ctx.gs.ais[ais[0]].player;
+            let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+            let weapon = ctx.gs.players[player_handle].cur_weapon;
+            ctx.gs.vehicles[vehicle_handle].weapon_states[weapon as usize]
+                .start_reload(&cvars, weapon, 3.5);
+            assert!(chat_texts(&mut ctx).is_empty());
+
+            ctx.gs.game_time = 4.0;
+            let expected = if enabled {
+                vec!["Sir Hurt: reloading".to_owned()]
+            } else {
+                vec![]
+            };
+            assert_eq!(chat_texts(&mut ctx), expected);
+        }
+    }
+
+    #[test]
+    fn test_ai_chat_one_player() {
+        let mut cvars = Cvars {
+            d_ai_chat: true,
+            ..Cvars::default()
+        };
+        let (mut server, ais) = chat_bots(&cvars, &["Mr. Pain", "Fireman"]);
+        cvars.d_ai_chat_player = server.gs.ais[ais[1]].player.slot() as i32;
+        let mut ctx = server.ctx(&cvars);
+
+        for &ai in &ais {
+            ctx.gs.ais[ai].firing = true;
+        }
+        assert_eq!(chat_texts(&mut ctx), ["Fireman: opening fire"]);
+
+        // Possessed bots don't talk, the human decides.
+        ctx.gs.ais[ais[1]].possessed = true;
+        ctx.gs.ais[ais[1]].firing = false;
+        ctx.gs.game_time = 10.0;
+        assert!(chat_texts(&mut ctx).is_empty());
+    }
+
+    #[test]
+    fn test_ai_muzzle_blocked() {
+        let cvars = Cvars::default();
+        let (mut server, ais) = chat_bots(&cvars, &["Bot"]);
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = ctx.gs.ais[ais[0]].player;
+        ctx.gs.players[player_handle].cur_weapon = Weapon::Rail;
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+
+        // Facing the left edge - tank railgun muzzle at 90 + 5 - 35 = 60, inside the wall.
+        // Then facing away from it.
+        for (angle, blocked) in [(PI, true), (0.0, false)] {
+            let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+            vehicle.veh_type = VehicleType::Tank;
+            vehicle.pos = v!(90 352);
+            vehicle.angle = angle;
+            for _ in 0..10 {
+                ctx.gs.ais[ais[0]].firing = true;
+                ctx.sys_ai();
+                let firing = ctx.gs.ais[ais[0]].firing;
+                assert_eq!(ctx.gs.players[player_handle].input.fire, firing && !blocked);
+            }
+        }
+
+        // Shooting checks the same thing so even forcing the trigger wastes nothing.
+        ctx.gs.vehicles[vehicle_handle].angle = PI;
+        ctx.gs.ais[ais[0]].firing = true;
+        ctx.sys_ai();
+        ctx.gs.players[player_handle].input.fire = true;
+        ctx.sys_shooting();
+        assert!(ctx.gs.projectiles.is_empty());
+        let state = ctx.gs.vehicles[vehicle_handle].weapon_states[Weapon::Rail as usize];
+        assert_eq!(state.rounds_loaded, cvars.g_railgun_reload_ammo);
+    }
+
+    #[test]
+    fn test_zone_overrides_wandering() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::server(&cvars, open_map());
+        let mut ctx = server.ctx(&cvars);
+
+        let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 1.0));
+        let player = Player::new("bot".to_owned(), ClientType::Ai(ai_handle));
+        let player_handle = ctx.gs.players.insert(player);
+        ctx.gs.ais[ai_handle].player = player_handle;
+        ctx.spawn_vehicle(player_handle, true);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        // Facing down, away from the zone.
+        ctx.gs.vehicles[vehicle_handle].angle = PI / 2.0;
+        let pos = ctx.gs.vehicles[vehicle_handle].pos;
+
+        // Inside - recently spawned bots drive forward.
+        ctx.gs.zone = Some(Zone {
+            start_center: pos,
+            start_radius: 100.0,
+            target_center: pos,
+            target_radius: 100.0,
+            shrink_start: 0.0,
+            shrink_end: 0.0,
+        });
+        ctx.sys_ai();
+        let input = ctx.gs.players[player_handle].input;
+        assert!(input.up && !input.down);
+
+        // Outside - backs up straight towards the zone no matter what it'd like to do.
+        ctx.gs.zone = Some(Zone {
+            start_center: pos - v!(0 300),
+            target_center: pos - v!(0 300),
+            ..ctx.gs.zone.unwrap()
+        });
+        for _ in 0..100 {
+            ctx.gs.game_time += 0.1;
+            ctx.sys_ai();
+            let input = ctx.gs.players[player_handle].input;
+            assert!(input.down && !input.up && !input.left && !input.right);
+        }
+    }
+
+    #[test]
+    fn test_ai_targets_revealed_guider() {
+        let cvars = Cvars {
+            g_gm_reveal_range: 405.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::server(&cvars, open_map());
+        let mut ctx = server.ctx(&cvars);
+
+        // The bots are closer to each other than to the human,
+        // only the first one is within reveal range of the human.
+        let mut ai_handles = Vec::new();
+        let mut player_handles = Vec::new();
+        for (name, pos, bot) in [
+            ("bot 1", v!(200 200), true),
+            ("bot 2", v!(300 200), true),
+            ("human", v!(200 600), false),
+        ] {
+            let client = if bot {
+                let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 0.5));
+                ai_handles.push(ai_handle);
+                ClientType::Ai(ai_handle)
+            } else {
+                ClientType::Local
+            };
+            let player_handle = ctx.gs.players.insert(Player::new(name.to_owned(), client));
+            if let ClientType::Ai(ai_handle) = client {
+                ctx.gs.ais[ai_handle].player = player_handle;
+            }
+            test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, pos, 0.0);
+            player_handles.push(player_handle);
+        }
+        let [bot1, bot2, human] = player_handles[..] else {
+            unreachable!();
+        };
+        let targets = |ctx: &mut ServerFrameCtx| {
+            ctx.sys_ai_targets();
+            ai_handles
+                .iter()
+                .map(|&ai_handle| ctx.gs.ais[ai_handle].target)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(targets(&mut ctx), [Some(bot2), Some(bot1)]);
+
+        // The human starts guiding a missile which is about to hit the top wall.
+        let gm = Projectile {
+            weapon: Weapon::Gm,
+            pos: v!(352 100),
+            vel: v!(0, -2000),
+            angle: -PI / 2.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: human,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let gm_handle = ctx.gs.projectiles.insert(gm);
+        ctx.gs.players[human].guided_missile = Some(gm_handle);
+        assert_eq!(targets(&mut ctx), [Some(human), Some(bot1)]);
+
+        // Back to normal after the impact.
+        ctx.gs.dt = 0.05;
+        ctx.sys_projectiles();
+        assert!(!ctx.gs.projectiles.contains(gm_handle));
+        assert_eq!(ctx.gs.players[human].guided_missile, None);
+        assert_eq!(targets(&mut ctx), [Some(bot2), Some(bot1)]);
+    }
+
+    /// Bots on one team, each in a tank at `pos` facing right.
+    fn team_bots(ctx: &mut ServerFrameCtx, positions: &[Vec2f]) -> Vec<Index> {
+        let mut player_handles = Vec::new();
+        for &pos in positions {
+            let ai_handle = ctx.gs.ais.insert(Ai::new(Index::DANGLING, 1.0));
+            let mut player = Player::new("bot".to_owned(), ClientType::Ai(ai_handle));
+            player.team = Some(0);
+            let player_handle = ctx.gs.players.insert(player);
+            ctx.gs.ais[ai_handle].player = player_handle;
+            test_utils::add_vehicle(ctx, player_handle, VehicleType::Tank, pos, 0.0);
+            player_handles.push(player_handle);
+        }
+        player_handles
+    }
+
+    fn run_bots(ctx: &mut ServerFrameCtx, seconds: f64) {
+        let dt = 1.0 / ctx.cvars.sys_tickrate_fixed_fps;
+        ctx.gs.dt = dt;
+        for _ in 0..(seconds / dt) as usize {
+            ctx.gs.game_time_prev = ctx.gs.game_time;
+            ctx.gs.game_time += dt;
+            ctx.sys_ai();
+            ctx.sys_vehicle_movement();
+        }
+    }
+
+    #[test]
+    fn test_ai_holds_fire_on_teammates() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::server(&cvars, open_map());
+        let mut ctx = server.ctx(&cvars);
+        let players = team_bots(&mut ctx, &[v!(200 352), v!(400 352)]);
+        let ClientType::Ai(ai_handle) = ctx.gs.players[players[0]].client else {
+            unreachable!();
+        };
+
+        // Teammate straight ahead, then the same vehicle as an enemy.
+        for (team, hold) in [(Some(0), true), (Some(1), false)] {
+            ctx.gs.players[players[1]].team = team;
+            for _ in 0..10 {
+                ctx.gs.ais[ai_handle].firing = true;
+                ctx.sys_ai();
+                let firing = ctx.gs.ais[ai_handle].firing;
+                assert_eq!(ctx.gs.players[players[0]].input.fire, firing && !hold);
             }
         }
+
+        // Only the turret counts, not where the hull is going.
+        ctx.gs.players[players[1]].team = Some(0);
+        let vehicle_handle = ctx.gs.players[players[0]].vehicle.unwrap();
+        ctx.gs.vehicles[vehicle_handle].turret_angle_current = PI / 2.0;
+        ctx.gs.ais[ai_handle].firing = true;
+        ctx.sys_ai();
+        let firing = ctx.gs.ais[ai_handle].firing;
+        assert_eq!(ctx.gs.players[players[0]].input.fire, firing);
+    }
+
+    #[test]
+    fn test_ai_teammates_spread_out() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::server(&cvars, open_map());
+        let mut ctx = server.ctx(&cvars);
+        let players = team_bots(&mut ctx, &[v!(340 352), v!(352 352), v!(352 364)]);
+        let positions = |ctx: &ServerFrameCtx| -> Vec<Vec2f> {
+            players
+                .iter()
+                .map(|&p| ctx.gs.vehicles[ctx.gs.players[p].vehicle.unwrap()].pos)
+                .collect()
+        };
+
+        // Right next to each other, everyone is pushed away from the others.
+        let pos = positions(&ctx);
+        for (i, &player_handle) in players.iter().enumerate() {
+            let vehicle = &ctx.gs.vehicles[ctx.gs.players[player_handle].vehicle.unwrap()];
+            let dir = separation_dir(&cvars, ctx.gs, player_handle, vehicle).unwrap();
+            let center = (pos[0] + pos[1] + pos[2]) / 3.0;
+            assert!(dir.dot(pos[i] - center) > 0.0, "bot {i}");
+        }
+
+        run_bots(&mut ctx, 3.0);
+        let pos = positions(&ctx);
+        for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+            let distance = pos[i].distance(pos[j]);
+            assert!(
+                distance > cvars.ai_separation_radius * 0.75,
+                "bots {i} and {j} only {distance} apart"
+            );
+        }
+
+        // Enemies don't keep their distance.
+        ctx.gs.players[players[1]].team = Some(1);
+        ctx.gs.players[players[2]].team = None;
+        for &player_handle in &players {
+            let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+            ctx.gs.vehicles[vehicle_handle].pos = v!(352 352);
+            let vehicle = &ctx.gs.vehicles[vehicle_handle];
+            assert_eq!(separation_dir(&cvars, ctx.gs, player_handle, vehicle), None);
+        }
+    }
+
+    #[test]
+    fn test_ai_role_weights() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let roles = |cvars: &Cvars, rng: &mut Xoshiro256PlusPlus| -> Vec<AiRole> {
+            (0..100).map(|_| ai_role(cvars, rng)).collect()
+        };
+
+        let cvars = Cvars::default();
+        let mixed = roles(&cvars, &mut rng);
+        assert!(mixed.contains(&AiRole::Attacker));
+        assert!(mixed.contains(&AiRole::Objective));
+
+        let cvars = Cvars {
+            ai_role_objective_weight: 0.0,
+            ..Cvars::default()
+        };
+        assert!(roles(&cvars, &mut rng)
+            .iter()
+            .all(|&r| r == AiRole::Attacker));
+
+        let cvars = Cvars {
+            ai_role_attacker_weight: 0.0,
+            ..Cvars::default()
+        };
+        assert!(roles(&cvars, &mut rng)
+            .iter()
+            .all(|&r| r == AiRole::Objective));
+
+        // Nonsense weights still give a role.
+        let cvars = Cvars {
+            ai_role_attacker_weight: 0.0,
+            ai_role_objective_weight: 0.0,
+            ..Cvars::default()
+        };
+        assert!(roles(&cvars, &mut rng)
+            .iter()
+            .all(|&r| r == AiRole::Attacker));
+    }
+
+    #[test]
+    fn test_ai_objective_role_heads_for_cow() {
+        let cvars = Cvars::default();
+        let map = test_utils::open_map(12, 12, "marker cow_start 9 9\n");
+        let mut server = test_utils::server(&cvars, map);
+        let mut ctx = server.ctx(&cvars);
+        ctx.gs.game_mode = GameMode::Ctc(Ctc { capture_limit: 3 });
+        let goal = objective_goal(ctx.gs, ctx.map).unwrap();
+
+        let players = team_bots(&mut ctx, &[v!(100 100)]);
+        let ClientType::Ai(ai_handle) = ctx.gs.players[players[0]].client else {
+            unreachable!();
+        };
+        ctx.gs.ais[ai_handle].role = AiRole::Objective;
+        let vehicle_handle = ctx.gs.players[players[0]].vehicle.unwrap();
+        // Facing away from the cow.
+        ctx.gs.vehicles[vehicle_handle].angle = -PI / 2.0;
+        let start = ctx.gs.vehicles[vehicle_handle].pos.distance(goal);
+
+        run_bots(&mut ctx, 5.0);
+        let end = ctx.gs.vehicles[vehicle_handle].pos.distance(goal);
+        assert!(end < start / 2.0, "only got from {start} to {end}");
+
+        // Outside objective modes there is nothing to go for.
+        ctx.gs.game_mode = GameMode::Ffa(Ffa { kill_limit: 20 });
+        assert_eq!(objective_goal(ctx.gs, ctx.map), None);
     }
 }
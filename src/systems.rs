@@ -6,7 +6,7 @@
 
 use vek::LineSegment2;
 
-use crate::prelude::*;
+use crate::{map::SurfaceKind, prelude::*};
 
 impl ServerFrameCtx<'_> {
     pub fn sys_respawning(&mut self) {
@@ -35,16 +35,28 @@ impl ServerFrameCtx<'_> {
                 && player.death_time + self.cvars.g_respawn_delay < self.gs.game_time
             {
                 player.respawn = Respawn::No;
-                self.gs.vehicles.remove(vehicle_handle).unwrap();
+                let old_vehicle = self.gs.vehicles.remove(vehicle_handle).unwrap();
+                self.gs.vehicle_ids.remove(vehicle_handle);
+                // The weapon can still be switched while dead.
+                let turret_angle = old_vehicle.turret_angle_wanted;
+                player.respawn_prefs =
+                    RespawnPrefs::remember(self.cvars, player.cur_weapon, turret_angle);
                 self.spawn_vehicle(player_handle, true);
             }
         }
     }
 
+    /// Spawn a new vehicle for the player and send it to clients.
     pub fn spawn_vehicle(&mut self, player_handle: Index, use_spawns: bool) {
-        let veh_type = VehicleType::from_repr(self.sg.rng.gen_range(0..3)).unwrap();
-        let (spawn_pos, spawn_angle) = if use_spawns {
-            self.map.random_spawn(&mut self.sg.rng)
+        let veh_type =
+            VehicleType::from_repr(self.sg.rng.gen_range(0..VehicleType::COUNT)).unwrap();
+        let (spawn_pos, spawn_angle) = if let Some(zone) = self.gs.zone {
+            self.zone_spawn(&zone)
+        } else if use_spawns {
+            match self.gs.players[player_handle].team {
+                Some(team) => self.map.random_spawn_team(&mut self.sg.rng, team),
+                None => self.map.random_spawn(&mut self.sg.rng),
+            }
         } else {
             let (pos, _angle) = self.map.random_nonwall(&mut self.sg.rng);
             // Most grass tiles have no rotation so everyone ends up facing right which looks bad.
@@ -52,22 +64,43 @@ impl ServerFrameCtx<'_> {
             let angle = self.sg.rng.gen_range(0.0..2.0 * PI);
             (pos, angle)
         };
+        self.spawn_vehicle_at(player_handle, spawn_pos, spawn_angle, veh_type);
+    }
 
-        let vehicle_handle = self.gs.vehicles.insert(Vehicle::new(
+    /// Like `spawn_vehicle` but with the position, angle and type decided by the caller.
+    ///
+    /// The turret and weapon come from the player's `RespawnPrefs`.
+    pub fn spawn_vehicle_at(
+        &mut self,
+        player_handle: Index,
+        pos: Vec2f,
+        angle: f64,
+        veh_type: VehicleType,
+    ) -> Index {
+        let prefs = self.gs.players[player_handle].respawn_prefs;
+        let turret_angle = prefs.turret_angle.unwrap_or(0.0);
+        let mut vehicle = Vehicle::new(
             self.cvars,
-            spawn_pos,
-            spawn_angle,
+            pos,
+            angle,
             veh_type,
             self.gs.game_time,
             player_handle,
-        ));
+        );
+        vehicle.turret_angle_current = turret_angle;
+        vehicle.turret_angle_wanted = turret_angle;
+        vehicle.add_armor(self.cvars, self.cvars.g_overheal_spawn);
+        let vehicle_handle = self.gs.vehicles.insert(vehicle);
+        let id = self.gs.vehicle_ids.assign(vehicle_handle);
 
         let player = &mut self.gs.players[player_handle];
         player.vehicle = Some(vehicle_handle);
+        player.cur_weapon = prefs.weapon(self.cvars, veh_type);
 
         let vehicle = &self.gs.vehicles[vehicle_handle];
         let init = VehicleInit {
             index: vehicle_handle.slot(),
+            id,
             physics: EntityPhysics {
                 pos: vehicle.pos,
                 vel: vehicle.vel,
@@ -82,6 +115,27 @@ impl ServerFrameCtx<'_> {
         };
         let msg = ServerMessage::SpawnVehicle(init);
         self.net_send_all(msg);
+
+        vehicle_handle
+    }
+
+    /// Place the map's wrecks and `g_ambient_wrecks` random ones.
+    ///
+    /// Part of match setup, clients get them in `Init`.
+    pub fn spawn_wrecks(&mut self) {
+        self.gs.wrecks.clear();
+        self.gs.wrecks.extend_from_slice(self.map.wrecks());
+        for _ in 0..self.cvars.g_ambient_wrecks {
+            let (pos, _) = self.map.random_nonwall(&mut self.sg.rng);
+            let angle = self.sg.rng.gen_range(0.0..2.0 * PI);
+            let veh_type =
+                VehicleType::from_repr(self.sg.rng.gen_range(0..VehicleType::COUNT)).unwrap();
+            self.gs.wrecks.push(Wreck {
+                pos,
+                angle,
+                veh_type,
+            });
+        }
     }
 
     pub fn self_destruct(&mut self) {
@@ -89,44 +143,122 @@ impl ServerFrameCtx<'_> {
             let vehicle = &self.gs.vehicles[vehicle_handle];
             let pos = vehicle.pos;
             let owner = vehicle.owner;
-            let input = &self.gs.players[owner].input;
-            if !input.self_destruct || vehicle.destroyed() {
+            let Some(player) = self.gs.players.soft_get(owner) else {
+                continue;
+            };
+            if !player.input.self_destruct || vehicle.destroyed() {
                 continue;
             }
 
+            // 0) anything left behind
+            let mut factor = 1.0;
+            match self.cvars.g_vehicle_self_destruct(vehicle.veh_type) {
+                SelfDestruct::Blast => factor = self.cvars.g_self_destruct_blast_factor,
+                SelfDestruct::Submunitions => self.self_destruct_submunitions(vehicle_handle),
+                SelfDestruct::Rockets => self.self_destruct_rockets(vehicle_handle),
+            }
+
             // 1) the big explosion
-            self.spawn_explosion(pos, self.cvars.g_self_destruct_explosion_scale, false);
+            let scale = self.cvars.g_self_destruct_explosion_scale * factor;
+            self.spawn_explosion(pos, scale, false, None);
 
             // 2) all vehicles in range
             self.explosion_damage(
                 owner,
+                None,
                 pos,
-                self.cvars.g_self_destruct_damage_center,
-                self.cvars.g_self_destruct_damage_edge,
-                self.cvars.g_self_destruct_radius,
+                self.cvars.g_self_destruct_damage_center * factor,
+                self.cvars.g_self_destruct_damage_edge * factor,
+                self.cvars.g_self_destruct_radius * factor,
                 Some(vehicle_handle),
+                false,
             );
+            self.detonate_mines_near(pos, self.cvars.g_self_destruct_radius * factor);
 
             // 3) the player vehicle to create the small explosion on top.
-            self.damage(owner, vehicle_handle, f64::MAX);
+            self.damage(owner, vehicle_handle, f64::MAX, None, None);
 
             // LATER What was the order of explosions in the original RecWar? Make it configurable?
         }
     }
 
+    /// Throw cluster bombs out in a ring around the vehicle.
+    fn self_destruct_submunitions(&mut self, vehicle_handle: Index) {
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        let count = self.cvars.g_self_destruct_submunitions_count;
+        let mut new_projectiles = Vec::new();
+        for i in 0..count {
+            let angle = vehicle.angle + i as f64 / count as f64 * 2.0 * PI;
+            let mut cb = Projectile {
+                weapon: Weapon::Cb,
+                pos: vehicle.pos,
+                vel: Vec2f::zero(),
+                angle,
+                turn_rate: 0.0,
+                spawn_time: self.gs.game_time,
+                explode_time: f64::MAX,
+                owner: vehicle.owner,
+                target: None,
+                flight: None,
+                team: None,
+                seeker: Seeker::default(),
+            };
+            cluster_bomb(
+                self.cvars,
+                &mut self.sg.rng,
+                self.gs.range_uniform11,
+                &mut cb,
+                self.cvars.g_self_destruct_submunitions_speed,
+                self.cvars.g_self_destruct_submunitions_time,
+            );
+            new_projectiles.push(self.gs.projectiles.insert(cb));
+        }
+        self.projectiles_spawned(new_projectiles);
+    }
+
+    /// Fire one rocket forward and one backward.
+    fn self_destruct_rockets(&mut self, vehicle_handle: Index) {
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        let mut new_projectiles = Vec::new();
+        for angle in [vehicle.angle, vehicle.angle + PI] {
+            let rocket = Projectile {
+                weapon: Weapon::Rockets,
+                pos: vehicle.pos,
+                vel: Vec2f::new(self.cvars.g_rockets_speed, 0.0).rotated_z(angle)
+                    + self.cvars.g_rockets_vehicle_velocity_factor * vehicle.vel,
+                angle: angle.rem_euclid(2.0 * PI),
+                turn_rate: 0.0,
+                spawn_time: self.gs.game_time,
+                explode_time: f64::MAX,
+                owner: vehicle.owner,
+                target: None,
+                flight: None,
+                team: None,
+                seeker: Seeker::default(),
+            };
+            new_projectiles.push(self.gs.projectiles.insert(rocket));
+        }
+        self.projectiles_spawned(new_projectiles);
+    }
+
     pub fn sys_vehicle_movement(&mut self) {
         for (_, vehicle) in self.gs.vehicles.iter_mut() {
-            let stats = self.cvars.g_vehicle_movement_stats(vehicle.veh_type);
+            let stats = vehicle.movement_stats(self.cvars, self.gs.game_time);
 
-            // No movement after death or when guiding
-            let input = if vehicle.destroyed() {
+            // No movement after death, while sinking, right after spawning or when guiding
+            let input = if vehicle.destroyed()
+                || vehicle.sinking.is_some()
+                || vehicle.input_locked(self.cvars, self.gs.game_time)
+            {
                 NetInput::empty()
             } else {
-                let player = &self.gs.players[vehicle.owner];
+                let Some(player) = self.gs.players.soft_get(vehicle.owner) else {
+                    continue;
+                };
                 if player.guided_missile.is_some() {
-                    self.gs.players[vehicle.owner].input.vehicle_while_guiding()
+                    player.input.vehicle_while_guiding()
                 } else {
-                    self.gs.players[vehicle.owner].input
+                    player.input
                 }
             };
             let new_angle = Self::turning(
@@ -138,35 +270,58 @@ impl ServerFrameCtx<'_> {
                 self.gs.dt,
             );
 
-            if vehicle
-                .hitbox
-                .corners(vehicle.pos, new_angle)
-                .iter()
-                .any(|&corner| self.map.is_wall(corner))
-            {
+            let angle = vehicle.hitbox.swept_rotation(
+                self.map,
+                vehicle.pos,
+                vehicle.angle,
+                new_angle,
+                self.cvars.g_rotation_sweep_step,
+                self.cvars.g_rotation_sweep_samples_max,
+            );
+            if angle != new_angle {
                 vehicle.turn_rate *= -0.5;
-            } else {
-                vehicle.angle = new_angle;
             }
+            vehicle.angle = angle;
 
             Self::accel_decel(&stats, &mut vehicle.vel, vehicle.angle, input, self.gs.dt);
 
             let new_pos = vehicle.pos + vehicle.vel * self.gs.dt;
-            if vehicle
-                .hitbox
-                .corners(new_pos, vehicle.angle)
-                .iter()
-                .any(|&corner| self.map.is_wall(corner))
-            {
+            let contacts =
+                vehicle
+                    .hitbox
+                    .swept_wall_contacts(self.map, vehicle.pos, new_pos, vehicle.angle);
+            // Rotation only checks corners so a wall can already poke into an edge,
+            // the vehicle must still be able to drive away then.
+            let hitbox = vehicle.hitbox;
+            let blocked = !contacts.is_empty()
+                || (!self
+                    .map
+                    .convex_touches_wall(&hitbox.corners(vehicle.pos, vehicle.angle))
+                    && hitbox.swept_hits_wall(self.map, vehicle.pos, new_pos, vehicle.angle));
+            if blocked {
+                // Only the part of the velocity along the wall, for scraping effects.
+                // No contacts means an edge hit something narrower than the hitbox, no scraping.
+                vehicle.scrape_speed = contacts.first().map_or(0.0, |contact| {
+                    let normal = contact.normal;
+                    (vehicle.vel - normal * vehicle.vel.dot(normal)).magnitude()
+                });
                 // LATER map edge in original RW absorbs the impact - there's no bounce
                 vehicle.vel *= -0.5;
             } else {
+                vehicle.scrape_speed = 0.0;
                 vehicle.pos = new_pos;
             }
+            if self.cvars.d_draw_contacts {
+                for contact in &contacts {
+                    dbg_cross!(contact.point, 0.5);
+                    dbg_arrow!(contact.point, contact.normal * 10.0, 0.5);
+                }
+            }
+            vehicle.wall_contacts = contacts;
         }
     }
 
-    fn turning(
+    pub fn turning(
         stats: &MovementStats,
         vel: &mut Vec2f,
         angle: &f64,
@@ -174,20 +329,24 @@ impl ServerFrameCtx<'_> {
         input: NetInput,
         dt: f64,
     ) -> f64 {
-        let tr_change = input.right_left() * stats.turn_rate_increase * dt;
-        *turn_rate += tr_change;
-
-        // Friction's constant component - always the same no matter the speed
-        let tr_fric_const = stats.turn_rate_friction_const * dt;
-        if *turn_rate >= 0.0 {
-            *turn_rate = (*turn_rate - tr_fric_const).max(0.0);
-        } else {
-            *turn_rate = (*turn_rate + tr_fric_const).min(0.0);
+        // Tracks spinning in opposite directions - constant rate, no momentum, no translation.
+        // The rate is kept so clients know to spawn track scrub particles.
+        // LATER track scrub sounds
+        if stats.pivots(*vel) {
+            *turn_rate = input.right_left() * stats.pivot_turn_rate;
+            let turn = *turn_rate * dt;
+            return (angle + turn).rem_euclid(2.0 * PI);
         }
 
-        // Friction's linear component - increases with speed
-        let tr_new = *turn_rate * (1.0 - stats.turn_rate_friction_linear).powf(dt);
-        *turn_rate = tr_new.clamped(-stats.turn_rate_max, stats.turn_rate_max);
+        turn_rate_step(
+            turn_rate,
+            input.right_left(),
+            stats.turn_rate_increase,
+            stats.turn_rate_friction_const,
+            stats.turn_rate_friction_linear,
+            stats.turn_rate_max,
+            dt,
+        );
 
         // A dirty hack to approximate car steering (i.e. no turning when still, reversed when moving backwards).
         let steering_coef = if stats.steering_car > 0.0 {
@@ -210,7 +369,13 @@ impl ServerFrameCtx<'_> {
         (angle + turn).rem_euclid(2.0 * PI)
     }
 
-    fn accel_decel(stats: &MovementStats, vel: &mut Vec2f, angle: f64, input: NetInput, dt: f64) {
+    pub fn accel_decel(
+        stats: &MovementStats,
+        vel: &mut Vec2f,
+        angle: f64,
+        input: NetInput,
+        dt: f64,
+    ) {
         let vel_change =
             (input.up() * stats.accel_forward - input.down() * stats.accel_backward) * dt;
         *vel += angle.to_vec2f() * vel_change;
@@ -229,15 +394,77 @@ impl ServerFrameCtx<'_> {
 
     pub fn sys_player_weapon(&mut self) {
         for (_, player) in self.gs.players.iter_mut() {
-            // Change weapon
+            let old_weapon = player.cur_weapon;
+
+            // Change weapon, skipping those the vehicle can't use
+            let order = &self.cvars.g_weapon_order;
+            let vehicle = player.vehicle.and_then(|h| self.gs.vehicles.get(h));
+            let usable = |weapon| {
+                vehicle.is_none_or(|v| self.cvars.g_vehicle_weapons(v.veh_type).contains(weapon))
+            };
             if !player.input_prev.prev_weapon && player.input.prev_weapon {
-                let prev = (player.cur_weapon as usize + Weapon::COUNT - 1) % Weapon::COUNT;
-                player.cur_weapon = Weapon::from_repr(prev).unwrap();
+                for _ in 0..Weapon::COUNT {
+                    player.cur_weapon = order.prev(player.cur_weapon);
+                    if usable(player.cur_weapon) {
+                        break;
+                    }
+                }
             }
             if !player.input_prev.next_weapon && player.input.next_weapon {
-                let next = (player.cur_weapon as usize + 1) % Weapon::COUNT;
-                player.cur_weapon = Weapon::from_repr(next).unwrap();
+                for _ in 0..Weapon::COUNT {
+                    player.cur_weapon = order.next(player.cur_weapon);
+                    if usable(player.cur_weapon) {
+                        break;
+                    }
+                }
+            }
+
+            // Restarts on each change instead of adding up when cycling through several weapons.
+            if player.cur_weapon != old_weapon {
+                if let Some(vehicle) = player.vehicle.and_then(|h| self.gs.vehicles.get_mut(h)) {
+                    vehicle.weapon_ready_time = self.gs.game_time + self.cvars.g_weapon_switch_time;
+                    vehicle.buffered_fire = None;
+                }
+            }
+        }
+    }
+
+    /// HP above full slowly goes back down to full.
+    pub fn sys_overheal_decay(&mut self) {
+        for (_, vehicle) in self.gs.vehicles.iter_mut() {
+            if vehicle.hp_fraction > 1.0 {
+                let decay = self.cvars.g_overheal_decay * self.gs.dt;
+                vehicle.hp_fraction = (vehicle.hp_fraction - decay).max(1.0);
+            }
+        }
+    }
+
+    /// Start boosting when the input is held and the cooldown is over, then drop expired modifiers.
+    pub fn sys_boost(&mut self) {
+        let game_time = self.gs.game_time;
+        for (_, vehicle) in self.gs.vehicles.iter_mut() {
+            if !vehicle.destroyed() && vehicle.sinking.is_none() {
+                let Some(player) = self.gs.players.soft_get(vehicle.owner) else {
+                    continue;
+                };
+                let input = if player.guided_missile.is_some() {
+                    player.input.vehicle_while_guiding()
+                } else {
+                    player.input
+                };
+                if input.boost && game_time >= vehicle.boost_ready_time {
+                    vehicle.boost_until = game_time + self.cvars.g_boost_duration;
+                    vehicle.boost_ready_time = vehicle.boost_until + self.cvars.g_boost_cooldown;
+                    vehicle.modifiers.add(StatModifier {
+                        mobility: self.cvars.g_boost_factor,
+                        turn: 1.0,
+                        turn_effectiveness: self.cvars.g_boost_turn_effectiveness,
+                        until: vehicle.boost_until,
+                    });
+                }
             }
+
+            vehicle.modifiers.remove_expired(game_time);
         }
     }
 
@@ -246,183 +473,218 @@ impl ServerFrameCtx<'_> {
             // This should run even while dead, otherwise the ammo indicator will be buggy.
             // Original RW also reloaded while dead.
 
-            let player = &self.gs.players[vehicle.owner];
+            let Some(player) = self.gs.players.soft_get(vehicle.owner) else {
+                continue;
+            };
 
             // Turret turning
-            if !player.input_prev.turret_left && player.input.turret_left {
-                vehicle.turret_angle_wanted -= self.cvars.g_turret_turn_step_angle_deg.to_radians();
+            let slaved = self.cvars.g_vehicle_turret_slaved(vehicle.veh_type);
+            let turn_left = !player.input_prev.turret_left && player.input.turret_left;
+            let turn_right = !player.input_prev.turret_right && player.input.turret_right;
+            if slaved != TurretSlaved::Hull {
+                let step = self.cvars.g_turret_turn_step_angle_deg.to_radians();
+                if turn_left {
+                    vehicle.turret_angle_wanted -= step;
+                }
+                if turn_right {
+                    vehicle.turret_angle_wanted += step;
+                }
+                if turn_left || turn_right {
+                    vehicle.turret_override_until =
+                        self.gs.game_time + self.cvars.g_turret_slaved_override_time;
+                }
             }
-            if !player.input_prev.turret_right && player.input.turret_right {
-                vehicle.turret_angle_wanted += self.cvars.g_turret_turn_step_angle_deg.to_radians();
+            match slaved {
+                TurretSlaved::Free => {}
+                TurretSlaved::Hull => vehicle.turret_angle_wanted = 0.0,
+                TurretSlaved::Velocity => {
+                    if self.gs.game_time >= vehicle.turret_override_until {
+                        if let Some(angle) = vehicle.velocity_turret_angle(self.cvars) {
+                            vehicle.turret_angle_wanted = angle;
+                        }
+                    }
+                }
             }
             vehicle.turret_angle_wanted = vehicle.turret_angle_wanted.rem_euclid(2.0 * PI);
 
-            let delta = vehicle
-                .turret_angle_current
-                .delta_angle(vehicle.turret_angle_wanted);
-            let change =
-                self.cvars.g_turret_turn_speed_deg.to_radians() * self.gs.dt * delta.signum();
-            let change_clamped = change.clamped(-delta.abs(), delta.abs());
-            vehicle.turret_angle_current += change_clamped;
-            vehicle.turret_angle_current = vehicle.turret_angle_current.rem_euclid(2.0 * PI);
+            // Wrecks keep the turret where it was at death.
+            // The wanted angle still changes so players can pre-aim for respawn.
+            if vehicle.destroyed() {
+                vehicle.turret_turn_rate = 0.0;
+            } else {
+                turret_turning(self.cvars, vehicle, self.gs.dt);
+            }
 
             // Reloading
-            let ammo = &mut vehicle.ammos[player.cur_weapon as usize];
-            if let Ammo::Reloading(_, end) = ammo {
-                if self.gs.game_time >= *end {
-                    *ammo = Ammo::Loaded(
-                        self.gs.game_time,
-                        self.cvars.g_weapon_reload_ammo(player.cur_weapon),
-                    );
-                }
-            }
+            let weapon = player.cur_weapon;
+            vehicle.weapon_states[weapon as usize].tick(self.cvars, weapon, self.gs.game_time);
         }
     }
 
     pub fn sys_shooting(&mut self) {
         let mut new_projectiles = Vec::new();
         for (_, vehicle) in self.gs.vehicles.iter_mut() {
-            let player = &mut self.gs.players[vehicle.owner];
-            // Note: vehicles can shoot while controlling a missile
-            if vehicle.destroyed() || !player.input.fire {
+            let Some(player) = self.gs.players.soft_get_mut(vehicle.owner) else {
+                continue;
+            };
+            if vehicle.destroyed() || vehicle.sinking.is_some() {
+                vehicle.pending_burst = None;
+                vehicle.buffered_fire = None;
+                continue;
+            }
+            if vehicle.materializing(self.cvars, self.gs.game_time) {
                 continue;
             }
 
-            let ammo = &mut vehicle.ammos[player.cur_weapon as usize];
-            if let Ammo::Loaded(ready_time, count) = ammo {
-                if self.gs.game_time < *ready_time {
+            // Note: vehicles can shoot while controlling a missile
+            let weapon;
+            let alt_side;
+            if let Some(burst) = &vehicle.pending_burst {
+                // The rest of a burst fires without holding the trigger
+                // and doesn't get interrupted by switching weapons.
+                if self.gs.game_time < burst.next_shot_time {
+                    continue;
+                }
+                weapon = Weapon::Rockets;
+                alt_side = burst.alt_side;
+                if weapons::muzzle_blocked(self.cvars, self.map, vehicle, weapon, alt_side) {
+                    // Not saved for later, it would be a surprise when the muzzle clears.
+                    vehicle.pending_burst = None;
+                    vehicle.fire_blocked_time = self.gs.game_time;
                     continue;
                 }
 
-                *ready_time = self.gs.game_time + self.cvars.g_weapon_refire(player.cur_weapon);
-                *count -= 1;
-                if *count == 0 {
-                    let reload_time = self.cvars.g_weapon_reload_time(player.cur_weapon);
-                    *ammo = Ammo::Reloading(self.gs.game_time, self.gs.game_time + reload_time);
+                let burst = vehicle.pending_burst.as_mut().unwrap();
+                burst.remaining -= 1;
+                burst.next_shot_time += self.cvars.g_rockets_burst_interval;
+                burst.alt_side = !burst.alt_side;
+
+                let state = &mut vehicle.weapon_states[weapon as usize];
+                if state.is_reloading() {
+                    vehicle.pending_burst = None;
+                    continue;
+                }
+                state.consume_round(self.cvars, weapon, self.gs.game_time);
+                if state.is_reloading() || burst.remaining == 0 {
+                    vehicle.pending_burst = None;
+                }
+            } else {
+                weapon = player.cur_weapon;
+                alt_side = false;
+                // Switching skips weapons the vehicle can't use,
+                // this only happens when the cvars change mid-match.
+                if !self
+                    .cvars
+                    .g_vehicle_weapons(vehicle.veh_type)
+                    .contains(weapon)
+                {
+                    continue;
                 }
 
-                let (hardpoint, weapon_offset) =
-                    self.cvars.g_hardpoint(vehicle.veh_type, player.cur_weapon);
-                let (shot_angle, shot_origin);
-                match hardpoint {
-                    Hardpoint::Chassis => {
-                        shot_angle = vehicle.angle;
-                        shot_origin = vehicle.pos + weapon_offset.rotated_z(shot_angle);
-                    }
-                    Hardpoint::Turret => {
-                        shot_angle = vehicle.angle + vehicle.turret_angle_current;
-                        let turret_offset =
-                            self.cvars.g_vehicle_turret_offset_chassis(vehicle.veh_type);
-                        shot_origin = vehicle.pos
-                            + turret_offset.rotated_z(vehicle.angle)
-                            + weapon_offset.rotated_z(shot_angle);
-                    }
+                // Remember a press which came a little too early.
+                let state = &vehicle.weapon_states[weapon as usize];
+                let ready_time = state.ready_time().max(vehicle.weapon_ready_time);
+                let pressed = player.input.fire && !player.input_prev.fire;
+                if pressed
+                    && self.gs.game_time < ready_time
+                    && ready_time - self.gs.game_time <= self.cvars.g_fire_buffer_window
+                {
+                    vehicle.buffered_fire = Some(weapon);
+                }
+                if self.cvars.g_fire_buffer_require_held && !player.input.fire {
+                    vehicle.buffered_fire = None;
                 }
+                let buffered = vehicle.buffered_fire == Some(weapon);
 
-                // Some sane defaults to be overriden later
-                let mut projectile = Projectile {
-                    weapon: Weapon::Mg,
-                    pos: shot_origin,
-                    vel: Vec2f::zero(), // LATER hardpoint vel? -> more vel if vehicle is turning?
-                    angle: shot_angle,
-                    turn_rate: 0.0, // LATER vehicle turn angle?
-                    explode_time: f64::MAX,
-                    owner: vehicle.owner,
-                    target: None,
-                };
+                if !(player.input.fire || buffered) || self.gs.game_time < vehicle.weapon_ready_time
+                {
+                    continue;
+                }
+                if !vehicle.weapon_states[weapon as usize].can_fire(self.gs.game_time) {
+                    continue;
+                }
+                // Whether it fires or not, a buffered press is used up.
+                vehicle.buffered_fire = None;
+                // No ammo used and no refire delay - it's as if the trigger wasn't pulled.
+                if weapons::muzzle_blocked(self.cvars, self.map, vehicle, weapon, false) {
+                    vehicle.fire_blocked_time = self.gs.game_time;
+                    continue;
+                }
 
-                match player.cur_weapon {
-                    Weapon::Mg => {
-                        let r: f64 = self.sg.rng.sample(StandardNormal);
-                        let spread = self.cvars.g_machine_gun_angle_spread * r;
-                        // Using spread as shot_vel.y would mean the resulting spread depends on speed
-                        // so it's better to use spread on angle.
-                        projectile.vel = Vec2f::new(self.cvars.g_machine_gun_speed, 0.0)
-                            .rotated_z(shot_angle + spread)
-                            + self.cvars.g_machine_gun_vehicle_velocity_factor * vehicle.vel;
-                        let handle = self.gs.projectiles.insert(projectile);
-                        new_projectiles.push(handle);
-                    }
-                    Weapon::Rail => {
-                        projectile.weapon = Weapon::Rail;
-                        projectile.vel = Vec2f::new(self.cvars.g_railgun_speed, 0.0)
-                            .rotated_z(shot_angle)
-                            + self.cvars.g_railgun_vehicle_velocity_factor * vehicle.vel;
-                        let handle = self.gs.projectiles.insert(projectile);
-                        new_projectiles.push(handle);
-                    }
-                    Weapon::Cb => {
-                        projectile.weapon = Weapon::Cb;
-                        for _ in 0..self.cvars.g_cluster_bomb_count {
-                            let speed = self.cvars.g_cluster_bomb_speed;
-                            let spread_forward;
-                            let spread_sideways;
-                            if self.cvars.g_cluster_bomb_speed_spread_gaussian {
-                                let r: f64 = self.sg.rng.sample(StandardNormal);
-                                spread_forward = self.cvars.g_cluster_bomb_speed_spread_forward * r;
-                                let r: f64 = self.sg.rng.sample(StandardNormal);
-                                spread_sideways =
-                                    self.cvars.g_cluster_bomb_speed_spread_sideways * r;
-                            } else {
-                                let r = self.sg.rng.sample(self.gs.range_uniform11);
-                                spread_forward = self.cvars.g_cluster_bomb_speed_spread_forward * r;
-                                let r = self.sg.rng.sample(self.gs.range_uniform11);
-                                spread_sideways =
-                                    self.cvars.g_cluster_bomb_speed_spread_sideways * r;
-                            }
-                            projectile.vel = Vec2f::new(speed + spread_forward, spread_sideways)
-                                .rotated_z(shot_angle)
-                                + self.cvars.g_cluster_bomb_vehicle_velocity_factor * vehicle.vel;
-                            projectile.explode_time = self.gs.game_time
-                                + self.cvars.g_cluster_bomb_time
-                                + self.sg.rng.sample(self.gs.range_uniform11)
-                                    * self.cvars.g_cluster_bomb_time_spread;
-                            let handle = self.gs.projectiles.insert(projectile.clone());
-                            new_projectiles.push(handle);
-                        }
-                    }
-                    Weapon::Rockets => {
-                        projectile.weapon = Weapon::Rockets;
-                        projectile.vel = Vec2f::new(self.cvars.g_rockets_speed, 0.0)
-                            .rotated_z(shot_angle)
-                            + self.cvars.g_rockets_vehicle_velocity_factor * vehicle.vel;
-                        let handle = self.gs.projectiles.insert(projectile);
-                        new_projectiles.push(handle);
-                    }
-                    Weapon::Hm => {
-                        projectile.weapon = Weapon::Hm;
-                        projectile.vel = Vec2f::new(self.cvars.g_homing_missile_speed_initial, 0.0)
-                            .rotated_z(shot_angle)
-                            + self.cvars.g_homing_missile_vehicle_velocity_factor * vehicle.vel;
-                        let handle = self.gs.projectiles.insert(projectile);
-                        new_projectiles.push(handle);
-                    }
-                    Weapon::Gm => {
-                        projectile.weapon = Weapon::Gm;
-                        projectile.vel = Vec2f::new(self.cvars.g_guided_missile_speed_initial, 0.0)
-                            .rotated_z(shot_angle)
-                            + self.cvars.g_guided_missile_vehicle_velocity_factor * vehicle.vel;
-                        // LATER Set angle according to vehicle angle (also some other weaps)
-                        let handle = self.gs.projectiles.insert(projectile);
-                        new_projectiles.push(handle);
-                        player.guided_missile = Some(handle);
-                    }
-                    Weapon::Bfg => {
-                        projectile.weapon = Weapon::Bfg;
-                        projectile.vel = Vec2f::new(self.cvars.g_bfg_speed, 0.0)
-                            .rotated_z(shot_angle)
-                            + self.cvars.g_bfg_vehicle_velocity_factor * vehicle.vel;
-                        let handle = self.gs.projectiles.insert(projectile);
-                        new_projectiles.push(handle);
-                    }
+                let state = &mut vehicle.weapon_states[weapon as usize];
+                state.fire(self.cvars, weapon, self.gs.game_time);
+                if !state.is_reloading()
+                    && weapon == Weapon::Rockets
+                    && self.cvars.g_rockets_burst_count > 1
+                {
+                    vehicle.pending_burst = Some(Burst {
+                        remaining: self.cvars.g_rockets_burst_count - 1,
+                        next_shot_time: self.gs.game_time + self.cvars.g_rockets_burst_interval,
+                        alt_side: true,
+                    });
+                }
+            }
+
+            let (shot_angle, shot_origin) = if alt_side {
+                weapons::rockets_alt_angle_origin(self.cvars, vehicle)
+            } else {
+                weapons::shot_angle_origin(self.cvars, vehicle, weapon)
+            };
+
+            let projectiles = weapon_projectiles(
+                self.cvars,
+                &mut self.sg.rng,
+                self.gs.range_uniform11,
+                weapon,
+                shot_angle,
+                shot_origin,
+                vehicle.vel,
+                vehicle.owner,
+                self.gs.game_time,
+            );
+            for projectile in projectiles {
+                let handle = self.gs.projectiles.insert(projectile);
+                new_projectiles.push(handle);
+                if weapon == Weapon::Gm {
+                    player.guided_missile = Some(handle);
                 }
             }
         }
 
+        self.projectiles_spawned(new_projectiles);
+    }
+
+    /// Set up the arc of lobbed projectiles which were just created and tell clients about them.
+    pub fn projectiles_spawned(&mut self, new_projectiles: Vec<Index>) {
         for handle in new_projectiles {
+            let owner = self.gs.projectiles[handle].owner;
+            // Unmanned emplacements fire projectiles with no owner and no team.
+            let team = self.gs.players.get(owner).and_then(|player| player.team);
+            let projectile = &mut self.gs.projectiles[handle];
+            projectile.team = team;
+            let apex = self.cvars.g_weapon_arc(projectile.weapon);
+            if apex > 0.0 {
+                let arc_time = self.cvars.g_weapon_arc_time(projectile.weapon);
+                let duration = if arc_time > 0.0 {
+                    // Comes down before the fuse runs out - explode on landing.
+                    projectile.explode_time =
+                        projectile.explode_time.min(self.gs.game_time + arc_time);
+                    arc_time
+                } else {
+                    projectile.explode_time - self.gs.game_time
+                };
+                projectile.flight = Some(Flight {
+                    start_time: self.gs.game_time,
+                    duration,
+                    apex,
+                });
+            }
+
+            let id = self.gs.projectile_ids.assign(handle);
             let projectile = &self.gs.projectiles[handle];
             let spawn = ProjectileInit {
                 index: handle.slot(),
+                id,
                 weapon: projectile.weapon,
                 physics: EntityPhysics {
                     pos: projectile.pos,
@@ -432,6 +694,11 @@ impl ServerFrameCtx<'_> {
                 },
                 explode_time: projectile.explode_time,
                 owner: projectile.owner.slot(),
+                flight: projectile.flight,
+                spawn_time: projectile.spawn_time,
+                team: projectile.team,
+                // New missiles aren't locked yet, locks are sent in `HmLock`.
+                target: None,
             };
             let msg = ServerMessage::SpawnProjectile(spawn);
             self.net_send_all(msg);
@@ -444,46 +711,192 @@ impl ServerFrameCtx<'_> {
         target.hms.swap_remove(index);
     }
 
+    /// Secondary fire - what it does depends on the current weapon, see `AltFire`.
+    pub fn sys_alt_fire(&mut self) {
+        for player_handle in self.gs.players.collect_handles() {
+            let player = &self.gs.players[player_handle];
+            if player.input_prev.fire_alt || !player.input.fire_alt {
+                continue;
+            }
+            let Some(vehicle_handle) = player.vehicle else {
+                continue;
+            };
+            let vehicle = &self.gs.vehicles[vehicle_handle];
+            if vehicle.destroyed() || vehicle.materializing(self.cvars, self.gs.game_time) {
+                continue;
+            }
+
+            match self.cvars.g_weapon_altfire(player.cur_weapon) {
+                AltFire::None => {}
+                AltFire::DetonateGm => {
+                    if let Some(gm_handle) = player.guided_missile {
+                        let hit_pos = self.gs.projectiles[gm_handle].pos; // borrowck dance
+                        self.projectile_impact(gm_handle, hit_pos, false);
+                    }
+                }
+                AltFire::DetonateCb => {
+                    let min_age = self.cvars.g_cluster_bomb_detonate_min_age;
+                    for projectile_handle in self.gs.projectiles.collect_handles() {
+                        let projectile = &self.gs.projectiles[projectile_handle];
+                        let age = self.gs.game_time - projectile.spawn_time;
+                        if projectile.weapon == Weapon::Cb
+                            && projectile.owner == player_handle
+                            && age >= min_age
+                        {
+                            let hit_pos = projectile.pos; // borrowck dance
+                            self.projectile_impact(projectile_handle, hit_pos, false);
+                        }
+                    }
+                }
+                AltFire::Smoke => self.deploy_smoke(player_handle, vehicle_handle),
+                AltFire::Flares => self.deploy_flares(player_handle, vehicle_handle),
+                AltFire::DropMine => self.drop_mine(player_handle, vehicle_handle),
+            }
+        }
+    }
+
+    fn deploy_smoke(&mut self, player_handle: Index, vehicle_handle: Index) {
+        let player = &mut self.gs.players[player_handle];
+        if self.gs.game_time < player.smoke_time + self.cvars.g_smoke_cooldown {
+            return;
+        }
+        player.smoke_time = self.gs.game_time;
+
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        let pos = vehicle.pos - vehicle.angle.to_vec2f() * self.cvars.g_smoke_offset;
+        let radius = self.cvars.g_smoke_radius;
+        let smoke = Smoke::new(pos, radius, self.gs.game_time, player_handle);
+        self.gs.smokes.push(smoke);
+
+        let init = SmokeInit {
+            pos,
+            radius,
+            owner: player_handle.slot(),
+            game_time: self.gs.game_time,
+        };
+        self.net_send_all(ServerMessage::SpawnSmoke(init));
+    }
+
+    /// Throw out a burst of flares behind the vehicle if it has any left.
+    ///
+    /// Public because bots use it directly, see `sys_ai_flares`.
+    pub fn deploy_flares(&mut self, player_handle: Index, vehicle_handle: Index) {
+        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+        if vehicle.flares == 0
+            || self.gs.game_time < vehicle.flare_time + self.cvars.g_flare_cooldown
+        {
+            return;
+        }
+        vehicle.flares -= 1;
+        vehicle.flare_time = self.gs.game_time;
+
+        let back = -vehicle.angle.to_vec2f();
+        let pos = vehicle.pos + back * self.cvars.g_flare_offset;
+        let spread = self.cvars.g_flare_angle_spread;
+        let mut vels = Vec::new();
+        for _ in 0..self.cvars.g_flare_burst {
+            let dir = back.rotated_z(self.sg.rng.gen_range(-spread..=spread));
+            let vel = vehicle.vel + dir * self.cvars.g_flare_speed;
+            vels.push(vel);
+            let flare = Flare::new(pos, vel, self.gs.game_time, player_handle);
+            self.gs.flares.insert(flare);
+        }
+
+        let init = FlaresInit {
+            pos,
+            vels,
+            owner: player_handle.slot(),
+            game_time: self.gs.game_time,
+        };
+        self.net_send_all(ServerMessage::SpawnFlares(init));
+    }
+
     /// The *homing* part of homing missile
+    ///
+    /// The seeker only sees targets within `g_homing_missile_seeker_fov` and `g_homing_missile_seeker_range`.
+    /// After losing its target, the missile flies straight looking for a new one
+    /// for `g_homing_missile_search_time`, then gives up according to `g_homing_missile_lost`.
+    /// Clients are told which vehicle each missile is locked onto for their lock warning.
     pub fn sys_hm_turning(&mut self) {
+        let game_time = self.gs.game_time;
+        // Sent after the loop because the projectiles are borrowed.
+        let mut lock_changes = Vec::new();
+        let vehicle_of = |target: Option<HmTarget>| match target {
+            Some(HmTarget::Vehicle(vehicle_handle)) => Some(vehicle_handle),
+            _ => None,
+        };
+
         for (hm_handle, hm) in self
             .gs
             .projectiles
             .iter_mut()
             .filter(|(_, proj)| proj.weapon == Weapon::Hm)
         {
+            // Inert missiles have no target and never look for one.
+            let seeking = hm.seeker != Seeker::Inert;
+            let target_before = hm.target;
+
             // Forget target under some conditions
-            if let Some(target_handle) = hm.target {
-                if !self.gs.vehicles.contains(target_handle) {
-                    // Vehicle is gone (player disconnected)
+            if let Some(HmTarget::Flare(flare_handle)) = hm.target {
+                // Flares only fool it for a while, then it goes back to looking for vehicles.
+                let spoofing = self
+                    .gs
+                    .flares
+                    .get(flare_handle)
+                    .is_some_and(|flare| flare.spoofing(self.cvars, game_time));
+                if !spoofing {
                     hm.target = None;
-                    continue;
                 }
+            }
+            if let Some(HmTarget::Vehicle(target_handle)) = hm.target {
+                if let Some(target) = self.gs.vehicles.get_mut(target_handle) {
+                    // Walls only make it lose track after a while so it can follow targets around corners.
+                    let occluded = self.map.is_wall_trace(hm.pos, target.pos).is_some();
+                    let occluded_since = match hm.seeker {
+                        Seeker::Active { occluded_since } if occluded => {
+                            Some(occluded_since.unwrap_or(game_time))
+                        }
+                        _ => None,
+                    };
+                    hm.seeker = Seeker::Active { occluded_since };
+                    let occluded_too_long = occluded_since.is_some_and(|since| {
+                        game_time - since > self.cvars.g_homing_missile_occlusion_grace
+                    });
 
-                let target = &mut self.gs.vehicles[target_handle];
-                if target.destroyed() {
-                    Self::hm_forget(hm_handle, hm, target);
-                    continue;
+                    // Smoke only makes it lose track, it can still pick another target below.
+                    if target.destroyed()
+                        || occluded_too_long
+                        || weapons::hm_seeker_angle(self.cvars, hm, target.pos).is_none()
+                        || smoke_blocks_view(&self.gs.smokes, hm.pos, target.pos)
+                    {
+                        Self::hm_forget(hm_handle, hm, target);
+                    }
+                } else {
+                    // Vehicle is gone (player disconnected)
+                    hm.target = None;
                 }
+            }
 
-                let target_dir = (target.pos - hm.pos).normalized();
-                let target_angle = target_dir.to_angle();
-
-                let mut angle_diff = (target_angle - hm.angle).rem_euclid(2.0 * PI);
-                if angle_diff > PI {
-                    angle_diff -= 2.0 * PI;
-                }
-                if angle_diff.abs() > self.cvars.g_homing_missile_angle_forget {
-                    Self::hm_forget(hm_handle, hm, target);
+            // Hot flares are more attractive than vehicles, even the one it's locked onto.
+            if seeking && !matches!(hm.target, Some(HmTarget::Flare(_))) {
+                let flare = weapons::hm_flare_target(
+                    self.cvars,
+                    self.map,
+                    &self.gs.flares,
+                    &self.gs.smokes,
+                    game_time,
+                    hm,
+                );
+                if let Some(flare_handle) = flare {
+                    if let Some(HmTarget::Vehicle(target_handle)) = hm.target {
+                        Self::hm_forget(hm_handle, hm, &mut self.gs.vehicles[target_handle]);
+                    }
+                    hm.target = Some(HmTarget::Flare(flare_handle));
                 }
             }
 
             // Pick new target
-            if hm.target.is_none() {
-                // Where the missile is aiming.
-                // Not using velocity because it can move sieways sometimes (especially during launch).
-                let forward_dir = hm.angle.to_vec2f();
-
+            if seeking && hm.target.is_none() {
                 let mut best_target = None;
                 let mut best_target_angle_diff = f64::INFINITY;
 
@@ -493,12 +906,16 @@ impl ServerFrameCtx<'_> {
                         continue;
                     }
 
-                    let target_dir = (vehicle.pos - hm.pos).normalized();
-                    let dot = forward_dir.dot(target_dir);
-                    let angle_diff = dot.acos();
+                    // Where the missile is aiming.
+                    // Not using velocity because it can move sieways sometimes (especially during launch).
+                    let Some(angle_diff) = weapons::hm_seeker_angle(self.cvars, hm, vehicle.pos)
+                    else {
+                        continue;
+                    };
                     if angle_diff < self.cvars.g_homing_missile_angle_detect
                         && angle_diff < best_target_angle_diff
                         && self.map.is_wall_trace(hm.pos, vehicle.pos).is_none()
+                        && !smoke_blocks_view(&self.gs.smokes, hm.pos, vehicle.pos)
                     {
                         best_target = Some(vehicle_handle);
                         best_target_angle_diff = angle_diff;
@@ -506,16 +923,44 @@ impl ServerFrameCtx<'_> {
                 }
 
                 if let Some(best_target) = best_target {
-                    hm.target = Some(best_target);
+                    hm.target = Some(HmTarget::Vehicle(best_target));
                     self.gs.vehicles[best_target].hms.push(hm_handle);
                 }
             }
 
+            // Search after losing the target, give up if nothing turns up
+            if hm.target != target_before {
+                hm.seeker = if hm.target.is_none() {
+                    Seeker::Searching(game_time)
+                } else {
+                    Seeker::default()
+                };
+            }
+            if let Seeker::Searching(since) = hm.seeker {
+                if game_time - since >= self.cvars.g_homing_missile_search_time {
+                    match self.cvars.g_homing_missile_lost {
+                        // Exploded by `sys_projectiles_timeout`.
+                        HmLost::Detonate => hm.explode_time = hm.explode_time.min(game_time),
+                        HmLost::Inert => hm.seeker = Seeker::Inert,
+                    }
+                }
+            }
+
+            let vehicle_after = vehicle_of(hm.target);
+            if vehicle_after != vehicle_of(target_before) {
+                lock_changes.push((hm_handle, vehicle_after));
+            }
+
             // Determine direction
             let mut input = NetInput::new_up();
-            if let Some(target_handle) = hm.target {
-                let target = &self.gs.vehicles[target_handle];
-                let target_dir = (target.pos - hm.pos).normalized();
+            let target_pos = hm.target.map(|target| match target {
+                HmTarget::Vehicle(vehicle_handle) => self.gs.vehicles[vehicle_handle].pos,
+                HmTarget::Flare(flare_handle) => {
+                    self.gs.flares[flare_handle].pos_at(self.gs.game_time)
+                }
+            });
+            if let Some(target_pos) = target_pos {
+                let target_dir = (target_pos - hm.pos).normalized();
                 let target_angle = target_dir.to_angle();
 
                 let angle_diff = (target_angle - hm.angle).rem_euclid(2.0 * PI);
@@ -540,6 +985,14 @@ impl ServerFrameCtx<'_> {
             );
             Self::accel_decel(&stats, &mut hm.vel, hm.angle, input, self.gs.dt);
         }
+
+        for (hm_handle, vehicle_handle) in lock_changes {
+            let msg = ServerMessage::HmLock {
+                index: hm_handle.slot(),
+                target: vehicle_handle.map(|handle| handle.slot()),
+            };
+            self.net_send_all(msg);
+        }
     }
 
     /// The *guided* part of guided missile
@@ -550,42 +1003,88 @@ impl ServerFrameCtx<'_> {
             .iter_mut()
             .filter(|(_, proj)| proj.weapon == Weapon::Gm)
         {
-            let stats = self.cvars.g_guided_missile_movement_stats();
-            let player = &self.gs.players[gm.owner];
-
-            // Only allow guiding the most recently launched missile
-            let input = if player.guided_missile == Some(gm_handle) {
-                player.input.missile_while_guiding()
-            } else {
-                NetInput::new_up()
+            // Only allow guiding the most recently launched missile.
+            // Orphaned missiles (owner left) just fly straight.
+            let input = match self.gs.players.get(gm.owner) {
+                Some(player) if player.guided_missile == Some(gm_handle) => {
+                    player.input.missile_while_guiding()
+                }
+                _ => NetInput::new_up(),
             };
 
-            gm.angle = Self::turning(
-                &stats,
-                &mut gm.vel,
-                &gm.angle,
-                &mut gm.turn_rate,
-                input,
-                self.gs.dt,
-            );
+            let motion = weapons::GmMotion::new(gm).steer(self.cvars, input, self.gs.dt);
+            motion.apply(gm);
+        }
+    }
 
-            Self::accel_decel(&stats, &mut gm.vel, gm.angle, input, self.gs.dt);
+    /// Everything projectiles can currently hit.
+    ///
+    /// Vehicles come first in arena order, then emplacements, then wrecks.
+    pub fn projectile_targets(&self) -> Vec<Targetable> {
+        let mut targets = Vec::new();
+        for (vehicle_handle, vehicle) in &self.gs.vehicles {
+            if vehicle.destroyed() {
+                continue;
+            }
+            targets.push(Targetable {
+                kind: TargetKind::Vehicle(vehicle_handle),
+                pos: vehicle.pos,
+                radius: self.cvars.g_hitcircle_radius,
+                takes_damage: true,
+                blocks_projectiles: true,
+            });
+        }
+        for (emplacement_handle, emplacement) in &self.gs.emplacements {
+            // Same as destroyed vehicles, projectiles fly over the remains.
+            if emplacement.destroyed() {
+                continue;
+            }
+            targets.push(Targetable {
+                kind: TargetKind::Emplacement(emplacement_handle),
+                pos: emplacement.pos,
+                radius: self.cvars.g_emplacement_radius,
+                takes_damage: true,
+                blocks_projectiles: true,
+            });
+        }
+        if self.cvars.g_wrecks_block_projectiles {
+            for (i, wreck) in self.gs.wrecks.iter().enumerate() {
+                targets.push(Targetable {
+                    kind: TargetKind::Wreck(i),
+                    pos: wreck.pos,
+                    radius: self.cvars.g_hitcircle_radius,
+                    takes_damage: false,
+                    blocks_projectiles: true,
+                });
+            }
         }
+        targets
     }
 
     /// Projectile movement and collisions / hit detection.
     /// Traces the projectile's path between positions to avoid passing through thin objects.
     pub fn sys_projectiles(&mut self) {
+        // Once per tick, not per projectile - MG streams and CB volleys mean a lot of them.
+        // Targets destroyed during the tick are skipped by the `projectile_hit_*` fns.
+        let targets = self.projectile_targets();
+        // Reused by all projectiles.
+        let mut damageable: Vec<&Targetable> = Vec::new();
+
         for proj_handle in self.gs.projectiles.collect_handles() {
             let projectile = &mut self.gs.projectiles[proj_handle];
             let max_new_pos = projectile.pos + projectile.vel * self.gs.dt;
 
-            if projectile.weapon == Weapon::Cb {
+            // Flies over walls and vehicles.
+            if projectile.airborne(self.cvars, self.gs.game_time) {
                 projectile.pos = max_new_pos;
                 continue;
             }
 
-            let maybe_collision = self.map.is_wall_trace(projectile.pos, max_new_pos);
+            let maybe_wall = self.map.is_wall_trace(projectile.pos, max_new_pos);
+            // Targets which only block work like walls - the path ends at the nearest one.
+            let maybe_collision =
+                nearest_blocker(&targets, projectile.pos, maybe_wall.unwrap_or(max_new_pos))
+                    .or(maybe_wall);
             let new_pos = if let Some(hit_pos) = maybe_collision {
                 hit_pos
             } else {
@@ -599,7 +1098,6 @@ impl ServerFrameCtx<'_> {
                 start: projectile.pos,
                 end: new_pos,
             };
-            let step_dir = (new_pos - projectile.pos).normalized();
 
             projectile.pos = new_pos;
             if self.cvars.d_projectiles {
@@ -608,51 +1106,43 @@ impl ServerFrameCtx<'_> {
 
             let is_rail = projectile.weapon == Weapon::Rail;
             if is_rail {
-                let beam = RailBeam::new(step.start, step.end, self.gs.game_time);
+                let beam = RailBeam::new(step.start, step.end, self.gs.game_time, projectile.team);
                 let msg = ServerMessage::RailBeam(beam);
                 self.net_send_all(msg);
             }
 
-            for vehicle_handle in self.gs.vehicles.collect_handles() {
-                // LATER immediately killing vehicles here means 2 players can't share a kill
-                let vehicle = &mut self.gs.vehicles[vehicle_handle];
-
-                // borrowck dance - reborrow each iteration of the loop
-                // so the borrow ends before we pass `gs` to other functions.
-                let projectile = &self.gs.projectiles[proj_handle];
-
-                if vehicle.destroyed()
-                    || vehicle.owner == projectile.owner
-                    || (is_rail && self.gs.rail_hits.get(&proj_handle) == Some(&vehicle_handle))
-                {
-                    continue;
-                }
-
-                let nearest_point = step.projected_point(vehicle.pos);
-                let dist2 = nearest_point.distance_squared(vehicle.pos);
-                if dist2 <= self.cvars.g_hitcircle_radius * self.cvars.g_hitcircle_radius {
-                    if self.cvars.d_tracing {
-                        dbg_cross!(nearest_point, 0.5);
-                    }
-                    let dmg = self.cvars.g_weapon_damage_direct(projectile.weapon);
-
-                    if is_rail {
-                        self.gs.rail_hits.insert(proj_handle, vehicle_handle);
-                        vehicle.vel += step_dir * self.cvars.g_railgun_push;
+            // Railguns remember which targets they're inside of and BFG beams reach past the step
+            // so they need all targets. Everything else can only hit those the step touches,
+            // usually none.
+            let weapon = self.gs.projectiles[proj_handle].weapon;
+            let needs_all = matches!(weapon, Weapon::Rail | Weapon::Bfg);
+            damageable.clear();
+            damageable.extend(targets.iter().filter(|target| {
+                let dist2 = step
+                    .projected_point(target.pos)
+                    .distance_squared(target.pos);
+                target.takes_damage && (needs_all || dist2 <= target.radius * target.radius)
+            }));
+            // In the order the projectile reaches them so it stops at the first one
+            // and railguns hit them in order.
+            damageable.sort_by(|a, b| {
+                let a = step.projected_point(a.pos).distance_squared(step.start);
+                let b = step.projected_point(b.pos).distance_squared(step.start);
+                a.total_cmp(&b)
+            });
+            for target in &damageable {
+                let stopped = match target.kind {
+                    TargetKind::Vehicle(vehicle_handle) => {
+                        self.projectile_hit_vehicle(proj_handle, vehicle_handle, step)
                     }
-
-                    let attacker_handle = projectile.owner;
-                    self.damage(attacker_handle, vehicle_handle, dmg);
-                    if !is_rail {
-                        self.projectile_impact(proj_handle, nearest_point);
-                        break; // LATER actually ... what if the segment is long and 2 vehicles are in the path
+                    TargetKind::Emplacement(emplacement_handle) => {
+                        self.projectile_hit_emplacement(proj_handle, emplacement_handle, step)
                     }
-                } else if projectile.weapon == Weapon::Bfg
-                    && weapons::bfg_beam_hit(self.cvars, self.map, projectile.pos, vehicle.pos)
-                {
-                    let dmg = self.cvars.g_bfg_beam_damage_per_sec * self.gs.dt;
-                    let attacker_handle = projectile.owner;
-                    self.damage(attacker_handle, vehicle_handle, dmg);
+                    // Never damageable, handled as blockers above.
+                    TargetKind::Wreck(_) => false,
+                };
+                if stopped {
+                    break;
                 }
             }
 
@@ -661,52 +1151,206 @@ impl ServerFrameCtx<'_> {
                 // Otherwise this tries to remove the projectile a second time.
                 // We could set a flag when hitting vehicles above instead of `.contains` but this is more future-proof.
                 if self.gs.projectiles.contains(proj_handle) {
-                    self.projectile_impact(proj_handle, hit_pos);
-                    if is_rail {
-                        self.gs.rail_hits.remove(&proj_handle);
-                    }
+                    self.projectile_impact(proj_handle, hit_pos, false);
                 }
             }
         }
     }
 
-    pub fn damage(&mut self, attacker_handle: Index, vehicle_handle: Index, dmg_amount: f64) {
+    /// Check whether the projectile's `step` hits the vehicle and apply damage.
+    ///
+    /// Returns whether the projectile was stopped (and removed).
+    fn projectile_hit_vehicle(
+        &mut self,
+        proj_handle: Index,
+        vehicle_handle: Index,
+        step: LineSegment2<f64>,
+    ) -> bool {
+        // LATER immediately killing vehicles here means 2 players can't share a kill
         let vehicle = &mut self.gs.vehicles[vehicle_handle];
+        let projectile = &self.gs.projectiles[proj_handle];
+        let is_rail = projectile.weapon == Weapon::Rail;
 
-        soft_assert!(!vehicle.destroyed());
+        if vehicle.destroyed() || vehicle.owner == projectile.owner {
+            return false;
+        }
 
-        vehicle.hp_fraction -= dmg_amount / self.cvars.g_vehicle_hp(vehicle.veh_type);
+        let radius2 = self.cvars.g_hitcircle_radius * self.cvars.g_hitcircle_radius;
+        let nearest_point = step.projected_point(vehicle.pos);
+        let dist2 = nearest_point.distance_squared(vehicle.pos);
+        let hit = dist2 <= radius2;
 
-        // Not using 0.0 here because of floating point errors.
-        // Some weapons should reduce health to exact 0 in a small number of hits but it ends up being a tiny bit above it.
-        if vehicle.hp_fraction > 0.001 {
-            return;
+        if is_rail {
+            let ends_inside = hit && step.end.distance_squared(vehicle.pos) <= radius2;
+            let kind = TargetKind::Vehicle(vehicle_handle);
+            if rail_was_inside(&mut self.gs.rail_hits, proj_handle, kind, ends_inside) {
+                return false;
+            }
         }
 
-        // Vehicle got killed
-
-        vehicle.hp_fraction = 0.0;
-        let veh_owner = vehicle.owner; // Borrowck
-        let veh_pos = vehicle.pos; // Borrowck
-        self.spawn_explosion(veh_pos, 1.0, false);
-        self.gs.players[veh_owner].guided_missile = None; // No guiding after death
+        if hit {
+            if self.cvars.d_tracing {
+                dbg_cross!(nearest_point, 0.5);
+            }
+            let dmg = self.cvars.g_weapon_damage_direct(projectile.weapon);
+
+            if is_rail {
+                let step_dir = (step.end - step.start).normalized();
+                vehicle.vel += step_dir * self.cvars.g_railgun_push;
+            }
+
+            let attacker_handle = projectile.owner;
+            let weapon = projectile.weapon;
+            let dir = projectile.vel;
+            self.damage(
+                attacker_handle,
+                vehicle_handle,
+                dmg,
+                Some(weapon),
+                Some(dir),
+            );
+            if !is_rail {
+                self.projectile_impact(proj_handle, nearest_point, true);
+                return true;
+            }
+        } else if projectile.weapon == Weapon::Bfg
+            && weapons::bfg_beam_hit(
+                self.cvars,
+                self.map,
+                projectile.pos,
+                weapons::bfg_beam_target(projectile.pos, vehicle),
+            )
+        {
+            let dmg = self.cvars.g_bfg_beam_damage_per_sec * self.gs.dt;
+            let attacker_handle = projectile.owner;
+            let dir = vehicle.pos - projectile.pos;
+            self.damage(
+                attacker_handle,
+                vehicle_handle,
+                dmg,
+                Some(Weapon::Bfg),
+                Some(dir),
+            );
+        }
+        false
+    }
+
+    /// `weapon` is None for self-destruct.
+    ///
+    /// `dir` is the direction the hit was traveling for directional armor,
+    /// None if unknown (e.g. self-destruct).
+    pub fn damage(
+        &mut self,
+        attacker_handle: Index,
+        vehicle_handle: Index,
+        dmg_amount: f64,
+        weapon: Option<Weapon>,
+        dir: Option<Vec2f>,
+    ) {
+        let Some(vehicle) = self.gs.vehicles.soft_get_mut(vehicle_handle) else {
+            return;
+        };
+
+        soft_assert!(!vehicle.destroyed());
+
+        // Self-destruct still works.
+        if attacker_handle != vehicle.owner && vehicle.materializing(self.cvars, self.gs.game_time)
+        {
+            return;
+        }
+
+        let arc = dir.and_then(|dir| HitArc::classify(self.cvars, vehicle.angle, dir));
+        let dmg_amount = match arc {
+            Some(arc) => dmg_amount * self.cvars.g_vehicle_armor(vehicle.veh_type, arc),
+            None => dmg_amount,
+        };
+        let dmg_amount = dmg_amount * self.gs.damage_multiplier;
+
+        let victim_handle = vehicle.owner; // Borrowck
+        let dmg_amount =
+            self.hook_modify_damage(attacker_handle, victim_handle, weapon, dmg_amount);
+        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+
+        let hp = self.cvars.g_vehicle_hp(vehicle.veh_type);
+        if attacker_handle != vehicle.owner {
+            // Self-destruct deals f64::MAX, only count what the vehicle actually had.
+            let dealt = dmg_amount.min(vehicle.hp_fraction * hp);
+            // The attacker might have left, then there's nobody to credit.
+            if let Some(attacker_id) = self.gs.player_ids.id(attacker_handle) {
+                self.sg
+                    .match_stats
+                    .record_damage(attacker_id, self.gs.game_time, dealt);
+                vehicle
+                    .damage_ledger
+                    .record(self.cvars, attacker_id, self.gs.game_time, dealt);
+            }
+        }
+
+        vehicle.hp_fraction -= dmg_amount / hp;
+
+        // Not using 0.0 here because of floating point errors.
+        // Some weapons should reduce health to exact 0 in a small number of hits but it ends up being a tiny bit above it.
+        if vehicle.hp_fraction > 0.001 {
+            return;
+        }
+
+        // Vehicle got killed
+
+        vehicle.hp_fraction = 0.0;
+        let veh_owner = vehicle.owner; // Borrowck
+        let veh_pos = vehicle.pos; // Borrowck
+        let killer_id = self.gs.player_ids.id(attacker_handle);
+        let assist_ids = vehicle
+            .damage_ledger
+            .assists(self.cvars, killer_id, self.gs.game_time);
+        // Sunk vehicles just disappear under the surface.
+        if vehicle.sinking.is_none() {
+            self.spawn_explosion(veh_pos, 1.0, false, None);
+        }
+        let Some(victim) = self.gs.players.soft_get_mut(veh_owner) else {
+            return;
+        };
+        victim.guided_missile = None; // No guiding after death
+
+        // The attacker might have left while their projectile was still flying.
+        let attacker_handle = Some(attacker_handle).filter(|&h| self.gs.players.contains(h));
 
         if self.cvars.d_log_kills {
             // Indent kill msgs because there's a lot of them so others stand out.
             // LATER configurable indent
-            let attacker_name = &self.gs.players[attacker_handle].name.clone();
+            let attacker_name = match attacker_handle {
+                Some(handle) => format!("{:?}", self.gs.players[handle].name),
+                None => "the world".to_owned(),
+            };
             let victim_name = &self.gs.players[veh_owner].name.clone();
-            dbg_logf!("    {victim_name:?} was killed by {attacker_name:?}");
+            dbg_logf!("    {victim_name:?} was killed by {attacker_name}");
         }
 
         let victim = &mut self.gs.players[veh_owner];
         victim.death_time = self.gs.game_time;
 
+        // Players who left since they dealt the damage get nothing.
+        let assist_handles: Vec<_> = assist_ids
+            .iter()
+            .filter_map(|&id| self.gs.player_ids.handle(id))
+            .collect();
+
         self.update_score_kill(attacker_handle, veh_owner);
+        self.update_score_assists(&assist_handles);
+        self.hook_on_kill(attacker_handle, veh_owner, weapon);
+        self.overtime_kill(attacker_handle, veh_owner);
+        self.sg.match_stats.record_death(DeathRecord {
+            time: self.gs.game_time,
+            victim: self.gs.player_ids.id(veh_owner).soft_unwrap(),
+            attacker: attacker_handle.and_then(|handle| self.gs.player_ids.id(handle)),
+            assists: assist_ids,
+            weapon,
+        });
 
         let kill = Kill {
-            attacker: attacker_handle.slot(),
+            attacker: attacker_handle.map(|handle| handle.slot()),
             victim: veh_owner.slot(),
+            assists: assist_handles.iter().map(|handle| handle.slot()).collect(),
         };
         let msg = ServerMessage::Kill(kill);
         self.net_send_all(msg);
@@ -719,44 +1363,129 @@ impl ServerFrameCtx<'_> {
             let projectile = &self.gs.projectiles[handle];
             if self.gs.game_time > projectile.explode_time {
                 let hit_pos = projectile.pos; // borrowck dance
-                self.projectile_impact(handle, hit_pos);
+                self.projectile_impact(handle, hit_pos, false);
+            }
+        }
+    }
+
+    /// Vehicles for which water is lethal start sinking when their center is on water,
+    /// usually after being pushed there by `g_explosion_knockback`.
+    /// When the sinking ends they die without an explosion and leave no wreck.
+    pub fn sys_sinking(&mut self) {
+        let game_time = self.gs.game_time;
+        for vehicle_handle in self.gs.vehicles.collect_handles() {
+            let vehicle = &mut self.gs.vehicles[vehicle_handle];
+            if vehicle.destroyed() {
+                continue;
+            }
+
+            match vehicle.sinking {
+                None => {
+                    if !self.cvars.g_vehicle_water_lethal(vehicle.veh_type)
+                        || self.map.surface_at_pos(vehicle.pos).kind != SurfaceKind::Water
+                    {
+                        continue;
+                    }
+                    vehicle.sinking = Some(Sinking::new(game_time, self.cvars.g_sink_time));
+                    vehicle.vel = Vec2f::zero();
+                    vehicle.turn_rate = 0.0;
+                    let msg = ServerMessage::Sink {
+                        index: vehicle_handle.slot(),
+                        game_time,
+                    };
+                    self.net_send_all(msg);
+                }
+                Some(sinking) if game_time >= sinking.end_time => {
+                    let attacker = sink_attacker(self.cvars, vehicle, game_time);
+                    self.damage(attacker, vehicle_handle, f64::MAX, None, None);
+                }
+                Some(_) => {}
             }
         }
     }
 
-    pub fn spawn_explosion(&mut self, pos: Vec2f, scale: f64, bfg: bool) {
+    /// Round positions and angles to a fixed precision, see `g_quantize_state`.
+    ///
+    /// Should run after all other gamelogic each tick.
+    pub fn sys_quantize(&mut self) {
+        let steps = self.cvars.g_quantize_state;
+        if steps <= 0.0 {
+            return;
+        }
+
+        for (_, vehicle) in self.gs.vehicles.iter_mut() {
+            vehicle.pos = vehicle.pos.map(|c| c.quantized(steps));
+            vehicle.angle = vehicle.angle.quantized(steps).rem_euclid(2.0 * PI);
+            vehicle.turret_angle_current = vehicle
+                .turret_angle_current
+                .quantized(steps)
+                .rem_euclid(2.0 * PI);
+        }
+        for (_, projectile) in self.gs.projectiles.iter_mut() {
+            projectile.pos = projectile.pos.map(|c| c.quantized(steps));
+            projectile.angle = projectile.angle.quantized(steps).rem_euclid(2.0 * PI);
+        }
+    }
+
+    pub fn spawn_explosion(&mut self, pos: Vec2f, scale: f64, bfg: bool, team: Option<u32>) {
         if scale == 0.0 {
             return;
         }
 
-        let init = ExplosionInit { pos, scale, bfg };
+        let init = ExplosionInit {
+            pos,
+            scale,
+            bfg,
+            game_time: self.gs.game_time,
+            team,
+        };
         let msg = ServerMessage::SpawnExplosion(init);
         self.net_send_all(msg);
     }
 
     // LATER This shouldn't need to take hit_pos
-    fn projectile_impact(&mut self, projectile_handle: Index, hit_pos: Vec2f) {
+    pub fn projectile_impact(
+        &mut self,
+        projectile_handle: Index,
+        hit_pos: Vec2f,
+        hit_vehicle: bool,
+    ) {
         let projectile = &mut self.gs.projectiles[projectile_handle];
 
         // borrowck dance
         let weapon = projectile.weapon;
         let owner = projectile.owner;
         let target = projectile.target;
+        let team = projectile.team;
 
         // Vehicle explosion first so it's below projectile explosion because it looks better.
         let expl_scale = self.cvars.g_weapon_explosion_scale(weapon);
         let expl_bfg = weapon == Weapon::Bfg;
-        self.spawn_explosion(hit_pos, expl_scale, expl_bfg);
+        self.spawn_explosion(hit_pos, expl_scale, expl_bfg, team);
 
         let expl_damage = expl_scale * self.cvars.g_weapon_explosion_damage(weapon);
         let expl_radius = expl_scale * self.cvars.g_weapon_explosion_radius(weapon);
         if expl_damage > 0.0 || expl_radius > 0.0 {
-            self.explosion_damage(owner, hit_pos, expl_damage, expl_damage, expl_radius, None);
+            self.explosion_damage(
+                owner,
+                Some(weapon),
+                hit_pos,
+                expl_damage,
+                expl_damage * self.cvars.g_explosion_damage_edge,
+                expl_radius,
+                None,
+                true,
+            );
         }
+        self.detonate_mines_near(hit_pos, expl_radius);
 
         if weapon == Weapon::Hm {
+            // The target might be gone if its owner left.
+            let target = match target {
+                Some(HmTarget::Vehicle(target)) => self.gs.vehicles.get_mut(target),
+                _ => None,
+            };
             if let Some(target) = target {
-                let target = &mut self.gs.vehicles[target];
                 // Borrowck dance:
                 // No need to hm_forget here because the projectile is desotryed anyway.
                 // We actually can't call it anyway because we can't keep projectile borrowed.
@@ -770,27 +1499,38 @@ impl ServerFrameCtx<'_> {
         }
 
         if weapon == Weapon::Gm {
-            let player = &mut self.gs.players[owner];
-            if player.guided_missile == Some(projectile_handle) {
-                player.guided_missile = None;
+            // The owner might be gone if g_orphan_projectiles is Keep.
+            if let Some(player) = self.gs.players.get_mut(owner) {
+                if player.guided_missile == Some(projectile_handle) {
+                    player.guided_missile = None;
+                }
             }
         }
 
         let msg = ServerMessage::DestroyProjectile {
             index: projectile_handle.slot(),
+            impact: (!hit_vehicle).then_some(hit_pos),
         };
         self.net_send_all(msg);
         self.gs.projectiles.remove(projectile_handle).unwrap();
+        self.gs.projectile_ids.remove(projectile_handle);
+        self.gs.rail_hits.remove(&projectile_handle);
     }
 
-    fn explosion_damage(
+    /// Damage and push all vehicles within `radius`, falling off linearly from the center to the edge.
+    /// Emplacements in range are damaged too, see `explosion_damage_emplacements`.
+    ///
+    /// The owner's own vehicle isn't spared.
+    pub fn explosion_damage(
         &mut self,
         owner: Index,
+        weapon: Option<Weapon>,
         expl_pos: Vec2f,
         damage_center: f64,
         damage_edge: f64,
         radius: f64,
         ignore: Option<Index>,
+        directional: bool,
     ) {
         if self.cvars.d_explosion_radius {
             dbg_line!(expl_pos, expl_pos + Vec2f::new(radius, 0.0), 5.0);
@@ -810,10 +1550,2969 @@ impl ServerFrameCtx<'_> {
 
             let center_dist = (vehicle.pos - expl_pos).magnitude();
             let dist = (center_dist - self.cvars.g_hitcircle_radius).max(0.0);
-            if dist < radius {
+            if dist < radius && !self.explosion_blocked(expl_pos, vehicle.pos) {
                 let expl_damage = lerp_ranges(0.0, radius, damage_center, damage_edge, dist);
-                self.damage(owner, vehicle_handle, expl_damage);
+                let dir = Some(vehicle.pos - expl_pos).filter(|_| directional);
+
+                let knockback =
+                    lerp_ranges(0.0, radius, self.cvars.g_explosion_knockback, 0.0, dist);
+                if knockback > 0.0 {
+                    let vehicle = &mut self.gs.vehicles[vehicle_handle];
+                    let push_dir = (vehicle.pos - expl_pos)
+                        .try_normalized()
+                        .unwrap_or_default();
+                    vehicle.vel += push_dir * knockback;
+                    vehicle.last_push = Some(Push {
+                        attacker: owner,
+                        time: self.gs.game_time,
+                    });
+                }
+
+                self.damage(owner, vehicle_handle, expl_damage, weapon, dir);
+            }
+        }
+
+        self.explosion_damage_emplacements(expl_pos, damage_center, damage_edge, radius);
+    }
+
+    /// Whether a wall between the explosion and `target` shields it, see `g_explosion_los`.
+    ///
+    /// Explosions on walls are slightly inside the wall they hit,
+    /// so tracing from the target, that wall only counts if it's entered from the other side.
+    pub fn explosion_blocked(&self, expl_pos: Vec2f, target: Vec2f) -> bool {
+        if !self.cvars.g_explosion_los {
+            return false;
+        }
+        match self.map.is_wall_trace(target, expl_pos) {
+            Some(hit) => hit.distance(expl_pos) > 1.0,
+            None => false,
+        }
+    }
+}
+
+/// Whether the railgun beam was already inside the target before this step.
+///
+/// The beam can take several segments to pass through a target,
+/// only entering it counts as a hit.
+pub fn rail_was_inside(
+    rail_hits: &mut FnvHashMap<Index, FnvHashSet<TargetKind>>,
+    proj_handle: Index,
+    kind: TargetKind,
+    ends_inside: bool,
+) -> bool {
+    let inside = rail_hits.entry(proj_handle).or_default();
+    if ends_inside {
+        !inside.insert(kind)
+    } else {
+        inside.remove(&kind)
+    }
+}
+
+/// Accelerate `turn_rate` in `dir` (-1, 0 or 1) and apply friction.
+///
+/// Shared by the hull (and missile) turning model and turret inertia.
+/// An infinite `increase` jumps straight to `max`.
+pub fn turn_rate_step(
+    turn_rate: &mut f64,
+    dir: f64,
+    increase: f64,
+    friction_const: f64,
+    friction_linear: f64,
+    max: f64,
+    dt: f64,
+) {
+    // Checked so an infinite increase doesn't turn into NaN.
+    if dir != 0.0 {
+        *turn_rate += dir * increase * dt;
+    }
+
+    // Friction's constant component - always the same no matter the speed
+    let tr_fric_const = friction_const * dt;
+    if *turn_rate >= 0.0 {
+        *turn_rate = (*turn_rate - tr_fric_const).max(0.0);
+    } else {
+        *turn_rate = (*turn_rate + tr_fric_const).min(0.0);
+    }
+
+    // Friction's linear component - increases with speed
+    let tr_new = *turn_rate * (1.0 - friction_linear).powf(dt);
+    *turn_rate = tr_new.clamped(-max, max);
+}
+
+/// Move the turret toward `turret_angle_wanted`.
+///
+/// The turret speeds up and slows down according to `g_turret_turn_accel_deg`
+/// and the friction cvars. With the default infinite acceleration and no friction
+/// it's either still or at `g_turret_turn_speed_deg` - a constant rate like the original.
+pub fn turret_turning(cvars: &Cvars, vehicle: &mut Vehicle, dt: f64) {
+    turn_turret(
+        cvars,
+        &mut vehicle.turret_angle_current,
+        vehicle.turret_angle_wanted,
+        &mut vehicle.turret_turn_rate,
+        dt,
+    );
+}
+
+/// `turret_turning` for anything with a turret, emplacements use it too.
+pub fn turn_turret(
+    cvars: &Cvars,
+    angle_current: &mut f64,
+    angle_wanted: f64,
+    turn_rate: &mut f64,
+    dt: f64,
+) {
+    let delta = angle_current.delta_angle(angle_wanted);
+    if delta == 0.0 {
+        *turn_rate = 0.0;
+        return;
+    }
+    let accel = cvars.g_turret_turn_accel_deg.to_radians();
+    let rate = *turn_rate;
+
+    // Start braking in time to stop at the wanted angle.
+    // Friction is ignored here so at worst it stops a bit short and creeps the rest of the way.
+    let stopping_dist = rate * rate / (2.0 * accel);
+    let dir = if rate * delta > 0.0 && stopping_dist >= delta.abs() {
+        -rate.signum()
+    } else {
+        delta.signum()
+    };
+    turn_rate_step(
+        turn_rate,
+        dir,
+        accel,
+        cvars.g_turret_turn_friction_const_deg.to_radians(),
+        cvars.g_turret_turn_friction_linear,
+        cvars.g_turret_turn_speed_deg.to_radians(),
+        dt,
+    );
+
+    let change = *turn_rate * dt;
+    if change * delta > 0.0 && change.abs() >= delta.abs() {
+        // Never overshoot.
+        *angle_current += delta;
+        *turn_rate = 0.0;
+    } else {
+        *angle_current += change;
+    }
+    *angle_current = angle_current.rem_euclid(2.0 * PI);
+}
+
+/// The projectiles of one shot of `weapon` from `shot_origin` in `shot_angle`.
+///
+/// `base_vel` is the velocity of whatever is firing, each weapon inherits a part of it
+/// according to its `*_vehicle_velocity_factor`. Emplacements don't move so theirs is zero.
+pub fn weapon_projectiles(
+    cvars: &Cvars,
+    rng: &mut Xoshiro256PlusPlus,
+    uniform11: Uniform<f64>,
+    weapon: Weapon,
+    shot_angle: f64,
+    shot_origin: Vec2f,
+    base_vel: Vec2f,
+    owner: Index,
+    game_time: f64,
+) -> Vec<Projectile> {
+    let mut projectiles = Vec::new();
+    // Some sane defaults to be overriden later
+    let mut projectile = Projectile {
+        weapon: Weapon::Mg,
+        pos: shot_origin,
+        vel: Vec2f::zero(), // LATER hardpoint vel? -> more vel if vehicle is turning?
+        angle: shot_angle,
+        turn_rate: 0.0, // LATER vehicle turn angle?
+        spawn_time: game_time,
+        explode_time: f64::MAX,
+        owner,
+        target: None,
+        flight: None,
+        team: None,
+        seeker: Seeker::default(),
+    };
+
+    match weapon {
+        Weapon::Mg => {
+            let r: f64 = rng.sample(StandardNormal);
+            let spread = cvars.g_machine_gun_angle_spread * r;
+            // Using spread as shot_vel.y would mean the resulting spread depends on speed
+            // so it's better to use spread on angle.
+            projectile.vel = Vec2f::new(cvars.g_machine_gun_speed, 0.0)
+                .rotated_z(shot_angle + spread)
+                + cvars.g_machine_gun_vehicle_velocity_factor * base_vel;
+            projectiles.push(projectile);
+        }
+        Weapon::Rail => {
+            projectile.weapon = Weapon::Rail;
+            projectile.vel = Vec2f::new(cvars.g_railgun_speed, 0.0).rotated_z(shot_angle)
+                + cvars.g_railgun_vehicle_velocity_factor * base_vel;
+            projectiles.push(projectile);
+        }
+        Weapon::Cb => {
+            projectile.weapon = Weapon::Cb;
+            for _ in 0..cvars.g_cluster_bomb_count {
+                cluster_bomb(
+                    cvars,
+                    rng,
+                    uniform11,
+                    &mut projectile,
+                    cvars.g_cluster_bomb_speed,
+                    cvars.g_cluster_bomb_time,
+                );
+                projectile.vel += cvars.g_cluster_bomb_vehicle_velocity_factor * base_vel;
+                projectiles.push(projectile.clone());
+            }
+        }
+        Weapon::Rockets => {
+            projectile.weapon = Weapon::Rockets;
+            projectile.vel = Vec2f::new(cvars.g_rockets_speed, 0.0).rotated_z(shot_angle)
+                + cvars.g_rockets_vehicle_velocity_factor * base_vel;
+            projectiles.push(projectile);
+        }
+        Weapon::Hm => {
+            projectile.weapon = Weapon::Hm;
+            projectile.vel = Vec2f::new(cvars.g_homing_missile_speed_initial, 0.0)
+                .rotated_z(shot_angle)
+                + cvars.g_homing_missile_vehicle_velocity_factor * base_vel;
+            projectiles.push(projectile);
+        }
+        Weapon::Gm => {
+            projectile.weapon = Weapon::Gm;
+            projectile.vel = Vec2f::new(cvars.g_guided_missile_speed_initial, 0.0)
+                .rotated_z(shot_angle)
+                + cvars.g_guided_missile_vehicle_velocity_factor * base_vel;
+            // LATER Set angle according to vehicle angle (also some other weaps)
+            projectiles.push(projectile);
+        }
+        Weapon::Bfg => {
+            projectile.weapon = Weapon::Bfg;
+            projectile.vel = Vec2f::new(cvars.g_bfg_speed, 0.0).rotated_z(shot_angle)
+                + cvars.g_bfg_vehicle_velocity_factor * base_vel;
+            projectiles.push(projectile);
+        }
+    }
+    projectiles
+}
+
+/// Give a cluster bomb flying in its `angle` a randomized velocity and fuse
+/// around `speed` and `time` according to the `g_cluster_bomb_*_spread` cvars.
+///
+/// Velocity inherited from the vehicle is up to the caller.
+fn cluster_bomb(
+    cvars: &Cvars,
+    rng: &mut Xoshiro256PlusPlus,
+    uniform11: Uniform<f64>,
+    cb: &mut Projectile,
+    speed: f64,
+    time: f64,
+) {
+    let spread_forward;
+    let spread_sideways;
+    if cvars.g_cluster_bomb_speed_spread_gaussian {
+        let r: f64 = rng.sample(StandardNormal);
+        spread_forward = cvars.g_cluster_bomb_speed_spread_forward * r;
+        let r: f64 = rng.sample(StandardNormal);
+        spread_sideways = cvars.g_cluster_bomb_speed_spread_sideways * r;
+    } else {
+        let r = rng.sample(uniform11);
+        spread_forward = cvars.g_cluster_bomb_speed_spread_forward * r;
+        let r = rng.sample(uniform11);
+        spread_sideways = cvars.g_cluster_bomb_speed_spread_sideways * r;
+    }
+    cb.vel = Vec2f::new(speed + spread_forward, spread_sideways).rotated_z(cb.angle);
+    cb.explode_time =
+        cb.spawn_time + time + rng.sample(uniform11) * cvars.g_cluster_bomb_time_spread;
+}
+
+/// Who gets the kill for a vehicle that sank.
+///
+/// Whoever pushed it in last, unless it was too long ago.
+/// Otherwise nobody, `damage` counts that the same as an attacker who left.
+fn sink_attacker(cvars: &Cvars, vehicle: &Vehicle, game_time: f64) -> Index {
+    match vehicle.last_push {
+        Some(push) if game_time - push.time <= cvars.g_knockback_credit_time => push.attacker,
+        _ => Index::DANGLING,
+    }
+}
+
+/// Where the path from `start` to `end` hits the nearest target
+/// which blocks projectiles but doesn't take damage.
+fn nearest_blocker(targets: &[Targetable], start: Vec2f, end: Vec2f) -> Option<Vec2f> {
+    let step = LineSegment2 { start, end };
+    targets
+        .iter()
+        .filter(|target| target.blocks_projectiles && !target.takes_damage)
+        .filter_map(|target| {
+            let nearest_point = step.projected_point(target.pos);
+            let dist2 = nearest_point.distance_squared(target.pos);
+            (dist2 <= target.radius * target.radius).then_some(nearest_point)
+        })
+        .min_by(|a, b| {
+            a.distance_squared(start)
+                .total_cmp(&b.distance_squared(start))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{map, test_utils, weapons::WeaponSet};
+
+    /// Kill the player with the railgun and turret turned, then respawn.
+    fn die_and_respawn(cvars: &Cvars) -> (Server, Index) {
+        let mut server = test_utils::atrium_server(cvars);
+        let mut ctx = server.ctx(cvars);
+
+        let (player_handle, _) = test_utils::spawn_player(&mut ctx, "test");
+        ctx.gs.players[player_handle].cur_weapon = Weapon::Rail;
+
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        ctx.gs.vehicles[vehicle_handle].turret_angle_wanted = 1.0;
+        ctx.damage(
+            player_handle,
+            vehicle_handle,
+            f64::MAX,
+            Some(Weapon::Rail),
+            None,
+        );
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+
+        ctx.gs.game_time += 100.0;
+        ctx.gs.players[player_handle].respawn = Respawn::Scheduled;
+        ctx.sys_respawning();
+        let new_vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        assert!(!ctx.gs.vehicles[new_vehicle_handle].destroyed());
+
+        (server, player_handle)
+    }
+
+    #[test]
+    fn test_respawn_keep() {
+        let cvars = Cvars {
+            g_respawn_keep_turret: true,
+            g_respawn_keep_weapon: true,
+            ..Cvars::default()
+        };
+        let (server, player_handle) = die_and_respawn(&cvars);
+        let player = &server.gs.players[player_handle];
+        assert_eq!(player.cur_weapon, Weapon::Rail);
+        let vehicle = &server.gs.vehicles[player.vehicle.unwrap()];
+        assert_eq!(vehicle.turret_angle_wanted, 1.0);
+        assert_eq!(vehicle.turret_angle_current, 1.0);
+    }
+
+    #[test]
+    fn test_respawn_reset() {
+        let cvars = Cvars {
+            g_respawn_keep_turret: false,
+            g_respawn_keep_weapon: false,
+            ..Cvars::default()
+        };
+        let (server, player_handle) = die_and_respawn(&cvars);
+        let player = &server.gs.players[player_handle];
+        assert_eq!(player.cur_weapon, Weapon::Mg);
+        let vehicle = &server.gs.vehicles[player.vehicle.unwrap()];
+        assert_eq!(vehicle.turret_angle_wanted, 0.0);
+    }
+
+    #[test]
+    fn test_respawn_weapon_fallback() {
+        // Every vehicle type so it doesn't matter which one it respawns in.
+        let weapons = |list: &str| -> WeaponSet { list.parse().unwrap() };
+        for (list, expected) in [
+            ("mg,cb", Weapon::Mg),
+            ("rockets,cb", Weapon::Cb),
+            ("rail,bfg", Weapon::Rail),
+        ] {
+            let cvars = Cvars {
+                g_respawn_keep_weapon: true,
+                g_hovercraft_weapons: weapons(list),
+                g_hummer_weapons: weapons(list),
+                g_tank_weapons: weapons(list),
+                ..Cvars::default()
+            };
+            let (server, player_handle) = die_and_respawn(&cvars);
+            assert_eq!(
+                server.gs.players[player_handle].cur_weapon, expected,
+                "{list}"
+            );
+        }
+
+        // First usable in the order players see, not the declaration order.
+        let cvars = Cvars {
+            g_hovercraft_weapons: weapons("bfg,cb"),
+            g_hummer_weapons: weapons("bfg,cb"),
+            g_tank_weapons: weapons("bfg,cb"),
+            g_weapon_order: "bfg,gm,hm,rockets,cb,rail,mg".parse().unwrap(),
+            ..Cvars::default()
+        };
+        let (server, player_handle) = die_and_respawn(&cvars);
+        assert_eq!(server.gs.players[player_handle].cur_weapon, Weapon::Bfg);
+    }
+
+    #[test]
+    fn test_switching_skips_unusable_weapons() {
+        let cvars = Cvars {
+            g_tank_weapons: "mg,rockets,bfg".parse().unwrap(),
+            g_weapon_switch_time: 0.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = test_utils::add_player(&mut ctx, "test");
+        test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, v!(300 300), 0.0);
+
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            let player = &mut ctx.gs.players[player_handle];
+            player.input_prev = NetInput::empty();
+            player.input.next_weapon = true;
+            ctx.sys_player_weapon();
+            seen.push(ctx.gs.players[player_handle].cur_weapon);
+        }
+        assert_eq!(
+            seen,
+            [Weapon::Rockets, Weapon::Bfg, Weapon::Mg, Weapon::Rockets]
+        );
+
+        let player = &mut ctx.gs.players[player_handle];
+        player.input = NetInput::empty();
+        player.input.prev_weapon = true;
+        ctx.sys_player_weapon();
+        assert_eq!(ctx.gs.players[player_handle].cur_weapon, Weapon::Mg);
+    }
+
+    #[test]
+    fn test_overheal_decay() {
+        let cvars = Cvars {
+            g_overheal_decay: 0.1,
+            g_overheal_max: 1.5,
+            g_overheal_spawn: 1.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+
+        // The spawn bonus is capped.
+        assert_eq!(ctx.gs.vehicles[vehicle_handle].hp_fraction, 1.5);
+
+        // Linear decay, stops at full HP.
+        ctx.gs.dt = 0.5;
+        let mut curve = Vec::new();
+        for _ in 0..12 {
+            ctx.sys_overheal_decay();
+            curve.push(ctx.gs.vehicles[vehicle_handle].hp_fraction);
+        }
+        let expected = [
+            1.45, 1.4, 1.35, 1.3, 1.25, 1.2, 1.15, 1.1, 1.05, 1.0, 1.0, 1.0,
+        ];
+        for (hp_fraction, expected) in curve.iter().zip(expected) {
+            assert!((hp_fraction - expected).abs() < 1e-9, "{curve:?}");
+        }
+        assert_eq!(curve[11], 1.0);
+
+        // Damage eats into overheal first, no special handling needed.
+        ctx.gs.vehicles[vehicle_handle].hp_fraction = 1.5;
+        let hp = cvars.g_vehicle_hp(ctx.gs.vehicles[vehicle_handle].veh_type);
+        ctx.damage(player_handle, vehicle_handle, hp, None, None);
+        let hp_fraction = ctx.gs.vehicles[vehicle_handle].hp_fraction;
+        assert!((hp_fraction - 0.5).abs() < 1e-9);
+
+        // Below full, nothing changes.
+        ctx.sys_overheal_decay();
+        assert_eq!(ctx.gs.vehicles[vehicle_handle].hp_fraction, hp_fraction);
+    }
+
+    #[test]
+    fn test_wreck_turret_frozen() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+        ctx.gs.vehicles[vehicle_handle].turret_angle_current = 1.0;
+        ctx.gs.vehicles[vehicle_handle].turret_angle_wanted = 1.0;
+        ctx.damage(player_handle, vehicle_handle, f64::MAX, None, None);
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+
+        ctx.gs.dt = 0.1;
+        ctx.gs.players[player_handle].input.turret_right = true;
+        for _ in 0..10 {
+            ctx.sys_vehicle_logic();
+        }
+        let vehicle = &ctx.gs.vehicles[vehicle_handle];
+        assert_eq!(vehicle.turret_angle_current, 1.0);
+        // Still pre-aiming for respawn
+        assert_ne!(vehicle.turret_angle_wanted, 1.0);
+    }
+
+    fn slaved_turret_setup(cvars: &Cvars) -> (Server, Index, Index) {
+        let mut server = test_utils::atrium_server(cvars);
+        let mut ctx = server.ctx(cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+        let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+        vehicle.angle = 0.0;
+        vehicle.turret_angle_current = 0.0;
+        vehicle.turret_angle_wanted = 0.0;
+        ctx.gs.dt = 0.01;
+        (server, player_handle, vehicle_handle)
+    }
+
+    #[test]
+    fn test_turret_slaved_velocity() {
+        let cvars = Cvars {
+            g_hovercraft_turret_slaved: TurretSlaved::Velocity,
+            g_hummer_turret_slaved: TurretSlaved::Velocity,
+            g_tank_turret_slaved: TurretSlaved::Velocity,
+            ..Cvars::default()
+        };
+        let (mut server, player_handle, vehicle_handle) = slaved_turret_setup(&cvars);
+        let mut ctx = server.ctx(&cvars);
+
+        // Drive a circle while the hull swings from side to side, slower than the turret can turn.
+        // The swing stays under 90 degrees, past that the vehicle would count as reversing.
+        let travel_rate = 60.0f64.to_radians();
+        let swing = 1.0;
+        let swing_rate = 1.5;
+        let turret_rate = cvars.g_turret_turn_speed_deg.to_radians();
+        assert!(swing * swing_rate < turret_rate);
+        let step = |ctx: &mut ServerFrameCtx<'_>| {
+            ctx.gs.game_time += ctx.gs.dt;
+            let travel = ctx.gs.game_time * travel_rate;
+            let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+            vehicle.vel = travel.to_vec2f() * 100.0;
+            vehicle.angle = travel + swing * (ctx.gs.game_time * swing_rate).sin();
+            ctx.sys_vehicle_logic();
+            let player = &mut ctx.gs.players[player_handle];
+            player.input_prev = player.input;
+        };
+        let misalignment = |ctx: &ServerFrameCtx<'_>| {
+            let vehicle = &ctx.gs.vehicles[vehicle_handle];
+            let turret_angle = vehicle.angle + vehicle.turret_angle_current;
+            turret_angle.delta_angle(vehicle.vel.to_angle()).abs()
+        };
+        for _ in 0..200 {
+            step(&mut ctx);
+            assert!(misalignment(&ctx) < 1e-6);
+        }
+
+        // Manual input wins for a while.
+        ctx.gs.players[player_handle].input.turret_left = true;
+        step(&mut ctx);
+        let wanted = ctx.gs.vehicles[vehicle_handle].turret_angle_wanted;
+        let override_ticks = (cvars.g_turret_slaved_override_time / ctx.gs.dt) as usize;
+        for _ in 0..override_ticks - 2 {
+            step(&mut ctx);
+            assert_eq!(ctx.gs.vehicles[vehicle_handle].turret_angle_wanted, wanted);
+        }
+        assert!(misalignment(&ctx) > 0.5);
+
+        // Then it catches up again at the normal turret speed.
+        ctx.gs.players[player_handle].input.turret_left = false;
+        for _ in 0..1000 {
+            step(&mut ctx);
+        }
+        assert!(misalignment(&ctx) < 1e-6);
+    }
+
+    #[test]
+    fn test_turret_slaved_reverse() {
+        for (reverse, expected) in [(SlavedReverse::Track, 0.0), (SlavedReverse::Hold, 0.3)] {
+            let cvars = Cvars {
+                g_hovercraft_turret_slaved: TurretSlaved::Velocity,
+                g_hummer_turret_slaved: TurretSlaved::Velocity,
+                g_tank_turret_slaved: TurretSlaved::Velocity,
+                g_turret_slaved_reverse: reverse,
+                ..Cvars::default()
+            };
+            let (mut server, _, vehicle_handle) = slaved_turret_setup(&cvars);
+            let mut ctx = server.ctx(&cvars);
+            let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+            vehicle.turret_angle_current = 0.3;
+            vehicle.turret_angle_wanted = 0.3;
+            vehicle.vel = v!(-100, 0);
+            assert!(vehicle.forward_speed() < 0.0);
+
+            for _ in 0..100 {
+                ctx.sys_vehicle_logic();
+                let vehicle = &ctx.gs.vehicles[vehicle_handle];
+                // Never heads for the back.
+                assert!(vehicle.turret_misalignment() <= 0.3 + 1e-9);
+            }
+            let vehicle = &ctx.gs.vehicles[vehicle_handle];
+            assert!(vehicle.turret_angle_current.delta_angle(expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_turret_slaved_hull() {
+        let cvars = Cvars {
+            g_hovercraft_turret_slaved: TurretSlaved::Hull,
+            g_hummer_turret_slaved: TurretSlaved::Hull,
+            g_tank_turret_slaved: TurretSlaved::Hull,
+            ..Cvars::default()
+        };
+        let (mut server, player_handle, vehicle_handle) = slaved_turret_setup(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        ctx.gs.vehicles[vehicle_handle].turret_angle_current = 1.0;
+        ctx.gs.vehicles[vehicle_handle].vel = v!(0 100);
+        ctx.gs.players[player_handle].input.turret_right = true;
+        for _ in 0..100 {
+            ctx.sys_vehicle_logic();
+        }
+        let vehicle = &ctx.gs.vehicles[vehicle_handle];
+        assert_eq!(vehicle.turret_angle_wanted, 0.0);
+        assert!(vehicle.turret_misalignment() < 1e-9);
+    }
+
+    /// The turret turning code from before turret inertia existed.
+    fn constant_rate_turret(cvars: &Cvars, vehicle: &mut Vehicle, dt: f64) {
+        let delta = vehicle
+            .turret_angle_current
+            .delta_angle(vehicle.turret_angle_wanted);
+        let change = cvars.g_turret_turn_speed_deg.to_radians() * dt * delta.signum();
+        let change_clamped = change.clamped(-delta.abs(), delta.abs());
+        vehicle.turret_angle_current += change_clamped;
+        vehicle.turret_angle_current = vehicle.turret_angle_current.rem_euclid(2.0 * PI);
+    }
+
+    #[test]
+    fn test_turret_default_constant_rate() {
+        let cvars = Cvars::default();
+        let dt = 1.0 / 60.0;
+        let vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        let mut old = vehicle.clone();
+        let mut new = vehicle;
+
+        // Steps both ways, reversing mid-turn, wrapping around 0 and holding still.
+        let script = [0.8, 0.0, -0.8, -2.4, 3.0, 3.0, 0.3, 0.0];
+        for (i, step) in script.into_iter().enumerate() {
+            old.turret_angle_wanted = (old.turret_angle_wanted + step).rem_euclid(2.0 * PI);
+            new.turret_angle_wanted = old.turret_angle_wanted;
+            for tick in 0..(20 + i * 7) {
+                constant_rate_turret(&cvars, &mut old, dt);
+                turret_turning(&cvars, &mut new, dt);
+                assert_eq!(
+                    old.turret_angle_current, new.turret_angle_current,
+                    "step {i} tick {tick}"
+                );
             }
         }
+        // Got there in the end.
+        assert_eq!(new.turret_angle_current, new.turret_angle_wanted);
+        assert_eq!(new.turret_turn_rate, 0.0);
+    }
+
+    #[test]
+    fn test_turret_inertia_step_response() {
+        // Full speed after half a second.
+        let cvars = Cvars {
+            g_turret_turn_accel_deg: 240.0,
+            g_turret_turn_speed_deg: 120.0,
+            ..Cvars::default()
+        };
+        let dt = 1.0 / 120.0;
+        let rate_max = 120.0f64.to_radians();
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(0 0),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        vehicle.turret_angle_wanted = 3.0;
+
+        let mut rates = Vec::new();
+        for _ in 0..600 {
+            turret_turning(&cvars, &mut vehicle, dt);
+            rates.push(vehicle.turret_turn_rate);
+            // Never overshoots.
+            assert!(vehicle.turret_angle_current <= 3.0);
+        }
+        let rate_at = |time: f64| rates[(time / dt).round() as usize - 1];
+
+        // Winds up linearly.
+        assert!((rate_at(0.25) - rate_max / 2.0).abs() < 1e-9);
+        assert!(rate_at(0.45) < rate_max);
+        assert_eq!(rate_at(0.5), rate_max);
+
+        // 3 rad takes 0.5 s to wind up, 0.5 s to wind down and the rest at full speed.
+        let full_speed_time = (3.0 - rate_max * 0.5) / rate_max;
+        let arrival = 1.0 + full_speed_time;
+        assert!(rate_at(arrival - 0.25) > 0.0);
+        assert!(rate_at(arrival - 0.25) < rate_max);
+        assert_eq!(rate_at(arrival + 0.1), 0.0);
+        assert_eq!(vehicle.turret_angle_current, 3.0);
+    }
+
+    #[test]
+    fn test_turn_rate_step_friction() {
+        let dt = 1.0 / 120.0;
+
+        // Linear friction - half the rate lost each second.
+        let mut rate = 1.0;
+        for _ in 0..120 {
+            turn_rate_step(&mut rate, 0.0, 10.0, 0.0, 0.5, 10.0, dt);
+        }
+        assert!((rate - 0.5).abs() < 1e-9);
+
+        // Constant friction - stops after rate / friction seconds.
+        let mut rate = -1.0;
+        for _ in 0..119 {
+            turn_rate_step(&mut rate, 0.0, 10.0, 1.0, 0.0, 10.0, dt);
+        }
+        assert!(rate < 0.0);
+        turn_rate_step(&mut rate, 0.0, 10.0, 1.0, 0.0, 10.0, dt);
+        turn_rate_step(&mut rate, 0.0, 10.0, 1.0, 0.0, 10.0, dt);
+        assert_eq!(rate, 0.0);
+
+        // Infinite acceleration jumps to max, no NaN when not accelerating.
+        let mut rate = 0.0;
+        turn_rate_step(&mut rate, -1.0, f64::INFINITY, 0.0, 0.0, 2.0, dt);
+        assert_eq!(rate, -2.0);
+        turn_rate_step(&mut rate, 0.0, f64::INFINITY, 0.0, 0.0, 2.0, dt);
+        assert_eq!(rate, -2.0);
+    }
+
+    /// Turn with `right` held for `ticks` ticks, return total angle turned and distance moved.
+    fn pivot(stats: &MovementStats, ticks: usize, dt: f64) -> (f64, f64) {
+        let input = NetInput {
+            right: true,
+            ..NetInput::empty()
+        };
+        let mut pos = Vec2f::zero();
+        let mut vel = Vec2f::zero();
+        let mut angle = 0.0;
+        let mut turn_rate = 0.0;
+        let mut turned = 0.0;
+        for _ in 0..ticks {
+            let new_angle =
+                ServerFrameCtx::turning(stats, &mut vel, &angle, &mut turn_rate, input, dt);
+            turned += (new_angle - angle).rem_euclid(2.0 * PI);
+            angle = new_angle;
+            ServerFrameCtx::accel_decel(stats, &mut vel, angle, input, dt);
+            pos += vel * dt;
+        }
+        (turned, pos.magnitude())
+    }
+
+    #[test]
+    fn test_pivot_in_place() {
+        let cvars = Cvars {
+            g_tank_pivot_turn_rate: PI,
+            ..Cvars::default()
+        };
+        let stats = cvars.g_vehicle_movement_stats(VehicleType::Tank);
+        let dt = 1.0 / 100.0;
+
+        // Half a turn per second so a full turn takes 2 s.
+        let (turned, moved) = pivot(&stats, 200, dt);
+        assert!((turned - 2.0 * PI).abs() < 1e-9, "turned {turned}");
+        assert!(moved < 1.0, "moved {moved}");
+
+        let (turned, _) = pivot(&stats, 100, dt);
+        assert!((turned - PI).abs() < 1e-9, "turned {turned}");
+    }
+
+    #[test]
+    fn test_pivot_tank_only_by_default() {
+        let cvars = Cvars::default();
+        let stats = cvars.g_vehicle_movement_stats(VehicleType::Tank);
+        assert!(stats.pivot_turn_rate > 0.0);
+        let stats = cvars.g_vehicle_movement_stats(VehicleType::Hummer);
+        assert_eq!(stats.pivot_turn_rate, 0.0);
+        let stats = cvars.g_vehicle_movement_stats(VehicleType::Hovercraft);
+        assert_eq!(stats.pivot_turn_rate, 0.0);
+
+        // The regular model accelerates the turn rate gradually.
+        let (turned, _) = pivot(&stats, 1, 1.0 / 100.0);
+        assert!(turned < PI / 100.0, "turned {turned}");
+    }
+
+    /// Spawn a player with a vehicle holding `weapon` which has `altfire`.
+    fn alt_fire_server(weapon: Weapon, altfire: AltFire) -> (Cvars, Server, Index) {
+        let cvars = Cvars {
+            g_cluster_bomb_altfire: altfire,
+            g_guided_missile_altfire: altfire,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, _) = test_utils::spawn_player(&mut ctx, "test");
+        ctx.gs.players[player_handle].cur_weapon = weapon;
+        (cvars, server, player_handle)
+    }
+
+    fn add_projectile(ctx: &mut ServerFrameCtx<'_>, weapon: Weapon, owner: Index) -> Index {
+        let projectile = Projectile {
+            weapon,
+            pos: v!(100 100),
+            vel: Vec2f::zero(),
+            angle: 0.0,
+            turn_rate: 0.0,
+            // Old enough for any alt-fire.
+            spawn_time: ctx.gs.game_time - 1.0,
+            explode_time: f64::MAX,
+            owner,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        ctx.gs.projectiles.insert(projectile)
+    }
+
+    fn press_alt_fire(ctx: &mut ServerFrameCtx<'_>, player_handle: Index) {
+        let player = &mut ctx.gs.players[player_handle];
+        player.input_prev = player.input;
+        player.input.fire_alt = true;
+        ctx.sys_alt_fire();
+    }
+
+    #[test]
+    fn test_altfire_table() {
+        let cvars = Cvars {
+            g_guided_missile_altfire: AltFire::DetonateGm,
+            ..Cvars::default()
+        };
+        assert_eq!(cvars.g_weapon_altfire(Weapon::Gm), AltFire::DetonateGm);
+        assert_eq!(cvars.g_weapon_altfire(Weapon::Cb), AltFire::None);
+        assert_eq!(cvars.g_weapon_altfire(Weapon::Mg), AltFire::None);
+        assert_eq!("detonategm".parse::<AltFire>(), Ok(AltFire::DetonateGm));
+    }
+
+    #[test]
+    fn test_altfire_detonate_gm() {
+        let (cvars, mut server, player_handle) = alt_fire_server(Weapon::Gm, AltFire::DetonateGm);
+        let mut ctx = server.ctx(&cvars);
+        let gm_handle = add_projectile(&mut ctx, Weapon::Gm, player_handle);
+        ctx.gs.players[player_handle].guided_missile = Some(gm_handle);
+
+        press_alt_fire(&mut ctx, player_handle);
+        assert!(!ctx.gs.projectiles.contains(gm_handle));
+        assert_eq!(ctx.gs.players[player_handle].guided_missile, None);
+    }
+
+    #[test]
+    fn test_altfire_detonate_cb() {
+        let (cvars, mut server, player_handle) = alt_fire_server(Weapon::Cb, AltFire::DetonateCb);
+        let mut ctx = server.ctx(&cvars);
+        let cb1 = add_projectile(&mut ctx, Weapon::Cb, player_handle);
+        let cb2 = add_projectile(&mut ctx, Weapon::Cb, player_handle);
+        let rocket = add_projectile(&mut ctx, Weapon::Rockets, player_handle);
+        let other_cb = add_projectile(&mut ctx, Weapon::Cb, Index::DANGLING);
+
+        press_alt_fire(&mut ctx, player_handle);
+        assert!(!ctx.gs.projectiles.contains(cb1));
+        assert!(!ctx.gs.projectiles.contains(cb2));
+        assert!(ctx.gs.projectiles.contains(rocket));
+        assert!(ctx.gs.projectiles.contains(other_cb));
+    }
+
+    #[test]
+    fn test_altfire_edge() {
+        let (cvars, mut server, player_handle) = alt_fire_server(Weapon::Cb, AltFire::DetonateCb);
+        let mut ctx = server.ctx(&cvars);
+
+        press_alt_fire(&mut ctx, player_handle);
+
+        // Still held - nothing happens.
+        let cb = add_projectile(&mut ctx, Weapon::Cb, player_handle);
+        press_alt_fire(&mut ctx, player_handle);
+        assert!(ctx.gs.projectiles.contains(cb));
+
+        // Released and pressed again.
+        ctx.gs.players[player_handle].input.fire_alt = false;
+        press_alt_fire(&mut ctx, player_handle);
+        assert!(!ctx.gs.projectiles.contains(cb));
+    }
+
+    #[test]
+    fn test_altfire_detonate_cb_min_age() {
+        let cvars = Cvars {
+            bots_max: 0,
+            g_cluster_bomb_altfire: AltFire::DetonateCb,
+            g_cluster_bomb_count: 5,
+            g_cluster_bomb_detonate_min_age: 0.1,
+            g_cluster_bomb_time: 10.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, _) = test_utils::spawn_player(&mut ctx, "test");
+        let player = &mut ctx.gs.players[player_handle];
+        player.cur_weapon = Weapon::Cb;
+        player.input.fire = true;
+        let other_cb = add_projectile(&mut ctx, Weapon::Cb, Index::DANGLING);
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let game_time = server.gs.game_time + dt;
+        server.simulate_tick(&cvars, game_time);
+        server.gs.players[player_handle].input.fire = false;
+        let own_cbs = |server: &Server| {
+            server
+                .gs
+                .projectiles
+                .iter()
+                .filter(|(_, proj)| proj.weapon == Weapon::Cb && proj.owner == player_handle)
+                .count()
+        };
+        assert_eq!(own_cbs(&server), 5);
+
+        // Too soon
+        let mut ctx = server.ctx(&cvars);
+        press_alt_fire(&mut ctx, player_handle);
+        ctx.gs.players[player_handle].input.fire_alt = false;
+        assert_eq!(own_cbs(&server), 5);
+
+        while server.gs.game_time < 0.2 {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(&cvars, game_time);
+        }
+        assert_eq!(own_cbs(&server), 5);
+        press_alt_fire(&mut server.ctx(&cvars), player_handle);
+        assert_eq!(own_cbs(&server), 0);
+        assert!(server.gs.projectiles.contains(other_cb));
+    }
+
+    #[test]
+    fn test_altfire_none() {
+        let (cvars, mut server, player_handle) = alt_fire_server(Weapon::Gm, AltFire::None);
+        let mut ctx = server.ctx(&cvars);
+        let gm_handle = add_projectile(&mut ctx, Weapon::Gm, player_handle);
+        ctx.gs.players[player_handle].guided_missile = Some(gm_handle);
+
+        press_alt_fire(&mut ctx, player_handle);
+        assert!(ctx.gs.projectiles.contains(gm_handle));
+    }
+
+    #[test]
+    fn test_altfire_drop_mine() {
+        let (cvars, mut server, player_handle) = alt_fire_server(Weapon::Gm, AltFire::DropMine);
+        let mut ctx = server.ctx(&cvars);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+
+        press_alt_fire(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.mines.len(), 1);
+        let vehicle = &ctx.gs.vehicles[vehicle_handle];
+        let behind = vehicle.pos - vehicle.angle.to_vec2f() * cvars.g_mine_offset;
+        let (_, mine) = ctx.gs.mines.iter().next().unwrap();
+        assert!(mine.pos.distance(behind) < 0.001);
+        assert_eq!(mine.owner, player_handle);
+
+        // Shares the cooldown with the mine key.
+        ctx.gs.players[player_handle].input.fire_alt = false;
+        press_alt_fire(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.mines.len(), 1);
+
+        ctx.gs.game_time += cvars.g_mine_cooldown;
+        ctx.gs.players[player_handle].input.fire_alt = false;
+        press_alt_fire(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.mines.len(), 2);
+    }
+
+    #[test]
+    fn test_smoke_deploy_cooldown() {
+        let (cvars, mut server, player_handle) = alt_fire_server(Weapon::Gm, AltFire::Smoke);
+        let mut ctx = server.ctx(&cvars);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+
+        press_alt_fire(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.smokes.len(), 1);
+        let vehicle = &ctx.gs.vehicles[vehicle_handle];
+        let behind = vehicle.pos - vehicle.angle.to_vec2f() * cvars.g_smoke_offset;
+        assert!(ctx.gs.smokes[0].pos.distance(behind) < 0.001);
+
+        // On cooldown
+        ctx.gs.players[player_handle].input.fire_alt = false;
+        press_alt_fire(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.smokes.len(), 1);
+
+        ctx.gs.game_time += cvars.g_smoke_cooldown;
+        ctx.gs.players[player_handle].input.fire_alt = false;
+        press_alt_fire(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.smokes.len(), 2);
+    }
+
+    #[test]
+    fn test_smoke_breaks_hm_lock() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (_, vehicle_handle) = test_utils::spawn_player(&mut ctx, "target");
+        let target_pos = ctx.gs.vehicles[vehicle_handle].pos;
+
+        // Close enough to stay in the spawn tile so there are no walls in the way.
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        ctx.gs.projectiles[hm_handle].pos = target_pos - v!(20 0);
+        assert!(ctx
+            .map
+            .is_wall_trace(target_pos - v!(20 0), target_pos)
+            .is_none());
+
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+
+        let smoke = Smoke::new(
+            target_pos - v!(10 0),
+            5.0,
+            ctx.gs.game_time,
+            Index::DANGLING,
+        );
+        ctx.gs.smokes.push(smoke);
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, None);
+        assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+
+        // Still blocked
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, None);
+
+        ctx.gs.game_time += cvars.g_smoke_duration;
+        ctx.sys_smoke_cleanup();
+        assert!(ctx.gs.smokes.is_empty());
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+    }
+
+    #[test]
+    fn test_flares_spoof_hm() {
+        let cvars = Cvars {
+            g_flare_burst: 1,
+            g_flare_count: 2,
+            g_flare_speed: 0.0,
+            g_flare_angle_spread: 0.0,
+            g_flare_offset: 15.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "target");
+        let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+        vehicle.angle = 0.0;
+        let target_pos = vehicle.pos;
+
+        // Same setup as the smoke test, the flare ends up between the missile and the vehicle.
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        ctx.gs.projectiles[hm_handle].pos = target_pos - v!(30 0);
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+
+        ctx.deploy_flares(player_handle, vehicle_handle);
+        assert_eq!(ctx.gs.flares.len(), 1);
+        assert_eq!(ctx.gs.vehicles[vehicle_handle].flares, 1);
+        let flare_handle = ctx.gs.flares.iter().next().unwrap().0;
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Flare(flare_handle))
+        );
+        assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+
+        // Still fooled
+        ctx.gs.game_time += cvars.g_flare_spoof_duration / 2.0;
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Flare(flare_handle))
+        );
+
+        // The flare still exists but it's cooled down, the vehicle is still in range.
+        ctx.gs.game_time += cvars.g_flare_spoof_duration;
+        ctx.sys_flare_cleanup();
+        assert_eq!(ctx.gs.flares.len(), 1);
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+        assert_eq!(ctx.gs.vehicles[vehicle_handle].hms, [hm_handle]);
+
+        ctx.gs.game_time += cvars.g_flare_duration;
+        ctx.sys_flare_cleanup();
+        assert_eq!(ctx.gs.flares.len(), 0);
+    }
+
+    /// 5x5 tiles, only the one in the middle is a wall.
+    fn walled_server(cvars: &Cvars) -> Server {
+        let map_text = "0 0 0 0 0\n0 0 0 0 0\n0 0 16 0 0\n0 0 0 0 0\n0 0 0 0 0\n";
+        let map = test_utils::parse_map(map_text);
+        test_utils::server(cvars, map)
+    }
+
+    #[test]
+    fn test_spawn_vehicle_team_spawns() {
+        let cvars = Cvars::default();
+        let map_text = "36 0 36\n0 0 0\n36 0 36\nspawn_team 2 2 1\n";
+        let map = test_utils::parse_map(map_text);
+        let mut server = test_utils::server(&cvars, map);
+        let mut ctx = server.ctx(&cvars);
+
+        let mut player = Player::new("team 1".to_owned(), ClientType::Local);
+        player.team = Some(1);
+        let player_handle = ctx.gs.players.insert(player);
+        for _ in 0..10 {
+            ctx.spawn_vehicle(player_handle, true);
+            let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+            assert_eq!(ctx.gs.vehicles[vehicle_handle].pos, v!(160 160));
+        }
+    }
+
+    fn add_hm_target(ctx: &mut ServerFrameCtx<'_>, pos: Vec2f) -> Index {
+        let player_handle = test_utils::add_player(ctx, "target");
+        test_utils::add_vehicle(ctx, player_handle, VehicleType::Tank, pos, 0.0)
+    }
+
+    #[test]
+    fn test_hm_seeker_cone_range() {
+        let cvars = Cvars {
+            g_homing_missile_seeker_range: 200.0,
+            ..Cvars::default()
+        };
+        let mut server = walled_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        ctx.gs.projectiles[hm_handle].pos = v!(32 32);
+        let target_pos = v!(32 32) + 45.0f64.to_radians().to_vec2f() * 60.0;
+        let vehicle_handle = add_hm_target(&mut ctx, target_pos);
+
+        // Inside the seeker cone but too far off the heading to acquire.
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, None);
+
+        ctx.gs.projectiles[hm_handle].angle = 20.0f64.to_radians();
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+        assert_eq!(ctx.gs.vehicles[vehicle_handle].hms, [hm_handle]);
+
+        // Once locked, it's tracked anywhere in the cone.
+        ctx.gs.projectiles[hm_handle].angle = -3.0f64.to_radians();
+        ctx.sys_hm_turning();
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+
+        ctx.gs.projectiles[hm_handle].angle = -10.0f64.to_radians();
+        ctx.sys_hm_turning();
+        let hm = &ctx.gs.projectiles[hm_handle];
+        assert_eq!(hm.target, None);
+        assert_eq!(hm.seeker, Seeker::Searching(ctx.gs.game_time));
+        assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+
+        // Range
+        ctx.gs.projectiles[hm_handle].angle = 0.0;
+        ctx.gs.vehicles[vehicle_handle].pos = v!(222 32);
+        ctx.sys_hm_turning();
+        let hm = &ctx.gs.projectiles[hm_handle];
+        assert_eq!(hm.target, Some(HmTarget::Vehicle(vehicle_handle)));
+        assert_eq!(hm.seeker, Seeker::default());
+
+        ctx.gs.vehicles[vehicle_handle].pos = v!(242 32);
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, None);
+    }
+
+    #[test]
+    fn test_hm_converges() {
+        let cvars = Cvars::default();
+        let mut server = walled_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        let target_pos = v!(288 32);
+        let vehicle_handle = add_hm_target(&mut ctx, target_pos);
+
+        // Fired 30 degrees off, away from the wall in the middle.
+        let hm = &mut ctx.gs.projectiles[hm_handle];
+        hm.pos = v!(32 32);
+        hm.angle = 30.0f64.to_radians();
+        hm.vel = hm.angle.to_vec2f() * cvars.g_homing_missile_speed_initial;
+
+        ctx.gs.dt = 1.0 / 150.0;
+        let mut closest = f64::INFINITY;
+        for _ in 0..150 {
+            ctx.gs.game_time += ctx.gs.dt;
+            ctx.sys_hm_turning();
+            let hm = &mut ctx.gs.projectiles[hm_handle];
+            hm.pos += hm.vel * ctx.gs.dt;
+            closest = closest.min(hm.pos.distance(target_pos));
+            if closest < 10.0 {
+                break;
+            }
+        }
+        assert!(closest < 10.0, "closest {closest}");
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].target,
+            Some(HmTarget::Vehicle(vehicle_handle))
+        );
+    }
+
+    #[test]
+    fn test_hm_no_lock_through_wall() {
+        let cvars = Cvars {
+            g_homing_missile_search_time: 10.0,
+            ..Cvars::default()
+        };
+        let mut server = walled_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        // Straight ahead but behind the wall in the middle.
+        let vehicle_handle = add_hm_target(&mut ctx, v!(288 160));
+        let hm = &mut ctx.gs.projectiles[hm_handle];
+        hm.pos = v!(32 160);
+        hm.vel = v!(100 0);
+
+        ctx.gs.dt = 1.0 / 150.0;
+        for _ in 0..10 {
+            ctx.gs.game_time += ctx.gs.dt;
+            ctx.sys_hm_turning();
+        }
+        let hm = &ctx.gs.projectiles[hm_handle];
+        assert_eq!(hm.target, None);
+        assert_eq!(hm.angle, 0.0);
+        assert_eq!(hm.turn_rate, 0.0);
+        assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+    }
+
+    #[test]
+    fn test_hm_occlusion_grace() {
+        let cvars = Cvars {
+            g_homing_missile_occlusion_grace: 0.5,
+            g_homing_missile_search_time: 10.0,
+            ..Cvars::default()
+        };
+        let mut server = walled_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        ctx.gs.projectiles[hm_handle].pos = v!(32 160);
+        let visible_pos = v!(288 288);
+        let hidden_pos = v!(288 160);
+        let vehicle_handle = add_hm_target(&mut ctx, visible_pos);
+        assert!(ctx.map.is_wall_trace(v!(32 160), visible_pos).is_none());
+        assert!(ctx.map.is_wall_trace(v!(32 160), hidden_pos).is_some());
+
+        ctx.sys_hm_turning();
+        let locked = Some(HmTarget::Vehicle(vehicle_handle));
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, locked);
+
+        ctx.gs.vehicles[vehicle_handle].pos = hidden_pos;
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, locked);
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].seeker,
+            Seeker::Active {
+                occluded_since: Some(0.0)
+            }
+        );
+        ctx.gs.game_time += 0.3;
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, locked);
+
+        // Seeing it again resets the timer.
+        ctx.gs.vehicles[vehicle_handle].pos = visible_pos;
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].seeker, Seeker::default());
+        ctx.gs.vehicles[vehicle_handle].pos = hidden_pos;
+        ctx.sys_hm_turning();
+
+        ctx.gs.game_time += 0.4;
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, locked);
+
+        ctx.gs.game_time += 0.2;
+        ctx.sys_hm_turning();
+        assert_eq!(ctx.gs.projectiles[hm_handle].target, None);
+        assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+        assert_eq!(
+            ctx.gs.projectiles[hm_handle].seeker,
+            Seeker::Searching(ctx.gs.game_time)
+        );
+    }
+
+    #[test]
+    fn test_hm_search_then_give_up() {
+        for lost in [HmLost::Detonate, HmLost::Inert] {
+            let cvars = Cvars {
+                g_homing_missile_lost: lost,
+                g_homing_missile_search_time: 1.0,
+                ..Cvars::default()
+            };
+            let mut server = walled_server(&cvars);
+            let mut ctx = server.ctx(&cvars);
+            let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+            ctx.gs.projectiles[hm_handle].pos = v!(160 32);
+            let ahead = v!(260 32);
+            let behind = v!(60 32);
+            let vehicle_handle = add_hm_target(&mut ctx, ahead);
+            ctx.sys_hm_turning();
+            assert!(ctx.gs.projectiles[hm_handle].target.is_some());
+
+            ctx.gs.vehicles[vehicle_handle].pos = behind;
+            ctx.sys_hm_turning();
+            assert_eq!(ctx.gs.projectiles[hm_handle].seeker, Seeker::Searching(0.0));
+
+            // Finds it again while searching.
+            ctx.gs.game_time = 0.5;
+            ctx.sys_hm_turning();
+            assert_eq!(ctx.gs.projectiles[hm_handle].seeker, Seeker::Searching(0.0));
+            ctx.gs.vehicles[vehicle_handle].pos = ahead;
+            ctx.sys_hm_turning();
+            assert_eq!(ctx.gs.projectiles[hm_handle].seeker, Seeker::default());
+
+            // Loses it again and doesn't find anything this time.
+            ctx.gs.vehicles[vehicle_handle].pos = behind;
+            ctx.sys_hm_turning();
+            assert_eq!(ctx.gs.projectiles[hm_handle].seeker, Seeker::Searching(0.5));
+            ctx.gs.game_time = 1.4;
+            ctx.sys_hm_turning();
+            assert_eq!(ctx.gs.projectiles[hm_handle].seeker, Seeker::Searching(0.5));
+            assert_eq!(ctx.gs.projectiles[hm_handle].explode_time, f64::MAX);
+
+            ctx.gs.game_time = 1.5;
+            ctx.sys_hm_turning();
+            match lost {
+                HmLost::Detonate => {
+                    assert_eq!(ctx.gs.projectiles[hm_handle].explode_time, 1.5);
+                    ctx.gs.game_time = 1.51;
+                    ctx.sys_projectiles_timeout();
+                    assert!(!ctx.gs.projectiles.contains(hm_handle));
+                }
+                HmLost::Inert => {
+                    let hm = &ctx.gs.projectiles[hm_handle];
+                    assert_eq!(hm.seeker, Seeker::Inert);
+                    assert_eq!(hm.explode_time, f64::MAX);
+
+                    // Never looks again.
+                    ctx.gs.vehicles[vehicle_handle].pos = ahead;
+                    ctx.sys_hm_turning();
+                    assert_eq!(ctx.gs.projectiles[hm_handle].target, None);
+                    assert!(ctx.gs.vehicles[vehicle_handle].hms.is_empty());
+                }
+            }
+        }
+    }
+
+    /// The lock warning follows `Vehicle::hms` which is what `HmLock` messages sync to clients.
+    #[test]
+    fn test_hm_lock_ticks() {
+        let cvars = Cvars::default();
+        let mut server = walled_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, Index::DANGLING);
+        ctx.gs.projectiles[hm_handle].pos = v!(32 160);
+        let vehicle_handle = add_hm_target(&mut ctx, v!(288 288));
+        ctx.gs.projectiles[hm_handle].angle = PI;
+
+        let mut locked_ticks = Vec::new();
+        for tick in 0..6 {
+            ctx.gs.game_time = tick as f64 * 0.1;
+            match tick {
+                // Turns towards the vehicle, acquires it.
+                2 => ctx.gs.projectiles[hm_handle].angle = 0.0,
+                // Dies, the missile lets go immediately.
+                4 => ctx.gs.vehicles[vehicle_handle].hp_fraction = 0.0,
+                _ => {}
+            }
+            ctx.sys_hm_turning();
+            if !ctx.gs.vehicles[vehicle_handle].hms.is_empty() {
+                locked_ticks.push(tick);
+            }
+        }
+        assert_eq!(locked_ticks, [2, 3]);
+    }
+
+    #[test]
+    fn test_flares_limits() {
+        let cvars = Cvars {
+            g_flare_burst: 3,
+            g_flare_cooldown: 1.0,
+            g_flare_count: 2,
+            g_flare_offset: 5.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+        ctx.gs.vehicles[vehicle_handle].angle = 0.0;
+
+        ctx.deploy_flares(player_handle, vehicle_handle);
+        assert_eq!(ctx.gs.flares.len(), 3);
+        // Cooldown
+        ctx.deploy_flares(player_handle, vehicle_handle);
+        assert_eq!(ctx.gs.flares.len(), 3);
+        ctx.gs.game_time += 1.0;
+        ctx.deploy_flares(player_handle, vehicle_handle);
+        assert_eq!(ctx.gs.flares.len(), 6);
+        assert_eq!(ctx.gs.vehicles[vehicle_handle].flares, 0);
+        // Out of flares
+        ctx.gs.game_time += 1.0;
+        ctx.deploy_flares(player_handle, vehicle_handle);
+        assert_eq!(ctx.gs.flares.len(), 6);
+
+        // Own missiles ignore them.
+        let hm_handle = add_projectile(&mut ctx, Weapon::Hm, player_handle);
+        ctx.gs.game_time -= 1.0;
+        let flare_pos = ctx.gs.flares.iter().next().unwrap().1.pos;
+        ctx.gs.projectiles[hm_handle].pos = flare_pos - v!(10 0);
+        let flare_target = |ctx: &ServerFrameCtx<'_>| {
+            weapons::hm_flare_target(
+                &cvars,
+                ctx.map,
+                &ctx.gs.flares,
+                &ctx.gs.smokes,
+                ctx.gs.game_time,
+                &ctx.gs.projectiles[hm_handle],
+            )
+        };
+        assert_eq!(flare_target(&ctx), None);
+        ctx.gs.projectiles[hm_handle].owner = Index::DANGLING;
+        assert!(flare_target(&ctx).is_some());
+    }
+
+    #[test]
+    fn test_smoke_not_physical() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let pos = ctx.map.tile_center(ctx.map.spawns()[0]);
+
+        let mg_handle = add_projectile(&mut ctx, Weapon::Mg, Index::DANGLING);
+        ctx.gs.projectiles[mg_handle].pos = pos;
+        ctx.gs.projectiles[mg_handle].vel = v!(80 0);
+        let smoke = Smoke::new(pos + v!(5 0), 20.0, ctx.gs.game_time, Index::DANGLING);
+        ctx.gs.smokes.push(smoke);
+
+        ctx.gs.dt = 0.125;
+        ctx.sys_projectiles();
+        assert!(ctx.gs.projectiles.contains(mg_handle));
+        assert_eq!(ctx.gs.projectiles[mg_handle].pos, pos + v!(10 0));
+    }
+
+    /// Speed after accelerating from standstill for one tick with the turret at the given angle.
+    fn misaligned_speed(cvars: &Cvars, turret_angle: f64) -> f64 {
+        let mut server = test_utils::atrium_server(cvars);
+        let mut ctx = server.ctx(cvars);
+        let (player_handle, _) = test_utils::spawn_player(&mut ctx, "test");
+        ctx.gs.players[player_handle].input = NetInput {
+            up: true,
+            ..NetInput::empty()
+        };
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+        vehicle.veh_type = VehicleType::Tank;
+        vehicle.turret_angle_current = turret_angle;
+
+        ctx.gs.dt = 0.05;
+        ctx.sys_vehicle_movement();
+        ctx.gs.vehicles[vehicle_handle].vel.magnitude()
+    }
+
+    #[test]
+    fn test_turret_misalign_penalty() {
+        let cvars = Cvars {
+            g_turret_misalign_angle: PI / 2.0,
+            g_turret_misalign_speed_factor: 0.5,
+            ..Cvars::default()
+        };
+        let aligned = misaligned_speed(&cvars, 0.0);
+        assert!(aligned > 0.0);
+
+        // Within the threshold
+        assert_eq!(misaligned_speed(&cvars, 1.5), aligned);
+
+        // Beyond
+        let misaligned = misaligned_speed(&cvars, PI);
+        assert!(misaligned < aligned * 0.6, "{misaligned} vs {aligned}");
+
+        // Re-centered
+        assert_eq!(misaligned_speed(&cvars, 2.0 * PI), aligned);
+
+        // Disabled by default
+        let cvars = Cvars::default();
+        assert_eq!(misaligned_speed(&cvars, PI), misaligned_speed(&cvars, 0.0));
+    }
+
+    #[test]
+    fn test_turret_misalign_speed_max() {
+        let cvars = Cvars {
+            g_tank_speed_max: 100.0,
+            g_turret_misalign_speed_factor: 0.5,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (_, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+        let vehicle = &mut ctx.gs.vehicles[vehicle_handle];
+        vehicle.veh_type = VehicleType::Tank;
+        vehicle.turret_angle_current = PI;
+        vehicle.vel = vehicle.angle.to_vec2f() * 100.0;
+
+        ctx.gs.dt = 0.001;
+        ctx.sys_vehicle_movement();
+        assert!(ctx.gs.vehicles[vehicle_handle].vel.magnitude() <= 50.0 + 1e-9);
+    }
+
+    /// Open map with a single wall column at c == 3 (x from 192 to 256).
+    fn wall_map() -> Map {
+        test_utils::arena(|r, c| match (r, c) {
+            (_, 3) => "16",
+            (8, 8) => "36",
+            _ => "0",
+        })
+    }
+
+    /// Fire once at the wall from the left, return the furthest x any projectile reached
+    /// after they've all exploded.
+    fn fire_at_wall(cvars: &Cvars, weapon: Weapon) -> f64 {
+        let mut server = test_utils::server(cvars, wall_map());
+        let mut ctx = server.ctx(cvars);
+        let player_handle = test_utils::add_player(&mut ctx, "test");
+        test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, v!(96 352), 0.0);
+        let player = &mut ctx.gs.players[player_handle];
+        player.cur_weapon = weapon;
+        player.input.fire = true;
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let mut max_x = 0.0_f64;
+        // Longer than any fuse or arc.
+        while server.gs.game_time < 1.5 {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(cvars, game_time);
+            server.gs.players[player_handle].input.fire = false;
+            for (_, projectile) in server.gs.projectiles.iter() {
+                max_x = max_x.max(projectile.pos.x);
+            }
+        }
+        assert!(server.gs.projectiles.is_empty());
+        max_x
+    }
+
+    #[test]
+    fn test_lob_over_wall() {
+        let cvars = Cvars {
+            bots_max: 0,
+            g_cluster_bomb_count: 1,
+            g_cluster_bomb_speed_spread_forward: 0.0,
+            g_cluster_bomb_speed_spread_sideways: 0.0,
+            g_cluster_bomb_time_spread: 0.0,
+            ..Cvars::default()
+        };
+        assert!(fire_at_wall(&cvars, Weapon::Cb) > 256.0);
+        assert!(fire_at_wall(&cvars, Weapon::Rockets) <= 192.0);
+
+        // Mortar
+        let cvars = Cvars {
+            g_rockets_arc: 0.5,
+            g_rockets_arc_time: 0.5,
+            ..cvars
+        };
+        assert!(fire_at_wall(&cvars, Weapon::Rockets) > 256.0);
+    }
+
+    /// Drive right across the open part of `wall_map`, return how many ticks it took.
+    fn ticks_to_cross(server: &mut Server, cvars: &Cvars, vehicle_handle: Index) -> usize {
+        let vehicle = &mut server.gs.vehicles[vehicle_handle];
+        vehicle.pos = v!(300 160);
+        vehicle.vel = Vec2f::zero();
+        vehicle.angle = 0.0;
+        vehicle.turn_rate = 0.0;
+
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let mut ticks = 0;
+        while server.gs.vehicles[vehicle_handle].pos.x < 560.0 {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(cvars, game_time);
+            ticks += 1;
+            assert!(ticks < 1000);
+        }
+        ticks
+    }
+
+    #[test]
+    fn test_cripple() {
+        let cvars = Cvars {
+            bots_max: 0,
+            g_cripple_hp_threshold: 0.25,
+            g_cripple_mobility_factor: 0.5,
+            g_cripple_turn_factor: 0.5,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::server(&cvars, wall_map());
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = test_utils::add_player(&mut ctx, "test");
+        let vehicle_handle =
+            test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, v!(300 160), 0.0);
+        let player = &mut ctx.gs.players[player_handle];
+        player.input.up = true;
+
+        let healthy = ticks_to_cross(&mut server, &cvars, vehicle_handle);
+
+        server.gs.vehicles[vehicle_handle].hp_fraction = 0.2;
+        assert!(server.gs.vehicles[vehicle_handle].crippled(&cvars));
+        let crippled = ticks_to_cross(&mut server, &cvars, vehicle_handle);
+        assert!(
+            crippled as f64 > healthy as f64 * 1.2,
+            "{crippled} vs {healthy}"
+        );
+
+        // Healed above the threshold
+        server.gs.vehicles[vehicle_handle].hp_fraction = 0.3;
+        let healed = ticks_to_cross(&mut server, &cvars, vehicle_handle);
+        assert_eq!(healed, healthy);
+
+        // Disabled by default
+        let cvars = Cvars::default();
+        assert!(!server.gs.vehicles[vehicle_handle].crippled(&cvars));
+    }
+
+    /// Fire a rocket from `from_x` at a tank facing +x, return the damage the tank took.
+    fn rocket_damage(cvars: &Cvars, from_x: f64) -> f64 {
+        let mut server = test_utils::server(cvars, wall_map());
+        let mut ctx = server.ctx(cvars);
+        let attacker_handle = test_utils::add_player(&mut ctx, "attacker");
+        let target_handle = test_utils::add_player(&mut ctx, "target");
+        let target_pos = v!(450 300);
+        let vehicle_handle =
+            test_utils::add_vehicle(&mut ctx, target_handle, VehicleType::Tank, target_pos, 0.0);
+
+        let pos = Vec2f::new(from_x, target_pos.y);
+        let rocket = Projectile {
+            weapon: Weapon::Rockets,
+            pos,
+            vel: (target_pos - pos).normalized() * cvars.g_rockets_speed,
+            angle: 0.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: attacker_handle,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let rocket_handle = ctx.gs.projectiles.insert(rocket);
+        ctx.gs.dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        while ctx.gs.projectiles.contains(rocket_handle) {
+            ctx.sys_projectiles();
+        }
+
+        let hp = cvars.g_vehicle_hp(VehicleType::Tank);
+        (1.0 - ctx.gs.vehicles[vehicle_handle].hp_fraction) * hp
+    }
+
+    #[test]
+    fn test_directional_armor() {
+        let cvars = Cvars {
+            // Survive both hits
+            g_armor: 200.0,
+            g_tank_armor_rear: 2.0,
+            ..Cvars::default()
+        };
+        let front = rocket_damage(&cvars, 550.0);
+        let rear = rocket_damage(&cvars, 350.0);
+        assert_eq!(front, cvars.g_rockets_damage_direct);
+        assert_eq!(rear, 2.0 * front);
+
+        // Multipliers are 1 by default.
+        let cvars = Cvars {
+            g_armor: 200.0,
+            ..Cvars::default()
+        };
+        assert_eq!(rocket_damage(&cvars, 350.0), front);
+    }
+
+    /// Fire a rocket from the right at a tank at x 450 with wrecks placed at `wreck_xs`,
+    /// return the damage dealt and where the rocket exploded.
+    fn rocket_past_wrecks(cvars: &Cvars, wreck_xs: &[f64]) -> (f64, Vec2f) {
+        let mut server = test_utils::server(cvars, wall_map());
+        let mut ctx = server.ctx(cvars);
+        let attacker_handle = test_utils::add_player(&mut ctx, "attacker");
+        let target_handle = test_utils::add_player(&mut ctx, "target");
+        let target_pos = v!(450 300);
+        let vehicle_handle =
+            test_utils::add_vehicle(&mut ctx, target_handle, VehicleType::Tank, target_pos, 0.0);
+        for &x in wreck_xs {
+            ctx.gs.wrecks.push(Wreck {
+                pos: Vec2f::new(x, target_pos.y),
+                angle: 0.0,
+                veh_type: VehicleType::Hummer,
+            });
+        }
+
+        let rocket = Projectile {
+            weapon: Weapon::Rockets,
+            pos: v!(600 300),
+            vel: v!(-1, 0) * cvars.g_rockets_speed,
+            angle: PI,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: attacker_handle,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let rocket_handle = ctx.gs.projectiles.insert(rocket);
+        ctx.gs.dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        let mut last_pos = v!(600 300);
+        while let Some(rocket) = ctx.gs.projectiles.get(rocket_handle) {
+            last_pos = rocket.pos;
+            ctx.sys_projectiles();
+        }
+
+        let hp = cvars.g_vehicle_hp(VehicleType::Tank);
+        let dmg = (1.0 - ctx.gs.vehicles[vehicle_handle].hp_fraction) * hp;
+        (dmg, last_pos)
+    }
+
+    #[test]
+    fn test_wrecks_block_projectiles() {
+        let cvars = Cvars {
+            g_armor: 200.0,
+            ..Cvars::default()
+        };
+        let (vehicles_only, _) = rocket_past_wrecks(&cvars, &[]);
+        assert_eq!(vehicles_only, cvars.g_rockets_damage_direct);
+        // By default wrecks are just scenery.
+        assert_eq!(rocket_past_wrecks(&cvars, &[530.0]).0, vehicles_only);
+
+        let cvars = Cvars {
+            g_wrecks_block_projectiles: true,
+            ..cvars
+        };
+        // Only vehicles - exactly as before.
+        assert_eq!(rocket_past_wrecks(&cvars, &[]).0, vehicles_only);
+        // Cover in front.
+        let (dmg, last_pos) = rocket_past_wrecks(&cvars, &[530.0]);
+        assert_eq!(dmg, 0.0);
+        assert!(last_pos.x > 530.0);
+        // Behind the target doesn't matter.
+        assert_eq!(rocket_past_wrecks(&cvars, &[370.0]).0, vehicles_only);
+    }
+
+    #[test]
+    fn test_projectile_targets() {
+        let cvars = Cvars::default();
+        let mut server = test_utils::server(&cvars, wall_map());
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = test_utils::add_player(&mut ctx, "test");
+        let vehicle_handle =
+            test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, v!(450 300), 0.0);
+        ctx.gs.wrecks.push(Wreck {
+            pos: v!(500 300),
+            angle: 0.0,
+            veh_type: VehicleType::Tank,
+        });
+
+        let targets = ctx.projectile_targets();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].kind, TargetKind::Vehicle(vehicle_handle));
+        assert!(targets[0].takes_damage && targets[0].blocks_projectiles);
+
+        let cvars = Cvars {
+            g_wrecks_block_projectiles: true,
+            ..cvars
+        };
+        let ctx = server.ctx(&cvars);
+        let targets = ctx.projectile_targets();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].kind, TargetKind::Wreck(0));
+        assert!(!targets[1].takes_damage && targets[1].blocks_projectiles);
+
+        // Destroyed vehicles can't be hit.
+        ctx.gs.vehicles[vehicle_handle].hp_fraction = 0.0;
+        let targets = ctx.projectile_targets();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].kind, TargetKind::Wreck(0));
+    }
+
+    #[test]
+    fn test_rail_penetration() {
+        let cvars = Cvars {
+            bots_max: 0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::server(&cvars, wall_map());
+        let mut ctx = server.ctx(&cvars);
+        // Shooter on the left, 3 vehicles lined up in front of it, the right border wall behind them.
+        let mut vehicle_handles = Vec::new();
+        for x in [280.0, 350.0, 420.0, 490.0] {
+            let player_handle = test_utils::add_player(&mut ctx, "test");
+            let pos = Vec2f::new(x, 300.0);
+            let vehicle_handle =
+                test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, pos, 0.0);
+            vehicle_handles.push(vehicle_handle);
+        }
+        let shooter = ctx.gs.vehicles[vehicle_handles[0]].owner;
+        ctx.gs.players[shooter].cur_weapon = Weapon::Rail;
+        ctx.gs.players[shooter].input.fire = true;
+
+        // The beam needs many steps to cross each hitbox.
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        while server.gs.game_time < 0.5 {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(&cvars, game_time);
+            server.gs.players[shooter].input.fire = false;
+        }
+
+        let hps: Vec<_> = vehicle_handles
+            .iter()
+            .map(|&handle| server.gs.vehicles[handle].hp_fraction)
+            .collect();
+        assert_eq!(hps[0], 1.0);
+        // Each hit exactly once.
+        let hit = hps[1];
+        assert!(hit < 1.0);
+        assert_eq!(hps[1..], [hit, hit, hit]);
+        assert!(server.gs.projectiles.is_empty());
+        assert!(server.gs.rail_hits.is_empty());
+    }
+
+    /// Tank at the middle of `wall_map` holding fire with rockets.
+    fn burst_server(cvars: &Cvars) -> (Server, Index) {
+        let mut server = test_utils::server(cvars, wall_map());
+        let mut ctx = server.ctx(cvars);
+        let player_handle = test_utils::add_player(&mut ctx, "test");
+        let vehicle_handle =
+            test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, v!(450 300), 0.0);
+        let player = &mut ctx.gs.players[player_handle];
+        player.cur_weapon = Weapon::Rockets;
+        player.input.fire = true;
+        (server, vehicle_handle)
+    }
+
+    /// Run `sys_shooting` in small steps until `until`,
+    /// return the time and relative y of each new rocket.
+    fn burst_shots(ctx: &mut ServerFrameCtx, vehicle_handle: Index, until: f64) -> Vec<(f64, f64)> {
+        let mut shots = Vec::new();
+        while ctx.gs.game_time < until {
+            let before: FnvHashSet<_> = ctx.gs.projectiles.iter().map(|(h, _)| h).collect();
+            ctx.sys_shooting();
+            for (handle, projectile) in ctx.gs.projectiles.iter() {
+                if !before.contains(&handle) {
+                    let y = projectile.pos.y - ctx.gs.vehicles[vehicle_handle].pos.y;
+                    shots.push((ctx.gs.game_time, y));
+                }
+            }
+            // Exact in binary so shots land exactly on the burst interval.
+            ctx.gs.game_time += 1.0 / 64.0;
+        }
+        shots
+    }
+
+    #[test]
+    fn test_projectile_team() {
+        let cvars = Cvars::default();
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+        ctx.gs.players[player_handle].team = Some(1);
+        ctx.sys_shooting();
+        assert!(!ctx.gs.projectiles.is_empty());
+        for (_, projectile) in ctx.gs.projectiles.iter() {
+            assert_eq!(projectile.team, Some(1));
+        }
+
+        // Kept after the owner leaves.
+        let cvars = Cvars {
+            g_orphan_projectiles: OrphanProjectiles::Keep,
+            ..cvars
+        };
+        let mut ctx = server.ctx(&cvars);
+        ctx.remove_player(player_handle);
+        assert!(!ctx.gs.projectiles.is_empty());
+        for (_, projectile) in ctx.gs.projectiles.iter() {
+            assert_eq!(projectile.team, Some(1));
+        }
+    }
+
+    #[test]
+    fn test_join_team() {
+        let cvars = Cvars {
+            bots_max: 0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let ctx = server.ctx(&cvars);
+        assert_eq!(ctx.join_team(), None);
+
+        ctx.gs.game_mode = GameMode::Tw(Tw { kill_limit: 20 });
+        let mut teams = Vec::new();
+        for _ in 0..5 {
+            let mut player = Player::new("test".to_owned(), ClientType::Local);
+            player.team = ctx.join_team();
+            teams.push(player.team.unwrap());
+            ctx.gs.players.insert(player);
+        }
+        assert_eq!(teams, [0, 1, 0, 1, 0]);
+    }
+
+    /// Press and release next weapon.
+    fn next_weapon(ctx: &mut ServerFrameCtx, player_handle: Index) {
+        ctx.gs.players[player_handle].input.next_weapon = true;
+        ctx.sys_player_weapon();
+        ctx.gs.players[player_handle].input.next_weapon = false;
+    }
+
+    #[test]
+    fn test_weapon_switch_time() {
+        let cvars = Cvars {
+            g_weapon_switch_time: 0.25,
+            ..Cvars::default()
+        };
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+
+        // Blocked during the swap, fires exactly at the deadline.
+        next_weapon(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.players[player_handle].cur_weapon, Weapon::Hm);
+        let shots = burst_shots(&mut ctx, vehicle_handle, 0.3);
+        assert_eq!(shots[0].0, 0.25, "{shots:?}");
+
+        // Cycling quickly - one delay from the last change.
+        burst_shots(&mut ctx, vehicle_handle, 1.0);
+        for until in [1.0625, 1.125] {
+            next_weapon(&mut ctx, player_handle);
+            let shots = burst_shots(&mut ctx, vehicle_handle, until);
+            assert!(shots.is_empty(), "{shots:?}");
+        }
+        next_weapon(&mut ctx, player_handle);
+        assert_eq!(ctx.gs.players[player_handle].cur_weapon, Weapon::Mg);
+        let shots = burst_shots(&mut ctx, vehicle_handle, 1.5);
+        assert_eq!(shots[0].0, 1.375, "{shots:?}");
+    }
+
+    fn buffer_cvars() -> Cvars {
+        Cvars {
+            g_fire_buffer_window: 0.2,
+            g_railgun_reload_ammo: 1,
+            g_railgun_reload_time: 1.0,
+            ..Cvars::default()
+        }
+    }
+
+    /// Fire the railgun at 0, then tap fire for a single tick at each of `taps`
+    /// (or hold it from there with `hold`) and return the times of the following shots.
+    fn buffered_shots(cvars: &Cvars, taps: &[f64], hold: bool, switch_at: Option<f64>) -> Vec<f64> {
+        let (mut server, vehicle_handle) = burst_server(cvars);
+        let mut ctx = server.ctx(cvars);
+        let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+        ctx.gs.players[player_handle].cur_weapon = Weapon::Rail;
+
+        let mut shots = Vec::new();
+        let mut held = false;
+        while ctx.gs.game_time < 2.5 {
+            let now = ctx.gs.game_time;
+            if Some(now) == switch_at {
+                next_weapon(&mut ctx, player_handle);
+            }
+            let tapped = taps.contains(&now);
+            held |= tapped && hold;
+            ctx.gs.players[player_handle].input.fire = now == 0.0 || tapped || held;
+
+            let weapon = ctx.gs.players[player_handle].cur_weapon;
+            ctx.gs.vehicles[vehicle_handle].weapon_states[weapon as usize].tick(cvars, weapon, now);
+            let before = ctx.gs.projectiles.len();
+            ctx.sys_shooting();
+            if ctx.gs.projectiles.len() > before && now > 0.0 {
+                shots.push(now);
+            }
+
+            let player = &mut ctx.gs.players[player_handle];
+            player.input_prev = player.input;
+            // Exact in binary so the taps and reloads line up with ticks.
+            ctx.gs.game_time += 1.0 / 64.0;
+        }
+        shots
+    }
+
+    #[test]
+    fn test_fire_buffer() {
+        let cvars = buffer_cvars();
+
+        // Reloaded at 1, pressed 0.09375 early.
+        assert_eq!(buffered_shots(&cvars, &[0.90625], false, None), [1.0]);
+        // Outside the window
+        assert!(buffered_shots(&cvars, &[0.5], false, None).is_empty());
+        // Pressed early and held - one shot at 1, the next after another reload.
+        assert_eq!(buffered_shots(&cvars, &[0.90625], true, None), [1.0, 2.0]);
+
+        // Released before it was ready
+        let held_cvars = Cvars {
+            g_fire_buffer_require_held: true,
+            ..buffer_cvars()
+        };
+        assert!(buffered_shots(&held_cvars, &[0.90625], false, None).is_empty());
+
+        // Buffering disabled
+        let off_cvars = Cvars {
+            g_fire_buffer_window: 0.0,
+            ..buffer_cvars()
+        };
+        assert!(buffered_shots(&off_cvars, &[0.90625], false, None).is_empty());
+    }
+
+    #[test]
+    fn test_fire_buffer_weapon_switch() {
+        let cvars = buffer_cvars();
+        assert!(buffered_shots(&cvars, &[0.90625], false, Some(0.953125)).is_empty());
+    }
+
+    fn burst_cvars() -> Cvars {
+        Cvars {
+            g_hardpoint_tank_rockets_y: 5.0,
+            g_hardpoint_tank_rockets_alt_y: -5.0,
+            g_rockets_burst_count: 3,
+            g_rockets_burst_interval: 0.125,
+            g_rockets_refire: 0.5,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_rockets_burst() {
+        let cvars = burst_cvars();
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+
+        // Holding fire doesn't start another burst before the refire time.
+        let shots = burst_shots(&mut ctx, vehicle_handle, 0.49);
+        assert_eq!(shots.len(), 3);
+        for (i, &(time, _)) in shots.iter().enumerate() {
+            assert_eq!(time, i as f64 * 0.125, "{shots:?}");
+        }
+        // Alternating launchers
+        assert!((shots[0].1 - shots[1].1 - 10.0).abs() < 1e-6, "{shots:?}");
+        assert!((shots[2].1 - shots[0].1).abs() < 1e-6, "{shots:?}");
+        assert!(ctx.gs.vehicles[vehicle_handle].pending_burst.is_none());
+        let state = ctx.gs.vehicles[vehicle_handle].weapon_states[Weapon::Rockets as usize];
+        assert!(!state.is_reloading());
+        assert_eq!(state.rounds_loaded, 3);
+
+        let shots = burst_shots(&mut ctx, vehicle_handle, 0.6);
+        assert_eq!(shots.len(), 1);
+
+        // The rest of the burst doesn't need the trigger held.
+        ctx.gs.players[ctx.gs.vehicles[vehicle_handle].owner]
+            .input
+            .fire = false;
+        let shots = burst_shots(&mut ctx, vehicle_handle, 2.0);
+        assert_eq!(shots.len(), 2);
+    }
+
+    #[test]
+    fn test_rockets_burst_abort() {
+        let cvars = burst_cvars();
+
+        // Death
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        assert_eq!(burst_shots(&mut ctx, vehicle_handle, 0.05).len(), 1);
+        ctx.gs.vehicles[vehicle_handle].hp_fraction = 0.0;
+        assert!(burst_shots(&mut ctx, vehicle_handle, 1.0).is_empty());
+        assert!(ctx.gs.vehicles[vehicle_handle].pending_burst.is_none());
+
+        // Out of ammo
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        ctx.gs.vehicles[vehicle_handle].weapon_states[Weapon::Rockets as usize].rounds_loaded = 2;
+        let shots = burst_shots(&mut ctx, vehicle_handle, 0.3);
+        assert_eq!(shots.len(), 2);
+        assert!(ctx.gs.vehicles[vehicle_handle].pending_burst.is_none());
+        assert!(
+            ctx.gs.vehicles[vehicle_handle].weapon_states[Weapon::Rockets as usize].is_reloading()
+        );
+    }
+
+    #[test]
+    fn test_muzzle_blocked_no_ammo_used() {
+        let cvars = Cvars::default();
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+        ctx.gs.players[player_handle].cur_weapon = Weapon::Rail;
+        // Railgun muzzle at 170 - 5 + 35 = 200, inside the wall.
+        ctx.gs.vehicles[vehicle_handle].pos = v!(170 352);
+
+        assert!(burst_shots(&mut ctx, vehicle_handle, 0.5).is_empty());
+        let vehicle = &ctx.gs.vehicles[vehicle_handle];
+        let state = vehicle.weapon_states[Weapon::Rail as usize];
+        assert!(!state.is_reloading());
+        assert_eq!(state.rounds_loaded, cvars.g_railgun_reload_ammo);
+        assert_eq!(state.next_fire_time, 0.0);
+        assert!(vehicle.fire_blocked_time > 0.0);
+
+        // No refire delay was started so it fires as soon as the muzzle is clear.
+        ctx.gs.vehicles[vehicle_handle].pos = v!(100 352);
+        let shots = burst_shots(&mut ctx, vehicle_handle, 0.6);
+        assert_eq!(shots.len(), 1);
+        assert_eq!(shots[0].0, 0.5);
+    }
+
+    #[test]
+    fn test_muzzle_blocked_burst() {
+        let cvars = burst_cvars();
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+
+        assert_eq!(burst_shots(&mut ctx, vehicle_handle, 0.05).len(), 1);
+        ctx.gs.vehicles[vehicle_handle].pos = v!(170 352);
+        assert!(burst_shots(&mut ctx, vehicle_handle, 0.3).is_empty());
+        let vehicle = &ctx.gs.vehicles[vehicle_handle];
+        assert!(vehicle.pending_burst.is_none());
+        let state = vehicle.weapon_states[Weapon::Rockets as usize];
+        assert_eq!(state.rounds_loaded, cvars.g_rockets_reload_ammo - 1);
+    }
+
+    #[test]
+    fn test_muzzle_check_strict() {
+        for strict in [false, true] {
+            let cvars = Cvars {
+                g_hardpoint_tank_railgun_x: 120.0,
+                g_muzzle_check_strict: strict,
+                ..Cvars::default()
+            };
+            let (mut server, vehicle_handle) = burst_server(&cvars);
+            let mut ctx = server.ctx(&cvars);
+            let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+            ctx.gs.players[player_handle].cur_weapon = Weapon::Rail;
+            // Pivot at 145, muzzle at 265 on the other side of the wall.
+            ctx.gs.vehicles[vehicle_handle].pos = v!(150 352);
+
+            let shots = burst_shots(&mut ctx, vehicle_handle, 0.1);
+            assert_eq!(shots.is_empty(), strict);
+            let state = ctx.gs.vehicles[vehicle_handle].weapon_states[Weapon::Rail as usize];
+            assert_eq!(state.is_reloading(), !strict);
+        }
+    }
+
+    #[test]
+    fn test_spawn_wrecks() {
+        let cvars = Cvars::default();
+        // No wrecks in the map and none requested - a clean world.
+        let server = test_utils::atrium_server(&cvars);
+        assert!(server.gs.wrecks.is_empty());
+        assert!(server.gs.vehicles.is_empty());
+        assert!(server.gs.players.is_empty());
+
+        let cvars = Cvars {
+            g_ambient_wrecks: 10,
+            ..cvars
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        assert_eq!(server.gs.wrecks.len(), 10);
+        for wreck in &server.gs.wrecks {
+            let tile_pos = server.map.tile_pos(wreck.pos);
+            let kind = server.map.surface_at_index(tile_pos.index).kind;
+            assert_ne!(kind, map::SurfaceKind::Wall);
+        }
+        // Ambient wrecks are not vehicles so nobody owns, respawns or scores with them.
+        assert!(server.gs.vehicles.is_empty());
+        assert!(server.gs.players.is_empty());
+
+        // Setting up again starts over.
+        server.ctx(&cvars).spawn_wrecks();
+        assert_eq!(server.gs.wrecks.len(), 10);
+    }
+
+    #[test]
+    fn test_predict_gm_impact() {
+        let cvars = Cvars::default();
+        for fps in [30.0, 60.0, 150.0] {
+            for speed in [100.0, 400.0, 1000.0] {
+                // Right of the wall, flying left, slightly curving.
+                let mut server = test_utils::server(&cvars, wall_map());
+                let gm = Projectile {
+                    weapon: Weapon::Gm,
+                    pos: v!(600 352),
+                    vel: v!(-speed, 0),
+                    angle: PI,
+                    turn_rate: 0.2,
+                    spawn_time: 0.0,
+                    explode_time: f64::MAX,
+                    owner: Index::DANGLING,
+                    target: None,
+                    flight: None,
+                    team: None,
+                    seeker: Seeker::default(),
+                };
+                let gm_handle = server.gs.projectiles.insert(gm);
+
+                let dt = 1.0 / fps;
+                let predicted = weapons::predict_gm_impact(
+                    &cvars,
+                    &server.map,
+                    &server.gs.projectiles[gm_handle],
+                    dt,
+                    10_000.0,
+                );
+                assert!(
+                    (predicted.x - 256.0).abs() < 1.0,
+                    "{fps} {speed} {predicted}"
+                );
+
+                let mut last = server.gs.projectiles[gm_handle].clone();
+                while let Some(gm) = server.gs.projectiles.get(gm_handle) {
+                    last = gm.clone();
+                    let game_time = server.gs.game_time + dt;
+                    server.simulate_tick(&cvars, game_time);
+                }
+                // It exploded somewhere during its last step.
+                let tolerance = last.vel.magnitude() * dt + 1.0;
+                let dist = last.pos.distance(predicted);
+                assert!(dist < tolerance, "{fps} {speed} {dist} >= {tolerance}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_predict_gm_impact_max_dist() {
+        let cvars = Cvars::default();
+        let gm = Projectile {
+            weapon: Weapon::Gm,
+            pos: v!(600 352),
+            vel: v!(-100, 0),
+            angle: PI,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: Index::DANGLING,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let predicted = weapons::predict_gm_impact(&cvars, &wall_map(), &gm, 1.0 / 150.0, 50.0);
+        assert!(predicted.x < 550.0 && predicted.x > 540.0, "{predicted}");
+        assert_eq!(predicted.y, 352.0);
+    }
+
+    fn stale_cvars() -> Cvars {
+        Cvars {
+            bots_max: 0,
+            d_strict: false,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_stale_vehicle_owner() {
+        let cvars = stale_cvars();
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) = test_utils::spawn_player(&mut ctx, "test");
+
+        // The owner is removed but the vehicle is left behind.
+        ctx.gs.players.remove(player_handle);
+        ctx.self_destruct();
+        ctx.sys_vehicle_movement();
+        ctx.sys_vehicle_logic();
+        ctx.sys_shooting();
+        ctx.damage(Index::DANGLING, vehicle_handle, f64::MAX, None, None);
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+    }
+
+    #[test]
+    fn test_stale_damage() {
+        let cvars = stale_cvars();
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let mut handles = Vec::new();
+        for name in ["attacker", "victim"] {
+            let player_handle = test_utils::add_player(&mut ctx, name);
+            ctx.spawn_vehicle(player_handle, true);
+            handles.push(player_handle);
+        }
+        let (attacker, victim) = (handles[0], handles[1]);
+        let vehicle_handle = ctx.gs.players[victim].vehicle.unwrap();
+
+        // The attacker left while their projectile was still flying.
+        ctx.gs.players.remove(attacker);
+        ctx.damage(attacker, vehicle_handle, f64::MAX, Some(Weapon::Rail), None);
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+        assert_eq!(ctx.gs.players[victim].score.deaths, 1);
+
+        // The vehicle itself is gone.
+        ctx.gs.vehicles.remove(vehicle_handle);
+        ctx.damage(victim, vehicle_handle, 1.0, None, None);
+    }
+
+    #[test]
+    fn test_damage_assists() {
+        let cvars = Cvars {
+            g_assist_min_damage: 20.0,
+            g_assist_window: 10.0,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let mut handles = Vec::new();
+        for name in ["killer", "helper", "victim"] {
+            let player_handle = test_utils::add_player(&mut ctx, name);
+            ctx.spawn_vehicle(player_handle, true);
+            handles.push(player_handle);
+        }
+        let (killer, helper, victim) = (handles[0], handles[1], handles[2]);
+        let vehicle_handle = ctx.gs.players[victim].vehicle.unwrap();
+        ctx.gs.vehicles[vehicle_handle].spawn_time = -100.0;
+        let hp = cvars.g_vehicle_hp(ctx.gs.vehicles[vehicle_handle].veh_type);
+
+        // Self-damage doesn't count, helper 40 %, killer 60 %.
+        ctx.damage(victim, vehicle_handle, 0.1 * hp, None, None);
+        ctx.damage(helper, vehicle_handle, 0.4 * hp, None, None);
+        ctx.damage(killer, vehicle_handle, f64::MAX, None, None);
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+
+        assert_eq!(ctx.gs.players[killer].score.kills, 1);
+        assert_eq!(ctx.gs.players[killer].score.assists, 0);
+        assert_eq!(ctx.gs.players[helper].score.assists, 1);
+        assert_eq!(ctx.gs.players[victim].score.assists, 0);
+        let helper_id = ctx.gs.player_ids.id(helper).unwrap();
+        assert_eq!(ctx.sg.match_stats.deaths[0].assists, [helper_id]);
+    }
+
+    #[test]
+    #[should_panic(expected = "stale handle")]
+    fn test_stale_strict() {
+        let cvars = Cvars {
+            d_strict: true,
+            ..stale_cvars()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        ctx.damage(Index::DANGLING, Index::DANGLING, 1.0, None, None);
+    }
+
+    /// Open map with water in the right half.
+    fn water_map() -> Map {
+        test_utils::arena(|r, c| match (r, c) {
+            (_, 6..) => "44",
+            (8, 2) => "36",
+            _ => "0",
+        })
+    }
+
+    /// Blow a hovercraft standing next to the water into it.
+    ///
+    /// Returns the server, the victim, the attacker and the victim's vehicle.
+    fn push_into_water(cvars: &Cvars) -> (Server, Index, Index, Index) {
+        let mut server = test_utils::server(cvars, water_map());
+        let mut ctx = server.ctx(cvars);
+        let victim_handle = test_utils::add_player(&mut ctx, "victim");
+        let attacker_handle = test_utils::add_player(&mut ctx, "attacker");
+        // Parked in a corner, out of the blast, so respawning has a vehicle to check.
+        test_utils::add_vehicle(
+            &mut ctx,
+            attacker_handle,
+            VehicleType::Tank,
+            v!(96 608),
+            0.0,
+        );
+        let vehicle_handle = test_utils::add_vehicle(
+            &mut ctx,
+            victim_handle,
+            VehicleType::Hovercraft,
+            v!(360 352),
+            0.0,
+        );
+
+        ctx.explosion_damage(
+            attacker_handle,
+            Some(Weapon::Rockets),
+            v!(320 352),
+            0.0,
+            0.0,
+            100.0,
+            None,
+            false,
+        );
+        let push = ctx.gs.vehicles[vehicle_handle].last_push.unwrap();
+        assert_eq!(push.attacker, attacker_handle);
+
+        run_until(&mut server, cvars, 0.5);
+        let vehicle = &server.gs.vehicles[vehicle_handle];
+        assert!(vehicle.pos.x > 384.0, "pos {:?}", vehicle.pos);
+
+        (server, victim_handle, attacker_handle, vehicle_handle)
+    }
+
+    fn sink_cvars() -> Cvars {
+        Cvars {
+            bots_max: 0,
+            g_explosion_knockback: 500.0,
+            g_hovercraft_water_lethal: true,
+            g_sink_time: 1.0,
+            ..Cvars::default()
+        }
+    }
+
+    /// Simulate fixed ticks until `game_time`.
+    fn run_until(server: &mut Server, cvars: &Cvars, game_time: f64) {
+        let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+        while server.gs.game_time < game_time {
+            let game_time = server.gs.game_time + dt;
+            server.simulate_tick(cvars, game_time);
+        }
+    }
+
+    #[test]
+    fn test_sink_after_push() {
+        let cvars = sink_cvars();
+        let (mut server, victim_handle, attacker_handle, vehicle_handle) = push_into_water(&cvars);
+
+        let vehicle = &server.gs.vehicles[vehicle_handle];
+        let sinking = vehicle.sinking.unwrap();
+        assert!(!vehicle.destroyed());
+        assert_eq!(vehicle.vel, Vec2f::zero());
+
+        run_until(&mut server, &cvars, sinking.end_time + 0.05);
+
+        // Dead and gone, there's no wreck to draw.
+        assert!(server.gs.vehicles[vehicle_handle].sunk());
+
+        assert_eq!(server.gs.players[attacker_handle].score.kills, 1);
+        assert_eq!(server.gs.players[victim_handle].score.deaths, 1);
+    }
+
+    #[test]
+    fn test_sink_credit_expires() {
+        let cvars = Cvars {
+            g_knockback_credit_time: 0.5,
+            ..sink_cvars()
+        };
+        let (mut server, victim_handle, attacker_handle, vehicle_handle) = push_into_water(&cvars);
+
+        let sinking = server.gs.vehicles[vehicle_handle].sinking.unwrap();
+        run_until(&mut server, &cvars, sinking.end_time + 0.05);
+        assert!(server.gs.vehicles[vehicle_handle].sunk());
+
+        // The water gets the kill, not the attacker and not the victim.
+        let victim = &server.gs.players[victim_handle];
+        assert_eq!(server.gs.players[attacker_handle].score.kills, 0);
+        assert_eq!(victim.score.deaths, 1);
+        assert_eq!(victim.score.suicides, 0);
+    }
+
+    #[test]
+    fn test_water_harmless_by_default() {
+        let cvars = Cvars {
+            g_hovercraft_water_lethal: false,
+            ..sink_cvars()
+        };
+        let (mut server, _, _, vehicle_handle) = push_into_water(&cvars);
+        run_until(&mut server, &cvars, 3.0);
+
+        let vehicle = &server.gs.vehicles[vehicle_handle];
+        assert!(vehicle.sinking.is_none());
+        assert!(!vehicle.destroyed());
+    }
+
+    #[test]
+    fn test_quantize_state_absorbs_noise() {
+        let cvars = Cvars {
+            d_seed: 7,
+            g_quantize_state: 1000.0,
+            ..Cvars::default()
+        };
+        let mut a = test_utils::atrium_server(&cvars);
+        let mut b = test_utils::atrium_server(&cvars);
+        run_until(&mut a, &cvars, 0.5);
+        run_until(&mut b, &cvars, 0.5);
+
+        // Noise way below the quantization step, e.g. from a different platform's libm.
+        for (_, vehicle) in b.gs.vehicles.iter_mut() {
+            vehicle.pos.x += 1e-9;
+            vehicle.angle += 1e-9;
+            vehicle.turret_angle_current += 1e-9;
+        }
+
+        run_until(&mut a, &cvars, 1.0);
+        run_until(&mut b, &cvars, 1.0);
+
+        assert_eq!(a.gs.vehicles.len(), b.gs.vehicles.len());
+        for ((ha, va), (hb, vb)) in a.gs.vehicles.iter().zip(b.gs.vehicles.iter()) {
+            assert_eq!(ha, hb);
+            assert_eq!(va.pos, vb.pos);
+            assert_eq!(va.angle, vb.angle);
+            assert_eq!(va.turret_angle_current, vb.turret_angle_current);
+        }
+        #[cfg(feature = "sim_hash")]
+        assert_eq!(
+            crate::sim_hash::hash_state(&a.gs, &a.sg.rng),
+            crate::sim_hash::hash_state(&b.gs, &b.sg.rng)
+        );
+    }
+
+    /// A cluster bomb owned by the first player, a tank for each of `vehicle_xs` at y 300,
+    /// on the map with a wall from x 192 to 256.
+    fn splash_server(
+        cvars: &Cvars,
+        cb_pos: Vec2f,
+        vehicle_xs: &[f64],
+    ) -> (Server, Index, Vec<Index>) {
+        let mut server = test_utils::server(cvars, wall_map());
+        let mut ctx = server.ctx(cvars);
+        let mut vehicles = Vec::new();
+        for &x in vehicle_xs {
+            let player_handle = test_utils::add_player(&mut ctx, "test");
+            let vehicle_handle = test_utils::add_vehicle(
+                &mut ctx,
+                player_handle,
+                VehicleType::Tank,
+                v!(x, 300),
+                0.0,
+            );
+            vehicles.push(vehicle_handle);
+        }
+        let owner = ctx.gs.vehicles[vehicles[0]].owner;
+        let cb = Projectile {
+            weapon: Weapon::Cb,
+            pos: cb_pos,
+            vel: Vec2f::zero(),
+            angle: 0.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: 0.0,
+            owner,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let cb_handle = ctx.gs.projectiles.insert(cb);
+        (server, cb_handle, vehicles)
+    }
+
+    fn splash_cvars() -> Cvars {
+        Cvars {
+            g_cluster_bomb_explosion_damage: 40.0,
+            g_cluster_bomb_explosion_radius: 100.0,
+            g_cluster_bomb_explosion_scale: 1.0,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_explosion_falloff() {
+        let cvars = splash_cvars();
+        let hp = cvars.g_vehicle_hp(VehicleType::Tank);
+        // Hit circles at 0 (the owner), 50 and 150 from the explosion.
+        let xs = [
+            500.0,
+            500.0 + 50.0 + cvars.g_hitcircle_radius,
+            500.0 + 150.0 + cvars.g_hitcircle_radius,
+        ];
+        let (mut server, cb_handle, vehicles) = splash_server(&cvars, v!(500 300), &xs);
+        let mut ctx = server.ctx(&cvars);
+        // Low enough for the explosion to finish off its own owner.
+        ctx.gs.vehicles[vehicles[0]].hp_fraction = 30.0 / hp;
+
+        ctx.projectile_impact(cb_handle, v!(500 300), false);
+
+        let owner = &ctx.gs.vehicles[vehicles[0]];
+        assert!(owner.destroyed());
+        assert_eq!(ctx.gs.players[owner.owner].score.suicides, 1);
+        let dmg = (1.0 - ctx.gs.vehicles[vehicles[1]].hp_fraction) * hp;
+        assert!((dmg - 20.0).abs() < 1e-9, "{dmg}");
+        assert_eq!(ctx.gs.vehicles[vehicles[2]].hp_fraction, 1.0);
+
+        // No falloff
+        let cvars = Cvars {
+            g_explosion_damage_edge: 1.0,
+            ..splash_cvars()
+        };
+        let (mut server, cb_handle, vehicles) = splash_server(&cvars, v!(500 300), &xs);
+        let mut ctx = server.ctx(&cvars);
+        ctx.projectile_impact(cb_handle, v!(500 300), false);
+        for &vehicle_handle in &vehicles[..2] {
+            let dmg = (1.0 - ctx.gs.vehicles[vehicle_handle].hp_fraction) * hp;
+            assert!((dmg - 40.0).abs() < 1e-9, "{dmg}");
+        }
+    }
+
+    #[test]
+    fn test_explosion_los() {
+        // Just inside the right side of the wall like a projectile which hit it.
+        let expl_pos = v!(255.99 300);
+        let xs = [150.0, 300.0];
+        for los in [false, true] {
+            let cvars = Cvars {
+                g_explosion_los: los,
+                ..splash_cvars()
+            };
+            let (mut server, cb_handle, vehicles) = splash_server(&cvars, expl_pos, &xs);
+            let mut ctx = server.ctx(&cvars);
+            ctx.projectile_impact(cb_handle, expl_pos, false);
+            let behind_wall = ctx.gs.vehicles[vehicles[0]].hp_fraction;
+            let in_front = ctx.gs.vehicles[vehicles[1]].hp_fraction;
+            assert_eq!(behind_wall == 1.0, los);
+            assert!(in_front < 1.0);
+        }
+    }
+
+    /// A vehicle of `veh_type` at `pos` whose owner holds self destruct,
+    /// returns the player and vehicle handles.
+    fn add_self_destructing(
+        ctx: &mut ServerFrameCtx<'_>,
+        veh_type: VehicleType,
+        pos: Vec2f,
+    ) -> (Index, Index) {
+        let player_handle = test_utils::add_player(ctx, "test");
+        ctx.gs.players[player_handle].input.self_destruct = true;
+        let vehicle_handle = test_utils::add_vehicle(ctx, player_handle, veh_type, pos, 0.5);
+        (player_handle, vehicle_handle)
+    }
+
+    fn self_destruct_cvars() -> Cvars {
+        Cvars {
+            bots_max: 0,
+            g_hovercraft_self_destruct: SelfDestruct::Submunitions,
+            g_hummer_self_destruct: SelfDestruct::Rockets,
+            g_tank_self_destruct: SelfDestruct::Blast,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_self_destruct_blast() {
+        for factor in [1.0, 1.5] {
+            let cvars = Cvars {
+                g_self_destruct_blast_factor: factor,
+                ..self_destruct_cvars()
+            };
+            let mut server = test_utils::atrium_server(&cvars);
+            let mut ctx = server.ctx(&cvars);
+            let (player_handle, vehicle_handle) =
+                add_self_destructing(&mut ctx, VehicleType::Tank, v!(500 500));
+            // Outside the normal radius, inside the bigger one.
+            let dist = cvars.g_self_destruct_radius * 1.25 + cvars.g_hitcircle_radius;
+            let (bystander, bystander_vehicle) =
+                add_self_destructing(&mut ctx, VehicleType::Tank, v!(500 500) + v!(dist, 0));
+            ctx.gs.players[bystander].input.self_destruct = false;
+
+            ctx.self_destruct();
+
+            assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+            assert!(ctx.gs.projectiles.is_empty());
+            let hp = ctx.gs.vehicles[bystander_vehicle].hp_fraction;
+            assert_eq!(hp < 1.0, factor > 1.0, "{factor}");
+            assert_eq!(ctx.gs.players[player_handle].score.suicides, 1);
+        }
+    }
+
+    #[test]
+    fn test_self_destruct_submunitions() {
+        let cvars = self_destruct_cvars();
+        let mut server = test_utils::atrium_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) =
+            add_self_destructing(&mut ctx, VehicleType::Hovercraft, v!(500 500));
+
+        ctx.self_destruct();
+
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+        let cbs: Vec<_> = ctx.gs.projectiles.iter().map(|(_, cb)| cb).collect();
+        assert_eq!(cbs.len(), cvars.g_self_destruct_submunitions_count);
+        let mut sum = Vec2f::zero();
+        for cb in cbs {
+            assert_eq!(cb.weapon, Weapon::Cb);
+            assert_eq!(cb.owner, player_handle);
+            assert_eq!(cb.pos, v!(500 500));
+            let fuse = cb.explode_time - ctx.gs.game_time;
+            let spread = cvars.g_cluster_bomb_time_spread;
+            assert!((fuse - cvars.g_self_destruct_submunitions_time).abs() <= spread);
+            sum += cb.angle.to_vec2f();
+        }
+        // Evenly spread out in all directions.
+        assert!(sum.magnitude() < 1e-9, "{sum}");
+    }
+
+    #[test]
+    fn test_self_destruct_rockets() {
+        let cvars = self_destruct_cvars();
+        let mut server = test_utils::server(&cvars, wall_map());
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, vehicle_handle) =
+            add_self_destructing(&mut ctx, VehicleType::Hummer, v!(400 300));
+
+        ctx.self_destruct();
+
+        assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+        let rockets: Vec<_> = ctx.gs.projectiles.iter().collect();
+        assert_eq!(rockets.len(), 2);
+        for (_, rocket) in &rockets {
+            assert_eq!(rocket.weapon, Weapon::Rockets);
+            assert_eq!(rocket.owner, player_handle);
+        }
+        // Forward and backward.
+        let forward = 0.5f64.to_vec2f();
+        let (forward_handle, _) = rockets
+            .iter()
+            .find(|(_, rocket)| rocket.vel.normalized().dot(forward) > 0.999)
+            .unwrap();
+        let forward_handle = *forward_handle;
+        assert!(rockets
+            .iter()
+            .any(|(_, rocket)| rocket.vel.normalized().dot(forward) < -0.999));
+
+        // A kill by the rocket goes to the player who self destructed.
+        let victim_pos = v!(400 300) + forward * 30.0;
+        let (victim, victim_vehicle) =
+            add_self_destructing(&mut ctx, VehicleType::Tank, victim_pos);
+        ctx.gs.players[victim].input.self_destruct = false;
+        ctx.gs.vehicles[victim_vehicle].hp_fraction = 0.01;
+        ctx.gs.dt = 0.1;
+        ctx.sys_projectiles();
+        assert!(!ctx.gs.projectiles.contains(forward_handle));
+        assert!(ctx.gs.vehicles[victim_vehicle].destroyed());
+        assert_eq!(ctx.gs.players[player_handle].score.kills, 1);
+    }
+
+    #[test]
+    fn test_boost_cooldown() {
+        let cvars = Cvars {
+            g_boost_cooldown: 4.0,
+            g_boost_duration: 1.0,
+            g_boost_factor: 2.0,
+            ..Cvars::default()
+        };
+        let (mut server, vehicle_handle) = burst_server(&cvars);
+        let mut ctx = server.ctx(&cvars);
+        let player_handle = ctx.gs.vehicles[vehicle_handle].owner;
+        ctx.gs.players[player_handle].input = NetInput {
+            boost: true,
+            ..NetInput::empty()
+        };
+        let speed_max = |ctx: &ServerFrameCtx| {
+            ctx.gs.vehicles[vehicle_handle]
+                .movement_stats(ctx.cvars, ctx.gs.game_time)
+                .speed_max
+        };
+        let base = cvars.g_vehicle_movement_stats(VehicleType::Tank).speed_max;
+
+        // Holding the input boosts again as soon as the cooldown is over and not before.
+        let mut starts = Vec::new();
+        while ctx.gs.game_time < 12.0 {
+            let before = ctx.gs.vehicles[vehicle_handle].boost_until;
+            ctx.sys_boost();
+            let vehicle = &ctx.gs.vehicles[vehicle_handle];
+            if vehicle.boost_until != before {
+                starts.push(ctx.gs.game_time);
+            }
+            assert!(vehicle.modifiers.modifiers.len() <= 1);
+            let expected = if vehicle.boosting(ctx.gs.game_time) {
+                base * 2.0
+            } else {
+                base
+            };
+            assert_eq!(speed_max(&ctx), expected, "{}", ctx.gs.game_time);
+            ctx.gs.game_time += 1.0 / 64.0;
+        }
+        assert_eq!(starts, [0.0, 5.0, 10.0]);
+
+        // No boosting while guiding a missile.
+        ctx.gs.game_time = 20.0;
+        ctx.gs.players[player_handle].guided_missile = Some(Index::DANGLING);
+        ctx.sys_boost();
+        assert!(!ctx.gs.vehicles[vehicle_handle].boosting(ctx.gs.game_time));
+    }
+
+    /// Player spawned at game time 10 with the spawn effect and input lock on.
+    fn spawn_effect_server() -> (Cvars, Server, Index) {
+        let cvars = Cvars {
+            g_respawn_delay: 0.0,
+            g_spawn_effect_duration: 0.5,
+            g_spawn_input_lock: 0.25,
+            ..Cvars::default()
+        };
+        let mut server = test_utils::atrium_server(&cvars);
+        server.gs.game_time = 10.0;
+        let mut ctx = server.ctx(&cvars);
+        let (player_handle, _) = test_utils::spawn_player(&mut ctx, "test");
+        (cvars, server, player_handle)
+    }
+
+    /// Replace the player's input, keeping the old one for edge detection like the server does.
+    fn set_input(ctx: &mut ServerFrameCtx<'_>, player_handle: Index, input: NetInput) {
+        let player = &mut ctx.gs.players[player_handle];
+        player.input_prev = player.input;
+        player.input = input;
+    }
+
+    #[test]
+    fn test_spawn_effect_blocks_fire_and_damage() {
+        let (cvars, mut server, player_handle) = spawn_effect_server();
+        let mut ctx = server.ctx(&cvars);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        let attacker_handle = test_utils::add_player(&mut ctx, "attacker");
+        let fire = NetInput {
+            fire: true,
+            ..NetInput::empty()
+        };
+        set_input(&mut ctx, player_handle, fire);
+
+        // Both resume exactly when the effect ends.
+        let mut first_shot = None;
+        let mut first_damage = None;
+        while first_shot.is_none() || first_damage.is_none() {
+            assert!(ctx.gs.game_time < 11.0);
+            ctx.sys_shooting();
+            if first_shot.is_none() && !ctx.gs.projectiles.is_empty() {
+                first_shot = Some(ctx.gs.game_time);
+            }
+            ctx.damage(attacker_handle, vehicle_handle, 1.0, Some(Weapon::Mg), None);
+            if first_damage.is_none() && ctx.gs.vehicles[vehicle_handle].hp_fraction < 1.0 {
+                first_damage = Some(ctx.gs.game_time);
+            }
+            ctx.gs.game_time += 1.0 / 64.0;
+        }
+        assert_eq!(first_shot, Some(10.5));
+        assert_eq!(first_damage, Some(10.5));
+    }
+
+    #[test]
+    fn test_spawn_input_lock() {
+        let (cvars, mut server, player_handle) = spawn_effect_server();
+        let mut ctx = server.ctx(&cvars);
+        let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+        let held = NetInput {
+            up: true,
+            fire: true,
+            ..NetInput::empty()
+        };
+        set_input(&mut ctx, player_handle, held);
+
+        // No lurching while materializing.
+        let dt = 1.0 / 64.0;
+        ctx.gs.dt = dt;
+        while ctx.gs.game_time < 10.25 {
+            ctx.sys_vehicle_movement();
+            assert_eq!(ctx.gs.vehicles[vehicle_handle].vel, Vec2f::zero());
+            ctx.gs.game_time += dt;
+        }
+        ctx.sys_vehicle_movement();
+        assert_ne!(ctx.gs.vehicles[vehicle_handle].vel, Vec2f::zero());
+
+        // The lock only applies to movement so edge detection keeps working -
+        // dying during the lock, the next press and release still respawns.
+        // Self-destruct works while materializing.
+        let released = NetInput::empty();
+        let pressed = NetInput {
+            fire: true,
+            ..NetInput::empty()
+        };
+        ctx.damage(player_handle, vehicle_handle, f64::MAX, None, None);
+        for _ in 0..2 {
+            let dead_handle = ctx.gs.players[player_handle].vehicle;
+            for input in [released, pressed, released] {
+                set_input(&mut ctx, player_handle, input);
+                ctx.sys_respawning();
+                ctx.gs.game_time += dt;
+            }
+            ctx.sys_respawning();
+            let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+            assert_ne!(Some(vehicle_handle), dead_handle);
+
+            let vehicle = &ctx.gs.vehicles[vehicle_handle];
+            assert!(vehicle.input_locked(ctx.cvars, ctx.gs.game_time));
+            ctx.damage(player_handle, vehicle_handle, f64::MAX, None, None);
+            assert!(ctx.gs.vehicles[vehicle_handle].destroyed());
+        }
+    }
+
+    #[test]
+    fn test_cb_landing_matches_spawn_code() {
+        for gaussian in [true, false] {
+            let cvars = Cvars {
+                g_cluster_bomb_speed_spread_gaussian: gaussian,
+                ..Cvars::default()
+            };
+            let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+            let uniform11 = Uniform::new_inclusive(-1.0, 1.0);
+            let shot_angle = 0.7;
+            let origin = v!(100 200);
+            // Sideways relative to the shot so the sideways spread also depends on the fuse.
+            let vehicle_vel = v!(30, -80);
+            let predicted = weapons::cb_landing(&cvars, shot_angle, origin, vehicle_vel);
+
+            // Same as shooting.
+            let samples = 20_000;
+            let mut offsets = Vec::new();
+            for _ in 0..samples {
+                let mut cb = Projectile {
+                    weapon: Weapon::Cb,
+                    pos: origin,
+                    vel: Vec2f::zero(),
+                    angle: shot_angle,
+                    turn_rate: 0.0,
+                    spawn_time: 0.0,
+                    explode_time: f64::MAX,
+                    owner: Index::DANGLING,
+                    target: None,
+                    flight: None,
+                    team: None,
+                    seeker: Seeker::default(),
+                };
+                cluster_bomb(
+                    &cvars,
+                    &mut rng,
+                    uniform11,
+                    &mut cb,
+                    cvars.g_cluster_bomb_speed,
+                    cvars.g_cluster_bomb_time,
+                );
+                cb.vel += cvars.g_cluster_bomb_vehicle_velocity_factor * vehicle_vel;
+                let landing = cb.pos + cb.vel * (cb.explode_time - cb.spawn_time);
+                offsets.push((landing - predicted.mean).rotated_z(-shot_angle));
+            }
+
+            let mean = offsets.iter().copied().sum::<Vec2f>() / samples as f64;
+            let variance = offsets
+                .iter()
+                .map(|&offset| (offset - mean).map(|c| c * c))
+                .sum::<Vec2f>()
+                / samples as f64;
+            let sigma = variance.map(f64::sqrt);
+
+            // A few % of the spread.
+            assert!(
+                mean.magnitude() < 0.05 * predicted.sigma_forward,
+                "{gaussian} {mean}"
+            );
+            let tolerance = 0.03;
+            let forward_ratio = sigma.x / predicted.sigma_forward;
+            let sideways_ratio = sigma.y / predicted.sigma_sideways;
+            assert!(
+                (forward_ratio - 1.0).abs() < tolerance,
+                "{gaussian} {forward_ratio}"
+            );
+            assert!(
+                (sideways_ratio - 1.0).abs() < tolerance,
+                "{gaussian} {sideways_ratio}"
+            );
+        }
     }
 }
@@ -0,0 +1,112 @@
+//! Fixtures shared by unit tests.
+
+use std::fs;
+
+use crate::{
+    map::{self, Surface},
+    net::NullListener,
+    prelude::*,
+};
+
+/// The real texture list so tile numbers mean the same as in the real maps.
+pub fn surfaces() -> Vec<Surface> {
+    let tex_list_text = fs::read_to_string("data/texture_list.txt").unwrap();
+    map::parse_texture_list(&tex_list_text)
+}
+
+/// Rows of tile numbers, optionally followed by metadata.
+pub fn parse_map(text: &str) -> Map {
+    map::parse_map(text, surfaces(), "test")
+}
+
+/// One of the maps shipped with the game, e.g. `maps/Atrium.map`.
+pub fn load_map(path: &str) -> Map {
+    let map_text = fs::read_to_string(path).unwrap();
+    map::parse_map(&map_text, surfaces(), path)
+}
+
+/// `cols` x `rows` tiles of open ground without walls, `metadata` appended after the tiles.
+pub fn open_map(cols: usize, rows: usize, metadata: &str) -> Map {
+    let row = vec!["0"; cols].join(" ");
+    let mut text = format!("{row}\n").repeat(rows);
+    text.push_str(metadata);
+    parse_map(&text)
+}
+
+/// 11x11 tiles surrounded by walls, `tile(r, c)` decides everything inside.
+pub fn arena(tile: impl Fn(usize, usize) -> &'static str) -> Map {
+    let mut text = String::new();
+    for r in 0..11 {
+        let row: Vec<_> = (0..11)
+            .map(|c| {
+                if r == 0 || r == 10 || c == 0 || c == 10 {
+                    "16"
+                } else {
+                    tile(r, c)
+                }
+            })
+            .collect();
+        text.push_str(&row.join(" "));
+        text.push('\n');
+    }
+    parse_map(&text)
+}
+
+/// A server which doesn't open any sockets so tests can run in parallel.
+pub fn server(cvars: &Cvars, map: Map) -> Server {
+    Server::with_listener(cvars, map, Box::new(NullListener))
+}
+
+/// A server on Atrium, the default map.
+pub fn atrium_server(cvars: &Cvars) -> Server {
+    server(cvars, load_map("maps/Atrium.map"))
+}
+
+/// A local player without a vehicle, with a stable ID like players who joined normally.
+pub fn add_player(ctx: &mut ServerFrameCtx, name: &str) -> Index {
+    let player = Player::new(name.to_owned(), ClientType::Local);
+    let player_handle = ctx.gs.players.insert(player);
+    ctx.gs.player_ids.assign(player_handle);
+    player_handle
+}
+
+/// A local player spawned the normal way, returns the player and their vehicle.
+pub fn spawn_player(ctx: &mut ServerFrameCtx, name: &str) -> (Index, Index) {
+    let player_handle = add_player(ctx, name);
+    ctx.spawn_vehicle(player_handle, true);
+    let vehicle_handle = ctx.gs.players[player_handle].vehicle.unwrap();
+    (player_handle, vehicle_handle)
+}
+
+/// Put the player in a new vehicle at an exact position instead of a random spawn.
+pub fn add_vehicle(
+    ctx: &mut ServerFrameCtx,
+    player_handle: Index,
+    veh_type: VehicleType,
+    pos: Vec2f,
+    angle: f64,
+) -> Index {
+    let vehicle = Vehicle::new(ctx.cvars, pos, angle, veh_type, 0.0, player_handle);
+    let vehicle_handle = ctx.gs.vehicles.insert(vehicle);
+    ctx.gs.players[player_handle].vehicle = Some(vehicle_handle);
+    vehicle_handle
+}
+
+/// A local player in a tank at an exact position, for tests which only need a `GameState`.
+///
+/// Returns the player and their vehicle.
+pub fn add_tank(cvars: &Cvars, gs: &mut GameState, pos: Vec2f, angle: f64) -> (Index, Index) {
+    let player = Player::new("test".to_owned(), ClientType::Local);
+    let player_handle = gs.players.insert(player);
+    gs.player_ids.assign(player_handle);
+    let vehicle = Vehicle::new(cvars, pos, angle, VehicleType::Tank, 0.0, player_handle);
+    let vehicle_handle = gs.vehicles.insert(vehicle);
+    gs.players[player_handle].vehicle = Some(vehicle_handle);
+    (player_handle, vehicle_handle)
+}
+
+/// Simulate one fixed tick.
+pub fn tick(server: &mut Server, cvars: &Cvars) {
+    let game_time = server.gs.game_time + 1.0 / cvars.sys_tickrate_fixed_fps;
+    server.simulate_tick(cvars, game_time);
+}
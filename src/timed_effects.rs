@@ -0,0 +1,221 @@
+//! Cosmetic effects which last a fixed time and then disappear.
+//!
+//! Each kind (explosions, railgun trails, ...) lives in its own `TimedEffects`
+//! instead of a hand-rolled Vec so they all age and get removed the same way:
+//! `ClientFrameCtx::sys_cl_cleanup` calls `cleanup` once per gamelogic frame
+//! and rendering only draws what `iter_alive` returns,
+//! even if the effect expired between the cleanup and the draw.
+//!
+//! New short-lived effect types should go here too.
+
+#[derive(Debug, Clone)]
+pub struct TimedEffect<T> {
+    pub item: T,
+    pub start_time: f64,
+    pub duration: f64,
+}
+
+impl<T> TimedEffect<T> {
+    /// From 0 when it starts to 1 when it ends, can be outside that range.
+    pub fn age(&self, time: f64) -> f64 {
+        if self.duration <= 0.0 {
+            // Lasts only the instant it starts.
+            return if time < self.start_time {
+                0.0
+            } else if time == self.start_time {
+                1.0
+            } else {
+                f64::INFINITY
+            };
+        }
+        (time - self.start_time) / self.duration
+    }
+
+    pub fn alive(&self, time: f64) -> bool {
+        self.age(time) <= 1.0
+    }
+}
+
+/// Effects of one kind, at most `max` of them.
+#[derive(Debug, Clone)]
+pub struct TimedEffects<T> {
+    /// In the order they were pushed.
+    effects: Vec<TimedEffect<T>>,
+    /// When full, the oldest effect is evicted to make room for a new one.
+    pub max: usize,
+    /// How many effects were evicted before they expired.
+    pub evicted: u64,
+}
+
+impl<T> TimedEffects<T> {
+    pub fn new(max: usize) -> Self {
+        Self {
+            effects: Vec::new(),
+            max,
+            evicted: 0,
+        }
+    }
+
+    pub fn push(&mut self, item: T, start_time: f64, duration: f64) {
+        if self.max == 0 {
+            self.evicted += 1;
+            return;
+        }
+        self.evict_to(self.max - 1);
+        self.effects.push(TimedEffect {
+            item,
+            start_time,
+            duration,
+        });
+    }
+
+    /// Items which haven't expired yet at `time` along with their age, see `TimedEffect::age`.
+    ///
+    /// The age is never negative, effects starting in the future are drawn as just started.
+    pub fn iter_alive(&self, time: f64) -> impl Iterator<Item = (&T, f64)> {
+        self.effects
+            .iter()
+            .filter(move |effect| effect.alive(time))
+            .map(move |effect| (&effect.item, effect.age(time).max(0.0)))
+    }
+
+    /// All effects, including expired ones which haven't been cleaned up yet.
+    pub fn iter(&self) -> impl Iterator<Item = &TimedEffect<T>> {
+        self.effects.iter()
+    }
+
+    /// Remove expired effects and enforce `max` in case it was lowered.
+    pub fn cleanup(&mut self, time: f64) {
+        self.effects.retain(|effect| effect.alive(time));
+        self.evict_to(self.max);
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Evict the oldest effects until at most `len` remain.
+    /// Effects which started at the same time go in the order they were pushed.
+    fn evict_to(&mut self, len: usize) {
+        while self.effects.len() > len {
+            let (oldest, _) = self
+                .effects
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.start_time.total_cmp(&b.start_time))
+                .unwrap();
+            self.effects.remove(oldest);
+            self.evicted += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items<T: Copy>(effects: &TimedEffects<T>) -> Vec<T> {
+        effects.iter().map(|effect| effect.item).collect()
+    }
+
+    #[test]
+    fn test_age() {
+        let effect = TimedEffect {
+            item: (),
+            start_time: 10.0,
+            duration: 2.0,
+        };
+        assert_eq!(effect.age(9.0), -0.5);
+        assert_eq!(effect.age(10.0), 0.0);
+        assert_eq!(effect.age(11.5), 0.75);
+        assert_eq!(effect.age(12.0), 1.0);
+        assert!(effect.alive(12.0));
+        assert!(!effect.alive(12.1));
+
+        let instant = TimedEffect {
+            duration: 0.0,
+            ..effect
+        };
+        assert_eq!(instant.age(9.0), 0.0);
+        assert_eq!(instant.age(10.0), 1.0);
+        assert!(instant.alive(10.0));
+        assert!(!instant.alive(10.1));
+    }
+
+    #[test]
+    fn test_iter_alive() {
+        let mut effects = TimedEffects::new(10);
+        effects.push('a', 0.0, 1.0);
+        effects.push('b', 0.5, 1.0);
+        effects.push('c', 2.0, 1.0);
+
+        // Expired ones are skipped even before cleanup, future ones haven't aged yet.
+        let alive: Vec<_> = effects.iter_alive(1.25).collect();
+        assert_eq!(alive, [(&'b', 0.75), (&'c', 0.0)]);
+        assert_eq!(effects.len(), 3);
+    }
+
+    #[test]
+    fn test_cleanup_timing() {
+        let mut effects = TimedEffects::new(10);
+        effects.push(1, 0.0, 1.0);
+        effects.push(2, 0.0, 2.0);
+
+        effects.cleanup(1.0);
+        assert_eq!(items(&effects), [1, 2]);
+        effects.cleanup(1.01);
+        assert_eq!(items(&effects), [2]);
+        effects.cleanup(2.01);
+        assert!(effects.is_empty());
+        assert_eq!(effects.evicted, 0);
+    }
+
+    #[test]
+    fn test_eviction_order() {
+        let mut effects = TimedEffects::new(3);
+        // Pushed out of order, e.g. catch-up effects aged differently.
+        effects.push(1, 5.0, 10.0);
+        effects.push(2, 3.0, 10.0);
+        effects.push(3, 3.0, 10.0);
+
+        effects.push(4, 6.0, 10.0);
+        assert_eq!(items(&effects), [1, 3, 4]);
+        effects.push(5, 6.0, 10.0);
+        assert_eq!(items(&effects), [1, 4, 5]);
+        effects.push(6, 7.0, 10.0);
+        assert_eq!(items(&effects), [4, 5, 6]);
+        assert_eq!(effects.evicted, 3);
+    }
+
+    #[test]
+    fn test_cap() {
+        let mut effects = TimedEffects::new(4);
+        for i in 0..10 {
+            effects.push(i, i as f64, 100.0);
+            assert!(effects.len() <= 4);
+        }
+        assert_eq!(items(&effects), [6, 7, 8, 9]);
+        assert_eq!(effects.evicted, 6);
+
+        // Lowering the cap applies at the next cleanup.
+        effects.max = 2;
+        effects.cleanup(10.0);
+        assert_eq!(items(&effects), [8, 9]);
+        assert_eq!(effects.evicted, 8);
+
+        // Nothing is kept with a cap of 0.
+        effects.max = 0;
+        effects.push(10, 10.0, 100.0);
+        effects.cleanup(10.0);
+        assert!(effects.is_empty());
+        assert_eq!(effects.evicted, 11);
+    }
+}
@@ -1,4 +1,5 @@
-//! Rudimentary FPS counter and performance tracker.
+//! Rudimentary FPS counter and performance tracker
+//! and the mapping from real (wall clock) time to game time.
 
 use crate::prelude::*;
 
@@ -11,6 +12,10 @@ pub struct CommonTimings {
 
     pub update_fps: f64,
     pub gamelogic_fps: f64,
+
+    /// Approximate size of one rewind history snapshot in bytes,
+    /// 0 without the `history` feature, see `history`.
+    pub history_snapshot_size: usize,
 }
 
 /// Saves frame times over some period of time to measure FPS.
@@ -84,3 +89,311 @@ impl Durations {
         Some((avg, max))
     }
 }
+
+/// The part of a frame which takes the most time, see `PerfWarning`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerfCost {
+    /// Gamelogic, networking and spawning effects
+    Update,
+    /// Draw calls, mostly depends on how many effects are on screen
+    Draw,
+}
+
+/// Tells the player the game can't keep up, see `cl_perf_warning`.
+///
+/// Update and draw together have to stay over the frame budget for `cl_perf_warning_time`
+/// to show the warning and then under `cl_perf_warning_hysteresis` of the budget
+/// for `cl_perf_warning_recover_time` to clear it so it doesn't flicker
+/// when the average hovers around the budget.
+#[derive(Debug, Clone, Default)]
+pub struct PerfWarning {
+    /// Whether the warning is shown.
+    pub degraded: bool,
+    /// What's taking the most time, updated while degraded.
+    pub dominant: Option<PerfCost>,
+    /// Real time when the averages started pointing the other way than `degraded`.
+    pub pending_since: Option<f64>,
+}
+
+impl PerfWarning {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed it the rolling averages of update and draw durations in seconds,
+    /// returns whether `degraded` just changed.
+    pub fn update(
+        &mut self,
+        cvars: &Cvars,
+        update_avg: Option<f64>,
+        draw_avg: Option<f64>,
+        real_time: f64,
+    ) -> bool {
+        let was_degraded = self.degraded;
+        if !cvars.cl_perf_warning {
+            *self = Self::new();
+            return was_degraded;
+        }
+        let (Some(update_avg), Some(draw_avg)) = (update_avg, draw_avg) else {
+            return false;
+        };
+
+        let budget = 1.0 / cvars.cl_perf_warning_fps;
+        let total = update_avg + draw_avg;
+        let (wants_change, delay) = if self.degraded {
+            (
+                total < budget * cvars.cl_perf_warning_hysteresis,
+                cvars.cl_perf_warning_recover_time,
+            )
+        } else {
+            (total > budget, cvars.cl_perf_warning_time)
+        };
+
+        if wants_change {
+            let since = *self.pending_since.get_or_insert(real_time);
+            if real_time - since >= delay {
+                self.pending_since = None;
+                self.degraded = !self.degraded;
+            }
+        } else {
+            self.pending_since = None;
+        }
+
+        self.dominant = if !self.degraded {
+            None
+        } else if update_avg > draw_avg {
+            Some(PerfCost::Update)
+        } else {
+            Some(PerfCost::Draw)
+        };
+        self.degraded != was_degraded
+    }
+}
+
+/// Game times of the gamelogic ticks to run in this update.
+///
+/// This is the only place where real time turns into game time,
+/// both server and client go through it.
+///
+/// `real_time_delta` is the real time since the previous update,
+/// it's thrown away while paused so unpausing after a long pause doesn't result in a huge dt.
+/// Pause only changes between updates so a catch-up is always either run whole or not at all.
+/// With `TickrateMode::Fixed`, `carry` is the leftover time less than a tick long
+/// and it's kept while paused.
+pub fn gamelogic_tick_times(
+    cvars: &Cvars,
+    game_time: f64,
+    carry: &mut f64,
+    real_time_delta: f64,
+    paused: bool,
+) -> Vec<f64> {
+    if paused {
+        return Vec::new();
+    }
+
+    let dt_update = real_time_delta * cvars.d_speed;
+    if dt_update > 5.0 {
+        dbg_logf!("WARNING: large dt_update: {dt_update}");
+    }
+
+    match cvars.sys_tickrate_mode {
+        TickrateMode::Variable => vec![game_time + dt_update],
+        TickrateMode::Fixed => {
+            let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+            let game_time_target = game_time + *carry + dt_update;
+
+            let mut times = Vec::new();
+            let mut time = game_time;
+            while time + dt < game_time_target {
+                time += dt;
+                times.push(time);
+            }
+
+            *carry = game_time_target - time;
+            if cvars.d_tickrate_fixed_carry {
+                dbg_logf!("Remaining time: {}", *carry);
+            }
+            times
+        }
+    }
+}
+
+/// When a tick from `gamelogic_tick_times` happened in real time, between the two updates.
+///
+/// `game_time` and `carry` are the values from before `gamelogic_tick_times` ran.
+/// The leftover `carry` is game time which already passed in real time during earlier updates.
+pub fn tick_real_time(
+    cvars: &Cvars,
+    real_time_prev: f64,
+    real_time: f64,
+    game_time: f64,
+    carry: f64,
+    tick_game_time: f64,
+) -> f64 {
+    match cvars.sys_tickrate_mode {
+        TickrateMode::Variable => real_time,
+        TickrateMode::Fixed => {
+            real_time_prev + (tick_game_time - game_time - carry) / cvars.d_speed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed the real times to `gamelogic_tick_times` like the game loop does,
+    /// pausing during `paused`, and return game time after each update.
+    fn run(cvars: &Cvars, real_times: &[f64], paused: impl Fn(f64) -> bool) -> Vec<f64> {
+        let mut game_time = 0.0;
+        let mut carry = 0.0;
+        let mut real_time_prev = 0.0;
+        let mut game_times = Vec::new();
+        for &real_time in real_times {
+            let delta = real_time - real_time_prev;
+            real_time_prev = real_time;
+            let times =
+                gamelogic_tick_times(cvars, game_time, &mut carry, delta, paused(real_time));
+            if let Some(&last) = times.last() {
+                game_time = last;
+            }
+            game_times.push(game_time);
+        }
+        game_times
+    }
+
+    #[test]
+    fn test_pause_variable() {
+        let cvars = Cvars {
+            sys_tickrate_mode: TickrateMode::Variable,
+            ..Cvars::default()
+        };
+        // Paused from 1 to 100 s of real time.
+        let real_times = [0.5, 1.0, 2.0, 50.0, 99.75, 100.0, 100.25];
+        let game_times = run(&cvars, &real_times, |t| (1.0..100.0).contains(&t));
+        assert_eq!(game_times, [0.5, 0.5, 0.5, 0.5, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn test_pause_variable_speed() {
+        let cvars = Cvars {
+            sys_tickrate_mode: TickrateMode::Variable,
+            d_speed: 2.0,
+            ..Cvars::default()
+        };
+        let real_times = [0.5, 1.0, 29.75, 30.0];
+        let game_times = run(&cvars, &real_times, |t| (1.0..30.0).contains(&t));
+        assert_eq!(game_times, [1.0, 1.0, 1.0, 1.5]);
+    }
+
+    #[test]
+    fn test_pause_fixed() {
+        let cvars = Cvars {
+            sys_tickrate_mode: TickrateMode::Fixed,
+            sys_tickrate_fixed_fps: 4.0,
+            ..Cvars::default()
+        };
+        // 0.375 s is 1.5 ticks, the half tick carries over the pause.
+        let real_times = [0.375, 10.0, 19.875, 20.0, 20.125];
+        let game_times = run(&cvars, &real_times, |t| (10.0..20.0).contains(&t));
+        assert_eq!(game_times, [0.25, 0.25, 0.25, 0.25, 0.5]);
+
+        // No ticks at all while paused, no matter how long.
+        let mut carry = 0.125;
+        assert!(gamelogic_tick_times(&cvars, 1.0, &mut carry, 1000.0, true).is_empty());
+        assert_eq!(carry, 0.125);
+    }
+
+    #[test]
+    fn test_tick_real_time() {
+        let cvars = Cvars {
+            sys_tickrate_mode: TickrateMode::Fixed,
+            sys_tickrate_fixed_fps: 4.0,
+            ..Cvars::default()
+        };
+        // 0.125 s of game time was left over so the first tick is only 0.125 s after the last update.
+        let mut carry = 0.125;
+        let times = gamelogic_tick_times(&cvars, 1.0, &mut carry, 0.5, false);
+        assert_eq!(times, [1.25, 1.5]);
+        let real_times: Vec<_> = times
+            .iter()
+            .map(|&t| tick_real_time(&cvars, 10.0, 10.5, 1.0, 0.125, t))
+            .collect();
+        assert_eq!(real_times, [10.125, 10.375]);
+
+        let cvars = Cvars {
+            d_speed: 2.0,
+            ..cvars
+        };
+        assert_eq!(tick_real_time(&cvars, 10.0, 10.5, 1.0, 0.0, 1.5), 10.25);
+
+        let cvars = Cvars {
+            sys_tickrate_mode: TickrateMode::Variable,
+            ..cvars
+        };
+        assert_eq!(tick_real_time(&cvars, 10.0, 10.5, 1.0, 0.0, 2.0), 10.5);
+    }
+
+    #[test]
+    fn test_perf_warning() {
+        let cvars = Cvars {
+            cl_perf_warning: true,
+            cl_perf_warning_fps: 100.0,
+            cl_perf_warning_hysteresis: 0.8,
+            cl_perf_warning_recover_time: 3.0,
+            cl_perf_warning_time: 2.0,
+            ..Cvars::default()
+        };
+        // Update and draw averages, the budget is 10 ms.
+        let slow_draw = (Some(0.004), Some(0.008));
+        let slow_update = (Some(0.008), Some(0.004));
+        let borderline = (Some(0.003), Some(0.006));
+        let fast = (Some(0.002), Some(0.004));
+
+        let mut warning = PerfWarning::new();
+        let mut update = |(update_avg, draw_avg), real_time| {
+            let changed = warning.update(&cvars, update_avg, draw_avg, real_time);
+            (changed, warning.degraded, warning.dominant)
+        };
+
+        assert_eq!(update((None, None), 0.0), (false, false, None));
+        assert_eq!(update(slow_draw, 0.0), (false, false, None));
+        assert_eq!(update(slow_draw, 1.0), (false, false, None));
+        // A short dip under the budget starts over.
+        assert_eq!(update(borderline, 1.5), (false, false, None));
+        assert_eq!(update(slow_draw, 2.0), (false, false, None));
+        assert_eq!(update(slow_draw, 3.9), (false, false, None));
+        let draw = Some(PerfCost::Draw);
+        assert_eq!(update(slow_draw, 4.0), (true, true, draw));
+
+        // The dominant cost follows the averages.
+        let update_cost = Some(PerfCost::Update);
+        assert_eq!(update(slow_update, 5.0), (false, true, update_cost));
+
+        // Under the budget but not under the hysteresis - stays degraded indefinitely.
+        assert_eq!(update(borderline, 6.0), (false, true, draw));
+        assert_eq!(update(borderline, 20.0), (false, true, draw));
+
+        // Recovering has to last.
+        assert_eq!(update(fast, 21.0), (false, true, draw));
+        assert_eq!(update(borderline, 22.0), (false, true, draw));
+        assert_eq!(update(fast, 23.0), (false, true, draw));
+        assert_eq!(update(fast, 25.9), (false, true, draw));
+        assert_eq!(update(fast, 26.0), (true, false, None));
+        assert_eq!(update(borderline, 30.0), (false, false, None));
+
+        // Turning it off clears the warning immediately.
+        let off = Cvars {
+            cl_perf_warning: false,
+            ..Cvars::default()
+        };
+        let mut warning = PerfWarning {
+            degraded: true,
+            dominant: draw,
+            pending_since: None,
+        };
+        assert!(warning.update(&off, slow_draw.0, slow_draw.1, 0.0));
+        assert!(!warning.degraded);
+        assert!(!warning.update(&off, slow_draw.0, slow_draw.1, 100.0));
+    }
+}
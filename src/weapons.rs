@@ -2,9 +2,668 @@
 
 use crate::prelude::*;
 
+/// Direction and starting position of a shot.
+///
+/// Depends on whether the weapon is mounted on the chassis or the turret.
+/// Shared by shooting and the crosshair so they can't disagree.
+pub fn shot_angle_origin(cvars: &Cvars, vehicle: &Vehicle, weapon: Weapon) -> (f64, Vec2f) {
+    let (hardpoint, weapon_offset) = cvars.g_hardpoint(vehicle.veh_type, weapon);
+    hardpoint_angle_origin(cvars, vehicle, hardpoint, weapon_offset)
+}
+
+/// Muzzle position and the direction the backblast goes when launching a missile - opposite the shot.
+pub fn launch_blast(cvars: &Cvars, vehicle: &Vehicle, weapon: Weapon) -> (Vec2f, Vec2f) {
+    let (shot_angle, shot_origin) = shot_angle_origin(cvars, vehicle, weapon);
+    (shot_origin, -shot_angle.to_vec2f())
+}
+
+/// Like `shot_angle_origin` but for the second rocket launcher used by bursts.
+pub fn rockets_alt_angle_origin(cvars: &Cvars, vehicle: &Vehicle) -> (f64, Vec2f) {
+    let (hardpoint, _) = cvars.g_hardpoint(vehicle.veh_type, Weapon::Rockets);
+    let weapon_offset = cvars.g_hardpoint_rockets_alt(vehicle.veh_type);
+    hardpoint_angle_origin(cvars, vehicle, hardpoint, weapon_offset)
+}
+
+/// Whether the shot would start inside a wall so it can't be fired.
+///
+/// With `g_muzzle_check_strict`, turret weapons are also blocked
+/// when the muzzle pokes through a thin wall right in front of the turret.
+/// `alt_side` is the second rocket launcher, see `rockets_alt_angle_origin`.
+pub fn muzzle_blocked(
+    cvars: &Cvars,
+    map: &Map,
+    vehicle: &Vehicle,
+    weapon: Weapon,
+    alt_side: bool,
+) -> bool {
+    let (_, muzzle) = if alt_side {
+        rockets_alt_angle_origin(cvars, vehicle)
+    } else {
+        shot_angle_origin(cvars, vehicle, weapon)
+    };
+    if map.is_wall(muzzle) {
+        return true;
+    }
+
+    let (hardpoint, _) = cvars.g_hardpoint(vehicle.veh_type, weapon);
+    if cvars.g_muzzle_check_strict && hardpoint == Hardpoint::Turret {
+        let turret_offset = cvars.g_vehicle_turret_offset_chassis(vehicle.veh_type);
+        let pivot = vehicle.pos + turret_offset.rotated_z(vehicle.angle);
+        return map.is_wall_trace(pivot, muzzle).is_some();
+    }
+    false
+}
+
+fn hardpoint_angle_origin(
+    cvars: &Cvars,
+    vehicle: &Vehicle,
+    hardpoint: Hardpoint,
+    weapon_offset: Vec2f,
+) -> (f64, Vec2f) {
+    match hardpoint {
+        Hardpoint::Chassis => {
+            let shot_angle = vehicle.angle;
+            let shot_origin = vehicle.pos + weapon_offset.rotated_z(shot_angle);
+            (shot_angle, shot_origin)
+        }
+        Hardpoint::Turret => {
+            let turret_offset = cvars.g_vehicle_turret_offset_chassis(vehicle.veh_type);
+            let pivot = vehicle.pos + turret_offset.rotated_z(vehicle.angle);
+            let shot_angle = vehicle.angle + vehicle.turret_angle_current;
+            turret_angle_origin(pivot, shot_angle, weapon_offset)
+        }
+    }
+}
+
+/// Shot from a turret rotating around `pivot` and currently pointing at `shot_angle`.
+///
+/// Shared by vehicles and emplacements.
+fn turret_angle_origin(pivot: Vec2f, shot_angle: f64, weapon_offset: Vec2f) -> (f64, Vec2f) {
+    (shot_angle, pivot + weapon_offset.rotated_z(shot_angle))
+}
+
+/// Like `shot_angle_origin` for emplacements - the turret rotates around the center.
+pub fn emplacement_angle_origin(cvars: &Cvars, emplacement: &Emplacement) -> (f64, Vec2f) {
+    let shot_angle = emplacement.angle + emplacement.turret_angle_current;
+    let weapon_offset = Vec2f::new(cvars.g_emplacement_barrel_length, 0.0);
+    turret_angle_origin(emplacement.pos, shot_angle, weapon_offset)
+}
+
+/// Like `muzzle_blocked` for emplacements, the barrel always counts as a turret weapon.
+pub fn emplacement_muzzle_blocked(cvars: &Cvars, map: &Map, emplacement: &Emplacement) -> bool {
+    let (_, muzzle) = emplacement_angle_origin(cvars, emplacement);
+    if map.is_wall(muzzle) {
+        return true;
+    }
+    cvars.g_muzzle_check_strict && map.is_wall_trace(emplacement.pos, muzzle).is_some()
+}
+
+/// Where the BFG's beam attaches to the vehicle - the point of its hitbox nearest to the BFG.
+pub fn bfg_beam_target(bfg_pos: Vec2f, vehicle: &Vehicle) -> Vec2f {
+    vehicle
+        .hitbox
+        .closest_point(vehicle.pos, vehicle.angle, bfg_pos)
+}
+
 // LATER This is all wrong, should be on context, cl needs prev pos to find nearest point.
-pub fn bfg_beam_hit(cvars: &Cvars, map: &Map, nearest_point: Vec2f, vehicle_pos: Vec2f) -> bool {
-    let dist2 = (nearest_point - vehicle_pos).magnitude_squared();
+pub fn bfg_beam_hit(cvars: &Cvars, map: &Map, bfg_pos: Vec2f, beam_target: Vec2f) -> bool {
+    let dist2 = (bfg_pos - beam_target).magnitude_squared();
     dist2 <= cvars.g_bfg_beam_range * cvars.g_bfg_beam_range
-        && map.is_wall_trace(nearest_point, vehicle_pos).is_none()
+        && map.is_wall_trace(bfg_pos, beam_target).is_none()
+}
+
+/// Angle between the missile's heading and `target_pos`,
+/// None if it's outside the seeker cone (`g_homing_missile_seeker_fov`) or range.
+///
+/// Acquiring a new target also needs the angle below `g_homing_missile_angle_detect`.
+/// This is only geometry, walls and smoke are checked separately.
+pub fn hm_seeker_angle(cvars: &Cvars, hm: &Projectile, target_pos: Vec2f) -> Option<f64> {
+    let to_target = target_pos - hm.pos;
+    let range = cvars.g_homing_missile_seeker_range;
+    if to_target.magnitude_squared() > range * range {
+        return None;
+    }
+    // Right on top of it.
+    let Some(target_dir) = to_target.try_normalized() else {
+        return Some(0.0);
+    };
+    let angle = hm
+        .angle
+        .to_vec2f()
+        .dot(target_dir)
+        .clamped(-1.0, 1.0)
+        .acos();
+    (angle <= cvars.g_homing_missile_seeker_fov / 2.0).then_some(angle)
+}
+
+/// The hot flare a homing missile should chase instead of its current target, if any.
+///
+/// Same seeker cone and line of sight rules as for vehicles.
+/// Missiles ignore their own owner's flares.
+pub fn hm_flare_target(
+    cvars: &Cvars,
+    map: &Map,
+    flares: &Arena<Flare>,
+    smokes: &[Smoke],
+    game_time: f64,
+    hm: &Projectile,
+) -> Option<Index> {
+    let mut best_target = None;
+    let mut best_target_angle_diff = f64::INFINITY;
+    for (flare_handle, flare) in flares.iter() {
+        if flare.owner == hm.owner || !flare.spoofing(cvars, game_time) {
+            continue;
+        }
+        let pos = flare.pos_at(game_time);
+        let Some(angle_diff) = hm_seeker_angle(cvars, hm, pos) else {
+            continue;
+        };
+        if angle_diff < cvars.g_homing_missile_angle_detect
+            && angle_diff < best_target_angle_diff
+            && map.is_wall_trace(hm.pos, pos).is_none()
+            && !smoke_blocks_view(smokes, hm.pos, pos)
+        {
+            best_target = Some(flare_handle);
+            best_target_angle_diff = angle_diff;
+        }
+    }
+    best_target
+}
+
+/// The parts of a guided missile which change as it flies.
+///
+/// Passed around by value so the prediction can run the same steps
+/// as the real missile without touching game state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GmMotion {
+    pub pos: Vec2f,
+    pub vel: Vec2f,
+    pub angle: f64,
+    pub turn_rate: f64,
+}
+
+impl GmMotion {
+    pub fn new(gm: &Projectile) -> Self {
+        Self {
+            pos: gm.pos,
+            vel: gm.vel,
+            angle: gm.angle,
+            turn_rate: gm.turn_rate,
+        }
+    }
+
+    pub fn apply(self, gm: &mut Projectile) {
+        gm.pos = self.pos;
+        gm.vel = self.vel;
+        gm.angle = self.angle;
+        gm.turn_rate = self.turn_rate;
+    }
+
+    /// Turning and acceleration for one tick, doesn't move.
+    ///
+    /// Movement is the same for all projectiles and happens in `sys_projectiles`.
+    pub fn steer(mut self, cvars: &Cvars, input: NetInput, dt: f64) -> Self {
+        let stats = cvars.g_guided_missile_movement_stats();
+        self.angle = ServerFrameCtx::turning(
+            &stats,
+            &mut self.vel,
+            &self.angle,
+            &mut self.turn_rate,
+            input,
+            dt,
+        );
+        ServerFrameCtx::accel_decel(&stats, &mut self.vel, self.angle, input, dt);
+        self
+    }
+}
+
+/// Where the guided missile would hit a wall if the player stopped steering now.
+///
+/// Simulates the missile tick by tick with no input (it keeps accelerating),
+/// gives up after `max_dist` and returns the last position.
+/// Vehicles and other blockers are ignored, they move anyway.
+pub fn predict_gm_impact(
+    cvars: &Cvars,
+    map: &Map,
+    gm: &Projectile,
+    dt: f64,
+    max_dist: f64,
+) -> Vec2f {
+    let mut motion = GmMotion::new(gm);
+    let mut dist = 0.0;
+    while dist < max_dist {
+        motion = motion.steer(cvars, NetInput::new_up(), dt);
+        let new_pos = motion.pos + motion.vel * dt;
+        if let Some(hit_pos) = map.is_wall_trace(motion.pos, new_pos) {
+            return hit_pos;
+        }
+        let step = (new_pos - motion.pos).magnitude();
+        if step == 0.0 {
+            break;
+        }
+        dist += step;
+        motion.pos = new_pos;
+    }
+    motion.pos
+}
+
+/// Where the cluster bombs of one shot explode on average and how much they spread.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CbLanding {
+    pub mean: Vec2f,
+    /// Direction of the shot, the spread is measured along and perpendicular to it.
+    pub angle: f64,
+    /// Standard deviation of the distance traveled along `angle`.
+    pub sigma_forward: f64,
+    /// Standard deviation perpendicular to `angle`.
+    pub sigma_sideways: f64,
+}
+
+/// Predict where cluster bombs fired now would explode, see `hud_range_hints`.
+///
+/// Uses the same `g_cluster_bomb_*` cvars as `cluster_bomb` in systems:
+/// speed and fuse time are randomized independently,
+/// gaussian spread uses the cvar as standard deviation,
+/// uniform spread (from -1 to 1 times the cvar) has a standard deviation of cvar / sqrt(3).
+/// Walls and vehicles are ignored.
+pub fn cb_landing(
+    cvars: &Cvars,
+    shot_angle: f64,
+    shot_origin: Vec2f,
+    vehicle_vel: Vec2f,
+) -> CbLanding {
+    let uniform_sigma = 1.0 / 3.0f64.sqrt();
+    let speed_sigma = if cvars.g_cluster_bomb_speed_spread_gaussian {
+        1.0
+    } else {
+        uniform_sigma
+    };
+    let sigma_f = cvars.g_cluster_bomb_speed_spread_forward * speed_sigma;
+    let sigma_s = cvars.g_cluster_bomb_speed_spread_sideways * speed_sigma;
+    let sigma_t = cvars.g_cluster_bomb_time_spread * uniform_sigma;
+
+    let inherited = cvars.g_cluster_bomb_vehicle_velocity_factor * vehicle_vel;
+    // Mean velocity in the shot's frame - forward and sideways.
+    let local = Vec2f::new(cvars.g_cluster_bomb_speed, 0.0) + inherited.rotated_z(-shot_angle);
+    let time = cvars.g_cluster_bomb_time;
+
+    // Distance is velocity times time, both independent random variables:
+    // Var(V * T) = E[V]^2 Var(T) + E[T]^2 Var(V) + Var(V) Var(T)
+    let product_sigma = |mean_vel: f64, sigma_vel: f64| {
+        (mean_vel.powi(2) * sigma_t.powi(2)
+            + time.powi(2) * sigma_vel.powi(2)
+            + sigma_vel.powi(2) * sigma_t.powi(2))
+        .sqrt()
+    };
+
+    CbLanding {
+        mean: shot_origin + local.rotated_z(shot_angle) * time,
+        angle: shot_angle,
+        sigma_forward: product_sigma(local.x, sigma_f),
+        sigma_sideways: product_sigma(local.y, sigma_s),
+    }
+}
+
+/// Where `guider`'s vehicle is if `viewer` should see them guiding a missile, see `g_gm_reveal`.
+///
+/// Only enemies within `g_gm_reveal_range` of the viewer's vehicle are revealed,
+/// dead players don't see anything.
+pub fn revealed_guider(
+    cvars: &Cvars,
+    gs: &GameState,
+    viewer: Index,
+    guider: Index,
+) -> Option<Vec2f> {
+    if !cvars.g_gm_reveal || viewer == guider {
+        return None;
+    }
+    let viewer_player = gs.players.get(viewer)?;
+    let guider_player = gs.players.get(guider)?;
+    guider_player.guided_missile?;
+    if viewer_player.team.is_some() && viewer_player.team == guider_player.team {
+        return None;
+    }
+
+    let alive_pos = |player: &Player| {
+        let vehicle = gs.vehicles.get(player.vehicle?)?;
+        (!vehicle.destroyed()).then_some(vehicle.pos)
+    };
+    let viewer_pos = alive_pos(viewer_player)?;
+    let guider_pos = alive_pos(guider_player)?;
+    let dist2 = (guider_pos - viewer_pos).magnitude_squared();
+    (dist2 <= cvars.g_gm_reveal_range * cvars.g_gm_reveal_range).then_some(guider_pos)
+}
+
+/// Order of weapons when cycling with prev/next, see `g_weapon_order`.
+///
+/// A permutation - each weapon exactly once.
+/// Only affects what players see and press,
+/// weapons are still indexed by their declaration order internally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeaponOrder(Vec<Weapon>);
+
+impl WeaponOrder {
+    pub fn weapons(&self) -> &[Weapon] {
+        &self.0
+    }
+
+    /// Where the weapon is in the cycle.
+    pub fn position(&self, weapon: Weapon) -> usize {
+        self.0.iter().position(|&w| w == weapon).unwrap()
+    }
+
+    /// The weapon after `weapon`, wraps around from the last to the first.
+    pub fn next(&self, weapon: Weapon) -> Weapon {
+        let i = self.position(weapon);
+        self.0[(i + 1) % self.0.len()]
+    }
+
+    /// The weapon before `weapon`, wraps around from the first to the last.
+    pub fn prev(&self, weapon: Weapon) -> Weapon {
+        let i = self.position(weapon);
+        self.0[(i + self.0.len() - 1) % self.0.len()]
+    }
+}
+
+impl Default for WeaponOrder {
+    fn default() -> Self {
+        Self(
+            (0..Weapon::COUNT)
+                .map(|i| Weapon::from_repr(i).unwrap())
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for WeaponOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weapons = Vec::new();
+        for name in s.split(',') {
+            let name = name.trim();
+            let weapon: Weapon = name.parse().map_err(|_| {
+                format!("Unknown weapon `{name}` in `{s}`, expected a comma-separated list of all weapons")
+            })?;
+            if weapons.contains(&weapon) {
+                return Err(format!(
+                    "Weapon `{weapon}` is listed more than once in `{s}`"
+                ));
+            }
+            weapons.push(weapon);
+        }
+        for weapon in WeaponOrder::default().0 {
+            if !weapons.contains(&weapon) {
+                return Err(format!("Weapon `{weapon}` is missing from `{s}`"));
+            }
+        }
+        Ok(Self(weapons))
+    }
+}
+
+impl Display for WeaponOrder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let names: Vec<_> = self.0.iter().map(Weapon::to_string).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+/// Weapons a vehicle type can use, see `g_tank_weapons` and friends.
+///
+/// Written as `all` or a comma-separated list, never empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeaponSet(Vec<Weapon>);
+
+impl WeaponSet {
+    pub fn contains(&self, weapon: Weapon) -> bool {
+        self.0.contains(&weapon)
+    }
+}
+
+impl Default for WeaponSet {
+    fn default() -> Self {
+        Self(WeaponOrder::default().0)
+    }
+}
+
+impl FromStr for WeaponSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "all" {
+            return Ok(Self::default());
+        }
+        let mut weapons = Vec::new();
+        for name in s.split(',') {
+            let name = name.trim();
+            let weapon: Weapon = name.parse().map_err(|_| {
+                format!("Unknown weapon `{name}` in `{s}`, expected `all` or a comma-separated list of weapons")
+            })?;
+            if !weapons.contains(&weapon) {
+                weapons.push(weapon);
+            }
+        }
+        Ok(Self(weapons))
+    }
+}
+
+impl Display for WeaponSet {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.0.len() == Weapon::COUNT {
+            return write!(f, "all");
+        }
+        let names: Vec<_> = self.0.iter().map(Weapon::to_string).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    /// Open area with a single wall tile at (1, 1) - x from 64 to 128.
+    fn pillar_map() -> Map {
+        test_utils::parse_map("0 0 0 0\n0 16 0 0\n0 0 0 0\n")
+    }
+
+    #[test]
+    fn test_muzzle_blocked() {
+        let map = pillar_map();
+        let cvars = Cvars::default();
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(40 96),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+
+        // Railgun muzzle at 40 - 5 + 35 = 70, the machine gun is too short to reach.
+        assert!(muzzle_blocked(&cvars, &map, &vehicle, Weapon::Rail, false));
+        assert!(!muzzle_blocked(&cvars, &map, &vehicle, Weapon::Mg, false));
+
+        vehicle.turret_angle_current = PI / 2.0;
+        assert!(!muzzle_blocked(&cvars, &map, &vehicle, Weapon::Rail, false));
+
+        // Outside the map counts as wall.
+        vehicle.turret_angle_current = PI;
+        assert!(muzzle_blocked(&cvars, &map, &vehicle, Weapon::Rail, false));
+    }
+
+    #[test]
+    fn test_muzzle_check_strict() {
+        let map = pillar_map();
+        let mut cvars = Cvars {
+            g_hardpoint_tank_railgun_x: 120.0,
+            g_hardpoint_tank_homing_missile_x: 120.0,
+            g_hardpoint_tank_homing_missile_y: 0.0,
+            ..Cvars::default()
+        };
+        let vehicle = Vehicle::new(
+            &cvars,
+            v!(20 96),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+
+        // Both muzzles are past the wall, only the turret weapon is checked from the pivot.
+        assert!(!muzzle_blocked(&cvars, &map, &vehicle, Weapon::Rail, false));
+        assert!(!muzzle_blocked(&cvars, &map, &vehicle, Weapon::Hm, false));
+        cvars.g_muzzle_check_strict = true;
+        assert!(muzzle_blocked(&cvars, &map, &vehicle, Weapon::Rail, false));
+        assert!(!muzzle_blocked(&cvars, &map, &vehicle, Weapon::Hm, false));
+    }
+
+    #[test]
+    fn test_shot_angle_hardpoints() {
+        let mut cvars = Cvars::default();
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(100 100),
+            0.5,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        vehicle.turret_angle_current = 1.0;
+
+        cvars.g_hardpoint_tank_machine_gun = Hardpoint::Chassis;
+        let (angle, _) = shot_angle_origin(&cvars, &vehicle, Weapon::Mg);
+        assert_eq!(angle, 0.5);
+
+        cvars.g_hardpoint_tank_machine_gun = Hardpoint::Turret;
+        let (angle, _) = shot_angle_origin(&cvars, &vehicle, Weapon::Mg);
+        assert_eq!(angle, 1.5);
+    }
+
+    #[test]
+    fn test_launch_blast_hardpoints() {
+        let mut cvars = Cvars::default();
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(100 100),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        vehicle.turret_angle_current = PI / 2.0;
+
+        // Out the back of the chassis
+        cvars.g_hardpoint_tank_homing_missile = Hardpoint::Chassis;
+        let (muzzle, dir) = launch_blast(&cvars, &vehicle, Weapon::Hm);
+        assert!((dir - v!(-1 0)).magnitude() < 1e-9);
+        let (_, origin) = shot_angle_origin(&cvars, &vehicle, Weapon::Hm);
+        assert_eq!(muzzle, origin);
+
+        // Out the back of the turret
+        cvars.g_hardpoint_tank_homing_missile = Hardpoint::Turret;
+        let (muzzle, dir) = launch_blast(&cvars, &vehicle, Weapon::Hm);
+        assert!((dir - v!(0, -1)).magnitude() < 1e-9);
+        let (_, origin) = shot_angle_origin(&cvars, &vehicle, Weapon::Hm);
+        assert_eq!(muzzle, origin);
+    }
+
+    #[test]
+    fn test_hm_seeker_angle() {
+        let cvars = Cvars {
+            g_homing_missile_seeker_fov: 90.0f64.to_radians(),
+            g_homing_missile_seeker_range: 500.0,
+            ..Cvars::default()
+        };
+        let hm = Projectile {
+            weapon: Weapon::Hm,
+            pos: v!(100 100),
+            vel: Vec2f::zero(),
+            angle: 0.0,
+            turn_rate: 0.0,
+            spawn_time: 0.0,
+            explode_time: f64::MAX,
+            owner: Index::DANGLING,
+            target: None,
+            flight: None,
+            team: None,
+            seeker: Seeker::default(),
+        };
+        let angle = |offset: Vec2f| hm_seeker_angle(&cvars, &hm, hm.pos + offset);
+
+        assert_eq!(angle(v!(200 0)), Some(0.0));
+        assert_eq!(angle(v!(0 0)), Some(0.0));
+        // Just inside and just outside the cone, it's the same on both sides.
+        let inside = angle(44.0f64.to_radians().to_vec2f() * 100.0).unwrap();
+        assert!((inside - 44.0f64.to_radians()).abs() < 1e-9);
+        assert!(angle((-44.0f64).to_radians().to_vec2f() * 100.0).is_some());
+        assert_eq!(angle(46.0f64.to_radians().to_vec2f() * 100.0), None);
+        assert_eq!(angle((-46.0f64).to_radians().to_vec2f() * 100.0), None);
+        assert_eq!(angle(v!(-200 0)), None);
+        // Range
+        assert!(angle(v!(400 300)).is_some());
+        assert_eq!(angle(v!(501 0)), None);
+    }
+
+    #[test]
+    fn test_weapon_order_parse() {
+        let order: WeaponOrder = "mg,rail,cb,rockets,hm,gm,bfg".parse().unwrap();
+        assert_eq!(order, WeaponOrder::default());
+
+        // Round-trip, whitespace and case don't matter.
+        let order: WeaponOrder = "Rockets, mg,rail, CB,hm,gm,bfg".parse().unwrap();
+        assert_eq!(order.to_string(), "rockets,mg,rail,cb,hm,gm,bfg");
+        assert_eq!(order.to_string().parse::<WeaponOrder>().unwrap(), order);
+
+        let err = "mg,rail,cb,rockets,hm,gm,bfg,mg"
+            .parse::<WeaponOrder>()
+            .unwrap_err();
+        assert!(err.contains("`mg` is listed more than once"), "{err}");
+        let err = "mg,rail,cb,rockets,hm,gm"
+            .parse::<WeaponOrder>()
+            .unwrap_err();
+        assert!(err.contains("`bfg` is missing"), "{err}");
+        let err = "mg,rail,cb,rockets,hm,gm,bfg,laser"
+            .parse::<WeaponOrder>()
+            .unwrap_err();
+        assert!(err.contains("Unknown weapon `laser`"), "{err}");
+    }
+
+    #[test]
+    fn test_weapon_order_cycle() {
+        let order: WeaponOrder = "bfg,rail,mg,cb,rockets,hm,gm".parse().unwrap();
+        assert_eq!(order.next(Weapon::Rail), Weapon::Mg);
+        assert_eq!(order.prev(Weapon::Mg), Weapon::Rail);
+        // Wraps both ways
+        assert_eq!(order.next(Weapon::Gm), Weapon::Bfg);
+        assert_eq!(order.prev(Weapon::Bfg), Weapon::Gm);
+
+        let mut weapon = Weapon::Cb;
+        for _ in 0..Weapon::COUNT {
+            weapon = order.prev(weapon);
+        }
+        assert_eq!(weapon, Weapon::Cb);
+    }
+
+    #[test]
+    fn test_weapon_set_parse() {
+        let all: WeaponSet = "all".parse().unwrap();
+        assert_eq!(all, WeaponSet::default());
+        assert_eq!(all.to_string(), "all");
+
+        // Duplicates don't matter, listing everything is `all`.
+        let set: WeaponSet = "Rail, mg,rail".parse().unwrap();
+        assert!(set.contains(Weapon::Rail) && set.contains(Weapon::Mg));
+        assert!(!set.contains(Weapon::Bfg));
+        assert_eq!(set.to_string(), "rail,mg");
+        assert_eq!(set.to_string().parse::<WeaponSet>().unwrap(), set);
+        let set: WeaponSet = "mg,rail,cb,rockets,hm,gm,bfg".parse().unwrap();
+        assert_eq!(set.to_string(), "all");
+
+        let err = "".parse::<WeaponSet>().unwrap_err();
+        assert!(err.contains("Unknown weapon ``"), "{err}");
+        let err = "mg,laser".parse::<WeaponSet>().unwrap_err();
+        assert!(err.contains("Unknown weapon `laser`"), "{err}");
+    }
 }
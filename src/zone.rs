@@ -0,0 +1,286 @@
+//! `g_zone` - a safe area which shrinks over time, vehicles outside take damage.
+//!
+//! Mostly for bot matches - bots wander randomly and without it
+//! they can go a long time without running into each other.
+
+use crate::{ai_dodge::dodge_input, prelude::*};
+
+/// How many random tiles to try when no spawn is inside the zone.
+const SPAWN_ATTEMPTS: usize = 100;
+
+impl ServerFrameCtx<'_> {
+    /// Create the zone if `g_zone` is on.
+    ///
+    /// Part of match setup, clients get it in `Init`.
+    pub fn init_zone(&mut self) {
+        if !self.cvars.g_zone {
+            self.gs.zone = None;
+            return;
+        }
+
+        // Starts out covering the whole map.
+        let start_center = (self.map.mins() + self.map.maxs()) / 2.0;
+        let start_radius = start_center.distance(self.map.mins());
+        let target_radius = self.cvars.g_zone_radius_min.min(start_radius);
+
+        // The final zone must fit inside the initial one
+        // so nobody gets caught outside by a zone moving faster than it shrinks.
+        let (pos, _) = self.map.random_nonwall(&mut self.sg.rng);
+        let offset = pos - start_center;
+        let offset_max = start_radius - target_radius;
+        let offset = if offset.magnitude() > offset_max {
+            offset.normalized() * offset_max
+        } else {
+            offset
+        };
+
+        let shrink_start = self.gs.game_time + self.cvars.g_zone_start_delay;
+        self.gs.zone = Some(Zone {
+            start_center,
+            start_radius,
+            target_center: start_center + offset,
+            target_radius,
+            shrink_start,
+            shrink_end: shrink_start + self.cvars.g_zone_shrink_duration,
+        });
+    }
+
+    /// Damage vehicles outside the zone.
+    pub fn sys_zone(&mut self) {
+        let Some(zone) = self.gs.zone else {
+            return;
+        };
+        let amount = self.cvars.g_zone_damage_per_sec * self.gs.dt;
+        if amount <= 0.0 {
+            return;
+        }
+
+        for vehicle_handle in self.gs.vehicles.collect_handles() {
+            let vehicle = &self.gs.vehicles[vehicle_handle];
+            if vehicle.destroyed() || zone.contains(self.gs.game_time, vehicle.pos) {
+                continue;
+            }
+            // Nobody gets the kill, `damage` treats it like an attacker who left.
+            self.damage(Index::DANGLING, vehicle_handle, amount, None, None);
+        }
+    }
+
+    /// A spawn inside the zone so players don't start out taking damage.
+    ///
+    /// Falls back to a random tile inside the zone if no spawn is in it
+    /// and to the spawn closest to the zone's center if that fails too.
+    /// Returns (pos, angle).
+    pub fn zone_spawn(&mut self, zone: &Zone) -> (Vec2f, f64) {
+        let game_time = self.gs.game_time;
+        let spawns: Vec<_> = self
+            .map
+            .spawns()
+            .iter()
+            .map(|&index| (self.map.tile_center(index), self.map[index].angle))
+            .collect();
+
+        let inside: Vec<_> = spawns
+            .iter()
+            .filter(|(pos, _)| zone.contains(game_time, *pos))
+            .collect();
+        if !inside.is_empty() {
+            return *inside[self.sg.rng.gen_range(0..inside.len())];
+        }
+
+        for _ in 0..SPAWN_ATTEMPTS {
+            let (pos, _) = self.map.random_nonwall(&mut self.sg.rng);
+            if zone.contains(game_time, pos) {
+                // Same as spawning on random tiles without the zone.
+                let angle = self.sg.rng.gen_range(0.0..2.0 * PI);
+                return (pos, angle);
+            }
+        }
+
+        let (center, _) = zone.at(game_time);
+        spawns
+            .into_iter()
+            .min_by(|(a, _), (b, _)| {
+                a.distance_squared(center)
+                    .total_cmp(&b.distance_squared(center))
+            })
+            .unwrap_or_else(|| self.map.random_spawn(&mut self.sg.rng))
+    }
+}
+
+/// Movement input for a bot outside the zone to head back in, None if it's inside.
+pub fn zone_input(zone: &Zone, game_time: f64, vehicle: &Vehicle) -> Option<NetInput> {
+    if zone.contains(game_time, vehicle.pos) {
+        return None;
+    }
+    let (center, _) = zone.at(game_time);
+    let dir = (center - vehicle.pos).try_normalized()?;
+    Some(dodge_input(vehicle.angle, dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn zone() -> Zone {
+        Zone {
+            start_center: v!(500 500),
+            start_radius: 700.0,
+            target_center: v!(300 400),
+            target_radius: 100.0,
+            shrink_start: 10.0,
+            shrink_end: 30.0,
+        }
+    }
+
+    #[test]
+    fn test_zone_shrink() {
+        let zone = zone();
+
+        // Waits for the delay.
+        assert_eq!(zone.at(0.0), (v!(500 500), 700.0));
+        assert_eq!(zone.at(10.0), (v!(500 500), 700.0));
+
+        assert_eq!(zone.at(20.0), (v!(400 450), 400.0));
+
+        // Never smaller than the target.
+        assert_eq!(zone.at(30.0), (v!(300 400), 100.0));
+        assert_eq!(zone.at(1000.0), (v!(300 400), 100.0));
+
+        // Instant shrinking.
+        let instant = Zone {
+            shrink_end: 10.0,
+            ..zone
+        };
+        assert_eq!(instant.at(9.9), (v!(500 500), 700.0));
+        assert_eq!(instant.at(10.0), (v!(300 400), 100.0));
+    }
+
+    fn zone_cvars() -> Cvars {
+        Cvars {
+            bots_max: 0,
+            g_zone: true,
+            g_zone_shrink_duration: 10.0,
+            g_zone_start_delay: 0.0,
+            ..Cvars::default()
+        }
+    }
+
+    #[test]
+    fn test_init_zone() {
+        for seed in 0..20 {
+            let cvars = Cvars {
+                d_seed: seed,
+                ..zone_cvars()
+            };
+            let server = test_utils::atrium_server(&cvars);
+            let zone = server.gs.zone.unwrap();
+
+            // Covers the whole map at first.
+            let maxs = server.map.maxs();
+            for corner in [v!(0 0), v!(maxs.x, 0), v!(0, maxs.y), maxs] {
+                assert!(zone.contains(0.0, corner), "{corner:?}");
+            }
+
+            // Ends up inside the initial zone.
+            let dist = zone.target_center.distance(zone.start_center);
+            assert!(dist + zone.target_radius <= zone.start_radius + 1e-9);
+            assert_eq!(zone.target_radius, cvars.g_zone_radius_min);
+        }
+
+        let server = test_utils::atrium_server(&Cvars {
+            g_zone: false,
+            ..zone_cvars()
+        });
+        assert!(server.gs.zone.is_none());
+    }
+
+    #[test]
+    fn test_zone_damage() {
+        let cvars = zone_cvars();
+        let mut server = test_utils::atrium_server(&cvars);
+        server.gs.zone = Some(circle(v!(100 100), 50.0));
+        server.gs.dt = 0.5;
+
+        let mut ctx = server.ctx(&cvars);
+        let mut vehicles = Vec::new();
+        for (name, pos) in [("inside", v!(120 100)), ("outside", v!(300 100))] {
+            let player_handle = test_utils::add_player(&mut ctx, name);
+            let vehicle_handle =
+                test_utils::add_vehicle(&mut ctx, player_handle, VehicleType::Tank, pos, 0.0);
+            vehicles.push(vehicle_handle);
+        }
+
+        ctx.sys_zone();
+
+        let inside = &ctx.gs.vehicles[vehicles[0]];
+        assert_eq!(inside.hp_fraction, 1.0);
+        let outside = &ctx.gs.vehicles[vehicles[1]];
+        let hp = cvars.g_vehicle_hp(VehicleType::Tank);
+        let expected = 1.0 - cvars.g_zone_damage_per_sec * 0.5 / hp;
+        assert!((outside.hp_fraction - expected).abs() < 1e-9);
+    }
+
+    /// Fixed zone, doesn't shrink.
+    fn circle(center: Vec2f, radius: f64) -> Zone {
+        Zone {
+            start_center: center,
+            start_radius: radius,
+            target_center: center,
+            target_radius: radius,
+            shrink_start: 0.0,
+            shrink_end: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_zone_spawn() {
+        // 5x5 tiles of grass, the only spawn is at column 1, row 1.
+        let map_text = "0 0 0 0 0\n0 36 0 0 0\n0 0 0 0 0\n0 0 0 0 0\n0 0 0 0 0\n";
+        let map = test_utils::parse_map(map_text);
+        let cvars = zone_cvars();
+        let mut server = test_utils::server(&cvars, map);
+        let mut ctx = server.ctx(&cvars);
+        let spawn = v!(96 96);
+
+        let zone = circle(spawn, 10.0);
+        assert_eq!(ctx.zone_spawn(&zone).0, spawn);
+
+        // The spawn is outside, any tile inside will do.
+        let zone = circle(v!(224 224), 100.0);
+        for _ in 0..10 {
+            let (pos, _) = ctx.zone_spawn(&zone);
+            assert!(zone.contains(0.0, pos), "{pos:?}");
+        }
+
+        // No tile centers inside at all - the closest spawn.
+        let zone = circle(v!(200 200), 5.0);
+        assert_eq!(ctx.zone_spawn(&zone).0, spawn);
+    }
+
+    #[test]
+    fn test_zone_input() {
+        let cvars = Cvars::default();
+        let zone = zone();
+        let mut vehicle = Vehicle::new(
+            &cvars,
+            v!(500 500),
+            0.0,
+            VehicleType::Tank,
+            0.0,
+            Index::DANGLING,
+        );
+        assert_eq!(zone_input(&zone, 0.0, &vehicle), None);
+
+        // Outside to the left, facing right - straight ahead back in.
+        vehicle.pos = v!(-300 500);
+        let input = zone_input(&zone, 0.0, &vehicle).unwrap();
+        assert!(input.up && !input.down && !input.left && !input.right);
+
+        // Facing away - reverse.
+        vehicle.angle = PI;
+        let input = zone_input(&zone, 0.0, &vehicle).unwrap();
+        assert!(input.down && !input.up);
+    }
+}